@@ -12,6 +12,7 @@
 use aws_sdk_kinesis::error::{GetShardIteratorError, ListShardsError};
 use aws_sdk_kinesis::model::{Shard, ShardIteratorType};
 use aws_sdk_kinesis::{Client, SdkError};
+use aws_smithy_types::Instant;
 
 use crate::config::AwsConfig;
 use crate::util;
@@ -75,6 +76,18 @@ pub async fn get_shard_ids(
         .map(|s| s.shard_id.unwrap_or_else(|| "".into())))
 }
 
+/// A position in a Kinesis shard.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShardPosition {
+    /// The oldest data record in the shard.
+    TrimHorizon,
+    /// The first data record at or after the given time, in milliseconds
+    /// since the Unix epoch.
+    AtTimestamp(i64),
+    /// The data record right after the one with the given sequence number.
+    AfterSequenceNumber(String),
+}
+
 /// Constructs an iterator over a Kinesis shard.
 ///
 /// This function is a wrapper around around the `GetShardIterator` API. It
@@ -91,12 +104,41 @@ pub async fn get_shard_iterator(
     stream_name: &str,
     shard_id: &str,
 ) -> Result<Option<String>, SdkError<GetShardIteratorError>> {
-    let res = client
+    get_shard_iterator_at(client, stream_name, shard_id, &ShardPosition::TrimHorizon).await
+}
+
+/// Constructs an iterator over a Kinesis shard that starts at the given
+/// position.
+///
+/// This function is like [`get_shard_iterator`], but allows choosing the
+/// location in the shard that the iterator starts at.
+///
+/// # Errors
+///
+/// Any errors from the underlying `GetShardIterator` API call are surfaced
+/// directly.
+pub async fn get_shard_iterator_at(
+    client: &Client,
+    stream_name: &str,
+    shard_id: &str,
+    position: &ShardPosition,
+) -> Result<Option<String>, SdkError<GetShardIteratorError>> {
+    let req = client
         .get_shard_iterator()
         .stream_name(stream_name)
-        .shard_id(shard_id)
-        .shard_iterator_type(ShardIteratorType::TrimHorizon)
-        .send()
-        .await?;
+        .shard_id(shard_id);
+    let req = match position {
+        ShardPosition::TrimHorizon => req.shard_iterator_type(ShardIteratorType::TrimHorizon),
+        ShardPosition::AtTimestamp(millis) => req
+            .shard_iterator_type(ShardIteratorType::AtTimestamp)
+            .timestamp(Instant::from_secs_and_nanos(
+                millis.div_euclid(1_000),
+                u32::try_from(millis.rem_euclid(1_000) * 1_000_000).expect("known to fit"),
+            )),
+        ShardPosition::AfterSequenceNumber(sequence_number) => req
+            .shard_iterator_type(ShardIteratorType::AfterSequenceNumber)
+            .starting_sequence_number(sequence_number),
+    };
+    let res = req.send().await?;
     Ok(res.shard_iterator)
 }
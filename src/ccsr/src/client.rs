@@ -157,6 +157,29 @@ impl Client {
         Ok(res.id)
     }
 
+    /// Checks whether a schema is compatible with the latest version of the
+    /// specified subject, according to the subject's forwards- or
+    /// backwards-compatibility requirements.
+    pub async fn check_compatibility(
+        &self,
+        subject: &str,
+        schema: &str,
+        schema_type: SchemaType,
+        references: &[SchemaReference],
+    ) -> Result<bool, CompatibilityError> {
+        let req = self.make_request(
+            Method::POST,
+            &["compatibility", "subjects", subject, "versions", "latest"],
+        );
+        let req = req.json(&PublishRequest {
+            schema,
+            schema_type,
+            references,
+        });
+        let res: CompatibilityResponse = send_request(req).await?;
+        Ok(res.is_compatible)
+    }
+
     /// Lists the names of all subjects that the schema registry is aware of.
     pub async fn list_subjects(&self) -> Result<Vec<String>, ListError> {
         let req = self.make_request(Method::GET, &["subjects"]);
@@ -473,6 +496,61 @@ impl fmt::Display for PublishError {
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct CompatibilityResponse {
+    is_compatible: bool,
+}
+
+/// Errors for compatibility checks.
+#[derive(Debug)]
+pub enum CompatibilityError {
+    /// The specified subject does not exist.
+    SubjectNotFound,
+    /// The provided schema was invalid.
+    InvalidSchema { message: String },
+    /// The underlying HTTP transport failed.
+    Transport(reqwest::Error),
+    /// An internal server error occured.
+    Server { code: i32, message: String },
+}
+
+impl From<UnhandledError> for CompatibilityError {
+    fn from(err: UnhandledError) -> CompatibilityError {
+        match err {
+            UnhandledError::Transport(err) => CompatibilityError::Transport(err),
+            UnhandledError::Api { code, message } => match code {
+                40401 => CompatibilityError::SubjectNotFound,
+                42201 => CompatibilityError::InvalidSchema { message },
+                _ => CompatibilityError::Server { code, message },
+            },
+        }
+    }
+}
+
+impl Error for CompatibilityError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompatibilityError::SubjectNotFound
+            | CompatibilityError::InvalidSchema { .. }
+            | CompatibilityError::Server { .. } => None,
+            CompatibilityError::Transport(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for CompatibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompatibilityError::SubjectNotFound => write!(f, "subject not found"),
+            CompatibilityError::InvalidSchema { message } => write!(f, "{}", message),
+            CompatibilityError::Transport(err) => write!(f, "transport: {}", err),
+            CompatibilityError::Server { code, message } => {
+                write!(f, "server error {}: {}", code, message)
+            }
+        }
+    }
+}
+
 /// Errors for list operations.
 #[derive(Debug)]
 pub enum ListError {
@@ -17,7 +17,10 @@ use hyper::StatusCode;
 use hyper::{Body, Response};
 use lazy_static::lazy_static;
 
-use ccsr::{Client, DeleteError, GetByIdError, GetBySubjectError, PublishError, SchemaType};
+use ccsr::{
+    Client, CompatibilityError, DeleteError, GetByIdError, GetBySubjectError, PublishError,
+    SchemaType,
+};
 
 lazy_static! {
     pub static ref SCHEMA_REGISTRY_URL: reqwest::Url = match env::var("SCHEMA_REGISTRY_URL") {
@@ -57,6 +60,22 @@ async fn test_client() -> Result<(), anyhow::Error> {
         .await?;
     assert!(schema_v1_id > 0);
 
+    assert!(
+        client
+            .check_compatibility("ccsr-test-schema", schema_v2, SchemaType::Avro, &[])
+            .await?
+    );
+    assert!(
+        !client
+            .check_compatibility(
+                "ccsr-test-schema",
+                schema_v2_incompat,
+                SchemaType::Avro,
+                &[]
+            )
+            .await?
+    );
+
     match client
         .publish_schema(
             "ccsr-test-schema",
@@ -271,6 +290,18 @@ async fn test_client_errors() -> Result<(), anyhow::Error> {
         res => panic!("expected PublishError::InvalidSchema, got {:?}", res),
     }
 
+    // Compatibility-specific errors.
+    match client
+        .check_compatibility("ccsr-test-noexist", "\"int\"", SchemaType::Avro, &[])
+        .await
+    {
+        Err(CompatibilityError::SubjectNotFound) => (),
+        res => panic!(
+            "expected CompatibilityError::SubjectNotFound, got {:?}",
+            res
+        ),
+    }
+
     // Delete-specific errors.
     match client.delete_subject("ccsr-test-noexist").await {
         Err(DeleteError::SubjectNotFound) => (),
@@ -318,6 +349,17 @@ async fn test_server_errors() -> Result<(), anyhow::Error> {
         res => panic!("expected GetBySubjectError::Server, got {:?}", res),
     }
 
+    match client_graceful
+        .check_compatibility("foo", "bar", SchemaType::Avro, &[])
+        .await
+    {
+        Err(CompatibilityError::Server {
+            code: 50001,
+            ref message,
+        }) if message == "overloaded; try again later" => (),
+        res => panic!("expected CompatibilityError::Server, got {:?}", res),
+    }
+
     match client_graceful.delete_subject("foo").await {
         Err(DeleteError::Server {
             code: 50001,
@@ -346,6 +346,7 @@ impl CatalogState {
                 SourceConnector::External { connector, .. } => match &connector {
                     ExternalSourceConnector::PubNub(_) => Volatile,
                     ExternalSourceConnector::Kinesis(_) => Volatile,
+                    ExternalSourceConnector::Webhook(_) => Volatile,
                     _ => Unknown,
                 },
                 SourceConnector::Local { .. } => Volatile,
@@ -541,6 +542,7 @@ pub struct Table {
     pub conn_id: Option<u32>,
     pub depends_on: Vec<GlobalId>,
     pub persist: Option<TablePersistDetails>,
+    pub retain_history: Option<Duration>,
 }
 
 impl Table {
@@ -565,7 +567,7 @@ pub struct Sink {
     pub create_sql: String,
     pub from: GlobalId,
     pub connector: SinkConnectorState,
-    pub envelope: SinkEnvelope,
+    pub envelope: Option<SinkEnvelope>,
     pub with_snapshot: bool,
     pub depends_on: Vec<GlobalId>,
 }
@@ -1063,6 +1065,7 @@ impl Catalog {
                             conn_id: None,
                             depends_on: vec![],
                             persist,
+                            retain_history: None,
                         }),
                     );
                     let oid = catalog.allocate_oid()?;
@@ -2311,6 +2314,7 @@ impl Catalog {
                     conn_id: None,
                     depends_on: table.depends_on,
                     persist,
+                    retain_history: table.retain_history,
                 })
             }
             Plan::CreateSource(CreateSourcePlan { mut source, .. }) => {
@@ -2629,6 +2633,11 @@ pub struct SerializedSourcePersistDetails {
 
     /// Any additional details that we need to make the envelope logic stateful.
     pub envelope_details: SerializedEnvelopePersistDetails,
+
+    /// Persisted stream of read positions, for sources that cannot resume from their timestamp
+    /// bindings alone.
+    #[serde(default)]
+    pub checkpoints_stream: Option<String>,
 }
 
 /// See `EnvelopePersistDesc` for an explanation of the fields.
@@ -2644,6 +2653,9 @@ impl From<SourcePersistDesc> for SerializedSourcePersistDetails {
             primary_stream: source_persist_desc.primary_stream.name,
             timestamp_bindings_stream: source_persist_desc.timestamp_bindings_stream.name,
             envelope_details: source_persist_desc.envelope_desc.into(),
+            checkpoints_stream: source_persist_desc
+                .checkpoints_stream
+                .map(|stream| stream.name),
         }
     }
 }
@@ -951,6 +951,18 @@ lazy_static! {
         // for this to be persisted.
         persistent: true,
     };
+    pub static ref MZ_S3_SINKS: BuiltinTable = BuiltinTable {
+        name: "mz_s3_sinks",
+        schema: MZ_CATALOG_SCHEMA,
+        desc: RelationDesc::empty()
+            .with_column("sink_id", ScalarType::String.nullable(false))
+            .with_column("bucket", ScalarType::String.nullable(false))
+            .with_column("prefix", ScalarType::String.nullable(false))
+            .with_key(vec![0]),
+        id: GlobalId::System(4049),
+        index_id: GlobalId::System(4050),
+        persistent: false,
+    };
 }
 
 pub const MZ_RELATIONS: BuiltinView = BuiltinView {
@@ -1754,6 +1766,7 @@ lazy_static! {
             Builtin::Table(&MZ_PROMETHEUS_READINGS),
             Builtin::Table(&MZ_PROMETHEUS_HISTOGRAMS),
             Builtin::Table(&MZ_PROMETHEUS_METRICS),
+            Builtin::Table(&MZ_S3_SINKS),
             Builtin::View(&MZ_CATALOG_NAMES),
             Builtin::View(&MZ_ARRANGEMENT_SHARING),
             Builtin::View(&MZ_ARRANGEMENT_SIZES),
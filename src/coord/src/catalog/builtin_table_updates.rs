@@ -9,7 +9,7 @@
 
 use std::os::unix::ffi::OsStringExt;
 
-use dataflow_types::sinks::{AvroOcfSinkConnector, KafkaSinkConnector};
+use dataflow_types::sinks::{AvroOcfSinkConnector, KafkaSinkConnector, S3SinkConnector};
 use expr::{GlobalId, MirScalarExpr};
 use ore::collections::CollectionExt;
 use repr::adt::array::ArrayDimension;
@@ -21,7 +21,7 @@ use sql_parser::ast::display::AstDisplay;
 use crate::catalog::builtin::{
     MZ_ARRAY_TYPES, MZ_AVRO_OCF_SINKS, MZ_BASE_TYPES, MZ_COLUMNS, MZ_DATABASES, MZ_FUNCTIONS,
     MZ_INDEXES, MZ_INDEX_COLUMNS, MZ_KAFKA_SINKS, MZ_LIST_TYPES, MZ_MAP_TYPES, MZ_PSEUDO_TYPES,
-    MZ_ROLES, MZ_SCHEMAS, MZ_SINKS, MZ_SOURCES, MZ_TABLES, MZ_TYPES, MZ_VIEWS,
+    MZ_ROLES, MZ_S3_SINKS, MZ_SCHEMAS, MZ_SINKS, MZ_SOURCES, MZ_TABLES, MZ_TYPES, MZ_VIEWS,
 };
 use crate::catalog::{
     CatalogItem, CatalogState, Func, Index, Sink, SinkConnector, SinkConnectorState, Source, Table,
@@ -266,6 +266,17 @@ impl CatalogState {
                         diff,
                     });
                 }
+                SinkConnector::S3(S3SinkConnector { bucket, prefix, .. }) => {
+                    updates.push(BuiltinTableUpdate {
+                        id: MZ_S3_SINKS.id,
+                        row: Row::pack_slice(&[
+                            Datum::String(&id.to_string()),
+                            Datum::String(bucket),
+                            Datum::String(prefix),
+                        ]),
+                        diff,
+                    });
+                }
                 _ => (),
             }
             updates.push(BuiltinTableUpdate {
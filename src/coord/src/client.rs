@@ -7,6 +7,7 @@
 // the Business Source License, use of this software will be governed
 // by the Apache License, Version 2.0.
 
+use std::collections::HashMap;
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Instant;
@@ -20,6 +21,7 @@ use ore::collections::CollectionExt;
 use ore::thread::JoinOnDropHandle;
 use repr::{Datum, Row};
 use sql::ast::{Raw, Statement};
+use sql::names::PartialName;
 
 use crate::command::{
     Canceled, Command, ExecuteResponse, Response, SimpleExecuteResponse, SimpleResult,
@@ -378,6 +380,29 @@ impl SessionClient {
         .await
     }
 
+    /// Appends the body of an HTTP request to the named webhook source.
+    ///
+    /// Header names must be lowercase. `authenticated` reports whether the
+    /// client proved its identity, which is required for sources that do not
+    /// validate signatures.
+    pub async fn append_webhook(
+        &mut self,
+        name: PartialName,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+        authenticated: bool,
+    ) -> Result<(), CoordError> {
+        self.send(|tx, session| Command::AppendWebhook {
+            name,
+            headers,
+            body,
+            authenticated,
+            session,
+            tx,
+        })
+        .await
+    }
+
     /// Executes SQL statements using a simple protocol that does not involve
     /// portals.
     ///
@@ -7,6 +7,7 @@
 // the Business Source License, use of this software will be governed
 // by the Apache License, Version 2.0.
 
+use std::collections::HashMap;
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
@@ -21,6 +22,7 @@ use expr::GlobalId;
 use ore::str::StrExt;
 use repr::Row;
 use sql::ast::{FetchDirection, ObjectType, Raw, Statement};
+use sql::names::PartialName;
 use sql::plan::ExecuteTimeout;
 use tokio::sync::watch;
 
@@ -93,6 +95,15 @@ pub enum Command {
         tx: oneshot::Sender<Response<ExecuteResponse>>,
     },
 
+    AppendWebhook {
+        name: PartialName,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+        authenticated: bool,
+        session: Session,
+        tx: oneshot::Sender<Response<()>>,
+    },
+
     Terminate {
         session: Session,
     },
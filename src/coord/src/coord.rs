@@ -97,8 +97,10 @@ use derivative::Derivative;
 use differential_dataflow::lattice::Lattice;
 use futures::future::{self, FutureExt, TryFutureExt};
 use futures::stream::StreamExt;
+use hmac::{Hmac, Mac};
 use rand::Rng;
 use repr::adt::interval::Interval;
+use sha2::Sha256;
 use timely::order::PartialOrder;
 use timely::progress::frontier::MutableAntichain;
 use timely::progress::{Antichain, ChangeBatch, Timestamp as _};
@@ -115,7 +117,7 @@ use dataflow_types::{
     sinks::{SinkConnector, TailSinkConnector},
     sources::{
         persistence::TimestampSourceUpdate, ExternalSourceConnector, PostgresSourceConnector,
-        SourceConnector,
+        SourceConnector, WebhookValidation,
     },
     DataflowDesc, DataflowDescription, IndexDesc, PeekResponse, Update,
 };
@@ -126,6 +128,7 @@ use expr::{
 use ore::metrics::MetricsRegistry;
 use ore::now::{to_datetime, NowFn};
 use ore::retry::Retry;
+use ore::str::StrExt;
 use ore::thread::{JoinHandleExt as _, JoinOnDropHandle};
 use repr::adt::numeric;
 use repr::{Datum, Diff, RelationDesc, Row, RowArena, Timestamp};
@@ -135,7 +138,7 @@ use sql::ast::{
     FetchStatement, Ident, InsertSource, ObjectType, Query, Raw, SetExpr, Statement,
 };
 use sql::catalog::{CatalogError, SessionCatalog as _};
-use sql::names::{DatabaseSpecifier, FullName};
+use sql::names::{DatabaseSpecifier, FullName, PartialName};
 use sql::plan::{
     AlterIndexEnablePlan, AlterIndexResetOptionsPlan, AlterIndexSetOptionsPlan,
     AlterItemRenamePlan, CreateDatabasePlan, CreateIndexPlan, CreateRolePlan, CreateSchemaPlan,
@@ -346,6 +349,11 @@ where
     write_lock: Arc<tokio::sync::Mutex<()>>,
     /// Holds plans deferred due to write lock.
     write_lock_wait_group: VecDeque<DeferredPlan>,
+
+    /// The HMAC secrets of webhook sources that validate signatures, or why
+    /// they could not be read. They are read once, when the source is created
+    /// or at startup, so that requests don't have to touch the file system.
+    webhook_secrets: HashMap<GlobalId, Result<Vec<u8>, String>>,
 }
 
 /// Metadata about an active connection.
@@ -518,6 +526,7 @@ where
                 CatalogItem::Source(source) => {
                     // Inform the timestamper about this source.
                     self.update_timestamper(entry.id(), true).await;
+                    self.load_webhook_secret(entry.id()).await;
                     let since_ts = {
                         match &source.connector {
                             SourceConnector::External { persist, .. } => {
@@ -1278,6 +1287,20 @@ where
                 let _ = tx.send(Response { result, session });
             }
 
+            Command::AppendWebhook {
+                name,
+                headers,
+                body,
+                authenticated,
+                session,
+                tx,
+            } => {
+                let result = self
+                    .sequence_append_webhook(&session, name, headers, body, authenticated)
+                    .await;
+                let _ = tx.send(Response { result, session });
+            }
+
             Command::Terminate { mut session } => {
                 self.handle_terminate(&mut session).await;
             }
@@ -1439,17 +1462,6 @@ where
         let mut table_source_since_updates = vec![];
 
         for (id, frontier) in since_updates.iter() {
-            // HACK: Avoid the "failed to compact persisted tables" error log at
-            // restart, by not trying to allow compaction on the minimum
-            // timestamp.
-            if !frontier
-                .elements()
-                .iter()
-                .any(|x| *x > Timestamp::minimum())
-            {
-                continue;
-            }
-
             // Not all ids will be present in the catalog however, those that are
             // in the catalog must also have their dependencies in the catalog as
             // well.
@@ -1457,13 +1469,48 @@ where
             if let Some(CatalogItem::Index(catalog::Index { on, .. })) = item {
                 if let CatalogItem::Table(catalog::Table {
                     persist: Some(persist),
+                    retain_history,
                     ..
                 }) = self.catalog.get_by_id(on).item()
                 {
-                    if self.catalog.default_index_for(*on) == Some(*id) {
-                        table_since_updates.push((persist.stream_id, frontier.clone()));
-                        table_source_since_updates.push((*on, frontier.clone()));
+                    if self.catalog.default_index_for(*on) != Some(*id) {
+                        continue;
                     }
+
+                    // Tables that retain history hold the persisted since back
+                    // by the retention window, so that `AS OF` queries can be
+                    // replayed from persistence even once the index itself
+                    // has compacted past the requested time.
+                    let frontier = match retain_history {
+                        Some(retain_history) => {
+                            let retain_ms = duration_to_timestamp_millis(*retain_history);
+                            let mut held = Antichain::new();
+                            for x in frontier.elements() {
+                                held.insert(x.saturating_sub(retain_ms));
+                            }
+                            match self.sources.since_of(on) {
+                                Some(since) if !<_ as PartialOrder>::less_than(&since, &held) => {
+                                    continue
+                                }
+                                _ => held,
+                            }
+                        }
+                        None => frontier.clone(),
+                    };
+
+                    // HACK: Avoid the "failed to compact persisted tables" error log at
+                    // restart, by not trying to allow compaction on the minimum
+                    // timestamp.
+                    if !frontier
+                        .elements()
+                        .iter()
+                        .any(|x| *x > Timestamp::minimum())
+                    {
+                        continue;
+                    }
+
+                    table_since_updates.push((persist.stream_id, frontier.clone()));
+                    table_source_since_updates.push((*on, frontier));
                 }
             }
         }
@@ -1713,7 +1760,7 @@ where
                         .unwrap()
                         .clone(),
                     connector: connector.clone(),
-                    envelope: Some(sink.envelope),
+                    envelope: sink.envelope,
                     as_of,
                 };
                 Ok(builder.build_sink_dataflow(name.to_string(), id, sink_description)?)
@@ -2075,6 +2122,12 @@ where
             .catalog
             .persist_details(table_id, &name)
             .map_err(|err| anyhow!("{}", err))?;
+        if table.retain_history.is_some() && persist.is_none() {
+            // History is only retained by holding back compaction of the
+            // table's persisted stream, so there is nothing to replay from
+            // for volatile tables.
+            coord_bail!("retain_history requires a persisted table");
+        }
         let table = catalog::Table {
             create_sql: table.create_sql,
             desc: table.desc,
@@ -2082,6 +2135,7 @@ where
             conn_id,
             depends_on: table.depends_on,
             persist,
+            retain_history: table.retain_history,
         };
         let index_id = self.catalog.allocate_id()?;
         let mut index_name = name.clone();
@@ -2205,6 +2259,7 @@ where
                 // shipping any dataflows that depend on its existence.
                 for source_id in source_ids {
                     self.update_timestamper(source_id, true).await;
+                    self.load_webhook_secret(source_id).await;
                     let since_ts = since_ts.unwrap_or(0);
 
                     let frontiers = self.new_frontiers(
@@ -2375,7 +2430,7 @@ where
                                 .unwrap()
                                 .clone(),
                             connector: SinkConnector::Tail(TailSinkConnector {}),
-                            envelope: Some(sink.envelope),
+                            envelope: sink.envelope,
                             as_of: SinkAsOf {
                                 frontier: Antichain::new(),
                                 strict: false,
@@ -2982,7 +3037,7 @@ where
         // single-statement transaction (TransactionStatus::Started), we don't need to
         // worry about preventing compaction or choosing a valid timestamp for future
         // queries.
-        let timestamp = if in_transaction && when == PeekWhen::Immediately {
+        let (timestamp, replay_ids) = if in_transaction && when == PeekWhen::Immediately {
             // Queries are independent of the logical timestamp iff there are no referenced
             // sources or indexes and there is no reference to `mz_logical_timestamp()`
             // which we check by using a Static prep style.
@@ -3067,9 +3122,9 @@ where
                 });
             }
 
-            timestamp
+            (timestamp, HashSet::new())
         } else {
-            self.determine_timestamp(&source_ids, when)?.0
+            self.determine_timestamp_or_replay(&source_ids, when)?
         };
 
         let source = self.prep_relation_expr(
@@ -3095,8 +3150,9 @@ where
         // The assembled dataflow contains a view and an index of that view.
         let mut dataflow = DataflowDesc::new(format!("temp-view-{}", view_id));
         dataflow.set_as_of(Antichain::from_elem(timestamp));
-        self.dataflow_builder()
-            .import_view_into_dataflow(&view_id, &source, &mut dataflow)?;
+        let mut builder = self.dataflow_builder();
+        builder.replay_ids = replay_ids;
+        builder.import_view_into_dataflow(&view_id, &source, &mut dataflow)?;
         dataflow.export_index(
             index_id,
             IndexDesc {
@@ -3149,14 +3205,13 @@ where
 
         // Determine the frontier of updates to tail *from*.
         // Updates greater or equal to this frontier will be produced.
-        let frontier = if let Some(ts) = ts {
+        let (frontier, replay_ids) = if let Some(ts) = ts {
             // If a timestamp was explicitly requested, use that.
-            Antichain::from_elem(
-                self.determine_timestamp(&[source_id], PeekWhen::AtTimestamp(ts))?
-                    .0,
-            )
+            let (timestamp, replay_ids) =
+                self.determine_timestamp_or_replay(&[source_id], PeekWhen::AtTimestamp(ts))?;
+            (Antichain::from_elem(timestamp), replay_ids)
         } else {
-            self.determine_frontier(source_id)
+            (self.determine_frontier(source_id), HashSet::new())
         };
         let sink_name = format!(
             "tail-source-{}",
@@ -3180,9 +3235,9 @@ where
                 strict: !with_snapshot,
             },
         };
-        let df =
-            self.dataflow_builder()
-                .build_sink_dataflow(sink_name, sink_id, sink_description)?;
+        let mut builder = self.dataflow_builder();
+        builder.replay_ids = replay_ids;
+        let df = builder.build_sink_dataflow(sink_name, sink_id, sink_description)?;
         self.ship_dataflow(df).await?;
 
         let resp = ExecuteResponse::Tailing { rx };
@@ -3348,6 +3403,104 @@ where
         }
     }
 
+    /// Like [`Coordinator::determine_timestamp`], but an explicitly requested
+    /// timestamp that available indexes have already compacted past may still
+    /// be served by replaying the history of persisted tables.
+    ///
+    /// Returns the timestamp along with the identifiers whose indexes must not
+    /// be used, per [`Coordinator::determine_history_replay`].
+    fn determine_timestamp_or_replay(
+        &mut self,
+        uses_ids: &[GlobalId],
+        when: PeekWhen,
+    ) -> Result<(Timestamp, HashSet<GlobalId>), CoordError> {
+        let requested = match when {
+            PeekWhen::AtTimestamp(timestamp) => Some(timestamp),
+            PeekWhen::Immediately => None,
+        };
+        match self.determine_timestamp(uses_ids, when) {
+            Ok((timestamp, _)) => Ok((timestamp, HashSet::new())),
+            Err(err) => match requested {
+                Some(timestamp) => match self.determine_history_replay(uses_ids, timestamp) {
+                    Some(replay_ids) => Ok((timestamp, replay_ids)),
+                    None => Err(err),
+                },
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Determines which of `uses_ids` and their transitive dependencies must
+    /// be rebuilt from persisted history to be read at `timestamp`.
+    ///
+    /// Items whose indexes are all valid at `timestamp` are read from those
+    /// indexes. Otherwise, persisted tables that retain history far enough
+    /// back are replayed from persistence, and views are rebuilt from their
+    /// inputs. Returns `None` if any input can be read neither way.
+    fn determine_history_replay(
+        &self,
+        uses_ids: &[GlobalId],
+        timestamp: Timestamp,
+    ) -> Option<HashSet<GlobalId>> {
+        let mut replay_ids = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut todo = uses_ids.to_vec();
+        while let Some(id) = todo.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            let index_ids: Vec<_> = self
+                .catalog
+                .enabled_indexes()
+                .get(&id)
+                .into_iter()
+                .flatten()
+                .map(|(index_id, _keys)| *index_id)
+                .filter(|index_id| self.indexes.contains_key(*index_id))
+                .collect();
+            let indexes_valid = index_ids.iter().all(|index_id| {
+                self.indexes
+                    .since_of(index_id)
+                    .expect("id not found")
+                    .less_equal(&timestamp)
+            });
+            if !index_ids.is_empty() && indexes_valid {
+                continue;
+            }
+            match self.catalog.get_by_id(&id).item() {
+                CatalogItem::Table(catalog::Table {
+                    persist: Some(_),
+                    retain_history: Some(_),
+                    ..
+                }) => {
+                    if !self.sources.since_of(&id)?.less_equal(&timestamp) {
+                        return None;
+                    }
+                    replay_ids.insert(id);
+                }
+                CatalogItem::View(view) => {
+                    replay_ids.insert(id);
+                    todo.extend(view.optimized_expr.global_uses());
+                }
+                // Sources can't retain history (see `plan_create_source`), so
+                // one is only usable if it's still valid at `timestamp`.
+                CatalogItem::Source(_) if index_ids.is_empty() => {
+                    if !self.sources.since_of(&id)?.less_equal(&timestamp) {
+                        return None;
+                    }
+                }
+                _ => return None,
+            }
+        }
+        // If nothing needs replaying, the failure to read at `timestamp` lies
+        // elsewhere.
+        if replay_ids.is_empty() {
+            None
+        } else {
+            Some(replay_ids)
+        }
+    }
+
     /// Determine the frontier of updates to start *from* for a sink based on
     /// `source_id`.
     ///
@@ -3695,6 +3848,91 @@ where
         }
     }
 
+    async fn sequence_append_webhook(
+        &mut self,
+        session: &Session,
+        name: PartialName,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+        authenticated: bool,
+    ) -> Result<(), CoordError> {
+        let id = self.catalog.for_session(session).resolve_item(&name)?.id();
+        let validation = match self.catalog.get_by_id(&id).item() {
+            CatalogItem::Source(catalog::Source {
+                connector:
+                    SourceConnector::External {
+                        connector: ExternalSourceConnector::Webhook(webhook),
+                        ..
+                    },
+                ..
+            }) => webhook.validation.clone(),
+            _ => coord_bail!("{} is not a webhook source", name.to_string().quoted()),
+        };
+
+        match validation {
+            Some(WebhookValidation { header, .. }) => {
+                let secret = match self.webhook_secrets.get(&id) {
+                    Some(Ok(secret)) => secret,
+                    Some(Err(e)) => coord_bail!(
+                        "reading secret of webhook source {}: {}",
+                        name.to_string().quoted(),
+                        e
+                    ),
+                    None => coord_bail!(
+                        "secret of webhook source {} has not been loaded",
+                        name.to_string().quoted()
+                    ),
+                };
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key size");
+                mac.update(&body);
+                // Some senders prefix the signature with the name of the hash
+                // function, as in `sha256=<hex>`.
+                let signature = headers
+                    .get(&header)
+                    .map(|signature| signature.trim_start_matches("sha256="))
+                    .and_then(|signature| hex::decode(signature).ok());
+                match signature {
+                    Some(signature) if mac.verify_slice(&signature).is_ok() => (),
+                    _ => return Err(CoordError::InvalidWebhookSignature(name.to_string())),
+                }
+            }
+            // Without signatures, only clients that authenticated themselves
+            // may write to the source.
+            None if !authenticated => {
+                return Err(CoordError::UnauthenticatedWebhook(name.to_string()))
+            }
+            None => (),
+        }
+
+        // The requests are fed to the dataflow that materializes the source.
+        // Without one, there is nothing to insert them into.
+        let materialized = self
+            .catalog
+            .enabled_indexes()
+            .get(&id)
+            .map_or(false, |indexes| !indexes.is_empty());
+        if !materialized {
+            return Err(CoordError::UnmaterializedWebhook(name.to_string()));
+        }
+
+        // Each request is written in a transaction of its own, at a timestamp
+        // assigned just like for writes to tables.
+        let timestamp = self.get_local_write_ts();
+        let row = Row::pack_slice(&[Datum::Bytes(&body)]);
+        self.dataflow_client
+            .table_insert(
+                id,
+                vec![Update {
+                    row,
+                    diff: 1,
+                    timestamp,
+                }],
+            )
+            .await;
+        Ok(())
+    }
+
     fn sequence_copy_rows(
         &mut self,
         session: &mut Session,
@@ -3920,6 +4158,7 @@ where
         F: FnOnce(DataflowBuilder) -> Result<T, CoordError>,
     {
         let mut sources_to_drop = vec![];
+        let mut webhook_inputs_to_drop = vec![];
         let mut tables_to_drop = vec![];
         let mut sinks_to_drop = vec![];
         let mut indexes_to_drop = vec![];
@@ -3933,20 +4172,26 @@ where
                     }
                     CatalogItem::Source(source) => {
                         sources_to_drop.push(*id);
-                        if let SourceConnector::External {
-                            connector:
-                                ExternalSourceConnector::Postgres(PostgresSourceConnector {
-                                    conn,
-                                    slot_name,
-                                    ..
-                                }),
-                            ..
-                        } = &source.connector
-                        {
-                            replication_slots_to_drop
-                                .entry(conn.clone())
-                                .or_insert_with(Vec::new)
-                                .push(slot_name.clone());
+                        match &source.connector {
+                            SourceConnector::External {
+                                connector:
+                                    ExternalSourceConnector::Postgres(PostgresSourceConnector {
+                                        conn,
+                                        slot_name,
+                                        ..
+                                    }),
+                                ..
+                            } => {
+                                replication_slots_to_drop
+                                    .entry(conn.clone())
+                                    .or_insert_with(Vec::new)
+                                    .push(slot_name.clone());
+                            }
+                            SourceConnector::External {
+                                connector: ExternalSourceConnector::Webhook(_),
+                                ..
+                            } => webhook_inputs_to_drop.push(*id),
+                            _ => (),
                         }
                     }
                     CatalogItem::Sink(catalog::Sink {
@@ -3971,6 +4216,7 @@ where
                 catalog,
                 indexes,
                 transient_id_counter,
+                replay_ids: HashSet::new(),
             };
             f(builder)
         })?;
@@ -3983,6 +4229,15 @@ where
                 self.sources.remove(&id);
             }
         }
+        if !webhook_inputs_to_drop.is_empty() {
+            for id in &webhook_inputs_to_drop {
+                self.webhook_secrets.remove(id);
+            }
+            // Webhook sources are fed through local inputs, just like tables.
+            self.dataflow_client
+                .drop_tables(webhook_inputs_to_drop)
+                .await;
+        }
         if !tables_to_drop.is_empty() {
             // NOTE: When creating a persistent table we insert its compaction frontier (aka since)
             // in `self.sources` to make sure that it is taken into account when rendering
@@ -4326,6 +4581,43 @@ where
         }
     }
 
+    /// Reads the HMAC secret of the source with the given ID, if it is a webhook
+    /// source that validates signatures.
+    ///
+    /// Failing to read the secret doesn't fail the creation of the source, or
+    /// startup, but every request to the source.
+    async fn load_webhook_secret(&mut self, source_id: GlobalId) {
+        let secret_file = match self.catalog.get_by_id(&source_id).item() {
+            CatalogItem::Source(catalog::Source {
+                connector:
+                    SourceConnector::External {
+                        connector: ExternalSourceConnector::Webhook(webhook),
+                        ..
+                    },
+                ..
+            }) => match &webhook.validation {
+                Some(validation) => validation.secret_file.clone(),
+                None => return,
+            },
+            _ => return,
+        };
+        let secret = tokio::task::spawn_blocking(move || {
+            std::fs::read_to_string(&secret_file)
+                .map(|secret| {
+                    // Trailing newlines are an artifact of how the file was
+                    // written, not part of the secret.
+                    secret
+                        .trim_end_matches(&['\r', '\n'][..])
+                        .as_bytes()
+                        .to_vec()
+                })
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+        self.webhook_secrets.insert(source_id, secret);
+    }
+
     fn allocate_transient_id(&mut self) -> Result<GlobalId, CoordError> {
         let id = self.transient_id_counter;
         if id == u64::max_value() {
@@ -4533,6 +4825,7 @@ where
                 pending_tails: HashMap::new(),
                 write_lock: Arc::new(tokio::sync::Mutex::new(())),
                 write_lock_wait_group: VecDeque::new(),
+                webhook_secrets: HashMap::new(),
             };
             if let Some(config) = &logging {
                 handle.block_on(
@@ -16,13 +16,20 @@
 
 use super::*;
 use crate::error::RematerializedSourceType;
+use dataflow_types::sinks::SinkEnvelope;
+use dataflow_types::sources::SourceEnvelope;
 use ore::stack::maybe_grow;
+use transform::monotonic::MonotonicFlag;
 
 /// Borrows of catalog and indexes sufficient to build dataflow descriptions.
 pub struct DataflowBuilder<'a> {
     pub catalog: &'a CatalogState,
     pub indexes: &'a ArrangementFrontiers<Timestamp>,
     pub transient_id_counter: &'a mut u64,
+    /// Identifiers whose indexes must not be used, because the dataflow reads
+    /// them at a time their indexes have already compacted past. Their
+    /// contents are instead rebuilt from persisted history.
+    pub replay_ids: HashSet<GlobalId>,
 }
 
 impl<C> Coordinator<C>
@@ -35,6 +42,7 @@ where
             catalog: self.catalog.state(),
             indexes: &self.indexes,
             transient_id_counter: &mut self.transient_id_counter,
+            replay_ids: HashSet::new(),
         }
     }
 
@@ -79,10 +87,15 @@ impl<'a> DataflowBuilder<'a> {
             }
 
             // A valid index is any index on `id` that is known to the dataflow
-            // layer, as indicated by its presence in `self.indexes`.
-            let valid_index = self.catalog.enabled_indexes()[id]
-                .iter()
-                .find(|(id, _keys)| self.indexes.contains_key(*id));
+            // layer, as indicated by its presence in `self.indexes`, unless
+            // `id` is being replayed from persisted history.
+            let valid_index = if self.replay_ids.contains(id) {
+                None
+            } else {
+                self.catalog.enabled_indexes()[id]
+                    .iter()
+                    .find(|(id, _keys)| self.indexes.contains_key(*id))
+            };
             if let Some((index_id, keys)) = valid_index {
                 let index_desc = IndexDesc {
                     on_id: *id,
@@ -194,6 +207,9 @@ impl<'a> DataflowBuilder<'a> {
 
             // TODO: indexes should be imported after the optimization process, and only those
             // actually used by the optimized plan
+            if self.replay_ids.contains(&get_id) {
+                continue;
+            }
             if let Some(indexes) = self.catalog.enabled_indexes().get(&get_id) {
                 for (id, keys) in indexes.iter() {
                     // Ensure only valid indexes (i.e. those in self.indexes) are imported.
@@ -241,10 +257,71 @@ impl<'a> DataflowBuilder<'a> {
         id: GlobalId,
         sink_description: dataflow_types::sinks::SinkDesc,
     ) -> Result<DataflowDesc, CoordError> {
+        if sink_description.envelope == Some(SinkEnvelope::AppendOnly)
+            && !self.is_append_only(&sink_description.from)?
+        {
+            let from = self.catalog.get_by_id(&sink_description.from);
+            return Err(CoordError::NonAppendOnlySinkInput(from.name().to_string()));
+        }
         let mut dataflow = DataflowDesc::new(name);
         dataflow.set_as_of(sink_description.as_of.frontier.clone());
         self.import_into_dataflow(&sink_description.from, &mut dataflow)?;
         dataflow.export_sink(id, sink_description);
         Ok(dataflow)
     }
+
+    /// Determines whether the view, source, or table with `id` only ever
+    /// receives insertions.
+    ///
+    /// Sources with `ENVELOPE NONE` whose connector only ever produces new
+    /// records are append-only, and so are views that derive from append-only
+    /// relations through operators that cannot introduce retractions, as
+    /// determined by [`MonotonicFlag`].
+    fn is_append_only(&self, id: &GlobalId) -> Result<bool, CoordError> {
+        maybe_grow(|| {
+            let entry = self.catalog.get_by_id(id);
+            let (mut expr, mut append_only) = match entry.item() {
+                CatalogItem::Source(source) => {
+                    // Postgres sources are planned with `ENVELOPE NONE` but
+                    // replicate the upstream table's deletes and updates.
+                    if !matches!(
+                        source.connector,
+                        SourceConnector::External {
+                            connector: ExternalSourceConnector::Kafka(_)
+                                | ExternalSourceConnector::Kinesis(_)
+                                | ExternalSourceConnector::File(_)
+                                | ExternalSourceConnector::AvroOcf(_)
+                                | ExternalSourceConnector::S3(_)
+                                | ExternalSourceConnector::PubNub(_)
+                                | ExternalSourceConnector::Webhook(_),
+                            envelope: SourceEnvelope::None(_),
+                            ..
+                        }
+                    ) {
+                        return Ok(false);
+                    }
+                    // Check the source's transformation, if any, against the
+                    // source itself.
+                    let mut expr = source.optimized_expr.clone();
+                    expr.0.visit_mut_post(&mut |node| {
+                        if let MirRelationExpr::Get { id: get_id, .. } = node {
+                            if *get_id == Id::LocalBareSource {
+                                *get_id = Id::Global(*id);
+                            }
+                        }
+                    });
+                    (expr, HashSet::from([*id]))
+                }
+                CatalogItem::View(view) => (view.optimized_expr.clone(), HashSet::new()),
+                _ => return Ok(false),
+            };
+            for use_id in expr.global_uses() {
+                if use_id != *id && self.is_append_only(&use_id)? {
+                    append_only.insert(use_id);
+                }
+            }
+            let flag = MonotonicFlag::default();
+            Ok(flag.apply(expr.as_inner_mut(), &append_only, &mut HashSet::new())?)
+        })
+    }
 }
@@ -61,6 +61,15 @@ pub enum CoordError {
     },
     /// The selection value for a table mutation operation refers to an invalid object.
     InvalidTableMutationSelection,
+    /// An append-only sink was created on a relation that may contain retractions.
+    NonAppendOnlySinkInput(String),
+    /// A request to a webhook source lacked a valid signature.
+    InvalidWebhookSignature(String),
+    /// A request to a webhook source that does not validate signatures came
+    /// from a client that did not authenticate.
+    UnauthenticatedWebhook(String),
+    /// A request was sent to a webhook source that is not materialized.
+    UnmaterializedWebhook(String),
     /// Expression violated a column's constraint
     ConstraintViolation(NotNullViolation),
     /// The named operation cannot be run in a transaction.
@@ -177,6 +186,7 @@ impl CoordError {
                 let source_name = match source_type {
                     RematerializedSourceType::Postgres => "Postgres",
                     RematerializedSourceType::S3 => "S3 with SQS notification ",
+                    RematerializedSourceType::Webhook => "Webhook",
                 };
                 Some(format!(
                     "{} sources can be materialized by only one set of indexes at a time.\
@@ -235,10 +245,20 @@ impl CoordError {
                 // because that leaks information to unauthenticated clients.)
                 Some("Try connecting as the \"materialize\" user.".into())
             }
+            CoordError::UnmaterializedWebhook(source) => Some(format!(
+                "Materialize the source with CREATE DEFAULT INDEX ON {} to accept requests.",
+                source.quoted()
+            )),
+            CoordError::NonAppendOnlySinkInput(_) => Some(
+                "Use ENVELOPE DEBEZIUM or ENVELOPE UPSERT to sink relations that may \
+                 contain retractions."
+                    .into(),
+            ),
             CoordError::InvalidRematerialization { source_type, .. } => {
                 let doc_page = match source_type {
                     RematerializedSourceType::Postgres => "postgres",
                     RematerializedSourceType::S3 => "text-s3",
+                    RematerializedSourceType::Webhook => "webhook",
                 };
                 Some(format!(
                     "See the documentation at https://materialize.com/docs/sql/create-source/{}",
@@ -304,6 +324,28 @@ impl fmt::Display for CoordError {
             CoordError::InvalidTableMutationSelection => {
                 f.write_str("invalid selection: operation may only refer to user-defined tables")
             }
+            CoordError::InvalidWebhookSignature(source) => {
+                write!(
+                    f,
+                    "invalid signature for webhook source {}",
+                    source.quoted()
+                )
+            }
+            CoordError::UnauthenticatedWebhook(source) => {
+                write!(
+                    f,
+                    "webhook source {} requires an authenticated client or an HMAC signature",
+                    source.quoted()
+                )
+            }
+            CoordError::UnmaterializedWebhook(source) => {
+                write!(f, "webhook source {} is not materialized", source.quoted())
+            }
+            CoordError::NonAppendOnlySinkInput(name) => write!(
+                f,
+                "cannot create append-only sink: {} may contain retractions",
+                name.quoted()
+            ),
             CoordError::ConstraintViolation(not_null_violation) => {
                 write!(f, "{}", not_null_violation)
             }
@@ -404,6 +446,7 @@ impl Error for CoordError {}
 pub enum RematerializedSourceType {
     Postgres,
     S3,
+    Webhook,
 }
 
 impl RematerializedSourceType {
@@ -417,6 +460,7 @@ impl RematerializedSourceType {
             SourceConnector::External { connector, .. } => match connector {
                 ExternalSourceConnector::S3(_) => RematerializedSourceType::S3,
                 ExternalSourceConnector::Postgres(_) => RematerializedSourceType::Postgres,
+                ExternalSourceConnector::Webhook(_) => RematerializedSourceType::Webhook,
                 _ => unreachable!(),
             },
             _ => unreachable!(),
@@ -51,6 +51,7 @@ pub use crate::command::{Canceled, ExecuteResponse, StartupMessage, StartupRespo
 pub use crate::coord::{serve, Config, LoggingConfig};
 pub use crate::error::CoordError;
 pub use crate::persistcfg::{
+    BatchCompression, BlobCacheConfig, CompactionConfig, EncryptionKey, Keyring, LeveledPolicy,
     PersistConfig, PersistFileStorage, PersistS3Storage, PersistStorage, PersisterWithConfig,
 };
 pub use crate::timestamp::Timestamper;
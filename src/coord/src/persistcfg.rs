@@ -26,7 +26,7 @@ use ore::metrics::MetricsRegistry;
 use persist::error::{Error, ErrorLog};
 use persist::indexed::encoding::Id as PersistId;
 use persist::s3::{S3Blob, S3BlobConfig};
-use persist::storage::{Blob, LockInfo};
+use persist::storage::{Blob, LockInfo, Log};
 use repr::Row;
 use serde::Serialize;
 use tokio::runtime::Runtime;
@@ -34,10 +34,16 @@ use url::Url;
 
 use expr::GlobalId;
 use persist::client::{MultiWriteHandle, RuntimeClient, StreamWriteHandle};
+use persist::encrypted::EncryptedBlob;
 use persist::file::FileBlob;
 use persist::runtime::{self, RuntimeConfig};
 use uuid::Uuid;
 
+pub use persist::encrypted::{EncryptionKey, Keyring};
+pub use persist::indexed::background::{CompactionConfig, LeveledPolicy};
+pub use persist::indexed::cache::BlobCacheConfig;
+pub use persist::indexed::columnar::BatchCompression;
+
 use crate::catalog::{SerializedEnvelopePersistDetails, SerializedSourcePersistDetails};
 
 #[derive(Clone, Debug)]
@@ -128,6 +134,14 @@ pub struct PersistConfig {
     /// Whether to make Kafka Upserts persistent for fast restarts. This is
     /// extremely experimental and should not even be tried by users.
     pub kafka_upsert_source_enabled: bool,
+    /// Whether to make Kinesis sources persistent, so that they resume from
+    /// where they left off after a restart. This is extremely experimental and
+    /// should not even be tried by users.
+    pub kinesis_source_enabled: bool,
+    /// Whether to make file sources persistent, so that they resume from
+    /// where they left off in each file after a restart. This is extremely
+    /// experimental and should not even be tried by users.
+    pub file_source_enabled: bool,
     /// Unstructured information stored in the "lock" files created by the
     /// log and blob to ensure that they are exclusive writers to those
     /// locations. This should contain whatever information might be useful to
@@ -135,6 +149,20 @@ pub struct PersistConfig {
     /// version of the creating process).
     pub lock_info: String,
     pub min_step_interval: Duration,
+    /// The codec used to compress newly persisted batches.
+    pub compression: BatchCompression,
+    /// The keys used to encrypt persisted data at rest, if any.
+    pub encryption: Option<Keyring>,
+    /// Size limits of the in-memory and on-disk cache of persisted data.
+    pub cache: BlobCacheConfig,
+    /// Policy and limits for compacting persisted data.
+    pub compaction: CompactionConfig,
+    /// Whether to commit persisted data by compare-and-set in blob storage,
+    /// taking over from any other process writing to it on startup, so that
+    /// nothing local is needed to recover. S3 has no conditional writes, so
+    /// this is only supported with file storage. This is extremely
+    /// experimental and should not even be tried by users.
+    pub blob_consensus: bool,
 }
 
 impl PersistConfig {
@@ -145,8 +173,15 @@ impl PersistConfig {
             user_table_enabled: false,
             system_table_enabled: false,
             kafka_upsert_source_enabled: false,
+            kinesis_source_enabled: false,
+            file_source_enabled: false,
             lock_info: Default::default(),
             min_step_interval: Duration::default(),
+            compression: BatchCompression::Uncompressed,
+            encryption: None,
+            cache: BlobCacheConfig::default(),
+            compaction: CompactionConfig::default(),
+            blob_consensus: false,
         }
     }
 
@@ -162,37 +197,32 @@ impl PersistConfig {
         let persister = if self.user_table_enabled
             || self.system_table_enabled
             || self.kafka_upsert_source_enabled
+            || self.kinesis_source_enabled
+            || self.file_source_enabled
         {
-            let lock_reentrance_id = catalog_id.to_string();
-            let lock_info = LockInfo::new(lock_reentrance_id, self.lock_info.clone())?;
+            let lock_info = if self.blob_consensus {
+                LockInfo::new_shared(self.lock_info.clone())
+            } else {
+                let lock_reentrance_id = catalog_id.to_string();
+                LockInfo::new(lock_reentrance_id, self.lock_info.clone())?
+            };
             let log = ErrorLog;
             let persister = match &self.storage {
                 PersistStorage::File(s) => {
-                    let mut blob = FileBlob::open_exclusive((&s.blob_path).into(), lock_info)?;
-                    persist::storage::check_meta_version_maybe_delete_data(&mut blob)?;
-                    runtime::start(
-                        RuntimeConfig::with_min_step_interval(self.min_step_interval),
-                        log,
-                        blob,
-                        build,
-                        reg,
-                        self.runtime.clone(),
-                    )
+                    let blob = FileBlob::open_exclusive((&s.blob_path).into(), lock_info)?;
+                    self.start(log, blob, build, reg)
+                }
+                PersistStorage::S3(_) if self.blob_consensus => {
+                    return Err(Error::from(
+                        "blob consensus is not supported with S3 persist storage",
+                    ));
                 }
                 PersistStorage::S3(s) => {
                     let config =
                         S3BlobConfig::new(s.bucket.clone(), s.prefix.clone(), s.role_arn.clone())
                             .await?;
-                    let mut blob = S3Blob::open_exclusive(config, lock_info)?;
-                    persist::storage::check_meta_version_maybe_delete_data(&mut blob)?;
-                    runtime::start(
-                        RuntimeConfig::with_min_step_interval(self.min_step_interval),
-                        log,
-                        blob,
-                        build,
-                        reg,
-                        self.runtime.clone(),
-                    )
+                    let blob = S3Blob::open_exclusive(config, lock_info)?;
+                    self.start(log, blob, build, reg)
                 }
             }?;
             Some(persister)
@@ -204,6 +234,44 @@ impl PersistConfig {
             config: self.clone(),
         })
     }
+
+    /// Starts the persistence runtime over the given storage, encrypting
+    /// everything written to the blob if keys are configured.
+    fn start<L, B>(
+        &self,
+        log: L,
+        blob: B,
+        build: BuildInfo,
+        reg: &MetricsRegistry,
+    ) -> Result<RuntimeClient, Error>
+    where
+        L: Log + Send + 'static,
+        B: Blob + Sync,
+        B::Read: Sync,
+    {
+        // Batches cached on local disk are encrypted just like the blob.
+        let cache = match &self.encryption {
+            Some(keys) => self.cache.clone().with_disk_encryption(keys.clone()),
+            None => self.cache.clone(),
+        };
+        let config = RuntimeConfig::with_min_step_interval(self.min_step_interval)
+            .with_compression(self.compression)
+            .with_cache(cache)
+            .with_compaction(self.compaction.clone())
+            .with_blob_consensus(self.blob_consensus);
+        match &self.encryption {
+            Some(keys) => {
+                let mut blob = EncryptedBlob::new(blob, keys.clone());
+                persist::storage::check_meta_version_maybe_delete_data(&mut blob)?;
+                runtime::start(config, log, blob, build, reg, self.runtime.clone())
+            }
+            None => {
+                let mut blob = blob;
+                persist::storage::check_meta_version_maybe_delete_data(&mut blob)?;
+                runtime::start(config, log, blob, build, reg, self.runtime.clone())
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -291,6 +359,7 @@ impl PersisterWithConfig {
         let name_prefix = format!("user-source-{}-{}", id, pretty);
         let primary_stream = format!("{}", name_prefix);
         let timestamp_bindings_stream = format!("{}-timestamp-bindings", name_prefix);
+        let checkpoints_stream = format!("{}-checkpoints", name_prefix);
 
         let serialized_details = match connector {
             SourceConnector::External {
@@ -301,6 +370,7 @@ impl PersisterWithConfig {
                 primary_stream,
                 timestamp_bindings_stream,
                 envelope_details: crate::catalog::SerializedEnvelopePersistDetails::Upsert,
+                checkpoints_stream: None,
             }),
             SourceConnector::External {
                 connector: ExternalSourceConnector::Kafka(_),
@@ -310,6 +380,30 @@ impl PersisterWithConfig {
                 primary_stream,
                 timestamp_bindings_stream,
                 envelope_details: crate::catalog::SerializedEnvelopePersistDetails::None,
+                checkpoints_stream: None,
+            }),
+            // Kinesis sequence numbers don't fit into timestamp bindings, so the source records
+            // its position in each shard in a separate stream.
+            SourceConnector::External {
+                connector: ExternalSourceConnector::Kinesis(_),
+                envelope: SourceEnvelope::None(_),
+                ..
+            } if self.config.kinesis_source_enabled => Some(SerializedSourcePersistDetails {
+                primary_stream,
+                timestamp_bindings_stream,
+                envelope_details: crate::catalog::SerializedEnvelopePersistDetails::None,
+                checkpoints_stream: Some(checkpoints_stream),
+            }),
+            // Likewise, the source records how far it has read each of its files.
+            SourceConnector::External {
+                connector: ExternalSourceConnector::File(_),
+                envelope: SourceEnvelope::None(_),
+                ..
+            } if self.config.file_source_enabled => Some(SerializedSourcePersistDetails {
+                primary_stream,
+                timestamp_bindings_stream,
+                envelope_details: crate::catalog::SerializedEnvelopePersistDetails::None,
+                checkpoints_stream: Some(checkpoints_stream),
             }),
             _ => None,
         };
@@ -373,11 +467,16 @@ impl PersisterWithConfig {
                 stream_desc_from_name(serialized_details.primary_stream, persister)?;
             let timestamp_bindings_stream =
                 stream_desc_from_name(serialized_details.timestamp_bindings_stream, persister)?;
+            let checkpoints_stream = serialized_details
+                .checkpoints_stream
+                .map(|name| stream_desc_from_name(name, persister))
+                .transpose()?;
 
             Ok(SourcePersistDesc {
                 primary_stream,
                 timestamp_bindings_stream,
                 envelope_desc,
+                checkpoints_stream,
             })
         });
 
@@ -21,7 +21,8 @@ use rdkafka::{Message, Offset, TopicPartitionList};
 use dataflow_types::sinks::{
     AvroOcfSinkConnector, AvroOcfSinkConnectorBuilder, KafkaSinkConnector,
     KafkaSinkConnectorBuilder, KafkaSinkConnectorRetention, KafkaSinkConsistencyConnector,
-    PublishedSchemaInfo, SinkConnector, SinkConnectorBuilder,
+    KafkaSinkPartitionBy, PostgresSinkConnector, PostgresSinkConnectorBuilder, PublishedSchemaInfo,
+    S3SinkConnector, S3SinkConnectorBuilder, SinkConnector, SinkConnectorBuilder,
 };
 use expr::GlobalId;
 use kafka_util::client::MzClientContext;
@@ -39,6 +40,8 @@ pub async fn build(
     match builder {
         SinkConnectorBuilder::Kafka(k) => build_kafka(k, id).await,
         SinkConnectorBuilder::AvroOcf(a) => build_avro_ocf(a, id),
+        SinkConnectorBuilder::S3(s) => build_s3(s, id).await,
+        SinkConnectorBuilder::Postgres(p) => build_postgres(p, id).await,
     }
 }
 
@@ -64,6 +67,7 @@ fn get_next_message(
 // Retrieves the latest committed timestamp from the consistency topic
 fn get_latest_ts(
     consistency_topic: &str,
+    json: bool,
     mut consumer_config: ClientConfig,
     timeout: Duration,
 ) -> Result<Option<Timestamp>, anyhow::Error> {
@@ -137,7 +141,12 @@ fn get_latest_ts(
         debug_assert!(offset >= latest_offset.unwrap_or(0));
         latest_offset = Some(offset);
 
-        if let Some(ts) = maybe_decode_consistency_end_record(&message, consistency_topic)? {
+        let ts = if json {
+            maybe_decode_json_consistency_end_record(&message, consistency_topic)?
+        } else {
+            maybe_decode_consistency_end_record(&message, consistency_topic)?
+        };
+        if let Some(ts) = ts {
             if ts >= latest_ts.unwrap_or(0) {
                 latest_ts = Some(ts);
             }
@@ -192,6 +201,31 @@ fn maybe_decode_consistency_end_record(
     }
 }
 
+// Like `maybe_decode_consistency_end_record`, but for JSON-encoded consistency
+// topics.
+fn maybe_decode_json_consistency_end_record(
+    bytes: &[u8],
+    consistency_topic: &str,
+) -> Result<Option<Timestamp>, anyhow::Error> {
+    let record: serde_json::Value =
+        serde_json::from_slice(bytes).context("Failed to decode consistency topic message")?;
+    match (record.get("status"), record.get("id")) {
+        (Some(serde_json::Value::String(status)), Some(serde_json::Value::String(id)))
+            if status == "END" =>
+        {
+            match id.parse::<u64>() {
+                Ok(ts) => Ok(Some(ts)),
+                Err(_) => bail!(
+                    "Malformed consistency record, failed to parse timestamp {} in topic {}",
+                    id,
+                    consistency_topic
+                ),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
 async fn register_kafka_topic(
     client: &AdminClient<MzClientContext>,
     topic: &str,
@@ -296,6 +330,9 @@ async fn register_kafka_topic(
     if let Some(ref retention_bytes) = retention_bytes_str {
         kafka_topic = kafka_topic.set("retention.bytes", retention_bytes);
     }
+    if retention.compact {
+        kafka_topic = kafka_topic.set("cleanup.policy", "compact");
+    }
 
     if succeed_if_exists {
         kafka_util::admin::ensure_topic(
@@ -355,6 +392,46 @@ async fn publish_kafka_schemas(
     Ok((key_schema_id, value_schema_id))
 }
 
+/// Checks that the value and optional key schemas of a sink that reuses its
+/// topic are compatible with the latest schemas published for that topic, so
+/// that consumers can decode the records written before and after the sink was
+/// recreated alike.
+async fn check_kafka_schema_compatibility(
+    ccsr: &ccsr::Client,
+    topic: &str,
+    key_schema: Option<&str>,
+    value_schema: &str,
+) -> Result<(), CoordError> {
+    let mut subjects = vec![(format!("{}-value", topic), value_schema)];
+    if let Some(key_schema) = key_schema {
+        subjects.push((format!("{}-key", topic), key_schema));
+    }
+    for (subject, schema) in subjects {
+        let compatible = match ccsr
+            .check_compatibility(&subject, schema, ccsr::SchemaType::Avro, &[])
+            .await
+        {
+            Ok(compatible) => compatible,
+            // Nothing has been published for the topic yet.
+            Err(ccsr::CompatibilityError::SubjectNotFound) => true,
+            Err(e) => Err(e).with_context(|| {
+                format!(
+                    "unable to check compatibility of schema for subject {}",
+                    subject
+                )
+            })?,
+        };
+        if !compatible {
+            coord_bail!(
+                "schema for topic {} is incompatible with the latest version of subject {}",
+                topic,
+                subject
+            );
+        }
+    }
+    Ok(())
+}
+
 async fn build_kafka(
     builder: KafkaSinkConnectorBuilder,
     id: GlobalId,
@@ -407,6 +484,15 @@ async fn build_kafka(
             ..
         } => {
             let ccsr = ccsr_config.build()?;
+            if builder.reuse_topic {
+                check_kafka_schema_compatibility(
+                    &ccsr,
+                    &topic,
+                    key_schema.as_deref(),
+                    &value_schema,
+                )
+                .await?;
+            }
             let (key_schema_id, value_schema_id) = publish_kafka_schemas(
                 &ccsr,
                 &topic,
@@ -426,18 +512,14 @@ async fn build_kafka(
     };
 
     let consistency = match builder.consistency_format {
-        Some(dataflow_types::sinks::KafkaSinkFormat::Avro {
-            value_schema,
-            ccsr_config,
-            ..
-        }) => {
+        Some(consistency_format) => {
             let consistency_topic = maybe_append_nonce(
                 builder
                     .consistency_topic_prefix
                     .as_ref()
                     .expect("known to exist"),
             );
-            // create consistency topic/schema and retrieve schema id
+            // create consistency topic
             register_kafka_topic(
                 &client,
                 &consistency_topic,
@@ -449,22 +531,39 @@ async fn build_kafka(
             .await
             .context("error registering kafka consistency topic for sink")?;
 
-            let ccsr = ccsr_config.build()?;
-            let (_, consistency_schema_id) = publish_kafka_schemas(
-                &ccsr,
-                &consistency_topic,
-                None,
-                None,
-                &value_schema,
-                ccsr::SchemaType::Avro,
-            )
-            .await
-            .context("error publishing kafka consistency schemas for sink")?;
+            // publish the consistency schema and retrieve its id, if the
+            // consistency records are Avro-encoded
+            let consistency_schema_id = match consistency_format {
+                dataflow_types::sinks::KafkaSinkFormat::Avro {
+                    value_schema,
+                    ccsr_config,
+                    ..
+                } => {
+                    let ccsr = ccsr_config.build()?;
+                    let (_, consistency_schema_id) = publish_kafka_schemas(
+                        &ccsr,
+                        &consistency_topic,
+                        None,
+                        None,
+                        &value_schema,
+                        ccsr::SchemaType::Avro,
+                    )
+                    .await
+                    .context("error publishing kafka consistency schemas for sink")?;
+                    Some(consistency_schema_id)
+                }
+                dataflow_types::sinks::KafkaSinkFormat::Json => None,
+            };
 
             // get latest committed timestamp from consistency topic
             let gate_ts = if builder.reuse_topic {
-                get_latest_ts(&consistency_topic, config.clone(), Duration::from_secs(10))
-                    .context("error restarting from existing kafka consistency topic for sink")?
+                get_latest_ts(
+                    &consistency_topic,
+                    consistency_schema_id.is_none(),
+                    config.clone(),
+                    Duration::from_secs(10),
+                )
+                .context("error restarting from existing kafka consistency topic for sink")?
             } else {
                 None
             };
@@ -475,8 +574,26 @@ async fn build_kafka(
                 gate_ts,
             })
         }
-        Some(other) => unreachable!("non-Avro consistency format for Kafka sink {:#?}", &other),
-        _ => None,
+        None => None,
+    };
+
+    let partition_by = match builder.partition_by {
+        Some(expr) => {
+            // The topic may have existed before the sink, so ask the broker
+            // how many partitions it actually has.
+            let partitions =
+                kafka_util::client::get_partitions(client.inner(), &topic, Duration::from_secs(5))
+                    .with_context(|| {
+                        format!("error fetching partitions of topic {} for sink", topic)
+                    })?;
+            let partition_count = i32::try_from(partitions.len())
+                .with_context(|| format!("topic {} has too many partitions", topic))?;
+            Some(KafkaSinkPartitionBy {
+                expr,
+                partition_count,
+            })
+        }
+        None => None,
     };
 
     Ok(SinkConnector::Kafka(KafkaSinkConnector {
@@ -488,7 +605,8 @@ async fn build_kafka(
         value_desc: builder.value_desc,
         published_schema_info,
         consistency,
-        exactly_once: builder.reuse_topic,
+        partition_by,
+        exactly_once: builder.exactly_once,
         transitive_source_dependencies: builder.transitive_source_dependencies,
         fuel: builder.fuel,
         config_options: builder.config_options,
@@ -534,3 +652,154 @@ fn build_avro_ocf(
         value_desc: builder.value_desc,
     }))
 }
+
+async fn build_s3(
+    builder: S3SinkConnectorBuilder,
+    id: GlobalId,
+) -> Result<SinkConnector, CoordError> {
+    // Each incarnation of the sink writes under its own prefix, so that
+    // readers never mix up the batches of different sinks.
+    let mut prefix = builder.prefix;
+    if !prefix.is_empty() {
+        prefix.push('/');
+    }
+    prefix.push_str(&format!("{}-{}", id, builder.prefix_suffix_nonce));
+
+    // Make sure that the bucket exists and that we can access it before
+    // creating the sink, rather than failing in the dataflow.
+    let config = builder.aws.load().await;
+    mz_aws_util::s3::client(&config)
+        .head_bucket()
+        .bucket(&builder.bucket)
+        .send()
+        .await
+        .map_err(|e| anyhow!("unable to access S3 bucket {}: {}", builder.bucket, e))?;
+
+    Ok(SinkConnector::S3(S3SinkConnector {
+        bucket: builder.bucket,
+        prefix,
+        aws: builder.aws,
+        format: builder.format,
+        mode: builder.mode,
+        interval: builder.interval,
+        value_desc: builder.value_desc,
+    }))
+}
+
+async fn build_postgres(
+    builder: PostgresSinkConnectorBuilder,
+    id: GlobalId,
+) -> Result<SinkConnector, CoordError> {
+    let client = postgres_util::connect(&builder.conn)
+        .await
+        .context("unable to connect to Postgres for sink")?;
+
+    let row = client
+        .query_opt(
+            "SELECT c.oid, quote_ident(n.nspname), quote_ident(c.relname)
+            FROM pg_class c JOIN pg_namespace n ON c.relnamespace = n.oid
+            WHERE c.oid = to_regclass($1)",
+            &[&builder.table],
+        )
+        .await
+        .context("unable to look up Postgres table for sink")?;
+    let (oid, schema, table): (u32, String, String) = match row {
+        Some(row) => (row.get(0), row.get(1), row.get(2)),
+        None => coord_bail!("Postgres table {} does not exist", builder.table),
+    };
+    let table = format!("{}.{}", schema, table);
+
+    // Find the downstream column for each column of the sinked relation, so
+    // that the sink can cast its values to the right types.
+    let downstream_columns: HashMap<String, (String, String)> = client
+        .query(
+            "SELECT a.attname, quote_ident(a.attname), format_type(a.atttypid, a.atttypmod)
+            FROM pg_attribute a
+            WHERE a.attrelid = $1 AND a.attnum > 0 AND NOT a.attisdropped",
+            &[&oid],
+        )
+        .await
+        .context("unable to look up columns of Postgres table for sink")?
+        .into_iter()
+        .map(|row| (row.get(0), (row.get(1), row.get(2))))
+        .collect();
+    let mut columns = vec![];
+    for (name, _typ) in builder.value_desc.iter() {
+        match downstream_columns.get(name.as_str()) {
+            Some(column) => columns.push(column.clone()),
+            None => coord_bail!("Postgres table {} has no column {}", table, name),
+        }
+    }
+
+    // Upserts rely on a unique index on exactly the key columns.
+    let mut key_columns: Vec<String> = builder
+        .key_indices
+        .iter()
+        .map(|i| builder.value_desc.get_name(*i).as_str().to_owned())
+        .collect();
+    key_columns.sort();
+    let unique_indexes = client
+        .query(
+            "SELECT array_agg(a.attname::text)
+            FROM pg_index i
+            JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY (i.indkey)
+            WHERE i.indrelid = $1 AND i.indisunique AND i.indpred IS NULL
+            GROUP BY i.indexrelid",
+            &[&oid],
+        )
+        .await
+        .context("unable to look up indexes of Postgres table for sink")?;
+    if !unique_indexes.iter().any(|row| {
+        let mut index_columns: Vec<String> = row.get(0);
+        index_columns.sort();
+        index_columns == key_columns
+    }) {
+        coord_bail!(
+            "Postgres table {} has no unique index on the sink key ({})",
+            table,
+            key_columns.join(", ")
+        );
+    }
+
+    // Each sink records its progress in a table next to the one it writes
+    // to, so that progress and data are committed in the same transaction.
+    // Progress is keyed by the sink rather than by the table it writes to,
+    // so that a new sink into the same table, possibly created by another
+    // Materialize deployment, does not skip timestamps that another sink
+    // wrote.
+    let progress_table = format!("{}.mz_sink_progress", schema);
+    let progress_key = format!("{}-{}", builder.cluster_id, id);
+    client
+        .batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                sink_id text PRIMARY KEY,
+                sink_table text NOT NULL,
+                ts bigint NOT NULL
+            )",
+            progress_table
+        ))
+        .await
+        .context("unable to create Postgres progress table for sink")?;
+    let gate_ts = client
+        .query_opt(
+            &format!("SELECT ts FROM {} WHERE sink_id = $1", progress_table),
+            &[&progress_key],
+        )
+        .await
+        .context("unable to read Postgres progress table for sink")?
+        .map(|row| Timestamp::try_from(row.get::<_, i64>(0)))
+        .transpose()
+        .context("invalid timestamp in Postgres progress table for sink")?;
+
+    Ok(SinkConnector::Postgres(PostgresSinkConnector {
+        conn: builder.conn,
+        table,
+        progress_table,
+        progress_key,
+        columns,
+        key_indices: builder.key_indices,
+        value_desc: builder.value_desc,
+        gate_ts,
+        transitive_source_dependencies: builder.transitive_source_dependencies,
+    }))
+}
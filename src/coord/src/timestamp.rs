@@ -35,7 +35,7 @@ use tracing::{debug, error, info, warn};
 use dataflow_types::sources::{
     encoding::DataEncoding,
     persistence::{Consistency, TimestampSourceUpdate},
-    DebeziumMode, ExternalSourceConnector, FileSourceConnector, KafkaSourceConnector,
+    DebeziumMode, ExternalSourceConnector, FileSourceConnector, KafkaSourceConnector, KafkaTopics,
     KinesisSourceConnector, MzOffset, S3SourceConnector, SourceConnector, SourceEnvelope,
 };
 use expr::{GlobalId, PartitionId};
@@ -150,7 +150,7 @@ enum ConsistencyFormatting {
 struct RtKafkaConnector {
     coordination_state: Arc<TimestampingState>,
     id: GlobalId,
-    topic: String,
+    topics: KafkaTopics,
 }
 
 /// Coordination structure for individual timestamping threads
@@ -269,6 +269,32 @@ fn get_kafka_partitions(
     Ok(meta_topic.partitions().iter().map(|x| x.id()).collect())
 }
 
+/// Return the list of partition ids of each currently existing topic that a source reads from
+fn get_kafka_topic_partitions(
+    consumer: &BaseConsumer<MzClientContext>,
+    topics: &KafkaTopics,
+    timeout: Duration,
+) -> Result<Vec<(String, Vec<i32>)>, anyhow::Error> {
+    match topics {
+        KafkaTopics::Single(topic) => Ok(vec![(
+            topic.clone(),
+            get_kafka_partitions(consumer, topic, timeout)?,
+        )]),
+        KafkaTopics::List(_) | KafkaTopics::Pattern(_) => {
+            let meta = consumer.fetch_metadata(None, timeout)?;
+            Ok(meta
+                .topics()
+                .iter()
+                .filter(|t| t.error().is_none() && topics.includes(t.name()))
+                .map(|t| {
+                    let partitions = t.partitions().iter().map(|x| x.id()).collect();
+                    (t.name().to_string(), partitions)
+                })
+                .collect())
+        }
+    }
+}
+
 pub struct Timestamper {
     /// Current list of up to date sources that use a real time consistency model
     rt_sources: HashMap<GlobalId, RtTimestampConsumer>,
@@ -707,6 +733,7 @@ impl Timestamper {
             }
             ExternalSourceConnector::Postgres(_) => None,
             ExternalSourceConnector::PubNub(_) => None,
+            ExternalSourceConnector::Webhook(_) => None,
         }
     }
 
@@ -755,7 +782,7 @@ impl Timestamper {
                 coordinator_channel: self.tx.clone(),
             }),
             id,
-            topic: kc.topic,
+            topics: kc.topics,
         };
 
         // Start metadata refresh thread
@@ -824,7 +851,7 @@ impl Timestamper {
     ) -> Option<ByoTimestampConsumer> {
         match sc {
             ExternalSourceConnector::Kafka(kc) => {
-                let topic = kc.topic.clone();
+                let topic = kc.topics.to_string();
                 match self.create_byo_kafka_connector(id, &kc, timestamp_topic) {
                     Some(connector) => Some(ByoTimestampConsumer {
                         source_name: topic,
@@ -842,6 +869,7 @@ impl Timestamper {
             ExternalSourceConnector::S3(_) => None,   // BYO is not supported for s3 sources
             ExternalSourceConnector::Postgres(_) => None, // BYO is not supported for postgres sources
             ExternalSourceConnector::PubNub(_) => None,   // BYO is not supported for pubnub sources
+            ExternalSourceConnector::Webhook(_) => None, // BYO is not supported for webhook sources
         }
     }
 
@@ -931,55 +959,60 @@ fn rt_kafka_metadata_fetch_loop(
 ) {
     debug!(
         "Starting realtime Kafka thread for {} (source {})",
-        &c.topic, &c.id
+        &c.topics, &c.id
     );
 
-    let mut current_partition_count = 0;
-    let mut max_available_offsets_metrics = vec![];
+    // Map from topic -> number of partitions discovered so far
+    let mut current_partition_counts: HashMap<String, i32> = HashMap::new();
+    let mut max_available_offsets_metrics = HashMap::new();
 
     while !c.coordination_state.stop.load(Ordering::SeqCst) {
-        match get_kafka_partitions(&consumer, &c.topic, Duration::from_secs(30)) {
-            Ok(partitions) => {
-                // There cannot be more than i32 partitions
-                let new_partition_count: i32 = partitions.len().try_into().unwrap();
-                match new_partition_count.cmp(&current_partition_count) {
-                    cmp::Ordering::Greater => {
-                        let diff = new_partition_count - current_partition_count;
-                        info!(
-                            "Discovered {} new ({} total) kafka partitions for topic {} (source {})",
-                            diff, new_partition_count, c.topic, c.id,
-                        );
-
-                        for partition in current_partition_count..new_partition_count {
-                            c.coordination_state
-                                .coordinator_channel
-                                .send(coord::Message::AdvanceSourceTimestamp(
-                                    coord::AdvanceSourceTimestamp {
-                                        id: c.id,
-                                        update: TimestampSourceUpdate::RealTime(
-                                            PartitionId::Kafka(partition),
-                                        ),
-                                    },
-                                ))
-                                .expect(
-                                    "Failed to send update to coordinator. This should not happen",
-                                );
+        match get_kafka_topic_partitions(&consumer, &c.topics, Duration::from_secs(30)) {
+            Ok(topic_partitions) => {
+                for (topic, partitions) in topic_partitions {
+                    let current_partition_count =
+                        current_partition_counts.entry(topic.clone()).or_insert(0);
+                    // There cannot be more than i32 partitions
+                    let new_partition_count: i32 = partitions.len().try_into().unwrap();
+                    match new_partition_count.cmp(current_partition_count) {
+                        cmp::Ordering::Greater => {
+                            let diff = new_partition_count - *current_partition_count;
+                            info!(
+                                "Discovered {} new ({} total) kafka partitions for topic {} (source {})",
+                                diff, new_partition_count, topic, c.id,
+                            );
+
+                            for partition in *current_partition_count..new_partition_count {
+                                c.coordination_state
+                                    .coordinator_channel
+                                    .send(coord::Message::AdvanceSourceTimestamp(
+                                        coord::AdvanceSourceTimestamp {
+                                            id: c.id,
+                                            update: TimestampSourceUpdate::RealTime(
+                                                c.topics.partition_id(&topic, partition),
+                                            ),
+                                        },
+                                    ))
+                                    .expect(
+                                        "Failed to send update to coordinator. This should not happen",
+                                    );
+                            }
+                            *current_partition_count = new_partition_count;
                         }
-                        current_partition_count = new_partition_count;
-                    }
-                    cmp::Ordering::Less => {
-                        info!(
-                            "Ignoring decrease in partitions (from {} to {}) for topic {} (source {})",
-                            current_partition_count, new_partition_count, c.topic, c.id,
-                        );
+                        cmp::Ordering::Less => {
+                            info!(
+                                "Ignoring decrease in partitions (from {} to {}) for topic {} (source {})",
+                                current_partition_count, new_partition_count, topic, c.id,
+                            );
+                        }
+                        cmp::Ordering::Equal => (),
                     }
-                    cmp::Ordering::Equal => (),
                 }
             }
             Err(e) => {
                 error!(
                     "Unable to fetch kafka metadata for topic {} (source {}): {}",
-                    c.topic, e, c.id
+                    c.topics, c.id, e
                 );
             }
         }
@@ -989,25 +1022,30 @@ fn rt_kafka_metadata_fetch_loop(
         // TODO(benesch): Kafka supports fetching these in bulk, but
         // rust-rdkafka does not. That would save us a lot of requests on
         // large topics.
-        for pid in 0..current_partition_count {
-            match consumer.fetch_watermarks(&c.topic, pid, Duration::from_secs(30)) {
-                Ok((_low, high)) => {
-                    while max_available_offsets_metrics.len() <= usize::try_from(pid).unwrap() {
-                        max_available_offsets_metrics.push(
-                            metrics.max_available_offset.get_delete_on_drop_gauge(vec![
-                                c.topic.clone(),
-                                c.id.to_string(),
-                                pid.to_string(),
-                            ]),
+        for (topic, partition_count) in &current_partition_counts {
+            let topic_metrics = max_available_offsets_metrics
+                .entry(topic.clone())
+                .or_insert_with(Vec::new);
+            for pid in 0..*partition_count {
+                match consumer.fetch_watermarks(topic, pid, Duration::from_secs(30)) {
+                    Ok((_low, high)) => {
+                        while topic_metrics.len() <= usize::try_from(pid).unwrap() {
+                            topic_metrics.push(
+                                metrics.max_available_offset.get_delete_on_drop_gauge(vec![
+                                    topic.clone(),
+                                    c.id.to_string(),
+                                    pid.to_string(),
+                                ]),
+                            );
+                        }
+                        topic_metrics[usize::try_from(pid).unwrap()].set(high);
+                    }
+                    Err(e) => {
+                        error!(
+                            "Unable to fetch Kafka watermarks for topic {} [{}] ({}): {}",
+                            topic, pid, c.id, e
                         );
                     }
-                    max_available_offsets_metrics[usize::try_from(pid).unwrap()].set(high);
-                }
-                Err(e) => {
-                    error!(
-                        "Unable to fetch Kafka watermarks for topic {} [{}] ({}): {}",
-                        c.topic, pid, c.id, e
-                    );
                 }
             }
         }
@@ -1015,15 +1053,18 @@ fn rt_kafka_metadata_fetch_loop(
         // Poll once to clear any extraneous messages on this queue.
         consumer.poll(Duration::from_secs(0));
 
-        if current_partition_count > 0 {
+        if current_partition_counts.values().any(|count| *count > 0)
+            || !matches!(c.topics, KafkaTopics::Single(_))
+        {
             thread::sleep(wait);
         } else {
             // If no partitions have been detected yet, sleep for a second rather than
             // the specified "wait" period of time, as we know that there should at least be one
-            // partition
+            // partition. Sources that read from several topics may legitimately not match any
+            // topics for a long time, so they always wait the full period.
             thread::sleep(Duration::from_secs(1));
         }
     }
 
-    debug!("Terminating realtime Kafka thread for {}", &c.topic);
+    debug!("Terminating realtime Kafka thread for {}", &c.topics);
 }
@@ -295,19 +295,21 @@ pub mod sources {
     use serde::{Deserialize, Serialize};
     use uuid::Uuid;
 
+    use expr::PartitionId;
     use interchange::avro::{self, DebeziumDeduplicationStrategy};
     use kafka_util::KafkaAddrs;
+    use repr::adt::regex::Regex;
     use repr::{ColumnType, RelationDesc, RelationType, ScalarType};
 
     // Types and traits related to the *decoding* of data for sources.
     pub mod encoding {
 
-        use anyhow::Context;
+        use anyhow::{bail, Context};
         use regex::Regex;
         use serde::{Deserialize, Serialize};
 
         use interchange::{avro, protobuf};
-        use repr::{ColumnType, RelationDesc, ScalarType};
+        use repr::{ColumnName, ColumnType, RelationDesc, ScalarType};
 
         /// A description of how to interpret data from various sources
         ///
@@ -331,6 +333,8 @@ pub mod sources {
             Protobuf(ProtobufEncoding),
             Csv(CsvEncoding),
             Regex(RegexEncoding),
+            Json(JsonEncoding),
+            Parquet(ParquetEncoding),
             Postgres,
             Bytes,
             Text,
@@ -443,6 +447,44 @@ pub mod sources {
                                 desc.with_column(name, ScalarType::String.nullable(false))
                             }),
                     },
+                    DataEncoding::Json(JsonEncoding {
+                        columns: None,
+                        debezium: false,
+                    }) => {
+                        RelationDesc::empty().with_column("data", ScalarType::Jsonb.nullable(false))
+                    }
+                    DataEncoding::Json(JsonEncoding {
+                        columns: Some(columns),
+                        debezium: false,
+                    }) => columns
+                        .iter()
+                        .fold(RelationDesc::empty(), |desc, (name, ty)| {
+                            desc.with_column(name.clone(), ty.clone())
+                        }),
+                    DataEncoding::Json(JsonEncoding {
+                        columns: Some(columns),
+                        debezium: true,
+                    }) => {
+                        // Mirror the shape of Avro-encoded Debezium values, so
+                        // that the Debezium envelope can treat both alike.
+                        let row = ScalarType::Record {
+                            fields: columns.clone(),
+                            custom_oid: None,
+                            custom_name: None,
+                        };
+                        RelationDesc::empty()
+                            .with_column("before", row.clone().nullable(true))
+                            .with_column("after", row.nullable(true))
+                    }
+                    DataEncoding::Json(JsonEncoding {
+                        columns: None,
+                        debezium: true,
+                    }) => bail!("JSON-encoded Debezium messages require typed columns"),
+                    DataEncoding::Parquet(ParquetEncoding { columns }) => columns
+                        .iter()
+                        .fold(RelationDesc::empty(), |desc, (name, ty)| {
+                            desc.with_column(name.clone(), ty.clone())
+                        }),
                     DataEncoding::Text => RelationDesc::empty()
                         .with_column("text", ScalarType::String.nullable(false)),
                     DataEncoding::Postgres => RelationDesc::empty()
@@ -466,6 +508,8 @@ pub mod sources {
                     DataEncoding::Protobuf(_) => "Protobuf",
                     DataEncoding::Regex { .. } => "Regex",
                     DataEncoding::Csv(_) => "Csv",
+                    DataEncoding::Json(_) => "Json",
+                    DataEncoding::Parquet(_) => "Parquet",
                     DataEncoding::Text => "Text",
                     DataEncoding::Postgres => "Postgres",
                 }
@@ -528,6 +572,24 @@ pub mod sources {
             }
         }
 
+        /// Encoding in JSON format.
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct JsonEncoding {
+            /// The typed columns to project out of each JSON object. If absent,
+            /// each message is decoded into a single `jsonb` column.
+            pub columns: Option<Vec<(ColumnName, ColumnType)>>,
+            /// Whether each message is a Debezium change event, whose `before`
+            /// and `after` fields hold the typed columns.
+            pub debezium: bool,
+        }
+
+        /// Encoding in Parquet format.
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct ParquetEncoding {
+            /// The columns to read from each Parquet file, matched by name.
+            pub columns: Vec<(ColumnName, ColumnType)>,
+        }
+
         #[derive(Clone, Debug, Serialize, Deserialize)]
         pub struct RegexEncoding {
             #[serde(with = "serde_regex")]
@@ -563,6 +625,10 @@ pub mod sources {
 
             /// Any additional details that we need to make the envelope logic stateful.
             pub envelope_desc: EnvelopePersistDesc,
+
+            /// Persisted stream of read positions, for sources that cannot resume from their
+            /// timestamp bindings alone.
+            pub checkpoints_stream: Option<PersistStreamDesc>,
         }
 
         /// The persistence details we need for persisting a source envelopes data structures.
@@ -698,6 +764,8 @@ pub mod sources {
         Offset,
         Timestamp,
         Topic,
+        Headers,
+        Filename,
     }
 
     /// Whether and how to include the decoded key of a stream in dataflows
@@ -916,7 +984,7 @@ pub mod sources {
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct KafkaSourceConnector {
         pub addrs: KafkaAddrs,
-        pub topic: String,
+        pub topics: KafkaTopics,
         // Represents options specified by user when creating the source, e.g.
         // security settings.
         pub config_options: BTreeMap<String, String>,
@@ -932,6 +1000,74 @@ pub mod sources {
         pub include_topic: Option<IncludedColumnPos>,
         /// If present, include the offset as an output column of the source with the given name.
         pub include_offset: Option<IncludedColumnPos>,
+        /// If present, include the message headers as an output column of the source with the
+        /// given name.
+        pub include_headers: Option<IncludedColumnPos>,
+    }
+
+    /// The topics that a Kafka source reads from.
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub enum KafkaTopics {
+        /// A single topic, whose partitions are identified by [`PartitionId::Kafka`].
+        Single(String),
+        /// A fixed set of topics.
+        List(Vec<String>),
+        /// Every topic whose name matches the regex, including topics that are created after
+        /// the source.
+        Pattern(Regex),
+    }
+
+    impl KafkaTopics {
+        /// Reports whether the source reads from `topic`.
+        pub fn includes(&self, topic: &str) -> bool {
+            match self {
+                KafkaTopics::Single(t) => t == topic,
+                KafkaTopics::List(ts) => ts.iter().any(|t| t == topic),
+                KafkaTopics::Pattern(regex) => regex.is_match(topic),
+            }
+        }
+
+        /// Returns the identifier of `partition` of `topic`.
+        ///
+        /// Single-topic sources identify partitions by their number alone, so that their
+        /// timestamp bindings remain compatible with sources created before multi-topic sources
+        /// existed.
+        pub fn partition_id(&self, topic: &str, partition: i32) -> PartitionId {
+            match self {
+                KafkaTopics::Single(_) => PartitionId::Kafka(partition),
+                KafkaTopics::List(_) | KafkaTopics::Pattern(_) => {
+                    PartitionId::KafkaTopic(topic.to_string(), partition)
+                }
+            }
+        }
+    }
+
+    impl std::fmt::Display for KafkaTopics {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                KafkaTopics::Single(topic) => f.write_str(topic),
+                KafkaTopics::List(topics) => f.write_str(&topics.join(",")),
+                KafkaTopics::Pattern(regex) => f.write_str(regex.as_str()),
+            }
+        }
+    }
+
+    /// The type of the column produced by `INCLUDE HEADERS`.
+    ///
+    /// Kafka permits duplicate header keys and the order of headers can be meaningful, so headers
+    /// are presented as a list of `(key, value)` records rather than as a map.
+    pub fn kafka_headers_type() -> ScalarType {
+        ScalarType::List {
+            element_type: Box::new(ScalarType::Record {
+                fields: vec![
+                    ("key".into(), ScalarType::String.nullable(false)),
+                    ("value".into(), ScalarType::Bytes.nullable(true)),
+                ],
+                custom_oid: None,
+                custom_name: None,
+            }),
+            custom_oid: None,
+        }
     }
 
     /// Legacy logic included something like an offset into almost data streams
@@ -1064,6 +1200,7 @@ pub mod sources {
         S3(S3SourceConnector),
         Postgres(PostgresSourceConnector),
         PubNub(PubNubSourceConnector),
+        Webhook(WebhookSourceConnector),
     }
 
     impl ExternalSourceConnector {
@@ -1085,12 +1222,13 @@ pub mod sources {
                     include_timestamp: time,
                     include_topic: topic,
                     include_offset: offset,
+                    include_headers: headers,
                     ..
                 }) => {
                     let mut items = BTreeMap::new();
                     // put the offset at the end if necessary
                     if include_defaults && offset.is_none() {
-                        items.insert(4, default_col("mz_offset"));
+                        items.insert(usize::MAX, default_col("mz_offset"));
                     }
 
                     for (include, ty) in [
@@ -1098,6 +1236,7 @@ pub mod sources {
                         (part, ScalarType::Int32),
                         (time, ScalarType::Timestamp),
                         (topic, ScalarType::String),
+                        (headers, kafka_headers_type()),
                     ] {
                         if let Some(include) = include {
                             items.insert(include.pos + 1, (&include.name, ty.nullable(false)));
@@ -1106,7 +1245,12 @@ pub mod sources {
 
                     items.into_values().collect()
                 }
-                Self::File(_) => {
+                Self::File(FileSourceConnector {
+                    include_filename, ..
+                }) => {
+                    if let Some(include) = include_filename {
+                        columns.push((&include.name, ScalarType::String.nullable(false)));
+                    }
                     if include_defaults {
                         columns.push(default_col("mz_line_no"));
                     }
@@ -1133,6 +1277,7 @@ pub mod sources {
                 }
                 Self::Postgres(_) => vec![],
                 Self::PubNub(_) => vec![],
+                Self::Webhook(_) => vec![],
             }
         }
 
@@ -1146,6 +1291,7 @@ pub mod sources {
                 ExternalSourceConnector::S3(_) => Some("mz_record"),
                 ExternalSourceConnector::Postgres(_) => None,
                 ExternalSourceConnector::PubNub(_) => None,
+                ExternalSourceConnector::Webhook(_) => None,
             }
         }
 
@@ -1156,6 +1302,7 @@ pub mod sources {
                     include_timestamp: time,
                     include_topic: topic,
                     include_offset: offset,
+                    include_headers: headers,
                     ..
                 }) => {
                     // create a sorted list of column types based on the order they were declared in sql
@@ -1163,13 +1310,14 @@ pub mod sources {
                     // already special (it commonly multiple columns embedded in it).
                     let mut items = BTreeMap::new();
                     if include_defaults {
-                        items.insert(4, IncludedColumnSource::DefaultPosition);
+                        items.insert(usize::MAX, IncludedColumnSource::DefaultPosition);
                     }
                     for (include, ty) in [
                         (offset, IncludedColumnSource::Offset),
                         (part, IncludedColumnSource::Partition),
                         (time, IncludedColumnSource::Timestamp),
                         (topic, IncludedColumnSource::Topic),
+                        (headers, IncludedColumnSource::Headers),
                     ] {
                         if let Some(include) = include {
                             items.insert(include.pos, ty);
@@ -1179,8 +1327,19 @@ pub mod sources {
                     items.into_values().collect()
                 }
 
+                ExternalSourceConnector::File(FileSourceConnector {
+                    include_filename, ..
+                }) => {
+                    let mut items = Vec::new();
+                    if include_filename.is_some() {
+                        items.push(IncludedColumnSource::Filename);
+                    }
+                    if include_defaults {
+                        items.push(IncludedColumnSource::DefaultPosition);
+                    }
+                    items
+                }
                 ExternalSourceConnector::Kinesis(_)
-                | ExternalSourceConnector::File(_)
                 | ExternalSourceConnector::AvroOcf(_)
                 | ExternalSourceConnector::S3(_) => {
                     if include_defaults {
@@ -1189,9 +1348,9 @@ pub mod sources {
                         Vec::new()
                     }
                 }
-                ExternalSourceConnector::Postgres(_) | ExternalSourceConnector::PubNub(_) => {
-                    Vec::new()
-                }
+                ExternalSourceConnector::Postgres(_)
+                | ExternalSourceConnector::PubNub(_)
+                | ExternalSourceConnector::Webhook(_) => Vec::new(),
             }
         }
 
@@ -1205,25 +1364,27 @@ pub mod sources {
                 ExternalSourceConnector::S3(_) => "s3",
                 ExternalSourceConnector::Postgres(_) => "postgres",
                 ExternalSourceConnector::PubNub(_) => "pubnub",
+                ExternalSourceConnector::Webhook(_) => "webhook",
             }
         }
 
         /// Optionally returns the name of the upstream resource this source corresponds to.
         /// (Currently only implemented for Kafka and Kinesis, to match old-style behavior
         ///  TODO: decide whether we want file paths and other upstream names to show up in metrics too.
-        pub fn upstream_name(&self) -> Option<&str> {
+        pub fn upstream_name(&self) -> Option<String> {
             match self {
-                ExternalSourceConnector::Kafka(KafkaSourceConnector { topic, .. }) => {
-                    Some(topic.as_str())
+                ExternalSourceConnector::Kafka(KafkaSourceConnector { topics, .. }) => {
+                    Some(topics.to_string())
                 }
                 ExternalSourceConnector::Kinesis(KinesisSourceConnector {
                     stream_name, ..
-                }) => Some(stream_name.as_str()),
+                }) => Some(stream_name.clone()),
                 ExternalSourceConnector::File(_) => None,
                 ExternalSourceConnector::AvroOcf(_) => None,
                 ExternalSourceConnector::S3(_) => None,
                 ExternalSourceConnector::Postgres(_) => None,
                 ExternalSourceConnector::PubNub(_) => None,
+                ExternalSourceConnector::Webhook(_) => None,
             }
         }
 
@@ -1231,6 +1392,8 @@ pub mod sources {
             match self {
                 ExternalSourceConnector::S3(c) => c.requires_single_materialization(),
                 ExternalSourceConnector::Postgres(_) => true,
+                // Each request is delivered to a single instance of the source.
+                ExternalSourceConnector::Webhook(_) => true,
 
                 ExternalSourceConnector::Kafka(_)
                 | ExternalSourceConnector::Kinesis(_)
@@ -1245,13 +1408,32 @@ pub mod sources {
     pub struct KinesisSourceConnector {
         pub stream_name: String,
         pub aws: AwsConfig,
+        /// Where to start reading shards for which no position has been
+        /// recorded yet.
+        pub start_position: KinesisStartPosition,
+    }
+
+    /// The position in a Kinesis shard from which to start reading.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub enum KinesisStartPosition {
+        /// The oldest record in the shard.
+        TrimHorizon,
+        /// The first record at or after the given time, in milliseconds since
+        /// the Unix epoch.
+        AtTimestamp(i64),
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct FileSourceConnector {
+        /// The file to read or, if `pattern` is set, the directory whose
+        /// files to read.
         pub path: PathBuf,
+        /// Restricts the files read from the directory at `path` to those
+        /// whose name matches the pattern.
+        pub pattern: Option<Glob>,
         pub tail: bool,
         pub compression: Compression,
+        pub include_filename: Option<IncludedColumnPos>,
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -1267,6 +1449,26 @@ pub mod sources {
         pub channel: String,
     }
 
+    /// A source that is fed by HTTP requests to materialized's webhook
+    /// endpoint, rather than by reading from an external system.
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct WebhookSourceConnector {
+        /// How to validate the signature of each request, if at all.
+        pub validation: Option<WebhookValidation>,
+    }
+
+    /// Requires each request to a webhook source to carry an HMAC-SHA256
+    /// signature of its body.
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct WebhookValidation {
+        /// The path of the file that holds the key that requests are signed
+        /// with. The file is read for every request, so that the key never
+        /// ends up in the catalog and can be rotated in place.
+        pub secret_file: String,
+        /// The name of the header that carries the hex-encoded signature.
+        pub header: String,
+    }
+
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct S3SourceConnector {
         pub key_sources: Vec<S3KeySource>,
@@ -1361,11 +1563,14 @@ pub mod sinks {
     use serde::{Deserialize, Serialize};
     use timely::progress::frontier::Antichain;
     use url::Url;
+    use uuid::Uuid;
 
-    use expr::GlobalId;
+    use expr::{GlobalId, MirScalarExpr};
     use kafka_util::KafkaAddrs;
     use repr::{RelationDesc, Timestamp};
 
+    use crate::sources::AwsConfig;
+
     /// A sink for updates to a relational collection.
     #[derive(Clone, Debug, Serialize, Deserialize)]
     pub struct SinkDesc {
@@ -1380,6 +1585,12 @@ pub mod sinks {
     pub enum SinkEnvelope {
         Debezium,
         Upsert,
+        /// Only insertions are written out, as plain rows. The sinked
+        /// collection must be append-only.
+        AppendOnly,
+        /// Updates are written out in the CDCv2 format, with their exact
+        /// timestamps and diffs, followed by progress statements.
+        CdcV2,
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -1393,12 +1604,16 @@ pub mod sinks {
         Kafka(KafkaSinkConnector),
         Tail(TailSinkConnector),
         AvroOcf(AvroOcfSinkConnector),
+        S3(S3SinkConnector),
+        Postgres(PostgresSinkConnector),
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct KafkaSinkConsistencyConnector {
         pub topic: String,
-        pub schema_id: i32,
+        /// The ID of the Avro schema of the consistency records, or `None` if
+        /// the consistency records are encoded as JSON.
+        pub schema_id: Option<i32>,
         // gate_ts is the most recent high watermark tailed from the consistency topic
         // Exactly-once sinks use this to determine when they should start publishing again. This
         // tells them when they have caught up to where the previous materialize instance stopped.
@@ -1415,6 +1630,7 @@ pub mod sinks {
         pub value_desc: RelationDesc,
         pub published_schema_info: Option<PublishedSchemaInfo>,
         pub consistency: Option<KafkaSinkConsistencyConnector>,
+        pub partition_by: Option<KafkaSinkPartitionBy>,
         pub exactly_once: bool,
         // Source dependencies for exactly-once sinks.
         pub transitive_source_dependencies: Vec<GlobalId>,
@@ -1424,6 +1640,16 @@ pub mod sinks {
         pub config_options: BTreeMap<String, String>,
     }
 
+    /// Determines the partition of each record that a Kafka sink writes.
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct KafkaSinkPartitionBy {
+        /// An expression over the sink key that evaluates to a `bigint`. The
+        /// record is written to this value modulo the partition count.
+        pub expr: MirScalarExpr,
+        /// The number of partitions of the topic.
+        pub partition_count: i32,
+    }
+
     /// TODO(JLDLaughlin): Documentation.
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct PublishedSchemaInfo {
@@ -1437,6 +1663,83 @@ pub mod sinks {
         pub path: PathBuf,
     }
 
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct S3SinkConnector {
+        pub bucket: String,
+        /// The key prefix under which this sink writes its batches. Data files
+        /// go under `{prefix}/data/` and manifests under `{prefix}/manifest/`.
+        pub prefix: String,
+        pub aws: AwsConfig,
+        pub format: S3SinkFormat,
+        pub mode: S3SinkMode,
+        /// The minimum span of timestamps, in milliseconds, covered by each
+        /// batch.
+        pub interval: Timestamp,
+        pub value_desc: RelationDesc,
+    }
+
+    /// The file format of the batches written by an S3 sink.
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub enum S3SinkFormat {
+        Parquet,
+        AvroOcf,
+    }
+
+    impl S3SinkFormat {
+        /// Returns the extension of the data files in this format.
+        pub fn extension(&self) -> &'static str {
+            match self {
+                S3SinkFormat::Parquet => "parquet",
+                S3SinkFormat::AvroOcf => "avro",
+            }
+        }
+    }
+
+    /// What each batch written by an S3 sink contains.
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub enum S3SinkMode {
+        /// The full contents of the sinked relation as of the batch's upper
+        /// timestamp.
+        Snapshot,
+        /// The updates to the sinked relation between the batch's lower and
+        /// upper timestamps, with their timestamps and diffs.
+        Changes,
+    }
+
+    impl S3SinkMode {
+        /// Returns the name of the mode, as used in the `WITH` options.
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                S3SinkMode::Snapshot => "snapshot",
+                S3SinkMode::Changes => "changes",
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct PostgresSinkConnector {
+        pub conn: String,
+        /// The schema-qualified and quoted name of the downstream table.
+        pub table: String,
+        /// The schema-qualified and quoted name of the table in which the
+        /// sink records the last timestamp it wrote to `table`.
+        pub progress_table: String,
+        /// The key of the sink's row in `progress_table`, which identifies
+        /// the sink across restarts and Materialize deployments.
+        pub progress_key: String,
+        /// The quoted name and the type of the downstream column for each
+        /// column of `value_desc`, in order.
+        pub columns: Vec<(String, String)>,
+        pub key_indices: Vec<usize>,
+        pub value_desc: RelationDesc,
+        // gate_ts is the last timestamp recorded in the progress table. The sink skips
+        // all timestamps up to and including it, as a previous materialize instance
+        // already wrote them out.
+        pub gate_ts: Option<Timestamp>,
+        // Source dependencies for exactly-once sinks.
+        pub transitive_source_dependencies: Vec<GlobalId>,
+    }
+
     impl SinkConnector {
         /// Returns the name of the sink connector.
         pub fn name(&self) -> &'static str {
@@ -1444,6 +1747,8 @@ pub mod sinks {
                 SinkConnector::AvroOcf(_) => "avro-ocf",
                 SinkConnector::Kafka(_) => "kafka",
                 SinkConnector::Tail(_) => "tail",
+                SinkConnector::S3(_) => "s3",
+                SinkConnector::Postgres(_) => "postgres",
             }
         }
 
@@ -1466,6 +1771,8 @@ pub mod sinks {
                 SinkConnector::Kafka(k) => k.exactly_once,
                 SinkConnector::AvroOcf(_) => false,
                 SinkConnector::Tail(_) => false,
+                SinkConnector::S3(_) => false,
+                SinkConnector::Postgres(_) => true,
             }
         }
 
@@ -1476,6 +1783,8 @@ pub mod sinks {
                 SinkConnector::Kafka(k) => &k.transitive_source_dependencies,
                 SinkConnector::AvroOcf(_) => &[],
                 SinkConnector::Tail(_) => &[],
+                SinkConnector::S3(_) => &[],
+                SinkConnector::Postgres(p) => &p.transitive_source_dependencies,
             }
         }
     }
@@ -1487,6 +1796,8 @@ pub mod sinks {
     pub enum SinkConnectorBuilder {
         Kafka(KafkaSinkConnectorBuilder),
         AvroOcf(AvroOcfSinkConnectorBuilder),
+        S3(S3SinkConnectorBuilder),
+        Postgres(PostgresSinkConnectorBuilder),
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -1496,6 +1807,34 @@ pub mod sinks {
         pub value_desc: RelationDesc,
     }
 
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct S3SinkConnectorBuilder {
+        pub bucket: String,
+        /// The user-specified key prefix, without a trailing slash.
+        pub prefix: String,
+        pub prefix_suffix_nonce: String,
+        pub aws: AwsConfig,
+        pub format: S3SinkFormat,
+        pub mode: S3SinkMode,
+        pub interval: Timestamp,
+        pub value_desc: RelationDesc,
+    }
+
+    #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    pub struct PostgresSinkConnectorBuilder {
+        pub conn: String,
+        /// The name of the downstream table, as specified by the user.
+        pub table: String,
+        /// The user-specified key of the sink, which must be a unique key of
+        /// both the sinked relation and the downstream table.
+        pub key_indices: Vec<usize>,
+        pub value_desc: RelationDesc,
+        /// The ID of the Materialize deployment that created the sink.
+        pub cluster_id: Uuid,
+        // Source dependencies for exactly-once sinks.
+        pub transitive_source_dependencies: Vec<GlobalId>,
+    }
+
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
     pub struct KafkaSinkConnectorBuilder {
         pub broker_addrs: KafkaAddrs,
@@ -1508,6 +1847,9 @@ pub mod sinks {
         pub topic_prefix: String,
         pub consistency_topic_prefix: Option<String>,
         pub consistency_format: Option<KafkaSinkFormat>,
+        /// An expression over the key columns that determines the partition
+        /// of each record.
+        pub partition_by: Option<MirScalarExpr>,
         pub topic_suffix_nonce: String,
         pub partition_count: i32,
         pub replication_factor: i32,
@@ -1516,6 +1858,11 @@ pub mod sinks {
         // Forces the sink to always write to the same topic across restarts instead
         // of picking a new topic each time.
         pub reuse_topic: bool,
+        // Whether the sink writes transactionally and holds back compaction of
+        // its sources so that restarts neither skip nor repeat updates. Only
+        // meaningful if `reuse_topic` is set; otherwise a reused topic only
+        // resumes after the last complete timestamp in the consistency topic.
+        pub exactly_once: bool,
         // Source dependencies for exactly-once sinks.
         pub transitive_source_dependencies: Vec<GlobalId>,
         pub retention: KafkaSinkConnectorRetention,
@@ -1525,6 +1872,9 @@ pub mod sinks {
     pub struct KafkaSinkConnectorRetention {
         pub retention_ms: Option<i64>,
         pub retention_bytes: Option<i64>,
+        /// Whether the topic is compacted, i.e. created with
+        /// `cleanup.policy=compact`, rather than deleting old segments.
+        pub compact: bool,
     }
 
     #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
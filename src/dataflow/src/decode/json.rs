@@ -0,0 +1,295 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use dataflow_types::{sources::encoding::JsonEncoding, DecodeError};
+use interchange::avro::{push_coords, DebeziumSourceCoordinates};
+use repr::adt::jsonb::JsonbPacker;
+use repr::adt::numeric;
+use repr::{strconv, ColumnName, ColumnType, Datum, Row, ScalarType};
+
+#[derive(Debug)]
+pub struct JsonDecoderState {
+    columns: Option<Vec<(ColumnName, ColumnType)>>,
+    /// Set if each message is a Debezium change event.
+    debezium: Option<DebeziumState>,
+    debug_name: String,
+    row_packer: Row,
+}
+
+#[derive(Debug, Default)]
+struct DebeziumState {
+    record_packer: Row,
+    filenames_to_indices: HashMap<Vec<u8>, usize>,
+    warned_on_unknown: bool,
+}
+
+impl JsonDecoderState {
+    pub fn new(JsonEncoding { columns, debezium }: JsonEncoding, debug_name: String) -> Self {
+        JsonDecoderState {
+            columns,
+            debezium: if debezium {
+                Some(DebeziumState::default())
+            } else {
+                None
+            },
+            debug_name,
+            row_packer: Row::default(),
+        }
+    }
+
+    /// Decodes a JSON-encoded message into a `Row`.
+    // The `Row` has the same shapes as the ones produced by the Avro decoder:
+    // * For Debezium change events it will be:
+    //   `Row(List[before-row], List[after-row], List[offsets]?, upstream_time_millis)`
+    // * For plain JSON objects it will just be `Row(columns...)`, or a single
+    //   `jsonb` column if no columns were specified.
+    pub fn decode(
+        &mut self,
+        bytes: &[u8],
+        upstream_time_millis: Option<i64>,
+    ) -> Result<Option<Row>, DecodeError> {
+        let columns = match &self.columns {
+            None => {
+                let row = JsonbPacker::new(std::mem::take(&mut self.row_packer))
+                    .pack_slice(bytes)
+                    .map_err(|e| DecodeError::Text(format!("Failed to decode JSON: {:#}", e)))?;
+                return Ok(Some(row));
+            }
+            Some(columns) => columns,
+        };
+
+        let value: Value = serde_json::from_slice(bytes)
+            .map_err(|e| DecodeError::Text(format!("Failed to decode JSON: {}", e)))?;
+        let object = unwrap_payload(expect_object(value)?, columns);
+        self.row_packer.clear();
+        match &mut self.debezium {
+            None => {
+                let row_packer = std::mem::take(&mut self.row_packer);
+                self.row_packer = pack_object(row_packer, object, columns)?;
+            }
+            Some(dbz) => {
+                let mut event = object;
+                for field in ["before", "after"] {
+                    match event.remove(field) {
+                        None | Some(Value::Null) => self.row_packer.push(Datum::Null),
+                        Some(record) => {
+                            dbz.record_packer.clear();
+                            let record_packer = std::mem::take(&mut dbz.record_packer);
+                            dbz.record_packer =
+                                pack_object(record_packer, expect_object(record)?, columns)?;
+                            self.row_packer
+                                .push_list_with(|row| row.extend_by_row(&dbz.record_packer));
+                        }
+                    }
+                }
+                let coords =
+                    DebeziumSourceCoordinates::from_json(&event, &mut dbz.filenames_to_indices)
+                        .map_err(|e| {
+                            DecodeError::Text(format!("Failed to decode Debezium source: {}", e))
+                        })?;
+                if !push_coords(coords, &mut self.row_packer) && !dbz.warned_on_unknown {
+                    dbz.warned_on_unknown = true;
+                    tracing::warn!(
+                        "Record with unrecognized source coordinates in {}. \
+                         You might be using an unsupported upstream database.",
+                        self.debug_name
+                    );
+                }
+                self.row_packer.push(match upstream_time_millis {
+                    Some(value) => Datum::Int64(value),
+                    None => Datum::Null,
+                });
+            }
+        }
+        Ok(Some(self.row_packer.finish_and_reuse()))
+    }
+}
+
+fn expect_object(value: Value) -> Result<Map<String, Value>, DecodeError> {
+    match value {
+        Value::Object(object) => Ok(object),
+        other => Err(DecodeError::Text(format!(
+            "Failed to decode JSON: expected an object, found {}",
+            other
+        ))),
+    }
+}
+
+/// Unwraps messages written by Kafka Connect's JSON converter with schemas
+/// enabled, which embed the schema of each message next to its `payload`.
+///
+/// Objects whose columns are named `schema` and `payload` are left alone.
+fn unwrap_payload(
+    mut object: Map<String, Value>,
+    columns: &[(ColumnName, ColumnType)],
+) -> Map<String, Value> {
+    let is_wrapped = object.len() == 2
+        && object.contains_key("schema")
+        && matches!(object.get("payload"), Some(Value::Object(_)))
+        && !columns
+            .iter()
+            .any(|(name, _)| name.as_str() == "schema" || name.as_str() == "payload");
+    if is_wrapped {
+        match object.remove("payload") {
+            Some(Value::Object(payload)) => payload,
+            _ => unreachable!("checked above"),
+        }
+    } else {
+        object
+    }
+}
+
+/// Appends the `columns` of `object` to `row`.
+fn pack_object(
+    mut row: Row,
+    mut object: Map<String, Value>,
+    columns: &[(ColumnName, ColumnType)],
+) -> Result<Row, DecodeError> {
+    for (name, typ) in columns {
+        match object.remove(name.as_str()) {
+            None | Some(Value::Null) if typ.nullable => row.push(Datum::Null),
+            None | Some(Value::Null) => {
+                return Err(DecodeError::Text(format!(
+                    "Failed to decode JSON: missing value for non-nullable field {}",
+                    name
+                )))
+            }
+            Some(value) => {
+                row = pack_value(row, value, &typ.scalar_type).map_err(|e| {
+                    DecodeError::Text(format!("Failed to decode JSON field {}: {}", name, e))
+                })?;
+            }
+        }
+    }
+    Ok(row)
+}
+
+/// Appends `value` to `row` as a datum of type `typ`.
+///
+/// The planner only admits the scalar types handled here.
+fn pack_value(mut row: Row, value: Value, typ: &ScalarType) -> Result<Row, String> {
+    match (typ, value) {
+        (ScalarType::Jsonb, value) => {
+            return JsonbPacker::new(row)
+                .pack_serde_json(value)
+                .map_err(|e| e.to_string())
+        }
+        (ScalarType::Bool, Value::Bool(b)) => row.push(Datum::from(b)),
+        (ScalarType::Int16, Value::Number(n)) => {
+            let i = n.as_i64().and_then(|i| i16::try_from(i).ok());
+            row.push(Datum::Int16(i.ok_or_else(|| out_of_range(&n, typ))?))
+        }
+        (ScalarType::Int32, Value::Number(n)) => {
+            let i = n.as_i64().and_then(|i| i32::try_from(i).ok());
+            row.push(Datum::Int32(i.ok_or_else(|| out_of_range(&n, typ))?))
+        }
+        (ScalarType::Int64, Value::Number(n)) => {
+            let i = n.as_i64().ok_or_else(|| out_of_range(&n, typ))?;
+            row.push(Datum::Int64(i))
+        }
+        (ScalarType::Float32, Value::Number(n)) => {
+            let f = n.as_f64().ok_or_else(|| out_of_range(&n, typ))?;
+            // Like PostgreSQL's float4, reject values that overflow to
+            // infinity or underflow to zero rather than silently rounding them.
+            let f32 = f as f32;
+            if f32.is_infinite() || (f32 == 0.0 && f != 0.0) {
+                return Err(out_of_range(&n, typ));
+            }
+            row.push(Datum::from(f32))
+        }
+        (ScalarType::Float64, Value::Number(n)) => {
+            let f = n.as_f64().ok_or_else(|| out_of_range(&n, typ))?;
+            row.push(Datum::from(f))
+        }
+        (ScalarType::Numeric { scale }, Value::Number(n)) => {
+            let mut d = strconv::parse_numeric(&n.to_string()).map_err(|e| e.to_string())?;
+            if let Some(scale) = scale {
+                numeric::rescale(&mut d.0, *scale).map_err(|e| e.to_string())?;
+            }
+            row.push(Datum::Numeric(d))
+        }
+        (ScalarType::String, Value::String(s)) => row.push(Datum::String(&s)),
+        (ScalarType::Date, Value::String(s)) => row.push(Datum::Date(
+            strconv::parse_date(&s).map_err(|e| e.to_string())?,
+        )),
+        (ScalarType::Timestamp, Value::String(s)) => row.push(Datum::Timestamp(
+            strconv::parse_timestamp(&s).map_err(|e| e.to_string())?,
+        )),
+        (ScalarType::TimestampTz, Value::String(s)) => row.push(Datum::TimestampTz(
+            strconv::parse_timestamptz(&s).map_err(|e| e.to_string())?,
+        )),
+        (ScalarType::Uuid, Value::String(s)) => row.push(Datum::Uuid(
+            strconv::parse_uuid(&s).map_err(|e| e.to_string())?,
+        )),
+        (typ, value) => return Err(format!("expected {:?}, found {}", typ, value)),
+    }
+    Ok(row)
+}
+
+fn out_of_range(n: &serde_json::Number, typ: &ScalarType) -> String {
+    format!("{} is out of range for {:?}", n, typ)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pack_float32(value: Value) -> Result<Datum<'static>, String> {
+        let row = pack_value(Row::default(), value, &ScalarType::Float32)?;
+        match row.unpack_first() {
+            Datum::Float32(f) => Ok(Datum::Float32(f)),
+            datum => panic!("expected a float32, got {:?}", datum),
+        }
+    }
+
+    #[test]
+    fn float32_range() {
+        for f in [
+            0.0,
+            -0.0,
+            1.5,
+            f32::MAX,
+            f32::MIN,
+            f32::MIN_POSITIVE,
+            -f32::MIN_POSITIVE,
+        ] {
+            assert_eq!(pack_float32(json!(f)), Ok(Datum::from(f)), "{}", f);
+        }
+        // Whole numbers are decoded too.
+        assert_eq!(
+            pack_float32(json!(16_777_216)),
+            Ok(Datum::from(16_777_216.0f32))
+        );
+
+        // Just past the largest finite float32 rounds back down to it, but
+        // anything that would round to infinity is out of range.
+        assert_eq!(
+            pack_float32(json!(f64::from(f32::MAX) * (1.0 + f64::EPSILON))),
+            Ok(Datum::from(f32::MAX))
+        );
+        for f in [
+            3.5e38,
+            -3.5e38,
+            f64::MAX,
+            f64::MIN,
+            1e-50,
+            -1e-50,
+            f64::MIN_POSITIVE,
+        ] {
+            let err = pack_float32(json!(f)).unwrap_err();
+            assert!(err.contains("out of range for Float32"), "{}: {}", f, err);
+        }
+    }
+}
@@ -41,12 +41,16 @@ use tracing::error;
 
 use self::avro::AvroDecoderState;
 use self::csv::CsvDecoderState;
+use self::json::JsonDecoderState;
+use self::parquet::ParquetDecoderState;
 use self::protobuf::ProtobufDecoderState;
 use crate::metrics::Metrics;
 use crate::source::{DecodeResult, SourceOutput};
 
 mod avro;
 mod csv;
+mod json;
+mod parquet;
 mod protobuf;
 
 /// Update row to blank out retractions of rows that we have never seen
@@ -188,10 +192,15 @@ pub(crate) enum PreDelimitedFormat {
     Text,
     Regex(Regex, Row),
     Protobuf(ProtobufDecoderState),
+    Json(JsonDecoderState),
 }
 
 impl PreDelimitedFormat {
-    pub fn decode(&mut self, bytes: &[u8]) -> Result<Option<Row>, DecodeError> {
+    pub fn decode(
+        &mut self,
+        bytes: &[u8],
+        upstream_time_millis: Option<i64>,
+    ) -> Result<Option<Row>, DecodeError> {
         match self {
             PreDelimitedFormat::Bytes => Ok(Some(Row::pack(Some(Datum::Bytes(bytes))))),
             PreDelimitedFormat::Text => {
@@ -215,6 +224,7 @@ impl PreDelimitedFormat {
                 Ok(Some(row_packer.finish_and_reuse()))
             }
             PreDelimitedFormat::Protobuf(pb) => pb.get_value(bytes).transpose(),
+            PreDelimitedFormat::Json(json) => json.decode(bytes, upstream_time_millis),
         }
     }
 }
@@ -227,6 +237,7 @@ pub(crate) enum DataDecoderInner {
         format: PreDelimitedFormat,
     },
     Csv(CsvDecoderState),
+    Parquet(ParquetDecoderState),
 
     PreDelimited(PreDelimitedFormat),
 }
@@ -252,12 +263,17 @@ impl DataDecoder {
                 };
                 let data = &bytes[0..chunk_idx];
                 *bytes = &bytes[chunk_idx + 1..];
-                format.decode(data)
+                format.decode(data, upstream_time_millis)
             }
             DataDecoderInner::Avro(avro) => avro.decode(bytes, upstream_time_millis),
             DataDecoderInner::Csv(csv) => csv.decode(bytes),
+            DataDecoderInner::Parquet(parquet) => {
+                parquet.buffer(bytes);
+                *bytes = &[];
+                Ok(None)
+            }
             DataDecoderInner::PreDelimited(format) => {
-                let result = format.decode(*bytes);
+                let result = format.decode(*bytes, upstream_time_millis);
                 *bytes = &[];
                 result
             }
@@ -268,8 +284,12 @@ impl DataDecoder {
     ///
     /// This is distinct from `next` because, for example, a CSV record should be returned even if it
     /// does not end in a newline.
+    ///
+    /// If [`DataDecoder::has_buffered_records`] returns true afterwards, the
+    /// remaining records of the object can be retrieved by calling this again.
     pub fn eof(&mut self, bytes: &mut &[u8]) -> Result<Option<Row>, DecodeError> {
         match &mut self.inner {
+            DataDecoderInner::Parquet(parquet) => parquet.eof(),
             DataDecoderInner::Csv(csv) => {
                 let result = csv.decode(bytes);
                 csv.reset_for_new_object();
@@ -282,13 +302,22 @@ impl DataDecoder {
                 if data.is_empty() {
                     Ok(None)
                 } else {
-                    format.decode(data)
+                    format.decode(data, None)
                 }
             }
             _ => Ok(None),
         }
     }
 
+    /// Reports whether records decoded at the last EOF have yet to be
+    /// returned by [`DataDecoder::eof`].
+    pub fn has_buffered_records(&self) -> bool {
+        match &self.inner {
+            DataDecoderInner::Parquet(parquet) => parquet.has_buffered_records(),
+            _ => false,
+        }
+    }
+
     pub fn log_errors(&self, n: usize) {
         self.metrics.count_errors(&self.inner, n);
     }
@@ -340,7 +369,8 @@ fn get_decoder(
         DataEncoding::Text
         | DataEncoding::Bytes
         | DataEncoding::Protobuf(_)
-        | DataEncoding::Regex(_) => {
+        | DataEncoding::Regex(_)
+        | DataEncoding::Json(_) => {
             let after_delimiting = match encoding {
                 DataEncoding::Regex(RegexEncoding { regex }) => {
                     PreDelimitedFormat::Regex(regex, Default::default())
@@ -351,6 +381,10 @@ fn get_decoder(
                                     client creation in purification.",
                     ))
                 }
+                DataEncoding::Json(encoding) => PreDelimitedFormat::Json(JsonDecoderState::new(
+                    encoding,
+                    debug_name.to_string(),
+                )),
                 DataEncoding::Bytes => PreDelimitedFormat::Bytes,
                 DataEncoding::Text => PreDelimitedFormat::Text,
                 _ => unreachable!(),
@@ -405,6 +439,13 @@ fn get_decoder(
                 metrics,
             }
         }
+        DataEncoding::Parquet(enc) => {
+            let state = ParquetDecoderState::new(enc, operators);
+            DataDecoder {
+                inner: DataDecoderInner::Parquet(state),
+                metrics,
+            }
+        }
         DataEncoding::Postgres => {
             unreachable!("Postgres sources should not go through the general decoding path.")
         }
@@ -516,6 +557,10 @@ where
                         position,
                         upstream_time_millis: upstream_time,
                         partition,
+                        topic,
+                        headers,
+                        filename: _,
+                        records_before: _,
                     } in data.iter()
                     {
                         let key = key_decoder
@@ -539,6 +584,9 @@ where
                                 partition.clone(),
                                 *position,
                                 *upstream_time,
+                                topic.as_deref(),
+                                headers.as_deref(),
+                                None,
                             ),
                         });
                     }
@@ -603,7 +651,9 @@ where
 
     // The `position` value from `SourceOutput` is meaningless here -- it's just the index of a chunk.
     // We therefore ignore it, and keep track ourselves of how many records we've seen (for filling in `mz_line_no`, etc).
-    // Historically, non-delimited sources have their offset start at 1
+    // Historically, non-delimited sources have their offset start at 1. Sources that resume from
+    // a checkpoint tell us how many records they had produced before, so that we continue from
+    // there rather than from 1.
     let mut n_seen = 1..;
     let results = stream.unary_frontier(Pipeline, &op_name, move |_, _| {
         let metadata_items = metadata_items;
@@ -620,8 +670,15 @@ where
                     position: _,
                     upstream_time_millis,
                     partition,
+                    topic: _,
+                    headers: _,
+                    filename,
+                    records_before,
                 } in data.iter()
                 {
+                    if let Some(records_before) = records_before {
+                        n_seen = *records_before + 1..;
+                    }
                     let value = match value {
                         MessagePayload::Data(data) => data,
                         MessagePayload::EOF => {
@@ -635,29 +692,38 @@ where
                             }
                             value_buf.clear();
 
-                            match result.transpose() {
-                                None => continue,
-                                Some(value) => {
-                                    if value.is_err() {
-                                        n_errors += 1;
-                                    } else if matches!(&value, Ok(_)) {
-                                        n_successes += 1;
-                                    }
-                                    let position = n_seen.next();
-                                    let metadata = to_metadata_row(
-                                        &metadata_items,
-                                        partition.clone(),
-                                        position,
-                                        *upstream_time_millis,
-                                    );
-
-                                    session.give(DecodeResult {
-                                        key: None,
-                                        value: Some(value),
-                                        position,
-                                        metadata,
-                                    });
+                            // Some decoders (e.g., Parquet) can only decode an
+                            // object once it has been read in full, and so
+                            // produce all of its records at EOF.
+                            while let Some(value) = result.transpose() {
+                                let is_ok = value.is_ok();
+                                if is_ok {
+                                    n_successes += 1;
+                                } else {
+                                    n_errors += 1;
                                 }
+                                let position = n_seen.next();
+                                let metadata = to_metadata_row(
+                                    &metadata_items,
+                                    partition.clone(),
+                                    position,
+                                    *upstream_time_millis,
+                                    None,
+                                    None,
+                                    filename.as_deref(),
+                                );
+
+                                session.give(DecodeResult {
+                                    key: None,
+                                    value: Some(value),
+                                    position,
+                                    metadata,
+                                });
+
+                                if !is_ok || !value_decoder.has_buffered_records() {
+                                    break;
+                                }
+                                result = value_decoder.eof(&mut &[][..]);
                             }
                             continue;
                         }
@@ -678,6 +744,9 @@ where
                             partition.clone(),
                             None,
                             *upstream_time_millis,
+                            None,
+                            None,
+                            filename.as_deref(),
                         );
                         session.give(DecodeResult {
                             key: None,
@@ -722,6 +791,9 @@ where
                                 partition.clone(),
                                 position,
                                 *upstream_time_millis,
+                                None,
+                                None,
+                                filename.as_deref(),
                             );
 
                             if value_bytes_remaining.is_empty() {
@@ -767,10 +839,13 @@ fn to_metadata_row(
     partition: PartitionId,
     position: Option<i64>,
     upstream_time_millis: Option<i64>,
+    topic: Option<&str>,
+    headers: Option<&[(String, Option<Vec<u8>>)]>,
+    filename: Option<&str>,
 ) -> Row {
     let mut row = Row::default();
     match partition {
-        PartitionId::Kafka(partition) => {
+        PartitionId::Kafka(partition) | PartitionId::KafkaTopic(_, partition) => {
             for item in metadata_items.iter() {
                 match item {
                     IncludedColumnSource::Partition => row.push(Datum::from(partition)),
@@ -791,7 +866,27 @@ fn to_metadata_row(
                             millis * 1_000_000,
                         )))
                     }
-                    IncludedColumnSource::Topic => unreachable!("Topic is not implemented yet"),
+                    IncludedColumnSource::Topic => row.push(Datum::from(
+                        topic.expect("kafka sources that include the topic always have a topic"),
+                    )),
+                    IncludedColumnSource::Headers => {
+                        let headers = headers
+                            .expect("kafka sources that include headers always have headers");
+                        row.push_list_with(|row| {
+                            for (key, value) in headers {
+                                row.push_list_with(|row| {
+                                    row.push(Datum::from(key.as_str()));
+                                    row.push(match value {
+                                        Some(value) => Datum::from(value.as_slice()),
+                                        None => Datum::Null,
+                                    });
+                                });
+                            }
+                        });
+                    }
+                    IncludedColumnSource::Filename => {
+                        unreachable!("Kafka sources cannot include a filename")
+                    }
                 }
             }
         }
@@ -801,7 +896,12 @@ fn to_metadata_row(
                     IncludedColumnSource::DefaultPosition => row.push(Datum::from(
                         position.expect("kafka sources always have position"),
                     )),
-                    _ => unreachable!("Only Kafka supports non-defaultposition metadata items"),
+                    IncludedColumnSource::Filename => row.push(Datum::from(
+                        filename.expect("file sources that include the filename always have one"),
+                    )),
+                    _ => unreachable!(
+                        "Only Kafka and file sources support non-defaultposition metadata items"
+                    ),
                 }
             }
         }
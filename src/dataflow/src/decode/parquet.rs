@@ -0,0 +1,89 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+use std::collections::VecDeque;
+
+use dataflow_types::{sources::encoding::ParquetEncoding, DecodeError, LinearOperator};
+use interchange::parquet::{Decoder, RowGroups};
+use repr::Row;
+
+/// Decodes Parquet objects.
+///
+/// Parquet stores its metadata at the end of each file, so nothing can be
+/// decoded until the whole object has been buffered. At EOF, the rows of the
+/// object are decoded one row group at a time, and the rows of each row group
+/// are handed out before the next one is decoded.
+#[derive(Debug)]
+pub struct ParquetDecoderState {
+    decoder: Decoder,
+    buffer: Vec<u8>,
+    /// The row groups of the object that have yet to be decoded.
+    row_groups: Option<RowGroups>,
+    /// The rows of the last decoded row group that have yet to be handed out.
+    pending: VecDeque<Row>,
+}
+
+impl ParquetDecoderState {
+    pub fn new(
+        ParquetEncoding { columns }: ParquetEncoding,
+        operators: &mut Option<LinearOperator>,
+    ) -> Self {
+        // Push the projection down into the Parquet reader, so that columns
+        // that are never used are never read.
+        let operators = operators.take();
+        let demanded = (0..columns.len())
+            .map(|c| {
+                operators
+                    .as_ref()
+                    .map(|o| o.projection.contains(&c))
+                    .unwrap_or(true)
+            })
+            .collect();
+        ParquetDecoderState {
+            decoder: Decoder::new(columns, demanded),
+            buffer: vec![],
+            row_groups: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn buffer(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn eof(&mut self) -> Result<Option<Row>, DecodeError> {
+        let to_error =
+            |e: anyhow::Error| DecodeError::Text(format!("Failed to decode Parquet file: {:#}", e));
+        if !self.buffer.is_empty() {
+            let buffer = std::mem::take(&mut self.buffer);
+            self.pending.clear();
+            self.row_groups = Some(self.decoder.decode_file(buffer).map_err(to_error)?);
+        }
+        while self.pending.is_empty() {
+            let row_groups = match &mut self.row_groups {
+                Some(row_groups) => row_groups,
+                None => return Ok(None),
+            };
+            match row_groups.next() {
+                Some(Ok(rows)) => self.pending.extend(rows),
+                Some(Err(e)) => {
+                    // An error ends the object.
+                    self.row_groups = None;
+                    return Err(to_error(e));
+                }
+                None => self.row_groups = None,
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    pub fn has_buffered_records(&self) -> bool {
+        !self.pending.is_empty() || self.row_groups.is_some()
+    }
+}
@@ -15,7 +15,8 @@ use std::collections::HashSet;
 use std::rc::Rc;
 
 use differential_dataflow::operators::arrange::arrangement::ArrangeByKey;
-use differential_dataflow::{Collection, Hashable};
+use differential_dataflow::{AsCollection, Collection, Hashable};
+use timely::dataflow::operators::Filter;
 use timely::dataflow::Scope;
 
 use dataflow_types::sinks::*;
@@ -159,6 +160,11 @@ where
     //   It then renders those as Avro.
     // * Upsert" does the same, except at the last step, it renders the diff pair in upsert format.
     //   (As part of doing so, it asserts that there are not multiple conflicting values at the same timestamp)
+    // * "AppendOnly" writes out the inserted rows as they are. The coordinator only allows it
+    //   on collections that it can prove to be append-only, so retractions should never show
+    //   up. Should one do anyway, it is dropped rather than written out.
+    // * "CdcV2" writes out the updates with their diffs, negative ones included. The sink
+    //   encodes their times and diffs alongside the rows.
     // * "Tail" writes some metadata.
     let collection = match sink.envelope {
         Some(SinkEnvelope::Debezium) => {
@@ -194,7 +200,24 @@ where
             });
             collection
         }
-        // No envelope, this can only happen for TAIL sinks, which work
+        Some(SinkEnvelope::AppendOnly) => {
+            // if there is no user-specified key, remove the synthetic
+            // distribution key again
+            let user_key_indices = sink_render.get_key_indices();
+            let keyed = if user_key_indices.is_some() {
+                keyed
+            } else {
+                keyed.map(|(_key, value)| (None, value))
+            };
+
+            keyed
+                .inner
+                .filter(|(_, _, diff)| *diff > 0)
+                .as_collection()
+                .map(|(key, value)| (key, Some(value)))
+        }
+        Some(SinkEnvelope::CdcV2) => keyed.map(|(_key, value)| (None, Some(value))),
+        // No envelope, this can only happen for TAIL and S3 sinks, which work
         // on vanilla rows.
         None => keyed.map(|(key, value)| (key, Some(value))),
     };
@@ -233,5 +256,7 @@ where
         SinkConnector::Kafka(connector) => Box::new(connector.clone()),
         SinkConnector::AvroOcf(connector) => Box::new(connector.clone()),
         SinkConnector::Tail(connector) => Box::new(connector.clone()),
+        SinkConnector::S3(connector) => Box::new(connector.clone()),
+        SinkConnector::Postgres(connector) => Box::new(connector.clone()),
     }
 }
@@ -11,12 +11,15 @@
 
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
 
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::{collection, AsCollection, Collection, Hashable};
 use serde::{Deserialize, Serialize};
+use timely::dataflow::channels::pact::Pipeline;
 use timely::dataflow::operators::generic::operator;
-use timely::dataflow::operators::{Concat, Map, OkErr, UnorderedInput};
+use timely::dataflow::operators::{Concat, Delay, Map, OkErr, Operator, UnorderedInput};
 use timely::dataflow::{Scope, Stream};
 
 use persist::client::StreamWriteHandle;
@@ -45,8 +48,8 @@ use crate::source::metrics::SourceBaseMetrics;
 use crate::source::timestamp::{AssignedTimestamp, SourceTimestamp};
 use crate::source::{
     self, DecodeResult, FileSourceReader, KafkaSourceReader, KinesisSourceReader,
-    PersistentTimestampBindingsConfig, PostgresSourceReader, PubNubSourceReader, S3SourceReader,
-    SourceConfig,
+    PersistentCheckpointsConfig, PersistentTimestampBindingsConfig, PostgresSourceReader,
+    PubNubSourceReader, S3SourceReader, SourceConfig, SourceOutput,
 };
 
 /// A type-level enum that holds one of two types of sources depending on their message type
@@ -129,6 +132,42 @@ where
     (local_input, collection_bundle)
 }
 
+/// Imports the requests to a webhook source.
+///
+/// The coordinator inserts the body of each request into the returned local
+/// input as a single-column row of bytes, at the timestamp it assigned to the
+/// request. The bodies are then decoded like the messages of any other source.
+fn import_webhook<G>(
+    scope: &mut G,
+) -> (
+    LocalInput,
+    Stream<G, SourceOutput<Option<Vec<u8>>, Option<Vec<u8>>>>,
+)
+where
+    G: Scope<Timestamp = Timestamp>,
+{
+    let ((handle, capability), stream) = scope.new_unordered_input();
+    // Inserts are introduced at the input's capability, which can trail the
+    // timestamps assigned to them.
+    let stream = stream
+        .delay(|(_, time, _): &(Row, Timestamp, Diff), _| *time)
+        .map(|(row, _, _)| {
+            let body = row.unpack_first().unwrap_bytes().to_vec();
+            SourceOutput::new(
+                None,
+                Some(body),
+                None,
+                None,
+                PartitionId::None,
+                None,
+                None,
+                None,
+                None,
+            )
+        });
+    (LocalInput { handle, capability }, stream)
+}
+
 /// Constructs a `CollectionBundle` and tokens from source arguments.
 ///
 /// The first return value is the collection bundle, and the second a pair of source and additional
@@ -184,9 +223,21 @@ where
         // Create a new local input (exposed as TABLEs to users). Data is inserted
         // via Command::Insert commands.
         SourceConnector::Local { persisted_name, .. } => {
+            let persisted = persisted_name.is_some();
             let (local_input, collection_bundle) =
                 import_table(as_of_frontier, storage_state, scope, persisted_name);
-            storage_state.local_inputs.insert(src_id, local_input);
+            // Persisted tables are fed from their persisted stream, not their
+            // local input, and may be imported again to replay their history
+            // at an earlier `as_of`. Such an import must not replace the local
+            // input of the table's original import.
+            if persisted {
+                storage_state
+                    .local_inputs
+                    .entry(src_id)
+                    .or_insert(local_input);
+            } else {
+                storage_state.local_inputs.insert(src_id, local_input);
+            }
 
             // TODO(mcsherry): Local tables are a special non-source we should relocate.
             (collection_bundle, (Rc::new(None), Vec::new()))
@@ -252,7 +303,7 @@ where
             let source_config = SourceConfig {
                 name: source_name.clone(),
                 sql_name: src.name.clone(),
-                upstream_name: connector.upstream_name().map(ToOwned::to_owned),
+                upstream_name: connector.upstream_name(),
                 id: uid,
                 scope,
                 // Distribute read responsibility among workers.
@@ -311,6 +362,9 @@ where
                             source_persist_config
                                 .as_ref()
                                 .map(|config| config.bindings_config.clone()),
+                            source_persist_config
+                                .as_ref()
+                                .and_then(|config| config.checkpoints_config.clone()),
                         );
                         ((SourceType::Delimited(ok), ts, err), cap)
                     }
@@ -321,6 +375,9 @@ where
                             source_persist_config
                                 .as_ref()
                                 .map(|config| config.bindings_config.clone()),
+                            source_persist_config
+                                .as_ref()
+                                .and_then(|config| config.checkpoints_config.clone()),
                         );
                         ((SourceType::Delimited(ok), ts, err), cap)
                     }
@@ -331,6 +388,9 @@ where
                             source_persist_config
                                 .as_ref()
                                 .map(|config| config.bindings_config.clone()),
+                            source_persist_config
+                                .as_ref()
+                                .and_then(|config| config.checkpoints_config.clone()),
                         );
                         ((SourceType::ByteStream(ok), ts, err), cap)
                     }
@@ -341,9 +401,18 @@ where
                             source_persist_config
                                 .as_ref()
                                 .map(|config| config.bindings_config.clone()),
+                            source_persist_config
+                                .as_ref()
+                                .and_then(|config| config.checkpoints_config.clone()),
                         );
                         ((SourceType::ByteStream(ok), ts, err), cap)
                     }
+                    ExternalSourceConnector::Webhook(_) => {
+                        let (local_input, ok) = import_webhook(scope);
+                        storage_state.local_inputs.insert(src_id, local_input);
+                        let (ts, err) = (operator::empty(scope), operator::empty(scope));
+                        ((SourceType::Delimited(ok), ts, err), None)
+                    }
                     ExternalSourceConnector::Postgres(_) => unreachable!(),
                     ExternalSourceConnector::PubNub(_) => unreachable!(),
                 };
@@ -589,6 +658,20 @@ where
                                             &source_name,
                                         );
 
+                                        // Sources that record checkpoints retract the ones
+                                        // that are no longer needed as the sealed data
+                                        // catches up with them.
+                                        if let Some(checkpoints_config) =
+                                            &source_persist_config.checkpoints_config
+                                        {
+                                            track_persisted_offset(
+                                                &ts_bindings,
+                                                &sealed_flattened_stream,
+                                                checkpoints_config.persisted_offset(),
+                                                &source_name,
+                                            );
+                                        }
+
                                         // NOTE: Persistence errors don't go through the same
                                         // pipeline as data, i.e. no sealing and awaiting.
                                         (sealed_flattened_stream, persist_errs)
@@ -728,6 +811,7 @@ where
 pub struct PersistentSourceConfig<K: Codec, V: Codec, ST: Codec, AT: Codec> {
     bindings_config: PersistentTimestampBindingsConfig<ST, AT>, // wrong ordering... AT-ST
     envelope_config: PersistentEnvelopeConfig<K, V>,
+    checkpoints_config: Option<PersistentCheckpointsConfig>,
 }
 
 impl<K: Codec, V: Codec, ST: Codec, AT: Codec> PersistentSourceConfig<K, V, ST, AT> {
@@ -735,10 +819,12 @@ impl<K: Codec, V: Codec, ST: Codec, AT: Codec> PersistentSourceConfig<K, V, ST,
     pub fn new(
         bindings_config: PersistentTimestampBindingsConfig<ST, AT>,
         envelope_config: PersistentEnvelopeConfig<K, V>,
+        checkpoints_config: Option<PersistentCheckpointsConfig>,
     ) -> Self {
         PersistentSourceConfig {
             bindings_config,
             envelope_config,
+            checkpoints_config,
         }
     }
 
@@ -791,6 +877,16 @@ fn get_persist_config(
             &persist_desc.timestamp_bindings_stream.name,
         );
 
+    let checkpoints_config = persist_desc.checkpoints_stream.map(|checkpoints_stream| {
+        let (checkpoints_write, checkpoints_read) =
+            persist_client.create_or_load::<Vec<u8>, ()>(&checkpoints_stream.name);
+        PersistentCheckpointsConfig::new(
+            checkpoints_stream.upper_seal_ts,
+            checkpoints_read,
+            checkpoints_write,
+        )
+    });
+
     match persist_desc.envelope_desc {
         EnvelopePersistDesc::Upsert => {
             let (data_write, data_read) = persist_client
@@ -822,6 +918,7 @@ fn get_persist_config(
             PersistentSourceConfig::new(
                 bindings_config,
                 PersistentEnvelopeConfig::Upsert(upsert_config),
+                checkpoints_config,
             )
         }
         EnvelopePersistDesc::None => {
@@ -853,6 +950,7 @@ fn get_persist_config(
             PersistentSourceConfig::new(
                 bindings_config,
                 PersistentEnvelopeConfig::EnvelopeNone(none_config),
+                checkpoints_config,
             )
         }
     }
@@ -891,6 +989,63 @@ where
     sealed_stream
 }
 
+/// Publishes the offset of the latest timestamp binding whose data has made it through
+/// `sealed_stream`, i.e. has been persisted and sealed.
+///
+/// Like when restoring, a binding counts as sealed once the frontier of the sealed data is
+/// beyond the time at which the binding was written.
+fn track_persisted_offset<G, D>(
+    bindings_stream: &Stream<G, ((SourceTimestamp, AssignedTimestamp), Timestamp, Diff)>,
+    sealed_stream: &Stream<G, (D, Timestamp, Diff)>,
+    persisted_offset: Arc<AtomicI64>,
+    source_name: &str,
+) where
+    G: Scope<Timestamp = Timestamp>,
+    D: timely::Data,
+{
+    let operator_name = format!("{}-track-persisted-offset", source_name);
+    let mut pending: Vec<(Timestamp, i64)> = Vec::new();
+    let mut bindings_buffer = Vec::new();
+    let mut sealed_buffer = Vec::new();
+    let _: Stream<G, ()> = sealed_stream.binary_frontier(
+        bindings_stream,
+        Pipeline,
+        Pipeline,
+        &operator_name,
+        |_cap, _info| {
+            move |sealed_input, bindings_input, _output| {
+                // We only care about the frontier of the sealed data.
+                sealed_input.for_each(|_cap, data| data.swap(&mut sealed_buffer));
+                sealed_buffer.clear();
+                bindings_input.for_each(|_cap, data| {
+                    data.swap(&mut bindings_buffer);
+                    for ((source_timestamp, _assigned_timestamp), ts, diff) in
+                        bindings_buffer.drain(..)
+                    {
+                        if diff > 0 {
+                            pending.push((ts, source_timestamp.offset.offset));
+                        }
+                    }
+                });
+
+                let sealed_frontier = sealed_input.frontier();
+                let mut max_offset = None;
+                pending.retain(|(ts, offset)| {
+                    if sealed_frontier.less_equal(ts) {
+                        true
+                    } else {
+                        max_offset = std::cmp::max(max_offset, Some(*offset));
+                        false
+                    }
+                });
+                if let Some(max_offset) = max_offset {
+                    persisted_offset.fetch_max(max_offset, Ordering::SeqCst);
+                }
+            }
+        },
+    );
+}
+
 /// After handling metadata insertion, we split streams into key/value parts for convenience
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 struct KV {
@@ -919,6 +919,12 @@ where
                             );
                             None
                         }
+                        (ExternalSourceConnector::Webhook(_), _) => {
+                            tracing::debug!(
+                                "Webhook sources do not communicate with the timestamper thread"
+                            );
+                            None
+                        }
                     }
                 } else {
                     tracing::debug!(
@@ -20,7 +20,7 @@ use std::time::Duration;
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::{AsCollection, Collection, Hashable};
 use futures::{StreamExt, TryFutureExt};
-use interchange::json::JsonEncoder;
+use interchange::json::{self, JsonEncoder};
 use itertools::Itertools;
 use ore::collections::CollectionExt;
 use ore::retry::Retry;
@@ -39,18 +39,19 @@ use timely::dataflow::{Scope, Stream};
 use timely::progress::frontier::AntichainRef;
 use timely::progress::Antichain;
 use timely::scheduling::Activator;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
 use dataflow_types::sinks::{
-    KafkaSinkConnector, KafkaSinkConsistencyConnector, PublishedSchemaInfo, SinkAsOf, SinkDesc,
+    KafkaSinkConnector, KafkaSinkConsistencyConnector, KafkaSinkPartitionBy, PublishedSchemaInfo,
+    SinkAsOf, SinkDesc, SinkEnvelope,
 };
 use expr::GlobalId;
-use interchange::avro::{self, AvroEncoder, AvroSchemaGenerator};
+use interchange::avro::{self, cdc_v2, AvroEncoder, AvroSchemaGenerator};
 use interchange::encode::Encode;
 use kafka_util::client::MzClientContext;
 use ore::cast::CastFrom;
 use ore::metrics::{CounterVecExt, DeleteOnDropCounter, DeleteOnDropGauge, GaugeVecExt};
-use repr::{Datum, Diff, RelationDesc, Row, Timestamp};
+use repr::{Datum, Diff, RelationDesc, Row, RowArena, Timestamp};
 use timely_util::async_op;
 use timely_util::operators_async_ext::OperatorBuilderExt;
 use tokio::task;
@@ -109,6 +110,24 @@ where
             sinked_collection
         };
 
+        // CDCv2 sinks write out the time and diff of each update as part of
+        // the update itself
+        let sinked_collection = if sink.envelope == Some(SinkEnvelope::CdcV2) {
+            sinked_collection
+                .inner
+                .map(|((k, v), t, diff)| {
+                    let v = v.map(|mut v| {
+                        v.push(Datum::Int64(t as i64));
+                        v.push(Datum::Int64(diff as i64));
+                        v
+                    });
+                    ((k, v), t, 1)
+                })
+                .as_collection()
+        } else {
+            sinked_collection
+        };
+
         // Extract handles to the relevant source timestamp histories the sink
         // needs to hear from before it can write data out to Kafka.
         let mut source_ts_histories = Vec::new();
@@ -153,6 +172,7 @@ where
                 .clone()
                 .map(|(desc, _indices)| desc),
             self.value_desc.clone(),
+            sink.envelope,
             sink.as_of.clone(),
             source_ts_histories,
             shared_frontier.clone(),
@@ -333,6 +353,14 @@ struct KafkaSinkState {
     /// Timestamp of the latest `END` record that was written out to Kafka.
     latest_progress_ts: Timestamp,
 
+    /// Encoder for the progress statements of CDCv2 sinks, which are written
+    /// out to the data topic.
+    cdc_v2_encoder: Option<cdc_v2::Encoder>,
+
+    /// Lower bound of the next CDCv2 progress statement, which is the upper
+    /// bound of the previous one.
+    cdc_v2_lower: Cell<Timestamp>,
+
     /// Write frontier of this sink.
     ///
     /// The write frontier potentially blocks compaction of timestamp bindings
@@ -353,6 +381,7 @@ impl KafkaSinkState {
         shutdown_flag: Arc<AtomicBool>,
         activator: Activator,
         latest_progress_ts: Timestamp,
+        cdc_v2_encoder: Option<cdc_v2::Encoder>,
         write_frontier: Rc<RefCell<Antichain<Timestamp>>>,
         metrics: &KafkaBaseMetrics,
     ) -> Self {
@@ -391,6 +420,8 @@ impl KafkaSinkState {
             ready_rows: VecDeque::new(),
             send_state: SendState::Init,
             latest_progress_ts,
+            cdc_v2_encoder,
+            cdc_v2_lower: Cell::new(Timestamp::MIN),
             write_frontier,
         }
     }
@@ -577,21 +608,61 @@ impl KafkaSinkState {
             .as_ref()
             .expect("no consistency information");
 
-        let encoded = avro::encode_debezium_transaction_unchecked(
-            consistency.schema_id,
-            &self.topic_prefix,
-            transaction_id,
-            status,
-            message_count,
-        );
+        let (key, encoded) = match consistency.schema_id {
+            Some(schema_id) => (
+                self.topic_prefix.clone().into_bytes(),
+                avro::encode_debezium_transaction_unchecked(
+                    schema_id,
+                    &self.topic_prefix,
+                    transaction_id,
+                    status,
+                    message_count,
+                ),
+            ),
+            // Like the keys of JSON-formatted sinks, the keys of JSON
+            // consistency records are themselves JSON.
+            None => (
+                serde_json::Value::String(self.topic_prefix.clone())
+                    .to_string()
+                    .into_bytes(),
+                json::encode_debezium_transaction(
+                    &self.topic_prefix,
+                    transaction_id,
+                    status,
+                    message_count,
+                ),
+            ),
+        };
 
         let record = BaseRecord::to(&consistency.topic)
             .payload(&encoded)
-            .key(&self.topic_prefix);
+            .key(&key);
 
         self.send(record).await
     }
 
+    /// Writes a CDCv2 progress statement to the data topic that covers all
+    /// times from the upper bound of the previous statement up to `upper`.
+    ///
+    /// `counts` must hold the number of updates that were written out at each
+    /// of these times.
+    async fn send_cdc_v2_progress(
+        &self,
+        upper: Timestamp,
+        counts: &[(Timestamp, usize)],
+    ) -> KafkaResult<()> {
+        let encoder = self.cdc_v2_encoder.as_ref().expect("no CDCv2 encoder");
+
+        let encoded =
+            encoder.encode_progress_unchecked(&[self.cdc_v2_lower.get()], &[upper], counts);
+
+        let record = BaseRecord::<(), _>::to(&self.topic).payload(&encoded);
+
+        self.send(record).await?;
+        self.cdc_v2_lower.set(upper);
+        Ok(())
+    }
+
     /// Asserts that the write frontier has not yet advanced beyond `t`.
     fn assert_progress(&self, ts: &Timestamp) {
         assert!(self.write_frontier.borrow().less_equal(ts));
@@ -660,6 +731,13 @@ impl KafkaSinkState {
                         self.commit_transaction().await?
                     }
                 }
+                // tell readers of CDCv2 sinks that no more updates are coming
+                // up to the write frontier.
+                if self.cdc_v2_encoder.is_some() {
+                    self.send_cdc_v2_progress(min_frontier + 1, &[])
+                        .await
+                        .map_err(|_| anyhow::anyhow!("Error sending write frontier update."))?;
+                }
                 self.latest_progress_ts = min_frontier;
             }
 
@@ -688,10 +766,16 @@ enum SendState {
     Running,
 }
 
+/// An encoded key and value, and the partition to write them to, if not left
+/// to the producer.
+pub type EncodedRecord = (Option<Vec<u8>>, Option<Vec<u8>>, Option<i32>);
+
 #[derive(Debug)]
 struct EncodedRow {
     key: Option<Vec<u8>>,
     value: Option<Vec<u8>>,
+    /// The partition to write the row to, if not left to the producer.
+    partition: Option<i32>,
     count: usize,
 }
 
@@ -702,6 +786,7 @@ fn kafka<G>(
     connector: KafkaSinkConnector,
     key_desc: Option<RelationDesc>,
     value_desc: RelationDesc,
+    envelope: Option<SinkEnvelope>,
     as_of: SinkAsOf,
     source_timestamp_histories: Vec<TimestampBindingRc>,
     write_frontier: Rc<RefCell<Antichain<Timestamp>>>,
@@ -714,7 +799,34 @@ where
 
     let stream = &collection.inner;
 
+    let mut cdc_v2_encoder = None;
+
+    let partitioner = connector.partition_by.clone().map(|partition_by| {
+        SinkPartitioner::new(
+            partition_by,
+            metrics,
+            &connector.topic,
+            &id.to_string(),
+            &collection.scope().index().to_string(),
+        )
+    });
+
     let encoded_stream = match connector.published_schema_info {
+        Some(PublishedSchemaInfo {
+            value_schema_id, ..
+        }) if envelope == Some(SinkEnvelope::CdcV2) => {
+            let encoder = cdc_v2::Encoder::new(value_desc.clone(), value_schema_id);
+            cdc_v2_encoder = Some(cdc_v2::Encoder::new(value_desc, value_schema_id));
+            encode_stream(
+                stream,
+                as_of.clone(),
+                None,
+                None,
+                encoder,
+                connector.fuel,
+                name.clone(),
+            )
+        }
         Some(PublishedSchemaInfo {
             key_schema_id,
             value_schema_id,
@@ -734,6 +846,7 @@ where
                     .consistency
                     .clone()
                     .and_then(|consistency| consistency.gate_ts),
+                partitioner,
                 encoder,
                 connector.fuel,
                 name.clone(),
@@ -748,6 +861,7 @@ where
                     .consistency
                     .clone()
                     .and_then(|consistency| consistency.gate_ts),
+                partitioner,
                 encoder,
                 connector.fuel,
                 name.clone(),
@@ -762,6 +876,7 @@ where
         connector,
         as_of,
         source_timestamp_histories,
+        cdc_v2_encoder,
         write_frontier,
         metrics,
     )
@@ -778,13 +893,21 @@ where
 ///
 /// Updates that are not beyond the given [`SinkAsOf`] and/or the `gate_ts` in
 /// [`KafkaSinkConnector`] will be discarded without producing them.
+///
+/// If a `cdc_v2_encoder` is given, the operator follows the updates of each timestamp with a
+/// CDCv2 progress statement, and writes further progress statements as the input frontier
+/// advances.
+///
+/// Updates that carry a partition are written to that partition of the topic. All other updates
+/// are assigned a partition by the producer.
 pub fn produce_to_kafka<G>(
-    stream: Stream<G, ((Option<Vec<u8>>, Option<Vec<u8>>), Timestamp, Diff)>,
+    stream: Stream<G, (EncodedRecord, Timestamp, Diff)>,
     id: GlobalId,
     name: String,
     connector: KafkaSinkConnector,
     as_of: SinkAsOf,
     source_timestamp_histories: Vec<TimestampBindingRc>,
+    cdc_v2_encoder: Option<cdc_v2::Encoder>,
     write_frontier: Rc<RefCell<Antichain<Timestamp>>>,
     metrics: &KafkaBaseMetrics,
 ) -> Box<dyn Any>
@@ -814,6 +937,7 @@ where
         Arc::clone(&shutdown_flag),
         activator,
         latest_progress_ts,
+        cdc_v2_encoder,
         write_frontier,
         metrics,
     );
@@ -860,7 +984,7 @@ where
             input.for_each(|_, rows| {
                 is_active_worker = true;
                 rows.swap(&mut vector);
-                for ((key, value), time, diff) in vector.drain(..) {
+                for ((key, value, partition), time, diff) in vector.drain(..) {
                     let should_emit = if as_of.strict {
                         as_of.frontier.less_than(&time)
                     } else {
@@ -891,6 +1015,7 @@ where
                     rows.push(EncodedRow {
                         key,
                         value,
+                        partition,
                         count: diff,
                     });
                     s.metrics.rows_queued.inc();
@@ -964,6 +1089,10 @@ where
                         Some(r) => record.key(r),
                         None => record,
                     };
+                    let record = match encoded_row.partition {
+                        Some(partition) => record.partition(partition),
+                        None => record,
+                    };
 
                     // Only fatal errors are returned from send
                     bail_err!(s.send(record).await);
@@ -978,6 +1107,12 @@ where
                     }
                 }
 
+                if s.cdc_v2_encoder.is_some() {
+                    bail_err!(
+                        s.send_cdc_v2_progress(ts + 1, &[(*ts, total_sent as usize)])
+                            .await
+                    );
+                }
                 if s.consistency.is_some() {
                     bail_err!(
                         s.send_consistency_record(&ts.to_string(), "END", Some(total_sent))
@@ -1059,6 +1194,8 @@ where
 /// Updates that are not beyond the given [`SinkAsOf`] and/or the `gate_ts` will be discarded
 /// without encoding them.
 ///
+/// If a `partitioner` is given, each update is assigned the partition computed from its key.
+///
 /// Input updates do not have to be partitioned and/or sorted. This operator will not exchange
 /// data. Updates with lower timestamps will be processed before updates with higher timestamps
 /// if they arrive in order. However, this is not a guarantee, as this operator does not wait
@@ -1070,10 +1207,11 @@ fn encode_stream<G>(
     input_stream: &Stream<G, ((Option<Row>, Option<Row>), Timestamp, Diff)>,
     as_of: SinkAsOf,
     gate_ts: Option<Timestamp>,
+    partitioner: Option<SinkPartitioner>,
     encoder: impl Encode + 'static,
     fuel: usize,
     name_prefix: String,
-) -> Stream<G, ((Option<Vec<u8>>, Option<Vec<u8>>), Timestamp, Diff)>
+) -> Stream<G, (EncodedRecord, Timestamp, Diff)>
 where
     G: Scope<Timestamp = Timestamp>,
 {
@@ -1097,7 +1235,11 @@ where
     >,
                                  output: &mut OutputHandle<
         _,
-        ((Option<Vec<u8>>, Option<Vec<u8>>), Timestamp, Diff),
+        (
+            (Option<Vec<u8>>, Option<Vec<u8>>, Option<i32>),
+            Timestamp,
+            Diff,
+        ),
         _,
     >| {
         let mut fuel_remaining = fuel;
@@ -1143,9 +1285,13 @@ where
             records
                 .drain(..num_records_to_drain)
                 .for_each(|((key, value), time, diff)| {
+                    let partition = partitioner.as_ref().map(|partitioner| {
+                        let key = key.as_ref().expect("PARTITION BY requires a sink key");
+                        partitioner.partition_for_key(key)
+                    });
                     let key = key.map(|key| encoder.encode_key_unchecked(key));
                     let value = value.map(|value| encoder.encode_value_unchecked(value));
-                    session.give(((key, value), time, diff));
+                    session.give(((key, value, partition), time, diff));
                 });
 
             fuel_remaining -= num_records_to_drain;
@@ -1173,3 +1319,62 @@ where
 
     output_stream
 }
+
+/// Assigns the records of a sink with a `PARTITION BY` expression to
+/// partitions of its topic.
+struct SinkPartitioner {
+    partition_by: KafkaSinkPartitionBy,
+    /// Counts the records whose `PARTITION BY` expression failed to evaluate.
+    errors_counter: DeleteOnDropCounter<'static, AtomicI64, Vec<String>>,
+}
+
+impl SinkPartitioner {
+    fn new(
+        partition_by: KafkaSinkPartitionBy,
+        metrics: &KafkaBaseMetrics,
+        topic_name: &str,
+        sink_id: &str,
+        worker_id: &str,
+    ) -> SinkPartitioner {
+        let labels = vec![
+            topic_name.to_string(),
+            sink_id.to_string(),
+            worker_id.to_string(),
+        ];
+        SinkPartitioner {
+            partition_by,
+            errors_counter: metrics
+                .partition_errors_counter
+                .get_delete_on_drop_counter(labels),
+        }
+    }
+
+    /// Computes the partition of a record with the given `key`, as the value
+    /// of the `PARTITION BY` expression modulo the partition count.
+    ///
+    /// Records for which the expression evaluates to `NULL` are written to
+    /// partition 0. So are records for which the expression fails to
+    /// evaluate: the sink has no way to surface per-record errors to the
+    /// user, so the failure is logged and counted in
+    /// `mz_kafka_sink_partition_errors_total` instead of stalling the sink.
+    fn partition_for_key(&self, key: &Row) -> i32 {
+        let arena = RowArena::new();
+        let datums = key.unpack();
+        match self.partition_by.expr.eval(&datums, &arena) {
+            Ok(Datum::Int64(value)) => {
+                let partition = value.rem_euclid(i64::from(self.partition_by.partition_count));
+                i32::try_from(partition).expect("partition is less than the partition count")
+            }
+            Ok(Datum::Null) => 0,
+            Ok(datum) => unreachable!("PARTITION BY expression evaluated to {:?}", datum),
+            Err(e) => {
+                warn!(
+                    "error evaluating PARTITION BY expression, writing record to partition 0: {}",
+                    e
+                );
+                self.errors_counter.inc();
+                0
+            }
+        }
+    }
+}
@@ -20,6 +20,7 @@ pub struct KafkaBaseMetrics {
     pub(crate) messages_sent_counter: IntCounterVec,
     pub(crate) message_send_errors_counter: IntCounterVec,
     pub(crate) message_delivery_errors_counter: IntCounterVec,
+    pub(crate) partition_errors_counter: IntCounterVec,
     pub(crate) rows_queued: UIntGaugeVec,
     pub(crate) messages_in_flight: UIntGaugeVec,
 }
@@ -42,6 +43,11 @@ impl KafkaBaseMetrics {
                 help: "The number of messages that the Kafka producer could not deliver to the topic",
                 var_labels: ["topic", "sink_id", "worker_id"],
             )),
+            partition_errors_counter: registry.register(metric!(
+                name: "mz_kafka_sink_partition_errors_total",
+                help: "The number of records whose PARTITION BY expression failed to evaluate and that were written to partition 0",
+                var_labels: ["topic", "sink_id", "worker_id"],
+            )),
             rows_queued: registry.register(metric!(
                 name: "mz_kafka_sink_rows_queued",
                 help: "The current number of rows queued by the Kafka sink operator (note that one row can generate multiple Kafka messages)",
@@ -56,15 +62,61 @@ impl KafkaBaseMetrics {
     }
 }
 
+/// Metrics reported by each S3 sink.
+#[derive(Clone)]
+pub struct S3BaseMetrics {
+    pub(crate) batches_written_counter: IntCounterVec,
+    pub(crate) errors_counter: IntCounterVec,
+}
+
+impl S3BaseMetrics {
+    pub(crate) fn register_with(registry: &MetricsRegistry) -> Self {
+        Self {
+            batches_written_counter: registry.register(metric!(
+                name: "mz_s3_sink_batches_written_total",
+                help: "The number of batches the S3 sink wrote out, including their manifests",
+                var_labels: ["sink_id"],
+            )),
+            errors_counter: registry.register(metric!(
+                name: "mz_s3_sink_errors_total",
+                help: "The number of errors that stopped the S3 sink from writing further batches",
+                var_labels: ["sink_id"],
+            )),
+        }
+    }
+}
+
+/// Metrics reported by each Postgres sink.
+#[derive(Clone)]
+pub struct PostgresBaseMetrics {
+    pub(crate) errors_counter: IntCounterVec,
+}
+
+impl PostgresBaseMetrics {
+    pub(crate) fn register_with(registry: &MetricsRegistry) -> Self {
+        Self {
+            errors_counter: registry.register(metric!(
+                name: "mz_postgres_sink_errors_total",
+                help: "The number of errors that stopped the Postgres sink from writing further updates",
+                var_labels: ["sink_id"],
+            )),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct SinkBaseMetrics {
     pub(crate) kafka: KafkaBaseMetrics,
+    pub(crate) s3: S3BaseMetrics,
+    pub(crate) postgres: PostgresBaseMetrics,
 }
 
 impl SinkBaseMetrics {
     pub(crate) fn register_with(registry: &MetricsRegistry) -> Self {
         Self {
             kafka: KafkaBaseMetrics::register_with(registry),
+            s3: S3BaseMetrics::register_with(registry),
+            postgres: PostgresBaseMetrics::register_with(registry),
         }
     }
 }
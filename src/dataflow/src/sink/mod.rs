@@ -10,7 +10,11 @@
 mod avro_ocf;
 mod kafka;
 mod metrics;
+mod postgres;
+mod s3;
 mod tail;
 
 pub(crate) use metrics::KafkaBaseMetrics;
+pub(crate) use metrics::PostgresBaseMetrics;
+pub(crate) use metrics::S3BaseMetrics;
 pub use metrics::SinkBaseMetrics;
@@ -0,0 +1,471 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! A sink that maintains a table in a Postgres database.
+//!
+//! The sink upserts and deletes rows of the downstream table by the sink's
+//! key. All updates at a timestamp are applied in one transaction, which also
+//! records the timestamp in the progress table. After a restart, the sink
+//! skips all timestamps up to the recorded one, so that each timestamp is
+//! applied exactly once, provided the sink's inputs replay the same updates at
+//! the same timestamps.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::{Collection, Hashable};
+use futures::StreamExt;
+use itertools::Itertools;
+use prometheus::core::AtomicI64;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::Scope;
+use timely::progress::Antichain;
+use timely::scheduling::activate::SyncActivator;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::Client;
+use tracing::{error, warn};
+
+use dataflow_types::sinks::{PostgresSinkConnector, SinkAsOf, SinkDesc};
+use expr::GlobalId;
+use ore::cast::CastFrom;
+use ore::metrics::{CounterVecExt, DeleteOnDropCounter};
+use ore::retry::Retry;
+use repr::{Diff, Row, ScalarType, Timestamp};
+
+use crate::render::sinks::SinkRender;
+use crate::source::timestamp::TimestampBindingRc;
+
+use super::{PostgresBaseMetrics, SinkBaseMetrics};
+
+impl<G> SinkRender<G> for PostgresSinkConnector
+where
+    G: Scope<Timestamp = Timestamp>,
+{
+    fn uses_keys(&self) -> bool {
+        true
+    }
+
+    fn get_key_indices(&self) -> Option<&[usize]> {
+        Some(&self.key_indices)
+    }
+
+    fn get_relation_key_indices(&self) -> Option<&[usize]> {
+        None
+    }
+
+    fn render_continuous_sink(
+        &self,
+        _compute_state: &mut crate::render::ComputeState,
+        storage_state: &mut crate::render::StorageState,
+        sink: &SinkDesc,
+        sink_id: GlobalId,
+        sinked_collection: Collection<G, (Option<Row>, Option<Row>), Diff>,
+        metrics: &SinkBaseMetrics,
+    ) -> Option<Box<dyn Any>>
+    where
+        G: Scope<Timestamp = Timestamp>,
+    {
+        // Extract handles to the relevant source timestamp histories the sink
+        // needs to hear from before it can write data out to Postgres.
+        let mut source_ts_histories = Vec::new();
+
+        for id in &self.transitive_source_dependencies {
+            if let Some(history) = storage_state.ts_histories.get(id) {
+                // As soon as we have one sink that depends on a given source,
+                // that source needs to persist timestamp bindings.
+                history.enable_persistence();
+
+                let mut history_bindings = history.clone();
+                // We don't want these to block compaction
+                // ever.
+                history_bindings.set_compaction_frontier(Antichain::new().borrow());
+                source_ts_histories.push(history_bindings);
+            }
+        }
+
+        let scope = sinked_collection.scope();
+        let active_write_worker =
+            (usize::cast_from(sink_id.hashed()) % scope.peers()) == scope.index();
+
+        // Only the active write worker will ever write data, so all other
+        // workers report an empty write frontier.
+        let shared_frontier = Rc::new(RefCell::new(if active_write_worker {
+            Antichain::from_elem(0)
+        } else {
+            Antichain::new()
+        }));
+
+        postgres(
+            sinked_collection,
+            sink_id,
+            self.clone(),
+            sink.as_of.clone(),
+            source_ts_histories,
+            active_write_worker,
+            Rc::clone(&shared_frontier),
+            &metrics.postgres,
+        );
+
+        storage_state
+            .sink_write_frontiers
+            .insert(sink_id, shared_frontier);
+
+        // no sink token
+        None
+    }
+}
+
+/// The updates at one timestamp: the new value of each updated key, or `None`
+/// if the key was deleted.
+type Batch = (Timestamp, Vec<(Row, Option<Row>)>);
+
+fn postgres<G>(
+    collection: Collection<G, (Option<Row>, Option<Row>)>,
+    id: GlobalId,
+    connector: PostgresSinkConnector,
+    as_of: SinkAsOf,
+    source_timestamp_histories: Vec<TimestampBindingRc>,
+    active_write_worker: bool,
+    write_frontier: Rc<RefCell<Antichain<Timestamp>>>,
+    metrics: &PostgresBaseMetrics,
+) where
+    G: Scope<Timestamp = Timestamp>,
+{
+    // All timestamps before `committed_upper` have been written downstream,
+    // either by this sink or by a previous incarnation of it.
+    let committed_upper = Arc::new(AtomicU64::new(
+        connector.gate_ts.map(|ts| ts + 1).unwrap_or(0),
+    ));
+
+    let scope = collection.scope();
+    let mut builder = OperatorBuilder::new(format!("postgres-{}", id), scope.clone());
+    // The writer activates the operator whenever it advances
+    // `committed_upper`, so that the write frontier follows promptly even if
+    // no more input arrives.
+    let activator = scope.sync_activator_for(&builder.operator_info().address[..]);
+
+    let tx = if active_write_worker {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_batches(
+            id,
+            connector.clone(),
+            rx,
+            Arc::clone(&committed_upper),
+            activator,
+            metrics
+                .errors_counter
+                .get_delete_on_drop_counter(vec![id.to_string()]),
+        ));
+        Some(tx)
+    } else {
+        None
+    };
+
+    let mut pending: HashMap<Timestamp, Vec<(Row, Option<Row>)>> = HashMap::new();
+    // Timestamps that were handed to the writer but are not yet committed, in
+    // ascending order.
+    let mut in_flight = VecDeque::new();
+    let mut vector = vec![];
+
+    // We want exactly one worker to write to the downstream table.
+    let hashed_id = id.hashed();
+    let mut input = builder.new_input(&collection.inner, Exchange::new(move |_| hashed_id));
+    builder.build(move |_capabilities| {
+        move |frontiers| {
+            input.for_each(|_, rows| {
+                rows.swap(&mut vector);
+                for ((key, value), time, diff) in vector.drain(..) {
+                    let should_emit = if as_of.strict {
+                        as_of.frontier.less_than(&time)
+                    } else {
+                        as_of.frontier.less_equal(&time)
+                    };
+                    let previously_published = match connector.gate_ts {
+                        Some(gate_ts) => time <= gate_ts,
+                        None => false,
+                    };
+                    if !should_emit || previously_published {
+                        // Skip stale data for already published timestamps
+                        continue;
+                    }
+
+                    assert!(diff >= 0, "can't sink negative multiplicities");
+                    if diff == 0 {
+                        continue;
+                    }
+                    let key = key.expect("Postgres sinks must have keys");
+                    pending.entry(time).or_default().push((key, value));
+                }
+            });
+
+            let tx = match &tx {
+                Some(tx) => tx,
+                None => return,
+            };
+
+            // Figure out the durablity frontier for all sources we depend on
+            let durability_frontier = source_timestamp_histories
+                .iter()
+                .fold(Antichain::new(), |accum, history| {
+                    accum.meet(&history.durability_frontier())
+                });
+            let frontier = frontiers[0].frontier().to_owned();
+
+            // Hand all closed timestamps to the writer, in order.
+            let mut closed_ts: Vec<Timestamp> = pending
+                .keys()
+                .filter(|ts| !frontier.less_equal(*ts) && !durability_frontier.less_equal(*ts))
+                .copied()
+                .collect();
+            closed_ts.sort_unstable();
+            for ts in closed_ts {
+                let updates = pending.remove(&ts).expect("known to exist");
+                in_flight.push_back(ts);
+                // If the writer has stopped, it has already reported why, and
+                // the write frontier no longer advances.
+                let _ = tx.send((ts, updates));
+            }
+
+            // The write frontier is the latest timestamp up to which all
+            // updates have been committed downstream.
+            let upper = committed_upper.load(Ordering::SeqCst);
+            while matches!(in_flight.front(), Some(ts) if *ts < upper) {
+                in_flight.pop_front();
+            }
+            let min_unwritten = frontier
+                .iter()
+                .chain(pending.keys())
+                .chain(in_flight.front())
+                .min()
+                .copied();
+            let mut write_frontier = write_frontier.borrow_mut();
+            write_frontier.clear();
+            if let Some(min_unwritten) = min_unwritten {
+                write_frontier.insert(min_unwritten.saturating_sub(1));
+            }
+        }
+    });
+}
+
+/// Applies the batches received on `rx` to the downstream table, in order.
+///
+/// Skipping a batch would break the sink's exactly-once guarantee, so batches
+/// that fail with a transient error are retried on a fresh connection until
+/// they succeed. Any other error (e.g. a missing table, a constraint violation
+/// or bad credentials) won't go away by retrying, so it stops the writer: the
+/// failure is logged and counted, and `committed_upper`, which drives the
+/// sink's write frontier, stops advancing.
+async fn write_batches(
+    id: GlobalId,
+    connector: PostgresSinkConnector,
+    mut rx: UnboundedReceiver<Batch>,
+    committed_upper: Arc<AtomicU64>,
+    activator: SyncActivator,
+    errors_counter: DeleteOnDropCounter<'static, AtomicI64, Vec<String>>,
+) {
+    let statements = Statements::new(&connector);
+    let mut client = None;
+    while let Some((ts, updates)) = rx.recv().await {
+        let retry = Retry::default()
+            .clamp_backoff(Duration::from_secs(60))
+            .max_tries(usize::MAX)
+            .into_retry_stream();
+        tokio::pin!(retry);
+        while retry.next().await.is_some() {
+            let result = async {
+                if client.is_none() {
+                    client = Some(postgres_util::connect(&connector.conn).await?);
+                }
+                let client = client.as_mut().expect("known to exist");
+                write_batch(client, &statements, &connector, ts, &updates).await
+            }
+            .await;
+            match result {
+                Ok(()) => {
+                    committed_upper.store(ts + 1, Ordering::SeqCst);
+                    let _ = activator.activate();
+                    break;
+                }
+                Err(e) if is_transient(&e) => {
+                    warn!(
+                        "writing timestamp {} of Postgres sink {} failed, retrying: {:#}",
+                        ts, id, e
+                    );
+                    client = None;
+                }
+                Err(e) => {
+                    errors_counter.inc();
+                    error!(
+                        "Postgres sink {} has stopped writing after an error at timestamp {}: {:#}",
+                        id, ts, e
+                    );
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether retrying the write that failed with `e` may succeed.
+///
+/// Errors that don't come from Postgres at all, like TLS configuration
+/// errors, are never transient.
+fn is_transient(e: &anyhow::Error) -> bool {
+    let e = match e.downcast_ref::<tokio_postgres::Error>() {
+        Some(e) => e,
+        None => return false,
+    };
+    match e.code() {
+        // Errors without a SQLSTATE come from the connection itself, e.g.
+        // because the server is unreachable or closed the connection.
+        None => true,
+        Some(code) => {
+            // Connection exceptions, transaction rollbacks (serialization
+            // failures and deadlocks), insufficient resources, operator
+            // intervention (like a server shutdown) and system errors.
+            matches!(&code.code()[..2], "08" | "40" | "53" | "57" | "58")
+                || code == &SqlState::LOCK_NOT_AVAILABLE
+        }
+    }
+}
+
+/// The SQL statements that apply updates to the downstream table.
+struct Statements {
+    upsert: String,
+    delete: String,
+    record_progress: String,
+}
+
+impl Statements {
+    fn new(connector: &PostgresSinkConnector) -> Statements {
+        let columns = &connector.columns;
+        let key_indices = &connector.key_indices;
+
+        // Values are passed as text and cast to the type of their downstream
+        // column, which works for every type.
+        let upsert_sets = columns
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !key_indices.contains(i))
+            .map(|(_, (name, _))| format!("{0} = EXCLUDED.{0}", name))
+            .join(", ");
+        let upsert = format!(
+            "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) {}",
+            connector.table,
+            columns.iter().map(|(name, _)| name).join(", "),
+            columns
+                .iter()
+                .enumerate()
+                .map(|(i, (_, typ))| format!("${}::text::{}", i + 1, typ))
+                .join(", "),
+            key_indices.iter().map(|i| &columns[*i].0).join(", "),
+            if upsert_sets.is_empty() {
+                "DO NOTHING".into()
+            } else {
+                format!("DO UPDATE SET {}", upsert_sets)
+            },
+        );
+        let delete = format!(
+            "DELETE FROM {} WHERE {}",
+            connector.table,
+            key_indices
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    let (name, typ) = &columns[*column];
+                    format!("{} = ${}::text::{}", name, i + 1, typ)
+                })
+                .join(" AND "),
+        );
+        let record_progress = format!(
+            "INSERT INTO {} (sink_id, sink_table, ts) VALUES ($1, $2, $3)
+            ON CONFLICT (sink_id) DO UPDATE SET ts = EXCLUDED.ts",
+            connector.progress_table
+        );
+        Statements {
+            upsert,
+            delete,
+            record_progress,
+        }
+    }
+}
+
+/// Applies the updates at `ts` and records `ts` in the progress table, in
+/// one transaction.
+async fn write_batch(
+    client: &mut Client,
+    statements: &Statements,
+    connector: &PostgresSinkConnector,
+    ts: Timestamp,
+    updates: &[(Row, Option<Row>)],
+) -> Result<(), anyhow::Error> {
+    let value_types: Vec<_> = connector
+        .value_desc
+        .typ()
+        .column_types
+        .iter()
+        .map(|typ| &typ.scalar_type)
+        .collect();
+    let key_types: Vec<_> = connector
+        .key_indices
+        .iter()
+        .map(|i| value_types[*i])
+        .collect();
+
+    let txn = client.transaction().await?;
+    let upsert = txn
+        .prepare_typed(&statements.upsert, &vec![Type::TEXT; value_types.len()])
+        .await?;
+    let delete = txn
+        .prepare_typed(&statements.delete, &vec![Type::TEXT; key_types.len()])
+        .await?;
+    for (key, value) in updates {
+        let (statement, params) = match value {
+            Some(value) => (&upsert, encode_text(value, &value_types)),
+            None => (&delete, encode_text(key, &key_types)),
+        };
+        let params: Vec<_> = params.iter().map(|p| p as &(dyn ToSql + Sync)).collect();
+        txn.execute(statement, &params).await?;
+    }
+    txn.execute(
+        statements.record_progress.as_str(),
+        &[
+            &connector.progress_key,
+            &connector.table,
+            &i64::try_from(ts)?,
+        ],
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(())
+}
+
+/// Encodes the datums of `row` in the text format, or as `None` if they are
+/// null.
+fn encode_text(row: &Row, types: &[&ScalarType]) -> Vec<Option<String>> {
+    row.iter()
+        .zip(types)
+        .map(|(datum, typ)| {
+            pgrepr::Value::from_datum(datum, typ).map(|value| {
+                let mut buf = String::new();
+                value.encode_text(&mut buf);
+                buf
+            })
+        })
+        .collect()
+}
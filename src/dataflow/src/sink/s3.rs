@@ -0,0 +1,511 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! A sink that periodically writes batches of a collection to S3.
+//!
+//! The sink cuts the collection into batches that each cover a contiguous
+//! range of timestamps, `[lower, upper)`. A batch is cut once the input
+//! frontier has advanced at least `interval` past the previous batch's upper,
+//! so that every update in the batch is final. The first batch covers all
+//! times up to the first frontier and so contains the initial snapshot.
+//!
+//! For each batch, the sink first writes its data file to
+//! `{prefix}/data/{upper}.{extension}` and then a manifest describing the
+//! batch to `{prefix}/manifest/{upper}.json`. Manifests are written in order
+//! and only once the batch's data is in place, so readers that only consider
+//! data files listed in a manifest never observe partial output.
+//!
+//! Encoding and uploading happen in a separate task, so that they do not
+//! block the timely worker. On startup, that task looks up the last manifest
+//! that an earlier incarnation of the sink wrote and continues from its
+//! upper. The sink's write frontier tracks the uppers of the manifests that
+//! have been written, so a sink that fails to write a batch visibly stops
+//! making progress.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use aws_sdk_s3::{ByteStream, Client};
+use differential_dataflow::consolidation::consolidate_updates;
+use differential_dataflow::{Collection, Hashable};
+use itertools::repeat_n;
+use prometheus::core::AtomicI64;
+use serde_json::json;
+use timely::dataflow::channels::pact::Exchange;
+use timely::dataflow::operators::generic::builder_rc::OperatorBuilder;
+use timely::dataflow::Scope;
+use timely::progress::Antichain;
+use timely::scheduling::activate::SyncActivator;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task;
+use tracing::{debug, error};
+
+use dataflow_types::sinks::{S3SinkConnector, S3SinkFormat, S3SinkMode, SinkDesc};
+use expr::GlobalId;
+use interchange::avro::{encode_datums_as_avro, AvroSchemaGenerator};
+use interchange::parquet;
+use ore::cast::CastFrom;
+use ore::metrics::{CounterVecExt, DeleteOnDropCounter};
+use ore::retry::Retry;
+use repr::{Datum, Diff, RelationDesc, Row, ScalarType, Timestamp};
+
+use crate::render::sinks::SinkRender;
+
+use super::{S3BaseMetrics, SinkBaseMetrics};
+
+impl<G> SinkRender<G> for S3SinkConnector
+where
+    G: Scope<Timestamp = Timestamp>,
+{
+    fn uses_keys(&self) -> bool {
+        false
+    }
+
+    fn get_key_indices(&self) -> Option<&[usize]> {
+        None
+    }
+
+    fn get_relation_key_indices(&self) -> Option<&[usize]> {
+        None
+    }
+
+    fn render_continuous_sink(
+        &self,
+        _compute_state: &mut crate::render::ComputeState,
+        storage_state: &mut crate::render::StorageState,
+        _sink: &SinkDesc,
+        sink_id: GlobalId,
+        sinked_collection: Collection<G, (Option<Row>, Option<Row>), Diff>,
+        metrics: &SinkBaseMetrics,
+    ) -> Option<Box<dyn Any>>
+    where
+        G: Scope<Timestamp = Timestamp>,
+    {
+        let scope = sinked_collection.scope();
+        let active_write_worker =
+            (usize::cast_from(sink_id.hashed()) % scope.peers()) == scope.index();
+
+        // Only the active write worker will ever write batches, so all other
+        // workers report an empty write frontier.
+        let shared_frontier = Rc::new(RefCell::new(if active_write_worker {
+            Antichain::from_elem(0)
+        } else {
+            Antichain::new()
+        }));
+
+        s3(
+            sinked_collection,
+            sink_id,
+            self.clone(),
+            active_write_worker,
+            Rc::clone(&shared_frontier),
+            &metrics.s3,
+        );
+
+        storage_state
+            .sink_write_frontiers
+            .insert(sink_id, shared_frontier);
+
+        // no sink token
+        None
+    }
+}
+
+/// The upper of a batch and the consolidated updates at times before it that
+/// were not part of any earlier batch.
+type S3Batch = (Timestamp, Vec<(Row, Timestamp, Diff)>);
+
+/// Encodes rows into data files of the sink's format.
+enum BatchEncoder {
+    Parquet(parquet::Encoder),
+    AvroOcf {
+        schema: mz_avro::Schema,
+        columns: Vec<(repr::ColumnName, repr::ColumnType)>,
+    },
+}
+
+impl BatchEncoder {
+    fn new(format: S3SinkFormat, desc: RelationDesc) -> Result<Self, anyhow::Error> {
+        match format {
+            S3SinkFormat::Parquet => Ok(BatchEncoder::Parquet(parquet::Encoder::new(&desc)?)),
+            S3SinkFormat::AvroOcf => {
+                let schema_generator = AvroSchemaGenerator::new(None, None, None, desc, false);
+                Ok(BatchEncoder::AvroOcf {
+                    schema: schema_generator.value_writer_schema().clone(),
+                    columns: schema_generator.value_columns().to_vec(),
+                })
+            }
+        }
+    }
+
+    fn encode(&self, rows: &[Row]) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            BatchEncoder::Parquet(encoder) => encoder.encode_file(rows),
+            BatchEncoder::AvroOcf { schema, columns } => {
+                let mut writer = mz_avro::Writer::new(schema.clone(), vec![]);
+                for row in rows {
+                    writer.append(encode_datums_as_avro(row.iter(), columns))?;
+                }
+                writer.flush()?;
+                Ok(writer.into_inner())
+            }
+        }
+    }
+}
+
+fn s3<G>(
+    collection: Collection<G, (Option<Row>, Option<Row>)>,
+    id: GlobalId,
+    connector: S3SinkConnector,
+    active_write_worker: bool,
+    write_frontier: Rc<RefCell<Antichain<Timestamp>>>,
+    metrics: &S3BaseMetrics,
+) where
+    G: Scope<Timestamp = Timestamp>,
+{
+    let collection = collection.map(|(k, v)| {
+        assert!(k.is_none(), "S3 sinks must not have keys");
+        v.expect("S3 sinks must have values")
+    });
+
+    let scope = collection.scope();
+    let mut builder = OperatorBuilder::new(format!("s3-{}", id), scope.clone());
+    let activator = scope.sync_activator_for(&builder.operator_info().address[..]);
+
+    // All timestamps before `written_upper` are covered by manifests, either
+    // written by this sink or by a previous incarnation of it.
+    let written_upper = Arc::new(AtomicU64::new(0));
+
+    // We want exactly one worker to write the batches, as they must be
+    // written in order.
+    let tx = if active_write_worker {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(upload_batches(
+            id,
+            connector.clone(),
+            rx,
+            Arc::clone(&written_upper),
+            activator,
+            S3SinkMetrics::new(metrics, &id.to_string()),
+        ));
+        Some(tx)
+    } else {
+        None
+    };
+
+    let hashed_id = id.hashed();
+    let mut input = builder.new_input(&collection.inner, Exchange::new(move |_| hashed_id));
+
+    // The lower bound of the times of the next batch.
+    let mut lower = 0;
+    // Updates that have not yet been handed to the upload task.
+    let mut pending = vec![];
+    let mut vector = vec![];
+    builder.build(move |_capabilities| {
+        move |frontiers| {
+            input.for_each(|_, rows| {
+                rows.swap(&mut vector);
+                if tx.is_some() {
+                    pending.extend(vector.drain(..));
+                } else {
+                    vector.clear();
+                }
+            });
+
+            let tx = match &tx {
+                Some(tx) => tx,
+                None => return,
+            };
+
+            // Once the input frontier is at least `interval` past the
+            // previous batch's upper, all updates before it are final and
+            // can be cut into a new batch.
+            if let Some(upper) = frontiers[0].frontier().first().copied() {
+                if upper > lower && upper >= lower.saturating_add(connector.interval) {
+                    let (mut updates, rest): (Vec<_>, Vec<_>) =
+                        pending.drain(..).partition(|(_, time, _)| *time < upper);
+                    pending = rest;
+                    consolidate_updates(&mut updates);
+                    // If the upload task has stopped, it has already reported
+                    // why, and the write frontier no longer advances.
+                    let _ = tx.send((upper, updates));
+                    lower = upper;
+                }
+            }
+
+            let mut write_frontier = write_frontier.borrow_mut();
+            write_frontier.clear();
+            write_frontier.insert(written_upper.load(Ordering::SeqCst).saturating_sub(1));
+        }
+    });
+}
+
+/// Per-S3 sink metrics.
+struct S3SinkMetrics {
+    batches_written_counter: DeleteOnDropCounter<'static, AtomicI64, Vec<String>>,
+    errors_counter: DeleteOnDropCounter<'static, AtomicI64, Vec<String>>,
+}
+
+impl S3SinkMetrics {
+    fn new(base: &S3BaseMetrics, sink_id: &str) -> S3SinkMetrics {
+        let labels = vec![sink_id.to_string()];
+        S3SinkMetrics {
+            batches_written_counter: base
+                .batches_written_counter
+                .get_delete_on_drop_counter(labels.clone()),
+            errors_counter: base.errors_counter.get_delete_on_drop_counter(labels),
+        }
+    }
+}
+
+/// Encodes and writes the batches received on `rx` to S3, in order.
+///
+/// Giving up on a batch means giving up on all later batches, so that the
+/// manifests stay contiguous. The failure is logged and counted, and
+/// `written_upper`, which drives the sink's write frontier, stops advancing.
+async fn upload_batches(
+    id: GlobalId,
+    connector: S3SinkConnector,
+    mut rx: UnboundedReceiver<S3Batch>,
+    written_upper: Arc<AtomicU64>,
+    activator: SyncActivator,
+    metrics: S3SinkMetrics,
+) {
+    let res = async {
+        let mut writer = BatchWriter::new(id, connector).await?;
+        written_upper.store(writer.lower, Ordering::SeqCst);
+        let _ = activator.activate();
+        while let Some((upper, updates)) = rx.recv().await {
+            if writer.write_batch(upper, updates).await? {
+                metrics.batches_written_counter.inc();
+                written_upper.store(upper, Ordering::SeqCst);
+                let _ = activator.activate();
+            }
+        }
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+    if let Err(e) = res {
+        metrics.errors_counter.inc();
+        error!(
+            "S3 sink {} has stopped writing batches after an error: {:#}",
+            id, e
+        );
+    }
+}
+
+/// The state of the task that writes an S3 sink's batches.
+struct BatchWriter {
+    id: GlobalId,
+    connector: S3SinkConnector,
+    client: Client,
+    encoder: Arc<BatchEncoder>,
+    /// The lower bound of the times of the next batch.
+    lower: Timestamp,
+    /// In snapshot mode, the contents of the collection as of `lower`.
+    contents: HashMap<Row, Diff>,
+}
+
+impl BatchWriter {
+    async fn new(id: GlobalId, connector: S3SinkConnector) -> Result<Self, anyhow::Error> {
+        // In changes mode, each update is written with its time and diff.
+        let desc = match connector.mode {
+            S3SinkMode::Snapshot => connector.value_desc.clone(),
+            S3SinkMode::Changes => RelationDesc::empty()
+                .with_column("mz_timestamp", ScalarType::Int64.nullable(false))
+                .with_column("mz_diff", ScalarType::Int64.nullable(false))
+                .concat(connector.value_desc.clone()),
+        };
+        let encoder = BatchEncoder::new(connector.format, desc)
+            .with_context(|| format!("creating encoder for S3 sink {}", id))?;
+        let config = connector.aws.load().await;
+        let client = mz_aws_util::s3::client(&config);
+        let mut writer = BatchWriter {
+            id,
+            connector,
+            client,
+            encoder: Arc::new(encoder),
+            lower: 0,
+            contents: HashMap::new(),
+        };
+        writer.lower = writer.last_manifest_upper().await?;
+        if writer.lower > 0 {
+            debug!(
+                "S3 sink {} resuming after existing manifest with upper {}",
+                id, writer.lower
+            );
+        }
+        Ok(writer)
+    }
+
+    /// Returns the upper of the last manifest under the sink's prefix, or 0
+    /// if there is none.
+    async fn last_manifest_upper(&self) -> Result<Timestamp, anyhow::Error> {
+        let manifest_prefix = format!("{}/manifest/", self.connector.prefix);
+        let mut last_upper = 0;
+        let mut continuation_token = None;
+        loop {
+            let response = Retry::default()
+                .clamp_backoff(Duration::from_secs(10))
+                .max_duration(Duration::from_secs(300))
+                .retry_async(|_| {
+                    self.client
+                        .list_objects_v2()
+                        .bucket(&self.connector.bucket)
+                        .prefix(&manifest_prefix)
+                        .set_continuation_token(continuation_token.clone())
+                        .send()
+                })
+                .await
+                .map_err(|e| {
+                    anyhow!(
+                        "listing s3://{}/{} failed: {}",
+                        self.connector.bucket,
+                        manifest_prefix,
+                        e
+                    )
+                })?;
+            for key in response
+                .contents
+                .into_iter()
+                .flatten()
+                .filter_map(|o| o.key)
+            {
+                let upper = key
+                    .strip_prefix(&manifest_prefix)
+                    .and_then(|name| name.strip_suffix(".json"))
+                    .and_then(|upper| upper.parse::<Timestamp>().ok());
+                if let Some(upper) = upper {
+                    last_upper = std::cmp::max(last_upper, upper);
+                }
+            }
+            if response.next_continuation_token.is_none() {
+                break;
+            }
+            continuation_token = response.next_continuation_token;
+        }
+        Ok(last_upper)
+    }
+
+    /// Writes out the batch of `updates` at times in `[self.lower, upper)`.
+    ///
+    /// Returns `false` if the batch was not written because an earlier
+    /// incarnation of the sink already wrote a batch at or past `upper`.
+    async fn write_batch(
+        &mut self,
+        upper: Timestamp,
+        updates: Vec<(Row, Timestamp, Diff)>,
+    ) -> Result<bool, anyhow::Error> {
+        let rows = match self.connector.mode {
+            S3SinkMode::Changes => {
+                // Updates before `lower` were written out before a restart.
+                let mut updates: Vec<_> = updates
+                    .into_iter()
+                    .filter(|(_, time, _)| *time >= self.lower)
+                    .collect();
+                updates.sort_by_key(|(_, time, _)| *time);
+                updates
+                    .into_iter()
+                    .map(|(row, time, diff)| {
+                        let mut packer = Row::default();
+                        packer.push(Datum::Int64(i64::try_from(time)?));
+                        packer.push(Datum::Int64(diff as i64));
+                        packer.extend_by_row(&row);
+                        Ok::<_, anyhow::Error>(packer)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            S3SinkMode::Snapshot => {
+                for (row, _time, diff) in updates {
+                    let count = self.contents.entry(row).or_insert(0);
+                    *count += diff;
+                }
+                self.contents.retain(|_, count| *count != 0);
+                let mut rows = vec![];
+                for (row, count) in &self.contents {
+                    if *count < 0 {
+                        bail!("S3 sink {} contents have a negative multiplicity", self.id);
+                    }
+                    rows.extend(repeat_n(row.clone(), usize::cast_from(*count as u64)));
+                }
+                rows
+            }
+        };
+        if upper <= self.lower {
+            return Ok(false);
+        }
+
+        let prefix = &self.connector.prefix;
+        let extension = self.connector.format.extension();
+        let mut files = vec![];
+        if !rows.is_empty() {
+            let key = format!("{}/data/{:020}.{}", prefix, upper, extension);
+            let encoder = Arc::clone(&self.encoder);
+            let (rows, body) = task::spawn_blocking(move || {
+                let body = encoder.encode(&rows);
+                (rows, body)
+            })
+            .await?;
+            self.put(&key, body?).await?;
+            files.push(key);
+            debug!(
+                "S3 sink {} wrote batch [{}, {}) with {} rows",
+                self.id,
+                self.lower,
+                upper,
+                rows.len()
+            );
+        }
+        let manifest = json!({
+            "lower": self.lower,
+            "upper": upper,
+            "mode": self.connector.mode.as_str(),
+            "format": extension,
+            "files": files,
+            "rows": rows.len(),
+        });
+        self.put(
+            &format!("{}/manifest/{:020}.json", prefix, upper),
+            serde_json::to_vec(&manifest)?,
+        )
+        .await?;
+        self.lower = upper;
+        Ok(true)
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), anyhow::Error> {
+        Retry::default()
+            .clamp_backoff(Duration::from_secs(10))
+            .max_duration(Duration::from_secs(300))
+            .retry_async(|_| {
+                self.client
+                    .put_object()
+                    .bucket(&self.connector.bucket)
+                    .key(key)
+                    .body(ByteStream::from(body.clone()))
+                    .send()
+            })
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "writing s3://{}/{} failed: {}",
+                    self.connector.bucket,
+                    key,
+                    e
+                )
+            })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Persistent checkpoints of the read positions of sources.
+
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::task::{self, Poll};
+
+use anyhow::Context;
+use persist::pfuture::PFuture;
+use persist::storage::SeqNo;
+use timely::progress::Antichain;
+
+use crate::source::PersistentCheckpointsConfig;
+
+/// A source-specific record of a read position.
+pub(crate) trait Checkpoint: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(buf: &[u8]) -> Result<Self, anyhow::Error>;
+}
+
+/// Records [`Checkpoint`]s in a persistent collection.
+///
+/// Each update is written at a timestamp of its own, which is sealed right
+/// away. Updates can be written either blocking, via [`Checkpointer::update`],
+/// or in the background, via [`Checkpointer::start_update`] and
+/// [`Checkpointer::poll_updated`].
+pub(crate) struct Checkpointer<C> {
+    config: PersistentCheckpointsConfig,
+    /// The timestamp that the next update is written at.
+    next_ts: u64,
+    /// Writes, seals and compactions that have been started but have not
+    /// completed yet, in the order they were issued.
+    in_flight: VecDeque<(&'static str, PFuture<SeqNo>)>,
+    /// A checkpoint from before a restart, whose data is being read again.
+    /// It is retracted along with the next checkpoint.
+    pub(crate) superseded: Option<C>,
+}
+
+impl<C: Checkpoint> Checkpointer<C> {
+    pub(crate) fn new(config: PersistentCheckpointsConfig) -> Self {
+        Checkpointer {
+            next_ts: config.upper_seal_ts,
+            config,
+            in_flight: VecDeque::new(),
+            superseded: None,
+        }
+    }
+
+    /// Returns the offset of the latest timestamp binding whose data is known
+    /// to be persisted and sealed, or 0 if there is none.
+    ///
+    /// This has the same meaning as the offset restored on startup. It only
+    /// grows while the source is running.
+    pub(crate) fn persisted_offset(&self) -> i64 {
+        self.config.persisted_offset.load(Ordering::SeqCst)
+    }
+
+    /// Records that the data up to the given restored offset is persisted,
+    /// for the time before the running source has caught up to it.
+    pub(crate) fn set_restored_offset(&self, offset: i64) {
+        self.config
+            .persisted_offset
+            .fetch_max(offset, Ordering::SeqCst);
+    }
+
+    /// Returns the checkpoints that are currently recorded.
+    pub(crate) fn restore(&self) -> Result<Vec<C>, anyhow::Error> {
+        let snapshot = self
+            .config
+            .read_handle
+            .snapshot()
+            .context("reading source checkpoints")?;
+        let mut checkpoints: HashMap<Vec<u8>, isize> = HashMap::new();
+        for update in snapshot {
+            let ((checkpoint, ()), _ts, diff) = update.context("reading source checkpoints")?;
+            *checkpoints.entry(checkpoint).or_default() += diff;
+        }
+        checkpoints
+            .into_iter()
+            .filter(|(_, diff)| *diff > 0)
+            .map(|(checkpoint, _)| C::decode(&checkpoint))
+            .collect()
+    }
+
+    /// Durably records the checkpoint of data that is about to be handed out.
+    pub(crate) fn checkpoint(&mut self, checkpoint: C) -> Result<(), anyhow::Error> {
+        let mut updates = vec![(checkpoint, 1)];
+        if let Some(superseded) = self.superseded.take() {
+            updates.push((superseded, -1));
+        }
+        self.update(updates)
+    }
+
+    /// Durably writes the given updates.
+    pub(crate) fn update(&mut self, updates: Vec<(C, isize)>) -> Result<(), anyhow::Error> {
+        self.start_update(updates, false);
+        while let Some((what, future)) = self.in_flight.pop_front() {
+            future
+                .recv()
+                .with_context(|| format!("{} source checkpoints", what))?;
+        }
+        Ok(())
+    }
+
+    /// Starts writing the given updates without waiting for them to become
+    /// durable. Use [`Checkpointer::poll_updated`] to find out when they are.
+    ///
+    /// If `compact` is set, the updates retracted so far are allowed to be
+    /// compacted away once the updates are written.
+    pub(crate) fn start_update(&mut self, updates: Vec<(C, isize)>, compact: bool) {
+        let ts = self.next_ts;
+        let updates: Vec<_> = updates
+            .iter()
+            .map(|(checkpoint, diff)| ((checkpoint.encode(), ()), ts, *diff))
+            .collect();
+        let write_handle = &self.config.write_handle;
+        // The persist runtime applies commands in the order they are issued,
+        // so the seal never overtakes the write it covers.
+        self.in_flight
+            .push_back(("writing", write_handle.write(&updates)));
+        self.in_flight
+            .push_back(("sealing", write_handle.seal(ts + 1)));
+        self.next_ts = ts + 1;
+        if compact {
+            self.in_flight.push_back((
+                "compacting",
+                write_handle.allow_compaction(Antichain::from_elem(self.next_ts)),
+            ));
+        }
+    }
+
+    /// Polls the updates started with [`Checkpointer::start_update`],
+    /// returning `Ready` once all of them are durable.
+    pub(crate) fn poll_updated(
+        &mut self,
+        cx: &mut task::Context,
+    ) -> Poll<Result<(), anyhow::Error>> {
+        while let Some((what, future)) = self.in_flight.front_mut() {
+            match Pin::new(future).poll(cx) {
+                Poll::Ready(result) => {
+                    let what = *what;
+                    self.in_flight.pop_front();
+                    if let Err(e) = result {
+                        return Poll::Ready(
+                            Err(e).with_context(|| format!("{} source checkpoints", what)),
+                        );
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Allows the retracted checkpoints to be compacted away.
+    pub(crate) fn compact(&self) -> Result<(), anyhow::Error> {
+        self.config
+            .write_handle
+            .allow_compaction(Antichain::from_elem(self.next_ts))
+            .recv()
+            .context("compacting source checkpoints")?;
+        Ok(())
+    }
+}
@@ -7,23 +7,36 @@
 // the Business Source License, use of this software will be governed
 // by the Apache License, Version 2.0.
 
-use std::io::{self, BufRead, Read};
-use std::path::PathBuf;
+use std::borrow::Borrow;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::{File, Metadata};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
 use std::thread;
+use std::time::SystemTime;
 
-use anyhow::{Context, Error};
+use anyhow::{anyhow, Context, Error};
+use csv_core::ReadRecordResult;
 use flate2::read::MultiGzDecoder;
+use globset::GlobMatcher;
 #[cfg(target_os = "linux")]
 use inotify::{EventMask, Inotify, WatchMask};
+use prost::Message;
 use repr::MessagePayload;
 use timely::scheduling::SyncActivator;
 use tracing::error;
 
 use dataflow_types::sources::{
-    encoding::AvroOcfEncoding, encoding::DataEncoding, encoding::SourceDataEncoding, Compression,
-    ExternalSourceConnector, MzOffset,
+    encoding::AvroOcfEncoding, encoding::ColumnSpec, encoding::CsvEncoding, encoding::DataEncoding,
+    encoding::SourceDataEncoding, Compression, ExternalSourceConnector, FileSourceConnector,
+    MzOffset,
 };
 use expr::{PartitionId, SourceInstanceId};
 use mz_avro::Block;
@@ -31,19 +44,42 @@ use mz_avro::BlockIter;
 use mz_avro::{AvroRead, Schema, Skip};
 
 use crate::logging::materialized::Logger;
-use crate::source::{NextMessage, SourceMessage, SourceReader};
+use crate::source::checkpoint::{Checkpoint, Checkpointer};
+use crate::source::gen::source::ProtoFileCheckpoint;
+use crate::source::{NextMessage, PersistentCheckpointsConfig, SourceMessage, SourceReader};
 
 use super::metrics::SourceBaseMetrics;
 
+/// The number of bytes to read from a file at a time.
+const READ_BUFFER_SIZE: usize = 64 << 10;
+
 /// Contains all information necessary to ingest data from file sources
 pub struct FileSourceReader {
     /// Unique source ID
     id: SourceInstanceId,
-    /// Receiver channel that ingests records
-    receiver_stream: Receiver<Result<MessagePayload, Error>>,
+    /// Receiver channel that ingests records, once reading has started
+    receiver_stream: Option<Receiver<Result<FileChunk, Error>>>,
+    /// The reading of the source's files, until it is started
+    task: Option<FileReadTask>,
     /// Current File Offset. This corresponds to the offset of last processed message
     /// (initially 0 if no records have been processed)
     current_file_offset: FileOffset,
+    /// Whether messages carry the name of the file they were read from
+    include_filename: bool,
+    /// The outcome of restoring our position from checkpoints, or `None` if
+    /// `add_partition` has not been called yet. We cannot hand out messages
+    /// before that.
+    restored: Option<Result<(), anyhow::Error>>,
+    /// Durable record of how far each file has been read, if the source is
+    /// persistent.
+    checkpointer: Option<Checkpointer<FileCheckpoint>>,
+    /// The number of records in the chunks handed out so far, including the
+    /// ones handed out before a restart.
+    records_read: i64,
+    /// After a restart, the number of records that precede the first chunk,
+    /// which is passed along with it so that decoding numbers records from
+    /// there.
+    records_before: Option<i64>,
 }
 
 #[derive(Copy, Clone)]
@@ -61,6 +97,138 @@ impl From<FileOffset> for MzOffset {
     }
 }
 
+/// A chunk of a file, as read by a file reading thread.
+struct FileChunk {
+    /// The file that the chunk was read from
+    path: PathBuf,
+    id: FileId,
+    payload: MessagePayload,
+    /// The position to resume reading the file at once the chunk has been
+    /// processed, if decoding it does not depend on any data that follows it.
+    /// Positions count decompressed bytes.
+    resume_position: Option<u64>,
+    /// The number of records in the chunk, if the framing of the file allows
+    /// counting them, and 0 otherwise.
+    records: i64,
+}
+
+/// Identifies a file independently of its name, so that a file that gets
+/// renamed (e.g. by log rotation) is not read again under its new name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct FileId {
+    dev: u64,
+    ino: u64,
+}
+
+impl FileId {
+    fn of(metadata: &Metadata) -> FileId {
+        FileId {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        }
+    }
+}
+
+/// A file to read, as found when listing the files of a source.
+struct ListedFile {
+    path: PathBuf,
+    id: FileId,
+    modified: SystemTime,
+}
+
+/// How the records of a file are delimited, which determines whether reading
+/// it can resume in the middle.
+#[derive(Clone, Copy, Debug)]
+enum Framing {
+    /// Every line is a record.
+    Lines,
+    /// CSV records, which can span lines when fields are quoted. If files
+    /// start with a `header` row, it has to be read again when resuming in the
+    /// middle of a file.
+    Csv { delimiter: u8, header: bool },
+    /// Files can only be decoded in full.
+    Whole,
+}
+
+/// How far a file has been read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadPosition {
+    /// The file has been read up to the given position.
+    At(u64),
+    /// The file has been read in full.
+    Complete,
+}
+
+impl FileSourceReader {
+    /// Restores how far each file has been read from the recorded
+    /// checkpoints, and starts reading from there.
+    ///
+    /// `restored_offset` is the offset of the first message that did not make
+    /// it into the persisted data. Checkpoints of chunks past it are
+    /// retracted, as are checkpoints that newer ones of the same file
+    /// supersede and checkpoints of files that no longer exist. Files are
+    /// identified by device and inode, so a file that was renamed since is
+    /// resumed under its new name.
+    fn restore(&mut self, restored_offset: Option<MzOffset>) -> Result<(), anyhow::Error> {
+        let mut positions = HashMap::new();
+        if let Some(checkpointer) = &mut self.checkpointer {
+            let last_offset = restored_offset.map(|o| o.offset - 1).unwrap_or(0);
+
+            let task = self.task.as_ref().expect("file sources are restored once");
+            let fc = &task.connector;
+            let matcher = fc.pattern.as_ref().map(|pattern| pattern.compile_matcher());
+            let existing: HashSet<FileId> = list_files(&fc.path, matcher.as_ref())?
+                .into_iter()
+                .map(|file| file.id)
+                .collect();
+
+            let mut latest: HashMap<FileId, FileCheckpoint> = HashMap::new();
+            let mut obsolete = vec![];
+            for checkpoint in checkpointer.restore()? {
+                if checkpoint.mz_offset > last_offset || !existing.contains(&checkpoint.id) {
+                    obsolete.push(checkpoint);
+                    continue;
+                }
+                match latest.entry(checkpoint.id) {
+                    Entry::Occupied(mut entry) => {
+                        if entry.get().mz_offset < checkpoint.mz_offset {
+                            obsolete.push(entry.insert(checkpoint));
+                        } else {
+                            obsolete.push(checkpoint);
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(checkpoint);
+                    }
+                }
+            }
+
+            // Decoding continues numbering records after the ones of the last
+            // chunk that we resume after.
+            if let Some(checkpoint) = latest.values().max_by_key(|c| c.mz_offset) {
+                self.records_read = checkpoint.records;
+                self.records_before = Some(checkpoint.records);
+            }
+            for (id, checkpoint) in latest {
+                let position = if checkpoint.complete {
+                    ReadPosition::Complete
+                } else {
+                    ReadPosition::At(checkpoint.position)
+                };
+                positions.insert(id, position);
+            }
+            self.current_file_offset.offset = last_offset;
+
+            checkpointer.update(obsolete.into_iter().map(|c| (c, -1)).collect())?;
+            checkpointer.compact()?;
+        }
+
+        let task = self.task.take().expect("file sources are restored once");
+        self.receiver_stream = Some(task.spawn(positions));
+        Ok(())
+    }
+}
+
 impl SourceReader for FileSourceReader {
     type Key = ();
     type Value = MessagePayload;
@@ -74,43 +242,46 @@ impl SourceReader for FileSourceReader {
         encoding: SourceDataEncoding,
         _: Option<Logger>,
         _: SourceBaseMetrics,
+        checkpoints: Option<PersistentCheckpointsConfig>,
     ) -> Result<(FileSourceReader, Option<PartitionId>), anyhow::Error> {
-        let receiver = match connector {
+        let mut reader = FileSourceReader {
+            id: source_id,
+            receiver_stream: None,
+            task: None,
+            current_file_offset: FileOffset { offset: 0 },
+            include_filename: false,
+            restored: None,
+            checkpointer: None,
+            records_read: 0,
+            records_before: None,
+        };
+        match connector {
             ExternalSourceConnector::File(fc) => {
                 tracing::debug!("creating FileSourceReader worker_id={}", worker_id);
-                let ctor = |fi| {
-                    let mut br = std::io::BufReader::new(fi);
-                    Ok(std::iter::from_fn(move || {
-                        let chunk = match br.fill_buf() {
-                            Err(e) => return Some(Err(e)),
-                            Ok(b) => b.to_vec(), // TODO - avoid this copy?
-                        };
-                        br.consume(chunk.len());
-                        if chunk.len() > 0 {
-                            Some(Ok(MessagePayload::Data(chunk)))
-                        } else {
-                            None
-                        }
-                    })
-                    .chain(std::iter::once(Ok(MessagePayload::EOF))))
-                };
-                let (tx, rx) = std::sync::mpsc::sync_channel(10000);
-                let tail = if fc.tail {
-                    FileReadStyle::TailFollowFd
-                } else {
-                    FileReadStyle::ReadOnce
+                let framing = match &encoding {
+                    SourceDataEncoding::Single(DataEncoding::Csv(CsvEncoding {
+                        columns,
+                        delimiter,
+                    })) => Framing::Csv {
+                        delimiter: *delimiter,
+                        header: matches!(columns, ColumnSpec::Header { .. }),
+                    },
+                    SourceDataEncoding::Single(DataEncoding::Parquet(_)) => Framing::Whole,
+                    _ => Framing::Lines,
                 };
-                std::thread::spawn(move || {
-                    read_file_task(
-                        fc.path,
-                        tx,
-                        Some(consumer_activator),
-                        tail,
-                        fc.compression,
-                        ctor,
-                    );
+                reader.include_filename = fc.include_filename.is_some();
+                reader.checkpointer = checkpoints.map(Checkpointer::new);
+                reader.task = Some(FileReadTask {
+                    connector: fc,
+                    activator: consumer_activator,
+                    framing,
                 });
-                rx
+                if reader.checkpointer.is_some() {
+                    // The single partition gets added once we know where to
+                    // resume from.
+                    return Ok((reader, None));
+                }
+                reader.restored = Some(reader.restore(None));
             }
             ExternalSourceConnector::AvroOcf(fc) => {
                 tracing::debug!("creating Avro FileSourceReader worker_id={}", worker_id);
@@ -126,12 +297,25 @@ impl SourceReader for FileSourceReader {
                     _ => unreachable!("Wrong encoding for OCF file"),
                 };
                 let reader_schema = Schema::from_str(reader_schema)?;
+                let path = fc.path.clone();
+                // Avro OCF sources are not checkpointed, so they don't need to
+                // identify their file.
+                let id = FileId { dev: 0, ino: 0 };
                 let ctor = move |file| {
-                    BlockIter::with_schema(&reader_schema, file).map(|bi| {
+                    BlockIter::with_schema(&reader_schema, file).map(move |bi| {
                         bi.map(|result| {
                             result.map(|Block { bytes, len: _ }| MessagePayload::Data(bytes))
                         })
                         .chain(std::iter::once(Ok(MessagePayload::EOF)))
+                        .map(move |result| {
+                            result.map(|payload| FileChunk {
+                                path: path.clone(),
+                                id,
+                                payload,
+                                resume_position: None,
+                                records: 0,
+                            })
+                        })
                     })
                 };
 
@@ -151,31 +335,74 @@ impl SourceReader for FileSourceReader {
                         ctor,
                     );
                 });
-                rx
+                reader.receiver_stream = Some(rx);
+                reader.restored = Some(Ok(()));
             }
             _ => unreachable!(),
         };
 
-        Ok((
-            FileSourceReader {
-                id: source_id,
-                receiver_stream: receiver,
-                current_file_offset: FileOffset { offset: 0 },
-            },
-            Some(PartitionId::None),
-        ))
+        Ok((reader, Some(PartitionId::None)))
+    }
+
+    fn add_partition(&mut self, pid: PartitionId, restored_offset: Option<MzOffset>) {
+        assert_eq!(
+            pid,
+            PartitionId::None,
+            "file sources have a single partition"
+        );
+        self.restored = Some(self.restore(restored_offset));
     }
 
     fn get_next_message(&mut self) -> Result<NextMessage<Self::Key, Self::Value>, anyhow::Error> {
-        match self.receiver_stream.try_recv() {
-            Ok(Ok(record)) => {
+        match &self.restored {
+            None => return Ok(NextMessage::Pending),
+            Some(Err(e)) => return Err(anyhow!("restoring file source positions: {:#}", e)),
+            Some(Ok(())) => (),
+        }
+
+        let receiver = self
+            .receiver_stream
+            .as_ref()
+            .expect("reading starts once the source is restored");
+        match receiver.try_recv() {
+            Ok(Ok(FileChunk {
+                path,
+                id,
+                payload,
+                resume_position,
+                records,
+            })) => {
                 self.current_file_offset.offset += 1;
+                self.records_read += records;
+                // Record how far the file has been read before handing out the
+                // chunk, so that we know where to resume once it is persisted.
+                if let (Some(checkpointer), Some(position)) =
+                    (&mut self.checkpointer, resume_position)
+                {
+                    checkpointer.checkpoint(FileCheckpoint {
+                        path: path.clone(),
+                        id,
+                        mz_offset: self.current_file_offset.offset,
+                        position,
+                        complete: matches!(payload, MessagePayload::EOF),
+                        records: self.records_read,
+                    })?;
+                }
+                let filename = if self.include_filename {
+                    Some(path.to_string_lossy().into_owned())
+                } else {
+                    None
+                };
                 let message = SourceMessage {
                     partition: PartitionId::None,
                     offset: self.current_file_offset.into(),
                     upstream_time_millis: None,
                     key: (),
-                    value: record,
+                    value: payload,
+                    topic: None,
+                    headers: None,
+                    filename,
+                    records_before: self.records_before.take(),
                 };
                 Ok(NextMessage::Ready(message))
             }
@@ -189,16 +416,411 @@ impl SourceReader for FileSourceReader {
     }
 }
 
+/// The reading of the files of a file source, before it has started.
+struct FileReadTask {
+    connector: FileSourceConnector,
+    activator: SyncActivator,
+    framing: Framing,
+}
+
+impl FileReadTask {
+    /// Starts reading on a thread of its own, resuming each file at the given
+    /// position.
+    fn spawn(self, positions: HashMap<FileId, ReadPosition>) -> Receiver<Result<FileChunk, Error>> {
+        let (tx, rx) = mpsc::sync_channel(10000);
+        thread::spawn(move || {
+            if let Err(err) = self.read_files(positions, &tx) {
+                // If we fail to send an error, it's likely due to a race condition
+                // with the source being closed.
+                let _ = tx.send(Err(err));
+            }
+        });
+        rx
+    }
+
+    /// Reads the files that have not been read in full yet, least recently
+    /// modified first.
+    ///
+    /// Files are told apart by device and inode rather than by name, so a file
+    /// that log rotation renames is not read again under its new name. When
+    /// tailing a directory, a file counts as complete once it has been renamed
+    /// or deleted, or once another file has been modified after it, and we
+    /// wait for new files once all of them are complete.
+    fn read_files(
+        &self,
+        mut positions: HashMap<FileId, ReadPosition>,
+        tx: &SyncSender<Result<FileChunk, Error>>,
+    ) -> Result<(), Error> {
+        let fc = &self.connector;
+        let matcher = fc.pattern.as_ref().map(|pattern| pattern.compile_matcher());
+        // Watch the directory before listing it, so that we notice files that
+        // get created in the meantime.
+        let notices = match &matcher {
+            Some(_) if fc.tail => Some(watch(&fc.path)?),
+            _ => None,
+        };
+        loop {
+            let files = list_files(&fc.path, matcher.as_ref())?;
+            // Forget about files that are gone, in case a new file reuses the
+            // inode of one of them.
+            positions.retain(|id, _| files.iter().any(|file| file.id == *id));
+            let next = files
+                .into_iter()
+                .find(|file| positions.get(&file.id) != Some(&ReadPosition::Complete));
+            let listed = match (next, &notices) {
+                (Some(listed), _) => listed,
+                (None, Some(notices)) => {
+                    if notices.recv().is_err() {
+                        error!("notify hung up while watching directory");
+                        return Ok(());
+                    }
+                    continue;
+                }
+                (None, None) => return Ok(()),
+            };
+            let file = File::open(&listed.path).with_context(|| {
+                format!(
+                    "file source: unable to open file at path {}",
+                    listed.path.to_string_lossy(),
+                )
+            })?;
+            if FileId::of(&file.metadata()?) != listed.id {
+                // The file was renamed after we listed it.
+                continue;
+            }
+            let position = match positions.get(&listed.id) {
+                Some(ReadPosition::At(position)) => *position,
+                _ => 0,
+            };
+            if !self.read_file(
+                &listed,
+                file,
+                position,
+                matcher.as_ref(),
+                notices.as_ref(),
+                tx,
+            )? {
+                // The receiver went away, probably due to `DROP SOURCE`
+                return Ok(());
+            }
+            positions.insert(listed.id, ReadPosition::Complete);
+        }
+    }
+
+    /// Reads `file` from `position` on, in chunks that end on a record
+    /// boundary if the framing allows telling records apart. Returns whether
+    /// the receiver is still interested in more chunks.
+    ///
+    /// When tailing a directory, `notices` tell of changes to it.
+    fn read_file(
+        &self,
+        listed: &ListedFile,
+        mut file: File,
+        position: u64,
+        matcher: Option<&GlobMatcher>,
+        notices: Option<&Notices>,
+        tx: &SyncSender<Result<FileChunk, Error>>,
+    ) -> Result<bool, Error> {
+        let path = &listed.path;
+        tracing::trace!("reading file {} from position {}", path.display(), position);
+        let fc = &self.connector;
+
+        // Decoding a CSV file from the middle requires its header row.
+        let mut pending = vec![];
+        if let (Framing::Csv { header: true, .. }, true) = (self.framing, position > 0) {
+            BufReader::new(decompress(file.try_clone()?, &fc.compression))
+                .read_until(b'\n', &mut pending)?;
+        }
+
+        let skip = match fc.compression {
+            Compression::None => {
+                file.seek(SeekFrom::Start(position))?;
+                0
+            }
+            Compression::Gzip => {
+                file.seek(SeekFrom::Start(0))?;
+                position
+            }
+        };
+        let mut reader = match (fc.tail, notices, matcher) {
+            (true, Some(notices), Some(matcher)) => {
+                let (dir, matcher, path, id) =
+                    (fc.path.clone(), matcher.clone(), path.clone(), listed.id);
+                let this = file.try_clone()?;
+                let is_complete = move || is_rotated(&dir, &matcher, &path, id, &this);
+                decompress(
+                    TailedFile {
+                        notices,
+                        inner: file,
+                        is_complete,
+                    },
+                    &fc.compression,
+                )
+            }
+            (true, _, _) => decompress(
+                TailedFile {
+                    notices: watch(path)?,
+                    inner: file,
+                    is_complete: || false,
+                },
+                &fc.compression,
+            ),
+            (false, _, _) => decompress(file, &fc.compression),
+        };
+        io::copy(&mut (&mut reader).take(skip), &mut io::sink())?;
+
+        // The header row of a CSV file is not a record. It is part of the
+        // first chunk, even when resuming in the middle of the file.
+        let mut header_pending = matches!(self.framing, Framing::Csv { header: true, .. });
+        let mut send = |payload: MessagePayload, resume_position: Option<u64>, records: usize| {
+            let mut records = i64::try_from(records).expect("chunks are small");
+            if header_pending && records > 0 {
+                records -= 1;
+                header_pending = false;
+            }
+            let chunk = FileChunk {
+                path: path.clone(),
+                id: listed.id,
+                payload,
+                resume_position,
+                records,
+            };
+            if tx.send(Ok(chunk)).is_err() {
+                return false;
+            }
+            self.activator.activate().expect("activation failed");
+            true
+        };
+
+        let mut splitter = RecordSplitter::new(self.framing);
+        let mut read_position = position;
+        let mut buf = vec![0; READ_BUFFER_SIZE];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(Error::new(e).context(format!(
+                        "file source: failed to read file at path {}",
+                        path.to_string_lossy()
+                    )))
+                }
+            };
+            read_position += u64::try_from(n).expect("reads are small");
+            pending.extend_from_slice(&buf[..n]);
+
+            let (len, records, resumable) = match splitter.split(&pending) {
+                Some((0, _)) => continue,
+                Some((len, records)) => (len, records, true),
+                None => (pending.len(), 0, false),
+            };
+            let rest = pending.split_off(len);
+            let chunk = mem::replace(&mut pending, rest);
+            let unsent = u64::try_from(pending.len()).expect("records are small");
+            let resume_position = if resumable {
+                Some(read_position - unsent)
+            } else {
+                None
+            };
+            if !send(MessagePayload::Data(chunk), resume_position, records) {
+                return Ok(false);
+            }
+        }
+
+        // A last record without a trailing newline only gets decoded at EOF.
+        if !pending.is_empty() {
+            let records = splitter.finish(&pending);
+            if !send(MessagePayload::Data(pending), None, records) {
+                return Ok(false);
+            }
+        }
+        Ok(send(MessagePayload::EOF, Some(read_position), 0))
+    }
+}
+
+/// Finds the boundaries of the records in the data read from a file.
+enum RecordSplitter {
+    Lines,
+    Csv(CsvSplitter),
+    Whole,
+}
+
+impl RecordSplitter {
+    fn new(framing: Framing) -> RecordSplitter {
+        match framing {
+            Framing::Lines => RecordSplitter::Lines,
+            Framing::Csv { delimiter, .. } => RecordSplitter::Csv(CsvSplitter {
+                reader: csv_core::ReaderBuilder::new().delimiter(delimiter).build(),
+                output: vec![0; 1024],
+                ends: vec![0; 64],
+                parsed: 0,
+            }),
+            Framing::Whole => RecordSplitter::Whole,
+        }
+    }
+
+    /// Returns the length of the longest prefix of `pending` that holds whole
+    /// records and the number of records in it, or `None` if records can't be
+    /// told apart.
+    ///
+    /// Between calls, `pending` only grows at its end, except that the caller
+    /// takes off the returned prefix.
+    fn split(&mut self, pending: &[u8]) -> Option<(usize, usize)> {
+        match self {
+            RecordSplitter::Lines => {
+                let len = pending
+                    .iter()
+                    .rposition(|b| *b == b'\n')
+                    .map_or(0, |i| i + 1);
+                let records = pending[..len].iter().filter(|b| **b == b'\n').count();
+                Some((len, records))
+            }
+            RecordSplitter::Csv(csv) => {
+                let (records, end) = csv.parse(&pending[csv.parsed..]);
+                let len = if records > 0 { csv.parsed + end } else { 0 };
+                csv.parsed = pending.len() - len;
+                Some((len, records))
+            }
+            RecordSplitter::Whole => None,
+        }
+    }
+
+    /// Returns the number of records in what is left of `pending` once the
+    /// file has been read in full.
+    fn finish(&mut self, pending: &[u8]) -> usize {
+        match self {
+            RecordSplitter::Lines => usize::from(!pending.is_empty()),
+            RecordSplitter::Csv(csv) => {
+                let (records, _) = csv.parse(&pending[csv.parsed..]);
+                // Parsing no data tells the parser that the input has ended,
+                // which completes a last record without a terminator.
+                records + csv.parse(&[]).0
+            }
+            RecordSplitter::Whole => 0,
+        }
+    }
+}
+
+/// Finds the boundaries of CSV records, which unlike lines can contain
+/// newlines in quoted fields.
+struct CsvSplitter {
+    reader: csv_core::Reader,
+    /// Scratch space for the parsed fields, which we have no use for.
+    output: Vec<u8>,
+    ends: Vec<usize>,
+    /// How much of the pending data has been parsed.
+    parsed: usize,
+}
+
+impl CsvSplitter {
+    /// Parses `data`, returning the number of records that end in it and the
+    /// position just past the last of them.
+    fn parse(&mut self, mut data: &[u8]) -> (usize, usize) {
+        let len = data.len();
+        let (mut records, mut end) = (0, 0);
+        loop {
+            let (result, n_input, _, _) =
+                self.reader
+                    .read_record(data, &mut self.output, &mut self.ends);
+            data = &data[n_input..];
+            match result {
+                ReadRecordResult::Record => {
+                    records += 1;
+                    end = len - data.len();
+                }
+                ReadRecordResult::InputEmpty | ReadRecordResult::End => return (records, end),
+                // Only the boundaries of records matter, so the parsed fields
+                // can be overwritten.
+                ReadRecordResult::OutputFull | ReadRecordResult::OutputEndsFull => (),
+            }
+        }
+    }
+}
+
+/// Returns whether a file that is tailed in the directory at `dir` is
+/// complete, because it no longer goes by `path` or because another file that
+/// matches `matcher` has been modified after it.
+fn is_rotated(dir: &Path, matcher: &GlobMatcher, path: &Path, id: FileId, file: &File) -> bool {
+    let renamed = std::fs::metadata(path).map_or(true, |metadata| FileId::of(&metadata) != id);
+    let modified = match file.metadata().and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return false,
+    };
+    renamed
+        || match list_files(dir, Some(matcher)) {
+            Ok(files) => files
+                .iter()
+                .any(|other| other.id != id && other.modified > modified),
+            Err(_) => false,
+        }
+}
+
+/// Lists the files to read, in the order to read them in: `path` itself if
+/// there is no `matcher`, and otherwise the files in the directory at `path`
+/// whose names match, least recently modified first.
+fn list_files(path: &Path, matcher: Option<&GlobMatcher>) -> Result<Vec<ListedFile>, Error> {
+    let listed = |path: PathBuf, metadata: Metadata| -> Result<ListedFile, Error> {
+        Ok(ListedFile {
+            id: FileId::of(&metadata),
+            modified: metadata.modified()?,
+            path,
+        })
+    };
+    let matcher = match matcher {
+        Some(matcher) => matcher,
+        None => {
+            let metadata = std::fs::metadata(path).with_context(|| {
+                format!(
+                    "file source: unable to open file at path {}",
+                    path.to_string_lossy(),
+                )
+            })?;
+            return Ok(vec![listed(path.to_owned(), metadata)?]);
+        }
+    };
+    let context = || {
+        format!(
+            "file source: unable to list directory at path {}",
+            path.to_string_lossy()
+        )
+    };
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path).with_context(context)? {
+        let entry = entry.with_context(context)?;
+        if !matcher.is_match(entry.file_name()) {
+            continue;
+        }
+        // Files can disappear while we list them.
+        match std::fs::metadata(entry.path()) {
+            Ok(metadata) if metadata.is_file() => files.push(listed(entry.path(), metadata)?),
+            _ => (),
+        }
+    }
+    files.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)));
+    Ok(files)
+}
+
+fn decompress<'a, R>(reader: R, compression: &Compression) -> Box<dyn Read + 'a>
+where
+    R: Read + 'a,
+{
+    match compression {
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::None => Box::new(reader),
+    }
+}
+
 /// Blocking logic to read from a file, intended for its own thread.
-pub fn read_file_task<Ctor, I, Err>(
+pub fn read_file_task<Ctor, I, Out, Err>(
     path: PathBuf,
-    tx: std::sync::mpsc::SyncSender<Result<MessagePayload, anyhow::Error>>,
+    tx: std::sync::mpsc::SyncSender<Result<Out, anyhow::Error>>,
     activator: Option<SyncActivator>,
     read_style: FileReadStyle,
     compression: Compression,
     iter_ctor: Ctor,
 ) where
-    I: IntoIterator<Item = Result<MessagePayload, Err>> + Send + 'static,
+    I: IntoIterator<Item = Result<Out, Err>> + Send + 'static,
     Ctor: FnOnce(Box<dyn AvroRead + Send>) -> Result<I, Err>,
     Err: Into<anyhow::Error>,
 {
@@ -251,97 +873,110 @@ pub fn read_file_task<Ctor, I, Err>(
 }
 
 fn open_file_stream(
-    _path: PathBuf,
+    path: PathBuf,
     file: std::fs::File,
     read_style: FileReadStyle,
 ) -> Result<Box<dyn AvroRead + Send>, anyhow::Error> {
     match read_style {
         FileReadStyle::ReadOnce => Ok(Box::new(file)),
-        FileReadStyle::TailFollowFd => {
-            let (notice_tx, notice_rx) = mpsc::channel();
-
-            // FSEvents doesn't raise events until you close the file, making it
-            // useless for tailing log files that are kept open by the daemon
-            // writing to them.
-            //
-            // Avoid this issue by just waking up and polling the file on macOS
-            // every 100ms. We don't want to use notify::PollWatcher, since that
-            // occasionally misses updates if the file is changed twice within
-            // one second (it uses an mtime granularity of 1s). Plus it's not
-            // actually more efficient; our call to poll_read will be as fast as
-            // the PollWatcher's call to stat, and it actually saves a syscall
-            // if the file has data available.
-            //
-            // https://github.com/notify-rs/notify/issues/240
-            #[cfg(not(target_os = "linux"))]
-            thread::spawn(move || {
-                while let Ok(()) = notice_tx.send(()) {
-                    thread::sleep(std::time::Duration::from_millis(100));
-                }
-            });
-
-            #[cfg(target_os = "linux")]
-            {
-                let mut inotify = Inotify::init()
-                    .with_context(|| format!("file source: failed to initialize inotify"))?;
-                inotify
-                    .add_watch(&_path, WatchMask::ALL_EVENTS)
-                    .with_context(|| format!("failed to add watch for file {}", _path.display()))?;
-                thread::spawn(move || {
-                    // This buffer must be at least `sizeof(struct inotify_event) + NAME_MAX + 1`.
-                    // The `inotify` crate documentation uses 1KB, so that's =
-                    // what we do too.
-                    let mut buf = [0; 1024];
-                    loop {
-                        match inotify.read_events_blocking(&mut buf) {
-                            Err(err) => {
-                                if notice_tx
-                                    .send(Err(format!(
-                                    "file source: failed to get events for file: {:#} (path: {})",
-                                    err,
-                                    _path.display()
-                                )))
-                                    .is_err()
-                                {
-                                    // If the notice_tx returns an error, it's because
-                                    // the source has been dropped. Just exit the
-                                    // thread.
-                                    return;
-                                }
-                                // We have no method for recovering from this error
-                                // Close this thread and log an error message (which duplicates the err above)
-                                error!(
-                                    "file source: closing stream due to read errors (path: {})",
-                                    _path.display()
-                                );
-                                return;
-                            }
-                            Ok(mut events) => {
-                                if events.any(|x| x.mask == EventMask::ATTRIB) && !_path.exists() {
-                                    error!(
-                                        "file source: closing stream due to deleted file (path: {})",
-                                        _path.display()
-                                    );
-                                    return;
-                                }
-                            }
-                        }
-                        if notice_tx.send(Ok(())).is_err() {
+        FileReadStyle::TailFollowFd => Ok(Box::new(TailedFile {
+            notices: watch(&path)?,
+            inner: file,
+            is_complete: || false,
+        })),
+    }
+}
+
+/// A channel that receives a notice whenever a watched file or directory may
+/// have changed.
+type Notices = Receiver<Result<(), String>>;
+
+/// Returns a channel that receives a notice whenever the file or directory at
+/// `path` may have changed.
+fn watch(path: &Path) -> Result<Notices, anyhow::Error> {
+    let (notice_tx, notice_rx) = mpsc::channel();
+
+    // FSEvents doesn't raise events until you close the file, making it
+    // useless for tailing log files that are kept open by the daemon
+    // writing to them.
+    //
+    // Avoid this issue by just waking up and polling the file on macOS
+    // every 100ms. We don't want to use notify::PollWatcher, since that
+    // occasionally misses updates if the file is changed twice within
+    // one second (it uses an mtime granularity of 1s). Plus it's not
+    // actually more efficient; our call to poll_read will be as fast as
+    // the PollWatcher's call to stat, and it actually saves a syscall
+    // if the file has data available.
+    //
+    // https://github.com/notify-rs/notify/issues/240
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        thread::spawn(move || {
+            while let Ok(()) = notice_tx.send(Ok(())) {
+                thread::sleep(std::time::Duration::from_millis(100));
+            }
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let path = path.to_owned();
+        let mut inotify = Inotify::init()
+            .with_context(|| format!("file source: failed to initialize inotify"))?;
+        inotify
+            .add_watch(&path, WatchMask::ALL_EVENTS)
+            .with_context(|| format!("failed to add watch for file {}", path.display()))?;
+        thread::spawn(move || {
+            // This buffer must be at least `sizeof(struct inotify_event) + NAME_MAX + 1`.
+            // The `inotify` crate documentation uses 1KB, so that's =
+            // what we do too.
+            let mut buf = [0; 1024];
+            loop {
+                match inotify.read_events_blocking(&mut buf) {
+                    Err(err) => {
+                        if notice_tx
+                            .send(Err(format!(
+                                "file source: failed to get events for file: {:#} (path: {})",
+                                err,
+                                path.display()
+                            )))
+                            .is_err()
+                        {
                             // If the notice_tx returns an error, it's because
                             // the source has been dropped. Just exit the
                             // thread.
                             return;
                         }
+                        // We have no method for recovering from this error
+                        // Close this thread and log an error message (which duplicates the err above)
+                        error!(
+                            "file source: closing stream due to read errors (path: {})",
+                            path.display()
+                        );
+                        return;
                     }
-                });
-            };
-
-            Ok(Box::new(ForeverTailedFile {
-                rx: notice_rx,
-                inner: file,
-            }))
-        }
+                    Ok(mut events) => {
+                        if events.any(|x| x.mask == EventMask::ATTRIB) && !path.exists() {
+                            error!(
+                                "file source: closing stream due to deleted file (path: {})",
+                                path.display()
+                            );
+                            return;
+                        }
+                    }
+                }
+                if notice_tx.send(Ok(())).is_err() {
+                    // If the notice_tx returns an error, it's because
+                    // the source has been dropped. Just exit the
+                    // thread.
+                    return;
+                }
+            }
+        });
     }
+
+    Ok(notice_rx)
 }
 
 /// Strategies for streaming content from a file.
@@ -354,29 +989,43 @@ pub enum FileReadStyle {
     // TODO: TailFollowName,
 }
 
-/// Wraps a file, producing a stream that is tailed forever.
+/// Wraps a file, producing a stream that is tailed until `is_complete` says
+/// that nothing more gets written to the file.
 ///
 /// This involves silently swallowing EOFs,
 /// and waiting on a Notify handle for more data to be written.
-struct ForeverTailedFile<Ev> {
-    rx: std::sync::mpsc::Receiver<Ev>,
+struct TailedFile<N, C> {
+    notices: N,
     inner: std::fs::File,
+    is_complete: C,
 }
 
-impl<Ev> Skip for ForeverTailedFile<Ev> {
+impl<N, C> Skip for TailedFile<N, C>
+where
+    N: Borrow<Notices>,
+    C: FnMut() -> bool,
+{
     fn skip(&mut self, len: usize) -> Result<(), io::Error> {
         self.inner.skip(len)
     }
 }
 
-impl<Ev> Read for ForeverTailedFile<Ev> {
+impl<N, C> Read for TailedFile<N, C>
+where
+    N: Borrow<Notices>,
+    C: FnMut() -> bool,
+{
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         loop {
             // First drain the buffer of pending events from notify.
-            for _ in self.rx.try_iter() {}
+            for _ in self.notices.borrow().try_iter() {}
             match self.inner.read(buf)? {
                 0 => {
-                    if self.rx.recv().is_ok() {
+                    if (self.is_complete)() {
+                        // Data may have been written after the read above but
+                        // before the file was complete.
+                        return self.inner.read(buf);
+                    } else if self.notices.borrow().recv().is_ok() {
                         // Notify thinks there might be new data. Go around
                         // the loop again to check.
                     } else {
@@ -392,6 +1041,54 @@ impl<Ev> Read for ForeverTailedFile<Ev> {
     }
 }
 
+/// A checkpoint of how far a file has been read, recorded before a chunk of
+/// it is handed out.
+///
+/// The chunk was assigned `mz_offset`, and once it has been processed reading
+/// the file identified by `id`, which was last seen at `path`, can resume at
+/// `position`. A `complete` file has been read in full.
+/// The chunks up to and including this one hold `records` records, across all
+/// files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileCheckpoint {
+    path: PathBuf,
+    id: FileId,
+    mz_offset: i64,
+    position: u64,
+    complete: bool,
+    records: i64,
+}
+
+impl Checkpoint for FileCheckpoint {
+    fn encode(&self) -> Vec<u8> {
+        ProtoFileCheckpoint {
+            path: self.path.as_os_str().as_bytes().to_vec(),
+            mz_offset: self.mz_offset,
+            position: self.position,
+            complete: self.complete,
+            records: self.records,
+            dev: self.id.dev,
+            ino: self.id.ino,
+        }
+        .encode_to_vec()
+    }
+
+    fn decode(buf: &[u8]) -> Result<Self, anyhow::Error> {
+        let proto = ProtoFileCheckpoint::decode(buf)?;
+        Ok(FileCheckpoint {
+            path: OsStr::from_bytes(&proto.path).into(),
+            id: FileId {
+                dev: proto.dev,
+                ino: proto.ino,
+            },
+            mz_offset: proto.mz_offset,
+            position: proto.position,
+            complete: proto.complete,
+            records: proto.records,
+        })
+    }
+}
+
 /// Sends a sequence of records and activates a timely operator for each.
 fn send_records<I, Out, Err>(
     iter: I,
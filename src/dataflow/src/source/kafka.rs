@@ -7,7 +7,6 @@
 // the Business Source License, use of this software will be governed
 // by the Apache License, Version 2.0.
 
-use std::cmp;
 use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,14 +14,14 @@ use std::time::Duration;
 use rdkafka::consumer::base_consumer::PartitionQueue;
 use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext};
 use rdkafka::error::KafkaError;
-use rdkafka::message::BorrowedMessage;
+use rdkafka::message::{BorrowedMessage, Headers};
 use rdkafka::topic_partition_list::Offset;
 use rdkafka::{ClientConfig, ClientContext, Message, TopicPartitionList};
 use timely::scheduling::activate::SyncActivator;
 
 use dataflow_types::sources::{
     encoding::SourceDataEncoding, ExternalSourceConnector, KafkaOffset, KafkaSourceConnector,
-    MzOffset,
+    KafkaTopics, MzOffset,
 };
 use expr::{PartitionId, SourceInstanceId};
 use kafka_util::{client::MzClientContext, KafkaAddrs};
@@ -31,14 +30,14 @@ use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::logging::materialized::{Logger, MaterializedEvent};
-use crate::source::{NextMessage, SourceMessage, SourceReader};
+use crate::source::{NextMessage, PersistentCheckpointsConfig, SourceMessage, SourceReader};
 
 use super::metrics::SourceBaseMetrics;
 
 /// Contains all information necessary to ingest data from Kafka
 pub struct KafkaSourceReader {
-    /// Name of the topic on which this source is backed on
-    topic_name: String,
+    /// The topics on which this source is backed on
+    topics: KafkaTopics,
     /// Name of the source (will have format kafka-source-id)
     source_name: String,
     /// Source instance ID
@@ -47,12 +46,10 @@ pub struct KafkaSourceReader {
     consumer: Arc<BaseConsumer<GlueConsumerContext>>,
     /// List of consumers. A consumer should be assigned per partition to guarantee fairness
     partition_consumers: VecDeque<PartitionConsumer>,
-    /// The number of known partitions.
-    known_partitions: i32,
     /// Worker ID
     worker_id: i32,
     /// Map from partition -> most recently read offset
-    last_offsets: HashMap<i32, i64>,
+    last_offsets: HashMap<PartitionId, i64>,
     /// Map from partition -> offset to start reading at
     start_offsets: HashMap<i32, i64>,
     /// Timely worker logger for source events
@@ -61,6 +58,8 @@ pub struct KafkaSourceReader {
     stats_rx: crossbeam_channel::Receiver<Jsonb>,
     // The last statistics JSON blob received.
     last_stats: Option<Jsonb>,
+    /// Which optional message metadata to attach to each message
+    included_metadata: IncludedMetadata,
 }
 
 impl SourceReader for KafkaSourceReader {
@@ -77,6 +76,7 @@ impl SourceReader for KafkaSourceReader {
         _: SourceDataEncoding,
         logger: Option<Logger>,
         _: SourceBaseMetrics,
+        _: Option<PersistentCheckpointsConfig>,
     ) -> Result<(KafkaSourceReader, Option<PartitionId>), anyhow::Error> {
         match connector {
             ExternalSourceConnector::Kafka(kc) => Ok((
@@ -94,12 +94,11 @@ impl SourceReader for KafkaSourceReader {
         }
     }
     /// Ensures that a partition queue for `pid` exists.
-    /// In Kafka, partitions are assigned contiguously. This function consequently
-    /// creates partition queues for every p <= pid
     fn add_partition(&mut self, pid: PartitionId, restored_offset: Option<MzOffset>) {
-        let pid = match pid {
-            PartitionId::Kafka(p) => p,
-            _ => unreachable!(),
+        // User-configured start offsets are only supported for single-topic sources.
+        let configured_start_offset = match pid {
+            PartitionId::Kafka(p) => self.start_offsets.get(&p).copied(),
+            _ => None,
         };
 
         // Passed-in initial offsets take precedence over potential user-configured start offsets.
@@ -122,8 +121,8 @@ impl SourceReader for KafkaSourceReader {
             let restored_offset = restored_offset.offset - 1;
 
             // Also verify that we didn't regress from any user-configured start offsets.
-            if let Some(start_offset) = self.start_offsets.get(&pid) {
-                assert!(restored_offset >= *start_offset);
+            if let Some(start_offset) = configured_start_offset {
+                assert!(restored_offset >= start_offset);
             }
 
             // We subtract 1 again because this will be put into `last_offsets`, which record the
@@ -136,16 +135,15 @@ impl SourceReader for KafkaSourceReader {
         } else {
             // Indicate a last offset of -1 if we have not been instructed to have a specific start
             // offset for this topic.
-            *self.start_offsets.get(&pid).unwrap_or(&-1)
+            configured_start_offset.unwrap_or(-1)
         };
 
         // Seek to the *next* offset (aka start_offset + 1) that we have not yet processed
-        self.create_partition_queue(pid, Offset::Offset(start_offset + 1));
+        self.create_partition_queue(&pid, Offset::Offset(start_offset + 1));
 
         let prev = self.last_offsets.insert(pid, start_offset);
 
         assert!(prev.is_none());
-        self.known_partitions = cmp::max(self.known_partitions, pid + 1);
     }
 
     fn get_next_message(&mut self) -> Result<NextMessage<Self::Key, Self::Value>, anyhow::Error> {
@@ -165,12 +163,18 @@ impl KafkaSourceReader {
     ) -> KafkaSourceReader {
         let KafkaSourceConnector {
             addrs,
-            topic,
+            topics,
             config_options,
             group_id_prefix,
             cluster_id,
+            ref include_topic,
+            ref include_headers,
             ..
         } = kc;
+        let included_metadata = IncludedMetadata {
+            topic: include_topic.is_some(),
+            headers: include_headers.is_some(),
+        };
         let worker_id = worker_id.try_into().unwrap();
         let kafka_config = create_kafka_config(
             &source_name,
@@ -190,11 +194,10 @@ impl KafkaSourceReader {
         let start_offsets = kc.start_offsets.iter().map(|(k, v)| (*k, v - 1)).collect();
 
         KafkaSourceReader {
-            topic_name: topic,
+            topics,
             source_name,
             id: source_id,
             partition_consumers: VecDeque::new(),
-            known_partitions: 0,
             consumer: Arc::new(consumer),
             worker_id,
             last_offsets: HashMap::new(),
@@ -202,6 +205,7 @@ impl KafkaSourceReader {
             logger,
             stats_rx,
             last_stats: None,
+            included_metadata,
         }
     }
 
@@ -212,25 +216,26 @@ impl KafkaSourceReader {
         self.partition_consumers.len().try_into().unwrap()
     }
 
-    /// Creates a new partition queue for `partition_id`.
-    fn create_partition_queue(&mut self, partition_id: i32, initial_offset: Offset) {
+    /// Creates a new partition queue for `pid`.
+    fn create_partition_queue(&mut self, pid: &PartitionId, initial_offset: Offset) {
+        let (topic, partition_id) = topic_partition(&self.topics, pid);
         info!(
             "Activating Kafka queue for {} [{}] (source {}) on worker {}",
-            self.topic_name, partition_id, self.id, self.worker_id
+            topic, partition_id, self.id, self.worker_id
         );
 
         // Collect old partition assignments
         let tpl = self.consumer.assignment().unwrap();
         // Create list from assignments
         let mut partition_list = TopicPartitionList::new();
-        for partition in tpl.elements_for_topic(&self.topic_name) {
+        for partition in tpl.elements() {
             partition_list
                 .add_partition_offset(partition.topic(), partition.partition(), partition.offset())
                 .expect("offset known to be valid");
         }
         // Add new partition
         partition_list
-            .add_partition_offset(&self.topic_name, partition_id, initial_offset)
+            .add_partition_offset(topic, partition_id, initial_offset)
             .expect("offset known to be valid");
         self.consumer
             .assign(&partition_list)
@@ -240,9 +245,10 @@ impl KafkaSourceReader {
         // after every call to `self.consumer.assign`.
         let context = self.consumer.context().clone();
         for pc in &mut self.partition_consumers {
+            let (pc_topic, pc_partition_id) = topic_partition(&self.topics, &pc.pid);
             pc.partition_queue = self
                 .consumer
-                .split_partition_queue(&self.topic_name, pc.pid)
+                .split_partition_queue(pc_topic, pc_partition_id)
                 .expect("partition known to be valid");
             pc.partition_queue.set_nonempty_callback({
                 let context = context.clone();
@@ -252,17 +258,16 @@ impl KafkaSourceReader {
 
         let mut partition_queue = self
             .consumer
-            .split_partition_queue(&self.topic_name, partition_id)
+            .split_partition_queue(topic, partition_id)
             .expect("partition known to be valid");
         partition_queue.set_nonempty_callback(move || context.activate());
-        self.partition_consumers
-            .push_front(PartitionConsumer::new(partition_id, partition_queue));
+        self.partition_consumers.push_front(PartitionConsumer::new(
+            pid.clone(),
+            partition_queue,
+            self.included_metadata,
+        ));
         assert_eq!(
-            self.consumer
-                .assignment()
-                .unwrap()
-                .elements_for_topic(&self.topic_name)
-                .len(),
+            self.consumer.assignment().unwrap().count(),
             self.partition_consumers.len()
         );
     }
@@ -270,9 +275,10 @@ impl KafkaSourceReader {
     /// Fast-forward consumer to specified Kafka Offset. Prints a warning if failed to do so
     /// Assumption: if offset does not exist (for instance, because of compaction), will seek
     /// to the next available offset
-    fn fast_forward_consumer(&self, pid: i32, next_offset: i64) {
+    fn fast_forward_consumer(&self, pid: &PartitionId, next_offset: i64) {
+        let (topic, pid) = topic_partition(&self.topics, pid);
         let res = self.consumer.seek(
-            &self.topic_name,
+            topic,
             pid,
             Offset::Offset(next_offset),
             Duration::from_secs(1),
@@ -280,12 +286,10 @@ impl KafkaSourceReader {
         match res {
             Ok(_) => {
                 let res = self.consumer.position().unwrap_or_default().to_topic_map();
-                let position = res
-                    .get(&(self.topic_name.clone(), pid))
-                    .and_then(|p| match p {
-                        Offset::Offset(o) => Some(o),
-                        _ => None,
-                    });
+                let position = res.get(&(topic.to_string(), pid)).and_then(|p| match p {
+                    Offset::Offset(o) => Some(o),
+                    _ => None,
+                });
                 if let Some(position) = position {
                     if *position != next_offset {
                         warn!("Did not fast-forward consumer on partition PID: {} to the correct Kafka offset. Currently at offset: {} Expected offset: {}",
@@ -327,10 +331,14 @@ impl KafkaSourceReader {
             match result {
                 Err(e) => error!(
                     "kafka error when polling consumer for source: {} topic: {} : {}",
-                    self.source_name, self.topic_name, e
+                    self.source_name, self.topics, e
                 ),
                 Ok(message) => {
-                    let source_message = SourceMessage::from(&message);
+                    let partition = self
+                        .topics
+                        .partition_id(message.topic(), message.partition());
+                    let source_message =
+                        construct_source_message(&message, partition, self.included_metadata);
                     next_message = self.handle_message(source_message);
                 }
             }
@@ -386,13 +394,14 @@ impl KafkaSourceReader {
                 let pid = partition_queue.pid();
                 let last_offset = self
                     .last_offsets
-                    .get(&pid)
+                    .get(pid)
                     .expect("partition known to be installed");
+                let (topic, pid) = topic_partition(&self.topics, pid);
 
                 error!(
                         "kafka error consuming from source: {} topic: {}: partition: {} last processed offset: {} : {}",
                         self.source_name,
-                        self.topic_name,
+                        topic,
                         pid,
                         last_offset,
                         e
@@ -413,11 +422,6 @@ impl KafkaSourceReader {
         &mut self,
         message: SourceMessage<Option<Vec<u8>>, Option<Vec<u8>>>,
     ) -> NextMessage<Option<Vec<u8>>, Option<Vec<u8>>> {
-        let partition = match message.partition {
-            PartitionId::Kafka(pid) => pid,
-            _ => unreachable!(),
-        };
-
         // Convert the received offset back from a 1-indexed MzOffset to the correct offset.
         let offset = message.offset.offset - 1;
         // Offsets are guaranteed to be 1) monotonically increasing *unless* there is
@@ -431,27 +435,26 @@ impl KafkaSourceReader {
 
         // Given the explicit consumer to partition assignment, we should never receive a message
         // for a partition for which we have no metadata
-        assert!(self.last_offsets.contains_key(&partition));
-
         let last_offset_ref = self
             .last_offsets
-            .get_mut(&partition)
+            .get_mut(&message.partition)
             .expect("partition known to be installed");
 
         let last_offset = *last_offset_ref;
         if offset <= last_offset {
+            let (topic, partition) = topic_partition(&self.topics, &message.partition);
             info!(
                 "Kafka message before expected offset, skipping: \
                              source {} (reading topic {}, partition {}) \
                              received offset {} expected offset {:?}",
                 self.source_name,
-                self.topic_name,
+                topic,
                 partition,
                 offset,
                 last_offset + 1,
             );
             // Seek to the *next* offset (aka last_offset + 1) that we have not yet processed
-            self.fast_forward_consumer(partition, last_offset + 1);
+            self.fast_forward_consumer(&message.partition, last_offset + 1);
             // We explicitly should not consume the message as we have already processed it
             // However, we make sure to activate the source to make sure that we get a chance
             // to read from this consumer again (even if no new data arrives)
@@ -551,35 +554,92 @@ fn create_kafka_config(
     kafka_config
 }
 
-impl<'a> From<&BorrowedMessage<'a>> for SourceMessage<Option<Vec<u8>>, Option<Vec<u8>>> {
-    fn from(msg: &BorrowedMessage<'a>) -> Self {
-        let kafka_offset = KafkaOffset {
-            offset: msg.offset(),
-        };
-        Self {
-            partition: PartitionId::Kafka(msg.partition()),
-            offset: kafka_offset.into(),
-            upstream_time_millis: msg.timestamp().to_millis(),
-            key: msg.key().map(|k| k.to_vec()),
-            value: msg.payload().map(|p| p.to_vec()),
-        }
+/// The optional pieces of message metadata that a Kafka source has been asked to surface.
+///
+/// Copying topic names and headers out of every message is not free, so we only do so when the
+/// source has an `INCLUDE TOPIC` or `INCLUDE HEADERS` clause.
+#[derive(Clone, Copy, Debug)]
+struct IncludedMetadata {
+    topic: bool,
+    headers: bool,
+}
+
+/// Returns the topic and partition number that `pid` identifies.
+fn topic_partition<'a>(topics: &'a KafkaTopics, pid: &'a PartitionId) -> (&'a str, i32) {
+    match (topics, pid) {
+        (KafkaTopics::Single(topic), PartitionId::Kafka(partition)) => (topic, *partition),
+        (
+            KafkaTopics::List(_) | KafkaTopics::Pattern(_),
+            PartitionId::KafkaTopic(topic, partition),
+        ) => (topic, *partition),
+        _ => unreachable!("unexpected partition {} for topics {}", pid, topics),
+    }
+}
+
+fn construct_source_message(
+    msg: &BorrowedMessage<'_>,
+    partition: PartitionId,
+    included_metadata: IncludedMetadata,
+) -> SourceMessage<Option<Vec<u8>>, Option<Vec<u8>>> {
+    let kafka_offset = KafkaOffset {
+        offset: msg.offset(),
+    };
+    let topic = if included_metadata.topic {
+        Some(msg.topic().to_string())
+    } else {
+        None
+    };
+    let headers = if included_metadata.headers {
+        let headers = msg
+            .headers()
+            .map(|headers| {
+                // Kafka distinguishes null header values from empty ones, but
+                // `Headers::get` returns an empty slice for both, so both are
+                // decoded as empty values.
+                (0..headers.count())
+                    .filter_map(|idx| headers.get(idx))
+                    .map(|(key, value)| (key.to_string(), Some(value.to_vec())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(headers)
+    } else {
+        None
+    };
+    SourceMessage {
+        partition,
+        offset: kafka_offset.into(),
+        upstream_time_millis: msg.timestamp().to_millis(),
+        key: msg.key().map(|k| k.to_vec()),
+        value: msg.payload().map(|p| p.to_vec()),
+        topic,
+        headers,
+        filename: None,
+        records_before: None,
     }
 }
 
 /// Wrapper around a partition containing the underlying consumer
 struct PartitionConsumer {
     /// the partition id with which this consumer is associated
-    pid: i32,
+    pid: PartitionId,
     /// The underlying Kafka partition queue
     partition_queue: PartitionQueue<GlueConsumerContext>,
+    /// Which optional message metadata to attach to each message
+    included_metadata: IncludedMetadata,
 }
 
 impl PartitionConsumer {
     /// Creates a new partition consumer from underlying Kafka consumer
-    fn new(pid: i32, partition_queue: PartitionQueue<GlueConsumerContext>) -> Self {
+    fn new(
+        pid: PartitionId,
+        partition_queue: PartitionQueue<GlueConsumerContext>,
+        included_metadata: IncludedMetadata,
+    ) -> Self {
         PartitionConsumer {
             pid,
             partition_queue,
+            included_metadata,
         }
     }
 
@@ -589,8 +649,12 @@ impl PartitionConsumer {
     ) -> Result<Option<SourceMessage<Option<Vec<u8>>, Option<Vec<u8>>>>, KafkaError> {
         match self.partition_queue.poll(Duration::from_millis(0)) {
             Some(Ok(msg)) => {
-                let result = SourceMessage::from(&msg);
-                assert_eq!(result.partition, PartitionId::Kafka(self.pid));
+                assert!(matches!(
+                    self.pid,
+                    PartitionId::Kafka(p) | PartitionId::KafkaTopic(_, p) if p == msg.partition()
+                ));
+                let result =
+                    construct_source_message(&msg, self.pid.clone(), self.included_metadata);
                 Ok(Some(result))
             }
             Some(Err(err)) => Err(err),
@@ -599,8 +663,8 @@ impl PartitionConsumer {
     }
 
     /// Return the partition id for this PartitionConsumer
-    fn pid(&self) -> i32 {
-        self.pid
+    fn pid(&self) -> &PartitionId {
+        &self.pid
     }
 }
 
@@ -7,29 +7,35 @@
 // the Business Source License, use of this software will be governed
 // by the Apache License, Version 2.0.
 
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 use std::time::Instant;
 
-use anyhow::anyhow;
-use aws_sdk_kinesis::error::GetRecordsError;
-use aws_sdk_kinesis::output::GetRecordsOutput;
-use aws_sdk_kinesis::{Client as KinesisClient, SdkError};
+use anyhow::{anyhow, bail};
 use futures::executor::block_on;
 use prometheus::core::AtomicI64;
+use prost::Message;
 use timely::scheduling::SyncActivator;
 use tracing::error;
 
+use aws_sdk_kinesis::error::GetRecordsError;
+use aws_sdk_kinesis::output::GetRecordsOutput;
+use aws_sdk_kinesis::{Client as KinesisClient, SdkError};
 use dataflow_types::sources::{
-    encoding::SourceDataEncoding, ExternalSourceConnector, KinesisSourceConnector, MzOffset,
+    encoding::SourceDataEncoding, ExternalSourceConnector, KinesisStartPosition, MzOffset,
 };
 use expr::{PartitionId, SourceInstanceId};
-use mz_aws_util::kinesis;
+use mz_aws_util::kinesis::{self, ShardPosition};
 use ore::metrics::{DeleteOnDropGauge, GaugeVecExt};
 
 use crate::logging::materialized::Logger;
+use crate::source::checkpoint::{Checkpoint, Checkpointer};
+use crate::source::gen::source::ProtoKinesisCheckpoint;
 use crate::source::metrics::{KinesisMetrics, SourceBaseMetrics};
-use crate::source::{NextMessage, SourceMessage, SourceReader};
+use crate::source::{NextMessage, PersistentCheckpointsConfig, SourceMessage, SourceReader};
 
 /// To read all data from a Kinesis stream, we need to continually update
 /// our knowledge of the stream's shards by calling the ListShards API.
@@ -44,11 +50,13 @@ pub struct KinesisSourceReader {
     kinesis_client: KinesisClient,
     /// The name of the stream
     stream_name: String,
-    /// The set of active shards
-    shard_set: HashMap<String, ShardMetrics>,
+    /// Where to start reading shards that we have no position for
+    start_position: KinesisStartPosition,
+    /// The state of all known shards, including the ones we have read in full
+    shards: HashMap<String, ShardState>,
     /// A queue representing the next shard to read from. This is necessary
     /// to ensure that all shards are read from uniformly
-    shard_queue: VecDeque<(String, Option<String>)>,
+    shard_queue: VecDeque<String>,
     /// The time at which we last refreshed metadata
     /// TODO(natacha): this should be moved to timestamper
     last_checked_shards: Instant,
@@ -56,10 +64,42 @@ pub struct KinesisSourceReader {
     buffered_messages: VecDeque<SourceMessage<Option<Vec<u8>>, Option<Vec<u8>>>>,
     /// Count of processed message
     processed_message_count: i64,
+    /// The outcome of restoring our position from checkpoints, or `None` if
+    /// `add_partition` has not been called yet. We cannot hand out messages
+    /// before that.
+    restored: Option<Result<(), anyhow::Error>>,
+    /// After a restart, the shard that the persisted data ends in the middle
+    /// of a batch of, and the offset of the last persisted message. That shard
+    /// is read exclusively until we are back at that offset, so that the
+    /// records read again get the same offsets as before.
+    resume: Option<(String, i64)>,
+    /// Durable record of our position in each shard, if the source is
+    /// persistent.
+    checkpointer: Option<Checkpointer<KinesisCheckpoint>>,
+    /// The recorded checkpoints of each shard, oldest first, except for one
+    /// that is about to be superseded after a restart.
+    checkpoints: HashMap<String, VecDeque<KinesisCheckpoint>>,
+    /// Activates the source operator once checkpoints are durable.
+    activator: Arc<SyncActivator>,
     /// Metrics from which per-shard metrics get created.
     base_metrics: KinesisMetrics,
 }
 
+/// The read state of a single shard.
+struct ShardState {
+    /// The shards this shard was split or merged from. Their records precede
+    /// this shard's, so they are read in full first.
+    parents: Vec<String>,
+    /// The iterator to read the next records with, if we have one. We don't
+    /// before the shard is first read, and after an iterator expires.
+    iterator: Option<String>,
+    /// The sequence number of the last record read from this shard
+    last_sequence_number: Option<String>,
+    /// Whether the shard has been closed and we have read all of its records
+    finished: bool,
+    metrics: ShardMetrics,
+}
+
 struct ShardMetrics {
     millis_behind_latest: DeleteOnDropGauge<'static, AtomicI64, Vec<String>>,
 }
@@ -76,28 +116,170 @@ impl ShardMetrics {
 
 impl KinesisSourceReader {
     async fn update_shard_information(&mut self) -> Result<(), anyhow::Error> {
-        let current_shards: HashSet<_> =
-            kinesis::get_shard_ids(&self.kinesis_client, &self.stream_name)
-                .await?
-                .collect();
-        let known_shards: HashSet<_> = self.shard_set.keys().cloned().collect();
-        let new_shards = current_shards
-            .difference(&known_shards)
-            .map(|shard_id| shard_id.to_owned());
-        for shard_id in new_shards {
-            self.shard_set.insert(
-                shard_id.to_string(),
-                ShardMetrics::new(&self.base_metrics, &self.stream_name, &shard_id),
-            );
-            self.shard_queue.push_back((
-                shard_id.to_string(),
-                kinesis::get_shard_iterator(&self.kinesis_client, &self.stream_name, &shard_id)
-                    .await?,
-            ));
+        for shard in kinesis::list_shards(&self.kinesis_client, &self.stream_name).await? {
+            let shard_id = match shard.shard_id {
+                Some(shard_id) => shard_id,
+                None => continue,
+            };
+            if let Entry::Vacant(entry) = self.shards.entry(shard_id) {
+                let metrics = ShardMetrics::new(&self.base_metrics, &self.stream_name, entry.key());
+                entry.insert(ShardState {
+                    parents: shard
+                        .parent_shard_id
+                        .into_iter()
+                        .chain(shard.adjacent_parent_shard_id)
+                        .collect(),
+                    iterator: None,
+                    last_sequence_number: None,
+                    finished: false,
+                    metrics,
+                });
+            }
         }
+        self.queue_ready_shards();
         Ok(())
     }
 
+    /// Queues the shards that are ready to be read but not queued yet.
+    ///
+    /// A shard is ready once its parents have been read in full or have
+    /// expired from the stream. Shards that we have a position in were ready
+    /// before, even if we have not seen their parents finish since a restart.
+    fn queue_ready_shards(&mut self) {
+        let mut ready: Vec<_> = self
+            .shards
+            .iter()
+            .filter(|(shard_id, state)| {
+                !state.finished
+                    && !self.shard_queue.contains(shard_id)
+                    && !matches!(&self.resume, Some((resume_id, _)) if resume_id == *shard_id)
+                    && (state.last_sequence_number.is_some()
+                        || state.parents.iter().all(|parent| {
+                            self.shards
+                                .get(parent)
+                                .map(|parent| parent.finished)
+                                .unwrap_or(true)
+                        }))
+            })
+            .map(|(shard_id, _)| shard_id.clone())
+            .collect();
+        ready.sort();
+        self.shard_queue.extend(ready);
+    }
+
+    /// Restores our position in each shard from the recorded checkpoints.
+    ///
+    /// `restored_offset` is the offset of the first message that did not make
+    /// it into the persisted data. Checkpoints of batches past it are
+    /// retracted, as are checkpoints that newer ones of the same shard
+    /// supersede.
+    fn restore(&mut self, restored_offset: Option<MzOffset>) -> Result<(), anyhow::Error> {
+        let checkpointer = match &mut self.checkpointer {
+            Some(checkpointer) => checkpointer,
+            None => return Ok(()),
+        };
+        let last_offset = restored_offset.map(|o| o.offset - 1).unwrap_or(0);
+
+        let mut latest: HashMap<String, KinesisCheckpoint> = HashMap::new();
+        let mut obsolete = vec![];
+        for checkpoint in checkpointer.restore()? {
+            if checkpoint.mz_offset >= last_offset {
+                obsolete.push(checkpoint);
+                continue;
+            }
+            match latest.entry(checkpoint.shard_id.clone()) {
+                Entry::Occupied(mut entry) => {
+                    if entry.get().mz_offset < checkpoint.mz_offset {
+                        obsolete.push(entry.insert(checkpoint));
+                    } else {
+                        obsolete.push(checkpoint);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(checkpoint);
+                }
+            }
+        }
+
+        self.processed_message_count = last_offset;
+        if let Some(restored_offset) = restored_offset {
+            checkpointer.set_restored_offset(restored_offset.offset);
+        }
+        for (shard_id, checkpoint) in latest {
+            let complete = checkpoint.mz_offset + checkpoint.len <= last_offset;
+            let state = match self.shards.get_mut(&shard_id) {
+                Some(state) => state,
+                None if complete => {
+                    // The shard has expired from the stream after we read it.
+                    obsolete.push(checkpoint);
+                    continue;
+                }
+                None => bail!(
+                    "Kinesis shard {} expired before the source caught up on it",
+                    shard_id
+                ),
+            };
+            if complete {
+                state.last_sequence_number = Some(checkpoint.to_sequence_number.clone());
+                self.checkpoints
+                    .entry(shard_id)
+                    .or_default()
+                    .push_back(checkpoint);
+            } else {
+                // Only part of this batch made it into the persisted data.
+                // Read it again, dropping the records that did.
+                state.last_sequence_number = checkpoint.from_sequence_number.clone();
+                self.processed_message_count = checkpoint.mz_offset;
+                self.shard_queue.retain(|queued| *queued != shard_id);
+                self.resume = Some((shard_id, last_offset));
+                checkpointer.superseded = Some(checkpoint);
+            }
+        }
+
+        checkpointer.update(obsolete.into_iter().map(|c| (c, -1)).collect())?;
+        checkpointer.compact()?;
+        self.queue_ready_shards();
+        Ok(())
+    }
+
+    /// Returns the checkpoints that are no longer needed to restore our
+    /// position, and forgets about them.
+    ///
+    /// Restoring uses the latest checkpoint of each shard whose batch starts
+    /// before the last persisted message, so any older checkpoints of the
+    /// shard can go.
+    fn take_obsolete_checkpoints(&mut self, persisted_offset: i64) -> Vec<KinesisCheckpoint> {
+        let last_offset = std::cmp::max(persisted_offset - 1, 0);
+        let mut obsolete = vec![];
+        for checkpoints in self.checkpoints.values_mut() {
+            while checkpoints.len() > 1 && checkpoints[1].mz_offset < last_offset {
+                obsolete.extend(checkpoints.pop_front());
+            }
+        }
+        obsolete
+    }
+
+    /// Returns the position to (re)start reading a shard from.
+    fn shard_position(
+        start_position: KinesisStartPosition,
+        last_sequence_number: &Option<String>,
+    ) -> ShardPosition {
+        match (last_sequence_number, start_position) {
+            (Some(sequence_number), _) => {
+                ShardPosition::AfterSequenceNumber(sequence_number.clone())
+            }
+            (None, KinesisStartPosition::TrimHorizon) => ShardPosition::TrimHorizon,
+            (None, KinesisStartPosition::AtTimestamp(millis)) => ShardPosition::AtTimestamp(millis),
+        }
+    }
+
+    /// Puts `shard_id` back in line after an attempt to read it.
+    fn requeue(&mut self, shard_id: String) {
+        if self.resume.is_none() && !self.shards[&shard_id].finished {
+            self.shard_queue.push_back(shard_id);
+        }
+    }
+
     /// Obtains the next record for this shard given a shard iterator
     async fn get_records(
         &self,
@@ -119,37 +301,68 @@ impl SourceReader for KinesisSourceReader {
         _source_name: String,
         _source_id: SourceInstanceId,
         _worker_id: usize,
-        _consumer_activator: SyncActivator,
+        consumer_activator: SyncActivator,
         connector: ExternalSourceConnector,
         _encoding: SourceDataEncoding,
         _: Option<Logger>,
         base_metrics: SourceBaseMetrics,
+        checkpoints: Option<PersistentCheckpointsConfig>,
     ) -> Result<(Self, Option<PartitionId>), anyhow::Error> {
         let kc = match connector {
             ExternalSourceConnector::Kinesis(kc) => kc,
             _ => unreachable!(),
         };
 
-        let state = block_on(create_state(&base_metrics.kinesis, kc));
-        match state {
-            Ok((kinesis_client, stream_name, shard_set, shard_queue)) => Ok((
-                KinesisSourceReader {
-                    kinesis_client,
-                    shard_queue,
-                    last_checked_shards: Instant::now(),
-                    buffered_messages: VecDeque::new(),
-                    shard_set,
-                    stream_name,
-                    processed_message_count: 0,
-                    base_metrics: base_metrics.kinesis,
-                },
-                Some(PartitionId::None),
-            )),
-            Err(e) => Err(anyhow!("{}", e)),
-        }
+        let config = block_on(kc.aws.load());
+        let mut reader = KinesisSourceReader {
+            kinesis_client: kinesis::client(&config),
+            stream_name: kc.stream_name,
+            start_position: kc.start_position,
+            shards: HashMap::new(),
+            shard_queue: VecDeque::new(),
+            last_checked_shards: Instant::now(),
+            buffered_messages: VecDeque::new(),
+            processed_message_count: 0,
+            restored: None,
+            resume: None,
+            checkpointer: checkpoints.map(Checkpointer::new),
+            checkpoints: HashMap::new(),
+            activator: Arc::new(consumer_activator),
+            base_metrics: base_metrics.kinesis,
+        };
+        block_on(reader.update_shard_information()).map_err(|e| anyhow!("{}", e))?;
+
+        // The single partition gets added once we know where to resume from.
+        Ok((reader, None))
+    }
+
+    fn add_partition(&mut self, pid: PartitionId, restored_offset: Option<MzOffset>) {
+        assert_eq!(
+            pid,
+            PartitionId::None,
+            "Kinesis sources have a single partition"
+        );
+        self.restored = Some(self.restore(restored_offset));
     }
+
     fn get_next_message(&mut self) -> Result<NextMessage<Self::Key, Self::Value>, anyhow::Error> {
-        assert_eq!(self.shard_queue.len(), self.shard_set.len());
+        match &self.restored {
+            None => return Ok(NextMessage::Pending),
+            Some(Err(e)) => return Err(anyhow!("restoring Kinesis shard positions: {:#}", e)),
+            Some(Ok(())) => (),
+        }
+
+        // Don't hand out any records before the checkpoint of their batch is
+        // durable.
+        if let Some(checkpointer) = &mut self.checkpointer {
+            let waker = futures::task::waker_ref(&self.activator);
+            let mut context = Context::from_waker(&waker);
+            match checkpointer.poll_updated(&mut context) {
+                Poll::Ready(result) => result?,
+                // The activator wakes us up once the write completes.
+                Poll::Pending => return Ok(NextMessage::Pending),
+            }
+        }
 
         //TODO move to timestamper
         if self.last_checked_shards.elapsed() >= KINESIS_SHARD_REFRESH_RATE {
@@ -161,126 +374,229 @@ impl SourceReader for KinesisSourceReader {
         }
 
         if let Some(message) = self.buffered_messages.pop_front() {
-            Ok(NextMessage::Ready(message))
-        } else {
-            // Rotate through all of a stream's shards, start with a new shard on each activation.
-            if let Some((shard_id, mut shard_iterator)) = self.shard_queue.pop_front() {
-                if let Some(iterator) = &shard_iterator {
-                    let get_records_output = match block_on(self.get_records(&iterator)) {
-                        Ok(output) => {
-                            shard_iterator = output.next_shard_iterator.clone();
-                            if let Some(millis) = output.millis_behind_latest {
-                                self.shard_set
-                                    .get(&shard_id)
-                                    .unwrap()
-                                    .millis_behind_latest
-                                    .set(millis);
-                            }
-                            output
-                        }
-                        Err(SdkError::DispatchFailure(e)) => {
-                            // todo@jldlaughlin: Parse this to determine fatal/retriable?
-                            error!("{}", e);
-                            self.shard_queue.push_back((shard_id, shard_iterator));
-                            // Do not send error message as this would cause source to terminate
-                            return Ok(NextMessage::TransientDelay);
-                        }
-                        Err(SdkError::ServiceError { err, .. })
-                            if err.is_expired_iterator_exception() =>
-                        {
-                            // todo@jldlaughlin: Will need track source offsets to grab a new iterator.
-                            error!("{}", err);
-                            return Err(anyhow::Error::msg(err));
-                        }
-                        Err(SdkError::ServiceError { err, .. })
-                            if err.is_provisioned_throughput_exceeded_exception() =>
-                        {
-                            self.shard_queue.push_back((shard_id, shard_iterator));
-                            // Do not send error message as this would cause source to terminate
-                            return Ok(NextMessage::Pending);
-                        }
-                        Err(e) => {
-                            // Fatal service errors:
-                            //  - InvalidArgument
-                            //  - KMSAccessDenied, KMSDisabled, KMSInvalidState, KMSNotFound,
-                            //    KMSOptInRequired, KMSThrottling
-                            //  - ResourceNotFound
-                            //
-                            // Other fatal Rusoto errors:
-                            // - Credentials
-                            // - Validation
-                            // - ParseError
-                            // - Unknown (raw HTTP provided)
-                            // - Blocking
-                            error!("{}", e);
-                            return Err(anyhow!("{}", e));
-                        }
-                    };
-
-                    for record in get_records_output.records.unwrap_or_default() {
-                        let data = record
-                            .data
-                            .map(|blob| blob.into_inner())
-                            .unwrap_or_else(Vec::new);
-                        self.processed_message_count += 1;
-                        let source_message = SourceMessage {
-                            partition: PartitionId::None,
-                            offset: MzOffset {
-                                //TODO: should MzOffset be modified to be a string?
-                                offset: self.processed_message_count,
-                            },
-                            upstream_time_millis: None,
-                            key: None,
-                            value: Some(data),
-                        };
-                        self.buffered_messages.push_back(source_message);
+            return Ok(NextMessage::Ready(message));
+        }
+
+        // Rotate through all of a stream's shards, start with a new shard on each activation.
+        let shard_id = match &self.resume {
+            Some((shard_id, _)) => shard_id.clone(),
+            None => match self.shard_queue.pop_front() {
+                Some(shard_id) => shard_id,
+                None => return Ok(NextMessage::Pending),
+            },
+        };
+
+        let state = &self.shards[&shard_id];
+        let iterator = match &state.iterator {
+            Some(iterator) => iterator.clone(),
+            None => {
+                let position =
+                    Self::shard_position(self.start_position, &state.last_sequence_number);
+                match block_on(kinesis::get_shard_iterator_at(
+                    &self.kinesis_client,
+                    &self.stream_name,
+                    &shard_id,
+                    &position,
+                )) {
+                    Ok(Some(iterator)) => iterator,
+                    Ok(None) => bail!("Kinesis returned no iterator for shard {}", shard_id),
+                    Err(e) => {
+                        error!("{}", e);
+                        return Err(anyhow!("{}", e));
                     }
-                    self.shard_queue.push_back((shard_id, shard_iterator));
                 }
             }
-            Ok(match self.buffered_messages.pop_front() {
-                Some(message) => NextMessage::Ready(message),
-                None => NextMessage::Pending,
-            })
+        };
+
+        let get_records_output = match block_on(self.get_records(&iterator)) {
+            Ok(output) => output,
+            Err(SdkError::DispatchFailure(e)) => {
+                // todo@jldlaughlin: Parse this to determine fatal/retriable?
+                error!("{}", e);
+                self.shards.get_mut(&shard_id).unwrap().iterator = Some(iterator);
+                self.requeue(shard_id);
+                // Do not send error message as this would cause source to terminate
+                return Ok(NextMessage::TransientDelay);
+            }
+            Err(SdkError::ServiceError { err, .. }) if err.is_expired_iterator_exception() => {
+                // Iterators expire five minutes after they are handed out. Get a
+                // fresh one from our last position the next time around.
+                self.shards.get_mut(&shard_id).unwrap().iterator = None;
+                self.requeue(shard_id);
+                return Ok(NextMessage::TransientDelay);
+            }
+            Err(SdkError::ServiceError { err, .. })
+                if err.is_provisioned_throughput_exceeded_exception() =>
+            {
+                self.shards.get_mut(&shard_id).unwrap().iterator = Some(iterator);
+                self.requeue(shard_id);
+                // Do not send error message as this would cause source to terminate
+                return Ok(NextMessage::Pending);
+            }
+            Err(e) => {
+                // Fatal service errors:
+                //  - InvalidArgument
+                //  - KMSAccessDenied, KMSDisabled, KMSInvalidState, KMSNotFound,
+                //    KMSOptInRequired, KMSThrottling
+                //  - ResourceNotFound
+                //
+                // Other fatal Rusoto errors:
+                // - Credentials
+                // - Validation
+                // - ParseError
+                // - Unknown (raw HTTP provided)
+                // - Blocking
+                error!("{}", e);
+                return Err(anyhow!("{}", e));
+            }
+        };
+
+        let records = get_records_output.records.unwrap_or_default();
+        let state = self.shards.get_mut(&shard_id).unwrap();
+        if let Some(millis) = get_records_output.millis_behind_latest {
+            state.metrics.millis_behind_latest.set(millis);
         }
+        // A closed shard has no next iterator once all of its records have
+        // been read.
+        state.iterator = get_records_output.next_shard_iterator;
+        state.finished = state.iterator.is_none();
+        let from_sequence_number = state.last_sequence_number.clone();
+        if let Some(sequence_number) = records.last().and_then(|r| r.sequence_number.clone()) {
+            state.last_sequence_number = Some(sequence_number);
+        }
+        let finished = state.finished;
+
+        let to_sequence_number = state.last_sequence_number.clone();
+
+        // Record where this batch came from before handing out any of it, so
+        // that its records get the same offsets if we have to read it again.
+        // The write happens in the background; the records are held back
+        // until it is durable. Checkpoints that the persisted data has caught
+        // up with are retracted along the way.
+        if let (Some(persisted_offset), Some(to_sequence_number)) = (
+            self.checkpointer.as_ref().map(|c| c.persisted_offset()),
+            to_sequence_number,
+        ) {
+            if !records.is_empty() {
+                let checkpoint = KinesisCheckpoint {
+                    shard_id: shard_id.clone(),
+                    mz_offset: self.processed_message_count,
+                    from_sequence_number,
+                    to_sequence_number,
+                    len: i64::try_from(records.len()).expect("batches are small"),
+                };
+                let mut updates: Vec<_> = self
+                    .take_obsolete_checkpoints(persisted_offset)
+                    .into_iter()
+                    .map(|c| (c, -1))
+                    .collect();
+                let compact = !updates.is_empty();
+                self.checkpoints
+                    .entry(shard_id.clone())
+                    .or_default()
+                    .push_back(checkpoint.clone());
+                updates.push((checkpoint, 1));
+                let checkpointer = self.checkpointer.as_mut().unwrap();
+                if let Some(superseded) = checkpointer.superseded.take() {
+                    updates.push((superseded, -1));
+                }
+                checkpointer.start_update(updates, compact);
+            }
+        }
+
+        for record in records {
+            self.processed_message_count += 1;
+            if let Some((_, last_offset)) = &self.resume {
+                if self.processed_message_count <= *last_offset {
+                    continue;
+                }
+            }
+            let data = record
+                .data
+                .map(|blob| blob.into_inner())
+                .unwrap_or_else(Vec::new);
+            let source_message = SourceMessage {
+                partition: PartitionId::None,
+                offset: MzOffset {
+                    //TODO: should MzOffset be modified to be a string?
+                    offset: self.processed_message_count,
+                },
+                upstream_time_millis: None,
+                key: None,
+                value: Some(data),
+                topic: None,
+                headers: None,
+                filename: None,
+                records_before: None,
+            };
+            self.buffered_messages.push_back(source_message);
+        }
+
+        if let Some((_, last_offset)) = &self.resume {
+            if self.processed_message_count >= *last_offset {
+                self.resume = None;
+            } else if finished {
+                bail!(
+                    "Kinesis shard {} ended before the source caught up on it",
+                    shard_id
+                );
+            }
+        }
+        if finished {
+            // The shard's children may be ready to be read now.
+            self.queue_ready_shards();
+        } else {
+            self.requeue(shard_id);
+        }
+
+        if self.checkpointer.is_some() && !self.buffered_messages.is_empty() {
+            // Come back right away to hand out the records once their
+            // checkpoint is durable.
+            return Ok(NextMessage::TransientDelay);
+        }
+        Ok(match self.buffered_messages.pop_front() {
+            Some(message) => NextMessage::Ready(message),
+            None => NextMessage::Pending,
+        })
     }
 }
 
-/// Creates the necessary data-structures for shard management
-// todo: Better error handling here! Not all errors mean we're done/can't progress.
-async fn create_state(
-    base_metrics: &KinesisMetrics,
-    c: KinesisSourceConnector,
-) -> Result<
-    (
-        KinesisClient,
-        String,
-        HashMap<String, ShardMetrics>,
-        VecDeque<(String, Option<String>)>,
-    ),
-    anyhow::Error,
-> {
-    let config = c.aws.load().await;
-    let kinesis_client = kinesis::client(&config);
-
-    let shard_set = kinesis::get_shard_ids(&kinesis_client, &c.stream_name).await?;
-    let mut shard_queue: VecDeque<(String, Option<String>)> = VecDeque::new();
-    let mut shard_map = HashMap::new();
-    for shard_id in shard_set {
-        shard_queue.push_back((
-            shard_id.clone(),
-            kinesis::get_shard_iterator(&kinesis_client, &c.stream_name, &shard_id).await?,
-        ));
-        shard_map.insert(
-            shard_id.clone(),
-            ShardMetrics::new(base_metrics, &c.stream_name, &shard_id),
-        );
+/// A checkpoint of a Kinesis source's position, recorded before the records
+/// of a batch are handed out.
+///
+/// The batch was read from `shard_id`, starting right after the record with
+/// `from_sequence_number` (or at the source's start position, if there is
+/// none) and ending with the record with `to_sequence_number`. Its `len`
+/// records were assigned the offsets following `mz_offset`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct KinesisCheckpoint {
+    shard_id: String,
+    mz_offset: i64,
+    from_sequence_number: Option<String>,
+    to_sequence_number: String,
+    len: i64,
+}
+
+impl Checkpoint for KinesisCheckpoint {
+    fn encode(&self) -> Vec<u8> {
+        ProtoKinesisCheckpoint {
+            shard_id: self.shard_id.clone(),
+            mz_offset: self.mz_offset,
+            from_sequence_number: self.from_sequence_number.clone().unwrap_or_default(),
+            to_sequence_number: self.to_sequence_number.clone(),
+            len: self.len,
+        }
+        .encode_to_vec()
     }
 
-    Ok((
-        kinesis_client,
-        c.stream_name.clone(),
-        shard_map,
-        shard_queue,
-    ))
+    fn decode(buf: &[u8]) -> Result<Self, anyhow::Error> {
+        let proto = ProtoKinesisCheckpoint::decode(buf)?;
+        Ok(KinesisCheckpoint {
+            shard_id: proto.shard_id,
+            mz_offset: proto.mz_offset,
+            from_sequence_number: Some(proto.from_sequence_number).filter(|s| !s.is_empty()),
+            to_sequence_number: proto.to_sequence_number,
+            len: proto.len,
+        })
+    }
 }
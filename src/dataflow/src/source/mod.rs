@@ -22,6 +22,7 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 use std::fmt::{self, Debug};
 use std::rc::Rc;
+use std::sync::atomic;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
@@ -64,6 +65,7 @@ use crate::source::timestamp::TimestampBindingRc;
 use crate::source::timestamp::TimestampBindingUpdater;
 use crate::source::timestamp::{AssignedTimestamp, SourceTimestamp};
 
+mod checkpoint;
 mod file;
 mod gen;
 mod kafka;
@@ -144,6 +146,16 @@ where
     pub upstream_time_millis: Option<i64>,
     /// The partition of this message, present iff the partition comes from Kafka
     pub partition: PartitionId,
+    /// The topic of this message, present iff the source requested it via `INCLUDE TOPIC`
+    pub topic: Option<String>,
+    /// The headers of this message, present iff the source requested them via `INCLUDE HEADERS`
+    pub headers: Option<Vec<(String, Option<Vec<u8>>)>>,
+    /// The file this message was read from, present iff the source requested it via
+    /// `INCLUDE FILENAME`
+    pub filename: Option<String>,
+    /// For byte stream sources, the number of records that preceded this chunk, if the source
+    /// needs to tell decoding where to continue numbering records from
+    pub records_before: Option<i64>,
 }
 
 /// The data that we send from Upsert to the decode process
@@ -200,6 +212,10 @@ where
         position: Option<i64>,
         upstream_time_millis: Option<i64>,
         partition: PartitionId,
+        topic: Option<String>,
+        headers: Option<Vec<(String, Option<Vec<u8>>)>>,
+        filename: Option<String>,
+        records_before: Option<i64>,
     ) -> SourceOutput<K, V> {
         SourceOutput {
             key,
@@ -207,6 +223,10 @@ where
             position,
             upstream_time_millis,
             partition,
+            topic,
+            headers,
+            filename,
+            records_before,
         }
     }
 }
@@ -357,6 +377,9 @@ pub(crate) trait SourceReader {
     /// This function returns the source reader and optionally, any "partition" it's
     /// already reading. In practice, the partition is only non-None for static sources
     /// that either don't truly have partitions or have a fixed number of partitions.
+    ///
+    /// Sources that need more than a restored offset to resume reading can record their position
+    /// in `checkpoints`, which is only present when the source is persistent.
     fn new(
         source_name: String,
         source_id: SourceInstanceId,
@@ -366,6 +389,7 @@ pub(crate) trait SourceReader {
         encoding: SourceDataEncoding,
         logger: Option<Logger>,
         metrics: crate::source::metrics::SourceBaseMetrics,
+        checkpoints: Option<PersistentCheckpointsConfig>,
     ) -> Result<(Self, Option<PartitionId>), anyhow::Error>
     where
         Self: Sized;
@@ -411,6 +435,16 @@ pub struct SourceMessage<Key, Value> {
     pub key: Key,
     /// The message value
     pub value: Value,
+    /// The topic the message was read from, if the source is configured to surface it
+    pub topic: Option<String>,
+    /// The message headers, if the source is configured to surface them
+    pub headers: Option<Vec<(String, Option<Vec<u8>>)>>,
+    /// The file the message was read from, if the source is configured to surface it
+    pub filename: Option<String>,
+    /// For byte stream sources, the number of records that preceded this chunk, if the source
+    /// needs to tell decoding where to continue numbering records from, e.g. after resuming
+    /// from a checkpoint
+    pub records_before: Option<i64>,
 }
 
 impl fmt::Debug for SourceMessage<(), MessagePayload> {
@@ -830,6 +864,12 @@ pub fn responsible_for(
             let hash = (source_id.hashed() >> 32) + *p as u64;
             (hash % worker_count as u64) == worker_id as u64
         }
+        PartitionId::KafkaTopic(topic, p) => {
+            // As above, but partitions with the same id in different topics should also be
+            // spread across workers.
+            let hash = (source_id.hashed() >> 32) + (topic.hashed() >> 32) + *p as u64;
+            (hash % worker_count as u64) == worker_id as u64
+        }
     }
 }
 
@@ -1276,6 +1316,7 @@ pub(crate) fn create_source<G, S: 'static>(
     config: SourceConfig<G>,
     source_connector: &ExternalSourceConnector,
     persist_config: Option<PersistentTimestampBindingsConfig<SourceTimestamp, AssignedTimestamp>>,
+    checkpoints_config: Option<PersistentCheckpointsConfig>,
 ) -> (
     (
         timely::dataflow::Stream<G, SourceOutput<S::Key, S::Value>>,
@@ -1430,6 +1471,7 @@ where
                 encoding,
                 logger,
                 base_metrics.clone(),
+                checkpoints_config,
             ) {
                 Ok((source_reader, partition)) => {
                     if let Some(pid) = partition {
@@ -1857,6 +1899,51 @@ impl<K: Codec, V: Codec> PersistentTimestampBindingsConfig<K, V> {
     }
 }
 
+/// Configuration for persistent source checkpoints.
+///
+/// Some sources cannot resume reading from a restored [`MzOffset`] alone. These sources record
+/// checkpoints of their read position in a separate persistent collection and consult them when
+/// [`SourceReader::add_partition`] hands them a restored offset. The checkpoints are opaque to
+/// everything but the source that wrote them.
+#[derive(Clone)]
+pub struct PersistentCheckpointsConfig {
+    /// The timestamp up to which checkpoints have been sealed.
+    upper_seal_ts: u64,
+
+    /// [`StreamReadHandle`] for the collection that checkpoints are restored from.
+    read_handle: StreamReadHandle<Vec<u8>, ()>,
+
+    /// [`StreamWriteHandle`] for the collection that checkpoints are written to.
+    write_handle: StreamWriteHandle<Vec<u8>, ()>,
+
+    /// The offset of the latest timestamp binding whose data has been persisted and sealed, or 0
+    /// if there is none. This is kept up to date while the source runs, so that checkpoints that
+    /// are no longer needed for restoring can be retracted.
+    persisted_offset: Arc<atomic::AtomicI64>,
+}
+
+impl PersistentCheckpointsConfig {
+    /// Creates a new [`PersistentCheckpointsConfig`] from the given parts.
+    pub fn new(
+        upper_seal_ts: u64,
+        read_handle: StreamReadHandle<Vec<u8>, ()>,
+        write_handle: StreamWriteHandle<Vec<u8>, ()>,
+    ) -> Self {
+        PersistentCheckpointsConfig {
+            upper_seal_ts,
+            read_handle,
+            write_handle,
+            persisted_offset: Arc::new(atomic::AtomicI64::new(0)),
+        }
+    }
+
+    /// Returns the shared offset of the latest timestamp binding whose data has been persisted and
+    /// sealed, for the operator that tracks it.
+    pub fn persisted_offset(&self) -> Arc<atomic::AtomicI64> {
+        Arc::clone(&self.persisted_offset)
+    }
+}
+
 /// Take `message` and assign it the appropriate timestamps and push it into the
 /// dataflow layer, if possible.
 ///
@@ -1922,6 +2009,10 @@ fn handle_message<S: SourceReader>(
                 Some(offset.offset),
                 message.upstream_time_millis,
                 message.partition,
+                message.topic,
+                message.headers,
+                message.filename,
+                message.records_before,
             )));
 
             // Update ingestion metrics
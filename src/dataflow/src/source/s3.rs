@@ -55,7 +55,7 @@ use ore::retry::{Retry, RetryReader};
 use repr::MessagePayload;
 
 use crate::logging::materialized::Logger;
-use crate::source::{NextMessage, SourceMessage, SourceReader};
+use crate::source::{NextMessage, PersistentCheckpointsConfig, SourceMessage, SourceReader};
 
 use self::metrics::{BucketMetrics, ScanBucketMetrics};
 use self::notifications::{Event, EventType, TestEvent};
@@ -808,6 +808,7 @@ impl SourceReader for S3SourceReader {
         _encoding: SourceDataEncoding,
         _: Option<Logger>,
         metrics: SourceBaseMetrics,
+        _: Option<PersistentCheckpointsConfig>,
     ) -> Result<(S3SourceReader, Option<PartitionId>), anyhow::Error> {
         let s3_conn = match connector {
             ExternalSourceConnector::S3(s3_conn) => s3_conn,
@@ -895,6 +896,10 @@ impl SourceReader for S3SourceReader {
                     upstream_time_millis: None,
                     key: (),
                     value: record,
+                    topic: None,
+                    headers: None,
+                    filename: None,
+                    records_before: None,
                 }))
             }
             Some(Some(Err(e))) => match e {
@@ -41,7 +41,7 @@ use ore::now::NowFn;
 use repr::Timestamp;
 
 use crate::source::gen::source::{
-    proto_source_timestamp, ProtoAssignedTimestamp, ProtoSourceTimestamp,
+    proto_source_timestamp, ProtoAssignedTimestamp, ProtoKafkaTopicPartition, ProtoSourceTimestamp,
 };
 
 /// This struct holds state for proposed timestamps and
@@ -666,13 +666,20 @@ impl PartialOrd for SourceTimestamp {
                 self.offset.offset.cmp(&other.offset.offset)
             }
             (PartitionId::Kafka(a), PartitionId::Kafka(b)) => a.cmp(b),
+            (PartitionId::KafkaTopic(ta, a), PartitionId::KafkaTopic(tb, b))
+                if ta == tb && a == b =>
+            {
+                self.offset.offset.cmp(&other.offset.offset)
+            }
+            (PartitionId::KafkaTopic(ta, a), PartitionId::KafkaTopic(tb, b)) => {
+                (ta, a).cmp(&(tb, b))
+            }
             (PartitionId::None, PartitionId::None) => self.offset.offset.cmp(&other.offset.offset),
             // We're not using a wildcard pattern here, to make sure this fails when someone adds
             // new types of partition ID.
-            (PartitionId::None, PartitionId::Kafka(_)) => {
-                unreachable!("PartitionId types must match")
-            }
-            (PartitionId::Kafka(_), PartitionId::None) => {
+            (PartitionId::None, PartitionId::Kafka(_) | PartitionId::KafkaTopic(..))
+            | (PartitionId::Kafka(_), PartitionId::None | PartitionId::KafkaTopic(..))
+            | (PartitionId::KafkaTopic(..), PartitionId::None | PartitionId::Kafka(_)) => {
                 unreachable!("PartitionId types must match")
             }
         };
@@ -690,13 +697,20 @@ impl Ord for SourceTimestamp {
                 self.offset.offset.cmp(&other.offset.offset)
             }
             (PartitionId::Kafka(a), PartitionId::Kafka(b)) => a.cmp(b),
+            (PartitionId::KafkaTopic(ta, a), PartitionId::KafkaTopic(tb, b))
+                if ta == tb && a == b =>
+            {
+                self.offset.offset.cmp(&other.offset.offset)
+            }
+            (PartitionId::KafkaTopic(ta, a), PartitionId::KafkaTopic(tb, b)) => {
+                (ta, a).cmp(&(tb, b))
+            }
             (PartitionId::None, PartitionId::None) => self.offset.offset.cmp(&other.offset.offset),
             // We're not using a wildcard pattern here, to make sure this fails when someone adds
             // new types of partition ID.
-            (PartitionId::None, PartitionId::Kafka(_)) => {
-                unreachable!("PartitionId types must match")
-            }
-            (PartitionId::Kafka(_), PartitionId::None) => {
+            (PartitionId::None, PartitionId::Kafka(_) | PartitionId::KafkaTopic(..))
+            | (PartitionId::Kafka(_), PartitionId::None | PartitionId::KafkaTopic(..))
+            | (PartitionId::KafkaTopic(..), PartitionId::None | PartitionId::Kafka(_)) => {
                 unreachable!("PartitionId types must match")
             }
         };
@@ -713,6 +727,12 @@ impl From<&SourceTimestamp> for ProtoSourceTimestamp {
         ProtoSourceTimestamp {
             partition_id: Some(match &x.partition {
                 PartitionId::Kafka(x) => proto_source_timestamp::PartitionId::Kafka(*x),
+                PartitionId::KafkaTopic(topic, partition) => {
+                    proto_source_timestamp::PartitionId::KafkaTopic(ProtoKafkaTopicPartition {
+                        topic: topic.clone(),
+                        partition: *partition,
+                    })
+                }
                 PartitionId::None => proto_source_timestamp::PartitionId::None(()),
             }),
             mz_offset: x.offset.offset,
@@ -726,6 +746,9 @@ impl TryFrom<ProtoSourceTimestamp> for SourceTimestamp {
     fn try_from(x: ProtoSourceTimestamp) -> Result<Self, Self::Error> {
         let partition = match x.partition_id {
             Some(proto_source_timestamp::PartitionId::Kafka(x)) => PartitionId::Kafka(x),
+            Some(proto_source_timestamp::PartitionId::KafkaTopic(x)) => {
+                PartitionId::KafkaTopic(x.topic, x.partition)
+            }
             Some(proto_source_timestamp::PartitionId::None(_)) => PartitionId::None,
             None => return Err("unknown partition_id".into()),
         };
@@ -903,14 +926,19 @@ mod tests {
 
     #[test]
     fn source_timestamp_roundtrip() -> Result<(), String> {
-        let partition = PartitionId::Kafka(42);
-        let offset = MzOffset { offset: 17 };
-        let original = SourceTimestamp { partition, offset };
-        let mut encoded = Vec::new();
-        original.encode(&mut encoded);
-        let decoded = SourceTimestamp::decode(&encoded)?;
-
-        assert_eq!(decoded, original);
+        for partition in [
+            PartitionId::Kafka(42),
+            PartitionId::KafkaTopic("events.us".into(), 42),
+            PartitionId::None,
+        ] {
+            let offset = MzOffset { offset: 17 };
+            let original = SourceTimestamp { partition, offset };
+            let mut encoded = Vec::new();
+            original.encode(&mut encoded);
+            let decoded = SourceTimestamp::decode(&encoded)?;
+
+            assert_eq!(decoded, original);
+        }
 
         Ok(())
     }
@@ -130,10 +130,12 @@ impl fmt::Display for SourceInstanceId {
 
 /// Unique identifier for each part of a whole source.
 ///     Kafka -> partition
+///     KafkaTopic -> topic and partition, for Kafka sources that read from several topics
 ///     None -> sources that have no notion of partitioning (e.g file sources)
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum PartitionId {
     Kafka(i32),
+    KafkaTopic(String, i32),
     None,
 }
 
@@ -141,6 +143,8 @@ impl fmt::Display for PartitionId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             PartitionId::Kafka(id) => write!(f, "{}", id),
+            // Kafka topic names cannot contain a colon, so this is unambiguous.
+            PartitionId::KafkaTopic(topic, id) => write!(f, "{}:{}", topic, id),
             PartitionId::None => write!(f, "none"),
         }
     }
@@ -161,10 +165,16 @@ impl FromStr for PartitionId {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "none" => Ok(PartitionId::None),
-            s => {
-                let val: i32 = s.parse()?;
-                Ok(PartitionId::Kafka(val))
-            }
+            s => match s.rsplit_once(':') {
+                Some((topic, val)) => {
+                    let val: i32 = val.parse()?;
+                    Ok(PartitionId::KafkaTopic(topic.to_string(), val))
+                }
+                None => {
+                    let val: i32 = s.parse()?;
+                    Ok(PartitionId::Kafka(val))
+                }
+            },
         }
     }
 }
@@ -22,7 +22,9 @@ pub use self::encode::{
     encode_datums_as_avro, encode_debezium_transaction_unchecked, get_debezium_transaction_schema,
     AvroEncoder, AvroSchemaGenerator,
 };
-pub use self::envelope_debezium::DebeziumDeduplicationStrategy;
+pub use self::envelope_debezium::{
+    push_coords, DebeziumDeduplicationStrategy, DebeziumSourceCoordinates,
+};
 pub use self::schema::{parse_schema, schema_to_relationdesc, ConfluentAvroResolver};
 
 use self::decode::{AvroStringDecoder, OptionalRecordDecoder, RowWrapper};
@@ -174,4 +176,38 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn debezium_json_coordinates() {
+        let mut filenames = std::collections::HashMap::new();
+        let mut coords = |event: serde_json::Value| {
+            let coords =
+                DebeziumSourceCoordinates::from_json(event.as_object().unwrap(), &mut filenames)
+                    .unwrap();
+            let mut row = repr::Row::default();
+            let known = push_coords(coords, &mut row);
+            (known, row)
+        };
+
+        let (known, row) = coords(serde_json::json!({
+            "op": "c",
+            "source": {"file": "binlog.000002", "pos": 7, "row": 1, "snapshot": false},
+        }));
+        assert!(known);
+        assert_eq!(
+            row.unpack_first().unwrap_list().iter().collect::<Vec<_>>(),
+            vec![Datum::Int32(0), Datum::Int64(7), Datum::Int64(1)]
+        );
+
+        // Snapshot reads have no meaningful coordinates.
+        let (known, row) = coords(serde_json::json!({
+            "op": "r",
+            "source": {"file": "binlog.000002", "pos": 7, "row": 1},
+        }));
+        assert!(known);
+        assert_eq!(row.unpack_first(), Datum::Null);
+
+        let (known, _) = coords(serde_json::json!({"source": {"name": "mystery"}}));
+        assert!(!known);
+    }
 }
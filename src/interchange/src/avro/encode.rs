@@ -92,7 +92,7 @@ fn build_schema(columns: &[(ColumnName, ColumnType)], class_name: Option<&str>)
     Schema::parse(&row_schema).expect("valid schema constructed")
 }
 
-fn encode_avro_header(buf: &mut Vec<u8>, schema_id: i32) {
+pub(super) fn encode_avro_header(buf: &mut Vec<u8>, schema_id: i32) {
     // The first byte is a magic byte (0) that indicates the Confluent
     // serialization format version, and the next four bytes are a
     // 32-bit schema ID.
@@ -10,7 +10,7 @@
 //! Logic for the Avro representation of the CDCv2 protocol.
 
 use mz_avro::schema::{FullName, SchemaNode};
-use repr::{Diff, Row, Timestamp};
+use repr::{ColumnName, ColumnType, Datum, Diff, RelationDesc, Row, Timestamp};
 use serde_json::json;
 
 use anyhow::anyhow;
@@ -18,13 +18,18 @@ use avro_derive::AvroDecodable;
 use differential_dataflow::capture::{Message, Progress};
 use mz_avro::error::{DecodeError, Error as AvroError};
 use mz_avro::schema::Schema;
+use mz_avro::types::Value;
 use mz_avro::{
     define_unexpected, ArrayAsVecDecoder, AvroDecodable, AvroDecode, AvroDeserializer, AvroRead,
     StatefulAvroDecodable,
 };
 use std::{cell::RefCell, rc::Rc};
 
+use super::encode::encode_avro_header;
 use super::RowWrapper;
+use crate::avro::encode_datums_as_avro;
+use crate::encode::{column_names_and_types, Encode};
+use crate::json::build_row_schema_json;
 
 pub fn extract_data_columns<'a>(schema: &'a Schema) -> anyhow::Result<SchemaNode<'a>> {
     let data_name = FullName::from_parts("data", Some("com.materialize.cdc"), "");
@@ -116,12 +121,147 @@ impl AvroDecode for Decoder {
 #[derive(Debug)]
 pub struct Decoder;
 
+/// Collected state to encode update batches and progress statements.
+///
+/// As an implementor of [`Encode`], the encoder expects each value row to
+/// carry the time and the diff of its update as two trailing `Int64` datums,
+/// and encodes it as a batch that contains just that update.
+#[derive(Debug)]
+pub struct Encoder {
+    columns: Vec<(ColumnName, ColumnType)>,
+    schema: Schema,
+    schema_id: i32,
+}
+
+impl Encoder {
+    /// Creates a new CDCv2 encoder from a relation description and the
+    /// Confluent schema ID under which [`Encoder::schema`] was published.
+    pub fn new(desc: RelationDesc, schema_id: i32) -> Self {
+        let schema = build_writer_schema(desc.clone());
+        let columns = column_names_and_types(desc);
+        Self {
+            columns,
+            schema,
+            schema_id,
+        }
+    }
+
+    /// Returns the writer schema of the encoded messages.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Encodes a batch of updates as an Avro value.
+    pub fn encode_updates(&self, updates: &[(Row, Timestamp, Diff)]) -> Value {
+        let mut enc_updates = Vec::new();
+        for (data, time, diff) in updates {
+            let enc_data = encode_datums_as_avro(&**data, &self.columns);
+            let enc_time = Value::Long(*time as i64);
+            let enc_diff = Value::Long(*diff as i64);
+            enc_updates.push(Value::Record(vec![
+                ("data".to_string(), enc_data),
+                ("time".to_string(), enc_time),
+                ("diff".to_string(), enc_diff),
+            ]));
+        }
+        Value::Union {
+            index: 0,
+            inner: Box::new(Value::Array(enc_updates)),
+            n_variants: 2,
+            null_variant: None,
+        }
+    }
+
+    /// Encodes the contents of a progress statement as an Avro value.
+    pub fn encode_progress(
+        &self,
+        lower: &[Timestamp],
+        upper: &[Timestamp],
+        counts: &[(Timestamp, usize)],
+    ) -> Value {
+        let enc_lower = Value::Array(lower.iter().map(|t| Value::Long(*t as i64)).collect());
+        let enc_upper = Value::Array(upper.iter().map(|t| Value::Long(*t as i64)).collect());
+        let enc_counts = Value::Array(
+            counts
+                .iter()
+                .map(|(time, count)| {
+                    Value::Record(vec![
+                        ("time".to_string(), Value::Long(*time as i64)),
+                        ("count".to_string(), Value::Long(*count as i64)),
+                    ])
+                })
+                .collect(),
+        );
+        let enc_progress = Value::Record(vec![
+            ("lower".to_string(), enc_lower),
+            ("upper".to_string(), enc_upper),
+            ("counts".to_string(), enc_counts),
+        ]);
+
+        Value::Union {
+            index: 1,
+            inner: Box::new(enc_progress),
+            n_variants: 2,
+            null_variant: None,
+        }
+    }
+
+    /// Encodes a progress statement in the Confluent wire format.
+    pub fn encode_progress_unchecked(
+        &self,
+        lower: &[Timestamp],
+        upper: &[Timestamp],
+        counts: &[(Timestamp, usize)],
+    ) -> Vec<u8> {
+        self.encode_message_unchecked(self.encode_progress(lower, upper, counts))
+    }
+
+    fn encode_message_unchecked(&self, value: Value) -> Vec<u8> {
+        let mut buf = vec![];
+        encode_avro_header(&mut buf, self.schema_id);
+        mz_avro::encode_unchecked(&value, &self.schema, &mut buf);
+        buf
+    }
+}
+
+impl Encode for Encoder {
+    fn get_format_name(&self) -> &str {
+        "avro-cdcv2"
+    }
+
+    fn encode_key_unchecked(&self, _row: Row) -> Vec<u8> {
+        panic!("CDCv2 messages do not have keys")
+    }
+
+    fn encode_value_unchecked(&self, row: Row) -> Vec<u8> {
+        let mut datums = row.unpack();
+        let (time, diff) = match (datums.pop(), datums.pop()) {
+            (Some(Datum::Int64(diff)), Some(Datum::Int64(time))) => (time, diff),
+            _ => panic!("CDCv2 row without trailing time and diff: {:?}", row),
+        };
+        let data = Row::pack(datums);
+        self.encode_message_unchecked(self.encode_updates(&[(
+            data,
+            time as Timestamp,
+            diff as Diff,
+        )]))
+    }
+}
+
+/// Constructs the schema of CDCv2 messages that carry updates of rows
+/// described by `desc`.
+pub fn build_writer_schema(desc: RelationDesc) -> Schema {
+    let columns = column_names_and_types(desc);
+    build_schema(build_row_schema_json(&columns, "data"))
+}
+
 /// Construct the schema for the CDC V2 protocol.
 pub fn build_schema(row_schema: serde_json::Value) -> Schema {
     let updates_schema = json!({
         "type": "array",
         "items": {
             "name" : "update",
+            "namespace" : "com.materialize.cdc",
             "type" : "record",
             "fields" : [
                 {
@@ -142,6 +282,7 @@ pub fn build_schema(row_schema: serde_json::Value) -> Schema {
 
     let progress_schema = json!({
         "name" : "progress",
+        "namespace" : "com.materialize.cdc",
         "type" : "record",
         "fields" : [
             {
@@ -189,83 +330,9 @@ pub fn build_schema(row_schema: serde_json::Value) -> Schema {
 mod tests {
 
     use super::*;
-    use crate::avro::encode_datums_as_avro;
-    use crate::encode::column_names_and_types;
-    use mz_avro::types::Value;
     use mz_avro::AvroDeserializer;
     use mz_avro::GeneralDeserializer;
-    use repr::{ColumnName, ColumnType, RelationDesc, Row, ScalarType};
-
-    use crate::json::build_row_schema_json;
-
-    /// Collected state to encode update batches and progress statements.
-    #[derive(Debug)]
-    struct Encoder {
-        columns: Vec<(ColumnName, ColumnType)>,
-    }
-
-    impl Encoder {
-        /// Creates a new CDCv2 encoder from a relation description.
-        pub fn new(desc: RelationDesc) -> Self {
-            let columns = column_names_and_types(desc);
-            Self { columns }
-        }
-
-        /// Encodes a batch of updates as an Avro value.
-        pub fn encode_updates(&self, updates: &[(Row, i64, i64)]) -> Value {
-            let mut enc_updates = Vec::new();
-            for (data, time, diff) in updates {
-                let enc_data = encode_datums_as_avro(&**data, &self.columns);
-                let enc_time = Value::Long(time.clone());
-                let enc_diff = Value::Long(diff.clone());
-                enc_updates.push(Value::Record(vec![
-                    ("data".to_string(), enc_data),
-                    ("time".to_string(), enc_time),
-                    ("diff".to_string(), enc_diff),
-                ]));
-            }
-            Value::Union {
-                index: 0,
-                inner: Box::new(Value::Array(enc_updates)),
-                n_variants: 2,
-                null_variant: None,
-            }
-        }
-
-        /// Encodes the contents of a progress statement as an Avro value.
-        pub fn encode_progress(
-            &self,
-            lower: &[i64],
-            upper: &[i64],
-            counts: &[(i64, i64)],
-        ) -> Value {
-            let enc_lower = Value::Array(lower.iter().cloned().map(Value::Long).collect());
-            let enc_upper = Value::Array(upper.iter().cloned().map(Value::Long).collect());
-            let enc_counts = Value::Array(
-                counts
-                    .iter()
-                    .map(|(time, count)| {
-                        Value::Record(vec![
-                            ("time".to_string(), Value::Long(time.clone())),
-                            ("count".to_string(), Value::Long(count.clone())),
-                        ])
-                    })
-                    .collect(),
-            );
-            let enc_progress = Value::Record(vec![
-                ("lower".to_string(), enc_lower),
-                ("upper".to_string(), enc_upper),
-                ("counts".to_string(), enc_counts),
-            ]);
-
-            Value::Union {
-                index: 1,
-                inner: Box::new(enc_progress),
-                n_variants: 2,
-                null_variant: None,
-            }
-        }
-    }
+    use repr::ScalarType;
 
     #[test]
     fn test_roundtrip() {
@@ -273,10 +340,8 @@ mod tests {
             .with_column("id", ScalarType::Int64.nullable(false))
             .with_column("price", ScalarType::Float64.nullable(true));
 
-        let encoder = Encoder::new(desc.clone());
-        let row_schema =
-            build_row_schema_json(&crate::encode::column_names_and_types(desc), "data");
-        let schema = build_schema(row_schema);
+        let encoder = Encoder::new(desc, 1);
+        let schema = encoder.schema().clone();
 
         let values = vec![
             encoder.encode_updates(&[]),
@@ -305,4 +370,25 @@ mod tests {
             Message::Progress(_)
         ),);
     }
+
+    #[test]
+    fn test_encode_value() {
+        let desc = RelationDesc::empty().with_column("id", ScalarType::Int64.nullable(false));
+        let encoder = Encoder::new(desc, 1);
+        let schema = encoder.schema().clone();
+        extract_data_columns(&schema).unwrap();
+
+        let row = Row::pack_slice(&[Datum::Int64(5), Datum::Int64(7), Datum::Int64(-1)]);
+        let bytes = encoder.encode_value_unchecked(row);
+        // Skip the magic byte and the schema ID.
+        let g = GeneralDeserializer {
+            schema: schema.top_node(),
+        };
+        match g.deserialize(&mut &bytes[5..], Decoder).unwrap() {
+            Message::Updates(updates) => {
+                assert_eq!(updates, vec![(Row::pack_slice(&[Datum::Int64(5)]), 7, -1)])
+            }
+            Message::Progress(_) => panic!("expected updates"),
+        }
+    }
 }
@@ -133,6 +133,139 @@ pub struct DebeziumSourceCoordinates {
     pub(super) row: RowCoordinates,
 }
 
+impl DebeziumSourceCoordinates {
+    /// Extracts the coordinates of a JSON-encoded Debezium change event from
+    /// its `source` field, the `total_order` of its `transaction` field, and
+    /// its `op` field, which marks snapshot records as reads.
+    pub fn from_json(
+        event: &serde_json::Map<String, serde_json::Value>,
+        filenames_to_indices: &mut HashMap<Vec<u8>, usize>,
+    ) -> Result<Option<DebeziumSourceCoordinates>, String> {
+        use serde_json::Value as JsonValue;
+
+        let source = match event.get("source") {
+            None | Some(JsonValue::Null) => return Ok(None),
+            Some(JsonValue::Object(source)) => source,
+            Some(other) => return Err(format!("\"source\" is not an object: {}", other)),
+        };
+        let mut snapshot = matches!(event.get("op"), Some(JsonValue::String(op)) if op == "r");
+        let mut fields = SourceFields::default();
+        for (name, val) in source {
+            match (name.as_str(), val) {
+                (_, JsonValue::Null) => (),
+                ("snapshot", JsonValue::Bool(val)) => snapshot |= *val,
+                ("snapshot", JsonValue::String(val)) => match val.as_str() {
+                    "true" | "last" => snapshot = true,
+                    "false" => (),
+                    _ => return Err(format!("`snapshot` had unexpected value {}", val)),
+                },
+                // MySQL
+                ("pos", val) => fields.pos = Some(json_integer(name, val)?),
+                ("row", val) => fields.row = Some(json_integer(name, val)?),
+                ("file", JsonValue::String(file)) => {
+                    let mut file = file.as_bytes().to_vec();
+                    fields.file_idx = Some(intern_filename(filenames_to_indices, &mut file));
+                }
+                // Postgres
+                ("lsn", val) => fields.lsn = Some(json_integer(name, val)?),
+                ("sequence", JsonValue::String(val)) => {
+                    fields.sequence = Some(parse_sequence(val)?)
+                }
+                // SQL Server
+                ("change_lsn", JsonValue::String(val)) => {
+                    fields.change_lsn = Some(
+                        decode_change_lsn(val)
+                            .ok_or_else(|| format!("Couldn't decode MS SQL LSN: {}", val))?,
+                    )
+                }
+                ("event_serial_no", val) => fields.event_serial_no = Some(json_integer(name, val)?),
+                ("snapshot" | "file" | "sequence" | "change_lsn", val) => {
+                    return Err(format!("\"{}\" has unexpected value {}", name, val))
+                }
+                _ => (),
+            }
+        }
+        let mut row = fields.into_row_coordinates()?;
+        if let RowCoordinates::Postgres { total_order, .. } = &mut row {
+            *total_order = match event.get("transaction").and_then(|t| t.get("total_order")) {
+                None | Some(JsonValue::Null) => None,
+                Some(val) => Some(json_integer("total_order", val)? as usize),
+            };
+        }
+        Ok(Some(DebeziumSourceCoordinates { snapshot, row }))
+    }
+}
+
+fn json_integer(name: &str, val: &serde_json::Value) -> Result<i64, String> {
+    val.as_i64()
+        .ok_or_else(|| format!("\"{}\" is not an integer", name))
+}
+
+/// Push `coords` onto `packer`, in a format understood by our downstream Debezium deduplication logic.
+///
+/// Returns false if the coordinates come from an upstream database we don't recognize.
+pub fn push_coords(coords: Option<DebeziumSourceCoordinates>, packer: &mut Row) -> bool {
+    let mut is_unknown = false;
+    match coords {
+        Some(coords) => {
+            if coords.snapshot {
+                packer.push(Datum::Null)
+            } else {
+                // Downstream in the deduplication logic, we pack these into rows,
+                // and aren't too careful to avoid cloning them. Thus
+                // it's important not to go over the 24-byte smallvec inline capacity.
+                let data = match coords.row {
+                    RowCoordinates::Postgres {
+                        last_commit_lsn,
+                        lsn,
+                        total_order,
+                    } => Some(vec![
+                        Datum::Int64(last_commit_lsn.unwrap_or(0) as i64),
+                        Datum::Int64(lsn as i64),
+                        Datum::Int64(total_order.unwrap_or(0) as i64),
+                    ]),
+                    RowCoordinates::MySql { file_idx, pos, row } => Some(vec![
+                        Datum::Int32(file_idx as i32),
+                        Datum::Int64(pos as i64),
+                        Datum::Int64(row as i64),
+                    ]),
+                    RowCoordinates::MSSql {
+                        change_lsn,
+                        event_serial_no,
+                    } => {
+                        // Consider everything but the file ID to be the offset within the file.
+                        let offset_in_file = ((change_lsn.log_block_offset as usize) << 16)
+                            | (change_lsn.slot_num as usize);
+                        Some(vec![
+                            Datum::Int32(change_lsn.file_seq_num as i32),
+                            Datum::Int64(offset_in_file as i64),
+                            Datum::Int64(event_serial_no as i64),
+                        ])
+                    }
+                    RowCoordinates::Unknown => {
+                        is_unknown = true;
+                        None
+                    }
+                };
+                match data {
+                    Some(data) => {
+                        packer.push_list_with(|packer| {
+                            for datum in data {
+                                packer.push(datum);
+                            }
+                        });
+                    }
+                    None => {
+                        packer.push(Datum::Null);
+                    }
+                }
+            }
+        }
+        None => packer.push(Datum::Null),
+    }
+    !is_unknown
+}
+
 #[derive(Debug)]
 pub struct DebeziumTransactionMetadata {
     // The order of the record within the transaction
@@ -212,6 +345,97 @@ impl AvroDecode for AvroDbzSnapshotDecoder {
     }
 }
 
+/// The fields of a Debezium `source` record that locate a change in the
+/// upstream database's log, independent of how the record was encoded.
+#[derive(Debug, Default)]
+struct SourceFields {
+    // Binlog file "pos" and "row" - present in MySQL sources.
+    pos: Option<i64>,
+    row: Option<i64>,
+    file_idx: Option<usize>,
+    // "log sequence number" - monotonically increasing log offset in Postgres
+    lsn: Option<i64>,
+    // Additional sequencing information for Postgres sources
+    sequence: Option<Vec<Option<usize>>>,
+    // SQL Server lsn - 10-byte, hex-encoded value.
+    // and "event_serial_no" - serial number of the event, when there is more than one per LSN.
+    change_lsn: Option<MSSqlLsn>,
+    event_serial_no: Option<i64>,
+}
+
+impl SourceFields {
+    fn into_row_coordinates(self) -> Result<RowCoordinates, String> {
+        let SourceFields {
+            pos,
+            row,
+            file_idx,
+            lsn,
+            sequence,
+            change_lsn,
+            event_serial_no,
+        } = self;
+        let mysql_any = pos.is_some() || row.is_some() || file_idx.is_some();
+        let pg_any = lsn.is_some();
+        let mssql_any = change_lsn.is_some() || event_serial_no.is_some();
+        if (mysql_any as usize) + (pg_any as usize) + (mssql_any as usize) > 1 {
+            return Err("Found source coordinate information for multiple databases - we don't know how to interpret this.".to_string());
+        }
+        Ok(if mysql_any {
+            let pos = pos.ok_or_else(|| "no pos".to_string())? as usize;
+            let row = row.ok_or_else(|| "no row".to_string())? as usize;
+            let file_idx = file_idx.ok_or_else(|| "no binlog filename".to_string())?;
+            RowCoordinates::MySql { file_idx, pos, row }
+        } else if pg_any {
+            let last_commit_lsn = match sequence {
+                Some(sequence) => sequence.get(0).cloned().expect("lastCommitLsn must exist"),
+                None => None,
+            };
+            let lsn = lsn.ok_or_else(|| "no lsn".to_string())? as usize;
+            RowCoordinates::Postgres {
+                last_commit_lsn,
+                lsn,
+                total_order: None,
+            }
+        } else if mssql_any {
+            let change_lsn = change_lsn.ok_or_else(|| "no change_lsn".to_string())?;
+            let event_serial_no =
+                event_serial_no.ok_or_else(|| "no event_serial_no".to_string())? as usize;
+            RowCoordinates::MSSql {
+                change_lsn,
+                event_serial_no,
+            }
+        } else {
+            RowCoordinates::Unknown
+        })
+    }
+}
+
+/// Returns the index of the binlog file named `file`, assigning the next
+/// unused index to file names that have not been seen before.
+fn intern_filename(
+    filenames_to_indices: &mut HashMap<Vec<u8>, usize>,
+    file: &mut Vec<u8>,
+) -> usize {
+    match filenames_to_indices.get(file) {
+        Some(idx) => *idx,
+        None => {
+            let n_files = filenames_to_indices.len();
+            filenames_to_indices.insert(std::mem::take(file), n_files);
+            n_files
+        }
+    }
+}
+
+/// Parses the `sequence` field of Postgres sources, a JSON array of
+/// stringified LSNs.
+fn parse_sequence(val: &str) -> Result<Vec<Option<usize>>, String> {
+    let seq: Vec<Option<String>> = serde_json::from_str(val).map_err(|e| e.to_string())?;
+    seq.into_iter()
+        .map(|s| s.map(|s| s.parse()).transpose())
+        .collect::<Result<_, ParseIntError>>()
+        .map_err(|e| e.to_string())
+}
+
 fn decode_change_lsn(input: &str) -> Option<MSSqlLsn> {
     // SQL Server change LSNs are 10-byte integers. Debezium
     // encodes them as hex, in the following format: xxxxxxxx:xxxxxxxx:xxxx
@@ -279,19 +503,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
         a: &mut A,
     ) -> Result<Self::Out, AvroError> {
         let mut snapshot = false;
-        // Binlog file "pos" and "row" - present in MySQL sources.
-        let mut pos = None;
-        let mut row = None;
-        // "log sequence number" - monotonically increasing log offset in Postgres
-        let mut lsn = None;
-        // Additional sequencing information for Postgres sources
-        let mut sequence: Option<Vec<Option<usize>>> = None;
-        // SQL Server lsn - 10-byte, hex-encoded value.
-        // and "event_serial_no" - serial number of the event, when there is more than one per LSN.
-        let mut change_lsn = None;
-        let mut event_serial_no = None;
-
-        let mut file_idx = None;
+        let mut fields = SourceFields::default();
         while let Some((name, _, field)) = a.next_field()? {
             match name {
                 "snapshot" => {
@@ -307,7 +519,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                     let next = ValueDecoder;
                     let val = field.decode_field(next)?;
 
-                    pos = Some(val.into_integral().ok_or_else(|| {
+                    fields.pos = Some(val.into_integral().ok_or_else(|| {
                         DecodeError::Custom("\"pos\" is not an integer".to_string())
                     })?);
                 }
@@ -315,22 +527,15 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                     let next = ValueDecoder;
                     let val = field.decode_field(next)?;
 
-                    row = Some(val.into_integral().ok_or_else(|| {
+                    fields.row = Some(val.into_integral().ok_or_else(|| {
                         DecodeError::Custom("\"row\" is not an integer".to_string())
                     })?);
                 }
                 "file" => {
                     let d = AvroStringDecoder::with_buf(self.file_buf);
                     field.decode_field(d)?;
-                    file_idx = Some(match self.filenames_to_indices.get(self.file_buf) {
-                        Some(idx) => *idx,
-                        None => {
-                            let n_files = self.filenames_to_indices.len();
-                            self.filenames_to_indices
-                                .insert(std::mem::take(self.file_buf), n_files);
-                            n_files
-                        }
-                    });
+                    fields.file_idx =
+                        Some(intern_filename(self.filenames_to_indices, self.file_buf));
                 }
                 // Postgres
                 "lsn" => {
@@ -340,7 +545,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                         Value::Union { inner, .. } => *inner,
                         val => val,
                     };
-                    lsn = Some(val.into_integral().ok_or_else(|| {
+                    fields.lsn = Some(val.into_integral().ok_or_else(|| {
                         DecodeError::Custom("\"lsn\" is not an integer".to_string())
                     })?);
                 }
@@ -360,13 +565,9 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                             )))
                         }
                     };
-                    let seq: Vec<Option<String>> = serde_json::from_str(&val)
-                        .map_err(|e| AvroError::Decode(DecodeError::Custom(e.to_string())))?;
-                    sequence = Some(
-                        seq.into_iter()
-                            .map(|s| s.map(|s| s.parse()).transpose())
-                            .collect::<Result<_, ParseIntError>>()
-                            .map_err(|e| AvroError::Decode(DecodeError::Custom(e.to_string())))?,
+                    fields.sequence = Some(
+                        parse_sequence(&val)
+                            .map_err(|e| AvroError::Decode(DecodeError::Custom(e)))?,
                     );
                 }
                 // SQL Server
@@ -381,7 +582,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                         Value::Null => {}
                         Value::String(s) => {
                             if let Some(i) = decode_change_lsn(&s) {
-                                change_lsn = Some(i);
+                                fields.change_lsn = Some(i);
                             } else {
                                 return Err(AvroError::Decode(DecodeError::Custom(format!(
                                     "Couldn't decode MS SQL LSN: {}",
@@ -403,7 +604,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                         Value::Union { inner, .. } => *inner,
                         val => val,
                     };
-                    event_serial_no = match val {
+                    fields.event_serial_no = match val {
                         Value::Null => None,
                         Value::Int(i) => Some(i.into()),
                         Value::Long(i) => Some(i),
@@ -420,44 +621,7 @@ impl<'a> AvroDecode for DebeziumSourceDecoder<'a> {
                 }
             }
         }
-        let mysql_any = pos.is_some() || row.is_some() || file_idx.is_some();
-        let pg_any = lsn.is_some();
-        let mssql_any = change_lsn.is_some() || event_serial_no.is_some();
-        if (mysql_any as usize) + (pg_any as usize) + (mssql_any as usize) > 1 {
-            return Err(DecodeError::Custom(
-            "Found source coordinate information for multiple databases - we don't know how to interpret this.".to_string()).into());
-        }
-        let row = if mysql_any {
-            let pos = pos.ok_or_else(|| DecodeError::Custom("no pos".to_string()))? as usize;
-            let row = row.ok_or_else(|| DecodeError::Custom("no row".to_string()))? as usize;
-            let file_idx = file_idx
-                .ok_or_else(|| DecodeError::Custom("no binlog filename".to_string()))?
-                as usize;
-            RowCoordinates::MySql { file_idx, pos, row }
-        } else if pg_any {
-            let last_commit_lsn = match sequence {
-                Some(sequence) => sequence.get(0).cloned().expect("lastCommitLsn must exist"),
-                None => None,
-            };
-            let lsn = lsn.ok_or_else(|| DecodeError::Custom("no lsn".to_string()))? as usize;
-            RowCoordinates::Postgres {
-                last_commit_lsn,
-                lsn,
-                total_order: None,
-            }
-        } else if mssql_any {
-            let change_lsn =
-                change_lsn.ok_or_else(|| DecodeError::Custom("no change_lsn".to_string()))?;
-            let event_serial_no = event_serial_no
-                .ok_or_else(|| DecodeError::Custom("no event_serial_no".to_string()))?
-                as usize;
-            RowCoordinates::MSSql {
-                change_lsn,
-                event_serial_no,
-            }
-        } else {
-            RowCoordinates::Unknown
-        };
+        let row = fields.into_row_coordinates().map_err(DecodeError::Custom)?;
         Ok(DebeziumSourceCoordinates { snapshot, row })
     }
     define_unexpected! {
@@ -52,21 +52,81 @@ pub fn extract_avro_header(buf: &[u8]) -> Result<(i32, &[u8])> {
     extract_schema_id(buf, "avro")
 }
 
-pub fn extract_protobuf_header(buf: &[u8]) -> Result<(i32, &[u8])> {
-    let (schema_id, buf) = extract_schema_id(buf, "protobuf")?;
+/// Extracts the schema ID and the message indexes placed in front of a
+/// serialized Protobuf message by the Confluent stack.
+///
+/// The message indexes are the path to the message's descriptor within the
+/// schema: the index of a top-level message in the file, followed by the index
+/// of each nested message. They are encoded as a zigzag varint count followed
+/// by that many zigzag varints, with a lone `0` as shorthand for `[0]`.
+///
+/// https://docs.confluent.io/platform/current/schema-registry/serdes-develop/index.html#wire-format
+pub fn extract_protobuf_header(buf: &[u8]) -> Result<(i32, Vec<i32>, &[u8])> {
+    let (schema_id, mut buf) = extract_schema_id(buf, "protobuf")?;
 
-    match buf.get(0) {
-        Some(0) => Ok((schema_id, &buf[1..])),
-        Some(message_id) => bail!(
-            "unsupported Confluent-style protobuf message descriptor id: \
-                expected 0, but found: {}. \
-                See https://github.com/MaterializeInc/materialize/issues/9250",
-            message_id
-        ),
-        None => bail!(
-            "Confluent-style protobuf datum is too few bytes: expected a message id after magic \
-            and schema id, got a buffer of length {}",
-            buf.len()
+    let count = read_zigzag_varint(&mut buf)?;
+    let message_indexes = match count {
+        0 => vec![0],
+        n if n < 0 => bail!(
+            "Confluent-style protobuf datum has a negative message index count: {}",
+            n
         ),
+        n => (0..n)
+            .map(|_| read_zigzag_varint(&mut buf))
+            .collect::<Result<_>>()?,
+    };
+    Ok((schema_id, message_indexes, buf))
+}
+
+/// Reads a zigzag-encoded variable length integer from the front of `buf`,
+/// advancing `buf` past it.
+fn read_zigzag_varint(buf: &mut &[u8]) -> Result<i32> {
+    let mut value: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = match buf.split_first() {
+            Some((byte, rest)) => {
+                *buf = rest;
+                *byte
+            }
+            None => bail!("Confluent-style protobuf datum ended inside a message index"),
+        };
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(((value >> 1) as i32) ^ -((value & 1) as i32));
+        }
+    }
+    bail!("Confluent-style protobuf datum has an overlong message index")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_protobuf_header;
+
+    #[test]
+    fn test_extract_protobuf_header() {
+        let header = |indexes: &[u8]| {
+            let mut buf = vec![0, 0, 0, 0, 7];
+            buf.extend(indexes);
+            buf.push(42);
+            buf
+        };
+
+        // A lone zero is shorthand for the first message.
+        let buf = header(&[0]);
+        assert_eq!(
+            extract_protobuf_header(&buf).unwrap(),
+            (7, vec![0], &[42][..])
+        );
+
+        // Two indexes, [1, 2], zigzag encoded.
+        let buf = header(&[4, 2, 4]);
+        assert_eq!(
+            extract_protobuf_header(&buf).unwrap(),
+            (7, vec![1, 2], &[42][..])
+        );
+
+        // Truncated index.
+        let buf = vec![0, 0, 0, 0, 7, 4, 0x80];
+        assert!(extract_protobuf_header(&buf).is_err());
     }
 }
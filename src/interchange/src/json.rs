@@ -10,6 +10,7 @@
 use std::collections::HashSet;
 use std::fmt;
 
+use anyhow::{anyhow, bail};
 use ore::collections::CollectionExt;
 use repr::adt::char;
 use repr::adt::jsonb::JsonbRef;
@@ -118,6 +119,30 @@ where
     serde_json::value::Value::Object(value_fields)
 }
 
+/// Encodes a record of a Debezium transaction metadata topic as JSON, with the
+/// same fields as [`crate::avro::encode_debezium_transaction_unchecked`].
+pub fn encode_debezium_transaction(
+    collection: &str,
+    id: &str,
+    status: &str,
+    message_count: Option<i64>,
+) -> Vec<u8> {
+    let data_collections = message_count.map(|message_count| {
+        json!([{
+            "data_collection": collection,
+            "event_count": message_count,
+        }])
+    });
+    json!({
+        "id": id,
+        "status": status,
+        "event_count": message_count,
+        "data_collections": data_collections,
+    })
+    .to_string()
+    .into_bytes()
+}
+
 pub trait ToJson {
     /// Transforms this value to a JSON value.
     fn json<F: FnMut() -> String>(self, namer: &mut F) -> serde_json::value::Value;
@@ -386,3 +411,61 @@ pub fn build_row_schema_json(
         "name": name
     })
 }
+
+/// Converts a JSON Schema describing an object into the typed columns that a
+/// `FORMAT JSON` source projects out of each message.
+///
+/// Each entry in the schema's `properties` becomes a column. Columns appear in
+/// lexicographic order of their names. A column is nullable unless its
+/// property is listed in `required` and does not admit `null`.
+pub fn json_schema_to_columns(
+    schema: &str,
+) -> Result<Vec<(ColumnName, ColumnType)>, anyhow::Error> {
+    let schema: serde_json::Value =
+        serde_json::from_str(schema).map_err(|e| anyhow!("invalid JSON schema: {}", e))?;
+    if let Some(typ) = schema.get("type") {
+        if typ != "object" {
+            bail!("JSON schema must describe an object, but has type {}", typ);
+        }
+    }
+    let properties = match schema.get("properties") {
+        Some(serde_json::Value::Object(properties)) => properties,
+        _ => bail!("JSON schema must have an object-valued \"properties\" field"),
+    };
+    let required: HashSet<&str> = match schema.get("required") {
+        None => HashSet::new(),
+        Some(serde_json::Value::Array(names)) => names.iter().filter_map(|n| n.as_str()).collect(),
+        Some(_) => bail!("JSON schema \"required\" field must be an array"),
+    };
+
+    let mut columns = vec![];
+    for (name, property) in properties {
+        let types: Vec<&str> = match property.get("type") {
+            None => vec![],
+            Some(serde_json::Value::String(t)) => vec![t],
+            Some(serde_json::Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            Some(other) => bail!("invalid type {} for JSON schema property {}", other, name),
+        };
+        let nullable = !required.contains(name.as_str()) || types.contains(&"null");
+        let non_null: Vec<_> = types.into_iter().filter(|t| *t != "null").collect();
+        let scalar_type = match &non_null[..] {
+            ["boolean"] => ScalarType::Bool,
+            ["integer"] => ScalarType::Int64,
+            ["number"] => ScalarType::Float64,
+            ["string"] => match property.get("format").and_then(|f| f.as_str()) {
+                Some("date") => ScalarType::Date,
+                Some("date-time") => ScalarType::TimestampTz,
+                Some("uuid") => ScalarType::Uuid,
+                _ => ScalarType::String,
+            },
+            // Properties without a single primitive type keep their JSON
+            // structure.
+            _ => ScalarType::Jsonb,
+        };
+        columns.push((
+            ColumnName::from(name.as_str()),
+            scalar_type.nullable(nullable),
+        ));
+    }
+    Ok(columns)
+}
@@ -16,4 +16,5 @@ mod confluent;
 pub mod encode;
 pub mod envelopes;
 pub mod json;
+pub mod parquet;
 pub mod protobuf;
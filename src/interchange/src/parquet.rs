@@ -0,0 +1,688 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Apache Parquet decoding for sources and encoding for sinks.
+
+use std::fmt;
+use std::io::{Cursor, Read, Seek};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use arrow2::array::{Array, BinaryArray, BooleanArray, PrimitiveArray, Utf8Array};
+use arrow2::bitmap::Bitmap;
+use arrow2::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow2::io::parquet::read::RecordReader;
+use arrow2::io::parquet::write::RowGroupIterator;
+use arrow2::record_batch::RecordBatch;
+use arrow2::types::NativeType;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use parquet2::compression::Compression;
+use parquet2::encoding::Encoding;
+use parquet2::write::{write_file, Version, WriteOptions};
+
+use ore::str::StrExt;
+use repr::adt::numeric;
+use repr::{ColumnName, ColumnType, Datum, RelationDesc, Row, ScalarType};
+
+/// Returns the columns of the Parquet file read from `r`, as [`Decoder`]
+/// would decode them.
+pub fn file_columns<R: Read + Seek>(r: R) -> Result<Vec<(ColumnName, ColumnType)>, anyhow::Error> {
+    let reader = RecordReader::try_new(r, None, None, None, None)?;
+    reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| Ok((ColumnName::from(field.name.as_str()), column_type(field)?)))
+        .collect()
+}
+
+/// Returns the type of the column into which values of `field` decode.
+fn column_type(field: &Field) -> Result<ColumnType, anyhow::Error> {
+    let scalar_type = match field.data_type() {
+        DataType::Boolean => ScalarType::Bool,
+        DataType::Int8 | DataType::Int16 => ScalarType::Int16,
+        DataType::Int32 => ScalarType::Int32,
+        DataType::Int64 => ScalarType::Int64,
+        DataType::Float32 => ScalarType::Float32,
+        DataType::Float64 => ScalarType::Float64,
+        DataType::Utf8 | DataType::LargeUtf8 => ScalarType::String,
+        DataType::Binary | DataType::LargeBinary => ScalarType::Bytes,
+        DataType::Date32 => ScalarType::Date,
+        DataType::Timestamp(_, None) => ScalarType::Timestamp,
+        DataType::Timestamp(_, Some(_)) => ScalarType::TimestampTz,
+        DataType::Decimal(precision, scale)
+            if *precision <= numeric::NUMERIC_DATUM_MAX_PRECISION =>
+        {
+            ScalarType::Numeric {
+                scale: Some(u8::try_from(*scale)?),
+            }
+        }
+        other => bail!(
+            "Parquet column {} has unsupported type {:?}",
+            field.name.quoted(),
+            other
+        ),
+    };
+    Ok(scalar_type.nullable(field.is_nullable()))
+}
+
+/// Decodes Parquet files into rows.
+#[derive(Debug)]
+pub struct Decoder {
+    columns: Vec<(ColumnName, ColumnType)>,
+    demanded: Vec<bool>,
+}
+
+impl Decoder {
+    /// Creates a decoder that produces rows of `columns`.
+    ///
+    /// Only the columns for which `demanded` is true are read from each file;
+    /// the remaining columns are filled with placeholder values.
+    pub fn new(columns: Vec<(ColumnName, ColumnType)>, demanded: Vec<bool>) -> Self {
+        assert_eq!(columns.len(), demanded.len());
+        Decoder { columns, demanded }
+    }
+
+    /// Starts decoding the Parquet file in `bytes`, whose rows are then
+    /// decoded one row group at a time by the returned iterator.
+    ///
+    /// The columns of the file are matched to the decoder's columns by name,
+    /// which fails if a demanded column that is not nullable is missing. An
+    /// error in a row group ends the file.
+    pub fn decode_file(&self, bytes: Vec<u8>) -> Result<RowGroups, anyhow::Error> {
+        let fields = RecordReader::try_new(Cursor::new(&bytes), None, None, None, None)?
+            .schema()
+            .fields()
+            .clone();
+
+        // For each of our columns, the index of the matching field in the
+        // file, if that column is demanded.
+        let mut sources = Vec::with_capacity(self.columns.len());
+        for ((name, typ), demanded) in self.columns.iter().zip(&self.demanded) {
+            if !demanded {
+                sources.push(None);
+                continue;
+            }
+            match fields.iter().position(|f| f.name == name.as_str()) {
+                None if typ.nullable => sources.push(None),
+                None => bail!("Parquet file has no column {}", name.as_str().quoted()),
+                Some(i) => {
+                    let found = column_type(&fields[i])?;
+                    if found.scalar_type != typ.scalar_type {
+                        bail!(
+                            "Parquet column {} has type {:?}, but the source expects {:?}",
+                            name.as_str().quoted(),
+                            found.scalar_type,
+                            typ.scalar_type
+                        );
+                    }
+                    sources.push(Some(i));
+                }
+            }
+        }
+
+        // Only read the columns we need. The reader wants at least one column
+        // to determine the number of rows, even if none are demanded.
+        let mut projection: Vec<usize> = sources.iter().flatten().copied().collect();
+        projection.sort_unstable();
+        projection.dedup();
+        if projection.is_empty() && !fields.is_empty() {
+            projection.push(0);
+        }
+
+        let reader = RecordReader::try_new(
+            Cursor::new(bytes),
+            Some(projection.clone()),
+            None,
+            None,
+            None,
+        )?;
+        Ok(RowGroups {
+            reader,
+            columns: self.columns.clone(),
+            fields,
+            sources,
+            projection,
+            packer: Row::default(),
+        })
+    }
+}
+
+/// The rows of a Parquet file, which iterates over the row groups of the file,
+/// decoding each into its rows.
+pub struct RowGroups {
+    reader: RecordReader<Cursor<Vec<u8>>>,
+    columns: Vec<(ColumnName, ColumnType)>,
+    fields: Vec<Field>,
+    /// For each column, the index of the field it is read from, if any.
+    sources: Vec<Option<usize>>,
+    /// The indexes of the fields that are read, in ascending order.
+    projection: Vec<usize>,
+    packer: Row,
+}
+
+impl fmt::Debug for RowGroups {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowGroups")
+            .field("columns", &self.columns)
+            .field("sources", &self.sources)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RowGroups {
+    fn decode(&mut self, batch: RecordBatch) -> Result<Vec<Row>, anyhow::Error> {
+        let arrays = self
+            .sources
+            .iter()
+            .map(|source| {
+                source.map(|i| {
+                    let pos = self.projection.binary_search(&i).expect("projected column");
+                    (&batch.columns()[pos], self.fields[i].data_type())
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut rows = Vec::with_capacity(batch.num_rows());
+        for row in 0..batch.num_rows() {
+            for (array, (name, typ)) in arrays.iter().zip(&self.columns) {
+                match array {
+                    None if typ.nullable => self.packer.push(Datum::Null),
+                    None => self.packer.push(placeholder(&typ.scalar_type)),
+                    Some((array, _)) if array.is_null(row) => {
+                        if !typ.nullable {
+                            self.packer.clear();
+                            bail!(
+                                "Parquet column {} contains a null value, but the source \
+                                 column is not nullable",
+                                name.as_str().quoted()
+                            );
+                        }
+                        self.packer.push(Datum::Null)
+                    }
+                    Some((array, data_type)) => {
+                        if let Err(e) = push_value(&mut self.packer, &***array, data_type, row) {
+                            self.packer.clear();
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+            rows.push(self.packer.finish_and_reuse());
+        }
+        Ok(rows)
+    }
+}
+
+impl Iterator for RowGroups {
+    type Item = Result<Vec<Row>, anyhow::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The reader produces one batch per row group.
+        let batch = self.reader.next()?;
+        Some(
+            batch
+                .map_err(Into::into)
+                .and_then(|batch| self.decode(batch)),
+        )
+    }
+}
+
+/// Pushes the value at `row` of `array`, whose type is `data_type`.
+fn push_value(
+    packer: &mut Row,
+    array: &dyn Array,
+    data_type: &DataType,
+    row: usize,
+) -> Result<(), anyhow::Error> {
+    fn downcast<'a, A: 'static>(array: &'a dyn Array) -> Result<&'a A, anyhow::Error> {
+        array
+            .as_any()
+            .downcast_ref::<A>()
+            .ok_or_else(|| anyhow!("unexpected Parquet array type {:?}", array.data_type()))
+    }
+
+    match data_type {
+        DataType::Boolean => packer.push(Datum::from(downcast::<BooleanArray>(array)?.value(row))),
+        DataType::Int8 => packer.push(Datum::Int16(i16::from(
+            downcast::<PrimitiveArray<i8>>(array)?.value(row),
+        ))),
+        DataType::Int16 => packer.push(Datum::Int16(
+            downcast::<PrimitiveArray<i16>>(array)?.value(row),
+        )),
+        DataType::Int32 => packer.push(Datum::Int32(
+            downcast::<PrimitiveArray<i32>>(array)?.value(row),
+        )),
+        DataType::Int64 => packer.push(Datum::Int64(
+            downcast::<PrimitiveArray<i64>>(array)?.value(row),
+        )),
+        DataType::Float32 => packer.push(Datum::from(
+            downcast::<PrimitiveArray<f32>>(array)?.value(row),
+        )),
+        DataType::Float64 => packer.push(Datum::from(
+            downcast::<PrimitiveArray<f64>>(array)?.value(row),
+        )),
+        DataType::Utf8 => packer.push(Datum::String(downcast::<Utf8Array<i32>>(array)?.value(row))),
+        DataType::LargeUtf8 => {
+            packer.push(Datum::String(downcast::<Utf8Array<i64>>(array)?.value(row)))
+        }
+        DataType::Binary => packer.push(Datum::Bytes(
+            downcast::<BinaryArray<i32>>(array)?.value(row),
+        )),
+        DataType::LargeBinary => packer.push(Datum::Bytes(
+            downcast::<BinaryArray<i64>>(array)?.value(row),
+        )),
+        DataType::Date32 => {
+            let days = downcast::<PrimitiveArray<i32>>(array)?.value(row);
+            packer.push(Datum::Date(
+                NaiveDate::from_ymd(1970, 1, 1) + Duration::days(i64::from(days)),
+            ))
+        }
+        DataType::Timestamp(unit, tz) => {
+            let t = downcast::<PrimitiveArray<i64>>(array)?.value(row);
+            let (per_second, nanos_per_unit) = match unit {
+                TimeUnit::Second => (1, 1_000_000_000),
+                TimeUnit::Millisecond => (1_000, 1_000_000),
+                TimeUnit::Microsecond => (1_000_000, 1_000),
+                TimeUnit::Nanosecond => (1_000_000_000, 1),
+            };
+            let ts = NaiveDateTime::from_timestamp_opt(
+                t.div_euclid(per_second),
+                u32::try_from(t.rem_euclid(per_second) * nanos_per_unit)?,
+            )
+            .ok_or_else(|| anyhow!("Parquet timestamp {} is out of range", t))?;
+            match tz {
+                None => packer.push(Datum::Timestamp(ts)),
+                Some(_) => packer.push(Datum::TimestampTz(DateTime::from_utc(ts, Utc))),
+            }
+        }
+        DataType::Decimal(_, scale) => {
+            let unscaled = downcast::<PrimitiveArray<i128>>(array)?.value(row);
+            let n = numeric::twos_complement_be_to_numeric(
+                &mut unscaled.to_be_bytes(),
+                u8::try_from(*scale)?,
+            )?;
+            packer.push(Datum::from(n))
+        }
+        other => bail!("unsupported Parquet type {:?}", other),
+    }
+    Ok(())
+}
+
+/// Returns a value of type `typ` to stand in for a column that was not read.
+fn placeholder(typ: &ScalarType) -> Datum<'static> {
+    match typ {
+        ScalarType::Bool => Datum::False,
+        ScalarType::Int16 => Datum::Int16(0),
+        ScalarType::Int32 => Datum::Int32(0),
+        ScalarType::Int64 => Datum::Int64(0),
+        ScalarType::Float32 => Datum::from(0f32),
+        ScalarType::Float64 => Datum::from(0f64),
+        ScalarType::String => Datum::String(""),
+        ScalarType::Bytes => Datum::Bytes(&[]),
+        ScalarType::Date => Datum::Date(NaiveDate::from_ymd(1970, 1, 1)),
+        ScalarType::Timestamp => Datum::Timestamp(NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0)),
+        ScalarType::TimestampTz => Datum::TimestampTz(DateTime::from_utc(
+            NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0),
+            Utc,
+        )),
+        ScalarType::Numeric { .. } => Datum::from(numeric::Numeric::zero()),
+        _ => Datum::Null,
+    }
+}
+
+/// Encodes rows into Parquet files.
+#[derive(Debug)]
+pub struct Encoder {
+    schema: Arc<Schema>,
+    types: Vec<ScalarType>,
+}
+
+impl Encoder {
+    /// Creates an encoder that writes rows of `desc`, with one Parquet column
+    /// per column of `desc`.
+    ///
+    /// Numeric values are written as text, as Parquet decimals cannot
+    /// represent every numeric value. Fails if any column has a type that
+    /// cannot be written to Parquet.
+    pub fn new(desc: &RelationDesc) -> Result<Self, anyhow::Error> {
+        let fields = desc
+            .iter()
+            .map(|(name, typ)| {
+                Ok(Field::new(
+                    name.as_str(),
+                    data_type(name, &typ.scalar_type)?,
+                    typ.nullable,
+                ))
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        Ok(Encoder {
+            schema: Arc::new(Schema::new(fields)),
+            types: desc
+                .iter_types()
+                .map(|typ| typ.scalar_type.clone())
+                .collect(),
+        })
+    }
+
+    /// Encodes `rows` into a single Parquet file.
+    pub fn encode_file(&self, rows: &[Row]) -> Result<Vec<u8>, anyhow::Error> {
+        let rows = rows.iter().map(|row| row.unpack()).collect::<Vec<_>>();
+        let arrays = self
+            .types
+            .iter()
+            .enumerate()
+            .map(|(i, typ)| array(typ, rows.iter().map(|datums| datums[i])))
+            .collect();
+        let batch = RecordBatch::try_new(Arc::clone(&self.schema), arrays)?;
+
+        let options = WriteOptions {
+            write_statistics: true,
+            compression: Compression::Uncompressed,
+            version: Version::V2,
+        };
+        let encodings = vec![Encoding::Plain; self.schema.fields().len()];
+        let row_groups = RowGroupIterator::try_new(
+            std::iter::once(Ok(batch)),
+            &self.schema,
+            options,
+            encodings,
+        )?;
+        let parquet_schema = row_groups.parquet_schema().clone();
+        let mut buf = vec![];
+        write_file(&mut buf, row_groups, parquet_schema, options, None, None)?;
+        Ok(buf)
+    }
+}
+
+/// Returns the Parquet type into which values of `typ` encode.
+fn data_type(name: &ColumnName, typ: &ScalarType) -> Result<DataType, anyhow::Error> {
+    Ok(match typ {
+        ScalarType::Bool => DataType::Boolean,
+        ScalarType::Int16 => DataType::Int16,
+        ScalarType::Int32 => DataType::Int32,
+        ScalarType::Int64 => DataType::Int64,
+        ScalarType::Float32 => DataType::Float32,
+        ScalarType::Float64 => DataType::Float64,
+        ScalarType::Numeric { .. } | ScalarType::String => DataType::Utf8,
+        ScalarType::Bytes => DataType::Binary,
+        ScalarType::Date => DataType::Date32,
+        ScalarType::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ScalarType::TimestampTz => {
+            DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into()))
+        }
+        other => bail!(
+            "column {} has type {:?}, which cannot be written to Parquet",
+            name.as_str().quoted(),
+            other
+        ),
+    })
+}
+
+/// Builds the Parquet array holding `datums`, which are of type `typ`.
+fn array<'a, I>(typ: &ScalarType, datums: I) -> Arc<dyn Array>
+where
+    I: Iterator<Item = Datum<'a>>,
+{
+    fn primitive<'a, T, I, F>(data_type: DataType, datums: I, f: F) -> Arc<dyn Array>
+    where
+        T: NativeType + Default,
+        I: Iterator<Item = Datum<'a>>,
+        F: Fn(Datum<'a>) -> T,
+    {
+        let mut values = vec![];
+        let mut validity = vec![];
+        for datum in datums {
+            validity.push(!datum.is_null());
+            values.push(if datum.is_null() {
+                T::default()
+            } else {
+                f(datum)
+            });
+        }
+        Arc::new(PrimitiveArray::from_data(
+            data_type,
+            values.into(),
+            Some(validity.into_iter().collect::<Bitmap>()),
+        ))
+    }
+
+    fn timestamp_micros(ts: NaiveDateTime) -> i64 {
+        ts.timestamp() * 1_000_000 + i64::from(ts.timestamp_subsec_micros())
+    }
+
+    let non_null = |datum: Datum<'a>| (!datum.is_null()).then(|| datum);
+    match typ {
+        ScalarType::Bool => Arc::new(BooleanArray::from(
+            datums
+                .map(|d| non_null(d).map(|d| d.unwrap_bool()))
+                .collect::<Vec<_>>(),
+        )),
+        ScalarType::Int16 => primitive(DataType::Int16, datums, |d| d.unwrap_int16()),
+        ScalarType::Int32 => primitive(DataType::Int32, datums, |d| d.unwrap_int32()),
+        ScalarType::Int64 => primitive(DataType::Int64, datums, |d| d.unwrap_int64()),
+        ScalarType::Float32 => primitive(DataType::Float32, datums, |d| d.unwrap_float32()),
+        ScalarType::Float64 => primitive(DataType::Float64, datums, |d| d.unwrap_float64()),
+        ScalarType::Numeric { .. } => Arc::new(Utf8Array::<i32>::from(
+            datums
+                .map(|d| non_null(d).map(|d| d.unwrap_numeric().0.to_standard_notation_string()))
+                .collect::<Vec<_>>(),
+        )),
+        ScalarType::String => Arc::new(Utf8Array::<i32>::from(
+            datums
+                .map(|d| non_null(d).map(|d| d.unwrap_str()))
+                .collect::<Vec<_>>(),
+        )),
+        ScalarType::Bytes => Arc::new(BinaryArray::<i32>::from(
+            datums
+                .map(|d| non_null(d).map(|d| d.unwrap_bytes()))
+                .collect::<Vec<_>>(),
+        )),
+        ScalarType::Date => primitive(DataType::Date32, datums, |d| {
+            let days = (d.unwrap_date() - NaiveDate::from_ymd(1970, 1, 1)).num_days();
+            i32::try_from(days).expect("dates fit in 32 bits of days")
+        }),
+        ScalarType::Timestamp => primitive(
+            DataType::Timestamp(TimeUnit::Microsecond, None),
+            datums,
+            |d| timestamp_micros(d.unwrap_timestamp()),
+        ),
+        ScalarType::TimestampTz => primitive(
+            DataType::Timestamp(TimeUnit::Microsecond, Some("+00:00".into())),
+            datums,
+            |d| timestamp_micros(d.unwrap_timestamptz().naive_utc()),
+        ),
+        other => unreachable!("{:?} is rejected by Encoder::new", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow2::datatypes::Schema;
+    use arrow2::io::parquet::write::RowGroupIterator;
+    use arrow2::record_batch::RecordBatch;
+    use parquet2::compression::Compression;
+    use parquet2::encoding::Encoding;
+    use parquet2::write::{write_file, Version, WriteOptions};
+
+    use super::*;
+
+    fn write_parquet(batches: Vec<RecordBatch>) -> Vec<u8> {
+        let schema = batches[0].schema().clone();
+        let options = WriteOptions {
+            write_statistics: false,
+            compression: Compression::Uncompressed,
+            version: Version::V2,
+        };
+        let encodings = vec![Encoding::Plain; schema.fields().len()];
+        let row_groups =
+            RowGroupIterator::try_new(batches.into_iter().map(Ok), &schema, options, encodings)
+                .unwrap();
+        let parquet_schema = row_groups.parquet_schema().clone();
+        let mut buf = vec![];
+        write_file(&mut buf, row_groups, parquet_schema, options, None, None).unwrap();
+        buf
+    }
+
+    fn test_batch(ids: Vec<i64>, names: &[Option<&str>]) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(PrimitiveArray::from_data(DataType::Int64, ids.into(), None)),
+                Arc::new(Utf8Array::<i32>::from(names)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn test_file() -> Vec<u8> {
+        write_parquet(vec![test_batch(vec![1, 2], &[Some("a"), None])])
+    }
+
+    fn decode(decoder: &Decoder, file: &[u8]) -> Result<Vec<Row>, anyhow::Error> {
+        let row_groups = decoder
+            .decode_file(file.to_vec())?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(row_groups.concat())
+    }
+
+    #[test]
+    fn test_file_columns() {
+        let columns = file_columns(Cursor::new(test_file())).unwrap();
+        assert_eq!(
+            columns,
+            vec![
+                (ColumnName::from("id"), ScalarType::Int64.nullable(false)),
+                (ColumnName::from("name"), ScalarType::String.nullable(true)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        let file = test_file();
+        let columns = file_columns(Cursor::new(&file)).unwrap();
+
+        let decoder = Decoder::new(columns.clone(), vec![true, true]);
+        assert_eq!(
+            decode(&decoder, &file).unwrap(),
+            vec![
+                Row::pack_slice(&[Datum::Int64(1), Datum::String("a")]),
+                Row::pack_slice(&[Datum::Int64(2), Datum::Null]),
+            ]
+        );
+
+        // Columns that are not demanded are not read.
+        let decoder = Decoder::new(columns, vec![true, false]);
+        assert_eq!(
+            decode(&decoder, &file).unwrap(),
+            vec![
+                Row::pack_slice(&[Datum::Int64(1), Datum::Null]),
+                Row::pack_slice(&[Datum::Int64(2), Datum::Null]),
+            ]
+        );
+
+        // Columns are matched by name, and a missing non-nullable column is
+        // an error.
+        let decoder = Decoder::new(
+            vec![(
+                ColumnName::from("missing"),
+                ScalarType::Int64.nullable(false),
+            )],
+            vec![true],
+        );
+        assert!(decode(&decoder, &file).is_err());
+
+        // A null in a column that is not nullable is an error.
+        let decoder = Decoder::new(
+            vec![(ColumnName::from("name"), ScalarType::String.nullable(false))],
+            vec![true],
+        );
+        assert!(decode(&decoder, &file).is_err());
+    }
+
+    #[test]
+    fn test_decode_row_groups() {
+        let file = write_parquet(vec![
+            test_batch(vec![1, 2], &[Some("a"), Some("b")]),
+            test_batch(vec![3], &[None]),
+        ]);
+        let columns = file_columns(Cursor::new(&file)).unwrap();
+
+        // Each row group is decoded on its own.
+        let decoder = Decoder::new(columns, vec![true, true]);
+        let row_groups = decoder
+            .decode_file(file.clone())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            row_groups,
+            vec![
+                vec![
+                    Row::pack_slice(&[Datum::Int64(1), Datum::String("a")]),
+                    Row::pack_slice(&[Datum::Int64(2), Datum::String("b")]),
+                ],
+                vec![Row::pack_slice(&[Datum::Int64(3), Datum::Null])],
+            ]
+        );
+
+        // An error in a later row group doesn't affect the earlier ones.
+        let decoder = Decoder::new(
+            vec![(ColumnName::from("name"), ScalarType::String.nullable(false))],
+            vec![true],
+        );
+        let mut row_groups = decoder.decode_file(file).unwrap();
+        assert_eq!(
+            row_groups.next().unwrap().unwrap(),
+            vec![
+                Row::pack_slice(&[Datum::String("a")]),
+                Row::pack_slice(&[Datum::String("b")]),
+            ]
+        );
+        assert!(row_groups.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        let desc = RelationDesc::empty()
+            .with_column("id", ScalarType::Int64.nullable(false))
+            .with_column("name", ScalarType::String.nullable(true))
+            .with_column("at", ScalarType::Date.nullable(true));
+        let rows = vec![
+            Row::pack_slice(&[
+                Datum::Int64(1),
+                Datum::String("a"),
+                Datum::Date(NaiveDate::from_ymd(2022, 1, 31)),
+            ]),
+            Row::pack_slice(&[Datum::Int64(2), Datum::Null, Datum::Null]),
+        ];
+        let file = Encoder::new(&desc).unwrap().encode_file(&rows).unwrap();
+
+        // Files round trip through the decoder.
+        let columns = file_columns(Cursor::new(&file)).unwrap();
+        assert_eq!(
+            columns,
+            desc.iter()
+                .map(|(name, typ)| (name.clone(), typ.clone()))
+                .collect::<Vec<_>>()
+        );
+        let decoder = Decoder::new(columns, vec![true, true, true]);
+        assert_eq!(decode(&decoder, &file).unwrap(), rows);
+
+        // Types that Parquet cannot represent are rejected up front.
+        let desc = RelationDesc::empty().with_column("i", ScalarType::Interval.nullable(false));
+        assert!(Encoder::new(&desc).is_err());
+    }
+}
@@ -11,10 +11,12 @@ use std::collections::HashSet;
 
 use anyhow::{anyhow, bail, Context};
 
+use prost::Message;
 use prost_reflect::{
     Cardinality, DynamicMessage, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor,
     ReflectMessage, Value,
 };
+use prost_types::{DescriptorProto, FileDescriptorSet};
 
 use ore::str::StrExt;
 use repr::{ColumnName, ColumnType, Datum, Row, ScalarType};
@@ -25,6 +27,9 @@ pub struct DecodedDescriptors {
     message_descriptor: MessageDescriptor,
     columns: Vec<(ColumnName, ColumnType)>,
     message_name: String,
+    /// The path to the message within its file, as used by the Confluent wire
+    /// format.
+    message_indexes: Vec<i32>,
 }
 
 impl DecodedDescriptors {
@@ -38,6 +43,16 @@ impl DecodedDescriptors {
                 message_name.quoted(),
             )
         })?;
+        let message_indexes = file_messages(bytes, None)?
+            .into_iter()
+            .find(|(name, _)| name == message_name.trim_start_matches('.'))
+            .map(|(_, indexes)| indexes)
+            .ok_or_else(|| {
+                anyhow!(
+                    "protobuf message {} not found in file descriptor set",
+                    message_name.quoted(),
+                )
+            })?;
         let mut seen_messages = HashSet::new();
         seen_messages.insert(message_descriptor.name().to_owned());
         let mut columns = vec![];
@@ -50,6 +65,7 @@ impl DecodedDescriptors {
             message_descriptor,
             columns,
             message_name,
+            message_indexes,
         })
     }
 
@@ -103,7 +119,20 @@ impl Decoder {
             // allocations).
             //
             // [0]: https://developers.google.com/protocol-buffers/docs/overview
-            let (_schema_id, adjusted_bytes) = crate::confluent::extract_protobuf_header(bytes)?;
+            //
+            // The message indexes, however, must identify the message we were
+            // asked to decode, as a schema may declare several messages.
+            let (_schema_id, message_indexes, adjusted_bytes) =
+                crate::confluent::extract_protobuf_header(bytes)?;
+            if message_indexes != self.descriptors.message_indexes {
+                bail!(
+                    "Confluent-style protobuf datum has message indexes {:?}, but message {} \
+                     has message indexes {:?}",
+                    message_indexes,
+                    self.descriptors.message_name.quoted(),
+                    self.descriptors.message_indexes,
+                );
+            }
             bytes = adjusted_bytes;
         }
         let message = DynamicMessage::decode(self.descriptors.message_descriptor.clone(), bytes)?;
@@ -112,6 +141,45 @@ impl Decoder {
     }
 }
 
+/// Lists the messages declared in an encoded `FileDescriptorSet`, including
+/// nested messages.
+///
+/// Each message is described by its fully qualified name and its message
+/// indexes, i.e., the path of indexes that leads to its descriptor within its
+/// file. If `file_name` is specified, only the messages declared in that file
+/// are listed.
+pub fn file_messages(
+    bytes: &[u8],
+    file_name: Option<&str>,
+) -> Result<Vec<(String, Vec<i32>)>, anyhow::Error> {
+    fn collect(
+        prefix: &str,
+        messages: &[DescriptorProto],
+        path: &mut Vec<i32>,
+        out: &mut Vec<(String, Vec<i32>)>,
+    ) {
+        for (i, message) in messages.iter().enumerate() {
+            let name = match prefix {
+                "" => message.name().to_owned(),
+                _ => format!("{}.{}", prefix, message.name()),
+            };
+            path.push(i32::try_from(i).expect("protobuf files have fewer than 2^31 messages"));
+            out.push((name.clone(), path.clone()));
+            collect(&name, &message.nested_type, path, out);
+            path.pop();
+        }
+    }
+
+    let fds = FileDescriptorSet::decode(bytes).context("decoding file descriptor set")?;
+    let mut out = vec![];
+    for file in &fds.file {
+        if file_name.map_or(true, |n| n == file.name()) {
+            collect(file.package(), &file.message_type, &mut vec![], &mut out);
+        }
+    }
+    Ok(out)
+}
+
 fn derive_column_type(
     seen_messages: &mut HashSet<String>,
     field: &FieldDescriptor,
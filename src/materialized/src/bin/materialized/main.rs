@@ -39,7 +39,10 @@ use anyhow::{bail, Context};
 use backtrace::Backtrace;
 use chrono::Utc;
 use clap::{AppSettings, Parser};
-use coord::{PersistConfig, PersistFileStorage, PersistStorage};
+use coord::{
+    BatchCompression, BlobCacheConfig, CompactionConfig, EncryptionKey, Keyring, LeveledPolicy,
+    PersistConfig, PersistFileStorage, PersistStorage,
+};
 use fail::FailScenario;
 use itertools::Itertools;
 use lazy_static::lazy_static;
@@ -155,6 +158,67 @@ struct Args {
     #[structopt(long, hide = true)]
     persistent_kafka_upsert_source: bool,
 
+    /// Enable persistent Kinesis sources. Has to be used with --experimental.
+    #[structopt(long, hide = true)]
+    persistent_kinesis_source: bool,
+
+    /// Enable persistent file sources. Has to be used with --experimental.
+    #[structopt(long, hide = true)]
+    persistent_file_source: bool,
+
+    /// The codec used to compress persisted data: none, zstd, lz4, or snappy.
+    ///
+    /// Data written with a different codec, e.g. before this flag was changed,
+    /// remains readable.
+    #[clap(long, hide = true, value_name = "CODEC", default_value = "zstd")]
+    persist_compression: BatchCompression,
+
+    /// Encrypt persisted data at rest with the hex-encoded 256-bit key in
+    /// PATH, identified by ID. Has to be used with --experimental.
+    ///
+    /// May be specified multiple times to rotate keys: the first key encrypts
+    /// newly persisted data and the others are only used to read data that was
+    /// persisted before the rotation. Run `persistcli rotate` while stopped to
+    /// re-encrypt old data with the first key, and `persistcli encrypt` to
+    /// encrypt data that was persisted before encryption was enabled.
+    #[clap(long, hide = true, value_name = "ID=PATH")]
+    persist_encryption_key: Vec<String>,
+
+    /// Limit on the approximate size of the persisted data that is cached in
+    /// memory.
+    #[clap(long, hide = true, value_name = "BYTES")]
+    persist_cache_memory_limit: Option<u64>,
+
+    /// Cache up to this much persisted data on local disk, in the data
+    /// directory, so that data evicted from memory doesn't have to be fetched
+    /// from --persist-storage again. With --persist-encryption-key, the data
+    /// cached on disk is encrypted as well.
+    #[clap(long, hide = true, value_name = "BYTES")]
+    persist_cache_disk_limit: Option<u64>,
+
+    /// Compact persisted data so that each batch is at least FANOUT times the
+    /// size of the next newer one, instead of merging batches of similar size.
+    ///
+    /// This keeps fewer batches around to be read at the cost of rewriting
+    /// data more often.
+    #[clap(long, hide = true, value_name = "FANOUT")]
+    persist_compaction_leveled_fanout: Option<u64>,
+
+    /// Limit on the number of compactions of persisted data running at once.
+    #[clap(long, hide = true, value_name = "N")]
+    persist_compaction_max_concurrent: Option<usize>,
+
+    /// Limit on the rate at which persisted data is read for compaction, in
+    /// bytes per second.
+    #[clap(long, hide = true, value_name = "BYTES")]
+    persist_compaction_rate_limit: Option<u64>,
+
+    /// Commit persisted data with compare-and-set in --persist-storage, so
+    /// that a process with no local state can take over from another one.
+    /// Not supported with S3 storage, which has no conditional writes.
+    #[clap(long, hide = true)]
+    persist_blob_consensus: bool,
+
     // === Timely worker configuration. ===
     /// Number of dataflow worker threads.
     #[clap(short, long, env = "MZ_WORKERS", value_name = "N", default_value_t)]
@@ -726,6 +790,30 @@ dataflow workers: {workers}",
                 false
             };
 
+        let kinesis_source_enabled = if args.experimental && args.persistent_kinesis_source {
+            true
+        } else if args.persistent_kinesis_source {
+            bail!("cannot specify --persistent-kinesis-source without --experimental");
+        } else {
+            false
+        };
+
+        let file_source_enabled = if args.experimental && args.persistent_file_source {
+            true
+        } else if args.persistent_file_source {
+            bail!("cannot specify --persistent-file-source without --experimental");
+        } else {
+            false
+        };
+
+        let blob_consensus = if args.experimental && args.persist_blob_consensus {
+            true
+        } else if args.persist_blob_consensus {
+            bail!("cannot specify --persist-blob-consensus without --experimental");
+        } else {
+            false
+        };
+
         let lock_info = format!(
             "materialized {mz_version}\nos: {os}\nstart time: {start_time}\nnum workers: {num_workers}\n",
             mz_version = materialized::BUILD_INFO.human_version(),
@@ -741,14 +829,64 @@ dataflow workers: {workers}",
         // latency vs resource usage, so for simplicity we reuse it here."
         let min_step_interval = args.timestamp_frequency;
 
+        let encryption = if args.persist_encryption_key.is_empty() {
+            None
+        } else if !args.experimental {
+            bail!("cannot specify --persist-encryption-key without --experimental");
+        } else {
+            let mut keys = args
+                .persist_encryption_key
+                .iter()
+                .map(|arg| {
+                    let (id, path) = match arg.split_once('=') {
+                        Some(x) => x,
+                        None => bail!("--persist-encryption-key must be of the form ID=PATH"),
+                    };
+                    let key = fs::read_to_string(path)
+                        .with_context(|| format!("reading encryption key {}", id))?;
+                    let key = hex::decode(key.trim())
+                        .with_context(|| format!("decoding encryption key {}", id))?;
+                    Ok(EncryptionKey::new(id.to_owned(), &key)?)
+                })
+                .collect::<Result<Vec<_>, anyhow::Error>>()?;
+            let current = keys.remove(0);
+            Some(Keyring::new(current, keys)?)
+        };
+
+        let mut cache = BlobCacheConfig::default();
+        if let Some(limit_bytes) = args.persist_cache_memory_limit {
+            cache = cache.with_memory_limit(limit_bytes);
+        }
+        if let Some(limit_bytes) = args.persist_cache_disk_limit {
+            cache = cache.with_disk(data_directory.join("persist").join("cache"), limit_bytes);
+        }
+
+        let mut compaction = CompactionConfig::default();
+        if let Some(fanout) = args.persist_compaction_leveled_fanout {
+            compaction = compaction.with_policy(Arc::new(LeveledPolicy::new(fanout)?));
+        }
+        if let Some(max_concurrent) = args.persist_compaction_max_concurrent {
+            compaction = compaction.with_max_concurrent(max_concurrent);
+        }
+        if let Some(bytes_per_second) = args.persist_compaction_rate_limit {
+            compaction = compaction.with_rate_limit(bytes_per_second);
+        }
+
         PersistConfig {
             runtime: Some(runtime.clone()),
             storage,
             user_table_enabled,
             system_table_enabled,
             kafka_upsert_source_enabled,
+            kinesis_source_enabled,
+            file_source_enabled,
             lock_info,
             min_step_interval,
+            compression: args.persist_compression,
+            encryption,
+            cache,
+            compaction,
+            blob_consensus,
         }
     };
 
@@ -11,7 +11,8 @@
 //!
 //! materialized embeds an HTTP server for introspection into the running
 //! process. At the moment, its primary exports are Prometheus metrics, heap
-//! profiles, and catalog dumps.
+//! profiles, and catalog dumps. It also accepts the requests that feed webhook
+//! sources.
 
 use std::net::SocketAddr;
 use std::pin::Pin;
@@ -40,6 +41,7 @@ mod prof;
 mod root;
 mod sql;
 mod util;
+mod webhook;
 
 const SYSTEM_USER: &str = "mz_system";
 
@@ -150,6 +152,10 @@ impl Server {
                 .ok_or("invalid user name in client certificate"),
         };
 
+        // Only client certificates prove who the user is. Webhook sources
+        // that do not validate signatures insist on that.
+        let authenticated = matches!(self.tls_mode(), Some(TlsMode::AssumeUser));
+
         let svc = service::service_fn(move |req| {
             let user = user.clone();
             let coord_client = self.coord_client.clone();
@@ -192,6 +198,9 @@ impl Server {
                     }
                     (&Method::POST, "/prof") => prof::handle_prof(req, &mut coord_client).await,
                     (&Method::POST, "/sql") => sql::handle_sql(req, &mut coord_client).await,
+                    (&Method::POST, path) if path.starts_with(webhook::PATH_PREFIX) => {
+                        webhook::handle_webhook(req, &mut coord_client, authenticated).await
+                    }
                     (&Method::GET, "/internal/catalog") => {
                         catalog::handle_internal_catalog(req, &mut coord_client).await
                     }
@@ -0,0 +1,84 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Webhook source HTTP endpoint.
+
+use std::collections::HashMap;
+
+use hyper::{Body, Request, Response, StatusCode};
+use percent_encoding::percent_decode_str;
+
+use coord::CoordError;
+use sql::names::PartialName;
+
+use crate::http::util;
+
+/// The path prefix of the endpoint, which is followed by the database, schema,
+/// and name of the source.
+pub const PATH_PREFIX: &str = "/api/webhook/";
+
+pub async fn handle_webhook(
+    req: Request<Body>,
+    coord_client: &mut coord::SessionClient,
+    authenticated: bool,
+) -> Result<Response<Body>, anyhow::Error> {
+    let name = match parse_source_name(req.uri().path()) {
+        Some(name) => name,
+        None => {
+            return Ok(util::error_response(
+                StatusCode::NOT_FOUND,
+                format!("expected {}<database>/<schema>/<source>", PATH_PREFIX),
+            ))
+        }
+    };
+    // Header names are already lowercase. Signatures are ASCII, so headers
+    // with other values can be skipped.
+    let headers: HashMap<_, _> = req
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+
+    match coord_client
+        .append_webhook(name, headers, body.to_vec(), authenticated)
+        .await
+    {
+        Ok(()) => Ok(Response::new(Body::empty())),
+        Err(
+            e @ (CoordError::InvalidWebhookSignature(_) | CoordError::UnauthenticatedWebhook(_)),
+        ) => Ok(util::error_response(
+            StatusCode::UNAUTHORIZED,
+            e.to_string(),
+        )),
+        Err(e @ CoordError::UnmaterializedWebhook(_)) => {
+            Ok(util::error_response(StatusCode::CONFLICT, e.to_string()))
+        }
+        Err(e @ CoordError::SqlCatalog(_)) => {
+            Ok(util::error_response(StatusCode::NOT_FOUND, e.to_string()))
+        }
+        Err(e) => Ok(util::error_response(StatusCode::BAD_REQUEST, e.to_string())),
+    }
+}
+
+fn parse_source_name(path: &str) -> Option<PartialName> {
+    let segments = path
+        .strip_prefix(PATH_PREFIX)?
+        .split('/')
+        .map(|segment| percent_decode_str(segment).decode_utf8().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match segments.as_slice() {
+        [database, schema, item] => Some(PartialName {
+            database: Some(database.to_string()),
+            schema: Some(schema.to_string()),
+            item: item.to_string(),
+        }),
+        _ => None,
+    }
+}
@@ -11,10 +11,14 @@
 
 use std::collections::HashMap;
 use std::error::Error;
+use std::io::Write;
 use std::thread;
 use std::time::Duration;
 
+use hmac::{Hmac, Mac};
+use ore::retry::Retry;
 use reqwest::{blocking::Client, StatusCode, Url};
+use sha2::Sha256;
 use tempfile::NamedTempFile;
 
 use crate::util::{PostgresErrorExt, KAFKA_ADDRS};
@@ -207,7 +211,10 @@ fn test_safe_mode() -> Result<(), Box<dyn Error>> {
     let err = client
         .batch_execute("CREATE SINK snk FROM mz_sources INTO FILE '/ignored' FORMAT BYTES")
         .unwrap_db_error();
-    assert_eq!(err.message(), "Expected one of KAFKA or AVRO, found FILE");
+    assert_eq!(
+        err.message(),
+        "Expected one of KAFKA or AVRO or S3 or POSTGRES, found FILE"
+    );
 
     // No Avro OCF sources or sinks.
     let err = client
@@ -298,6 +305,104 @@ fn test_http_sql() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+// Test the webhook endpoint of the HTTP server.
+#[test]
+fn test_http_webhook() -> Result<(), Box<dyn Error>> {
+    let server = util::start_server(util::Config::default())?;
+    let mut client = server.connect(postgres::NoTls)?;
+    let mut secret_file = NamedTempFile::new()?;
+    writeln!(secret_file, "shh")?;
+    client.batch_execute(&format!(
+        "CREATE MATERIALIZED SOURCE signed FROM WEBHOOK
+            WITH (hmac_secret_file = '{0}') FORMAT TEXT;
+         CREATE SOURCE unmaterialized FROM WEBHOOK
+            WITH (hmac_secret_file = '{0}') FORMAT TEXT;
+         CREATE MATERIALIZED SOURCE unsigned FROM WEBHOOK FORMAT TEXT;",
+        secret_file.path().display()
+    ))?;
+
+    let post = |source: &str, body: &'static str, signature: Option<&str>| {
+        let url = format!(
+            "http://{}/api/webhook/materialize/public/{}",
+            server.inner.local_addr(),
+            source
+        );
+        let mut req = Client::new().post(url).body(body);
+        if let Some(signature) = signature {
+            req = req.header("x-signature", signature);
+        }
+        req.send()
+    };
+    let sign = |body: &str| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"shh").unwrap();
+        mac.update(body.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    };
+
+    // Requests need a valid signature.
+    let res = post("signed", "hello", Some(&sign("hello")))?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = post(
+        "signed",
+        "world",
+        Some(&format!("sha256={}", sign("world"))),
+    )?;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = post("signed", "forged", Some(&sign("hello")))?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = post("signed", "unsigned", None)?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // The secret is read when the source is created, not for every request.
+    secret_file.close()?;
+    let res = post("signed", "again", Some(&sign("again")))?;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Sources that don't validate signatures require clients to authenticate,
+    // which they can't over plain HTTP.
+    let res = post("unsigned", "hello", None)?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    // Requests to sources that are not materialized are rejected, rather than
+    // inserted into a dataflow that doesn't exist.
+    let res = post("unmaterialized", "hello", Some(&sign("hello")))?;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = post("missing", "hello", Some(&sign("hello")))?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // The rows show up once the source's timestamps catch up with the ones
+    // assigned to the requests.
+    let read = |client: &mut postgres::Client, query: &str, expected: &[&str]| {
+        Retry::default()
+            .max_duration(Duration::from_secs(10))
+            .retry(|_| -> Result<(), Box<dyn Error>> {
+                let rows: Vec<String> = client
+                    .query(query, &[])?
+                    .into_iter()
+                    .map(|row| row.get(0))
+                    .collect();
+                if rows == expected {
+                    Ok(())
+                } else {
+                    Err(format!("unexpected rows: {:?}", rows).into())
+                }
+            })
+    };
+    read(
+        &mut client,
+        "SELECT text FROM signed ORDER BY text",
+        &["again", "hello", "world"],
+    )?;
+
+    // Once materialized, the source accepts requests.
+    client.batch_execute("CREATE DEFAULT INDEX ON unmaterialized")?;
+    let res = post("unmaterialized", "hello", Some(&sign("hello")))?;
+    assert_eq!(res.status(), StatusCode::OK);
+    read(&mut client, "SELECT text FROM unmaterialized", &["hello"])?;
+
+    Ok(())
+}
+
 #[test]
 fn test_metrics_registry_hygiene() -> Result<(), Box<dyn Error>> {
     // Minor setup chores to ensure the server has done at least a little work:
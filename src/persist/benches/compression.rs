@@ -0,0 +1,106 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Benchmarks for encoding and decoding batches with each compression codec.
+
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use differential_dataflow::trace::Description;
+use timely::progress::Antichain;
+
+use persist::indexed::columnar::parquet::{
+    decode_trace_parquet, decode_unsealed_parquet, encode_trace_parquet, encode_unsealed_parquet,
+};
+use persist::indexed::columnar::BatchCompression;
+use persist::indexed::encoding::{BlobTraceBatch, BlobUnsealedBatch};
+use persist::storage::SeqNo;
+use persist::workload::DataGenerator;
+
+pub fn bench_compression(c: &mut Criterion) {
+    let data = DataGenerator::default();
+    let unsealed = BlobUnsealedBatch {
+        desc: SeqNo(0)..SeqNo(1),
+        updates: data.batches().collect::<Vec<_>>(),
+    };
+    let trace = BlobTraceBatch {
+        desc: Description::new(
+            Antichain::from_elem(0),
+            Antichain::from_elem(1),
+            Antichain::from_elem(0),
+        ),
+        updates: data.records().collect::<Vec<_>>(),
+    };
+
+    for compression in BatchCompression::ALL {
+        let mut unsealed_buf = Vec::new();
+        encode_unsealed_parquet(&mut unsealed_buf, &unsealed, compression)
+            .expect("encoding to a Vec is infallible");
+        let mut trace_buf = Vec::new();
+        encode_trace_parquet(&mut trace_buf, &trace, compression)
+            .expect("encoding to a Vec is infallible");
+        // Criterion only reports timings, so print the other half of the
+        // tradeoff.
+        println!(
+            "{} encoded sizes: unsealed={} trace={} goodput={}",
+            compression,
+            unsealed_buf.len(),
+            trace_buf.len(),
+            data.goodput_bytes(),
+        );
+
+        let mut group = c.benchmark_group(format!("compression_{}", compression));
+        group.throughput(Throughput::Bytes(data.goodput_bytes()));
+
+        group.bench_function(
+            BenchmarkId::new("encode_unsealed", data.goodput_pretty()),
+            |b| {
+                b.iter(|| {
+                    // Intentionally alloc a new buf each iter.
+                    let mut buf = Vec::new();
+                    encode_unsealed_parquet(&mut buf, &unsealed, compression)
+                        .expect("encoding to a Vec is infallible");
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new("decode_unsealed", data.goodput_pretty()),
+            |b| {
+                b.iter(|| {
+                    decode_unsealed_parquet(&mut Cursor::new(&unsealed_buf))
+                        .expect("decoding a valid batch succeeds")
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new("encode_trace", data.goodput_pretty()),
+            |b| {
+                b.iter(|| {
+                    // Intentionally alloc a new buf each iter.
+                    let mut buf = Vec::new();
+                    encode_trace_parquet(&mut buf, &trace, compression)
+                        .expect("encoding to a Vec is infallible");
+                })
+            },
+        );
+        group.bench_function(
+            BenchmarkId::new("decode_trace", data.goodput_pretty()),
+            |b| {
+                b.iter(|| {
+                    decode_trace_parquet(&mut Cursor::new(&trace_buf))
+                        .expect("decoding a valid batch succeeds")
+                })
+            },
+        );
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_compression);
+criterion_main!(benches);
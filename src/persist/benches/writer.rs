@@ -38,6 +38,7 @@ use persist::indexed::metrics::Metrics;
 use persist::indexed::Indexed;
 use persist::mem::MemRegistry;
 use persist::pfuture::{PFuture, PFutureHandle};
+use persist::schema::Schema;
 use persist::storage::{Atomicity, Blob, LockInfo, Log, SeqNo};
 use persist::workload::DataGenerator;
 
@@ -230,7 +231,7 @@ fn bench_writes_indexed_inner<B: Blob, L: Log>(
 
     g.throughput(Throughput::Bytes(data.goodput_bytes()));
 
-    let id = block_on(|res| index.register("0", "()", "()", res))?;
+    let id = block_on(|res| index.register("0", "()", "()", Schema::new(), res))?;
     g.bench_with_input(
         BenchmarkId::new(&format!("{}_sorted", name), data.goodput_pretty()),
         &sorted_updates,
@@ -0,0 +1,611 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Inspection and repair of persisted data.
+//!
+//! Everything except `gc`, `rotate` and `encrypt` only reads, and so is safe
+//! to run against a location that a running process is writing to. Those
+//! three take the exclusive-writer lock and refuse to run while any other
+//! process holds it.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use persist_types::Codec;
+use timely::progress::Antichain;
+use uuid::Uuid;
+
+use persist::encrypted::{EncryptedBlob, EncryptedBlobConfig, EncryptionKey, Keyring};
+use persist::error::Error;
+use persist::file::FileBlob;
+use persist::gen::persist::ProtoMeta;
+use persist::indexed::encoding::{BlobMeta, BlobTraceBatch, BlobUnsealedBatch, Id};
+use persist::s3::{S3Blob, S3BlobConfig};
+use persist::storage::{Blob, BlobRead, LockInfo};
+
+const META_KEY: &str = "META";
+const LOCK_KEY: &str = "LOCK";
+
+/// Inspects and repairs persisted data.
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Where the data is persisted: either a local directory or
+    /// s3://BUCKET/PREFIX.
+    location: String,
+
+    /// An AWS role ARN to assume when reading from S3.
+    #[clap(long, value_name = "ARN")]
+    aws_role_arn: Option<String>,
+
+    /// A key the data is encrypted with, given as the key's id and the path to
+    /// a file containing its hex-encoded material. May be specified multiple
+    /// times to read data written before a key rotation.
+    #[clap(long, value_name = "ID=PATH")]
+    encryption_key: Vec<String>,
+
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, clap::Parser)]
+enum Command {
+    /// List every stream with its frontiers and batch counts.
+    Streams,
+    /// List the key and size of every batch.
+    Batches {
+        /// Only list batches of the stream with this name.
+        #[clap(long)]
+        stream: Option<String>,
+    },
+    /// Print the records of a stream, decoded if their codec is known.
+    Dump {
+        /// The name of the stream to dump.
+        #[clap(long)]
+        stream: String,
+        /// Stop after printing this many records.
+        #[clap(long)]
+        limit: Option<usize>,
+    },
+    /// Check the meta for consistency and that every batch it references
+    /// exists.
+    Verify,
+    /// Delete values that are not referenced by the meta.
+    Gc {
+        /// Print what would be deleted without deleting it.
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Re-encrypt every value written under a previous key with the first
+    /// --encryption-key, after which the other keys are no longer needed.
+    Rotate,
+    /// Encrypt every value that was written without encryption with the first
+    /// --encryption-key, for example to start encrypting existing data.
+    /// Values written under a previous key are re-encrypted as well.
+    Encrypt,
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Args = ore::cli::parse_args();
+    if let Err(err) = run(args).await {
+        eprintln!("persistcli: {}", err);
+        process::exit(1);
+    }
+}
+
+async fn run(args: Args) -> Result<(), Error> {
+    let keys = parse_keys(&args.encryption_key)?;
+    if let Command::Rotate | Command::Encrypt = args.command {
+        let keys = keys.ok_or_else(|| Error::from("--encryption-key is required"))?;
+        return match args.location.strip_prefix("s3://") {
+            Some(location) => {
+                let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+                let config =
+                    S3BlobConfig::new(bucket.to_owned(), prefix.to_owned(), args.aws_role_arn)
+                        .await?;
+                reencrypt::<S3Blob>(config, keys, args.command).await
+            }
+            None => {
+                let config = PathBuf::from(&args.location).into();
+                reencrypt::<FileBlob>(config, keys, args.command).await
+            }
+        };
+    }
+    match args.location.strip_prefix("s3://") {
+        Some(location) => {
+            let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+            let config =
+                S3BlobConfig::new(bucket.to_owned(), prefix.to_owned(), args.aws_role_arn).await?;
+            match keys {
+                Some(keys) => {
+                    let config = EncryptedBlobConfig::new(config, keys);
+                    run_command::<EncryptedBlob<S3Blob>>(config, args.command).await
+                }
+                None => run_command::<S3Blob>(config, args.command).await,
+            }
+        }
+        None => {
+            let config = PathBuf::from(&args.location).into();
+            match keys {
+                Some(keys) => {
+                    let config = EncryptedBlobConfig::new(config, keys);
+                    run_command::<EncryptedBlob<FileBlob>>(config, args.command).await
+                }
+                None => run_command::<FileBlob>(config, args.command).await,
+            }
+        }
+    }
+}
+
+fn parse_keys(args: &[String]) -> Result<Option<Keyring>, Error> {
+    let mut keys = args
+        .iter()
+        .map(|arg| {
+            let (id, path) = arg
+                .split_once('=')
+                .ok_or_else(|| Error::from("--encryption-key must be of the form ID=PATH"))?;
+            let key = fs::read_to_string(path)?;
+            let key = hex::decode(key.trim())
+                .map_err(|err| Error::from(format!("decoding encryption key {}: {}", id, err)))?;
+            EncryptionKey::new(id.to_owned(), &key)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    let current = keys.remove(0);
+    Ok(Some(Keyring::new(current, keys)?))
+}
+
+/// Runs `rotate` or `encrypt` against the blob described by `config`.
+async fn reencrypt<B>(config: B::Config, keys: Keyring, command: Command) -> Result<(), Error>
+where
+    B: Blob + Sync,
+    B::Read: Sync,
+{
+    let lock_info = LockInfo::new(Uuid::new_v4().to_string(), "persistcli".into())?;
+    let mut blob =
+        EncryptedBlob::<B>::open_exclusive(EncryptedBlobConfig::new(config, keys), lock_info)?;
+    let (res, what) = match command {
+        Command::Rotate => (blob.rotate().await, "re-encrypted"),
+        Command::Encrypt => (blob.encrypt_plaintext().await, "encrypted"),
+        _ => unreachable!("only rotate and encrypt re-encrypt"),
+    };
+    blob.close().await?;
+    println!("{} {} values", what, res?);
+    Ok(())
+}
+
+async fn run_command<B>(config: B::Config, command: Command) -> Result<(), Error>
+where
+    B: Blob + Sync,
+    B::Read: Sync,
+{
+    let lines = match command {
+        Command::Gc { dry_run } => {
+            let lock_info = LockInfo::new(Uuid::new_v4().to_string(), "persistcli gc".into())?;
+            let mut blob = B::open_exclusive(config, lock_info)?;
+            let res = gc(&mut blob, dry_run).await;
+            blob.close().await?;
+            res?
+        }
+        command => {
+            let mut blob = B::open_read(config)?;
+            let res = match command {
+                Command::Streams => streams(&blob).await,
+                Command::Batches { stream } => batches(&blob, stream.as_deref()).await,
+                Command::Dump { stream, limit } => dump(&blob, &stream, limit).await,
+                Command::Verify => match verify(&blob).await {
+                    Ok(problems) if problems.is_empty() => Ok(vec!["ok".into()]),
+                    Ok(problems) => Err(Error::from(problems.join("\n"))),
+                    Err(err) => Err(err),
+                },
+                Command::Gc { .. } => unreachable!("handled above"),
+                Command::Rotate | Command::Encrypt => unreachable!("handled in run"),
+            };
+            blob.close().await?;
+            res?
+        }
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+async fn load_meta<B: BlobRead>(blob: &B) -> Result<BlobMeta, Error> {
+    let bytes = blob.get(META_KEY).await?.ok_or_else(|| {
+        Error::from("no META found: this location does not contain persisted data")
+    })?;
+    let meta = ProtoMeta::decode(&bytes)
+        .map_err(|err| Error::from(format!("invalid meta at key {}: {}", META_KEY, err)))?;
+    Ok(BlobMeta::from(meta))
+}
+
+fn stream_name(meta: &BlobMeta, id: Id) -> &str {
+    meta.id_mapping
+        .iter()
+        .find(|x| x.id == id)
+        .map_or("<unknown>", |x| x.name.as_str())
+}
+
+/// Every key referenced by the meta, with a description of what it is.
+fn referenced_keys(meta: &BlobMeta) -> Vec<(&str, String)> {
+    let mut keys = Vec::new();
+    for arrangement in meta.arrangements.iter() {
+        let name = stream_name(meta, arrangement.id);
+        for batch in arrangement.unsealed_batches.iter() {
+            keys.push((
+                batch.key.as_str(),
+                format!("unsealed batch of stream {}", name),
+            ));
+        }
+        for batch in arrangement.trace_batches.iter() {
+            keys.push((
+                batch.key.as_str(),
+                format!("trace batch of stream {}", name),
+            ));
+        }
+    }
+    keys
+}
+
+async fn streams<B: BlobRead>(blob: &B) -> Result<Vec<String>, Error> {
+    let meta = load_meta(blob).await?;
+    let mut lines = Vec::new();
+    for arrangement in meta.arrangements.iter() {
+        let registration = meta.id_mapping.iter().find(|x| x.id == arrangement.id);
+        let (name, key_codec, val_codec) = match registration {
+            Some(x) => (
+                x.name.as_str(),
+                x.key_codec_name.as_str(),
+                x.val_codec_name.as_str(),
+            ),
+            None => ("<unknown>", "<unknown>", "<unknown>"),
+        };
+        let size_bytes = arrangement
+            .unsealed_batches
+            .iter()
+            .map(|x| x.size_bytes)
+            .chain(arrangement.trace_batches.iter().map(|x| x.size_bytes))
+            .sum::<u64>();
+        lines.push(format!(
+            "{} id={} key_codec={} val_codec={} since={:?} seal={:?} unsealed_batches={} trace_batches={} size_bytes={} schema_version={}",
+            name,
+            arrangement.id.0,
+            key_codec,
+            val_codec,
+            arrangement.since.elements(),
+            arrangement.seal.elements(),
+            arrangement.unsealed_batches.len(),
+            arrangement.trace_batches.len(),
+            size_bytes,
+            arrangement.schema_version,
+        ));
+    }
+    for registration in meta.graveyard.iter() {
+        lines.push(format!(
+            "{} id={} deleted",
+            registration.name, registration.id.0
+        ));
+    }
+    Ok(lines)
+}
+
+async fn batches<B: BlobRead>(blob: &B, stream: Option<&str>) -> Result<Vec<String>, Error> {
+    let meta = load_meta(blob).await?;
+    let mut lines = Vec::new();
+    for arrangement in meta.arrangements.iter() {
+        let name = stream_name(&meta, arrangement.id);
+        if stream.map_or(false, |stream| stream != name) {
+            continue;
+        }
+        for batch in arrangement.unsealed_batches.iter() {
+            lines.push(format!(
+                "{} unsealed {} seqno=[{}, {}) ts=[{}, {}] format={:?} size_bytes={} schema_version={}",
+                name,
+                batch.key,
+                batch.desc.start.0,
+                batch.desc.end.0,
+                batch.ts_lower,
+                batch.ts_upper,
+                batch.format,
+                batch.size_bytes,
+                batch.schema_version,
+            ));
+        }
+        for batch in arrangement.trace_batches.iter() {
+            lines.push(format!(
+                "{} trace {} lower={:?} upper={:?} since={:?} level={} format={:?} size_bytes={} schema_version={}",
+                name,
+                batch.key,
+                batch.desc.lower().elements(),
+                batch.desc.upper().elements(),
+                batch.desc.since().elements(),
+                batch.level,
+                batch.format,
+                batch.size_bytes,
+                batch.schema_version,
+            ));
+        }
+    }
+    Ok(lines)
+}
+
+/// Formats a key or value, decoding it if its codec is one we know.
+fn pretty(codec_name: &str, buf: &[u8]) -> String {
+    fn decode<T: Codec + std::fmt::Debug>(buf: &[u8]) -> String {
+        match T::decode(buf) {
+            Ok(x) => format!("{:?}", x),
+            Err(err) => format!("<undecodable {}: {}>", T::codec_name(), err),
+        }
+    }
+    if codec_name == <()>::codec_name() {
+        decode::<()>(buf)
+    } else if codec_name == String::codec_name() {
+        decode::<String>(buf)
+    } else if codec_name == Vec::<u8>::codec_name() {
+        decode::<Vec<u8>>(buf)
+    } else {
+        match std::str::from_utf8(buf) {
+            Ok(x) => format!("{:?}", x),
+            Err(_) => format!("{:?}", buf),
+        }
+    }
+}
+
+async fn dump<B: BlobRead>(
+    blob: &B,
+    stream: &str,
+    limit: Option<usize>,
+) -> Result<Vec<String>, Error> {
+    let meta = load_meta(blob).await?;
+    let registration = meta
+        .id_mapping
+        .iter()
+        .find(|x| x.name == stream)
+        .ok_or_else(|| Error::from(format!("unknown stream: {}", stream)))?;
+    let arrangement = meta
+        .arrangements
+        .iter()
+        .find(|x| x.id == registration.id)
+        .ok_or_else(|| Error::from(format!("no arrangement for stream: {}", stream)))?;
+    let get = |key: String| async move {
+        blob.get(&key)
+            .await?
+            .ok_or_else(|| Error::from(format!("missing batch: {}", key)))
+    };
+
+    let mut records = Vec::new();
+    for batch in arrangement.trace_batches.iter() {
+        let buf = get(batch.key.clone()).await?;
+        let batch_data = BlobTraceBatch::decode(&buf)
+            .map_err(|err| Error::from(format!("invalid trace batch {}: {}", batch.key, err)))?;
+        for ((k, v), ts, diff) in batch_data.updates {
+            records.push((batch.key.clone(), k, v, ts, diff));
+        }
+    }
+    // Unsealed batches may still contain updates that have since been moved
+    // into the trace, so skip those to avoid printing them twice.
+    let trace_ts_upper = arrangement
+        .trace_batches
+        .last()
+        .map_or_else(|| Antichain::from_elem(0), |x| x.desc.upper().clone());
+    for batch in arrangement.unsealed_batches.iter() {
+        let buf = get(batch.key.clone()).await?;
+        let batch_data = BlobUnsealedBatch::decode(&buf)
+            .map_err(|err| Error::from(format!("invalid unsealed batch {}: {}", batch.key, err)))?;
+        for updates in batch_data.updates.iter() {
+            for ((k, v), ts, diff) in updates.iter() {
+                if trace_ts_upper.less_equal(&ts) {
+                    records.push((batch.key.clone(), k.to_vec(), v.to_vec(), ts, diff));
+                }
+            }
+        }
+    }
+
+    let lines = records
+        .into_iter()
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(batch, k, v, ts, diff)| {
+            format!(
+                "{} ({}, {}) {} {}",
+                batch,
+                pretty(&registration.key_codec_name, &k),
+                pretty(&registration.val_codec_name, &v),
+                ts,
+                diff
+            )
+        })
+        .collect();
+    Ok(lines)
+}
+
+/// Returns a description of each problem found with the persisted data.
+async fn verify<B: BlobRead>(blob: &B) -> Result<Vec<String>, Error> {
+    let meta = load_meta(blob).await?;
+    let mut problems = Vec::new();
+    if let Err(err) = meta.validate() {
+        problems.push(format!("invalid meta: {}", err));
+    }
+    let existing = blob.list_keys().await?.into_iter().collect::<HashSet<_>>();
+    for (key, what) in referenced_keys(&meta) {
+        if !existing.contains(key) {
+            problems.push(format!("missing {}: {}", what, key));
+        }
+    }
+    Ok(problems)
+}
+
+/// Deletes (or, if `dry_run`, lists) every value not referenced by the meta.
+async fn gc<B: Blob>(blob: &mut B, dry_run: bool) -> Result<Vec<String>, Error> {
+    let meta = load_meta(blob).await?;
+    let referenced = referenced_keys(&meta)
+        .into_iter()
+        .map(|(key, _)| key.to_owned())
+        .collect::<HashSet<_>>();
+    let mut orphans = blob
+        .list_keys()
+        .await?
+        .into_iter()
+        .filter(|key| key != META_KEY && key != LOCK_KEY && !referenced.contains(key))
+        .collect::<Vec<_>>();
+    orphans.sort();
+
+    let mut lines = Vec::new();
+    for key in orphans {
+        if dry_run {
+            lines.push(format!("would delete {}", key));
+        } else {
+            blob.delete(&key).await?;
+            lines.push(format!("deleted {}", key));
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use ore::metrics::MetricsRegistry;
+    use persist::encrypted::is_encrypted;
+    use persist::error::ErrorLog;
+    use persist::indexed::Snapshot;
+    use persist::mem::MemRegistry;
+    use persist::runtime::{self, RuntimeConfig};
+    use persist::storage::Atomicity;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn persistcli() -> Result<(), Error> {
+        let mut registry = MemRegistry::new();
+        let mut client = registry.runtime_no_reentrance()?;
+        let (write, _) = client.create_or_load::<String, String>("s");
+        write.write(&[(("k1".into(), "v1".into()), 1, 1)]).recv()?;
+        write.seal(2).recv()?;
+        write.write(&[(("k2".into(), "v2".into()), 3, 1)]).recv()?;
+        client.stop()?;
+
+        let mut blob = registry.blob_no_reentrance()?;
+        let streams = streams(&blob).await?;
+        assert_eq!(streams.len(), 1);
+        assert!(
+            streams[0].starts_with("s id=0 key_codec=String val_codec=String since=[0] seal=[2]"),
+            "{}",
+            streams[0]
+        );
+
+        let mut records = dump(&blob, "s", None)
+            .await?
+            .into_iter()
+            .map(|line| {
+                line.split_once(' ')
+                    .expect("line has a batch key")
+                    .1
+                    .to_owned()
+            })
+            .collect::<Vec<_>>();
+        records.sort();
+        assert_eq!(records, vec![r#"("k1", "v1") 1 1"#, r#"("k2", "v2") 3 1"#]);
+        assert_eq!(dump(&blob, "s", Some(1)).await?.len(), 1);
+
+        // An orphaned value is found and deleted by gc, but is harmless to
+        // verify.
+        blob.set("orphan", b"x".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        assert_eq!(verify(&blob).await?, Vec::<String>::new());
+        assert_eq!(gc(&mut blob, true).await?, vec!["would delete orphan"]);
+        assert_eq!(gc(&mut blob, false).await?, vec!["deleted orphan"]);
+        assert_eq!(gc(&mut blob, false).await?, Vec::<String>::new());
+
+        // A missing batch is found by verify.
+        let batches = batches(&blob, Some("s")).await?;
+        let key = batches[0].split(' ').nth(2).expect("line has a key");
+        blob.delete(key).await?;
+        let problems = verify(&blob).await?;
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].ends_with(key), "{}", problems[0]);
+
+        blob.close().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encrypt_and_rotate() -> Result<(), Error> {
+        let keys_dir = tempfile::tempdir()?;
+        let blob_dir = tempfile::tempdir()?;
+        let location = blob_dir.path().to_string_lossy().into_owned();
+        let key_arg = |id: &str, material: u8| -> Result<String, Error> {
+            let path = keys_dir.path().join(id);
+            fs::write(&path, hex::encode([material; 32]))?;
+            Ok(format!("{}={}", id, path.display()))
+        };
+        let args = |encryption_key: Vec<String>, command: Command| Args {
+            location: location.clone(),
+            aws_role_arn: None,
+            encryption_key,
+            command,
+        };
+        let start = |keys: Option<Keyring>| {
+            let blob = FileBlob::open_exclusive(
+                blob_dir.path().to_path_buf().into(),
+                LockInfo::new_no_reentrance("encrypt_and_rotate".into()),
+            )?;
+            let (build, reg) = (build_info::DUMMY_BUILD_INFO, MetricsRegistry::new());
+            match keys {
+                Some(keys) => runtime::start(
+                    RuntimeConfig::default(),
+                    ErrorLog,
+                    EncryptedBlob::new(blob, keys),
+                    build,
+                    &reg,
+                    None,
+                ),
+                None => runtime::start(RuntimeConfig::default(), ErrorLog, blob, build, &reg, None),
+            }
+        };
+
+        // Write some data before encryption is enabled.
+        let mut client = start(None)?;
+        let (write, _) = client.create_or_load::<String, String>("s");
+        write.write(&[(("k1".into(), "v1".into()), 1, 1)]).recv()?;
+        write.seal(2).recv()?;
+        client.stop()?;
+
+        // Reading or rotating through an encrypting delegate fails until the
+        // data is encrypted.
+        let k1 = key_arg("k1", 1)?;
+        assert!(run(args(vec![k1.clone()], Command::Streams)).await.is_err());
+        run(args(vec![k1.clone()], Command::Rotate)).await?;
+        run(args(vec![k1.clone()], Command::Encrypt)).await?;
+        let raw = FileBlob::open_read(blob_dir.path().to_path_buf().into())?;
+        let keys = raw.list_keys().await?;
+        assert!(!keys.is_empty());
+        for key in keys {
+            let value = raw.get(&key).await?.expect("value was listed");
+            assert!(is_encrypted(&value), "{} is not encrypted", key);
+        }
+        run(args(vec![k1.clone()], Command::Verify)).await?;
+
+        // After a rotation to a new key, the old one is no longer needed.
+        let k2 = key_arg("k2", 2)?;
+        run(args(vec![k2.clone(), k1], Command::Rotate)).await?;
+        let mut client = start(parse_keys(&[k2])?)?;
+        let (_, read) = client.create_or_load::<String, String>("s");
+        let updates = read
+            .snapshot()?
+            .into_iter()
+            .collect::<Result<Vec<_>, Error>>()?;
+        assert_eq!(updates, vec![(("k1".to_owned(), "v1".to_owned()), 1, 1)]);
+        client.stop()?;
+        Ok(())
+    }
+}
@@ -27,6 +27,7 @@ use crate::indexed::encoding::Id;
 use crate::indexed::{ListenEvent, Snapshot};
 use crate::pfuture::{PFuture, PFutureHandle};
 use crate::runtime::{Cmd, RuntimeCore, RuntimeId};
+use crate::schema::Schema;
 use crate::storage::SeqNo;
 
 /// A clone-able handle to the persistence runtime.
@@ -76,11 +77,30 @@ impl RuntimeClient {
     pub fn create_or_load<K: Codec, V: Codec>(
         &self,
         name: &str,
+    ) -> (StreamWriteHandle<K, V>, StreamReadHandle<K, V>) {
+        self.create_or_load_with_schema(name, Schema::new())
+    }
+
+    /// Synchronously registers a new stream for writes and reads, using the
+    /// given [Schema] to migrate data written at older schema versions.
+    ///
+    /// If the schema is newer than the one the stream was last written with,
+    /// new writes are recorded at the new version and existing data is
+    /// migrated when it's read and when it's compacted. It's an error to
+    /// register a schema older than the one the stream was last written with.
+    ///
+    /// All handles for a stream in a runtime should be created with the same
+    /// schema.
+    pub fn create_or_load_with_schema<K: Codec, V: Codec>(
+        &self,
+        name: &str,
+        schema: Schema,
     ) -> (StreamWriteHandle<K, V>, StreamReadHandle<K, V>) {
         let (tx, rx) = PFuture::new();
         self.core.send(Cmd::Register(
             name.to_owned(),
             (K::codec_name(), V::codec_name()),
+            schema,
             tx,
         ));
         let id = rx.recv();
@@ -593,6 +613,10 @@ impl<K: Codec, V: Codec> StreamReadHandle<K, V> {
     /// logic on everything that was previously persisted before registering the
     /// listener, and all writes and seals that happen after registration without
     /// duplicating or dropping data.
+    ///
+    /// Through a read-only runtime (see [crate::runtime::start_read_only]),
+    /// the channel is disconnected if the writer allows compaction past the
+    /// data the listener has been sent.
     pub fn listen(
         &self,
         sender: crossbeam_channel::Sender<ListenEvent>,
@@ -0,0 +1,803 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Envelope encryption of data at rest in [Blob] and [Log] storage.
+//!
+//! Each value is encrypted with its own randomly generated data key, which is
+//! in turn encrypted ("wrapped") with a long-lived, customer-managed
+//! [EncryptionKey]. The id of the wrapping key is stored in the clear
+//! alongside each value, so values written under a previous key remain
+//! readable after a rotation as long as that key is still supplied.
+//!
+//! Each value is also authenticated together with where it's stored (its key
+//! for blobs, its sequence number for logs), so that a value moved elsewhere
+//! fails to decrypt.
+
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+
+use async_trait::async_trait;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+use crate::error::Error;
+use crate::storage::{Atomicity, Blob, BlobRead, LockInfo, Log, SeqNo};
+
+/// Prefix of every encrypted value, which also versions the format.
+///
+/// The following is an EBNF-ish spec for the format:
+///
+/// ```none
+/// encrypted = MAGIC key_id_len key_id wrapped_data_key sealed_data
+/// key_id_len = u8 u8 (big endian)
+/// key_id = u8* (utf-8, key_id_len bytes)
+/// wrapped_data_key = sealed (of the 32 byte data key, with key_id as aad)
+/// sealed_data = sealed (of the value, with the blob key as aad for blobs
+///     and the u64 big endian seqno as aad for log entries)
+/// sealed = nonce tag ciphertext (AES-256-GCM)
+/// nonce = u8{12}
+/// tag = u8{16}
+/// ```
+const MAGIC: &[u8; 4] = b"MZE1";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const WRAPPED_KEY_LEN: usize = NONCE_LEN + TAG_LEN + KEY_LEN;
+
+/// The key under which [crate::file::FileBlob] and [crate::s3::S3Blob] keep
+/// their lock file, which they write in the clear.
+const LOCK_KEY: &str = "LOCK";
+
+/// Returns whether the given stored value was written by an encrypting
+/// delegate.
+pub fn is_encrypted(value: &[u8]) -> bool {
+    value.starts_with(MAGIC)
+}
+
+/// Returns an error if a value that was read without an encrypting delegate
+/// is nonetheless encrypted.
+pub(crate) fn check_not_encrypted(value: &[u8]) -> Result<(), Error> {
+    if is_encrypted(value) {
+        return Err(Error::from(
+            "persisted data is encrypted, but no encryption key was configured",
+        ));
+    }
+    Ok(())
+}
+
+/// A customer-managed key, used to wrap the data keys of encrypted values.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    key: [u8; KEY_LEN],
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Intentionally omit the key material.
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptionKey {
+    /// Returns a new key with the given id and 256 bits of key material.
+    ///
+    /// The id is stored in the clear next to every value encrypted with this
+    /// key, so it must not itself be sensitive.
+    pub fn new(id: String, key: &[u8]) -> Result<Self, Error> {
+        if id.is_empty() || u16::try_from(id.len()).is_err() {
+            return Err(
+                format!("encryption key id must be between 1 and {} bytes", u16::MAX).into(),
+            );
+        }
+        let key = <[u8; KEY_LEN]>::try_from(key).map_err(|_| {
+            Error::from(format!(
+                "encryption key {} must be {} bytes, got {}",
+                id,
+                KEY_LEN,
+                key.len()
+            ))
+        })?;
+        Ok(EncryptionKey { id, key })
+    }
+
+    /// The id of this key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// The set of keys available to an encrypting delegate.
+#[derive(Clone, Debug)]
+pub struct Keyring {
+    current: EncryptionKey,
+    previous: Vec<EncryptionKey>,
+}
+
+impl Keyring {
+    /// Returns a keyring that encrypts new values with `current` and can
+    /// additionally decrypt values written under any of the `previous` keys.
+    pub fn new(current: EncryptionKey, previous: Vec<EncryptionKey>) -> Result<Self, Error> {
+        let mut ids = HashSet::new();
+        for key in std::iter::once(&current).chain(previous.iter()) {
+            if !ids.insert(key.id.as_str()) {
+                return Err(format!("duplicate encryption key id: {}", key.id).into());
+            }
+        }
+        Ok(Keyring { current, previous })
+    }
+
+    /// The id of the key used to encrypt new values.
+    pub fn current_id(&self) -> &str {
+        &self.current.id
+    }
+
+    fn get(&self, id: &str) -> Option<&EncryptionKey> {
+        std::iter::once(&self.current)
+            .chain(self.previous.iter())
+            .find(|key| key.id == id)
+    }
+
+    /// Encrypts `value` under the current key, additionally authenticating
+    /// (but not encrypting) `aad`.
+    pub(crate) fn seal(&self, value: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        let key = &self.current;
+        let mut data_key = [0u8; KEY_LEN];
+        rand_bytes(&mut data_key).map_err(|err| Error::from(err.to_string()))?;
+
+        let id_len = u16::try_from(key.id.len()).expect("validated in EncryptionKey::new");
+        let mut ret = Vec::with_capacity(
+            MAGIC.len() + 2 + key.id.len() + WRAPPED_KEY_LEN + NONCE_LEN + TAG_LEN + value.len(),
+        );
+        ret.extend_from_slice(MAGIC);
+        ret.extend_from_slice(&id_len.to_be_bytes());
+        ret.extend_from_slice(key.id.as_bytes());
+        aead_seal(&key.key, key.id.as_bytes(), &data_key, &mut ret)?;
+        aead_seal(&data_key, aad, value, &mut ret)?;
+        Ok(ret)
+    }
+
+    /// Decrypts a value produced by [Self::seal] with the same `aad`.
+    ///
+    /// `what` describes the value for error messages.
+    pub(crate) fn open(&self, sealed: &[u8], aad: &[u8], what: &str) -> Result<Vec<u8>, Error> {
+        let envelope = Envelope::parse(sealed)
+            .ok_or_else(|| Error::from(format!("{} is not encrypted", what)))?;
+        let key = self.get(envelope.key_id).ok_or_else(|| {
+            Error::from(format!(
+                "{} was encrypted with key {}, which is not one of the configured keys: {}",
+                what,
+                envelope.key_id,
+                self.ids()
+            ))
+        })?;
+        let data_key = aead_open(&key.key, key.id.as_bytes(), envelope.wrapped_key)
+            .map_err(|_| {
+                Error::from(format!(
+                    "{} could not be decrypted: the material configured for key {} does not match the key it was encrypted with",
+                    what, key.id
+                ))
+            })?;
+        aead_open(&data_key, aad, envelope.data).map_err(|_| {
+            Error::from(format!(
+                "{} failed authentication: it is corrupted or was not written at this location",
+                what
+            ))
+        })
+    }
+
+    fn ids(&self) -> String {
+        std::iter::once(&self.current)
+            .chain(self.previous.iter())
+            .map(|key| key.id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The parsed, still encrypted, components of a sealed value.
+struct Envelope<'a> {
+    key_id: &'a str,
+    wrapped_key: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    fn parse(buf: &'a [u8]) -> Option<Self> {
+        let buf = buf.strip_prefix(MAGIC)?;
+        if buf.len() < 2 {
+            return None;
+        }
+        let (id_len, buf) = buf.split_at(2);
+        let id_len = usize::from(u16::from_be_bytes([id_len[0], id_len[1]]));
+        if buf.len() < id_len + WRAPPED_KEY_LEN {
+            return None;
+        }
+        let (key_id, buf) = buf.split_at(id_len);
+        let (wrapped_key, data) = buf.split_at(WRAPPED_KEY_LEN);
+        Some(Envelope {
+            key_id: std::str::from_utf8(key_id).ok()?,
+            wrapped_key,
+            data,
+        })
+    }
+}
+
+/// Appends the AES-256-GCM encryption of `plaintext` to `out`.
+fn aead_seal(key: &[u8], aad: &[u8], plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand_bytes(&mut nonce).map_err(|err| Error::from(err.to_string()))?;
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        aad,
+        plaintext,
+        &mut tag,
+    )
+    .map_err(|err| Error::from(err.to_string()))?;
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&tag);
+    out.extend_from_slice(&ciphertext);
+    Ok(())
+}
+
+/// Inverts [aead_seal], failing if `sealed` was not encrypted with `key` and
+/// `aad`.
+fn aead_open(key: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, ()> {
+    if sealed.len() < NONCE_LEN + TAG_LEN {
+        return Err(());
+    }
+    let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+    let (tag, ciphertext) = sealed.split_at(TAG_LEN);
+    decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(nonce),
+        aad,
+        ciphertext,
+        tag,
+    )
+    .map_err(|_| ())
+}
+
+/// An encrypting delegate to [Log].
+///
+/// Each entry is authenticated together with its sequence number, so an entry
+/// copied to a different sequence number fails to decrypt.
+///
+/// Unlike [EncryptedBlob], there's no way to re-encrypt a log with a new key,
+/// because entries are never rewritten. Instead, a previous key can be dropped
+/// from the [Keyring] once every entry written under it has been truncated,
+/// which can be checked with [Self::key_ids].
+#[derive(Debug)]
+pub struct EncryptedLog<L> {
+    keys: Keyring,
+    log: L,
+    /// The sequence number the next write will be assigned, if known.
+    next_seqno: Option<SeqNo>,
+}
+
+impl<L: Log> EncryptedLog<L> {
+    /// Returns a new [EncryptedLog] using the given keys.
+    pub fn new(log: L, keys: Keyring) -> Self {
+        EncryptedLog {
+            keys,
+            log,
+            next_seqno: None,
+        }
+    }
+
+    /// Returns the ids of the keys that the entries in the log, which haven't
+    /// yet been truncated, are encrypted with.
+    pub fn key_ids(&self) -> Result<BTreeSet<String>, Error> {
+        let mut ids = BTreeSet::new();
+        self.log.snapshot(|seqno, buf| {
+            let envelope = Envelope::parse(buf)
+                .ok_or_else(|| Error::from(format!("log entry {:?} is not encrypted", seqno)))?;
+            ids.insert(envelope.key_id.to_owned());
+            Ok(())
+        })?;
+        Ok(ids)
+    }
+}
+
+impl<L: Log> Log for EncryptedLog<L> {
+    fn write_sync(&mut self, buf: Vec<u8>) -> Result<SeqNo, Error> {
+        // The sequence number has to be known before the write, so that the
+        // entry can be bound to it. It's looked up again after a failed write,
+        // which may or may not have used one up.
+        let seqno = match self.next_seqno.take() {
+            Some(seqno) => seqno,
+            None => self.log.snapshot(|_, _| Ok(()))?.end,
+        };
+        let buf = self.keys.seal(&buf, &seqno.0.to_be_bytes())?;
+        let write_seqno = self.log.write_sync(buf)?;
+        if write_seqno != seqno {
+            return Err(Error::from(format!(
+                "log entry was written at {:?} instead of the expected {:?}",
+                write_seqno, seqno
+            )));
+        }
+        self.next_seqno = Some(SeqNo(seqno.0 + 1));
+        Ok(write_seqno)
+    }
+
+    fn snapshot<F>(&self, mut logic: F) -> Result<Range<SeqNo>, Error>
+    where
+        F: FnMut(SeqNo, &[u8]) -> Result<(), Error>,
+    {
+        let keys = &self.keys;
+        self.log.snapshot(|seqno, buf| {
+            let buf = keys.open(
+                buf,
+                &seqno.0.to_be_bytes(),
+                &format!("log entry {:?}", seqno),
+            )?;
+            logic(seqno, &buf)
+        })
+    }
+
+    fn truncate(&mut self, upper: SeqNo) -> Result<(), Error> {
+        self.log.truncate(upper)
+    }
+
+    fn close(&mut self) -> Result<bool, Error> {
+        self.log.close()
+    }
+}
+
+/// Configuration for opening an [EncryptedBlob].
+#[derive(Debug)]
+pub struct EncryptedBlobConfig<B: Blob> {
+    keys: Keyring,
+    blob: B::Config,
+}
+
+impl<B: Blob> EncryptedBlobConfig<B> {
+    /// Returns a config for encrypting the blob described by `blob`.
+    pub fn new(blob: B::Config, keys: Keyring) -> Self {
+        EncryptedBlobConfig { keys, blob }
+    }
+}
+
+/// An encrypting delegate to [Blob].
+///
+/// Each value is authenticated together with its key, so a value copied to a
+/// different key fails to decrypt.
+#[derive(Debug)]
+pub struct EncryptedBlob<B> {
+    keys: Keyring,
+    blob: B,
+}
+
+impl<B: BlobRead> EncryptedBlob<B> {
+    /// Returns a new [EncryptedBlob] using the given keys.
+    pub fn new(blob: B, keys: Keyring) -> Self {
+        EncryptedBlob { keys, blob }
+    }
+}
+
+impl<B: Blob + Sync> EncryptedBlob<B>
+where
+    B::Read: Sync,
+{
+    /// Re-encrypts every value written under a previous key with the current
+    /// key, after which the previous keys are no longer needed to read this
+    /// blob.
+    ///
+    /// Values that weren't written through an encrypting delegate, such as
+    /// the LOCK file of the underlying blob, are left as they are. Returns the
+    /// number of values that were re-encrypted.
+    pub async fn rotate(&mut self) -> Result<usize, Error> {
+        self.reseal(false).await
+    }
+
+    /// Encrypts every value that was written without encryption with the
+    /// current key, and re-encrypts every value written under a previous key,
+    /// so that data persisted before encryption was enabled can be read by an
+    /// encrypting delegate.
+    ///
+    /// The LOCK file of the underlying blob is left in the clear. Returns the
+    /// number of values that were encrypted or re-encrypted.
+    pub async fn encrypt_plaintext(&mut self) -> Result<usize, Error> {
+        self.reseal(true).await
+    }
+
+    async fn reseal(&mut self, include_plaintext: bool) -> Result<usize, Error> {
+        let mut resealed = 0;
+        for key in self.blob.list_keys().await? {
+            let stored = match self.blob.get(&key).await? {
+                Some(stored) => stored,
+                // Deleted since we listed it.
+                None => continue,
+            };
+            let value = match Envelope::parse(&stored) {
+                Some(envelope) if envelope.key_id == self.keys.current_id() => continue,
+                Some(_) => self
+                    .keys
+                    .open(&stored, key.as_bytes(), &format!("blob {}", key))?,
+                None if include_plaintext && key != LOCK_KEY => stored,
+                None => continue,
+            };
+            let sealed = self.keys.seal(&value, key.as_bytes())?;
+            self.blob
+                .set(&key, sealed, Atomicity::RequireAtomic)
+                .await?;
+            resealed += 1;
+        }
+        Ok(resealed)
+    }
+}
+
+#[async_trait]
+impl<B: BlobRead + Sync> BlobRead for EncryptedBlob<B> {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        match self.blob.get(key).await? {
+            Some(sealed) => {
+                let value = self
+                    .keys
+                    .open(&sealed, key.as_bytes(), &format!("blob {}", key))?;
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn list_keys(&self) -> Result<Vec<String>, Error> {
+        self.blob.list_keys().await
+    }
+
+    async fn close(&mut self) -> Result<bool, Error> {
+        self.blob.close().await
+    }
+
+    fn encryption_key_id(&self) -> Option<&str> {
+        Some(self.keys.current_id())
+    }
+}
+
+#[async_trait]
+impl<B> Blob for EncryptedBlob<B>
+where
+    B: Blob + Sync,
+    B::Read: Sync,
+{
+    type Config = EncryptedBlobConfig<B>;
+    type Read = EncryptedBlob<B::Read>;
+
+    fn open_exclusive(config: EncryptedBlobConfig<B>, lock_info: LockInfo) -> Result<Self, Error> {
+        let blob = B::open_exclusive(config.blob, lock_info)?;
+        Ok(EncryptedBlob {
+            blob,
+            keys: config.keys,
+        })
+    }
+
+    fn open_read(config: EncryptedBlobConfig<B>) -> Result<EncryptedBlob<B::Read>, Error> {
+        let blob = B::open_read(config.blob)?;
+        Ok(EncryptedBlob {
+            blob,
+            keys: config.keys,
+        })
+    }
+
+    async fn set(&mut self, key: &str, value: Vec<u8>, atomic: Atomicity) -> Result<(), Error> {
+        let value = self.keys.seal(&value, key.as_bytes())?;
+        self.blob.set(key, value, atomic).await
+    }
+
+    async fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.blob.delete(key).await
+    }
+
+    async fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        // Encryption isn't deterministic, so compare the plaintexts here and
+        // then hand the underlying blob the exact ciphertext we read. If it
+        // changed in between, the inner compare_and_set catches it.
+        let sealed = self.blob.get(key).await?;
+        let current = match sealed.as_ref() {
+            Some(sealed) => Some(self.keys.open(
+                sealed,
+                key.as_bytes(),
+                &format!("blob {}", key),
+            )?),
+            None => None,
+        };
+        if current.as_deref() != expected {
+            return Ok(Err(current));
+        }
+        let value = self.keys.seal(&value, key.as_bytes())?;
+        match self
+            .blob
+            .compare_and_set(key, sealed.as_deref(), value)
+            .await?
+        {
+            Ok(()) => Ok(Ok(())),
+            Err(Some(sealed)) => {
+                let current = self
+                    .keys
+                    .open(&sealed, key.as_bytes(), &format!("blob {}", key))?;
+                Ok(Err(Some(current)))
+            }
+            Err(None) => Ok(Err(None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::mem::{MemBlob, MemLog, MemMultiRegistry};
+    use crate::storage::tests::{blob_impl_test, compare_and_set_impl_test, log_impl_test};
+
+    use super::*;
+
+    fn key(id: &str, material: u8) -> EncryptionKey {
+        EncryptionKey::new(id.into(), &[material; KEY_LEN]).expect("valid key")
+    }
+
+    fn keys(current: EncryptionKey, previous: Vec<EncryptionKey>) -> Keyring {
+        Keyring::new(current, previous).expect("valid keyring")
+    }
+
+    #[test]
+    fn encrypted_log() -> Result<(), Error> {
+        let mut registry = MemMultiRegistry::new();
+        let keyring = keys(key("k1", 1), vec![]);
+        log_impl_test(move |t| {
+            let log = registry.log(t.path, (t.reentrance_id, "log_impl_test").into())?;
+            Ok(EncryptedLog::new(log, keyring.clone()))
+        })
+    }
+
+    #[test]
+    fn log_at_rest() -> Result<(), Error> {
+        let mut log = EncryptedLog::new(
+            MemLog::new_no_reentrance("log_at_rest"),
+            keys(key("k1", 1), vec![]),
+        );
+        assert_eq!(log.write_sync(b"1".to_vec())?, SeqNo(0));
+        assert_eq!(log.write_sync(b"2".to_vec())?, SeqNo(1));
+
+        // Entries are bound to their seqno.
+        let mut sealed = Vec::new();
+        log.log.snapshot(|_, buf| {
+            sealed.push(buf.to_vec());
+            Ok(())
+        })?;
+        for buf in sealed {
+            log.log.write_sync(buf)?;
+        }
+        log.truncate(SeqNo(2))?;
+        assert_eq!(
+            log.snapshot(|_, _| Ok(())),
+            Err(Error::from(
+                "log entry SeqNo(2) failed authentication: it is corrupted or was not written at this location"
+            ))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn log_rotation() -> Result<(), Error> {
+        let mut log = EncryptedLog::new(
+            MemLog::new_no_reentrance("log_rotation"),
+            keys(key("k1", 1), vec![]),
+        );
+        log.write_sync(b"1".to_vec())?;
+
+        // After a rotation, new entries are written with the new key but old
+        // ones are still readable.
+        let mut log = EncryptedLog::new(log.log, keys(key("k2", 2), vec![key("k1", 1)]));
+        let seqno = log.write_sync(b"2".to_vec())?;
+        let mut entries = Vec::new();
+        log.snapshot(|_, buf| {
+            entries.push(buf.to_vec());
+            Ok(())
+        })?;
+        assert_eq!(entries, vec![b"1".to_vec(), b"2".to_vec()]);
+        assert_eq!(
+            log.key_ids()?,
+            vec!["k1".to_owned(), "k2".to_owned()].into_iter().collect()
+        );
+
+        // The old key is in use until every entry written under it has been
+        // truncated, after which it can be dropped.
+        log.truncate(seqno)?;
+        assert_eq!(log.key_ids()?, vec!["k2".to_owned()].into_iter().collect());
+        let log = EncryptedLog::new(log.log, keys(key("k2", 2), vec![]));
+        let mut entries = Vec::new();
+        log.snapshot(|_, buf| {
+            entries.push(buf.to_vec());
+            Ok(())
+        })?;
+        assert_eq!(entries, vec![b"2".to_vec()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn encrypted_blob() -> Result<(), Error> {
+        let registry = Arc::new(Mutex::new(MemMultiRegistry::new()));
+        let registry_read = registry.clone();
+        let keyring = keys(key("k1", 1), vec![]);
+        let keyring_read = keyring.clone();
+        blob_impl_test(
+            move |t| {
+                let blob = registry
+                    .lock()?
+                    .blob(t.path, (t.reentrance_id, "blob_impl_test").into())?;
+                Ok(EncryptedBlob::new(blob, keyring.clone()))
+            },
+            move |path| {
+                let blob = registry_read.lock()?.blob_read(path);
+                Ok(EncryptedBlob::new(blob, keyring_read.clone()))
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn encrypted_blob_compare_and_set() -> Result<(), Error> {
+        let mut blob = EncryptedBlob::new(
+            MemBlob::new_no_reentrance("encrypted_blob_compare_and_set"),
+            keys(key("k1", 1), vec![]),
+        );
+        compare_and_set_impl_test(&mut blob).await
+    }
+
+    #[tokio::test]
+    async fn at_rest() -> Result<(), Error> {
+        let mut blob = EncryptedBlob::new(
+            MemBlob::new_no_reentrance("at_rest"),
+            keys(key("k1", 1), vec![]),
+        );
+        blob.set("a", b"plaintext".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        let stored = blob.blob.get("a").await?.expect("value was set");
+        assert!(is_encrypted(&stored));
+        assert!(!stored
+            .windows(b"plaintext".len())
+            .any(|x| x == b"plaintext"));
+        assert_eq!(blob.get("a").await?, Some(b"plaintext".to_vec()));
+
+        // The same value encrypts differently each time.
+        blob.set("b", b"plaintext".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        assert_ne!(blob.blob.get("b").await?, Some(stored.clone()));
+
+        // Values are bound to their key.
+        blob.blob.set("c", stored, Atomicity::RequireAtomic).await?;
+        assert_eq!(
+            blob.get("c").await,
+            Err(Error::from(
+                "blob c failed authentication: it is corrupted or was not written at this location"
+            ))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wrong_key() -> Result<(), Error> {
+        let mut blob = EncryptedBlob::new(
+            MemBlob::new_no_reentrance("wrong_key"),
+            keys(key("k1", 1), vec![]),
+        );
+        blob.set("a", b"1".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        let blob = blob.blob;
+
+        // Unknown key id.
+        let blob = EncryptedBlob::new(blob, keys(key("k2", 2), vec![key("k3", 3)]));
+        assert_eq!(
+            blob.get("a").await,
+            Err(Error::from(
+                "blob a was encrypted with key k1, which is not one of the configured keys: k2, k3"
+            ))
+        );
+
+        // Right key id, wrong key material.
+        let blob = EncryptedBlob::new(blob.blob, keys(key("k1", 2), vec![]));
+        assert_eq!(
+            blob.get("a").await,
+            Err(Error::from(
+                "blob a could not be decrypted: the material configured for key k1 does not match the key it was encrypted with"
+            ))
+        );
+
+        // Unencrypted data.
+        let mut blob = blob.blob;
+        blob.set("b", b"1".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        let blob = EncryptedBlob::new(blob, keys(key("k1", 1), vec![]));
+        assert_eq!(
+            blob.get("b").await,
+            Err(Error::from("blob b is not encrypted"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rotation() -> Result<(), Error> {
+        let mut blob = EncryptedBlob::new(
+            MemBlob::new_no_reentrance("rotation"),
+            keys(key("k1", 1), vec![]),
+        );
+        blob.set("a", b"1".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+
+        // After a rotation, new values are written with the new key but old
+        // ones are still readable.
+        let mut blob = EncryptedBlob::new(blob.blob, keys(key("k2", 2), vec![key("k1", 1)]));
+        blob.set("b", b"2".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        assert_eq!(blob.get("a").await?, Some(b"1".to_vec()));
+        assert_eq!(blob.get("b").await?, Some(b"2".to_vec()));
+
+        // Re-encrypting only touches the values written under the old key.
+        assert_eq!(blob.rotate().await?, 1);
+        assert_eq!(blob.rotate().await?, 0);
+
+        // Now the old key can be dropped.
+        let blob = EncryptedBlob::new(blob.blob, keys(key("k2", 2), vec![]));
+        assert_eq!(blob.get("a").await?, Some(b"1".to_vec()));
+        assert_eq!(blob.get("b").await?, Some(b"2".to_vec()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn plaintext_migration() -> Result<(), Error> {
+        let mut blob = MemBlob::new_no_reentrance("plaintext_migration");
+        blob.set("a", b"1".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        blob.set(LOCK_KEY, b"lock".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+
+        // Rotation leaves plaintext alone, but migration encrypts everything
+        // except the lock file.
+        let mut blob = EncryptedBlob::new(blob, keys(key("k1", 1), vec![]));
+        blob.set("b", b"2".to_vec(), Atomicity::RequireAtomic)
+            .await?;
+        assert_eq!(blob.rotate().await?, 0);
+        assert_eq!(blob.encrypt_plaintext().await?, 1);
+        assert_eq!(blob.encrypt_plaintext().await?, 0);
+        assert_eq!(blob.get("a").await?, Some(b"1".to_vec()));
+        assert_eq!(blob.get("b").await?, Some(b"2".to_vec()));
+        assert_eq!(blob.blob.get(LOCK_KEY).await?, Some(b"lock".to_vec()));
+        Ok(())
+    }
+
+    #[test]
+    fn keyring_validation() {
+        assert_eq!(
+            EncryptionKey::new("k1".into(), &[0u8; 16]).map(|_| ()),
+            Err(Error::from("encryption key k1 must be 32 bytes, got 16"))
+        );
+        assert_eq!(
+            Keyring::new(key("k1", 1), vec![key("k1", 2)]).map(|_| ()),
+            Err(Error::from("duplicate encryption key id: k1"))
+        );
+        // Key material never shows up in debug output.
+        assert_eq!(
+            format!("{:?}", key("k1", 1)),
+            "EncryptionKey { id: \"k1\", .. }"
+        );
+    }
+}
@@ -12,6 +12,7 @@
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write as StdWrite};
 use std::ops::Range;
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
@@ -298,6 +299,12 @@ impl FileBlobCore {
             .ok_or_else(|| return Error::from("FileBlob unexpectedly closed"))
     }
 
+    fn base_dir(&self) -> Result<&Path, Error> {
+        self.base_dir
+            .as_deref()
+            .ok_or_else(|| Error::from("FileBlob unexpectedly closed"))
+    }
+
     fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
         let file_path = self.blob_path(key)?;
         let mut file = match File::open(file_path) {
@@ -388,6 +395,77 @@ impl FileBlob {
     fn lockfile_path(base_dir: &Path) -> PathBuf {
         base_dir.join(Self::LOCKFILE_PATH)
     }
+
+    /// Returns once this process holds an exclusive flock on the blob
+    /// directory, which is released when the returned file is dropped (or by
+    /// the OS, if this process crashes).
+    ///
+    /// Every write to this location, from this process or any other, holds the
+    /// lock for its duration. This makes compare_and_set atomic not only with
+    /// respect to every other compare_and_set, but also to plain sets and
+    /// deletes of the same key, which could otherwise land between its compare
+    /// and its write and be silently lost.
+    async fn lock_dir(&self) -> Result<File, Error> {
+        let dir = File::open(self.core.base_dir()?)?;
+        // Acquiring the lock may block for as long as another process holds
+        // it, so do it on the blocking thread pool (when there is one) instead
+        // of stalling whichever async task is running us.
+        if tokio::runtime::Handle::try_current().is_err() {
+            flock_exclusive(&dir)?;
+            return Ok(dir);
+        }
+        tokio::task::spawn_blocking(move || flock_exclusive(&dir).map(|()| dir))
+            .await
+            .map_err(|err| Error::from(format!("FileBlob lock task: {}", err)))?
+    }
+
+    fn set_locked(&mut self, key: &str, value: Vec<u8>, atomic: Atomicity) -> Result<(), Error> {
+        let file_path = self.core.blob_path(key)?;
+        match atomic {
+            Atomicity::RequireAtomic => {
+                // To implement require_atomic, write to a temp file and rename
+                // it into place.
+                let mut tmp_name = file_path.clone();
+                debug_assert_eq!(tmp_name.extension(), None);
+                tmp_name.set_extension("tmp");
+                // NB: Don't use create_new(true) for this so that if we have a
+                // partial one from a previous crash, it will just get
+                // overwritten (which is safe).
+                let mut file = File::create(&tmp_name)?;
+                file.write_all(&value[..])?;
+
+                fail_point!("fileblob_set_sync", |_| {
+                    Err(Error::from(format!(
+                        "FileBlob::set sync fail point reached for file {:?}",
+                        file_path
+                    )))
+                });
+
+                file.sync_all()?;
+                fs::rename(tmp_name, &file_path)?;
+                // TODO: We also need to fsync the directory to be truly
+                // confidant that this is permanently there. It doesn't seem
+                // like this is available in the stdlib, find a crate for it?
+            }
+            Atomicity::AllowNonAtomic => {
+                let mut file = OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .open(&file_path)?;
+                file.write_all(&value[..])?;
+
+                fail_point!("fileblob_set_sync", |_| {
+                    Err(Error::from(format!(
+                        "FileBlob::set sync fail point reached for file {:?}",
+                        file_path
+                    )))
+                });
+
+                file.sync_all()?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -446,54 +524,12 @@ impl Blob for FileBlob {
     }
 
     async fn set(&mut self, key: &str, value: Vec<u8>, atomic: Atomicity) -> Result<(), Error> {
-        let file_path = self.core.blob_path(key)?;
-        match atomic {
-            Atomicity::RequireAtomic => {
-                // To implement require_atomic, write to a temp file and rename
-                // it into place.
-                let mut tmp_name = file_path.clone();
-                debug_assert_eq!(tmp_name.extension(), None);
-                tmp_name.set_extension("tmp");
-                // NB: Don't use create_new(true) for this so that if we have a
-                // partial one from a previous crash, it will just get
-                // overwritten (which is safe).
-                let mut file = File::create(&tmp_name)?;
-                file.write_all(&value[..])?;
-
-                fail_point!("fileblob_set_sync", |_| {
-                    Err(Error::from(format!(
-                        "FileBlob::set sync fail point reached for file {:?}",
-                        file_path
-                    )))
-                });
-
-                file.sync_all()?;
-                fs::rename(tmp_name, &file_path)?;
-                // TODO: We also need to fsync the directory to be truly
-                // confidant that this is permanently there. It doesn't seem
-                // like this is available in the stdlib, find a crate for it?
-            }
-            Atomicity::AllowNonAtomic => {
-                let mut file = OpenOptions::new()
-                    .write(true)
-                    .create(true)
-                    .open(&file_path)?;
-                file.write_all(&value[..])?;
-
-                fail_point!("fileblob_set_sync", |_| {
-                    Err(Error::from(format!(
-                        "FileBlob::set sync fail point reached for file {:?}",
-                        file_path
-                    )))
-                });
-
-                file.sync_all()?;
-            }
-        }
-        Ok(())
+        let _dir = self.lock_dir().await?;
+        self.set_locked(key, value, atomic)
     }
 
     async fn delete(&mut self, key: &str) -> Result<(), Error> {
+        let _dir = self.lock_dir().await?;
         let file_path = self.core.blob_path(key)?;
         // TODO: strict correctness requires that we fsync the parent directory
         // as well after file removal.
@@ -506,6 +542,33 @@ impl Blob for FileBlob {
 
         Ok(())
     }
+
+    async fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        let _dir = self.lock_dir().await?;
+        let current = self.core.get(key)?;
+        if current.as_deref() != expected {
+            return Ok(Err(current));
+        }
+        self.set_locked(key, value, Atomicity::RequireAtomic)?;
+        Ok(Ok(()))
+    }
+}
+
+/// Blocks until this process holds an exclusive advisory lock on `file`. The
+/// lock is released when `file` is closed.
+fn flock_exclusive(file: &File) -> Result<(), Error> {
+    // SAFETY: flock has no memory safety requirements and the fd is valid for
+    // as long as `file` is.
+    let ret = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
 }
 
 fn file_storage_lock(lockfile_path: &Path, new_lock: LockInfo) -> Result<File, Error> {
@@ -531,7 +594,11 @@ fn file_storage_lock(lockfile_path: &Path, new_lock: LockInfo) -> Result<File, E
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::tests::{blob_impl_test, log_impl_test};
+    use std::thread;
+
+    use futures_executor::block_on;
+
+    use crate::storage::tests::{blob_impl_test, compare_and_set_impl_test, log_impl_test};
 
     use super::*;
 
@@ -552,6 +619,54 @@ mod tests {
         .await
     }
 
+    #[tokio::test]
+    async fn file_blob_compare_and_set() -> Result<(), Error> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut blob = FileBlob::open_exclusive(
+            temp_dir.path().into(),
+            ("reentrance0", "file_blob_test").into(),
+        )?;
+        compare_and_set_impl_test(&mut blob).await
+    }
+
+    // Two handles to the same location (as if from two processes) racing to
+    // increment a counter with compare_and_set never lose an increment. The
+    // counter is stored as the length of the value.
+    #[test]
+    fn file_blob_compare_and_set_concurrent() -> Result<(), Error> {
+        const INCREMENTS: usize = 100;
+        let temp_dir = tempfile::tempdir()?;
+        let open = || {
+            FileBlob::open_exclusive(
+                temp_dir.path().into(),
+                LockInfo::new_shared("file_blob_test".into()),
+            )
+        };
+        let increment = |mut blob: FileBlob| {
+            move || -> Result<(), Error> {
+                let mut done = 0;
+                while done < INCREMENTS {
+                    let current = block_on(blob.get("counter"))?;
+                    let next = vec![0; current.as_ref().map_or(0, |x| x.len()) + 1];
+                    if block_on(blob.compare_and_set("counter", current.as_deref(), next))?.is_ok()
+                    {
+                        done += 1;
+                    }
+                }
+                Ok(())
+            }
+        };
+        let t0 = thread::spawn(increment(open()?));
+        let t1 = thread::spawn(increment(open()?));
+        t0.join().expect("thread doesn't panic")?;
+        t1.join().expect("thread doesn't panic")?;
+
+        let blob = FileBlob::open_read(temp_dir.path().into())?;
+        let counter = block_on(blob.get("counter"))?.map(|x| x.len());
+        assert_eq!(counter, Some(2 * INCREMENTS));
+        Ok(())
+    }
+
     #[test]
     fn file_log() -> Result<(), Error> {
         let temp_dir = tempfile::tempdir()?;
@@ -22,7 +22,7 @@ use timely::PartialOrder;
 use uuid::Uuid;
 
 use crate::error::Error;
-use crate::indexed::background::{CompactTraceReq, Maintainer};
+use crate::indexed::background::{CompactTraceReq, CompactTraceRes, CompactionPolicy, Maintainer};
 use crate::indexed::cache::BlobCache;
 use crate::indexed::columnar::ColumnarRecordsVec;
 use crate::indexed::encoding::{
@@ -30,7 +30,8 @@ use crate::indexed::encoding::{
 };
 use crate::indexed::{BlobUnsealedBatch, Id, Snapshot};
 use crate::pfuture::PFuture;
-use crate::storage::{Blob, SeqNo};
+use crate::schema::{Schema, StreamSchema};
+use crate::storage::{Blob, BlobRead, SeqNo};
 
 /// A persistent, compacting data structure containing indexed `(Key, Value,
 /// Time, Diff)` entries.
@@ -140,6 +141,8 @@ use crate::storage::{Blob, SeqNo};
 /// - The compaction levels across the list of batches in a trace are weakly
 ///   decreasing (non-increasing) when iterating from oldest to most recent time
 ///   intervals.
+/// - The schema version of every batch is <= the arrangement's schema version.
+///   Batches at older versions are migrated when read and when compacted.
 /// - TODO: Space usage.
 #[derive(Debug)]
 pub struct Arrangement {
@@ -151,6 +154,8 @@ pub struct Arrangement {
 
     unsealed_batches: Vec<UnsealedBatchMeta>,
     trace_batches: Vec<TraceBatchMeta>,
+
+    schema: StreamSchema,
 }
 
 impl Arrangement {
@@ -163,6 +168,7 @@ impl Arrangement {
             since: meta.since,
             unsealed_batches: meta.unsealed_batches,
             trace_batches: meta.trace_batches,
+            schema: StreamSchema::new(meta.schema_version),
         }
     }
 
@@ -179,9 +185,29 @@ impl Arrangement {
             seal: self.seal.clone(),
             unsealed_batches: self.unsealed_batches.clone(),
             trace_batches: self.trace_batches.clone(),
+            schema_version: self.schema.version(),
         }
     }
 
+    /// Registers the [Schema] used to read and write this arrangement,
+    /// advancing its schema version to match.
+    ///
+    /// Returns an error if the schema is older than the arrangement's current
+    /// schema version.
+    pub fn register_schema(&mut self, schema: Schema) -> Result<(), Error> {
+        self.schema.register(schema)
+    }
+
+    /// The [Schema] version of newly written data.
+    pub fn schema_version(&self) -> u64 {
+        self.schema.version()
+    }
+
+    /// The [Schema] set by [Self::register_schema], if any.
+    pub fn registered_schema(&self) -> Option<&Schema> {
+        self.schema.schema()
+    }
+
     /// An open upper bound on the seqnos of contained updates.
     pub fn unsealed_seqno_upper(&self) -> SeqNo {
         self.unsealed_batches
@@ -191,7 +217,7 @@ impl Arrangement {
 
     /// Returns a consistent read of all the updates contained in this
     /// arrangement.
-    pub fn snapshot<L: Blob>(
+    pub fn snapshot<L: BlobRead>(
         &self,
         seqno: SeqNo,
         blob: &BlobCache<L>,
@@ -205,10 +231,12 @@ impl Arrangement {
     /// Write a [BlobUnsealedBatch] to [Blob] storage and return the corresponding
     /// [UnsealedBatchMeta].
     ///
-    /// The input batch is expected to satisfy all [BlobUnsealedBatch] invariants.
+    /// The input batch is expected to satisfy all [BlobUnsealedBatch] invariants
+    /// and to have been written with the given schema version.
     fn unsealed_write_batch<L: Blob>(
         &mut self,
         batch: BlobUnsealedBatch,
+        schema_version: u64,
         blob: &mut BlobCache<L>,
     ) -> Result<UnsealedBatchMeta, Error> {
         let key = Self::new_blob_key();
@@ -255,16 +283,19 @@ impl Arrangement {
             ts_upper,
             ts_lower,
             size_bytes,
+            schema_version,
         })
     }
 
     /// Writes the given batch to [Blob] storage and logically adds the contained
     /// updates to this unsealed.
+    ///
+    /// Returns the number of bytes written.
     pub fn unsealed_append<L: Blob>(
         &mut self,
         batch: BlobUnsealedBatch,
         blob: &mut BlobCache<L>,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         if batch.desc.start != self.unsealed_seqno_upper() {
             return Err(Error::from(format!(
                 "batch lower doesn't match seqno_upper {:?}: {:?}",
@@ -306,14 +337,15 @@ impl Arrangement {
             }
         }
 
-        let meta = self.unsealed_write_batch(batch, blob)?;
+        let meta = self.unsealed_write_batch(batch, self.schema.version(), blob)?;
+        let written_bytes = meta.size_bytes;
         self.unsealed_batches.push(meta);
-        Ok(())
+        Ok(written_bytes)
     }
 
     /// Returns a consistent read of the updates contained in this unsealed
     /// matching the given filters (in practice, everything not in Trace).
-    pub fn unsealed_snapshot<L: Blob>(
+    pub fn unsealed_snapshot<L: BlobRead>(
         &self,
         ts_lower: Antichain<u64>,
         ts_upper: Antichain<u64>,
@@ -334,13 +366,17 @@ impl Arrangement {
             // - ts_lower <= hi
             // - ts_upper > lo
             if ts_lower.less_equal(&meta.ts_upper) && !ts_upper.less_equal(&meta.ts_lower) {
-                batches.push(blob.get_unsealed_batch_async(&meta.key));
+                batches.push((
+                    meta.schema_version,
+                    blob.get_unsealed_batch_async(&meta.key),
+                ));
             }
         }
 
         Ok(UnsealedSnapshot {
             ts_lower,
             ts_upper,
+            schema: self.schema.clone(),
             batches,
         })
     }
@@ -348,13 +384,25 @@ impl Arrangement {
     /// Atomically moves all writes in unsealed not in advance of the trace's
     /// seal frontier into the trace and does any necessary resulting eviction
     /// work to remove unnecessary batches.
-    pub fn unsealed_drain<L: Blob>(&mut self, blob: &mut BlobCache<L>) -> Result<(), Error> {
+    ///
+    /// Returns the number of bytes written.
+    pub fn unsealed_drain<L: Blob>(&mut self, blob: &mut BlobCache<L>) -> Result<u64, Error> {
         // If the trace's physical frontier matches the arrangement's logical
         // seal frontier, then nothing to do.
         let seal = self.get_seal();
         let trace_upper = self.trace_ts_upper();
         if seal == trace_upper {
-            return Ok(());
+            return Ok(0);
+        }
+
+        // The drained data is written to trace at the current schema version,
+        // so wait until we're able to migrate everything still in unsealed.
+        if !self
+            .unsealed_batches
+            .iter()
+            .all(|b| self.schema.can_read(b.schema_version))
+        {
+            return Ok(0);
         }
 
         let desc = Description::new(
@@ -379,7 +427,7 @@ impl Arrangement {
         // everything works perfectly well when the trace upper hasn't yet
         // caught up to sealed.
         if updates.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
 
         // Trace batches are required to be sorted and consolidated by ((k, v), t)
@@ -436,7 +484,9 @@ impl Arrangement {
             updates,
         };
 
-        self.unsealed_write_batch(new_batch, blob)
+        // Trimming doesn't migrate the data, so the new batch keeps the
+        // original's schema version.
+        self.unsealed_write_batch(new_batch, batch.schema_version, blob)
     }
 
     /// Take one step towards shrinking the representation of this unsealed.
@@ -540,11 +590,13 @@ impl Arrangement {
 
     /// Writes the given batch to [Blob] storage and logically adds the contained
     /// updates to this trace.
+    ///
+    /// Returns the number of bytes written.
     pub fn trace_append<B: Blob>(
         &mut self,
         batch: BlobTraceBatch,
         blob: &mut BlobCache<B>,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         if &self.trace_ts_upper() != batch.desc.lower() {
             return Err(Error::from(format!(
                 "batch lower doesn't match trace upper {:?}: {:?}",
@@ -563,66 +615,101 @@ impl Arrangement {
             desc,
             level: 0,
             size_bytes,
+            schema_version: self.schema.version(),
         });
-        Ok(())
+        Ok(size_bytes)
     }
 
     /// Returns a consistent read of all the updates contained in this trace.
-    pub fn trace_snapshot<B: Blob>(&self, blob: &BlobCache<B>) -> TraceSnapshot {
+    pub fn trace_snapshot<B: BlobRead>(&self, blob: &BlobCache<B>) -> TraceSnapshot {
         let ts_upper = self.trace_ts_upper();
         let since = self.since();
         let mut batches = Vec::with_capacity(self.trace_batches.len());
         for meta in self.trace_batches.iter() {
-            batches.push(blob.get_trace_batch_async(&meta.key));
+            batches.push((meta.schema_version, blob.get_trace_batch_async(&meta.key)));
         }
         TraceSnapshot {
             ts_upper,
             since,
+            schema: self.schema.clone(),
             batches,
         }
     }
 
-    /// Take one step towards compacting the trace.
+    /// Returns a request to merge the next pair of trace batches chosen by the
+    /// given [CompactionPolicy], if any.
+    ///
+    /// Only batches entirely before the since frontier and readable with the
+    /// registered schema are eligible.
+    pub fn trace_next_compaction(&self, policy: &dyn CompactionPolicy) -> Option<CompactTraceReq> {
+        let eligible = self
+            .trace_batches
+            .iter()
+            .take_while(|b| {
+                PartialOrder::less_equal(b.desc.upper(), &self.since)
+                    && self.schema.can_read(b.schema_version)
+            })
+            .count();
+        let i = policy.next_merge(&self.trace_batches[..eligible])?;
+        Some(CompactTraceReq {
+            b0: self.trace_batches[i - 1].clone(),
+            b1: self.trace_batches[i].clone(),
+            since: self.since.clone(),
+            schema: self.schema.clone(),
+        })
+    }
+
+    /// Swaps the batch produced by a compaction in for the batches it merged.
     ///
     /// Returns a list of trace batches that can now be physically deleted after
     /// the compaction step is committed to durable storage.
+    pub fn trace_apply_compaction(
+        &mut self,
+        res: CompactTraceRes,
+    ) -> Result<Vec<TraceBatchMeta>, Error> {
+        let i = (1..self.trace_batches.len())
+            .find(|&i| {
+                self.trace_batches[i - 1].key == res.req.b0.key
+                    && self.trace_batches[i].key == res.req.b1.key
+            })
+            .ok_or_else(|| {
+                Error::from(format!(
+                    "compacted batches are no longer adjacent in trace: {:?} and {:?}",
+                    res.req.b0, res.req.b1
+                ))
+            })?;
+
+        // TODO: more performant way to do this?
+        let mut new_batch = res.merged;
+        let mut deleted = vec![self.trace_batches.remove(i)];
+        mem::swap(&mut self.trace_batches[i - 1], &mut new_batch);
+        deleted.push(new_batch);
+
+        // Sanity check that the modified list of batches satisfies
+        // all invariants.
+        if cfg!(any(debug_assertions, test)) {
+            self.meta().validate()?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Take one step towards compacting the trace.
+    ///
+    /// Returns the number of bytes written and a list of trace batches that can
+    /// now be physically deleted after the compaction step is committed to
+    /// durable storage.
     pub fn trace_step<B: Blob>(
         &mut self,
         maintainer: &Maintainer<B>,
     ) -> Result<(u64, Vec<TraceBatchMeta>), Error> {
-        let mut written_bytes = 0;
-        let mut deleted = vec![];
-        // TODO: should we remember our position in this list?
-        for i in 1..self.trace_batches.len() {
-            if (self.trace_batches[i - 1].level == self.trace_batches[i].level)
-                && PartialOrder::less_equal(self.trace_batches[i].desc.upper(), &self.since)
-            {
-                let b0 = self.trace_batches[i - 1].clone();
-                let b1 = self.trace_batches[i].clone();
-
-                let req = CompactTraceReq {
-                    b0,
-                    b1,
-                    since: self.since.clone(),
-                };
-                let res = maintainer.compact_trace(req).recv()?;
-                let mut new_batch = res.merged;
-                written_bytes += new_batch.size_bytes;
-
-                // TODO: more performant way to do this?
-                deleted.push(self.trace_batches.remove(i));
-                mem::swap(&mut self.trace_batches[i - 1], &mut new_batch);
-                deleted.push(new_batch);
-
-                // Sanity check that the modified list of batches satisfies
-                // all invariants.
-                if cfg!(any(debug_assertions, test)) {
-                    self.meta().validate()?;
-                }
-
-                break;
-            }
-        }
+        let req = match self.trace_next_compaction(maintainer.policy()) {
+            Some(req) => req,
+            None => return Ok((0, vec![])),
+        };
+        let res = maintainer.compact_trace(req).recv()?;
+        let written_bytes = res.merged.size_bytes;
+        let deleted = self.trace_apply_compaction(res)?;
         Ok((written_bytes, deleted))
     }
 }
@@ -635,7 +722,8 @@ pub struct UnsealedSnapshot {
     pub ts_lower: Antichain<u64>,
     /// An open upper bound on the times of the contained updates.
     pub ts_upper: Antichain<u64>,
-    batches: Vec<PFuture<Arc<BlobUnsealedBatch>>>,
+    schema: StreamSchema,
+    batches: Vec<(u64, PFuture<Arc<BlobUnsealedBatch>>)>,
 }
 
 impl Snapshot<Vec<u8>, Vec<u8>> for UnsealedSnapshot {
@@ -646,6 +734,7 @@ impl Snapshot<Vec<u8>, Vec<u8>> for UnsealedSnapshot {
         iters.resize_with(num_iters.get(), || UnsealedSnapshotIter {
             ts_lower: self.ts_lower.clone(),
             ts_upper: self.ts_upper.clone(),
+            schema: self.schema.clone(),
             current_batch: Vec::new(),
             batches: VecDeque::new(),
         });
@@ -669,9 +758,11 @@ pub struct UnsealedSnapshotIter {
     ts_lower: Antichain<u64>,
     /// An open upper bound on the times of the contained updates.
     ts_upper: Antichain<u64>,
+    /// Used to migrate batches written at older schema versions.
+    schema: StreamSchema,
 
     current_batch: Vec<((Vec<u8>, Vec<u8>), u64, isize)>,
-    batches: VecDeque<PFuture<Arc<BlobUnsealedBatch>>>,
+    batches: VecDeque<(u64, PFuture<Arc<BlobUnsealedBatch>>)>,
 }
 
 impl fmt::Debug for UnsealedSnapshotIter {
@@ -679,6 +770,7 @@ impl fmt::Debug for UnsealedSnapshotIter {
         f.debug_struct("UnsealedSnapshotIter")
             .field("ts_lower", &self.ts_lower)
             .field("ts_upper", &self.ts_upper)
+            .field("schema", &self.schema)
             .field("current_batch(len)", &self.current_batch.len())
             .field("batches", &self.batches)
             .finish()
@@ -695,7 +787,7 @@ impl Iterator for UnsealedSnapshotIter {
                 return Some(Ok(update));
             } else {
                 // current_batch is empty, find a new one.
-                let b = match self.batches.pop_front() {
+                let (schema_version, b) = match self.batches.pop_front() {
                     None => return None,
                     Some(b) => b,
                 };
@@ -708,16 +800,25 @@ impl Iterator for UnsealedSnapshotIter {
                         // because those are not guaranteed to be in any order.
                         let ts_lower = self.ts_lower.borrow();
                         let ts_upper = self.ts_upper.borrow();
-                        self.current_batch.extend(
-                            b.updates
-                                .iter()
-                                .rev()
-                                .flat_map(|u| u.iter())
-                                .filter(|(_, ts, _)| {
-                                    ts_lower.less_equal(&ts) && !ts_upper.less_equal(&ts)
-                                })
-                                .map(|((k, v), t, d)| ((k.to_vec(), v.to_vec()), t, d)),
-                        );
+                        let schema = &self.schema;
+                        let updates = b
+                            .updates
+                            .iter()
+                            .rev()
+                            .flat_map(|u| u.iter())
+                            .filter(|(_, ts, _)| {
+                                ts_lower.less_equal(&ts) && !ts_upper.less_equal(&ts)
+                            })
+                            .map(|((k, v), t, d)| {
+                                schema
+                                    .migrate(schema_version, k.to_vec(), v.to_vec())
+                                    .map(|kv| (kv, t, d))
+                            })
+                            .collect::<Result<Vec<_>, Error>>();
+                        match updates {
+                            Ok(updates) => self.current_batch.extend(updates),
+                            Err(err) => return Some(Err(err)),
+                        }
                         continue;
                     }
                     Err(err) => return Some(Err(err)),
@@ -738,7 +839,8 @@ pub struct TraceSnapshot {
     /// All updates not at times greater than this frontier must be advanced
     /// to a time that is equivalent to this frontier.
     pub since: Antichain<u64>,
-    batches: Vec<PFuture<Arc<BlobTraceBatch>>>,
+    schema: StreamSchema,
+    batches: Vec<(u64, PFuture<Arc<BlobTraceBatch>>)>,
 }
 
 impl Snapshot<Vec<u8>, Vec<u8>> for TraceSnapshot {
@@ -746,7 +848,11 @@ impl Snapshot<Vec<u8>, Vec<u8>> for TraceSnapshot {
 
     fn into_iters(self, num_iters: NonZeroUsize) -> Vec<Self::Iter> {
         let mut iters = Vec::with_capacity(num_iters.get());
-        iters.resize_with(num_iters.get(), TraceSnapshotIter::default);
+        iters.resize_with(num_iters.get(), || TraceSnapshotIter {
+            schema: self.schema.clone(),
+            current_batch: Vec::new(),
+            batches: VecDeque::new(),
+        });
         // TODO: This should probably distribute batches based on size, but for
         // now it's simpler to round-robin them.
         for (i, batch) in self.batches.into_iter().enumerate() {
@@ -763,22 +869,17 @@ impl Snapshot<Vec<u8>, Vec<u8>> for TraceSnapshot {
 // in roughly increasing timestamp order, but it's unclear if this is in any way
 // important.
 pub struct TraceSnapshotIter {
-    current_batch: Vec<((Vec<u8>, Vec<u8>), u64, isize)>,
-    batches: VecDeque<PFuture<Arc<BlobTraceBatch>>>,
-}
+    /// Used to migrate batches written at older schema versions.
+    schema: StreamSchema,
 
-impl Default for TraceSnapshotIter {
-    fn default() -> Self {
-        TraceSnapshotIter {
-            current_batch: Vec::new(),
-            batches: VecDeque::new(),
-        }
-    }
+    current_batch: Vec<((Vec<u8>, Vec<u8>), u64, isize)>,
+    batches: VecDeque<(u64, PFuture<Arc<BlobTraceBatch>>)>,
 }
 
 impl fmt::Debug for TraceSnapshotIter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TraceSnapshotIter")
+            .field("schema", &self.schema)
             .field("current_batch(len)", &self.current_batch.len())
             .field("batches", &self.batches)
             .finish()
@@ -795,7 +896,7 @@ impl Iterator for TraceSnapshotIter {
                 return Some(Ok(update));
             } else {
                 // current_batch is empty, find a new one.
-                let b = match self.batches.pop_front() {
+                let (schema_version, b) = match self.batches.pop_front() {
                     None => return None,
                     Some(b) => b,
                 };
@@ -803,7 +904,20 @@ impl Iterator for TraceSnapshotIter {
                     Ok(b) => {
                         // Reverse the updates so we can pop them off the back
                         // in roughly increasing time order.
-                        self.current_batch.extend(b.updates.iter().rev().cloned());
+                        let schema = &self.schema;
+                        let updates = b
+                            .updates
+                            .iter()
+                            .rev()
+                            .cloned()
+                            .map(|((k, v), t, d)| {
+                                schema.migrate(schema_version, k, v).map(|kv| (kv, t, d))
+                            })
+                            .collect::<Result<Vec<_>, Error>>();
+                        match updates {
+                            Ok(updates) => self.current_batch.extend(updates),
+                            Err(err) => return Some(Err(err)),
+                        }
                         continue;
                     }
                     Err(err) => return Some(Err(err)),
@@ -955,6 +1069,7 @@ mod tests {
             ts_upper,
             ts_lower,
             size_bytes,
+            schema_version: 0,
         }
     }
 
@@ -1017,6 +1132,7 @@ mod tests {
                 desc: desc_from(0, 2, 0),
                 level: 1,
                 size_bytes: 0,
+                schema_version: 0,
             }],
             ..Default::default()
         });
@@ -1038,7 +1154,7 @@ mod tests {
             desc: SeqNo(0)..SeqNo(1),
             updates: columnar_records(vec![(("k".into(), "v".into()), 2, 1)]),
         };
-        assert_eq!(f.unsealed_append(batch, &mut blob), Ok(()));
+        assert!(f.unsealed_append(batch, &mut blob).is_ok());
 
         Ok(())
     }
@@ -1068,7 +1184,7 @@ mod tests {
             ]),
         };
 
-        assert_eq!(f.unsealed_append(batch, &mut blob), Ok(()));
+        assert!(f.unsealed_append(batch, &mut blob).is_ok());
 
         // Check that the batch has the correct min/max time bounds.
         let meta = &f.unsealed_batches[0];
@@ -1266,6 +1382,7 @@ mod tests {
                 desc: desc_from(0, 10, 5),
                 level: 1,
                 size_bytes: 0,
+                schema_version: 0,
             }],
             since: Antichain::from_elem(5),
             seal: Antichain::from_elem(10),
@@ -1307,6 +1424,7 @@ mod tests {
                 ),
                 level: 1,
                 size_bytes: 0,
+                schema_version: 0,
             }],
             since: Antichain::from_elem(5),
             seal: Antichain::from_elem(10),
@@ -1349,7 +1467,7 @@ mod tests {
             ],
         };
 
-        assert_eq!(t.trace_append(batch, &mut blob), Ok(()));
+        assert!(t.trace_append(batch, &mut blob).is_ok());
         let batch = BlobTraceBatch {
             desc: desc_from(1, 3, 0),
             updates: vec![
@@ -1357,13 +1475,13 @@ mod tests {
                 (("k3".into(), "v3".into()), 2, 1),
             ],
         };
-        assert_eq!(t.trace_append(batch, &mut blob), Ok(()));
+        assert!(t.trace_append(batch, &mut blob).is_ok());
 
         let batch = BlobTraceBatch {
             desc: desc_from(3, 9, 0),
             updates: vec![(("k".into(), "v".into()), 5, 1)],
         };
-        assert_eq!(t.trace_append(batch, &mut blob), Ok(()));
+        assert!(t.trace_append(batch, &mut blob).is_ok());
 
         t.validate_allow_compaction(&Antichain::from_elem(3))?;
         t.allow_compaction(Antichain::from_elem(3));
@@ -1393,6 +1511,7 @@ mod tests {
                     desc: desc_from(0, 3, 3),
                     level: 1,
                     size_bytes: 0,
+                    schema_version: 0,
                 },
                 TraceBatchMeta {
                     key: "KEY".to_string(),
@@ -1400,6 +1519,7 @@ mod tests {
                     desc: desc_from(3, 9, 0),
                     level: 0,
                     size_bytes: 0,
+                    schema_version: 0,
                 },
             ]
         );
@@ -1426,7 +1546,7 @@ mod tests {
             desc: desc_from(9, 10, 0),
             updates: vec![(("k".into(), "v".into()), 9, 1)],
         };
-        assert_eq!(t.trace_append(batch, &mut blob), Ok(()));
+        assert!(t.trace_append(batch, &mut blob).is_ok());
         t.validate_allow_compaction(&Antichain::from_elem(10))?;
         t.allow_compaction(Antichain::from_elem(10));
         let (written_bytes, deleted_batches) = t.trace_step(&maintainer)?;
@@ -1452,6 +1572,7 @@ mod tests {
                     desc: desc_from(0, 3, 3),
                     level: 1,
                     size_bytes: 0,
+                    schema_version: 0,
                 },
                 TraceBatchMeta {
                     key: "KEY".to_string(),
@@ -1459,6 +1580,7 @@ mod tests {
                     desc: desc_from(3, 10, 10),
                     level: 0,
                     size_bytes: 0,
+                    schema_version: 0,
                 },
             ]
         );
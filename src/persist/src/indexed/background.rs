@@ -9,7 +9,9 @@
 
 //! A runtime for background asynchronous maintenance of stored data.
 
+use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 
 use differential_dataflow::lattice::Lattice;
 use differential_dataflow::trace::Description;
@@ -22,6 +24,7 @@ use crate::indexed::arrangement::Arrangement;
 use crate::indexed::cache::BlobCache;
 use crate::indexed::encoding::{BlobTraceBatch, TraceBatchMeta};
 use crate::pfuture::PFuture;
+use crate::schema::StreamSchema;
 use crate::storage::Blob;
 
 /// A request to merge two trace batches and write the results to blob storage.
@@ -34,6 +37,16 @@ pub struct CompactTraceReq {
     /// The since frontier to be used for the output batch. This must be at or
     /// in advance of the since frontier for both of the input batch.
     pub since: Antichain<u64>,
+    /// The schema used to migrate both input batches to the version of the
+    /// output batch.
+    pub(crate) schema: StreamSchema,
+}
+
+impl CompactTraceReq {
+    /// The total size of the batches being merged.
+    pub fn size_bytes(&self) -> u64 {
+        self.b0.size_bytes + self.b1.size_bytes
+    }
 }
 
 /// A successful merge.
@@ -45,6 +58,185 @@ pub struct CompactTraceRes {
     pub merged: TraceBatchMeta,
 }
 
+/// A strategy for choosing which trace batches to merge.
+///
+/// Trace batches are ordered from oldest to newest and must remain so, so a
+/// compaction always merges some batch with the one immediately after it. Each
+/// batch is also tagged with a compaction level, which must be weakly
+/// decreasing from oldest to newest.
+pub trait CompactionPolicy: fmt::Debug + Send + Sync {
+    /// Returns the index `i` such that batches `i - 1` and `i` should be
+    /// merged next, if any should.
+    ///
+    /// `batches` is the prefix of the trace that is eligible for compaction,
+    /// oldest first.
+    fn next_merge(&self, batches: &[TraceBatchMeta]) -> Option<usize>;
+
+    /// Returns the compaction level of the batch produced by merging `b0` and
+    /// the batch after it, `b1`, given the size of the result.
+    ///
+    /// To keep levels weakly decreasing, this must be no less than the level
+    /// of `b1` and no greater than the level of the batch before `b0`.
+    fn merged_level(&self, b0: &TraceBatchMeta, b1: &TraceBatchMeta, size_bytes: u64) -> u64;
+}
+
+/// A [CompactionPolicy] that merges adjacent batches at the same level.
+///
+/// A merged batch is promoted to the next level if it's bigger than both of
+/// its inputs, so every level holds batches of roughly similar size. This
+/// writes each update a logarithmic number of times at the cost of leaving
+/// more batches around to be read.
+#[derive(Clone, Debug, Default)]
+pub struct SizeTieredPolicy;
+
+impl CompactionPolicy for SizeTieredPolicy {
+    fn next_merge(&self, batches: &[TraceBatchMeta]) -> Option<usize> {
+        (1..batches.len()).find(|&i| batches[i - 1].level == batches[i].level)
+    }
+
+    fn merged_level(&self, b0: &TraceBatchMeta, b1: &TraceBatchMeta, size_bytes: u64) -> u64 {
+        // Only upgrade the compaction level if we know this new batch represents
+        // an increase in data over both of its parents so that we know we need
+        // even more additional batches to amortize the cost of compacting it in
+        // the future.
+        if size_bytes > b0.size_bytes && size_bytes > b1.size_bytes {
+            b0.level + 1
+        } else {
+            b0.level
+        }
+    }
+}
+
+/// A [CompactionPolicy] that keeps each batch at least `fanout` times the size
+/// of the batch after it.
+///
+/// This keeps the number of batches in the trace small, which makes reads
+/// cheaper, at the cost of rewriting data more often than [SizeTieredPolicy].
+/// Levels are not used to pick merges, so a merged batch keeps the level of
+/// the older of its inputs.
+#[derive(Clone, Debug)]
+pub struct LeveledPolicy {
+    fanout: u64,
+}
+
+impl LeveledPolicy {
+    /// Returns a new [LeveledPolicy] with the given fanout, which must be at
+    /// least 2.
+    pub fn new(fanout: u64) -> Result<Self, Error> {
+        if fanout < 2 {
+            return Err(Error::from(format!(
+                "invalid leveled compaction fanout {}: must be at least 2",
+                fanout
+            )));
+        }
+        Ok(LeveledPolicy { fanout })
+    }
+}
+
+impl CompactionPolicy for LeveledPolicy {
+    fn next_merge(&self, batches: &[TraceBatchMeta]) -> Option<usize> {
+        (1..batches.len()).find(|&i| {
+            batches[i - 1].size_bytes < batches[i].size_bytes.saturating_mul(self.fanout)
+        })
+    }
+
+    fn merged_level(&self, b0: &TraceBatchMeta, _b1: &TraceBatchMeta, _size_bytes: u64) -> u64 {
+        b0.level
+    }
+}
+
+/// Configuration for how a [Maintainer] compacts traces.
+#[derive(Debug, Clone)]
+pub struct CompactionConfig {
+    /// Which trace batches to merge
+    policy: Arc<dyn CompactionPolicy>,
+    /// Limit on the number of merges in flight at once
+    max_concurrent: usize,
+    /// Limit on the rate at which batches may be read for merging
+    rate_limit_bytes_per_second: Option<u64>,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        CompactionConfig {
+            policy: Arc::new(SizeTieredPolicy),
+            max_concurrent: Self::DEFAULT_MAX_CONCURRENT,
+            rate_limit_bytes_per_second: None,
+        }
+    }
+}
+
+impl CompactionConfig {
+    const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+    /// Returns this configuration with a different [CompactionPolicy].
+    pub fn with_policy(mut self, policy: Arc<dyn CompactionPolicy>) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Returns this configuration with a different limit on the number of
+    /// merges in flight at once, across all traces.
+    ///
+    /// Each merge reads both of its inputs and writes its output to blob
+    /// storage, so this bounds the amount of concurrent compaction I/O.
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent;
+        self
+    }
+
+    /// Returns this configuration with compaction limited to reading
+    /// `bytes_per_second` of trace batches, averaged over time.
+    ///
+    /// A merge is started whenever the limit hasn't yet been exceeded, and
+    /// once it has, no more are started until the excess has been paid back.
+    /// This means a single merge bigger than the limit is still allowed to
+    /// run. A limit of 0 disables trace compaction.
+    pub fn with_rate_limit(mut self, bytes_per_second: u64) -> Self {
+        self.rate_limit_bytes_per_second = Some(bytes_per_second);
+        self
+    }
+}
+
+/// A token bucket holding up to one second's worth of compaction I/O.
+#[derive(Debug)]
+struct RateLimiter {
+    bytes_per_second: u64,
+    /// Bytes that may be read before compaction is throttled. This goes
+    /// negative when a merge overspends.
+    available_bytes: i128,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_second: u64, now: Instant) -> Self {
+        RateLimiter {
+            bytes_per_second,
+            available_bytes: i128::from(bytes_per_second),
+            last_refill: now,
+        }
+    }
+
+    /// Returns whether work reading `bytes` may start at `now`, charging it
+    /// against the limit if so.
+    fn try_acquire(&mut self, bytes: u64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        let refill = elapsed.as_nanos() * u128::from(self.bytes_per_second) / 1_000_000_000;
+        let refill = i128::try_from(refill).unwrap_or(i128::MAX);
+        self.available_bytes = self
+            .available_bytes
+            .saturating_add(refill)
+            .min(i128::from(self.bytes_per_second));
+        self.last_refill = now;
+
+        if self.available_bytes <= 0 {
+            return false;
+        }
+        self.available_bytes -= i128::from(bytes);
+        true
+    }
+}
+
 /// A runtime for background asynchronous maintenance of stored data.
 //
 // TODO: Add migrating records from unsealed to trace as well as deletion of
@@ -57,6 +249,8 @@ pub struct Maintainer<B: Blob> {
     // thread. Perhaps we should split the Meta parts out of BlobCache.
     blob: Arc<BlobCache<B>>,
     async_runtime: Arc<AsyncRuntime>,
+    config: CompactionConfig,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl<B: Blob> Maintainer<B> {
@@ -65,6 +259,37 @@ impl<B: Blob> Maintainer<B> {
         Maintainer {
             blob: Arc::new(blob),
             async_runtime,
+            config: CompactionConfig::default(),
+            rate_limiter: None,
+        }
+    }
+
+    /// Returns this [Maintainer] with a different [CompactionConfig].
+    pub fn with_config(mut self, config: CompactionConfig) -> Self {
+        self.rate_limiter = config
+            .rate_limit_bytes_per_second
+            .map(|bytes_per_second| RateLimiter::new(bytes_per_second, Instant::now()));
+        self.config = config;
+        self
+    }
+
+    /// The [CompactionPolicy] used to choose which trace batches to merge.
+    pub fn policy(&self) -> &dyn CompactionPolicy {
+        self.config.policy.as_ref()
+    }
+
+    /// The limit on the number of merges in flight at once.
+    pub fn max_concurrent(&self) -> usize {
+        self.config.max_concurrent
+    }
+
+    /// Returns whether the requested compaction may be started at `now`
+    /// without exceeding the configured rate limit, charging it against the
+    /// limit if so.
+    pub fn try_start(&mut self, req: &CompactTraceReq, now: Instant) -> bool {
+        match self.rate_limiter.as_mut() {
+            Some(rate_limiter) => rate_limiter.try_acquire(req.size_bytes(), now),
+            None => true,
         }
     }
 
@@ -73,6 +298,7 @@ impl<B: Blob> Maintainer<B> {
     pub fn compact_trace(&self, req: CompactTraceReq) -> PFuture<CompactTraceRes> {
         let (tx, rx) = PFuture::new();
         let blob = self.blob.clone();
+        let policy = self.config.policy.clone();
         // Ignore the spawn_blocking response since we communicate
         // success/failure through the returned future.
         //
@@ -80,12 +306,13 @@ impl<B: Blob> Maintainer<B> {
         // use spawn here once the storage traits are made async.
         let _ = self
             .async_runtime
-            .spawn_blocking(move || tx.fill(Self::compact_trace_blocking(blob, req)));
+            .spawn_blocking(move || tx.fill(Self::compact_trace_blocking(blob, policy, req)));
         rx
     }
 
     fn compact_trace_blocking(
         blob: Arc<BlobCache<B>>,
+        policy: Arc<dyn CompactionPolicy>,
         req: CompactTraceReq,
     ) -> Result<CompactTraceRes, Error> {
         let (first, second) = (&req.b0, &req.b1);
@@ -111,9 +338,9 @@ impl<B: Blob> Maintainer<B> {
             )));
         }
 
-        // Sanity check that both batches being merged are at identical compaction
-        // levels.
-        debug_assert_eq!(first.level, second.level);
+        // Sanity check that merging these batches keeps compaction levels
+        // weakly decreasing.
+        debug_assert!(first.level >= second.level);
 
         let desc = Description::new(
             first.desc.lower().clone(),
@@ -123,20 +350,15 @@ impl<B: Blob> Maintainer<B> {
 
         let mut updates = vec![];
 
-        updates.extend(
-            blob.get_trace_batch_async(&first.key)
-                .recv()?
-                .updates
-                .iter()
-                .cloned(),
-        );
-        updates.extend(
-            blob.get_trace_batch_async(&second.key)
-                .recv()?
-                .updates
-                .iter()
-                .cloned(),
-        );
+        // Rewrite any data written at an older schema version so the merged
+        // batch is entirely at the current one.
+        for batch in [first, second] {
+            let contents = blob.get_trace_batch_async(&batch.key).recv()?;
+            for ((k, v), ts, diff) in contents.updates.iter().cloned() {
+                let (k, v) = req.schema.migrate(batch.schema_version, k, v)?;
+                updates.push(((k, v), ts, diff));
+            }
+        }
 
         for ((_, _), ts, _) in updates.iter_mut() {
             ts.advance_by(desc.since().borrow());
@@ -152,15 +374,7 @@ impl<B: Blob> Maintainer<B> {
         let merged_key = Arrangement::new_blob_key();
         let (format, size_bytes) = blob.set_trace_batch(merged_key.clone(), new_batch)?;
 
-        // Only upgrade the compaction level if we know this new batch represents
-        // an increase in data over both of its parents so that we know we need
-        // even more additional batches to amortize the cost of compacting it in
-        // the future.
-        let merged_level = if size_bytes > first.size_bytes && size_bytes > second.size_bytes {
-            first.level + 1
-        } else {
-            first.level
-        };
+        let merged_level = policy.merged_level(first, second, size_bytes);
 
         let merged = TraceBatchMeta {
             key: merged_key,
@@ -168,6 +382,7 @@ impl<B: Blob> Maintainer<B> {
             desc,
             level: merged_level,
             size_bytes,
+            schema_version: req.schema.version(),
         };
         Ok(CompactTraceRes { req, merged })
     }
@@ -175,6 +390,8 @@ impl<B: Blob> Maintainer<B> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use differential_dataflow::trace::Description;
     use tokio::runtime::Runtime as AsyncRuntime;
 
@@ -228,6 +445,7 @@ mod tests {
                 desc: b0.desc,
                 level: 0,
                 size_bytes: b0_size_bytes,
+                schema_version: 0,
             },
             b1: TraceBatchMeta {
                 key: "b1".into(),
@@ -235,8 +453,10 @@ mod tests {
                 desc: b1.desc,
                 level: 0,
                 size_bytes: b1_size_bytes,
+                schema_version: 0,
             },
             since: Antichain::from_elem(2),
+            schema: StreamSchema::new(0),
         };
 
         let expected_res = CompactTraceRes {
@@ -247,6 +467,7 @@ mod tests {
                 desc: desc_from(0, 3, 2),
                 level: 1,
                 size_bytes: 0,
+                schema_version: 0,
             },
         };
         let mut res = maintainer.compact_trace(req).recv()?;
@@ -285,6 +506,7 @@ mod tests {
                 desc: desc_from(0, 2, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             b1: TraceBatchMeta {
                 key: "".into(),
@@ -292,10 +514,12 @@ mod tests {
                 desc: desc_from(3, 4, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             since: Antichain::from_elem(0),
+            schema: StreamSchema::new(0),
         };
-        assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("invalid merge of non-consecutive batches TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [0] }, upper: Antichain { elements: [2] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0 } and TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [3] }, upper: Antichain { elements: [4] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0 }")));
+        assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("invalid merge of non-consecutive batches TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [0] }, upper: Antichain { elements: [2] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0, schema_version: 0 } and TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [3] }, upper: Antichain { elements: [4] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0, schema_version: 0 }")));
 
         // Overlapping batch descs
         let req = CompactTraceReq {
@@ -305,6 +529,7 @@ mod tests {
                 desc: desc_from(0, 2, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             b1: TraceBatchMeta {
                 key: "".into(),
@@ -312,10 +537,12 @@ mod tests {
                 desc: desc_from(1, 4, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             since: Antichain::from_elem(0),
+            schema: StreamSchema::new(0),
         };
-        assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("invalid merge of non-consecutive batches TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [0] }, upper: Antichain { elements: [2] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0 } and TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [1] }, upper: Antichain { elements: [4] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0 }")));
+        assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("invalid merge of non-consecutive batches TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [0] }, upper: Antichain { elements: [2] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0, schema_version: 0 } and TraceBatchMeta { key: \"\", format: Unknown, desc: Description { lower: Antichain { elements: [1] }, upper: Antichain { elements: [4] }, since: Antichain { elements: [0] } }, level: 0, size_bytes: 0, schema_version: 0 }")));
 
         // Since not at or in advance of b0's since
         let req = CompactTraceReq {
@@ -325,6 +552,7 @@ mod tests {
                 desc: desc_from(0, 2, 1),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             b1: TraceBatchMeta {
                 key: "".into(),
@@ -332,8 +560,10 @@ mod tests {
                 desc: desc_from(2, 4, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             since: Antichain::from_elem(0),
+            schema: StreamSchema::new(0),
         };
         assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("output since Antichain { elements: [0] } must be at or in advance of input since Antichain { elements: [1] }")));
 
@@ -345,6 +575,7 @@ mod tests {
                 desc: desc_from(0, 2, 0),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             b1: TraceBatchMeta {
                 key: "".into(),
@@ -352,11 +583,95 @@ mod tests {
                 desc: desc_from(2, 4, 1),
                 level: 0,
                 size_bytes: 0,
+                schema_version: 0,
             },
             since: Antichain::from_elem(0),
+            schema: StreamSchema::new(0),
         };
         assert_eq!(maintainer.compact_trace(req).recv(), Err(Error::from("output since Antichain { elements: [0] } must be at or in advance of input since Antichain { elements: [1] }")));
 
         Ok(())
     }
+
+    fn batch_meta(lower: u64, upper: u64, level: u64, size_bytes: u64) -> TraceBatchMeta {
+        TraceBatchMeta {
+            key: format!("{}-{}", lower, upper),
+            format: ProtoBatchFormat::Unknown,
+            desc: desc_from(lower, upper, 0),
+            level,
+            size_bytes,
+            schema_version: 0,
+        }
+    }
+
+    #[test]
+    fn size_tiered_policy() {
+        let policy = SizeTieredPolicy;
+        let b = |level, size_bytes| batch_meta(0, 1, level, size_bytes);
+
+        assert_eq!(policy.next_merge(&[]), None);
+        assert_eq!(policy.next_merge(&[b(0, 10)]), None);
+        assert_eq!(policy.next_merge(&[b(1, 10), b(0, 10)]), None);
+        assert_eq!(policy.next_merge(&[b(1, 10), b(0, 10), b(0, 10)]), Some(2));
+        assert_eq!(
+            policy.next_merge(&[b(1, 10), b(1, 10), b(0, 10), b(0, 10)]),
+            Some(1)
+        );
+
+        // The merged batch is only promoted if it's bigger than both inputs.
+        assert_eq!(policy.merged_level(&b(1, 10), &b(1, 10), 15), 2);
+        assert_eq!(policy.merged_level(&b(1, 10), &b(1, 10), 10), 1);
+    }
+
+    #[test]
+    fn leveled_policy() -> Result<(), Error> {
+        assert_eq!(
+            LeveledPolicy::new(1).map(|_| ()),
+            Err(Error::from(
+                "invalid leveled compaction fanout 1: must be at least 2"
+            ))
+        );
+
+        let policy = LeveledPolicy::new(4)?;
+        let b = |level, size_bytes| batch_meta(0, 1, level, size_bytes);
+
+        assert_eq!(policy.next_merge(&[]), None);
+        assert_eq!(policy.next_merge(&[b(0, 10)]), None);
+        // Each batch is at least 4x the next, so nothing to do.
+        assert_eq!(policy.next_merge(&[b(0, 160), b(0, 40), b(0, 10)]), None);
+        // The oldest batch that isn't 4x the next is merged with it.
+        assert_eq!(policy.next_merge(&[b(0, 160), b(0, 40), b(0, 20)]), Some(2));
+        assert_eq!(policy.next_merge(&[b(0, 100), b(0, 40), b(0, 10)]), Some(1));
+
+        // Levels are left alone.
+        assert_eq!(policy.merged_level(&b(1, 10), &b(0, 10), 20), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn rate_limiter() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(100, start);
+
+        // The bucket starts full and a single request may overspend it.
+        assert!(limiter.try_acquire(60, start));
+        assert!(limiter.try_acquire(60, start));
+        assert!(!limiter.try_acquire(1, start));
+
+        // The debt is paid back over time...
+        let t = start + Duration::from_millis(100);
+        assert!(!limiter.try_acquire(1, t));
+        let t = start + Duration::from_millis(250);
+        assert!(limiter.try_acquire(1, t));
+
+        // ...and the bucket never holds more than one second's worth.
+        let t = t + Duration::from_secs(10);
+        assert!(limiter.try_acquire(150, t));
+        assert!(!limiter.try_acquire(1, t));
+
+        // A limit of zero never admits anything.
+        let mut limiter = RateLimiter::new(0, start);
+        assert!(!limiter.try_acquire(0, start));
+        assert!(!limiter.try_acquire(0, start + Duration::from_secs(10)));
+    }
 }
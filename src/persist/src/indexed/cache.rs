@@ -9,7 +9,10 @@
 
 //! A disk-backed cache for objects in blob storage.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Instant;
@@ -20,57 +23,293 @@ use ore::cast::CastFrom;
 use persist_types::Codec;
 use semver::Version;
 use tokio::runtime::Runtime as AsyncRuntime;
+use uuid::Uuid;
 
+use crate::encrypted::{self, Keyring};
 use crate::error::Error;
 use crate::gen::persist::{ProtoBatchFormat, ProtoMeta};
+use crate::indexed::columnar::parquet::{encode_trace_parquet, encode_unsealed_parquet};
+use crate::indexed::columnar::BatchCompression;
 use crate::indexed::encoding::{
     BlobMeta, BlobTraceBatch, BlobUnsealedBatch, TraceBatchMeta, UnsealedBatchMeta,
 };
 use crate::indexed::metrics::Metrics;
 use crate::pfuture::PFuture;
-use crate::storage::{Atomicity, Blob};
+use crate::storage::{Atomicity, Blob, BlobRead};
+
+/// Configuration for how much a [BlobCache] may hold in memory and on local
+/// disk.
+#[derive(Debug, Clone)]
+pub struct BlobCacheConfig {
+    /// Limit on the approximate size of the decoded batches held in memory
+    memory_limit_bytes: u64,
+    /// Directory and size limit of the optional local-disk tier
+    disk: Option<(PathBuf, u64)>,
+    /// Keys to encrypt the batches held in the local-disk tier with
+    disk_encryption: Option<Keyring>,
+}
+
+impl Default for BlobCacheConfig {
+    fn default() -> Self {
+        BlobCacheConfig {
+            memory_limit_bytes: Self::DEFAULT_MEMORY_LIMIT_BYTES,
+            disk: None,
+            disk_encryption: None,
+        }
+    }
+}
+
+impl BlobCacheConfig {
+    const DEFAULT_MEMORY_LIMIT_BYTES: u64 = 512 * 1024 * 1024;
+
+    /// Returns this configuration with a different limit on the approximate
+    /// size of the decoded batches held in memory.
+    pub fn with_memory_limit(mut self, limit_bytes: u64) -> Self {
+        self.memory_limit_bytes = limit_bytes;
+        self
+    }
+
+    /// Returns this configuration with a local-disk tier in `dir` that holds
+    /// up to `limit_bytes` of encoded batches.
+    ///
+    /// Batches evicted from memory can then be read back from local disk
+    /// instead of being fetched from blob storage again, which is mostly
+    /// useful when blob storage is remote (e.g. S3). Any files left in `dir`
+    /// by a previous process are removed.
+    ///
+    /// NB: The disk tier holds batches as they're returned by the blob, so if
+    /// the blob is an [crate::encrypted::EncryptedBlob], they're stored
+    /// decrypted unless [Self::with_disk_encryption] is also used.
+    pub fn with_disk(mut self, dir: PathBuf, limit_bytes: u64) -> Self {
+        self.disk = Some((dir, limit_bytes));
+        self
+    }
+
+    /// Returns this configuration with the batches held in the local-disk
+    /// tier encrypted with `keys`, which should be those the blob itself is
+    /// encrypted with.
+    pub fn with_disk_encryption(mut self, keys: Keyring) -> Self {
+        self.disk_encryption = Some(keys);
+        self
+    }
+}
+
+/// A map keyed by blob key that evicts its least recently used entries to
+/// keep the total size of its values within a limit.
+#[derive(Debug)]
+struct Lru<V> {
+    limit_bytes: u64,
+    size_bytes: u64,
+    clock: u64,
+    entries: HashMap<String, LruEntry<V>>,
+    by_last_used: BTreeMap<u64, String>,
+}
+
+#[derive(Debug)]
+struct LruEntry<V> {
+    val: V,
+    size_bytes: u64,
+    last_used: u64,
+}
+
+impl<V: Clone> Lru<V> {
+    fn new(limit_bytes: u64) -> Self {
+        Lru {
+            limit_bytes,
+            size_bytes: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            by_last_used: BTreeMap::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the value for `key`, marking it as the most recently used.
+    fn get(&mut self, key: &str) -> Option<V> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.by_last_used.remove(&entry.last_used);
+        entry.last_used = self.clock;
+        self.by_last_used.insert(self.clock, key.to_owned());
+        Some(entry.val.clone())
+    }
+
+    /// Inserts a value and then evicts the least recently used entries until
+    /// the total size is within the limit. Returns the keys of the evicted
+    /// entries.
+    ///
+    /// A value that is by itself larger than the limit is evicted right away.
+    fn insert(&mut self, key: String, val: V, size_bytes: u64) -> Vec<String> {
+        self.remove(&key);
+        self.clock += 1;
+        self.by_last_used.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            LruEntry {
+                val,
+                size_bytes,
+                last_used: self.clock,
+            },
+        );
+        self.size_bytes += size_bytes;
+
+        let mut evicted = Vec::new();
+        while self.size_bytes > self.limit_bytes {
+            let key = match self.by_last_used.values().next() {
+                Some(key) => key.clone(),
+                None => break,
+            };
+            self.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+
+    fn remove(&mut self, key: &str) -> Option<V> {
+        let entry = self.entries.remove(key)?;
+        self.by_last_used.remove(&entry.last_used);
+        self.size_bytes -= entry.size_bytes;
+        Some(entry.val)
+    }
+
+    /// Removes every entry whose key doesn't match the predicate. Returns the
+    /// keys of the removed entries.
+    fn retain<F: FnMut(&str) -> bool>(&mut self, mut f: F) -> Vec<String> {
+        let removed = self
+            .entries
+            .keys()
+            .filter(|key| !f(key))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in removed.iter() {
+            self.remove(key);
+        }
+        removed
+    }
+}
+
+/// A decoded batch held in memory by a [BlobCache].
+#[derive(Debug, Clone)]
+enum CachedBatch {
+    Unsealed(Arc<BlobUnsealedBatch>),
+    Trace(Arc<BlobTraceBatch>),
+}
+
+/// The local-disk tier of a [BlobCache], which holds each encoded batch in a
+/// file named after its blob key.
+#[derive(Debug)]
+struct DiskCache {
+    dir: PathBuf,
+    files: Lru<()>,
+    /// If set, each file holds its batch encrypted with these keys and
+    /// authenticated together with the batch's blob key.
+    keys: Option<Arc<Keyring>>,
+}
+
+impl DiskCache {
+    const EXTENSION: &'static str = "batch";
+    const TMP_EXTENSION: &'static str = "tmp";
+
+    /// Opens the tier in `dir`, removing any files left there by a previous
+    /// process.
+    ///
+    /// Batches are immutable, so leftover files would be correct to serve, but
+    /// they may be partially written and we'd have to look at each of them to
+    /// account for its size. Starting from scratch is simpler.
+    fn open(dir: PathBuf, limit_bytes: u64, keys: Option<Keyring>) -> Result<Self, Error> {
+        fs::create_dir_all(&dir)?;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|x| x.to_str());
+            if extension == Some(Self::EXTENSION) || extension == Some(Self::TMP_EXTENSION) {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(DiskCache {
+            dir,
+            files: Lru::new(limit_bytes),
+            keys: keys.map(Arc::new),
+        })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", key, Self::EXTENSION))
+    }
+
+    fn tmp_path(&self) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", Uuid::new_v4(), Self::TMP_EXTENSION))
+    }
+
+    /// Removes the files for the given (already removed from `files`) keys.
+    fn remove_files(&self, keys: &[String]) -> Result<(), io::Error> {
+        for key in keys {
+            match fs::remove_file(self.path(key)) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
 
 /// A disk-backed cache for objects in [Blob] storage.
 ///
-/// The data for the objects in the cache is stored on disk, mmap'd, and a
-/// validated handle is stored in-memory to avoid repeatedly decoding it.
+/// Decoded batches are held in memory up to a configurable limit on their
+/// approximate size, past which the least recently used ones are evicted. An
+/// optional local-disk tier holds the encoded batches, also up to a limit, so
+/// that batches evicted from memory don't have to be fetched from blob storage
+/// again.
 ///
-/// TODO: Add a limit to bound how much disk this cache can use. The `Arc`
-/// return type for `get_batch` seems correct, but means that a bad user could
-/// starve the cache by indefinitely holding handles. The Arcs could be made
-/// into weak references so the cache could forcefully reclaim the backing data,
-/// but this is going to make performance of using the cached batches
-/// unpredictable. I think we probably want a soft limit and a hard limit where
-/// the soft limit does some alerting and the hard limit starts blocking (or
-/// erroring) until disk space frees up.
+/// The limits only cover what the cache itself holds: the `Arc` returned by
+/// `get_*_batch_async` keeps a batch alive after it's evicted for as long as
+/// the caller holds on to it.
 #[derive(Debug)]
-pub struct BlobCache<B: Blob> {
+pub struct BlobCache<B: BlobRead> {
     build_version: Version,
     metrics: Arc<Metrics>,
     blob: Arc<Mutex<B>>,
     async_runtime: Arc<AsyncRuntime>,
-    // TODO: Use a disk-backed LRU cache.
-    unsealed: Arc<Mutex<HashMap<String, Arc<BlobUnsealedBatch>>>>,
-    trace: Arc<Mutex<HashMap<String, Arc<BlobTraceBatch>>>>,
+    compression: BatchCompression,
+    memory: Arc<Mutex<Lru<CachedBatch>>>,
+    disk: Option<Arc<Mutex<DiskCache>>>,
     prev_meta_len: u64,
+    consensus: Option<Consensus>,
+}
+
+/// The state of a [BlobCache] that has taken over as the writer of its
+/// location and commits META with compare-and-set.
+#[derive(Debug, Clone)]
+struct Consensus {
+    /// The writer epoch claimed by this process.
+    epoch: u64,
+    /// The exact bytes of the last META this process committed.
+    committed: Vec<u8>,
 }
 
-impl<B: Blob> Clone for BlobCache<B> {
+impl<B: BlobRead> Clone for BlobCache<B> {
     fn clone(&self) -> Self {
         BlobCache {
             build_version: self.build_version.clone(),
             metrics: self.metrics.clone(),
             blob: self.blob.clone(),
             async_runtime: self.async_runtime.clone(),
-            unsealed: self.unsealed.clone(),
-            trace: self.trace.clone(),
+            compression: self.compression,
+            memory: self.memory.clone(),
+            disk: self.disk.clone(),
             prev_meta_len: self.prev_meta_len,
+            consensus: self.consensus.clone(),
         }
     }
 }
 
-impl<B: Blob> BlobCache<B> {
+impl<B: BlobRead> BlobCache<B> {
     const META_KEY: &'static str = "META";
+    const TAKE_OVER_MAX_ATTEMPTS: usize = 10;
 
     /// Returns a new, empty cache for the given [Blob] storage.
     pub fn new(
@@ -79,17 +318,47 @@ impl<B: Blob> BlobCache<B> {
         async_runtime: Arc<AsyncRuntime>,
         blob: B,
     ) -> Self {
+        let config = BlobCacheConfig::default();
         BlobCache {
             build_version: build.semver_version(),
             metrics,
             async_runtime,
+            compression: BatchCompression::Uncompressed,
             blob: Arc::new(Mutex::new(blob)),
-            unsealed: Arc::new(Mutex::new(HashMap::new())),
-            trace: Arc::new(Mutex::new(HashMap::new())),
+            memory: Arc::new(Mutex::new(Lru::new(config.memory_limit_bytes))),
+            disk: None,
             prev_meta_len: 0,
+            consensus: None,
         }
     }
 
+    /// Returns this cache, configured to compress newly written batches with
+    /// the given codec.
+    ///
+    /// Batches already in storage are read back regardless of the codec they
+    /// were written with.
+    pub fn with_compression(mut self, compression: BatchCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns this cache, configured with the given memory limit and
+    /// local-disk tier.
+    ///
+    /// This must be called before the cache holds anything.
+    pub fn with_config(mut self, config: BlobCacheConfig) -> Result<Self, Error> {
+        self.memory = Arc::new(Mutex::new(Lru::new(config.memory_limit_bytes)));
+        self.disk = match config.disk {
+            Some((dir, limit_bytes)) => Some(Arc::new(Mutex::new(DiskCache::open(
+                dir,
+                limit_bytes,
+                config.disk_encryption,
+            )?))),
+            None => None,
+        };
+        Ok(self)
+    }
+
     /// Synchronously closes the cache, releasing exclusive-writer locks and
     /// causing all future commands to error.
     ///
@@ -99,25 +368,213 @@ impl<B: Blob> BlobCache<B> {
         block_on(self.blob.lock()?.close())
     }
 
+    /// Returns the decoded batch for the given key, if it's held in memory.
+    fn memory_get(&self, key: &str) -> Result<Option<CachedBatch>, Error> {
+        let batch = self.memory.lock()?.get(key);
+        match batch {
+            Some(_) => self.metrics.blob_read_cache_hit_count.inc(),
+            None => self.metrics.blob_read_cache_miss_count.inc(),
+        }
+        Ok(batch)
+    }
+
+    /// Holds a decoded batch in memory, evicting others as necessary to stay
+    /// within the limit.
+    fn memory_insert(
+        &self,
+        key: String,
+        batch: CachedBatch,
+        size_bytes: usize,
+    ) -> Result<(), Error> {
+        let mut memory = self.memory.lock()?;
+        let evicted = memory.insert(key, batch, u64::cast_from(size_bytes));
+        self.metrics
+            .blob_read_cache_eviction_count
+            .inc_by(u64::cast_from(evicted.len()));
+        self.set_memory_metrics(&memory);
+        Ok(())
+    }
+
+    fn set_memory_metrics(&self, memory: &Lru<CachedBatch>) {
+        self.metrics.blob_read_cache_bytes.set(memory.size_bytes);
+        self.metrics
+            .blob_read_cache_entry_count
+            .set(u64::cast_from(memory.len()));
+    }
+
+    /// Returns the encoded batch for the given key, if it's held in the
+    /// local-disk tier.
+    fn disk_get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let disk = match self.disk.as_ref() {
+            Some(disk) => disk,
+            None => return Ok(None),
+        };
+        let (path, keys) = {
+            let mut disk = disk.lock()?;
+            if disk.files.get(key).is_none() {
+                return Ok(None);
+            }
+            (disk.path(key), disk.keys.clone())
+        };
+        // NB: The file is read outside the lock, so it may be evicted in the
+        // meantime. That's just a miss.
+        let res = fs::read(&path)
+            .map_err(Error::from)
+            .and_then(|bytes| match keys {
+                Some(keys) => keys.open(&bytes, key.as_bytes(), &format!("cached batch {}", key)),
+                None => Ok(bytes),
+            });
+        match res {
+            Ok(bytes) => {
+                self.metrics.blob_read_cache_disk_hit_count.inc();
+                Ok(Some(bytes))
+            }
+            Err(Error::IO(err)) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                self.metrics.blob_read_cache_disk_error_count.inc();
+                tracing::warn!("failed to read {:?} from persist disk cache: {}", path, err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Holds an encoded batch in the local-disk tier, if there is one, evicting
+    /// others as necessary to stay within the limit.
+    ///
+    /// The disk tier is only an optimization, so failing to write to it is
+    /// logged but otherwise ignored.
+    fn disk_insert(&self, key: &str, bytes: &[u8]) -> Result<(), Error> {
+        let disk = match self.disk.as_ref() {
+            Some(disk) => disk,
+            None => return Ok(()),
+        };
+        let (path, tmp_path, keys) = {
+            let disk = disk.lock()?;
+            (disk.path(key), disk.tmp_path(), disk.keys.clone())
+        };
+        let sealed = match keys {
+            Some(keys) => Some(keys.seal(bytes, key.as_bytes())),
+            None => None,
+        };
+        // Write to a temporary file and move it into place, so that a
+        // concurrent read never sees a partially written batch.
+        let res = match &sealed {
+            Some(Ok(sealed)) => Ok(sealed.as_slice()),
+            Some(Err(err)) => Err(err.clone()),
+            None => Ok(bytes),
+        }
+        .and_then(|bytes| {
+            fs::write(&tmp_path, bytes)?;
+            fs::rename(&tmp_path, &path)?;
+            Ok(bytes.len())
+        });
+        let size_bytes = match res {
+            Ok(size_bytes) => size_bytes,
+            Err(err) => {
+                self.metrics.blob_read_cache_disk_error_count.inc();
+                tracing::warn!("failed to write {:?} to persist disk cache: {}", path, err);
+                let _ = fs::remove_file(&tmp_path);
+                return Ok(());
+            }
+        };
+
+        let mut disk = disk.lock()?;
+        let evicted = disk
+            .files
+            .insert(key.to_owned(), (), u64::cast_from(size_bytes));
+        if let Err(err) = disk.remove_files(&evicted) {
+            self.metrics.blob_read_cache_disk_error_count.inc();
+            tracing::warn!("failed to evict from persist disk cache: {}", err);
+        }
+        self.metrics
+            .blob_read_cache_disk_eviction_count
+            .inc_by(u64::cast_from(evicted.len()));
+        self.set_disk_metrics(&disk);
+        Ok(())
+    }
+
+    fn set_disk_metrics(&self, disk: &DiskCache) {
+        self.metrics
+            .blob_read_cache_disk_bytes
+            .set(disk.files.size_bytes);
+        self.metrics
+            .blob_read_cache_disk_entry_count
+            .set(u64::cast_from(disk.files.len()));
+    }
+
+    /// Removes every cached batch whose key doesn't match the predicate from
+    /// both memory and the local-disk tier.
+    fn remove_cached<F: FnMut(&str) -> bool>(&self, mut f: F) -> Result<(), Error> {
+        {
+            let mut memory = self.memory.lock()?;
+            memory.retain(&mut f);
+            self.set_memory_metrics(&memory);
+        }
+        if let Some(disk) = self.disk.as_ref() {
+            let mut disk = disk.lock()?;
+            let removed = disk.files.retain(&mut f);
+            disk.remove_files(&removed)?;
+            self.set_disk_metrics(&disk);
+        }
+        Ok(())
+    }
+
+    /// Removes the batch for the given key from both memory and the local-disk
+    /// tier.
+    fn uncache(&self, key: &str) -> Result<(), Error> {
+        {
+            let mut memory = self.memory.lock()?;
+            memory.remove(key);
+            self.set_memory_metrics(&memory);
+        }
+        if let Some(disk) = self.disk.as_ref() {
+            let mut disk = disk.lock()?;
+            if disk.files.remove(key).is_some() {
+                disk.remove_files(&[key.to_owned()])?;
+            }
+            self.set_disk_metrics(&disk);
+        }
+        Ok(())
+    }
+
+    /// Fetches the encoded batch for the given key, from the local-disk tier
+    /// if it's there and otherwise from [Blob] storage.
+    fn fetch_batch_bytes(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        if let Some(bytes) = self.disk_get(key)? {
+            return Ok(Some(bytes));
+        }
+        let bytes = match block_on(self.blob.lock()?.get(key))? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        self.metrics
+            .blob_read_cache_fetch_bytes
+            .inc_by(u64::cast_from(bytes.len()));
+        self.disk_insert(key, &bytes)?;
+        Ok(Some(bytes))
+    }
+
     /// Synchronously fetches the batch for the given key.
     fn fetch_unsealed_batch_sync(&self, key: &str) -> Result<Arc<BlobUnsealedBatch>, Error> {
         let async_guard = self.async_runtime.enter();
 
-        let bytes = block_on(self.blob.lock()?.get(key))?
+        let bytes = self
+            .fetch_batch_bytes(key)?
             .ok_or_else(|| Error::from(format!("no blob for unsealed batch at key: {}", key)))?;
-        self.metrics
-            .blob_read_cache_fetch_bytes
-            .inc_by(u64::cast_from(bytes.len()));
         let batch: BlobUnsealedBatch = BlobUnsealedBatch::decode(&bytes).map_err(|err| {
             Error::from(format!("invalid unsealed batch at key {}: {}", key, err))
         })?;
+        debug_assert_eq!(batch.validate(), Ok(()), "{:?}", &batch);
 
         // NB: Batch blobs are write-once, so we're not worried about the race
         // of two get calls for the same key.
-        let mut cache = self.unsealed.lock()?;
-        debug_assert_eq!(batch.validate(), Ok(()), "{:?}", &batch);
-        cache.insert(key.to_owned(), Arc::new(batch));
-        let ret = cache.get(key).unwrap().clone();
+        let size_bytes = batch.size_bytes();
+        let ret = Arc::new(batch);
+        self.memory_insert(
+            key.to_owned(),
+            CachedBatch::Unsealed(ret.clone()),
+            size_bytes,
+        )?;
 
         drop(async_guard);
         Ok(ret)
@@ -127,22 +584,16 @@ impl<B: Blob> BlobCache<B> {
     /// thread if it's not already in the cache.
     pub fn get_unsealed_batch_async(&self, key: &str) -> PFuture<Arc<BlobUnsealedBatch>> {
         let (tx, rx) = PFuture::new();
-        {
-            // New scope to ensure the cache lock is dropped during the
-            // (expensive) get.
-            let unsealed = match self.unsealed.lock() {
-                Ok(unsealed) => unsealed,
-                Err(err) => {
-                    tx.fill(Err(err.into()));
-                    return rx;
-                }
-            };
-            if let Some(entry) = unsealed.get(key) {
-                self.metrics.blob_read_cache_hit_count.inc();
-                tx.fill(Ok(entry.clone()));
+        match self.memory_get(key) {
+            Ok(Some(CachedBatch::Unsealed(batch))) => {
+                tx.fill(Ok(batch));
+                return rx;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tx.fill(Err(err));
                 return rx;
             }
-            self.metrics.blob_read_cache_miss_count.inc();
         }
 
         // TODO: If a fetch for this key is already in progress join that one
@@ -159,6 +610,148 @@ impl<B: Blob> BlobCache<B> {
         rx
     }
 
+    /// Synchronously fetches the batch for the given key.
+    fn fetch_trace_batch_sync(&self, key: &str) -> Result<Arc<BlobTraceBatch>, Error> {
+        let async_guard = self.async_runtime.enter();
+
+        let bytes = self
+            .fetch_batch_bytes(key)?
+            .ok_or_else(|| Error::from(format!("no blob for trace batch at key: {}", key)))?;
+        let batch: BlobTraceBatch = BlobTraceBatch::decode(&bytes)
+            .map_err(|err| Error::from(format!("invalid trace batch at key {}: {}", key, err)))?;
+        debug_assert_eq!(batch.validate(), Ok(()), "{:?}", &batch);
+
+        // NB: Batch blobs are write-once, so we're not worried about the race
+        // of two get calls for the same key.
+        let size_bytes = batch.size_bytes();
+        let ret = Arc::new(batch);
+        self.memory_insert(key.to_owned(), CachedBatch::Trace(ret.clone()), size_bytes)?;
+
+        drop(async_guard);
+        Ok(ret)
+    }
+
+    /// Asynchronously returns the batch for the given key, fetching in another
+    /// thread if it's not already in the cache.
+    pub fn get_trace_batch_async(&self, key: &str) -> PFuture<Arc<BlobTraceBatch>> {
+        let (tx, rx) = PFuture::new();
+        match self.memory_get(key) {
+            Ok(Some(CachedBatch::Trace(batch))) => {
+                tx.fill(Ok(batch));
+                return rx;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tx.fill(Err(err));
+                return rx;
+            }
+        }
+
+        // TODO: If a fetch for this key is already in progress join that one
+        // instead of starting another.
+        let cache = self.clone();
+        let key = key.to_owned();
+        // TODO: IO thread pool for persist instead of spawning one here.
+        let _ = thread::spawn(move || {
+            let async_guard = cache.async_runtime.enter();
+            let res = cache.fetch_trace_batch_sync(&key);
+            tx.fill(res);
+            drop(async_guard);
+        });
+        rx
+    }
+
+    /// Fetches metadata about what batches are in [Blob] storage.
+    pub fn get_meta(&self) -> Result<Option<BlobMeta>, Error> {
+        let async_guard = self.async_runtime.enter();
+
+        let blob = self.blob.lock()?;
+        let bytes = match block_on(blob.get(Self::META_KEY))? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let meta = self.decode_meta(&bytes)?;
+        if !meta.encryption_key_id.is_empty()
+            && blob.encryption_key_id() != Some(meta.encryption_key_id.as_str())
+        {
+            tracing::info!(
+                "persisted data was encrypted with key {} and will be encrypted with key {:?} going forward",
+                meta.encryption_key_id,
+                blob.encryption_key_id()
+            );
+        }
+        let meta = BlobMeta::from(meta);
+        debug_assert_eq!(meta.validate(), Ok(()), "{:?}", &meta);
+
+        drop(async_guard);
+        Ok(Some(meta))
+    }
+
+    /// Returns whether another process has taken over writing to this
+    /// location since this cache did. See [BlobCache::take_over].
+    pub fn is_fenced(&self) -> Result<bool, Error> {
+        let consensus = match self.consensus.as_ref() {
+            Some(consensus) => consensus,
+            None => return Ok(false),
+        };
+        let async_guard = self.async_runtime.enter();
+        let current = block_on(self.blob.lock()?.get(Self::META_KEY))?;
+        drop(async_guard);
+        Ok(current.as_deref() != Some(consensus.committed.as_slice()))
+    }
+
+    fn decode_meta(&self, bytes: &[u8]) -> Result<ProtoMeta, Error> {
+        encrypted::check_not_encrypted(bytes)?;
+        let meta = ProtoMeta::decode(bytes).map_err(|err| {
+            Error::from(format!("invalid meta at key {}: {}", Self::META_KEY, err))
+        })?;
+        self.check_meta_build_version(&meta)?;
+        Ok(meta)
+    }
+
+    fn check_meta_build_version(&self, meta: &ProtoMeta) -> Result<(), Error> {
+        // TODO: After ENCODING_VERSION is bumped to 8 or higher, this can be
+        // removed.
+        let meta_version = if meta.version.is_empty() {
+            // Any build that includes this check comes after a ProtoMeta that
+            // was written with no version set.
+            Version::new(0, 0, 0)
+        } else {
+            meta.version
+                .parse::<Version>()
+                .map_err(|err| err.to_string())?
+        };
+        // Allow data written by any previous version of persist (backward
+        // compatible for all time) but disallow data written by a future
+        // version of persist (aka we're currently *not* forward compatible).
+        // Note that at some point, mz will need to be forward compatible to
+        // allow for rollbacks but this policy is not yet settled.
+        //
+        // NB: Since ProtoMeta is the entrypoint for all written persist
+        // metadata and data, it's an upper bound on versions involved in any
+        // persist data.
+        if meta_version > self.build_version {
+            return Err(format!(
+                "persist v{} cannot read data written by future persist v{}",
+                self.build_version, meta_version
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns the list of keys known to the underlying [Blob].
+    pub fn list_keys(&self) -> Result<Vec<String>, Error> {
+        block_on(self.blob.lock()?.list_keys())
+    }
+
+    /// Drops every cached batch whose key is not in `keys`.
+    pub fn retain_cached(&self, keys: &HashSet<&str>) -> Result<(), Error> {
+        self.remove_cached(|key| keys.contains(key))
+    }
+}
+
+impl<B: Blob> BlobCache<B> {
     /// Writes a batch to backing [Blob] storage.
     ///
     /// Returns the size of the encoded blob value in bytes.
@@ -180,8 +773,12 @@ impl<B: Blob> BlobCache<B> {
 
         let mut val = Vec::new();
         let format = ProtoBatchFormat::ParquetKvtd;
-        batch.encode(&mut val);
+        encode_unsealed_parquet(&mut val, &batch, self.compression)?;
         let val_len = u64::cast_from(val.len());
+        // NB: This happens before the write so `val` can be handed to the
+        // blob without a copy. If the write fails, nothing references this
+        // key and it's eventually evicted.
+        self.disk_insert(&key, &val)?;
 
         let write_start = Instant::now();
         block_on(self.blob.lock()?.set(&key, val, Atomicity::AllowNonAtomic))
@@ -193,7 +790,8 @@ impl<B: Blob> BlobCache<B> {
         self.metrics.unsealed.blob_write_count.inc();
         self.metrics.unsealed.blob_write_bytes.inc_by(val_len);
 
-        self.unsealed.lock()?.insert(key, Arc::new(batch));
+        let size_bytes = batch.size_bytes();
+        self.memory_insert(key, CachedBatch::Unsealed(Arc::new(batch)), size_bytes)?;
 
         drop(async_guard);
         Ok((format, val_len))
@@ -204,7 +802,7 @@ impl<B: Blob> BlobCache<B> {
         let async_guard = self.async_runtime.enter();
 
         let delete_start = Instant::now();
-        self.unsealed.lock()?.remove(&batch.key);
+        self.uncache(&batch.key)?;
         block_on(self.blob.lock()?.delete(&batch.key))?;
         self.metrics
             .unsealed
@@ -220,65 +818,6 @@ impl<B: Blob> BlobCache<B> {
         Ok(())
     }
 
-    /// Synchronously fetches the batch for the given key.
-    fn fetch_trace_batch_sync(&self, key: &str) -> Result<Arc<BlobTraceBatch>, Error> {
-        let async_guard = self.async_runtime.enter();
-
-        let bytes = block_on(self.blob.lock()?.get(key))?
-            .ok_or_else(|| Error::from(format!("no blob for trace batch at key: {}", key)))?;
-        self.metrics
-            .blob_read_cache_fetch_bytes
-            .inc_by(u64::cast_from(bytes.len()));
-        let batch: BlobTraceBatch = BlobTraceBatch::decode(&bytes)
-            .map_err(|err| Error::from(format!("invalid trace batch at key {}: {}", key, err)))?;
-
-        // NB: Batch blobs are write-once, so we're not worried about the race
-        // of two get calls for the same key.
-        let mut cache = self.trace.lock()?;
-        debug_assert_eq!(batch.validate(), Ok(()), "{:?}", &batch);
-        cache.insert(key.to_owned(), Arc::new(batch));
-        let ret = cache.get(key).unwrap().clone();
-
-        drop(async_guard);
-        Ok(ret)
-    }
-
-    /// Asynchronously returns the batch for the given key, fetching in another
-    /// thread if it's not already in the cache.
-    pub fn get_trace_batch_async(&self, key: &str) -> PFuture<Arc<BlobTraceBatch>> {
-        let (tx, rx) = PFuture::new();
-        {
-            // New scope to ensure the cache lock is dropped during the
-            // (expensive) get.
-            let trace = match self.trace.lock() {
-                Ok(trace) => trace,
-                Err(err) => {
-                    tx.fill(Err(err.into()));
-                    return rx;
-                }
-            };
-            if let Some(entry) = trace.get(key) {
-                self.metrics.blob_read_cache_hit_count.inc();
-                tx.fill(Ok(entry.clone()));
-                return rx;
-            }
-            self.metrics.blob_read_cache_miss_count.inc();
-        }
-
-        // TODO: If a fetch for this key is already in progress join that one
-        // instead of starting another.
-        let cache = self.clone();
-        let key = key.to_owned();
-        // TODO: IO thread pool for persist instead of spawning one here.
-        let _ = thread::spawn(move || {
-            let async_guard = cache.async_runtime.enter();
-            let res = cache.fetch_trace_batch_sync(&key);
-            tx.fill(res);
-            drop(async_guard);
-        });
-        rx
-    }
-
     /// Writes a batch to backing [Blob] storage.
     ///
     /// Returns the size of the encoded blob value in bytes.
@@ -296,8 +835,12 @@ impl<B: Blob> BlobCache<B> {
 
         let mut val = Vec::new();
         let format = ProtoBatchFormat::ParquetKvtd;
-        batch.encode(&mut val);
+        encode_trace_parquet(&mut val, &batch, self.compression)?;
         let val_len = u64::cast_from(val.len());
+        // NB: This happens before the write so `val` can be handed to the
+        // blob without a copy. If the write fails, nothing references this
+        // key and it's eventually evicted.
+        self.disk_insert(&key, &val)?;
 
         let write_start = Instant::now();
         block_on(self.blob.lock()?.set(&key, val, Atomicity::AllowNonAtomic))
@@ -309,7 +852,8 @@ impl<B: Blob> BlobCache<B> {
         self.metrics.trace.blob_write_count.inc();
         self.metrics.trace.blob_write_bytes.inc_by(val_len);
 
-        self.trace.lock()?.insert(key, Arc::new(batch));
+        let size_bytes = batch.size_bytes();
+        self.memory_insert(key, CachedBatch::Trace(Arc::new(batch)), size_bytes)?;
 
         drop(async_guard);
         Ok((format, val_len))
@@ -320,7 +864,7 @@ impl<B: Blob> BlobCache<B> {
         let async_guard = self.async_runtime.enter();
 
         let delete_start = Instant::now();
-        self.trace.lock()?.remove(&batch.key);
+        self.uncache(&batch.key)?;
         block_on(self.blob.lock()?.delete(&batch.key))?;
         self.metrics
             .trace
@@ -336,32 +880,21 @@ impl<B: Blob> BlobCache<B> {
         Ok(())
     }
 
-    /// Fetches metadata about what batches are in [Blob] storage.
-    pub fn get_meta(&self) -> Result<Option<BlobMeta>, Error> {
-        let async_guard = self.async_runtime.enter();
-
-        let blob = self.blob.lock()?;
-        let bytes = match block_on(blob.get(Self::META_KEY))? {
-            Some(bytes) => bytes,
-            None => return Ok(None),
-        };
-        let meta = ProtoMeta::decode(&bytes).map_err(|err| {
-            Error::from(format!("invalid meta at key {}: {}", Self::META_KEY, err))
-        })?;
-        self.check_meta_build_version(&meta)?;
-        let meta = BlobMeta::from(meta);
-        debug_assert_eq!(meta.validate(), Ok(()), "{:?}", &meta);
-
-        drop(async_guard);
-        Ok(Some(meta))
-    }
-
     /// Overwrites metadata about what batches are in [Blob] storage.
     pub fn set_meta(&mut self, meta: &BlobMeta) -> Result<(), Error> {
         let async_guard = self.async_runtime.enter();
 
         debug_assert_eq!(meta.validate(), Ok(()), "{:?}", &meta);
-        let meta = ProtoMeta::from((meta, &self.build_version));
+        let mut meta = ProtoMeta::from((meta, &self.build_version));
+        meta.encryption_key_id = self
+            .blob
+            .lock()?
+            .encryption_key_id()
+            .unwrap_or_default()
+            .to_owned();
+        if let Some(consensus) = self.consensus.as_ref() {
+            meta.writer_epoch = consensus.epoch;
+        }
 
         let mut val = Vec::new();
         meta.encode(&mut val);
@@ -369,12 +902,36 @@ impl<B: Blob> BlobCache<B> {
         self.metrics.meta_size_bytes.set(val_len);
 
         let write_start = Instant::now();
-        block_on(
-            self.blob
-                .lock()?
-                .set(Self::META_KEY, val, Atomicity::RequireAtomic),
-        )
-        .map_err(|err| self.metric_set_error(err))?;
+        match self.consensus.as_ref() {
+            None => block_on(
+                self.blob
+                    .lock()?
+                    .set(Self::META_KEY, val, Atomicity::RequireAtomic),
+            )
+            .map_err(|err| self.metric_set_error(err))?,
+            Some(consensus) => {
+                // NB: If this errors, we don't know whether the write went
+                // through, so every later attempt fails the compare and this
+                // process has to be restarted to take over again. That's
+                // always safe.
+                let res = block_on(self.blob.lock()?.compare_and_set(
+                    Self::META_KEY,
+                    Some(&consensus.committed),
+                    val.clone(),
+                ))
+                .map_err(|err| self.metric_set_error(err))?;
+                if res.is_err() {
+                    return Err(self.metric_set_error(Error::from(format!(
+                        "another process took over writing to persist storage after writer epoch {}",
+                        consensus.epoch
+                    ))));
+                }
+                self.consensus = Some(Consensus {
+                    epoch: consensus.epoch,
+                    committed: val,
+                });
+            }
+        }
         self.metrics
             .meta
             .blob_write_seconds
@@ -398,35 +955,58 @@ impl<B: Blob> BlobCache<B> {
         Ok(())
     }
 
-    fn check_meta_build_version(&self, meta: &ProtoMeta) -> Result<(), Error> {
-        // TODO: After ENCODING_VERSION is bumped to 8 or higher, this can be
-        // removed.
-        let meta_version = if meta.version.is_empty() {
-            // Any build that includes this check comes after a ProtoMeta that
-            // was written with no version set.
-            Version::new(0, 0, 0)
-        } else {
-            meta.version
-                .parse::<Version>()
-                .map_err(|err| err.to_string())?
-        };
-        // Allow data written by any previous version of persist (backward
-        // compatible for all time) but disallow data written by a future
-        // version of persist (aka we're currently *not* forward compatible).
-        // Note that at some point, mz will need to be forward compatible to
-        // allow for rollbacks but this policy is not yet settled.
-        //
-        // NB: Since ProtoMeta is the entrypoint for all written persist
-        // metadata and data, it's an upper bound on versions involved in any
-        // persist data.
-        if meta_version > self.build_version {
-            return Err(format!(
-                "persist v{} cannot read data written by future persist v{}",
-                self.build_version, meta_version
-            )
-            .into());
+    /// Takes over as the only process writing to this [Blob] location.
+    ///
+    /// This claims the next writer epoch in META with a compare-and-set, after
+    /// which every META write by this cache is a compare-and-set against the
+    /// previous one. Any other process that was writing here, even one that
+    /// still believes it holds the lock, has its next META write fail instead
+    /// of clobbering ours. This is only sound if every process writing to
+    /// the location does the same.
+    pub fn take_over(&mut self) -> Result<(), Error> {
+        let async_guard = self.async_runtime.enter();
+
+        for _ in 0..Self::TAKE_OVER_MAX_ATTEMPTS {
+            let current = block_on(self.blob.lock()?.get(Self::META_KEY))?;
+            let mut meta = match current.as_ref() {
+                Some(bytes) => self.decode_meta(bytes)?,
+                None => ProtoMeta::from((&BlobMeta::default(), &self.build_version)),
+            };
+            meta.version = self.build_version.to_string();
+            meta.encryption_key_id = self
+                .blob
+                .lock()?
+                .encryption_key_id()
+                .unwrap_or_default()
+                .to_owned();
+            meta.writer_epoch += 1;
+
+            let mut val = Vec::new();
+            meta.encode(&mut val);
+            let res = block_on(self.blob.lock()?.compare_and_set(
+                Self::META_KEY,
+                current.as_deref(),
+                val.clone(),
+            ))
+            .map_err(|err| self.metric_set_error(err))?;
+            match res {
+                Ok(()) => {
+                    self.prev_meta_len = u64::cast_from(val.len());
+                    self.consensus = Some(Consensus {
+                        epoch: meta.writer_epoch,
+                        committed: val,
+                    });
+                    drop(async_guard);
+                    return Ok(());
+                }
+                // Someone else wrote META in the meantime, try again.
+                Err(_) => continue,
+            }
         }
-        Ok(())
+        Err(Error::from(format!(
+            "unable to take over writing to persist storage after {} attempts",
+            Self::TAKE_OVER_MAX_ATTEMPTS
+        )))
     }
 
     fn metric_set_error(&self, err: Error) -> Error {
@@ -436,16 +1016,15 @@ impl<B: Blob> BlobCache<B> {
         };
         err
     }
-
-    /// Returns the list of keys known to the underlying [Blob].
-    pub fn list_keys(&self) -> Result<Vec<String>, Error> {
-        block_on(self.blob.lock()?.list_keys())
-    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::mem::MemRegistry;
+    use differential_dataflow::trace::Description;
+    use timely::progress::Antichain;
+
+    use crate::encrypted::{EncryptedBlob, EncryptionKey, Keyring};
+    use crate::mem::{MemBlob, MemRegistry};
     use crate::storage::SeqNo;
 
     use super::*;
@@ -493,4 +1072,252 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn encrypted_meta() -> Result<(), Error> {
+        let registry = MemRegistry::new();
+        let key = EncryptionKey::new("k1".into(), &[1; 32])?;
+        let mut cache = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            Arc::new(Metrics::default()),
+            Arc::new(AsyncRuntime::new()?),
+            EncryptedBlob::new(registry.blob_no_reentrance()?, Keyring::new(key, vec![])?),
+        );
+
+        // The key id is recorded alongside the meta, but it doesn't affect
+        // what roundtrips.
+        cache.set_meta(&BlobMeta::default())?;
+        assert_eq!(cache.get_meta(), Ok(Some(BlobMeta::default())));
+        let bytes = block_on(cache.blob.lock()?.get(BlobCache::<MemBlob>::META_KEY))?;
+        let meta = ProtoMeta::decode(&bytes.expect("meta was set"))?;
+        assert_eq!(meta.encryption_key_id, "k1");
+        cache.close()?;
+
+        // Reading it back without the key is a clear error.
+        let mut cache = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            Arc::new(Metrics::default()),
+            Arc::new(AsyncRuntime::new()?),
+            registry.blob_no_reentrance()?,
+        );
+        assert_eq!(
+            cache.get_meta(),
+            Err("persisted data is encrypted, but no encryption key was configured".into())
+        );
+        cache.close()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn take_over() -> Result<(), Error> {
+        let registry = MemRegistry::new();
+        let new_cache = || -> Result<_, Error> {
+            Ok(BlobCache::new(
+                build_info::DUMMY_BUILD_INFO,
+                Arc::new(Metrics::default()),
+                Arc::new(AsyncRuntime::new()?),
+                registry.blob_shared()?,
+            ))
+        };
+        let meta = |seqno| BlobMeta {
+            seqno: SeqNo(seqno),
+            ..Default::default()
+        };
+        let epoch = |cache: &BlobCache<MemBlob>| -> Result<u64, Error> {
+            let bytes = block_on(cache.blob.lock()?.get(BlobCache::<MemBlob>::META_KEY))?;
+            Ok(ProtoMeta::decode(&bytes.expect("meta was set"))?.writer_epoch)
+        };
+
+        // Taking over an empty location starts at epoch 1 and doesn't change
+        // what's in META.
+        let mut c0 = new_cache()?;
+        c0.take_over()?;
+        assert_eq!(epoch(&c0)?, 1);
+        assert_eq!(c0.get_meta(), Ok(Some(meta(0))));
+        c0.set_meta(&meta(1))?;
+        c0.set_meta(&meta(2))?;
+        assert_eq!(epoch(&c0)?, 1);
+
+        // A second writer takes over without losing what the first one
+        // committed.
+        let mut c1 = new_cache()?;
+        c1.take_over()?;
+        assert_eq!(epoch(&c1)?, 2);
+        assert_eq!(c1.get_meta(), Ok(Some(meta(2))));
+
+        // From then on, the first one is fenced out and can't clobber what
+        // the second one writes.
+        assert_eq!(
+            c0.set_meta(&meta(3)),
+            Err(Error::from(
+                "another process took over writing to persist storage after writer epoch 1"
+            ))
+        );
+        c1.set_meta(&meta(3))?;
+        assert_eq!(c0.is_fenced(), Ok(true));
+        assert_eq!(c1.is_fenced(), Ok(false));
+        assert_eq!(
+            c0.set_meta(&meta(4)),
+            Err(Error::from(
+                "another process took over writing to persist storage after writer epoch 1"
+            ))
+        );
+        assert_eq!(c1.get_meta(), Ok(Some(meta(3))));
+        assert_eq!(epoch(&c1)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_compression() -> Result<(), Error> {
+        let uncompressed = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            Arc::new(Metrics::default()),
+            Arc::new(AsyncRuntime::new()?),
+            MemRegistry::new().blob_no_reentrance()?,
+        );
+        let zstd = uncompressed
+            .clone()
+            .with_compression(BatchCompression::Zstd);
+
+        let batch = |lower, upper| BlobTraceBatch {
+            desc: Description::new(
+                Antichain::from_elem(lower),
+                Antichain::from_elem(upper),
+                Antichain::from_elem(0),
+            ),
+            updates: vec![((b"k".to_vec(), b"v".to_vec()), lower, 1)],
+        };
+        let (b0, b1) = (batch(0, 1), batch(1, 2));
+        uncompressed.set_trace_batch("b0".into(), b0.clone())?;
+        zstd.set_trace_batch("b1".into(), b1.clone())?;
+
+        // Drop the decoded batches so they're read back from blob storage.
+        uncompressed.retain_cached(&HashSet::new())?;
+        assert_eq!(*uncompressed.get_trace_batch_async("b0").recv()?, b0);
+        assert_eq!(*uncompressed.get_trace_batch_async("b1").recv()?, b1);
+
+        Ok(())
+    }
+
+    fn trace_batch(lower: u64) -> BlobTraceBatch {
+        BlobTraceBatch {
+            desc: Description::new(
+                Antichain::from_elem(lower),
+                Antichain::from_elem(lower + 1),
+                Antichain::from_elem(0),
+            ),
+            updates: vec![((b"k".to_vec(), b"v".to_vec()), lower, 1)],
+        }
+    }
+
+    #[test]
+    fn memory_limit() -> Result<(), Error> {
+        let metrics = Arc::new(Metrics::default());
+        let size_bytes = u64::cast_from(trace_batch(0).size_bytes());
+        let cache = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            Arc::new(AsyncRuntime::new()?),
+            MemRegistry::new().blob_no_reentrance()?,
+        )
+        .with_config(BlobCacheConfig::default().with_memory_limit(2 * size_bytes))?;
+
+        // Reading b0 makes b1 the least recently used batch, so it's the one
+        // evicted to make room for b2.
+        cache.set_trace_batch("b0".into(), trace_batch(0))?;
+        cache.set_trace_batch("b1".into(), trace_batch(1))?;
+        assert_eq!(*cache.get_trace_batch_async("b0").recv()?, trace_batch(0));
+        cache.set_trace_batch("b2".into(), trace_batch(2))?;
+        assert_eq!(metrics.blob_read_cache_eviction_count.get(), 1);
+        assert_eq!(metrics.blob_read_cache_entry_count.get(), 2);
+        assert_eq!(metrics.blob_read_cache_bytes.get(), 2 * size_bytes);
+        assert_eq!(metrics.blob_read_cache_hit_count.get(), 1);
+        assert_eq!(metrics.blob_read_cache_miss_count.get(), 0);
+
+        // An evicted batch is fetched from blob storage again.
+        assert_eq!(*cache.get_trace_batch_async("b1").recv()?, trace_batch(1));
+        assert_eq!(metrics.blob_read_cache_miss_count.get(), 1);
+        assert!(metrics.blob_read_cache_fetch_bytes.get() > 0);
+        assert_eq!(metrics.blob_read_cache_eviction_count.get(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn disk_tier() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let stale = dir.path().join("stale.batch");
+        fs::write(&stale, b"left over from a previous process")?;
+
+        let metrics = Arc::new(Metrics::default());
+        let cache = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            Arc::new(AsyncRuntime::new()?),
+            MemRegistry::new().blob_no_reentrance()?,
+        )
+        .with_config(
+            BlobCacheConfig::default()
+                .with_memory_limit(0)
+                .with_disk(dir.path().to_owned(), u64::MAX),
+        )?;
+        assert!(!stale.exists());
+
+        // Nothing fits in memory, but the batch we wrote is read back from
+        // local disk instead of blob storage.
+        cache.set_trace_batch("b0".into(), trace_batch(0))?;
+        assert!(dir.path().join("b0.batch").exists());
+        assert_eq!(*cache.get_trace_batch_async("b0").recv()?, trace_batch(0));
+        assert_eq!(metrics.blob_read_cache_miss_count.get(), 1);
+        assert_eq!(metrics.blob_read_cache_disk_hit_count.get(), 1);
+        assert_eq!(metrics.blob_read_cache_fetch_bytes.get(), 0);
+        assert_eq!(metrics.blob_read_cache_disk_entry_count.get(), 1);
+
+        // Batches that are no longer referenced are removed from disk, too.
+        cache.retain_cached(&HashSet::new())?;
+        assert!(!dir.path().join("b0.batch").exists());
+        assert_eq!(metrics.blob_read_cache_disk_entry_count.get(), 0);
+        assert_eq!(metrics.blob_read_cache_disk_bytes.get(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn disk_tier_encryption() -> Result<(), Error> {
+        let dir = tempfile::tempdir()?;
+        let keys = Keyring::new(EncryptionKey::new("k1".into(), &[1; 32])?, vec![])?;
+        let metrics = Arc::new(Metrics::default());
+        let cache = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            Arc::new(AsyncRuntime::new()?),
+            EncryptedBlob::new(MemRegistry::new().blob_no_reentrance()?, keys.clone()),
+        )
+        .with_config(
+            BlobCacheConfig::default()
+                .with_memory_limit(0)
+                .with_disk(dir.path().to_owned(), u64::MAX)
+                .with_disk_encryption(keys),
+        )?;
+
+        // The batch is held on disk encrypted and still read back from there.
+        cache.set_trace_batch("b0".into(), trace_batch(0))?;
+        let path = dir.path().join("b0.batch");
+        assert!(encrypted::is_encrypted(&fs::read(&path)?));
+        assert_eq!(*cache.get_trace_batch_async("b0").recv()?, trace_batch(0));
+        assert_eq!(metrics.blob_read_cache_disk_hit_count.get(), 1);
+        assert_eq!(metrics.blob_read_cache_fetch_bytes.get(), 0);
+
+        // A file that fails authentication, e.g. one copied from another key,
+        // is a miss and the batch is fetched from the blob instead.
+        cache.set_trace_batch("b1".into(), trace_batch(1))?;
+        fs::copy(&path, dir.path().join("b1.batch"))?;
+        assert_eq!(*cache.get_trace_batch_async("b1").recv()?, trace_batch(1));
+        assert_eq!(metrics.blob_read_cache_disk_error_count.get(), 1);
+        assert!(metrics.blob_read_cache_fetch_bytes.get() > 0);
+
+        Ok(())
+    }
 }
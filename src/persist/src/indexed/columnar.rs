@@ -11,12 +11,16 @@
 //! reads and persistent storage.
 
 use std::iter::FromIterator;
+use std::mem::size_of;
+use std::str::FromStr;
 use std::{cmp, fmt};
 
 use arrow2::buffer::{Buffer, MutableBuffer};
 use arrow2::types::Index;
 use ore::cast::CastFrom;
 
+use crate::gen::persist::ProtoBatchCompression;
+
 pub mod arrow;
 pub mod parquet;
 
@@ -39,6 +43,93 @@ pub const KEY_VAL_DATA_MAX_LEN: usize = i32::MAX as usize;
 
 const BYTES_PER_KEY_VAL_OFFSET: usize = 4;
 
+/// The codec used to compress the columns of an encoded batch.
+///
+/// The codec is recorded in each batch's inline metadata, so batches written
+/// with different codecs (including those written before compression was
+/// configurable, which are uncompressed) can be read side by side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchCompression {
+    /// No compression.
+    Uncompressed,
+    /// Zstandard compression.
+    Zstd,
+    /// LZ4 compression.
+    Lz4,
+    /// Snappy compression.
+    Snappy,
+}
+
+impl BatchCompression {
+    /// All supported codecs, in the order they're listed in error messages.
+    pub const ALL: [BatchCompression; 4] = [
+        BatchCompression::Uncompressed,
+        BatchCompression::Zstd,
+        BatchCompression::Lz4,
+        BatchCompression::Snappy,
+    ];
+
+    /// The name used to configure this codec.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchCompression::Uncompressed => "none",
+            BatchCompression::Zstd => "zstd",
+            BatchCompression::Lz4 => "lz4",
+            BatchCompression::Snappy => "snappy",
+        }
+    }
+}
+
+impl fmt::Display for BatchCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BatchCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BatchCompression::ALL
+            .iter()
+            .find(|x| x.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "unknown compression {}, expected one of: {}",
+                    s,
+                    BatchCompression::ALL
+                        .iter()
+                        .map(|x| x.name())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+impl From<BatchCompression> for ProtoBatchCompression {
+    fn from(x: BatchCompression) -> Self {
+        match x {
+            BatchCompression::Uncompressed => ProtoBatchCompression::Uncompressed,
+            BatchCompression::Zstd => ProtoBatchCompression::Zstd,
+            BatchCompression::Lz4 => ProtoBatchCompression::Lz4,
+            BatchCompression::Snappy => ProtoBatchCompression::Snappy,
+        }
+    }
+}
+
+impl From<ProtoBatchCompression> for BatchCompression {
+    fn from(x: ProtoBatchCompression) -> Self {
+        match x {
+            ProtoBatchCompression::Uncompressed => BatchCompression::Uncompressed,
+            ProtoBatchCompression::Zstd => BatchCompression::Zstd,
+            ProtoBatchCompression::Lz4 => BatchCompression::Lz4,
+            ProtoBatchCompression::Snappy => BatchCompression::Snappy,
+        }
+    }
+}
+
 /// A set of ((Key, Val), Time, Diff) records stored in a columnar
 /// representation.
 ///
@@ -106,6 +197,16 @@ impl ColumnarRecords {
     pub fn iter<'a>(&'a self) -> ColumnarRecordsIter<'a> {
         self.borrow().iter()
     }
+
+    /// The approximate size in bytes of the data held by Self.
+    pub fn size_bytes(&self) -> usize {
+        self.key_data.len()
+            + self.key_offsets.len() * size_of::<i32>()
+            + self.val_data.len()
+            + self.val_offsets.len() * size_of::<i32>()
+            + self.timestamps.len() * size_of::<u64>()
+            + self.diffs.len() * size_of::<i64>()
+    }
 }
 
 // TODO: deduplicate this with the other FromIterator implementation.
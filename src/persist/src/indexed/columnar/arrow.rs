@@ -16,7 +16,7 @@ use std::sync::Arc;
 use arrow2::array::{BinaryArray, PrimitiveArray};
 use arrow2::datatypes::{DataType, Field, Schema};
 use arrow2::io::ipc::read::{read_file_metadata, FileMetadata, FileReader};
-use arrow2::io::ipc::write::{FileWriter, WriteOptions};
+use arrow2::io::ipc::write::{Compression, FileWriter, WriteOptions};
 use arrow2::record_batch::RecordBatch;
 use differential_dataflow::trace::Description;
 use lazy_static::lazy_static;
@@ -24,7 +24,7 @@ use timely::progress::{Antichain, Timestamp};
 
 use crate::error::Error;
 use crate::gen::persist::ProtoBatchFormat;
-use crate::indexed::columnar::{ColumnarRecords, ColumnarRecordsVec};
+use crate::indexed::columnar::{BatchCompression, ColumnarRecords, ColumnarRecordsVec};
 use crate::indexed::encoding::{
     decode_trace_inline_meta, decode_unsealed_inline_meta, encode_trace_inline_meta,
     encode_unsealed_inline_meta, BlobTraceBatch, BlobUnsealedBatch,
@@ -75,14 +75,20 @@ const INLINE_METADATA_KEY: &'static str = "MZ:inline";
 ///
 /// NB: This is currently unused, but it's here because we may want to use it
 /// for the local cache and so we can easily compare arrow vs parquet.
-pub fn encode_unsealed_arrow<W: Write>(w: &mut W, batch: &BlobUnsealedBatch) -> Result<(), Error> {
+pub fn encode_unsealed_arrow<W: Write>(
+    w: &mut W,
+    batch: &BlobUnsealedBatch,
+    compression: BatchCompression,
+) -> Result<(), Error> {
     let mut metadata = HashMap::with_capacity(1);
     metadata.insert(
         INLINE_METADATA_KEY.into(),
-        encode_unsealed_inline_meta(batch, ProtoBatchFormat::ArrowKvtd),
+        encode_unsealed_inline_meta(batch, ProtoBatchFormat::ArrowKvtd, compression),
     );
     let schema = Schema::new_from(SCHEMA_ARROW_KVTD.fields().clone(), metadata);
-    let options = WriteOptions { compression: None };
+    let options = WriteOptions {
+        compression: arrow_compression(compression)?,
+    };
     let mut writer = FileWriter::try_new(w, &schema, options)?;
     for records in batch.updates.iter() {
         writer.write(&encode_arrow_batch_kvtd(records))?;
@@ -95,14 +101,20 @@ pub fn encode_unsealed_arrow<W: Write>(w: &mut W, batch: &BlobUnsealedBatch) ->
 ///
 /// NB: This is currently unused, but it's here because we may want to use it
 /// for the local cache and so we can easily compare arrow vs parquet.
-pub fn encode_trace_arrow<W: Write>(w: &mut W, batch: &BlobTraceBatch) -> Result<(), Error> {
+pub fn encode_trace_arrow<W: Write>(
+    w: &mut W,
+    batch: &BlobTraceBatch,
+    compression: BatchCompression,
+) -> Result<(), Error> {
     let mut metadata = HashMap::with_capacity(1);
     metadata.insert(
         INLINE_METADATA_KEY.into(),
-        encode_trace_inline_meta(batch, ProtoBatchFormat::ArrowKvtd),
+        encode_trace_inline_meta(batch, ProtoBatchFormat::ArrowKvtd, compression),
     );
     let schema = Schema::new_from(SCHEMA_ARROW_KVTD.fields().clone(), metadata);
-    let options = WriteOptions { compression: None };
+    let options = WriteOptions {
+        compression: arrow_compression(compression)?,
+    };
     let mut writer = FileWriter::try_new(w, &schema, options)?;
     let records = batch
         .updates
@@ -183,6 +195,19 @@ pub fn decode_trace_arrow<R: Read + Seek>(r: &mut R) -> Result<BlobTraceBatch, E
     Ok(ret)
 }
 
+/// Maps a codec onto the ones supported by the Arrow IPC format, which are a
+/// subset of the ones supported by Parquet.
+fn arrow_compression(compression: BatchCompression) -> Result<Option<Compression>, Error> {
+    match compression {
+        BatchCompression::Uncompressed => Ok(None),
+        BatchCompression::Zstd => Ok(Some(Compression::ZSTD)),
+        BatchCompression::Lz4 => Ok(Some(Compression::LZ4)),
+        BatchCompression::Snappy => {
+            Err(format!("{} compression not supported in arrow", compression).into())
+        }
+    }
+}
+
 fn decode_arrow_file_kvtd<R: Read + Seek>(
     r: &mut R,
     file_meta: FileMetadata,
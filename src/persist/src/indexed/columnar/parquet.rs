@@ -26,7 +26,7 @@ use crate::gen::persist::ProtoBatchFormat;
 use crate::indexed::columnar::arrow::{
     decode_arrow_batch_kvtd, encode_arrow_batch_kvtd, SCHEMA_ARROW_KVTD,
 };
-use crate::indexed::columnar::{ColumnarRecords, ColumnarRecordsVec};
+use crate::indexed::columnar::{BatchCompression, ColumnarRecords, ColumnarRecordsVec};
 use crate::indexed::encoding::{
     decode_trace_inline_meta, decode_unsealed_inline_meta, encode_trace_inline_meta,
     encode_unsealed_inline_meta, BlobTraceBatch, BlobUnsealedBatch,
@@ -39,16 +39,22 @@ const INLINE_METADATA_KEY: &'static str = "MZ:inline";
 pub fn encode_unsealed_parquet<W: Write>(
     w: &mut W,
     batch: &BlobUnsealedBatch,
+    compression: BatchCompression,
 ) -> Result<(), Error> {
     encode_parquet_kvtd(
         w,
-        encode_unsealed_inline_meta(batch, ProtoBatchFormat::ParquetKvtd),
+        encode_unsealed_inline_meta(batch, ProtoBatchFormat::ParquetKvtd, compression),
         &batch.updates,
+        compression,
     )
 }
 
 /// Encodes an BlobTraceBatch into the Parquet format.
-pub fn encode_trace_parquet<W: Write>(w: &mut W, batch: &BlobTraceBatch) -> Result<(), Error> {
+pub fn encode_trace_parquet<W: Write>(
+    w: &mut W,
+    batch: &BlobTraceBatch,
+    compression: BatchCompression,
+) -> Result<(), Error> {
     let updates = batch
         .updates
         .iter()
@@ -57,8 +63,9 @@ pub fn encode_trace_parquet<W: Write>(w: &mut W, batch: &BlobTraceBatch) -> Resu
         .into_inner();
     encode_parquet_kvtd(
         w,
-        encode_trace_inline_meta(batch, ProtoBatchFormat::ParquetKvtd),
+        encode_trace_inline_meta(batch, ProtoBatchFormat::ParquetKvtd, compression),
         &updates,
+        compression,
     )
 }
 
@@ -133,13 +140,22 @@ fn encode_parquet_kvtd<W: Write>(
     w: &mut W,
     inline_base64: String,
     batches: &[ColumnarRecords],
+    compression: BatchCompression,
 ) -> Result<(), Error> {
     let iter = batches.iter().map(|x| Ok(encode_arrow_batch_kvtd(x)));
 
     let schema = SCHEMA_ARROW_KVTD.clone();
+    // NB: Parquet records the codec of each column chunk, so decoding doesn't
+    // need to be told which one was used here.
+    let compression = match compression {
+        BatchCompression::Uncompressed => Compression::Uncompressed,
+        BatchCompression::Zstd => Compression::Zstd,
+        BatchCompression::Lz4 => Compression::Lz4,
+        BatchCompression::Snappy => Compression::Snappy,
+    };
     let options = WriteOptions {
         write_statistics: false,
-        compression: Compression::Uncompressed,
+        compression,
         version: Version::V2,
     };
     let row_groups = RowGroupIterator::try_new(
@@ -17,6 +17,7 @@ use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::io::Cursor;
+use std::mem::size_of;
 use std::ops::Range;
 
 use bytes::BufMut;
@@ -31,14 +32,15 @@ use timely::PartialOrder;
 
 use crate::error::Error;
 use crate::gen::persist::{
-    proto_batch_inline, ProtoArrangement, ProtoBatchFormat, ProtoBatchInline, ProtoMeta,
-    ProtoStreamRegistration, ProtoTraceBatchInline, ProtoTraceBatchMeta, ProtoU64Antichain,
-    ProtoU64Description, ProtoUnsealedBatchInline, ProtoUnsealedBatchMeta,
+    proto_batch_inline, ProtoArrangement, ProtoBatchCompression, ProtoBatchFormat,
+    ProtoBatchInline, ProtoMeta, ProtoStreamRegistration, ProtoTraceBatchInline,
+    ProtoTraceBatchMeta, ProtoU64Antichain, ProtoU64Description, ProtoUnsealedBatchInline,
+    ProtoUnsealedBatchMeta,
 };
 use crate::indexed::columnar::parquet::{
     decode_trace_parquet, decode_unsealed_parquet, encode_trace_parquet, encode_unsealed_parquet,
 };
-use crate::indexed::columnar::ColumnarRecords;
+use crate::indexed::columnar::{BatchCompression, ColumnarRecords};
 use crate::storage::SeqNo;
 
 /// An internally unique id for a persisted stream. External users identify
@@ -129,6 +131,7 @@ pub struct StreamRegistration {
 /// - The compaction level of trace_batches is weakly decreasing when iterating
 ///   from oldest to most recent time intervals.
 /// - Every trace_batch's upper is <= the overall trace's seal frontier.
+/// - Every batch's schema version is <= the overall schema version.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ArrangementMeta {
     /// The stream this unsealed belongs to.
@@ -145,6 +148,8 @@ pub struct ArrangementMeta {
     pub unsealed_batches: Vec<UnsealedBatchMeta>,
     /// The batches that make up the Trace.
     pub trace_batches: Vec<TraceBatchMeta>,
+    /// The [crate::schema::Schema] version of newly written data.
+    pub schema_version: u64,
 }
 
 /// The metadata necessary to reconstruct a [BlobUnsealedBatch].
@@ -166,6 +171,8 @@ pub struct UnsealedBatchMeta {
     pub ts_lower: u64,
     /// Size of the encoded batch.
     pub size_bytes: u64,
+    /// The [crate::schema::Schema] version the batch's data was written with.
+    pub schema_version: u64,
 }
 
 /// The metadata necessary to reconstruct a [BlobTraceBatch].
@@ -186,6 +193,8 @@ pub struct TraceBatchMeta {
     pub level: u64,
     /// Size of the encoded batch.
     pub size_bytes: u64,
+    /// The [crate::schema::Schema] version the batch's data was written with.
+    pub schema_version: u64,
 }
 
 /// The structure serialized and stored as a value in [crate::storage::Blob]
@@ -397,6 +406,7 @@ impl Default for ArrangementMeta {
             seal: Antichain::from_elem(Timestamp::minimum()),
             unsealed_batches: Vec::new(),
             trace_batches: Vec::new(),
+            schema_version: 0,
         }
     }
 }
@@ -416,6 +426,13 @@ impl ArrangementMeta {
         let mut unsealed_prev: Option<&UnsealedBatchMeta> = None;
         for meta in self.unsealed_batches.iter() {
             meta.validate()?;
+            if meta.schema_version > self.schema_version {
+                return Err(format!(
+                    "invalid batch schema version: {} in advance of arrangement schema version {}",
+                    meta.schema_version, self.schema_version
+                )
+                .into());
+            }
             if let Some(prev) = unsealed_prev {
                 if prev.desc.end > meta.desc.start {
                     return Err(format!(
@@ -448,6 +465,14 @@ impl ArrangementMeta {
 
             meta.validate()?;
 
+            if meta.schema_version > self.schema_version {
+                return Err(format!(
+                    "invalid batch schema version: {} in advance of arrangement schema version {}",
+                    meta.schema_version, self.schema_version
+                )
+                .into());
+            }
+
             if let Some(prev) = trace_prev {
                 if prev.desc.upper() != meta.desc.lower() {
                     return Err(format!(
@@ -536,6 +561,11 @@ impl BlobUnsealedBatch {
 
         Ok(())
     }
+
+    /// The approximate size in bytes of the decoded batch.
+    pub fn size_bytes(&self) -> usize {
+        self.updates.iter().map(|x| x.size_bytes()).sum()
+    }
 }
 
 // BlobUnsealedBatch doesn't really need to implement Codec (it's never stored
@@ -550,7 +580,8 @@ impl Codec for BlobUnsealedBatch {
     where
         B: BufMut,
     {
-        encode_unsealed_parquet(&mut buf.writer(), &self).expect("writes to BufMut are infallible");
+        encode_unsealed_parquet(&mut buf.writer(), &self, BatchCompression::Uncompressed)
+            .expect("writes to BufMut are infallible");
     }
 
     fn decode<'a>(buf: &'a [u8]) -> Result<Self, String> {
@@ -617,6 +648,14 @@ impl BlobTraceBatch {
         }
         Ok(())
     }
+
+    /// The approximate size in bytes of the decoded batch.
+    pub fn size_bytes(&self) -> usize {
+        self.updates
+            .iter()
+            .map(|((k, v), _, _)| size_of::<((Vec<u8>, Vec<u8>), u64, isize)>() + k.len() + v.len())
+            .sum()
+    }
 }
 
 // BlobTraceBatch doesn't really need to implement Codec (it's never stored as a
@@ -631,7 +670,8 @@ impl Codec for BlobTraceBatch {
     where
         B: BufMut,
     {
-        encode_trace_parquet(&mut buf.writer(), self).expect("writes to BufMut are infallible");
+        encode_trace_parquet(&mut buf.writer(), self, BatchCompression::Uncompressed)
+            .expect("writes to BufMut are infallible");
     }
 
     fn decode<'a>(buf: &'a [u8]) -> Result<Self, String> {
@@ -689,6 +729,7 @@ impl From<(u64, ProtoArrangement)> for ArrangementMeta {
                 .map_or_else(|| Antichain::from_elem(u64::minimum()), |x| x.into()),
             unsealed_batches: x.unsealed_batches.into_iter().map(|x| x.into()).collect(),
             trace_batches: x.trace_batches.into_iter().map(|x| x.into()).collect(),
+            schema_version: x.schema_version,
         }
     }
 }
@@ -714,6 +755,7 @@ impl From<ProtoUnsealedBatchMeta> for UnsealedBatchMeta {
             ts_upper: x.ts_upper,
             ts_lower: x.ts_lower,
             size_bytes: x.size_bytes,
+            schema_version: x.schema_version,
         }
     }
 }
@@ -735,6 +777,7 @@ impl From<ProtoTraceBatchMeta> for TraceBatchMeta {
             ),
             level: x.level,
             size_bytes: x.size_bytes,
+            schema_version: x.schema_version,
         }
     }
 }
@@ -767,6 +810,10 @@ impl From<(&BlobMeta, &Version)> for ProtoMeta {
             id_mapping: x.id_mapping.iter().map(|x| (x.id.0, x.into())).collect(),
             graveyard: x.graveyard.iter().map(|x| (x.id.0, x.into())).collect(),
             arrangements: x.arrangements.iter().map(|x| (x.id.0, x.into())).collect(),
+            // Filled in by BlobCache::set_meta, these are properties of the
+            // Blob that the meta is written through.
+            encryption_key_id: String::new(),
+            writer_epoch: 0,
         }
     }
 }
@@ -778,6 +825,7 @@ impl From<&ArrangementMeta> for ProtoArrangement {
             seal: Some((&x.seal).into()),
             unsealed_batches: x.unsealed_batches.iter().map(|x| x.into()).collect(),
             trace_batches: x.trace_batches.iter().map(|x| x.into()).collect(),
+            schema_version: x.schema_version,
         }
     }
 }
@@ -802,6 +850,7 @@ impl From<&UnsealedBatchMeta> for ProtoUnsealedBatchMeta {
             ts_upper: x.ts_upper,
             ts_lower: x.ts_lower,
             size_bytes: x.size_bytes,
+            schema_version: x.schema_version,
         }
     }
 }
@@ -814,6 +863,7 @@ impl From<&TraceBatchMeta> for ProtoTraceBatchMeta {
             desc: Some((&x.desc).into()),
             level: x.level,
             size_bytes: x.size_bytes,
+            schema_version: x.schema_version,
         }
     }
 }
@@ -837,11 +887,16 @@ impl From<&Description<u64>> for ProtoU64Description {
 }
 
 /// Encodes the inline metadata for an unsealed batch into a base64 string.
-pub fn encode_unsealed_inline_meta(batch: &BlobUnsealedBatch, format: ProtoBatchFormat) -> String {
+pub fn encode_unsealed_inline_meta(
+    batch: &BlobUnsealedBatch,
+    format: ProtoBatchFormat,
+    compression: BatchCompression,
+) -> String {
     let inline = ProtoBatchInline {
         batch_type: Some(proto_batch_inline::BatchType::Unsealed(
             ProtoUnsealedBatchInline {
                 format: format.into(),
+                compression: ProtoBatchCompression::from(compression).into(),
                 seqno_lower: batch.desc.start.0,
                 seqno_upper: batch.desc.end.0,
             },
@@ -852,11 +907,16 @@ pub fn encode_unsealed_inline_meta(batch: &BlobUnsealedBatch, format: ProtoBatch
 }
 
 /// Encodes the inline metadata for a trace batch into a base64 string.
-pub fn encode_trace_inline_meta(batch: &BlobTraceBatch, format: ProtoBatchFormat) -> String {
+pub fn encode_trace_inline_meta(
+    batch: &BlobTraceBatch,
+    format: ProtoBatchFormat,
+    compression: BatchCompression,
+) -> String {
     let inline = ProtoBatchInline {
         batch_type: Some(proto_batch_inline::BatchType::Trace(
             ProtoTraceBatchInline {
                 format: format.into(),
+                compression: ProtoBatchCompression::from(compression).into(),
                 desc: Some((&batch.desc).into()),
             },
         )),
@@ -876,6 +936,10 @@ pub fn decode_unsealed_inline_meta(
         Some(proto_batch_inline::BatchType::Unsealed(x)) => {
             let format = ProtoBatchFormat::from_i32(x.format)
                 .ok_or_else(|| Error::from(format!("unknown format: {}", x.format)))?;
+            // The codec itself is recorded by the file format, but refuse to
+            // read batches compressed with a codec we don't know about.
+            ProtoBatchCompression::from_i32(x.compression)
+                .ok_or_else(|| Error::from(format!("unknown compression: {}", x.compression)))?;
             Ok((format, x))
         }
         x => return Err(format!("incorrect batch type: {:?}", x).into()),
@@ -893,6 +957,10 @@ pub fn decode_trace_inline_meta(
         Some(proto_batch_inline::BatchType::Trace(x)) => {
             let format = ProtoBatchFormat::from_i32(x.format)
                 .ok_or_else(|| Error::from(format!("unknown format: {}", x.format)))?;
+            // The codec itself is recorded by the file format, but refuse to
+            // read batches compressed with a codec we don't know about.
+            ProtoBatchCompression::from_i32(x.compression)
+                .ok_or_else(|| Error::from(format!("unknown compression: {}", x.compression)))?;
             Ok((format, x))
         }
         x => return Err(format!("incorrect batch type: {:?}", x).into()),
@@ -930,6 +998,7 @@ mod tests {
             desc: u64_desc(lower, upper),
             level: 1,
             size_bytes: 0,
+            schema_version: 0,
         }
     }
 
@@ -940,6 +1009,7 @@ mod tests {
             desc: u64_desc_since(lower, upper, since),
             level,
             size_bytes: 0,
+            schema_version: 0,
         }
     }
 
@@ -959,6 +1029,7 @@ mod tests {
             ts_upper: 0,
             ts_lower: 0,
             size_bytes: 0,
+            schema_version: 0,
         }
     }
 
@@ -1424,6 +1495,7 @@ mod tests {
                 trace_batches: vec![batch_meta(0, 1)],
                 since: Antichain::from_elem(0),
                 seal: Antichain::from_elem(1),
+                schema_version: 0,
             }],
             ..Default::default()
         };
@@ -1438,6 +1510,7 @@ mod tests {
                 trace_batches: vec![batch_meta(0, 1)],
                 since: Antichain::from_elem(0),
                 seal: Antichain::from_elem(1),
+                schema_version: 0,
             }],
             ..Default::default()
         };
@@ -1611,4 +1684,31 @@ mod tests {
             "1/1=(481, 501) 25/1=(2229, 2249) 1000/1=(72468, 72488) 1000/100=(106557, 72488)"
         );
     }
+
+    #[test]
+    fn compressed_batch_roundtrip() -> Result<(), Error> {
+        let data = DataGenerator::new(1_000, DataGenerator::default().record_size_bytes, 100);
+        let unsealed = BlobUnsealedBatch {
+            desc: SeqNo(0)..SeqNo(1),
+            updates: data.batches().collect(),
+        };
+        let trace = BlobTraceBatch {
+            desc: u64_desc(0, 1),
+            updates: data.records().collect(),
+        };
+
+        for compression in BatchCompression::ALL {
+            let mut buf = Vec::new();
+            encode_unsealed_parquet(&mut buf, &unsealed, compression)?;
+            let decoded = decode_unsealed_parquet(&mut Cursor::new(&buf))?;
+            assert_eq!(decoded.desc, unsealed.desc, "{}", compression);
+            assert_eq!(decoded.updates, unsealed.updates, "{}", compression);
+
+            let mut buf = Vec::new();
+            encode_trace_parquet(&mut buf, &trace, compression)?;
+            let decoded = decode_trace_parquet(&mut Cursor::new(&buf))?;
+            assert_eq!(decoded, trace, "{}", compression);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,553 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! A read-only view of persisted data written by another process.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::sync::Arc;
+
+use differential_dataflow::trace::Description;
+use timely::progress::{Antichain, Timestamp};
+use timely::PartialOrder;
+
+use crate::error::Error;
+use crate::indexed::arrangement::{Arrangement, ArrangementSnapshot};
+use crate::indexed::cache::BlobCache;
+use crate::indexed::encoding::{ArrangementMeta, BlobMeta, Id};
+use crate::indexed::metrics::Metrics;
+use crate::indexed::ListenEvent;
+use crate::pfuture::PFutureHandle;
+use crate::schema::{Schema, StreamSchema};
+use crate::storage::BlobRead;
+
+/// The number of times a read is retried because a batch it needed was
+/// concurrently deleted by the writer.
+const MAX_READ_ATTEMPTS: usize = 10;
+
+/// A listener and the frontier of the data it's been sent so far.
+#[derive(Debug)]
+struct FollowerListener {
+    sender: crossbeam_channel::Sender<ListenEvent>,
+    upper: Antichain<u64>,
+}
+
+/// A read-only counterpart to [crate::indexed::Indexed], which follows the
+/// data written to a [BlobRead] by an `Indexed` in another process.
+///
+/// Unlike `Indexed`, which is the source of truth for its [BlobMeta], a
+/// Follower only learns about changes by re-reading the meta. It does this on
+/// every [Self::poll] as well as before serving each read.
+///
+/// A Follower only observes data that the writer has sealed: snapshots include
+/// everything at times before the seal frontier and listeners are sent records
+/// in batches as the seal frontier advances, each followed by the new frontier.
+///
+/// The writer is free to compact and delete batches at any point, including
+/// while we're reading them. If a read finds that a batch it needs is gone,
+/// it's retried with the newer meta written by that compaction. A listener can
+/// only be kept up to date as long as the writer's since frontier doesn't pass
+/// the frontier the listener was last sent, after which the writer may have
+/// merged records we've already sent with ones we haven't and there's no way
+/// to tell them apart. Such listeners are disconnected: the sender half of
+/// their channel is dropped, the disconnect is logged, and it's counted in the
+/// `mz_persist_follower_listener_disconnect_count` metric. The writer's since
+/// frontier is advanced by `allow_compaction`, so a listener that falls behind
+/// it has to be restarted with a new [Self::listen].
+#[derive(Debug)]
+pub struct Follower<B: BlobRead> {
+    blob: BlobCache<B>,
+    metrics: Arc<Metrics>,
+    meta: BlobMeta,
+    listeners: HashMap<Id, Vec<FollowerListener>>,
+    schemas: HashMap<Id, Schema>,
+}
+
+impl<B: BlobRead> Follower<B> {
+    /// Returns a new Follower reading from the given blob.
+    pub fn new(blob: BlobCache<B>, metrics: Arc<Metrics>) -> Result<Self, Error> {
+        let meta = blob.get_meta()?.unwrap_or_default();
+        Ok(Follower {
+            blob,
+            metrics,
+            meta,
+            listeners: HashMap::new(),
+            schemas: HashMap::new(),
+        })
+    }
+
+    /// Releases resources held by this Follower and disconnects any
+    /// listeners.
+    pub fn close(&mut self) -> Result<(), Error> {
+        self.listeners.clear();
+        self.blob.close()?;
+        Ok(())
+    }
+
+    /// Looks up the internal id of an existing stream.
+    ///
+    /// Unlike [crate::indexed::Indexed::register], this never creates the
+    /// stream or advances its schema version, that's up to the writer. The
+    /// given [Schema] is only used to migrate data written at older versions.
+    pub fn register(
+        &mut self,
+        id_str: &str,
+        key_codec_name: &str,
+        val_codec_name: &str,
+        schema: Schema,
+        res: PFutureHandle<Id>,
+    ) {
+        res.fill((|| {
+            self.refresh()?;
+            if self.meta.graveyard.iter().any(|r| r.name == id_str) {
+                return Err(Error::from(format!(
+                    "invalid registration: stream {} already destroyed",
+                    id_str
+                )));
+            }
+            let registration = self
+                .meta
+                .id_mapping
+                .iter()
+                .find(|s| s.name == id_str)
+                .ok_or_else(|| Error::UnknownRegistration(id_str.to_owned()))?;
+            if key_codec_name != registration.key_codec_name {
+                return Err(Error::from(format!(
+                    "invalid registration: key codec mismatch {} vs previous {}",
+                    key_codec_name, registration.key_codec_name
+                )));
+            }
+            if val_codec_name != registration.val_codec_name {
+                return Err(Error::from(format!(
+                    "invalid registration: val codec mismatch {} vs previous {}",
+                    val_codec_name, registration.val_codec_name
+                )));
+            }
+            let id = registration.id;
+            let arrangement = self.arrangement(id)?;
+            StreamSchema::new(arrangement.schema_version).register(schema.clone())?;
+            self.schemas.insert(id, schema);
+            Ok(id)
+        })());
+    }
+
+    /// Returns a [Description] of the stream identified by `id_str`.
+    pub fn get_description(&mut self, id_str: &str, res: PFutureHandle<Description<u64>>) {
+        res.fill((|| {
+            self.refresh()?;
+            let registration = self
+                .meta
+                .id_mapping
+                .iter()
+                .find(|s| s.name == id_str)
+                .ok_or_else(|| Error::UnknownRegistration(id_str.to_owned()))?;
+            let arrangement = self.arrangement(registration.id)?;
+            Ok(Description::new(
+                Antichain::from_elem(u64::minimum()),
+                arrangement.seal.clone(),
+                arrangement.since.clone(),
+            ))
+        })());
+    }
+
+    /// Returns a [ArrangementSnapshot] of everything sealed for the given id.
+    pub fn snapshot(&mut self, id: Id, res: PFutureHandle<ArrangementSnapshot>) {
+        res.fill((|| {
+            self.refresh()?;
+            self.with_retries(|follower| follower.try_snapshot(id))
+        })());
+    }
+
+    /// Returns a [ArrangementSnapshot] of everything sealed for the given id
+    /// and registers a listener for everything sealed after it.
+    ///
+    /// The listener is disconnected if the writer's since frontier passes the
+    /// frontier it was last sent. See [Follower] for details.
+    pub fn listen(
+        &mut self,
+        id: Id,
+        sender: crossbeam_channel::Sender<ListenEvent>,
+        res: PFutureHandle<ArrangementSnapshot>,
+    ) {
+        res.fill((|| {
+            self.refresh()?;
+            let snap = self.with_retries(|follower| follower.try_snapshot(id))?;
+            self.listeners
+                .entry(id)
+                .or_default()
+                .push(FollowerListener {
+                    sender,
+                    upper: snap.get_seal(),
+                });
+            Ok(snap)
+        })());
+    }
+
+    /// Re-reads the meta and sends listeners anything that was sealed since
+    /// they were last updated.
+    pub fn poll(&mut self) -> Result<(), Error> {
+        if !self.refresh()? {
+            return Ok(());
+        }
+
+        let mut listeners = mem::take(&mut self.listeners);
+        for (id, id_listeners) in listeners.iter_mut() {
+            let mut retained = Vec::with_capacity(id_listeners.len());
+            for mut listener in id_listeners.drain(..) {
+                let events =
+                    self.with_retries(|follower| follower.sealed_since(*id, &listener.upper));
+                match events {
+                    Ok(None) => retained.push(listener),
+                    Ok(Some((records, seal))) => {
+                        let mut connected = true;
+                        if !records.is_empty() {
+                            connected &=
+                                listener.sender.send(ListenEvent::Records(records)).is_ok();
+                        }
+                        for ts in seal.elements().iter() {
+                            connected &= listener.sender.send(ListenEvent::Sealed(*ts)).is_ok();
+                        }
+                        listener.upper = seal;
+                        if connected {
+                            retained.push(listener);
+                        }
+                    }
+                    Err(err) => {
+                        // Dropping the sender disconnects the listener.
+                        self.metrics.follower_listener_disconnect_count.inc();
+                        tracing::error!(
+                            "disconnecting persist follower listener for {:?}: {}",
+                            id,
+                            err
+                        );
+                    }
+                }
+            }
+            *id_listeners = retained;
+        }
+        listeners.retain(|_, id_listeners| !id_listeners.is_empty());
+        self.listeners = listeners;
+        Ok(())
+    }
+
+    /// Re-reads the meta, returning whether it changed.
+    fn refresh(&mut self) -> Result<bool, Error> {
+        let meta = self.blob.get_meta()?.unwrap_or_default();
+        if meta == self.meta {
+            return Ok(false);
+        }
+        self.meta = meta;
+
+        // Batches are immutable, so anything we have cached is still correct,
+        // but release the ones that the writer no longer references so the
+        // cache doesn't grow without bound.
+        let keys = self
+            .meta
+            .arrangements
+            .iter()
+            .flat_map(|x| {
+                x.unsealed_batches
+                    .iter()
+                    .map(|b| b.key.as_str())
+                    .chain(x.trace_batches.iter().map(|b| b.key.as_str()))
+            })
+            .collect::<HashSet<_>>();
+        self.blob.retain_cached(&keys)?;
+        Ok(true)
+    }
+
+    /// Runs a read, retrying it if it fails and the meta has changed in the
+    /// meantime.
+    ///
+    /// A batch referenced by the meta we read may have been deleted by the
+    /// writer's compaction before we fetched it. In that case, the writer has
+    /// also written a new meta that no longer references it.
+    fn with_retries<T, F>(&mut self, mut read: F) -> Result<T, Error>
+    where
+        F: FnMut(&Self) -> Result<T, Error>,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match read(self) {
+                Ok(ret) => return Ok(ret),
+                Err(err) if attempts < MAX_READ_ATTEMPTS && self.refresh()? => {
+                    self.metrics.follower_read_retry_count.inc();
+                    tracing::debug!("retrying persist follower read after: {}", err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn arrangement(&self, id: Id) -> Result<&ArrangementMeta, Error> {
+        self.meta
+            .arrangements
+            .iter()
+            .find(|x| x.id == id)
+            .ok_or_else(|| Error::from(format!("never registered: {:?}", id)))
+    }
+
+    /// Returns the [StreamSchema] used to migrate the data for the given id.
+    fn schema(&self, id: Id, meta: &ArrangementMeta) -> Result<StreamSchema, Error> {
+        let mut schema = StreamSchema::new(meta.schema_version);
+        if let Some(registered) = self.schemas.get(&id) {
+            schema.register(registered.clone())?;
+        }
+        Ok(schema)
+    }
+
+    fn try_snapshot(&self, id: Id) -> Result<ArrangementSnapshot, Error> {
+        let mut arrangement = Arrangement::new(self.arrangement(id)?.clone());
+        if let Some(schema) = self.schemas.get(&id) {
+            arrangement.register_schema(schema.clone())?;
+        }
+        let seal = arrangement.get_seal();
+        let trace = arrangement.trace_snapshot(&self.blob);
+        let unsealed =
+            arrangement.unsealed_snapshot(trace.ts_upper.clone(), seal.clone(), &self.blob)?;
+
+        // The snapshot fetches its batches lazily, so fetch them all now to
+        // find out if any of them have already been deleted. This populates
+        // the cache, so the snapshot won't fetch them again.
+        let meta = self.arrangement(id)?;
+        let trace_fetches = meta
+            .trace_batches
+            .iter()
+            .map(|b| self.blob.get_trace_batch_async(&b.key))
+            .collect::<Vec<_>>();
+        let unsealed_fetches = meta
+            .unsealed_batches
+            .iter()
+            .filter(|b| unsealed.ts_lower.less_equal(&b.ts_upper) && !seal.less_equal(&b.ts_lower))
+            .map(|b| self.blob.get_unsealed_batch_async(&b.key))
+            .collect::<Vec<_>>();
+        for fetch in trace_fetches {
+            fetch.recv()?;
+        }
+        for fetch in unsealed_fetches {
+            fetch.recv()?;
+        }
+
+        Ok(ArrangementSnapshot(unsealed, trace, self.meta.seqno, seal))
+    }
+
+    /// Returns every record in `[upper, seal)` for the given id along with the
+    /// seal frontier, or None if the seal hasn't advanced past `upper`.
+    #[allow(clippy::type_complexity)]
+    fn sealed_since(
+        &self,
+        id: Id,
+        upper: &Antichain<u64>,
+    ) -> Result<Option<(Vec<((Vec<u8>, Vec<u8>), u64, isize)>, Antichain<u64>)>, Error> {
+        let meta = self.arrangement(id)?;
+        let seal = &meta.seal;
+        if !PartialOrder::less_than(upper, seal) {
+            return Ok(None);
+        }
+        if !PartialOrder::less_equal(&meta.since, upper) {
+            return Err(Error::from(format!(
+                "compaction to since {:?} removed detail needed to update a listener at {:?}",
+                meta.since, upper
+            )));
+        }
+
+        // The trace holds everything before its upper and unsealed holds
+        // everything after it. Because the since is not past `upper`, any
+        // trace batch that extends past `upper` has not been merged, so its
+        // records are still at the times they were written.
+        let trace_ts_upper = meta.trace_batches.last().map_or_else(
+            || Antichain::from_elem(u64::minimum()),
+            |b| b.desc.upper().clone(),
+        );
+        let in_range = |ts: u64| upper.less_equal(&ts) && !seal.less_equal(&ts);
+        let schema = self.schema(id, meta)?;
+
+        let mut records = Vec::new();
+        for batch in meta.trace_batches.iter() {
+            if PartialOrder::less_equal(batch.desc.upper(), upper) {
+                continue;
+            }
+            let schema_version = batch.schema_version;
+            let batch = self.blob.get_trace_batch_async(&batch.key).recv()?;
+            for ((k, v), ts, diff) in batch.updates.iter() {
+                if in_range(*ts) {
+                    let kv = schema.migrate(schema_version, k.clone(), v.clone())?;
+                    records.push((kv, *ts, *diff));
+                }
+            }
+        }
+        for batch in meta.unsealed_batches.iter() {
+            if !upper.less_equal(&batch.ts_upper)
+                || !trace_ts_upper.less_equal(&batch.ts_upper)
+                || seal.less_equal(&batch.ts_lower)
+            {
+                continue;
+            }
+            let schema_version = batch.schema_version;
+            let batch = self.blob.get_unsealed_batch_async(&batch.key).recv()?;
+            for updates in batch.updates.iter() {
+                for ((k, v), ts, diff) in updates.iter() {
+                    if in_range(ts) && trace_ts_upper.less_equal(&ts) {
+                        let kv = schema.migrate(schema_version, k.to_vec(), v.to_vec())?;
+                        records.push((kv, ts, diff));
+                    }
+                }
+            }
+        }
+        Ok(Some((records, seal.clone())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use ore::metrics::MetricsRegistry;
+    use tokio::runtime::Runtime as AsyncRuntime;
+
+    use crate::indexed::background::Maintainer;
+    use crate::indexed::columnar::{ColumnarRecords, ColumnarRecordsVec};
+    use crate::indexed::{Indexed, SnapshotExt};
+    use crate::mem::{MemBlob, MemBlobRead, MemLog, MemMultiRegistry};
+    use crate::pfuture::PFuture;
+    use crate::storage::LockInfo;
+
+    use super::*;
+
+    type Hook = Box<dyn FnOnce() -> Result<(), Error> + Send>;
+
+    /// A [BlobRead] that runs a hook right after the next read of the meta, so
+    /// the follower's view of the meta is stale before it fetches any batches.
+    struct AfterMetaBlob {
+        blob: MemBlobRead,
+        hook: Arc<Mutex<Option<Hook>>>,
+    }
+
+    #[async_trait]
+    impl BlobRead for AfterMetaBlob {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+            let ret = self.blob.get(key).await?;
+            if key == "META" {
+                let hook = self.hook.lock()?.take();
+                if let Some(hook) = hook {
+                    hook()?;
+                }
+            }
+            Ok(ret)
+        }
+
+        async fn list_keys(&self) -> Result<Vec<String>, Error> {
+            self.blob.list_keys().await
+        }
+
+        async fn close(&mut self) -> Result<bool, Error> {
+            self.blob.close().await
+        }
+    }
+
+    fn block_on<T, F: FnOnce(PFutureHandle<T>)>(f: F) -> Result<T, Error> {
+        let (tx, rx) = PFuture::new();
+        f(tx);
+        rx.recv()
+    }
+
+    fn block_on_step<T, F: FnOnce(&mut Indexed<MemLog, MemBlob>, PFutureHandle<T>)>(
+        i: &mut Indexed<MemLog, MemBlob>,
+        f: F,
+    ) -> Result<T, Error> {
+        let (tx, rx) = PFuture::new();
+        f(i, tx);
+        i.step()?;
+        rx.recv()
+    }
+
+    fn write_req_payload(
+        id: Id,
+        updates: &[((Vec<u8>, Vec<u8>), u64, isize)],
+    ) -> Vec<(Id, ColumnarRecords)> {
+        updates
+            .iter()
+            .collect::<ColumnarRecordsVec>()
+            .into_inner()
+            .into_iter()
+            .map(|x| (id, x))
+            .collect()
+    }
+
+    #[test]
+    fn batch_deleted_after_meta_read() -> Result<(), Error> {
+        let mut registry = MemMultiRegistry::new();
+        let lock_info = LockInfo::new_no_reentrance("writer".into());
+        let metrics = Arc::new(Metrics::register_with(&MetricsRegistry::new()));
+        let async_runtime = Arc::new(AsyncRuntime::new()?);
+        let blob = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            async_runtime.clone(),
+            registry.blob("path", lock_info.clone())?,
+        );
+        let maintainer = Maintainer::new(blob.clone(), async_runtime.clone());
+        let mut writer = Indexed::new(registry.log("path", lock_info)?, blob, maintainer, metrics)?;
+
+        // Write two trace batches.
+        let id = block_on_step(&mut writer, |i, res| {
+            i.register("0", "", "", Schema::new(), res)
+        })?;
+        for ts in 0..2 {
+            let updates = vec![((format!("k{}", ts).into(), vec![]), ts, 1)];
+            block_on_step(&mut writer, |i, res| {
+                i.write(write_req_payload(id, &updates), res)
+            })?;
+            block_on_step(&mut writer, |i, res| i.seal(vec![id], ts + 1, res))?;
+        }
+
+        let hook = Arc::new(Mutex::new(None));
+        let blob = AfterMetaBlob {
+            blob: registry.blob_read("path"),
+            hook: Arc::clone(&hook),
+        };
+        let metrics = Arc::new(Metrics::register_with(&MetricsRegistry::new()));
+        let blob = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            async_runtime,
+            blob,
+        );
+        let mut follower = Follower::new(blob, metrics.clone())?;
+        let follower_id = block_on(|res| follower.register("0", "", "", Schema::new(), res))?;
+        assert_eq!(follower_id, id);
+        assert_eq!(follower.arrangement(id)?.trace_batches.len(), 2);
+
+        // Right after the follower reads the meta for its snapshot, the writer
+        // compacts the two trace batches into one and deletes them. The
+        // follower's first attempt fails to fetch them and is retried with the
+        // writer's new meta.
+        *hook.lock()? = Some(Box::new(move || {
+            block_on_step(&mut writer, |i, res| {
+                i.allow_compaction(vec![(id, Antichain::from_elem(2))], res)
+            })?;
+            writer.step()
+        }) as Hook);
+        let snap = block_on(|res| follower.snapshot(id, res))?;
+        assert!(hook.lock()?.is_none());
+        assert_eq!(metrics.follower_read_retry_count.get(), 1);
+        assert_eq!(follower.arrangement(id)?.trace_batches.len(), 1);
+        let mut updates = snap
+            .read_to_end()?
+            .into_iter()
+            .map(|((k, _), _, diff)| (k, diff))
+            .collect::<Vec<_>>();
+        updates.sort();
+        assert_eq!(updates, vec![(b"k0".to_vec(), 1), (b"k1".to_vec(), 1)]);
+
+        Ok(())
+    }
+}
@@ -10,8 +10,11 @@
 //! Persistence related monitoring metrics.
 
 use ore::metric;
+use ore::metrics::raw::UIntCounterVec;
 use ore::metrics::{Counter, MetricsRegistry, ThirdPartyMetric, UIntCounter, UIntGauge};
 
+use crate::indexed::encoding::Id;
+
 /// Persistence related monitoring metrics for blob storage.
 #[derive(Clone, Debug)]
 pub struct BlobMetricsByType {
@@ -84,6 +87,10 @@ pub struct Metrics {
     pub(crate) compaction_count: ThirdPartyMetric<UIntCounter>,
     pub(crate) compaction_seconds: ThirdPartyMetric<Counter>,
     pub(crate) compaction_write_bytes: ThirdPartyMetric<UIntCounter>,
+    pub(crate) compaction_throttled_count: ThirdPartyMetric<UIntCounter>,
+
+    pub(crate) follower_read_retry_count: ThirdPartyMetric<UIntCounter>,
+    pub(crate) follower_listener_disconnect_count: ThirdPartyMetric<UIntCounter>,
 
     // TODO: Tag cmd_process_count with cmd type and remove this?
     pub(crate) cmd_write_count: ThirdPartyMetric<UIntCounter>,
@@ -96,11 +103,20 @@ pub struct Metrics {
     pub(crate) blob_write_error_quota_count: ThirdPartyMetric<UIntCounter>,
     pub(crate) blob_write_error_other_count: ThirdPartyMetric<UIntCounter>,
 
-    // TODO: pub(crate) blob_read_cache_bytes: ThirdPartyMetric<UIntGauge>,
-    // TODO: pub(crate) blob_read_cache_entry_count: ThirdPartyMetric<UIntGauge>,
+    pub(crate) blob_read_cache_bytes: ThirdPartyMetric<UIntGauge>,
+    pub(crate) blob_read_cache_entry_count: ThirdPartyMetric<UIntGauge>,
     pub(crate) blob_read_cache_hit_count: ThirdPartyMetric<UIntCounter>,
     pub(crate) blob_read_cache_miss_count: ThirdPartyMetric<UIntCounter>,
+    pub(crate) blob_read_cache_eviction_count: ThirdPartyMetric<UIntCounter>,
     pub(crate) blob_read_cache_fetch_bytes: ThirdPartyMetric<UIntCounter>,
+    pub(crate) blob_read_cache_disk_bytes: ThirdPartyMetric<UIntGauge>,
+    pub(crate) blob_read_cache_disk_entry_count: ThirdPartyMetric<UIntGauge>,
+    pub(crate) blob_read_cache_disk_hit_count: ThirdPartyMetric<UIntCounter>,
+    pub(crate) blob_read_cache_disk_eviction_count: ThirdPartyMetric<UIntCounter>,
+    pub(crate) blob_read_cache_disk_error_count: ThirdPartyMetric<UIntCounter>,
+
+    pub(crate) stream_ingest_bytes: ThirdPartyMetric<UIntCounterVec>,
+    pub(crate) stream_write_bytes: ThirdPartyMetric<UIntCounterVec>,
     // TODO: pub(crate) blob_read_error_count: ThirdPartyMetric<UIntCounter>,
 }
 
@@ -168,6 +184,18 @@ impl Metrics {
                 name: "mz_persist_compaction_bytes",
                 help: "bytes written compacting unsealed and trace",
             )),
+            compaction_throttled_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_compaction_throttled_count",
+                help: "count of trace compactions delayed by the compaction rate limit",
+            )),
+            follower_read_retry_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_follower_read_retry_count",
+                help: "count of follower reads retried because the writer deleted a batch they needed",
+            )),
+            follower_listener_disconnect_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_follower_listener_disconnect_count",
+                help: "count of follower listeners disconnected because they could no longer be kept up to date",
+            )),
             cmd_write_count: registry.register_third_party_visible(metric!(
                 name: "mz_persist_cmd_write_count",
                 help: "count of write commands run",
@@ -191,6 +219,14 @@ impl Metrics {
                 name: "mz_persist_blob_write_error_other_count",
                 help: "count of blob storage writes failing for other reasons",
             )),
+            blob_read_cache_bytes: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_bytes",
+                help: "approximate size of the decoded batches held in memory by the blob cache",
+            )),
+            blob_read_cache_entry_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_entry_count",
+                help: "count of decoded batches held in memory by the blob cache",
+            )),
             blob_read_cache_hit_count: registry.register_third_party_visible(metric!(
                 name: "mz_persist_blob_read_cache_hit_count",
                 help: "count of blob reads served by cached data",
@@ -199,12 +235,81 @@ impl Metrics {
                 name: "mz_persist_blob_read_cache_miss_count",
                 help: "count of blob reads that had to be fetched",
             )),
+            blob_read_cache_eviction_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_eviction_count",
+                help: "count of decoded batches evicted from memory to stay within the cache limit",
+            )),
             blob_read_cache_fetch_bytes: registry.register_third_party_visible(metric!(
                 name: "mz_persist_blob_read_cache_fetch_bytes",
                 help: "total size of blob reads that had to be fetched",
             )),
+            blob_read_cache_disk_bytes: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_disk_bytes",
+                help: "total size of the encoded batches held on local disk by the blob cache",
+            )),
+            blob_read_cache_disk_entry_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_disk_entry_count",
+                help: "count of encoded batches held on local disk by the blob cache",
+            )),
+            blob_read_cache_disk_hit_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_disk_hit_count",
+                help: "count of blob reads missing in memory that were served from local disk",
+            )),
+            blob_read_cache_disk_eviction_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_disk_eviction_count",
+                help: "count of encoded batches evicted from local disk to stay within the cache limit",
+            )),
+            blob_read_cache_disk_error_count: registry.register_third_party_visible(metric!(
+                name: "mz_persist_blob_read_cache_disk_error_count",
+                help: "count of local disk cache reads and writes that failed and were skipped",
+            )),
+            stream_ingest_bytes: registry.register_third_party_visible(metric!(
+                name: "mz_persist_stream_ingest_bytes",
+                help: "total size of newly written data, by stream",
+                var_labels: ["stream_id"],
+            )),
+            stream_write_bytes: registry.register_third_party_visible(metric!(
+                name: "mz_persist_stream_write_bytes",
+                help: "total size written to blob storage including compaction, by stream",
+                var_labels: ["stream_id"],
+            )),
+        }
+    }
+}
+
+/// Persistence related monitoring metrics for a single stream.
+///
+/// The write amplification of a stream is the ratio of its write bytes to its
+/// ingest bytes.
+#[derive(Debug)]
+pub(crate) struct StreamMetrics {
+    ingest_bytes: UIntCounter,
+    write_bytes: UIntCounter,
+}
+
+impl StreamMetrics {
+    pub(crate) fn new(metrics: &Metrics, id: Id) -> Self {
+        let id = id.0.to_string();
+        StreamMetrics {
+            ingest_bytes: metrics
+                .stream_ingest_bytes
+                .third_party_metric_with_label_values(&[id.as_str()]),
+            write_bytes: metrics
+                .stream_write_bytes
+                .third_party_metric_with_label_values(&[id.as_str()]),
         }
     }
+
+    /// Records newly written data.
+    pub(crate) fn inc_ingest_bytes(&self, bytes: u64) {
+        self.ingest_bytes.inc_by(bytes);
+        self.write_bytes.inc_by(bytes);
+    }
+
+    /// Records data rewritten by moving it into the trace or compacting it.
+    pub(crate) fn inc_rewrite_bytes(&self, bytes: u64) {
+        self.write_bytes.inc_by(bytes);
+    }
 }
 
 impl Default for Metrics {
@@ -16,10 +16,12 @@ pub mod background;
 pub mod cache;
 pub mod columnar;
 pub mod encoding;
+pub mod follower;
 pub mod metrics;
 
 use std::any::TypeId;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
 use std::num::NonZeroUsize;
 use std::ops::Range;
 use std::sync::Arc;
@@ -39,9 +41,10 @@ use crate::indexed::encoding::{
     ArrangementMeta, BlobMeta, BlobUnsealedBatch, Id, StreamRegistration, TraceBatchMeta,
     UnsealedBatchMeta,
 };
-use crate::indexed::metrics::Metrics;
+use crate::indexed::metrics::{Metrics, StreamMetrics};
 use crate::mem::MemBlob;
 use crate::pfuture::PFutureHandle;
+use crate::schema::Schema;
 use crate::storage::{Blob, Log, SeqNo};
 use crate::unreliable::UnreliableBlob;
 
@@ -144,6 +147,7 @@ pub struct Indexed<L: Log, B: Blob> {
     maintainer: Maintainer<B>,
     listeners: HashMap<Id, Vec<crossbeam_channel::Sender<ListenEvent>>>,
     metrics: Arc<Metrics>,
+    stream_metrics: HashMap<Id, StreamMetrics>,
     state: AppliedState,
     pending: Option<Pending>,
 }
@@ -198,6 +202,7 @@ impl<L: Log, B: Blob> Indexed<L, B> {
             maintainer,
             listeners: HashMap::new(),
             metrics,
+            stream_metrics: HashMap::new(),
             state,
             pending: None,
         };
@@ -222,6 +227,26 @@ impl AppliedState {
         }
     }
 
+    /// Resets this state back to the given previous version of meta (which is
+    /// expected to match what's in durable storage).
+    ///
+    /// Registered schemas only exist in memory, so they're carried over for
+    /// any arrangement whose reverted schema version still matches them.
+    fn revert(&mut self, meta: BlobMeta) {
+        let mut prev = mem::take(&mut self.arrangements);
+        *self = AppliedState::new(meta);
+        for (id, arrangement) in self.arrangements.iter_mut() {
+            let schema = prev
+                .remove(id)
+                .and_then(|x| x.registered_schema().cloned())
+                .filter(|x| x.version() == arrangement.schema_version());
+            if let Some(schema) = schema {
+                // The versions match, so this can't fail.
+                let _ = arrangement.register_schema(schema);
+            }
+        }
+    }
+
     fn assign_seqno(&mut self) -> SeqNo {
         let seqno = self.highest_assigned_seqno + 1;
         self.highest_assigned_seqno = seqno;
@@ -260,7 +285,7 @@ impl<L: Log, B: Blob> Indexed<L, B> {
         if let Err(e) = self.blob.set_meta(&new_meta) {
             // We were unable to durably commit the in-memory state. Revert back to the
             // previous version of meta.
-            self.state = AppliedState::new(prev_meta);
+            self.state.revert(prev_meta);
             return Err(e);
         } else {
             self.state.saved_seqno = new_meta.seqno;
@@ -322,7 +347,7 @@ impl<L: Log, B: Blob> Indexed<L, B> {
         let work_ret = match work_fn(&mut self.state, &mut self.blob, &mut self.maintainer) {
             Ok(work_ret) => work_ret,
             Err(err) => {
-                self.state = AppliedState::new(meta_before);
+                self.state.revert(meta_before);
                 return Err(err);
             }
         };
@@ -370,18 +395,22 @@ impl<L: Log, B: Blob> Indexed<L, B> {
     /// Creates, if necessary, a new unsealed and trace with the given external
     /// stream name, returning the corresponding internal stream id.
     ///
+    /// The given [Schema] is used to read data written at older schema versions
+    /// and, if newer than the stream's current schema version, advances it.
+    ///
     /// This method is idempotent: ids may be registered multiple times.
     pub fn register(
         &mut self,
         id_str: &str,
         key_codec_name: &str,
         val_codec_name: &str,
+        schema: Schema,
         res: PFutureHandle<Id>,
     ) {
         res.fill((|| {
             self.drain_pending()?;
             self.apply_unbatched_cmd(|state, _, _| {
-                state.do_register(id_str, key_codec_name, val_codec_name)
+                state.do_register(id_str, key_codec_name, val_codec_name, schema)
             })
         })());
     }
@@ -393,6 +422,7 @@ impl AppliedState {
         id_str: &str,
         key_codec_name: &str,
         val_codec_name: &str,
+        schema: Schema,
     ) -> Result<Id, Error> {
         if self.graveyard.iter().any(|r| r.name == id_str) {
             return Err(Error::from(format!(
@@ -437,6 +467,11 @@ impl AppliedState {
                 id
             }
         };
+        let arrangement = self
+            .arrangements
+            .get_mut(&id)
+            .ok_or_else(|| Error::from(format!("never registered: {:?}", id)))?;
+        arrangement.register_schema(schema)?;
         Ok(id)
     }
 }
@@ -500,6 +535,11 @@ impl<L: Log, B: Blob> Indexed<L, B> {
     /// Validates that the meta we might roll back to must be equal to the
     /// durably persisted meta.
     fn validate_matches_storage(blob: &BlobCache<B>, meta: &BlobMeta) -> Result<(), Error> {
+        // Once another process has taken over writing to storage, it's
+        // expected to have moved on without us.
+        if let Ok(true) = blob.is_fenced() {
+            return Ok(());
+        }
         // We can only check this invariant when blob is available, as otherwise
         // we fail to make progress on draining pending requests and writes
         // during nemesis tests.
@@ -530,6 +570,11 @@ impl<L: Log, B: Blob> Indexed<L, B> {
     /// Validates that all of the referenced blob keys in all unsealeds and
     /// traces actually exist in blob's key-val map.
     fn validate_referenced_keys_exist(&self) -> Result<(), Error> {
+        // Same as validate_matches_storage, the process that took over may
+        // have deleted batches that we still reference.
+        if let Ok(true) = self.blob.is_fenced() {
+            return Ok(());
+        }
         match self.blob.list_keys() {
             // Same as validate_matches_storage, we can only check this
             // invariant if blob is available.
@@ -601,22 +646,26 @@ impl<L: Log, B: Blob> Indexed<L, B> {
         let ret = {
             // TODO: The following error handling took a while to debug, see if
             // we can make this more obvious.
-            if let Err(err) = self
+            match self
                 .state
                 .drain_pending_writes(updates_by_id, &mut self.blob)
             {
-                self.state = AppliedState::new(meta_before);
-                Err(err)
-            } else {
-                self.try_set_meta(meta_before)
+                Ok(written_bytes) => self.try_set_meta(meta_before).map(|_| written_bytes),
+                Err(err) => {
+                    self.state.revert(meta_before);
+                    Err(err)
+                }
             }
         };
 
         let ret = match ret {
-            Ok(()) => {
+            Ok(written_bytes) => {
                 let mut responses = pending.responses;
                 responses.drain(..).for_each(|r| r.fill());
                 self.update_listeners(updates_for_listeners, seals_for_listeners);
+                for (id, bytes) in written_bytes {
+                    self.stream_metrics(id).inc_ingest_bytes(bytes);
+                }
                 Ok(())
             }
             Err(e) => {
@@ -639,25 +688,54 @@ impl<L: Log, B: Blob> Indexed<L, B> {
 impl AppliedState {
     fn compact_inner<B: Blob>(
         &mut self,
-        maintainer: &Maintainer<B>,
-    ) -> Result<(u64, Vec<UnsealedBatchMeta>, Vec<TraceBatchMeta>), Error> {
-        let mut total_written_bytes = 0;
-        let mut deleted_unsealed_batches = vec![];
-        let mut deleted_trace_batches = vec![];
-        for arrangement in self.arrangements.values_mut() {
-            deleted_unsealed_batches.extend(arrangement.unsealed_evict());
-            let (written_bytes, deleted_batches) = arrangement.trace_step(maintainer)?;
-            total_written_bytes += written_bytes;
-            deleted_trace_batches.extend(deleted_batches);
+        maintainer: &mut Maintainer<B>,
+    ) -> Result<CompactionStep, Error> {
+        let mut step = CompactionStep::default();
+
+        // Start a merge for each trace that has one to do, within the limits
+        // configured on the maintainer...
+        let mut in_flight = vec![];
+        for (id, arrangement) in self.arrangements.iter_mut() {
+            step.deleted_unsealed_batches
+                .extend(arrangement.unsealed_evict());
+            if in_flight.len() >= maintainer.max_concurrent() {
+                continue;
+            }
+            let req = match arrangement.trace_next_compaction(maintainer.policy()) {
+                Some(req) => req,
+                None => continue,
+            };
+            if !maintainer.try_start(&req, Instant::now()) {
+                step.throttled_count += 1;
+                continue;
+            }
+            in_flight.push((*id, maintainer.compact_trace(req)));
+        }
+
+        // ...and then wait for them all to finish.
+        for (id, res) in in_flight {
+            let res = res.recv()?;
+            let arrangement = self
+                .arrangements
+                .get_mut(&id)
+                .ok_or_else(|| Error::from(format!("never registered: {:?}", id)))?;
+            *step.written_bytes_by_id.entry(id).or_default() += res.merged.size_bytes;
+            step.deleted_trace_batches
+                .extend(arrangement.trace_apply_compaction(res)?);
         }
-        Ok((
-            total_written_bytes,
-            deleted_unsealed_batches,
-            deleted_trace_batches,
-        ))
+        Ok(step)
     }
 }
 
+/// The work done by one call to [AppliedState::compact_inner].
+#[derive(Debug, Default)]
+struct CompactionStep {
+    written_bytes_by_id: HashMap<Id, u64>,
+    throttled_count: u64,
+    deleted_unsealed_batches: Vec<UnsealedBatchMeta>,
+    deleted_trace_batches: Vec<TraceBatchMeta>,
+}
+
 impl<L: Log, B: Blob> Indexed<L, B> {
     /// Compact all traces and truncate all unsealeds, if possible.
     ///
@@ -683,13 +761,17 @@ impl<L: Log, B: Blob> Indexed<L, B> {
             .compaction_seconds
             .inc_by(compaction_start.elapsed().as_secs_f64());
 
-        let (total_written_bytes, deleted_unsealed_batches, deleted_trace_batches) = ret?;
-        if !deleted_unsealed_batches.is_empty() || !deleted_trace_batches.is_empty() {
+        let step = ret?;
+        if !step.deleted_unsealed_batches.is_empty() || !step.deleted_trace_batches.is_empty() {
             self.metrics.compaction_count.inc();
         }
         self.metrics
-            .compaction_write_bytes
-            .inc_by(total_written_bytes);
+            .compaction_throttled_count
+            .inc_by(step.throttled_count);
+        for (id, bytes) in step.written_bytes_by_id {
+            self.metrics.compaction_write_bytes.inc_by(bytes);
+            self.stream_metrics(id).inc_rewrite_bytes(bytes);
+        }
 
         // After we've committed our logical deletions to durable storage, we can
         // physically delete the data.
@@ -701,11 +783,11 @@ impl<L: Log, B: Blob> Indexed<L, B> {
         // method on blob and have a periodic cleanup task that attempts to find
         // and delete unused blobs. We could also use the list method to verify
         // that all referenced blobs exist.
-        for batch in deleted_unsealed_batches {
+        for batch in step.deleted_unsealed_batches {
             self.blob.delete_unsealed_batch(&batch)?;
         }
 
-        for batch in deleted_trace_batches {
+        for batch in step.deleted_trace_batches {
             self.blob.delete_trace_batch(&batch)?;
         }
 
@@ -720,10 +802,22 @@ impl<L: Log, B: Blob> Indexed<L, B> {
     /// but it's exposed this way so we can write deterministic tests.
     pub fn step(&mut self) -> Result<(), Error> {
         self.drain_pending()?;
-        self.apply_unbatched_cmd(|state, blob, _| state.drain_unsealed(blob))?;
+        let written_bytes =
+            self.apply_unbatched_cmd(|state, blob, _| state.drain_unsealed(blob))?;
+        for (id, bytes) in written_bytes {
+            self.stream_metrics(id).inc_rewrite_bytes(bytes);
+        }
         self.compact()?;
         Ok(())
     }
+
+    /// Returns the metrics for the stream with the given id.
+    fn stream_metrics(&mut self, id: Id) -> &StreamMetrics {
+        let metrics = &self.metrics;
+        self.stream_metrics
+            .entry(id)
+            .or_insert_with(|| StreamMetrics::new(metrics, id))
+    }
 }
 
 impl AppliedState {
@@ -767,13 +861,16 @@ impl AppliedState {
     ///
     /// The caller is responsible for commiting metadata after this succeeds, and
     /// restoring metadata if this fails.
+    ///
+    /// Returns the number of bytes written for each stream.
     fn drain_pending_writes<B: Blob>(
         &mut self,
         mut writes_by_id: HashMap<Id, Vec<ColumnarRecords>>,
         blob: &mut BlobCache<B>,
-    ) -> Result<(), Error> {
+    ) -> Result<HashMap<Id, u64>, Error> {
+        let mut written_bytes = HashMap::new();
         if writes_by_id.is_empty() {
-            return Ok(());
+            return Ok(written_bytes);
         }
         // This range represents the [lower, upper) of sequence numbers assigned
         // to this write.
@@ -796,10 +893,11 @@ impl AppliedState {
             let mut desc = desc.clone();
             desc.start = seqno_upper;
 
-            self.drain_pending_writes_inner(id, writes, &desc, blob)?;
+            let bytes = self.drain_pending_writes_inner(id, writes, &desc, blob)?;
+            written_bytes.insert(id, bytes);
         }
 
-        Ok(())
+        Ok(written_bytes)
     }
 }
 
@@ -870,15 +968,17 @@ impl AppliedState {
     ///
     /// The caller is responsible for updating META after they've finished
     /// updating unsealeds.
+    ///
+    /// Returns the number of bytes written.
     fn drain_pending_writes_inner<B: Blob>(
         &mut self,
         id: Id,
         updates: Vec<ColumnarRecords>,
         desc: &Range<SeqNo>,
         blob: &mut BlobCache<B>,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         if updates.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
 
         // Sanity check the invariant that only non-empty writes get appended to
@@ -893,19 +993,26 @@ impl AppliedState {
             desc: desc.clone(),
             updates,
         };
-        self.append_unsealed(id, batch, blob)?;
-
-        Ok(())
+        self.append_unsealed(id, batch, blob)
     }
 
     /// Atomically moves all writes in unsealed not in advance of the trace's
     /// seal frontier into the trace and does any necessary resulting eviction
     /// work to remove unnecessary batches.
-    fn drain_unsealed<B: Blob>(&mut self, blob: &mut BlobCache<B>) -> Result<(), Error> {
-        for arrangement in self.arrangements.values_mut() {
-            arrangement.unsealed_drain(blob)?;
+    ///
+    /// Returns the number of bytes written for each stream.
+    fn drain_unsealed<B: Blob>(
+        &mut self,
+        blob: &mut BlobCache<B>,
+    ) -> Result<HashMap<Id, u64>, Error> {
+        let mut written_bytes = HashMap::new();
+        for (id, arrangement) in self.arrangements.iter_mut() {
+            let bytes = arrangement.unsealed_drain(blob)?;
+            if bytes > 0 {
+                written_bytes.insert(*id, bytes);
+            }
         }
-        Ok(())
+        Ok(written_bytes)
     }
 
     /// Returns the current "sealed" frontier for an id.
@@ -1049,12 +1156,14 @@ impl AppliedState {
     ///
     /// The caller is responsible for updating META after they've finished
     /// updating unsealeds.
+    ///
+    /// Returns the number of bytes written.
     fn append_unsealed<B: Blob>(
         &mut self,
         id: Id,
         batch: BlobUnsealedBatch,
         blob: &mut BlobCache<B>,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         let arrangement = self
             .arrangements
             .get_mut(&id)
@@ -1184,10 +1293,14 @@ impl<K: Ord, V: Ord, S: Snapshot<K, V> + Sized> SnapshotExt<K, V> for S {}
 
 #[cfg(test)]
 mod tests {
+    use ore::metrics::MetricsRegistry;
+    use tokio::runtime::Runtime as AsyncRuntime;
+
     use crate::error::Error;
+    use crate::indexed::background::CompactionConfig;
     use crate::indexed::columnar::ColumnarRecordsVec;
     use crate::indexed::SnapshotExt;
-    use crate::mem::MemRegistry;
+    use crate::mem::{MemLog, MemRegistry};
     use crate::pfuture::PFuture;
     use crate::unreliable::UnreliableHandle;
 
@@ -1230,7 +1343,7 @@ mod tests {
         ];
 
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
-        let id = block_on(|res| i.register("0", "()", "()", res))?;
+        let id = block_on(|res| i.register("0", "()", "()", Schema::new(), res))?;
 
         // Empty things are empty.
         let ArrangementSnapshot(unsealed, trace, seqno, seal_frontier) =
@@ -1322,7 +1435,7 @@ mod tests {
         ];
 
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
-        let id = block_on(|res| i.register("0", "", "", res))?;
+        let id = block_on(|res| i.register("0", "", "", Schema::new(), res))?;
 
         // Write the data and move it into the unsealed part of the index, which
         // orders it within each batch by time. It's not, so this will fire a
@@ -1353,7 +1466,7 @@ mod tests {
         ];
 
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
-        let id = block_on(|res| i.register("0", "", "", res))?;
+        let id = block_on(|res| i.register("0", "", "", Schema::new(), res))?;
 
         // Write the data and move it into the unsealed part of the index.
         block_on_drain(&mut i, |i, handle| {
@@ -1398,7 +1511,7 @@ mod tests {
         let updates: Vec<((Vec<u8>, Vec<u8>), _, _)> = vec![];
 
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
-        let id = block_on(|res| i.register("0", "", "", res))?;
+        let id = block_on(|res| i.register("0", "", "", Schema::new(), res))?;
 
         // Write the data and move it into the unsealed part of the index.
         assert_eq!(
@@ -1426,14 +1539,14 @@ mod tests {
         // This caused a violation of our invariants (which are checked in tests
         // and debug mode), so we just need the following to run without error
         // to verify the fix.
-        let s1 = block_on(|res| i.register("s1", "", "", res))?;
+        let s1 = block_on(|res| i.register("s1", "", "", Schema::new(), res))?;
         block_on_drain(&mut i, |i, handle| {
             i.write(
                 write_req_payload(s1, &[(("".into(), "".into()), 0, 1)]),
                 handle,
             )
         })?;
-        let s2 = block_on(|res| i.register("s2", "", "", res))?;
+        let s2 = block_on(|res| i.register("s2", "", "", Schema::new(), res))?;
         block_on_drain(&mut i, |i, handle| {
             i.write(
                 write_req_payload(s2, &[(("".into(), "".into()), 1, 1)]),
@@ -1458,7 +1571,7 @@ mod tests {
     fn test_destroy() -> Result<(), Error> {
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
 
-        let _ = block_on(|res| i.register("stream", "", "", res))?;
+        let _ = block_on(|res| i.register("stream", "", "", Schema::new(), res))?;
 
         // Normal case: destroy registered stream.
         assert_eq!(block_on(|res| i.destroy("stream", res)), Ok(true));
@@ -1476,7 +1589,7 @@ mod tests {
 
         // Creating a previously destroyed stream.
         assert_eq!(
-            block_on(|res| i.register("stream", "", "", res)),
+            block_on(|res| i.register("stream", "", "", Schema::new(), res)),
             Err(Error::from(
                 "invalid registration: stream stream already destroyed"
             ))
@@ -1489,14 +1602,14 @@ mod tests {
     fn codec_mismatch() -> Result<(), Error> {
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
 
-        let _ = block_on(|res| i.register("stream", "key", "val", res))?;
+        let _ = block_on(|res| i.register("stream", "key", "val", Schema::new(), res))?;
 
         // Normal case: registration uses same key and value codec.
-        let _ = block_on(|res| i.register("stream", "key", "val", res))?;
+        let _ = block_on(|res| i.register("stream", "key", "val", Schema::new(), res))?;
 
         // Different key codec
         assert_eq!(
-            block_on(|res| i.register("stream", "nope", "val", res)),
+            block_on(|res| i.register("stream", "nope", "val", Schema::new(), res)),
             Err(Error::from(
                 "invalid registration: key codec mismatch nope vs previous key"
             ))
@@ -1504,7 +1617,7 @@ mod tests {
 
         // Different val codec
         assert_eq!(
-            block_on(|res| i.register("stream", "key", "nope", res)),
+            block_on(|res| i.register("stream", "key", "nope", Schema::new(), res)),
             Err(Error::from(
                 "invalid registration: val codec mismatch nope vs previous val"
             ))
@@ -1525,7 +1638,7 @@ mod tests {
 
         let mut unreliable = UnreliableHandle::default();
         let mut i = MemRegistry::new().indexed_unreliable(unreliable.clone())?;
-        let id = block_on(|res| i.register("0", "", "", res))?;
+        let id = block_on(|res| i.register("0", "", "", Schema::new(), res))?;
 
         // Write the data out but don't close it.
         block_on_drain(&mut i, |i, handle| {
@@ -1539,10 +1652,89 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn schema_evolution() -> Result<(), Error> {
+        let mut registry = MemRegistry::new();
+
+        // Write some data at the initial schema version and move part of it
+        // into the trace.
+        let mut i = registry.indexed_no_reentrance()?;
+        let id = block_on(|res| i.register("0", "String", "String", Schema::new(), res))?;
+        let v0_updates = vec![
+            (("1".into(), "a".into()), 1, 1),
+            (("2".into(), "b".into()), 3, 1),
+        ];
+        block_on_drain(&mut i, |i, res| {
+            i.write(write_req_payload(id, &v0_updates), res)
+        })?;
+        block_on_drain(&mut i, |i, res| i.seal(vec![id], 2, res))?;
+        i.step()?;
+        i.close()?;
+
+        // Registering a schema with a migration advances the schema version.
+        // Data written before that is migrated when it's read, data written
+        // after isn't.
+        let schema = Schema::new().with_migration(|k: String, v: String| Ok((k, v.to_uppercase())));
+        let mut i = registry.indexed_no_reentrance()?;
+        let id = block_on(|res| i.register("0", "String", "String", schema.clone(), res))?;
+        let v1_updates = vec![(("3".into(), "c".into()), 4, 1)];
+        block_on_drain(&mut i, |i, res| {
+            i.write(write_req_payload(id, &v1_updates), res)
+        })?;
+        let mut actual = block_on(|res| i.snapshot(id, res))?.read_to_end()?;
+        actual.sort();
+        let expected = vec![
+            (("1".into(), "A".into()), 1, 1),
+            (("2".into(), "B".into()), 3, 1),
+            (("3".into(), "c".into()), 4, 1),
+        ];
+        assert_eq!(actual, expected);
+
+        // Registering an older schema is an error, but doesn't forget the
+        // schema that's already registered.
+        assert_eq!(
+            block_on(|res| i.register("0", "String", "String", Schema::new(), res)),
+            Err(Error::from(
+                "invalid registration: schema version 0 is older than persisted version 1"
+            ))
+        );
+        let mut actual = block_on(|res| i.snapshot(id, res))?.read_to_end()?;
+        actual.sort();
+        assert_eq!(actual, expected);
+
+        // Moving data into the trace and compacting it rewrites everything at
+        // the current schema version.
+        block_on_drain(&mut i, |i, res| i.seal(vec![id], 5, res))?;
+        i.step()?;
+        block_on_drain(&mut i, |i, res| {
+            i.allow_compaction(vec![(id, Antichain::from_elem(5))], res)
+        })?;
+        i.step()?;
+        let meta = i.state.serialize_meta();
+        let arrangement = &meta.arrangements[0];
+        assert_eq!(arrangement.schema_version, 1);
+        assert_eq!(arrangement.trace_batches.len(), 1);
+        assert_eq!(arrangement.trace_batches[0].schema_version, 1);
+        assert!(arrangement
+            .unsealed_batches
+            .iter()
+            .all(|b| b.schema_version == 1));
+        let mut actual = block_on(|res| i.snapshot(id, res))?.read_to_end()?;
+        actual.sort();
+        let expected = vec![
+            (("1".into(), "A".into()), 5, 1),
+            (("2".into(), "B".into()), 5, 1),
+            (("3".into(), "c".into()), 5, 1),
+        ];
+        assert_eq!(actual, expected);
+
+        Ok(())
+    }
+
     #[test]
     fn regression_8303_snapshot_advance_since() -> Result<(), Error> {
         let mut i = MemRegistry::new().indexed_no_reentrance()?;
-        let id = block_on(|res| i.register("0", "", "", res))?;
+        let id = block_on(|res| i.register("0", "", "", Schema::new(), res))?;
 
         // Introduce some data, seal it, and advance since. Intentionally don't
         // call step because might compact it and accidentally produce the right
@@ -1575,4 +1767,95 @@ mod tests {
 
         Ok(())
     }
+
+    fn indexed_with_compaction(
+        config: CompactionConfig,
+    ) -> Result<Indexed<MemLog, MemBlob>, Error> {
+        let registry = MemRegistry::new();
+        let metrics = Arc::new(Metrics::register_with(&MetricsRegistry::new()));
+        let async_runtime = Arc::new(AsyncRuntime::new()?);
+        let blob = BlobCache::new(
+            build_info::DUMMY_BUILD_INFO,
+            metrics.clone(),
+            async_runtime.clone(),
+            registry.blob_no_reentrance()?,
+        );
+        let maintainer = Maintainer::new(blob.clone(), async_runtime).with_config(config);
+        Indexed::new(registry.log_no_reentrance()?, blob, maintainer, metrics)
+    }
+
+    fn trace_batch_count<L: Log, B: Blob>(i: &Indexed<L, B>) -> usize {
+        i.state
+            .arrangements
+            .values()
+            .map(|x| x.meta().trace_batches.len())
+            .sum()
+    }
+
+    #[test]
+    fn compaction_limits() -> Result<(), Error> {
+        // Writes two trace batches to each of two streams and allows them to
+        // be compacted.
+        fn setup<L: Log, B: Blob>(i: &mut Indexed<L, B>) -> Result<Vec<Id>, Error> {
+            let ids = vec![
+                block_on(|res| i.register("0", "", "", Schema::new(), res))?,
+                block_on(|res| i.register("1", "", "", Schema::new(), res))?,
+            ];
+            for ts in 0..2 {
+                for id in ids.iter() {
+                    let updates = vec![(("k".into(), "v".into()), ts, 1)];
+                    block_on_drain(i, |i, res| i.write(write_req_payload(*id, &updates), res))?;
+                }
+                block_on_drain(i, |i, res| i.seal(ids.clone(), ts + 1, res))?;
+                i.step()?;
+            }
+            let since = ids
+                .iter()
+                .map(|id| (*id, Antichain::from_elem(2)))
+                .collect();
+            block_on_drain(i, |i, res| i.allow_compaction(since, res))?;
+            Ok(ids)
+        }
+
+        // Only one merge is started per step when concurrency is capped at one.
+        let mut i = indexed_with_compaction(CompactionConfig::default().with_max_concurrent(1))?;
+        let ids = setup(&mut i)?;
+        assert_eq!(trace_batch_count(&i), 4);
+        i.step()?;
+        assert_eq!(trace_batch_count(&i), 3);
+        i.step()?;
+        assert_eq!(trace_batch_count(&i), 2);
+
+        // Compaction counts towards each stream's write amplification.
+        for id in ids {
+            let id = id.0.to_string();
+            let ingest_bytes = i
+                .metrics
+                .stream_ingest_bytes
+                .third_party_metric_with_label_values(&[id.as_str()])
+                .get();
+            let write_bytes = i
+                .metrics
+                .stream_write_bytes
+                .third_party_metric_with_label_values(&[id.as_str()])
+                .get();
+            assert!(ingest_bytes > 0);
+            assert!(write_bytes > ingest_bytes);
+        }
+
+        // Without a concurrency cap, both merges happen in one step.
+        let mut i = indexed_with_compaction(CompactionConfig::default())?;
+        setup(&mut i)?;
+        i.step()?;
+        assert_eq!(trace_batch_count(&i), 2);
+
+        // A rate limit of zero throttles all compaction.
+        let mut i = indexed_with_compaction(CompactionConfig::default().with_rate_limit(0))?;
+        setup(&mut i)?;
+        i.step()?;
+        assert_eq!(trace_batch_count(&i), 4);
+        assert_eq!(i.metrics.compaction_throttled_count.get(), 2);
+
+        Ok(())
+    }
 }
@@ -20,6 +20,7 @@
 use std::fmt;
 
 pub mod client;
+pub mod encrypted;
 pub mod error;
 pub mod file;
 pub mod gen;
@@ -29,6 +30,7 @@ pub mod operators;
 pub mod pfuture;
 pub mod runtime;
 pub mod s3;
+pub mod schema;
 pub mod storage;
 pub mod unreliable;
 pub mod workload;
@@ -246,6 +246,21 @@ impl MemBlobCore {
         self.dataz.remove(key);
         Ok(())
     }
+
+    fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        self.ensure_open()?;
+        let current = self.dataz.get(key);
+        if current.map(|x| x.as_slice()) != expected {
+            return Ok(Err(current.cloned()));
+        }
+        self.dataz.insert(key.to_owned(), value);
+        Ok(Ok(()))
+    }
 }
 
 /// Configuration for opening a [MemBlob] or [MemBlobRead].
@@ -386,6 +401,17 @@ impl Blob for MemBlob {
     async fn delete(&mut self, key: &str) -> Result<(), Error> {
         self.core_lock()?.delete(key)
     }
+
+    async fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        // NB: This is atomic because the core is only ever accessed while
+        // holding its Mutex.
+        self.core_lock()?.compare_and_set(key, expected, value)
+    }
 }
 
 /// An in-memory representation of a [Log] and [Blob] that can be reused
@@ -429,6 +455,17 @@ impl MemRegistry {
         )
     }
 
+    /// Opens the [MemBlob] contained by this registry with a shared lock, as
+    /// if by a process that takes over with [Blob::compare_and_set].
+    pub fn blob_shared(&self) -> Result<MemBlob, Error> {
+        MemBlob::open_exclusive(
+            MemBlobConfig {
+                core: self.blob.clone(),
+            },
+            LockInfo::new_shared("MemRegistry".to_owned()),
+        )
+    }
+
     /// Returns a [RuntimeClient] using the [MemLog] and [MemBlob] contained by
     /// this registry.
     pub fn indexed_no_reentrance(&mut self) -> Result<Indexed<MemLog, MemBlob>, Error> {
@@ -571,11 +608,44 @@ impl MemMultiRegistry {
             None,
         )
     }
+
+    /// Open a [RuntimeClient] that takes over writing to `path` from any
+    /// other runtime, as if in a new process with no local state.
+    pub fn open_blob_consensus(
+        &mut self,
+        path: &str,
+        lock_info: &str,
+    ) -> Result<RuntimeClient, Error> {
+        let lock_info = LockInfo::new_shared(lock_info.to_owned());
+        let log = self.log(path, lock_info.clone())?;
+        let blob = self.blob(path, lock_info)?;
+        runtime::start(
+            RuntimeConfig::for_tests().with_blob_consensus(true),
+            log,
+            blob,
+            build_info::DUMMY_BUILD_INFO,
+            &MetricsRegistry::new(),
+            None,
+        )
+    }
+
+    /// Open a read-only [RuntimeClient] that follows the streams written to
+    /// `path` by some other runtime.
+    pub fn open_read_only(&mut self, path: &str) -> Result<RuntimeClient, Error> {
+        let blob = self.blob_read(path);
+        runtime::start_read_only(
+            RuntimeConfig::for_tests(),
+            blob,
+            build_info::DUMMY_BUILD_INFO,
+            &MetricsRegistry::new(),
+            None,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::tests::{blob_impl_test, log_impl_test};
+    use crate::storage::tests::{blob_impl_test, compare_and_set_impl_test, log_impl_test};
     use crate::storage::Atomicity::RequireAtomic;
 
     use super::*;
@@ -601,6 +671,12 @@ mod tests {
         .await
     }
 
+    #[tokio::test]
+    async fn mem_blob_compare_and_set() -> Result<(), Error> {
+        let mut blob = MemRegistry::new().blob_no_reentrance()?;
+        compare_and_set_impl_test(&mut blob).await
+    }
+
     // This test covers a regression that was affecting the nemesis tests where
     // async fetches happening in background threads could race with a close and
     // re-open of MemBlob and then incorrectly still affect the newly open
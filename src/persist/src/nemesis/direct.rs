@@ -97,7 +97,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::Receiver;
 use std::sync::{mpsc, Arc, Mutex};
 use std::time::Instant;
-use std::{fmt, thread};
+use std::{fmt, iter, thread};
 
 use timely::communication::WorkerGuards;
 use timely::dataflow::operators::capture::{Capture, Event as TimelyCaptureEvent};
@@ -113,10 +113,10 @@ use crate::indexed::encoding::Id;
 use crate::indexed::SnapshotExt;
 use crate::nemesis::progress::{DataflowProgress, DataflowProgressHandle};
 use crate::nemesis::{
-    AllowCompactionReq, FutureRes, FutureStep, Input, ReadOutputEvent, ReadOutputReq,
+    AllowCompactionReq, CrashReq, FutureRes, FutureStep, Input, ReadOutputEvent, ReadOutputReq,
     ReadOutputRes, ReadSnapshotReq, ReadSnapshotRes, Req, Res, Runtime, RuntimeWorker, SealReq,
-    SnapshotId, TakeSnapshotReq, WriteReq, WriteReqMulti, WriteReqSingle,
-    NUM_DATAFLOW_WORKER_THREADS,
+    SnapshotId, StoragePartialWritesReq, StorageSlowReq, TakeSnapshotReq, WriteReq, WriteReqMulti,
+    WriteReqSingle, NUM_DATAFLOW_WORKER_THREADS,
 };
 use crate::operators::source::PersistedSource;
 use crate::pfuture::PFuture;
@@ -140,10 +140,18 @@ struct Dataflow {
     progress_tx: DataflowProgressHandle,
 }
 
+/// A stream that a runtime which has been taken over tries to register, which
+/// must fail.
+const FENCED_STREAM: &str = "fenced";
+
 #[derive(Debug)]
 struct DirectCore {
     start_fn: Box<dyn StartRuntime>,
     unreliable: UnreliableHandle,
+    /// The handle given to the current persist runtime, which can be crashed
+    /// independently of the storage it shares with the runtimes before and
+    /// after it.
+    runtime_unreliable: UnreliableHandle,
     runtime: RuntimeClient,
     streams: HashMap<String, (Ingest, Dataflow)>,
 }
@@ -204,16 +212,78 @@ impl DirectCore {
     }
 
     fn start(&mut self) -> Result<(), Error> {
-        let _ = self.stop()?;
+        // A crash that hasn't gotten to its last write yet happens now, before
+        // the new runtime takes over.
+        self.runtime_unreliable.crash_if_pending();
+        let stop_res = self.stop();
+        // A crashed runtime can't shut down cleanly, but that doesn't stop a
+        // new one from starting.
+        if !self.runtime_unreliable.crashed() {
+            let _ = stop_res?;
+        }
         // The self.stop call clears dataflows but do it again defensively.
         self.streams.clear();
 
-        let runtime = self.start_fn.start_runtime(self.unreliable.clone())?;
+        let runtime_unreliable = self.unreliable.fork();
+        let runtime = self.start_fn.start_runtime(runtime_unreliable.clone())?;
+        self.runtime_unreliable = runtime_unreliable;
         self.runtime = runtime;
 
         Ok(())
     }
 
+    fn crash(&mut self, req: CrashReq) {
+        self.runtime_unreliable.crash_after(req.writes);
+    }
+
+    fn take_over(&mut self) -> Result<(), Error> {
+        // Start a new runtime while the current one is still running, as a new
+        // process would take over from one that hasn't noticed yet.
+        let runtime_unreliable = self.unreliable.fork();
+        let runtime = match self.start_fn.start_runtime(runtime_unreliable.clone()) {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                let _ = self.stop();
+                return Err(err);
+            }
+        };
+
+        // The old runtime is fenced: every write it attempts from now on must
+        // fail instead of clobbering the new one's. Check this for a stream it
+        // registers now as well as for the ones it already has.
+        let mut fenced_res = Ok(());
+        let (fenced_write, fenced_read) = self.runtime.create_or_load::<String, ()>(FENCED_STREAM);
+        let handles = self
+            .streams
+            .iter()
+            .map(|(name, (ingest, _))| (name.as_str(), &ingest.write, &ingest.read))
+            .chain(iter::once((FENCED_STREAM, &fenced_write, &fenced_read)));
+        for (name, write, read) in handles {
+            // Write at the seal, so that the write is otherwise valid.
+            let ts = read
+                .snapshot()
+                .ok()
+                .and_then(|snap| snap.get_seal().elements().first().copied())
+                .unwrap_or_default();
+            let update = (("fenced".to_owned(), ()), ts, 1);
+            if let Ok(seqno) = write.write(&[update]).recv() {
+                fenced_res = Err(Error::from(format!(
+                    "runtime wrote to {} at {:?} after another one took over",
+                    name, seqno
+                )));
+                break;
+            }
+        }
+
+        // The old runtime can't shut down cleanly, since that writes too.
+        let _ = self.stop();
+        self.streams.clear();
+        self.runtime_unreliable = runtime_unreliable;
+        self.runtime = runtime;
+
+        fenced_res
+    }
+
     fn stop(&mut self) -> Result<(), Error> {
         let res = self.runtime.stop();
 
@@ -245,10 +315,12 @@ struct DirectShared {
 impl DirectShared {
     fn new(mut start_fn: Box<dyn StartRuntime>) -> Result<Self, Error> {
         let unreliable = UnreliableHandle::default();
-        let runtime = start_fn.start_runtime(unreliable.clone())?;
+        let runtime_unreliable = unreliable.fork();
+        let runtime = start_fn.start_runtime(runtime_unreliable.clone())?;
         let core = DirectCore {
             start_fn,
             unreliable: unreliable.clone(),
+            runtime_unreliable,
             runtime,
             streams: HashMap::new(),
         };
@@ -334,6 +406,17 @@ impl DirectShared {
     pub fn stop(&self) -> Result<(), Error> {
         self.core.lock()?.stop()
     }
+
+    pub fn crash(&self, req: CrashReq) -> Result<(), Error> {
+        self.core.lock()?.crash(req);
+        Ok(())
+    }
+
+    pub fn take_over(&self) -> Result<(), Error> {
+        let res = self.core.lock()?.take_over();
+        let _ = self.generation.fetch_add(1, Ordering::SeqCst);
+        res
+    }
 }
 
 // TODO: With the recent addition of dataflows, this is much less "direct" than
@@ -432,6 +515,16 @@ impl RuntimeWorker for DirectWorker {
                 let res = Res::Stop(self.shared.stop());
                 FutureRes::Ready(res)
             }
+            Req::Crash(req) => {
+                self.shared
+                    .crash(req)
+                    .expect("internal nemesis error: DirectCore lock poisoned");
+                FutureRes::Ready(Res::Crash)
+            }
+            Req::TakeOver => {
+                let res = Res::TakeOver(self.shared.take_over());
+                FutureRes::Ready(res)
+            }
             Req::StorageUnavailable => {
                 self.unreliable.make_unavailable();
                 FutureRes::Ready(Res::StorageUnavailable)
@@ -440,6 +533,14 @@ impl RuntimeWorker for DirectWorker {
                 self.unreliable.make_available();
                 FutureRes::Ready(Res::StorageAvailable)
             }
+            Req::StoragePartialWrites(StoragePartialWritesReq { seed }) => {
+                self.unreliable.make_partial_writes(seed);
+                FutureRes::Ready(Res::StoragePartialWrites)
+            }
+            Req::StorageSlow(StorageSlowReq { seed, max_latency }) => {
+                self.unreliable.make_slow(seed, max_latency);
+                FutureRes::Ready(Res::StorageSlow)
+            }
         };
 
         FutureStep {
@@ -565,7 +666,7 @@ mod tests {
     use crate::mem::MemRegistry;
     use crate::nemesis::generator::GeneratorConfig;
     use crate::runtime::RuntimeConfig;
-    use crate::storage::Blob;
+    use crate::storage::{Blob, LockInfo};
     use crate::unreliable::{UnreliableBlob, UnreliableLog};
     use crate::{nemesis, runtime};
 
@@ -613,6 +714,44 @@ mod tests {
         nemesis::run(10, GeneratorConfig::default(), direct);
     }
 
+    /// File-backed storage that commits META with compare-and-set, so every
+    /// restart takes over from the previous runtime as a new process with no
+    /// local state would, even while the previous one is still running.
+    #[derive(Debug)]
+    struct BlobConsensus(TempDir);
+
+    impl StartRuntime for BlobConsensus {
+        fn start_runtime(&mut self, unreliable: UnreliableHandle) -> Result<RuntimeClient, Error> {
+            let (log_dir, blob_dir) = (self.0.path().join("log"), self.0.path().join("blob"));
+            let lock_info = LockInfo::new_shared("direct_blob_consensus".into());
+            let log = FileLog::new(log_dir, lock_info.clone())?;
+            let log = UnreliableLog::from_handle(log, unreliable.clone());
+            let blob = FileBlob::open_exclusive(blob_dir.into(), lock_info)?;
+            let blob = UnreliableBlob::from_handle(blob, unreliable);
+            runtime::start(
+                RuntimeConfig::for_tests().with_blob_consensus(true),
+                log,
+                blob,
+                build_info::DUMMY_BUILD_INFO,
+                &MetricsRegistry::new(),
+                None,
+            )
+        }
+    }
+
+    #[test]
+    fn direct_blob_consensus() {
+        let temp_dir = tempfile::tempdir().expect("tempdir creation failed");
+        let direct = Direct::new(BlobConsensus(temp_dir)).expect("initial start failed");
+        // Take over often enough to reliably happen in this few steps.
+        let config = GeneratorConfig {
+            take_over_weight: 5,
+            ..Default::default()
+        };
+        // NB: Same as direct_file, this is slow enough to run for fewer steps.
+        nemesis::run(10, config, direct);
+    }
+
     // A variant with a traffic pattern vaguely like production usage of
     // Materialize.
     #[test]
@@ -8,6 +8,7 @@
 // by the Apache License, Version 2.0.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use rand::distributions::WeightedIndex;
 use rand::prelude::{Distribution, IteratorRandom, SliceRandom, SmallRng};
@@ -15,8 +16,9 @@ use rand::{Rng, SeedableRng};
 use serde::Serialize;
 
 use crate::nemesis::{
-    AllowCompactionReq, Input, ReadOutputReq, ReadSnapshotReq, Req, ReqId, SealReq, SnapshotId,
-    TakeSnapshotReq, WriteReq, WriteReqMulti, WriteReqSingle,
+    AllowCompactionReq, CrashReq, Input, ReadOutputReq, ReadSnapshotReq, Req, ReqId, SealReq,
+    SnapshotId, StoragePartialWritesReq, StorageSlowReq, TakeSnapshotReq, WriteReq, WriteReqMulti,
+    WriteReqSingle,
 };
 
 /// Configuration of the relative probabilities of producing various
@@ -33,8 +35,12 @@ pub struct GeneratorConfig {
     pub read_snapshot_weight: u32,
     pub start_weight: u32,
     pub stop_weight: u32,
+    pub crash_weight: u32,
+    pub take_over_weight: u32,
     pub storage_unavailable: u32,
     pub storage_available: u32,
+    pub storage_partial_writes: u32,
+    pub storage_slow: u32,
 }
 
 impl GeneratorConfig {
@@ -50,19 +56,26 @@ impl GeneratorConfig {
             read_snapshot_weight: 1,
             start_weight: 1,
             stop_weight: 1,
+            crash_weight: 1,
+            take_over_weight: 1,
             storage_unavailable: 1,
             storage_available: 1,
+            storage_partial_writes: 1,
+            storage_slow: 1,
         }
     }
 }
 
 impl Default for GeneratorConfig {
     fn default() -> Self {
-        #[allow(unused_mut)]
         let mut ops = Self::all_operations();
         // NB: If we need to temporarily disable an operation in all the nemesis
         // tests, set it to 0 here. (As opposed to clearing it in the impl of
         // `all_operations`, which will break the Generator tests.)
+        //
+        // Only a runtime that commits META with compare-and-set can be taken
+        // over while it's still running, so tests of those opt in to this.
+        ops.take_over_weight = 0;
         ops
     }
 }
@@ -75,6 +88,8 @@ struct GeneratorState {
     snap_id: SnapshotId,
     outstanding_snaps: HashMap<SnapshotId, String>,
     storage_available: bool,
+    storage_partial_writes: bool,
+    storage_slow: bool,
     streams: Vec<String>,
     stream_weights: WeightedIndex<u32>,
     keys: Vec<String>,
@@ -90,6 +105,8 @@ impl Default for GeneratorState {
             snap_id: SnapshotId(0),
             outstanding_snaps: HashMap::new(),
             storage_available: true,
+            storage_partial_writes: false,
+            storage_slow: false,
             // TODO: Allow for a dynamic number of streams and keys
             streams: ('a'..='e').map(|x| x.to_string()).collect(),
             stream_weights: WeightedIndex::new(&[9, 5, 3, 1, 1]).expect("weights are valid"),
@@ -120,8 +137,12 @@ enum ReqGenerator {
     ReadSnapshot,
     Stop,
     Start,
+    Crash,
+    TakeOver,
     StorageUnavailable,
     StorageAvailable,
+    StoragePartialWrites,
+    StorageSlow,
 }
 
 impl ReqGenerator {
@@ -241,6 +262,28 @@ impl ReqGenerator {
         Req::ReadSnapshot(ReadSnapshotReq { snap })
     }
 
+    fn crash(rng: &mut SmallRng, state: &mut GeneratorState) -> Req {
+        // Let a few writes through first so the crash can land in the middle
+        // of a multi-step storage update (e.g. after writing a batch but
+        // before writing the META that references it).
+        let writes = rng.gen_range(0..5);
+        state.running = false;
+        Req::Crash(CrashReq { writes })
+    }
+
+    fn storage_partial_writes(rng: &mut SmallRng, state: &mut GeneratorState) -> Req {
+        let seed = rng.gen();
+        state.storage_partial_writes = true;
+        Req::StoragePartialWrites(StoragePartialWritesReq { seed })
+    }
+
+    fn storage_slow(rng: &mut SmallRng, state: &mut GeneratorState) -> Req {
+        let seed = rng.gen();
+        let max_latency = Duration::from_millis(rng.gen_range(1..=10));
+        state.storage_slow = true;
+        Req::StorageSlow(StorageSlowReq { seed, max_latency })
+    }
+
     fn gen(&self, rng: &mut SmallRng, state: &mut GeneratorState) -> Req {
         match self {
             ReqGenerator::WriteUnsealed => {
@@ -261,14 +304,23 @@ impl ReqGenerator {
                 state.running = false;
                 Req::Stop
             }
+            ReqGenerator::Crash => ReqGenerator::crash(rng, state),
+            ReqGenerator::TakeOver => {
+                state.running = state.storage_available;
+                Req::TakeOver
+            }
             ReqGenerator::StorageUnavailable => {
                 state.storage_available = false;
                 Req::StorageUnavailable
             }
             ReqGenerator::StorageAvailable => {
                 state.storage_available = true;
+                state.storage_partial_writes = false;
+                state.storage_slow = false;
                 Req::StorageAvailable
             }
+            ReqGenerator::StoragePartialWrites => ReqGenerator::storage_partial_writes(rng, state),
+            ReqGenerator::StorageSlow => ReqGenerator::storage_slow(rng, state),
         }
     }
 }
@@ -318,18 +370,32 @@ impl Generator {
             Some((self.config.read_snapshot_weight, ReqGenerator::ReadSnapshot))
                 .filter(|_| !self.state.outstanding_snaps.is_empty()),
             Some((self.config.stop_weight, ReqGenerator::Stop)).filter(|_| self.state.running),
+            Some((self.config.crash_weight, ReqGenerator::Crash)).filter(|_| self.state.running),
+            Some((self.config.take_over_weight, ReqGenerator::TakeOver))
+                .filter(|_| self.state.running),
             Some((
                 self.config.storage_unavailable,
                 ReqGenerator::StorageUnavailable,
             ))
             .filter(|_| self.state.storage_available),
+            Some((
+                self.config.storage_partial_writes,
+                ReqGenerator::StoragePartialWrites,
+            ))
+            .filter(|_| self.state.storage_available && !self.state.storage_partial_writes),
+            Some((self.config.storage_slow, ReqGenerator::StorageSlow))
+                .filter(|_| self.state.storage_available && !self.state.storage_slow),
         ];
         let also_when_not_running = vec![
             Some((
                 self.config.storage_available,
                 ReqGenerator::StorageAvailable,
             ))
-            .filter(|_| !self.state.storage_available),
+            .filter(|_| {
+                !self.state.storage_available
+                    || self.state.storage_partial_writes
+                    || self.state.storage_slow
+            }),
             Some((self.config.start_weight, ReqGenerator::Start)).filter(|_| !self.state.running),
         ];
 
@@ -446,8 +512,12 @@ mod tests {
                 read_snapshot_weight: 0,
                 start_weight: 0,
                 stop_weight: 0,
+                crash_weight: 0,
+                take_over_weight: 0,
                 storage_unavailable: 0,
                 storage_available: 0,
+                storage_partial_writes: 0,
+                storage_slow: 0,
             };
             ReqCounter {
                 counts,
@@ -511,9 +581,21 @@ mod tests {
                 Req::StorageUnavailable => {
                     self.counts.storage_unavailable += 1;
                 }
+                Req::Crash(_) => {
+                    self.counts.crash_weight += 1;
+                }
+                Req::TakeOver => {
+                    self.counts.take_over_weight += 1;
+                }
                 Req::StorageAvailable => {
                     self.counts.storage_available += 1;
                 }
+                Req::StoragePartialWrites(_) => {
+                    self.counts.storage_partial_writes += 1;
+                }
+                Req::StorageSlow(_) => {
+                    self.counts.storage_slow += 1;
+                }
             }
         }
     }
@@ -34,21 +34,31 @@
 //!
 //! The seed is printed when the test is run and override-able, so if a test
 //! does fail (persist violated one of its invariants aka a bug), the previous
-//! traffic can be exactly replayed. For a deterministic runtime (the "direct_*"
-//! variants) this results in exactly the same execution sequence and can be
-//! used to verify the bug fix.
+//! traffic can be exactly replayed. This includes the storage faults, which
+//! are driven by randomness derived from the same seed.
 //!
 //! ```shell
-//! NEMESIS_SEED=16385522461931935384 cargo test -p persist -- failing_test_name
+//! MZ_NEMESIS_SEED=16385522461931935384 cargo test -p persist -- failing_test_name
 //! ```
 //!
-//! If the runtime is not deterministic, the seed can still be fixed to increase
-//! the likelihood of ticking the same bug again. The cargo-stress crate, which
-//! runs a unit test (or set of tests) in a tight loop until failure, is useful
-//! for this.
+//! By default, requests are pipelined, so the order in which they execute
+//! varies from run to run even with a fixed seed. Setting `MZ_NEMESIS_REPLAY`
+//! issues each request only once the previous one has finished. For a
+//! deterministic runtime (the "direct_*" variants) this results in exactly the
+//! same execution sequence and can be used to verify the bug fix. A failing
+//! test prints the variables needed to reproduce it this way.
 //!
 //! ```shell
-//! NEMESIS_SEED=16385522461931935384 cargo stress -p persist -- failing_test_name
+//! MZ_NEMESIS_SEED=16385522461931935384 MZ_NEMESIS_STEPS=100 MZ_NEMESIS_REPLAY=1 cargo test -p persist -- failing_test_name
+//! ```
+//!
+//! If the failure only reproduces with pipelining, the seed can still be fixed
+//! to increase the likelihood of ticking the same bug again. The cargo-stress
+//! crate, which runs a unit test (or set of tests) in a tight loop until
+//! failure, is useful for this.
+//!
+//! ```shell
+//! MZ_NEMESIS_SEED=16385522461931935384 cargo stress -p persist -- failing_test_name
 //! ```
 //!
 //! Finally, the cargo-stress crate is also useful without fixing the seed to
@@ -64,12 +74,11 @@
 // - Variant with S3Blob
 // - Impl of Runtime directly using Indexed
 // - Impl of Runtime with Timely workers running in processes
-// - Storage (log/blob) with variable latency/slow requests
 // - Vary key size
 // - Deleting streams
 
 use std::collections::VecDeque;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{env, thread};
 
 use ore::test::init_logging;
@@ -135,8 +144,12 @@ pub enum Req {
     ReadSnapshot(ReadSnapshotReq),
     Start,
     Stop,
+    Crash(CrashReq),
+    TakeOver,
     StorageUnavailable,
     StorageAvailable,
+    StoragePartialWrites(StoragePartialWritesReq),
+    StorageSlow(StorageSlowReq),
 }
 
 #[derive(Debug)]
@@ -149,8 +162,12 @@ pub enum Res {
     ReadSnapshot(ReadSnapshotReq, Result<ReadSnapshotRes, Error>),
     Start(Result<(), Error>),
     Stop(Result<(), Error>),
+    Crash,
+    TakeOver(Result<(), Error>),
     StorageUnavailable,
     StorageAvailable,
+    StoragePartialWrites,
+    StorageSlow,
 }
 
 #[derive(Clone, Debug)]
@@ -211,6 +228,33 @@ pub struct ReadSnapshotReq {
     snap: SnapshotId,
 }
 
+/// Crashes the runtime, without giving it a chance to shut down cleanly.
+///
+/// The crash happens once the given number of further writes have made it to
+/// storage (or at the next Start, whichever comes first), which lets it land
+/// at any point in a multi-step update, such as between writing a blob and
+/// writing the META that references it.
+#[derive(Clone, Debug)]
+pub struct CrashReq {
+    writes: u64,
+}
+
+/// Causes blob writes to be randomly torn until storage is made available
+/// again.
+#[derive(Clone, Debug)]
+pub struct StoragePartialWritesReq {
+    seed: u64,
+}
+
+/// Causes storage operations to be randomly delayed and deleted blob keys to
+/// be listed for a while after they're deleted, until storage is made
+/// available again.
+#[derive(Clone, Debug)]
+pub struct StorageSlowReq {
+    seed: u64,
+    max_latency: Duration,
+}
+
 #[derive(Clone, Debug)]
 pub struct ReadSnapshotRes {
     seqno: u64,
@@ -282,6 +326,7 @@ pub trait Runtime {
 pub struct Runner<R: Runtime> {
     generator: Generator,
     runtime: R,
+    max_outstanding: usize,
 }
 
 // TODO: Increase this to 3 once Validator can handle that.
@@ -292,12 +337,24 @@ impl<R: Runtime> Runner<R> {
     const MAX_OUTSTANDING: usize = 10;
 
     pub fn new(generator: Generator, runtime: R) -> Self {
-        Runner { generator, runtime }
+        Runner {
+            generator,
+            runtime,
+            max_outstanding: Self::MAX_OUTSTANDING,
+        }
+    }
+
+    /// Issues each request only once the previous one has finished, so that
+    /// a history can be reproduced exactly from its seed.
+    pub fn replay(mut self) -> Self {
+        self.max_outstanding = 1;
+        self
     }
 
     pub fn run(mut self, num_steps: usize) -> Vec<Step> {
         let (tx, rx) = crossbeam_channel::bounded::<Input>(num_steps);
 
+        let max_outstanding = self.max_outstanding;
         let mut threads = Vec::new();
         for idx in 0..NUM_INGEST_WORKER_THREADS {
             let mut worker = self.runtime.add_worker();
@@ -307,7 +364,7 @@ impl<R: Runtime> Runner<R> {
                     .name(format!("nemesis:work-{}", idx))
                     .spawn(move || {
                         let mut outstanding =
-                            VecDeque::<FutureStep>::with_capacity(Self::MAX_OUTSTANDING);
+                            VecDeque::<FutureStep>::with_capacity(max_outstanding);
                         let mut steps = Vec::with_capacity(num_steps);
 
                         // Pipeline up to MAX_OUTSTANDING requests by filling up `outstanding`
@@ -336,7 +393,7 @@ impl<R: Runtime> Runner<R> {
                         // guaranteed that a ReadOutput that trails a Seal by MAX_OUTSTANDING
                         // will include dataflow output up to that seal.
                         for input in rx {
-                            while outstanding.len() >= Self::MAX_OUTSTANDING {
+                            while outstanding.len() >= max_outstanding {
                                 let step_fut = outstanding.pop_front().unwrap();
                                 let step = step_fut.recv();
                                 steps.push(step);
@@ -375,14 +432,28 @@ pub fn run<R: Runtime>(steps: usize, config: GeneratorConfig, runtime: R) {
     let seed =
         env::var("MZ_NEMESIS_SEED").map_or_else(|_| OsRng.next_u64(), |s| s.parse().unwrap());
     let steps = env::var("MZ_NEMESIS_STEPS").map_or(steps, |s| s.parse().unwrap());
-    tracing::info!("MZ_NEMESIS_SEED={} MZ_NEMESIS_STEPS={}", seed, steps);
+    let replay = env::var_os("MZ_NEMESIS_REPLAY").is_some();
+    tracing::info!(
+        "MZ_NEMESIS_SEED={} MZ_NEMESIS_STEPS={} replay={}",
+        seed,
+        steps,
+        replay
+    );
     let generator = Generator::new(seed, config);
-    let runner = Runner::new(generator, runtime);
+    let mut runner = Runner::new(generator, runtime);
+    if replay {
+        runner = runner.replay();
+    }
     let history = runner.run(steps);
     if let Err(errors) = Validator::validate(history) {
         for err in errors.iter() {
             tracing::warn!("invariant violation: {}", err)
         }
+        tracing::warn!(
+            "reproduce with: MZ_NEMESIS_SEED={} MZ_NEMESIS_STEPS={} MZ_NEMESIS_REPLAY=1",
+            seed,
+            steps
+        );
         assert!(errors.is_empty());
     }
 }
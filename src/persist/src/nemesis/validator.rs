@@ -8,8 +8,8 @@
 // by the Apache License, Version 2.0.
 
 use std::collections::{BTreeMap, HashMap};
-use std::fmt;
 use std::time::Instant;
+use std::{cmp, fmt};
 
 use differential_dataflow::lattice::Lattice;
 use timely::progress::{Antichain, Timestamp};
@@ -23,11 +23,31 @@ use crate::nemesis::{
 };
 use crate::storage::SeqNo;
 
+/// The most writes that may or may not have been applied to a stream that we
+/// still attempt to verify its contents with.
+///
+/// Each of them doubles the number of possible contents we have to check.
+const MAX_AMBIGUOUS_WRITES: usize = 10;
+
+/// A write that returned an error but may or may not have been applied.
+#[derive(Debug)]
+struct AmbiguousWrite {
+    updates: Vec<((String, ()), u64, isize)>,
+    // When the write returned its error.
+    after: Instant,
+}
+
 #[derive(Debug)]
 pub struct Validator {
     seal_frontier: HashMap<String, u64>,
     since_frontier: HashMap<String, u64>,
     writes_by_seqno: BTreeMap<(String, SeqNo), Vec<((String, ()), u64, isize)>>,
+    // Writes, seals, and compactions that returned an error while the runtime
+    // was crashing or storage was tearing writes, so may or may not have been
+    // durably applied. Writes are resolved once a snapshot shows which.
+    ambiguous_writes: HashMap<String, Vec<AmbiguousWrite>>,
+    ambiguous_seal_frontier: HashMap<String, u64>,
+    ambiguous_since_frontier: HashMap<String, u64>,
     output_by_stream:
         HashMap<String, Vec<ReadOutputEvent<(Result<(String, ()), String>, u64, isize)>>>,
     available_snapshots: HashMap<SnapshotId, (String, Instant)>,
@@ -54,6 +74,9 @@ impl Validator {
             seal_frontier: HashMap::new(),
             since_frontier: HashMap::new(),
             writes_by_seqno: BTreeMap::new(),
+            ambiguous_writes: HashMap::new(),
+            ambiguous_seal_frontier: HashMap::new(),
+            ambiguous_since_frontier: HashMap::new(),
             output_by_stream: HashMap::new(),
             available_snapshots: HashMap::new(),
             errors: Vec::new(),
@@ -81,10 +104,39 @@ impl Validator {
             Res::ReadSnapshot(req, res) => self.step_read_snapshot(&s.meta, req, res),
             Res::Start(res) => self.step_start(&s.meta, res),
             Res::Stop(res) => self.step_stop(&s.meta, res),
-            Res::StorageUnavailable | Res::StorageAvailable => {}
+            Res::TakeOver(res) => self.step_take_over(&s.meta, res),
+            Res::Crash
+            | Res::StorageUnavailable
+            | Res::StorageAvailable
+            | Res::StoragePartialWrites
+            | Res::StorageSlow => {}
         }
     }
 
+    /// Returns the latest seal that may have been applied to the given stream,
+    /// including any that errored ambiguously.
+    fn maybe_seal_frontier(&self, stream: &str) -> u64 {
+        cmp::max(
+            self.seal_frontier.get(stream).copied().unwrap_or_default(),
+            self.ambiguous_seal_frontier
+                .get(stream)
+                .copied()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns the latest compaction that may have been applied to the given
+    /// stream, including any that errored ambiguously.
+    fn maybe_since_frontier(&self, stream: &str) -> u64 {
+        cmp::max(
+            self.since_frontier.get(stream).copied().unwrap_or_default(),
+            self.ambiguous_since_frontier
+                .get(stream)
+                .copied()
+                .unwrap_or_default(),
+        )
+    }
+
     /// Enqueues an error if the given result was an error but we know
     /// externally that it should have succeeded.
     ///
@@ -149,14 +201,24 @@ impl Validator {
                 .unwrap_or_default();
         let require_succeed = self.uptime.storage_available(meta.before, meta.after)
             && self.uptime.runtime_available(meta.before, meta.after)
-            && req_ok;
+            && req.update.1 >= self.maybe_seal_frontier(&req.stream);
         self.check_success(meta, &res, require_succeed);
         self.check_failure(meta, &res, !req_ok);
-        if let Ok(res) = res {
-            self.writes_by_seqno
+        match res {
+            Ok(res) => self
+                .writes_by_seqno
                 .entry((req.stream, res))
                 .or_default()
-                .push(req.update);
+                .push(req.update),
+            Err(_) if req_ok && self.uptime.writes_ambiguous(meta.before, meta.after) => self
+                .ambiguous_writes
+                .entry(req.stream)
+                .or_default()
+                .push(AmbiguousWrite {
+                    updates: vec![req.update],
+                    after: meta.after,
+                }),
+            Err(_) => {}
         }
     }
 
@@ -172,16 +234,39 @@ impl Validator {
             });
         let require_succeed = self.uptime.storage_available(meta.before, meta.after)
             && self.uptime.runtime_available(meta.before, meta.after)
-            && req_ok;
+            && req
+                .writes
+                .iter()
+                .all(|req| req.update.1 >= self.maybe_seal_frontier(&req.stream));
         self.check_success(meta, &res, require_succeed);
         self.check_failure(meta, &res, !req_ok);
-        if let Ok(res) = res {
-            for req in req.writes {
-                self.writes_by_seqno
-                    .entry((req.stream, res))
-                    .or_default()
-                    .push(req.update);
+        match res {
+            Ok(res) => {
+                for req in req.writes {
+                    self.writes_by_seqno
+                        .entry((req.stream, res))
+                        .or_default()
+                        .push(req.update);
+                }
             }
+            Err(_) if req_ok && self.uptime.writes_ambiguous(meta.before, meta.after) => {
+                // The multi write is atomic, so its updates to each stream are
+                // either all applied or none of them are.
+                let mut by_stream: HashMap<String, Vec<_>> = HashMap::new();
+                for req in req.writes {
+                    by_stream.entry(req.stream).or_default().push(req.update);
+                }
+                for (stream, updates) in by_stream {
+                    self.ambiguous_writes
+                        .entry(stream)
+                        .or_default()
+                        .push(AmbiguousWrite {
+                            updates,
+                            after: meta.after,
+                        });
+                }
+            }
+            Err(_) => {}
         }
     }
 
@@ -231,11 +316,7 @@ impl Validator {
             }
 
             // The latest seal shouldn't be past anything we sent.
-            let latest_seal_sent = self
-                .seal_frontier
-                .get(&req.stream)
-                .copied()
-                .unwrap_or_default();
+            let latest_seal_sent = self.maybe_seal_frontier(&req.stream);
             if latest_seal > latest_seal_sent {
                 self.errors.push(format!(
                     "received seal {} greater than the latest one we sent {}",
@@ -258,13 +339,9 @@ impl Validator {
             //
             // So what we do for now is forward both sets of records to a since
             // that's guaranteed to be at in advance of both of them:
-            // specifically the the largest thing we've allowed_compaction to.
-            let as_of = Antichain::from_elem(
-                self.since_frontier
-                    .get(&req.stream)
-                    .copied()
-                    .unwrap_or_default(),
-            );
+            // specifically the the largest thing we've (maybe) allowed_compaction
+            // to.
+            let as_of = Antichain::from_elem(self.maybe_since_frontier(&req.stream));
 
             if !as_of.less_than(&latest_seal) {
                 // TODO: We cannot currently verify cases where the compaction frontier is beyond
@@ -296,18 +373,39 @@ impl Validator {
                     (kv, ts, diff)
                 })
                 .collect();
-            let mut expected: Vec<((String, ()), u64, isize)> = self
+            let expected: Vec<((String, ()), u64, isize)> = self
                 .writes_by_seqno
-                .range((req.stream.clone(), SeqNo(0))..(req.stream, SeqNo(u64::MAX)))
+                .range((req.stream.clone(), SeqNo(0))..(req.stream.clone(), SeqNo(u64::MAX)))
                 .flat_map(|(_, v)| v)
                 .filter(|(_, ts, _)| *ts < latest_seal)
                 .cloned()
                 .collect();
-            if !updates_eq(&mut actual, &mut expected, as_of) {
-                self.errors.push(format!(
-                    "incorrect output {:?} up to {}, expected {:?} got: {:?}",
-                    meta.req_id, latest_seal, expected, actual
-                ));
+            let ambiguous: Vec<Vec<_>> = self
+                .ambiguous_writes
+                .get(&req.stream)
+                .into_iter()
+                .flatten()
+                .map(|write| {
+                    write
+                        .updates
+                        .iter()
+                        .filter(|(_, ts, _)| *ts < latest_seal)
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .filter(|updates| !updates.is_empty())
+                .collect();
+            match updates_eq_ambiguous(&mut actual, &expected, &ambiguous, as_of) {
+                Some(true) => {}
+                Some(false) => self.errors.push(format!(
+                    "incorrect output {:?} up to {}, expected {:?} (plus any of {:?}) got: {:?}",
+                    meta.req_id, latest_seal, expected, ambiguous, actual
+                )),
+                None => self.errors.push(format!(
+                    "unable to verify output {:?}: too many ambiguous writes {}",
+                    meta.req_id,
+                    ambiguous.len()
+                )),
             }
         }
     }
@@ -321,11 +419,18 @@ impl Validator {
                 .unwrap_or_default();
         let require_succeed = self.uptime.storage_available(meta.before, meta.after)
             && self.uptime.runtime_available(meta.before, meta.after)
-            && req_ok;
+            && req.ts >= self.maybe_seal_frontier(&req.stream);
         self.check_success(meta, &res, require_succeed);
         self.check_failure(meta, &res, !req_ok);
-        if let Ok(_) = res {
-            self.seal_frontier.insert(req.stream, req.ts);
+        match res {
+            Ok(_) => {
+                self.seal_frontier.insert(req.stream, req.ts);
+            }
+            Err(_) if req_ok && self.uptime.writes_ambiguous(meta.before, meta.after) => {
+                let ts = self.ambiguous_seal_frontier.entry(req.stream).or_default();
+                *ts = cmp::max(*ts, req.ts);
+            }
+            Err(_) => {}
         }
     }
 
@@ -343,11 +448,18 @@ impl Validator {
                 .unwrap_or_default();
         let require_succeed = self.uptime.storage_available(meta.before, meta.after)
             && self.uptime.runtime_available(meta.before, meta.after)
-            && req_ok;
+            && req.ts >= self.maybe_since_frontier(&req.stream);
         self.check_success(meta, &res, require_succeed);
         self.check_failure(meta, &res, !req_ok);
-        if let Ok(_) = res {
-            self.since_frontier.insert(req.stream, req.ts);
+        match res {
+            Ok(_) => {
+                self.since_frontier.insert(req.stream, req.ts);
+            }
+            Err(_) if req_ok && self.uptime.writes_ambiguous(meta.before, meta.after) => {
+                let ts = self.ambiguous_since_frontier.entry(req.stream).or_default();
+                *ts = cmp::max(*ts, req.ts);
+            }
+            Err(_) => {}
         }
     }
 
@@ -382,23 +494,87 @@ impl Validator {
                 self.check_success(meta, &res, require_succeed);
                 if let Ok(res) = res {
                     let mut actual = res.contents;
-                    let mut expected: Vec<((String, ()), u64, isize)> = self
+                    let expected: Vec<((String, ()), u64, isize)> = self
                         .writes_by_seqno
-                        .range((stream.clone(), SeqNo(0))..=(stream, SeqNo(res.seqno)))
+                        .range((stream.clone(), SeqNo(0))..=(stream.clone(), SeqNo(res.seqno)))
                         .flat_map(|(_, v)| v)
                         .cloned()
                         .collect();
-                    if !updates_eq(&mut actual, &mut expected, res.since) {
-                        self.errors.push(format!(
-                            "incorrect snapshot {:?} expected {:?} got: {:?}",
-                            meta.req_id, expected, actual
-                        ));
+                    // We don't know the seqnos of ambiguous writes, so any of
+                    // them may or may not be in the snapshot.
+                    let ambiguous: Vec<_> = self
+                        .ambiguous_writes
+                        .get(&stream)
+                        .into_iter()
+                        .flatten()
+                        .map(|write| write.updates.clone())
+                        .collect();
+                    match applied_ambiguous_subsets(&mut actual, &expected, &ambiguous, res.since) {
+                        Some(applied) if applied.is_empty() => self.errors.push(format!(
+                            "incorrect snapshot {:?} expected {:?} (plus any of {:?}) got: {:?}",
+                            meta.req_id, expected, ambiguous, actual
+                        )),
+                        Some(applied) => self.resolve_ambiguous_writes(
+                            &stream,
+                            &applied,
+                            before_snap_start,
+                            SeqNo(res.seqno),
+                        ),
+                        None => self.errors.push(format!(
+                            "unable to verify snapshot {:?}: too many ambiguous writes {}",
+                            meta.req_id,
+                            ambiguous.len()
+                        )),
                     }
                 }
             }
         }
     }
 
+    /// Settles the ambiguous writes to `stream` that a snapshot taken at
+    /// `snap_before` with the given seqno has shown to be applied (or not).
+    ///
+    /// `applied` is every subset of the stream's ambiguous writes, as a bitmask,
+    /// that the snapshot contents were consistent with. A write is only settled
+    /// if it was in all of them or none of them, and if it had already returned
+    /// by the time of the snapshot while nothing could still apply it later.
+    fn resolve_ambiguous_writes(
+        &mut self,
+        stream: &str,
+        applied: &[usize],
+        snap_before: Instant,
+        seqno: SeqNo,
+    ) {
+        // A crashed runtime that hasn't been restarted yet or storage that's
+        // still tearing writes may yet apply (or lose) any of them.
+        if self.uptime.writes_ambiguous(snap_before, snap_before) {
+            return;
+        }
+        let writes = match self.ambiguous_writes.remove(stream) {
+            Some(writes) => writes,
+            None => return,
+        };
+        let mut still_ambiguous = Vec::new();
+        for (idx, write) in writes.into_iter().enumerate() {
+            let applied_count = applied.iter().filter(|x| *x & (1 << idx) != 0).count();
+            if write.after >= snap_before || (applied_count > 0 && applied_count < applied.len()) {
+                still_ambiguous.push(write);
+            } else if applied_count == applied.len() {
+                // We don't know its real seqno, but it's no later than the
+                // snapshot's, which is all that later checks need.
+                self.writes_by_seqno
+                    .entry((stream.to_owned(), seqno))
+                    .or_default()
+                    .extend(write.updates);
+            }
+            // Otherwise, it definitely wasn't applied, so forget it.
+        }
+        if !still_ambiguous.is_empty() {
+            self.ambiguous_writes
+                .insert(stream.to_owned(), still_ambiguous);
+        }
+    }
+
     fn step_start(&mut self, meta: &StepMeta, res: Result<(), Error>) {
         // The semantics of Req::Start are pretty blunt. It unconditionally
         // attempts to start a new persister. If the storage is down, the new
@@ -409,12 +585,28 @@ impl Validator {
         // and storage_available when the runtime is not available.
         let require_succeed = self.uptime.storage_available(meta.before, meta.after);
         self.check_success(meta, &res, require_succeed);
+        // Starting tears down the dataflows of any previous runtime, even one
+        // that crashed instead of being stopped, and the new ones replay the
+        // persisted data from the beginning.
+        self.output_by_stream.clear();
+    }
+
+    fn step_take_over(&mut self, meta: &StepMeta, res: Result<(), Error>) {
+        // Like Req::Start, except that the previous runtime is still running
+        // while the new one takes over. The runtime checks that the old one
+        // can no longer write and reports an error if it can, so this also
+        // must succeed whenever storage is available.
+        let require_succeed = self.uptime.storage_available(meta.before, meta.after);
+        self.check_success(meta, &res, require_succeed);
+        self.output_by_stream.clear();
     }
 
     fn step_stop(&mut self, meta: &StepMeta, res: Result<(), Error>) {
         // Stop will succeed if it can cleanly release locks, which
-        // requires the storage to be available.
-        let require_succeed = self.uptime.storage_available(meta.before, meta.after);
+        // requires the storage to be available and the runtime to not have
+        // crashed.
+        let require_succeed = self.uptime.storage_available(meta.before, meta.after)
+            && !self.uptime.writes_ambiguous(meta.before, meta.after);
         self.check_success(meta, &res, require_succeed);
         self.output_by_stream.clear();
     }
@@ -438,6 +630,131 @@ fn updates_eq(
     actual == expected
 }
 
+/// Returns whether `actual` matches `expected` plus any subset of `ambiguous`,
+/// each element of which is a group of updates that were either all applied or
+/// none of them were.
+///
+/// Returns None without checking if there are too many ambiguous groups to
+/// reasonably check every subset.
+fn updates_eq_ambiguous(
+    actual: &mut Vec<((String, ()), u64, isize)>,
+    expected: &[((String, ()), u64, isize)],
+    ambiguous: &[Vec<((String, ()), u64, isize)>],
+    since: Antichain<u64>,
+) -> Option<bool> {
+    applied_ambiguous_subsets(actual, expected, ambiguous, since).map(|x| !x.is_empty())
+}
+
+/// Returns every subset of `ambiguous`, as a bitmask, such that `actual`
+/// matches `expected` plus exactly the groups in that subset.
+///
+/// Returns None without checking if there are too many ambiguous groups to
+/// reasonably check every subset.
+fn applied_ambiguous_subsets(
+    actual: &mut Vec<((String, ()), u64, isize)>,
+    expected: &[((String, ()), u64, isize)],
+    ambiguous: &[Vec<((String, ()), u64, isize)>],
+    since: Antichain<u64>,
+) -> Option<Vec<usize>> {
+    if ambiguous.len() > MAX_AMBIGUOUS_WRITES {
+        return None;
+    }
+    let applied = (0..1usize << ambiguous.len())
+        .filter(|applied| {
+            let mut expected = expected.to_vec();
+            for (idx, updates) in ambiguous.iter().enumerate() {
+                if applied & (1 << idx) != 0 {
+                    expected.extend(updates.iter().cloned());
+                }
+            }
+            updates_eq(actual, &mut expected, since.clone())
+        })
+        .collect();
+    Some(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_eq_ambiguous_subsets() {
+        let u = |k: &str, ts: u64, diff: isize| ((k.to_owned(), ()), ts, diff);
+        let since = Antichain::from_elem(0);
+        let expected = vec![u("a", 1, 1)];
+        let ambiguous = vec![vec![u("b", 1, 1), u("c", 2, 1)], vec![u("d", 1, 1)]];
+
+        // Any subset of the ambiguous groups is fine.
+        for mut actual in vec![
+            vec![u("a", 1, 1)],
+            vec![u("a", 1, 1), u("b", 1, 1), u("c", 2, 1)],
+            vec![u("a", 1, 1), u("d", 1, 1)],
+            vec![u("d", 1, 1), u("c", 2, 1), u("b", 1, 1), u("a", 1, 1)],
+        ] {
+            assert_eq!(
+                updates_eq_ambiguous(&mut actual, &expected, &ambiguous, since.clone()),
+                Some(true)
+            );
+        }
+
+        // But a group is all or nothing and definite writes must be there.
+        for mut actual in vec![
+            vec![u("a", 1, 1), u("b", 1, 1)],
+            vec![u("d", 1, 1)],
+            vec![u("a", 1, 2)],
+        ] {
+            assert_eq!(
+                updates_eq_ambiguous(&mut actual, &expected, &ambiguous, since.clone()),
+                Some(false)
+            );
+        }
+
+        // Too many ambiguous writes to check.
+        let ambiguous = vec![vec![u("b", 1, 1)]; MAX_AMBIGUOUS_WRITES + 1];
+        assert_eq!(
+            updates_eq_ambiguous(&mut vec![], &expected, &ambiguous, since),
+            None
+        );
+    }
+
+    #[test]
+    fn applied_ambiguous_subsets_resolution() {
+        let u = |k: &str, ts: u64, diff: isize| ((k.to_owned(), ()), ts, diff);
+        let since = Antichain::from_elem(0);
+        let expected = vec![u("a", 1, 1)];
+        let ambiguous = vec![vec![u("b", 1, 1)], vec![u("c", 1, 1)], vec![u("b", 1, 1)]];
+
+        // Exactly one way to get here: c was applied and neither b was.
+        assert_eq!(
+            applied_ambiguous_subsets(
+                &mut vec![u("a", 1, 1), u("c", 1, 1)],
+                &expected,
+                &ambiguous,
+                since.clone()
+            ),
+            Some(vec![0b010])
+        );
+
+        // One of the b writes was applied, but we can't tell which, so only c
+        // (not applied) is settled.
+        assert_eq!(
+            applied_ambiguous_subsets(
+                &mut vec![u("a", 1, 1), u("b", 1, 1)],
+                &expected,
+                &ambiguous,
+                since.clone()
+            ),
+            Some(vec![0b001, 0b100])
+        );
+
+        // Nothing is consistent with a missing definite write.
+        assert_eq!(
+            applied_ambiguous_subsets(&mut vec![u("b", 1, 1)], &expected, &ambiguous, since),
+            Some(vec![])
+        );
+    }
+}
+
 mod uptime {
     use std::cmp;
     use std::collections::BTreeMap;
@@ -445,12 +762,26 @@ mod uptime {
 
     use crate::nemesis::{Res, Step};
 
+    /// How a step changes whether storage/runtime is up.
+    enum Transition {
+        /// Up from the end of the step on.
+        Up,
+        /// Down from the start of the step on.
+        Down,
+        /// Down for the duration of the step, then up.
+        Restart,
+    }
+
+    use Transition::*;
+
     /// A helper for Validate that tracks which times storage/runtime were
     /// unambiguously up.
     #[derive(Debug)]
     pub struct Uptime {
         storage_downtime: IntervalTree<Instant>,
         runtime_downtime: IntervalTree<Instant>,
+        crash_downtime: IntervalTree<Instant>,
+        partial_writes_downtime: IntervalTree<Instant>,
     }
 
     impl Uptime {
@@ -458,17 +789,52 @@ mod uptime {
         ///
         /// Steps must be sorted by before.
         pub fn new(steps: &[Step]) -> Self {
-            let (storage_downtime, runtime_downtime) =
-                match steps.iter().max_by_key(|s| s.meta.after) {
-                    Some(max_after) => (
-                        Uptime::storage_downtime(steps, max_after.meta.after),
-                        Uptime::runtime_downtime(steps, max_after.meta.after),
-                    ),
-                    None => (IntervalTree::default(), IntervalTree::default()),
-                };
+            let after_all_steps = match steps.iter().max_by_key(|s| s.meta.after) {
+                Some(max_after) => max_after.meta.after,
+                None => {
+                    return Uptime {
+                        storage_downtime: IntervalTree::default(),
+                        runtime_downtime: IntervalTree::default(),
+                        crash_downtime: IntervalTree::default(),
+                        partial_writes_downtime: IntervalTree::default(),
+                    }
+                }
+            };
             Uptime {
-                storage_downtime,
-                runtime_downtime,
+                storage_downtime: Uptime::downtime(steps, after_all_steps, |res| match res {
+                    Res::StorageUnavailable | Res::StoragePartialWrites => Some(Down),
+                    Res::StorageAvailable => Some(Up),
+                    _ => None,
+                }),
+                runtime_downtime: Uptime::downtime(steps, after_all_steps, |res| match res {
+                    // We tried to start the runtime and it succeeded.
+                    Res::Start(Ok(_)) => Some(Up),
+                    // A new runtime took over from one that was still running.
+                    // Requests to the old one fail from some point in the step
+                    // on, and those to the new one succeed once it's done.
+                    Res::TakeOver(Ok(_)) => Some(Restart),
+                    // We either tried to start the runtime and it failed (most
+                    // likely the storage was down) or we stopped or crashed the
+                    // runtime.
+                    Res::Start(Err(_)) | Res::TakeOver(Err(_)) | Res::Stop(_) | Res::Crash => {
+                        Some(Down)
+                    }
+                    _ => None,
+                }),
+                crash_downtime: Uptime::downtime(steps, after_all_steps, |res| match res {
+                    Res::Start(Ok(_)) | Res::TakeOver(Ok(_)) => Some(Up),
+                    Res::Crash => Some(Down),
+                    _ => None,
+                }),
+                partial_writes_downtime: Uptime::downtime(
+                    steps,
+                    after_all_steps,
+                    |res| match res {
+                        Res::StorageAvailable => Some(Up),
+                        Res::StoragePartialWrites => Some(Down),
+                        _ => None,
+                    },
+                ),
             }
         }
 
@@ -488,63 +854,52 @@ mod uptime {
             !self.runtime_downtime.overlaps(before, after)
         }
 
-        // NB: Steps must be sorted by before.
-        fn storage_downtime(steps: &[Step], after_all_steps: Instant) -> IntervalTree<Instant> {
-            let mut downtime_before = None;
-            let mut storage_downtime = IntervalTree::default();
-            for step in steps {
-                match step.res {
-                    Res::StorageAvailable => match downtime_before.take() {
-                        Some(downtime_before) => {
-                            storage_downtime.push(downtime_before, step.meta.after)
-                        }
-                        None => {}
-                    },
-                    Res::StorageUnavailable => {
-                        if downtime_before.is_none() {
-                            downtime_before = Some(step.meta.before)
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            // If downtime_before is still a Some, that means the test ended in
-            // downtime.
-            if let Some(downtime_before) = downtime_before.take() {
-                storage_downtime.push(downtime_before, after_all_steps);
-            }
-            storage_downtime
+        /// Returns true if the runtime may have crashed or storage may have torn
+        /// a write at any point in the given range, in which case a write that
+        /// returned an error may or may not have been applied.
+        ///
+        /// Before and after are both inclusive.
+        pub fn writes_ambiguous(&self, before: Instant, after: Instant) -> bool {
+            self.crash_downtime.overlaps(before, after)
+                || self.partial_writes_downtime.overlaps(before, after)
         }
 
+        // Returns the intervals between a step for which `up` returns
+        // Some(Down) and the next one for which it returns Some(Up), as well
+        // as the duration of any step for which it returns Some(Restart).
+        //
         // NB: Steps must be sorted by before.
-        fn runtime_downtime(steps: &[Step], after_all_steps: Instant) -> IntervalTree<Instant> {
+        fn downtime<F: Fn(&Res) -> Option<Transition>>(
+            steps: &[Step],
+            after_all_steps: Instant,
+            up: F,
+        ) -> IntervalTree<Instant> {
             let mut downtime_before = None;
-            let mut runtime_downtime = IntervalTree::default();
+            let mut downtime = IntervalTree::default();
             for step in steps {
-                match step.res {
-                    // We tried to start the runtime and it succeeded.
-                    Res::Start(Ok(_)) => match downtime_before.take() {
-                        Some(downtime_before) => {
-                            runtime_downtime.push(downtime_before, step.meta.after)
-                        }
+                match up(&step.res) {
+                    Some(Up) => match downtime_before.take() {
+                        Some(downtime_before) => downtime.push(downtime_before, step.meta.after),
                         None => {}
                     },
-                    // We either tried to start the runtime and it failed (most
-                    // likely the storage was down) or we stopped the runtime.
-                    Res::Start(Err(_)) | Res::Stop(_) => {
+                    Some(Down) => {
                         if downtime_before.is_none() {
                             downtime_before = Some(step.meta.before)
                         }
                     }
-                    _ => {}
+                    Some(Restart) => {
+                        let downtime_before = downtime_before.take().unwrap_or(step.meta.before);
+                        downtime.push(downtime_before, step.meta.after);
+                    }
+                    None => {}
                 }
             }
             // If downtime_before is still a Some, that means the test ended in
             // downtime.
             if let Some(downtime_before) = downtime_before.take() {
-                runtime_downtime.push(downtime_before, after_all_steps);
+                downtime.push(downtime_before, after_all_steps);
             }
-            runtime_downtime
+            downtime
         }
     }
 
@@ -658,6 +1013,30 @@ mod uptime {
                 },
                 res: Res::Stop(Ok(())),
             };
+            let crash = |before, after| Step {
+                meta: StepMeta {
+                    req_id: ReqId(0),
+                    before: ts(before),
+                    after: ts(after),
+                },
+                res: Res::Crash,
+            };
+            let take_over = |before, after| Step {
+                meta: StepMeta {
+                    req_id: ReqId(0),
+                    before: ts(before),
+                    after: ts(after),
+                },
+                res: Res::TakeOver(Ok(())),
+            };
+            let storage_partial_writes = |before, after| Step {
+                meta: StepMeta {
+                    req_id: ReqId(0),
+                    before: ts(before),
+                    after: ts(after),
+                },
+                res: Res::StoragePartialWrites,
+            };
 
             // Empty. Both storage and runtime start as available.
             let u = Uptime::new(&[]);
@@ -722,6 +1101,55 @@ mod uptime {
             assert_eq!(u.runtime_available(ts(1), ts(5)), false);
             assert_eq!(u.storage_available(ts(7), ts(11)), false);
             assert_eq!(u.runtime_available(ts(7), ts(11)), true);
+
+            // A crash takes the runtime down like a stop, but also makes
+            // writes ambiguous until the runtime is successfully restarted.
+            let u = Uptime::new(&[
+                crash(1, 2),
+                runtime_up(4, 5, false),
+                runtime_up(7, 8, true),
+                runtime_down(10, 11),
+                runtime_up(13, 14, true),
+            ]);
+            assert_eq!(u.runtime_available(ts(0), ts(0)), true);
+            assert_eq!(u.writes_ambiguous(ts(0), ts(0)), false);
+            assert_eq!(u.runtime_available(ts(1), ts(8)), false);
+            assert_eq!(u.writes_ambiguous(ts(1), ts(1)), true);
+            assert_eq!(u.writes_ambiguous(ts(6), ts(6)), true);
+            assert_eq!(u.writes_ambiguous(ts(8), ts(8)), true);
+            assert_eq!(u.runtime_available(ts(9), ts(9)), true);
+            assert_eq!(u.writes_ambiguous(ts(9), ts(9)), false);
+            // A clean stop is not ambiguous.
+            assert_eq!(u.runtime_available(ts(10), ts(14)), false);
+            assert_eq!(u.writes_ambiguous(ts(9), ts(15)), false);
+
+            // Taking over from a running runtime takes it down only for the
+            // duration of the take over, without making writes ambiguous.
+            let u = Uptime::new(&[take_over(1, 2), runtime_down(4, 5), take_over(7, 8)]);
+            assert_eq!(u.runtime_available(ts(0), ts(0)), true);
+            assert_eq!(u.runtime_available(ts(1), ts(1)), false);
+            assert_eq!(u.runtime_available(ts(2), ts(2)), false);
+            assert_eq!(u.runtime_available(ts(3), ts(3)), true);
+            assert_eq!(u.writes_ambiguous(ts(0), ts(3)), false);
+            // Taking over from a stopped runtime is just a start.
+            assert_eq!(u.runtime_available(ts(4), ts(8)), false);
+            assert_eq!(u.runtime_available(ts(9), ts(9)), true);
+
+            // Torn writes take storage down and make writes ambiguous until
+            // storage is made available again.
+            let u = Uptime::new(&[
+                storage_partial_writes(1, 2),
+                storage_up(4, 5),
+                storage_down(7, 8),
+                storage_up(10, 11),
+            ]);
+            assert_eq!(u.storage_available(ts(1), ts(5)), false);
+            assert_eq!(u.writes_ambiguous(ts(1), ts(5)), true);
+            assert_eq!(u.storage_available(ts(6), ts(6)), true);
+            assert_eq!(u.writes_ambiguous(ts(6), ts(6)), false);
+            // Unavailable storage fails writes without applying them.
+            assert_eq!(u.storage_available(ts(7), ts(11)), false);
+            assert_eq!(u.writes_ambiguous(ts(7), ts(11)), false);
         }
 
         #[test]
@@ -26,19 +26,21 @@ use tokio::time;
 use crate::client::RuntimeClient;
 use crate::error::Error;
 use crate::indexed::arrangement::ArrangementSnapshot;
-use crate::indexed::background::Maintainer;
-use crate::indexed::cache::BlobCache;
-use crate::indexed::columnar::ColumnarRecords;
+use crate::indexed::background::{CompactionConfig, Maintainer};
+use crate::indexed::cache::{BlobCache, BlobCacheConfig};
+use crate::indexed::columnar::{BatchCompression, ColumnarRecords};
 use crate::indexed::encoding::Id;
+use crate::indexed::follower::Follower;
 use crate::indexed::metrics::Metrics;
 use crate::indexed::{Indexed, ListenEvent};
 use crate::pfuture::{PFuture, PFutureHandle};
-use crate::storage::{Blob, Log, SeqNo};
+use crate::schema::Schema;
+use crate::storage::{Blob, BlobRead, Log, SeqNo};
 use futures_executor::block_on;
 
 #[derive(Debug)]
 pub(crate) enum Cmd {
-    Register(String, (String, String), PFutureHandle<Id>),
+    Register(String, (String, String), Schema, PFutureHandle<Id>),
     Destroy(String, PFutureHandle<bool>),
     Write(Vec<(Id, ColumnarRecords)>, PFutureHandle<SeqNo>),
     Seal(Vec<Id>, u64, PFutureHandle<SeqNo>),
@@ -74,7 +76,7 @@ pub(crate) enum Cmd {
 // Blob storage traits async, this will clear up a bit.
 pub fn start<L, B>(
     config: RuntimeConfig,
-    log: L,
+    mut log: L,
     blob: B,
     build: BuildInfo,
     reg: &MetricsRegistry,
@@ -94,15 +96,97 @@ where
     };
 
     // Start up the runtime.
-    let blob = BlobCache::new(build, metrics.clone(), async_runtime.clone(), blob);
-    let maintainer = Maintainer::new(blob.clone(), async_runtime.clone());
+    let mut blob = BlobCache::new(build, metrics.clone(), async_runtime.clone(), blob)
+        .with_compression(config.compression)
+        .with_config(config.cache.clone())?;
+    if config.blob_consensus {
+        if let Err(err) = blob.take_over() {
+            // Same as in Indexed::new, we're expected to close the log and
+            // blob we were handed.
+            if let Err(err) = log.close() {
+                tracing::warn!("error closing log: {}", err);
+            }
+            if let Err(err) = blob.close() {
+                tracing::warn!("error closing blob: {}", err);
+            }
+            return Err(err);
+        }
+    }
+    let maintainer =
+        Maintainer::new(blob.clone(), async_runtime.clone()).with_config(config.compaction.clone());
     let indexed = Indexed::new(log, blob, maintainer, metrics.clone())?;
     let mut runtime = RuntimeImpl::new(config.clone(), indexed, rx, metrics.clone());
-    let id = RuntimeId::new();
     let impl_handle = thread::Builder::new()
         .name("persist:runtime".into())
         .spawn(move || while runtime.work() {})?;
 
+    Ok(start_client(
+        config,
+        tx,
+        impl_handle,
+        async_runtime,
+        metrics,
+    ))
+}
+
+/// Starts a read-only runtime in a [std::thread], which follows the data
+/// written to `blob` by a runtime in another process.
+///
+/// The returned client can register streams that the writer has already
+/// created, take snapshots, and listen to them. Everything that would modify
+/// persisted data returns an error. Snapshots and listens only include data
+/// that the writer has sealed. The follower learns about newly sealed data by
+/// polling the writer's meta, roughly once per `min_step_interval`. See
+/// [Follower] for details.
+///
+/// Since this only reads, it does not take the exclusive-writer lock and any
+/// number of read-only runtimes may follow the same location.
+pub fn start_read_only<B>(
+    config: RuntimeConfig,
+    blob: B,
+    build: BuildInfo,
+    reg: &MetricsRegistry,
+    async_runtime: Option<Arc<AsyncRuntime>>,
+) -> Result<RuntimeClient, Error>
+where
+    B: BlobRead + Send + 'static,
+{
+    let (tx, rx) = crossbeam_channel::unbounded();
+    let metrics = Arc::new(Metrics::register_with(reg));
+
+    let async_runtime = match async_runtime {
+        Some(pool) => pool,
+        None => Arc::new(AsyncRuntime::new()?),
+    };
+
+    let blob = BlobCache::new(build, metrics.clone(), async_runtime.clone(), blob)
+        .with_config(config.cache.clone())?;
+    let follower = Follower::new(blob, metrics.clone())?;
+    let mut runtime = ReadOnlyRuntimeImpl::new(config.clone(), follower, rx, metrics.clone());
+    let impl_handle = thread::Builder::new()
+        .name("persist:follower".into())
+        .spawn(move || while runtime.work() {})?;
+
+    Ok(start_client(
+        config,
+        tx,
+        impl_handle,
+        async_runtime,
+        metrics,
+    ))
+}
+
+/// Starts the ticker for a runtime running in `impl_handle` and returns a
+/// client connected to it.
+fn start_client(
+    config: RuntimeConfig,
+    tx: crossbeam_channel::Sender<Cmd>,
+    impl_handle: JoinHandle<()>,
+    async_runtime: Arc<AsyncRuntime>,
+    metrics: Arc<Metrics>,
+) -> RuntimeClient {
+    let id = RuntimeId::new();
+
     // Start up the ticker thread.
     let ticker_tx = tx.clone();
     let ticker_handle = async_runtime.spawn(async move {
@@ -134,9 +218,7 @@ where
         tx,
         metrics,
     };
-    let client = RuntimeClient::new(id, Arc::new(core));
-
-    Ok(client)
+    RuntimeClient::new(id, Arc::new(core))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -177,7 +259,7 @@ impl RuntimeCore {
                     // Already stopped: no-op.
                     res.fill(Ok(()))
                 }
-                Cmd::Register(_, _, res) => res.fill(Err(Error::RuntimeShutdown)),
+                Cmd::Register(_, _, _, res) => res.fill(Err(Error::RuntimeShutdown)),
                 Cmd::Destroy(_, res) => res.fill(Err(Error::RuntimeShutdown)),
                 Cmd::Write(_, res) => res.fill(Err(Error::RuntimeShutdown)),
                 Cmd::Seal(_, _, res) => res.fill(Err(Error::RuntimeShutdown)),
@@ -241,30 +323,90 @@ struct RuntimeImpl<L: Log, B: Blob> {
 pub struct RuntimeConfig {
     /// Minimum step interval to use
     min_step_interval: Duration,
+    /// Codec used to compress newly written unsealed and trace batches
+    compression: BatchCompression,
+    /// Size limits of the in-memory and on-disk batch cache
+    cache: BlobCacheConfig,
+    /// Policy and limits for trace compaction
+    compaction: CompactionConfig,
+    /// Whether META is committed with compare-and-set, see
+    /// [Self::with_blob_consensus]
+    blob_consensus: bool,
 }
 
 impl Default for RuntimeConfig {
     fn default() -> Self {
         Self {
             min_step_interval: Self::DEFAULT_MIN_STEP_INTERVAL,
+            compression: Self::DEFAULT_COMPRESSION,
+            cache: BlobCacheConfig::default(),
+            compaction: CompactionConfig::default(),
+            blob_consensus: false,
         }
     }
 }
 
 impl RuntimeConfig {
     const DEFAULT_MIN_STEP_INTERVAL: Duration = Duration::from_millis(1000);
+    const DEFAULT_COMPRESSION: BatchCompression = BatchCompression::Zstd;
 
     /// An alternate configuration that minimizes latency at the cost of
     /// increased storage traffic.
     pub(crate) fn for_tests() -> Self {
         RuntimeConfig {
             min_step_interval: Duration::from_millis(1),
+            compression: Self::DEFAULT_COMPRESSION,
+            cache: BlobCacheConfig::default(),
+            compaction: CompactionConfig::default(),
+            blob_consensus: false,
         }
     }
 
     /// A configuration with a configurable min_step_interval
     pub fn with_min_step_interval(min_step_interval: Duration) -> Self {
-        RuntimeConfig { min_step_interval }
+        RuntimeConfig {
+            min_step_interval,
+            compression: Self::DEFAULT_COMPRESSION,
+            cache: BlobCacheConfig::default(),
+            compaction: CompactionConfig::default(),
+            blob_consensus: false,
+        }
+    }
+
+    /// Returns this configuration with a different codec for newly written
+    /// batches.
+    pub fn with_compression(mut self, compression: BatchCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns this configuration with different size limits for the cache of
+    /// batches read from and written to blob storage.
+    pub fn with_cache(mut self, cache: BlobCacheConfig) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Returns this configuration with a different policy and limits for
+    /// compacting traces in the background.
+    pub fn with_compaction(mut self, compaction: CompactionConfig) -> Self {
+        self.compaction = compaction;
+        self
+    }
+
+    /// Returns this configuration with META committed to blob storage with
+    /// [Blob::compare_and_set](crate::storage::Blob::compare_and_set).
+    ///
+    /// On startup, the runtime takes over as the writer of its location from
+    /// any other process, even one that crashed without releasing its lock or
+    /// one that is still running. Everything needed to recover lives in blob
+    /// storage, so a process with no local state can pick up where another
+    /// left off. Every process writing to a location must agree on this
+    /// setting and should open it with
+    /// [LockInfo::new_shared](crate::storage::LockInfo::new_shared).
+    pub fn with_blob_consensus(mut self, blob_consensus: bool) -> Self {
+        self.blob_consensus = blob_consensus;
+        self
     }
 }
 
@@ -330,9 +472,9 @@ impl<L: Log, B: Blob> RuntimeImpl<L, B> {
                     res.fill(self.indexed.close());
                     return false;
                 }
-                Cmd::Register(id, (key_codec_name, val_codec_name), res) => {
+                Cmd::Register(id, (key_codec_name, val_codec_name), schema, res) => {
                     self.indexed
-                        .register(&id, &key_codec_name, &val_codec_name, res);
+                        .register(&id, &key_codec_name, &val_codec_name, schema, res);
                 }
                 Cmd::Destroy(id, res) => {
                     self.indexed.destroy(&id, res);
@@ -432,6 +574,88 @@ impl<L: Log, B: Blob> RuntimeImpl<L, B> {
     }
 }
 
+struct ReadOnlyRuntimeImpl<B: BlobRead> {
+    follower: Follower<B>,
+    rx: crossbeam_channel::Receiver<Cmd>,
+    metrics: Arc<Metrics>,
+    prev_poll: Instant,
+    min_poll_interval: Duration,
+}
+
+impl<B: BlobRead> ReadOnlyRuntimeImpl<B> {
+    fn new(
+        config: RuntimeConfig,
+        follower: Follower<B>,
+        rx: crossbeam_channel::Receiver<Cmd>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        ReadOnlyRuntimeImpl {
+            follower,
+            rx,
+            metrics,
+            prev_poll: Instant::now(),
+            min_poll_interval: config.min_step_interval,
+        }
+    }
+
+    /// Synchronously waits for the next command, executes it, and responds.
+    ///
+    /// Returns false to indicate a graceful shutdown, true otherwise.
+    fn work(&mut self) -> bool {
+        let cmd = match self.rx.recv() {
+            Ok(cmd) => cmd,
+            Err(crossbeam_channel::RecvError) => {
+                // All Runtime handles hung up. Drop should have shut things down
+                // nicely, so this is unexpected.
+                return false;
+            }
+        };
+
+        fn read_only<T>(what: &str) -> Result<T, Error> {
+            Err(Error::from(format!(
+                "cannot {} through a read-only persist runtime",
+                what
+            )))
+        }
+        match cmd {
+            Cmd::Stop(res) => {
+                res.fill(self.follower.close());
+                return false;
+            }
+            Cmd::Register(id, (key_codec_name, val_codec_name), schema, res) => {
+                self.follower
+                    .register(&id, &key_codec_name, &val_codec_name, schema, res);
+            }
+            Cmd::Destroy(_, res) => res.fill(read_only("destroy")),
+            Cmd::Write(_, res) => res.fill(read_only("write")),
+            Cmd::Seal(_, _, res) => res.fill(read_only("seal")),
+            Cmd::GetDescription(id_str, res) => {
+                self.follower.get_description(&id_str, res);
+            }
+            Cmd::AllowCompaction(_, res) => res.fill(read_only("allow compaction")),
+            Cmd::Snapshot(id, res) => {
+                self.follower.snapshot(id, res);
+            }
+            Cmd::Listen(id, sender, res) => {
+                self.follower.listen(id, sender, res);
+            }
+            Cmd::Tick => {}
+        }
+        self.metrics.cmd_run_count.inc();
+
+        let now = Instant::now();
+        if now.duration_since(self.prev_poll) > self.min_poll_interval {
+            self.prev_poll = now;
+            if let Err(e) = self.follower.poll() {
+                self.metrics.cmd_step_error_count.inc();
+                tracing::warn!("error polling persisted meta: {:?}", e);
+            }
+        }
+
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use timely::dataflow::operators::capture::Extract;
@@ -739,4 +963,118 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn read_only() -> Result<(), Error> {
+        let mut registry = MemMultiRegistry::new();
+        let mut writer = registry.open("path", "writer")?;
+        let (write, _) = writer.create_or_load::<String, ()>("0");
+        write
+            .write(&[(("a".into(), ()), 1, 1), (("b".into(), ()), 3, 1)])
+            .recv()?;
+        write.seal(2).recv()?;
+
+        // A follower sees the stream registered by the writer, but only the
+        // sealed part of it.
+        let mut follower = registry.open_read_only("path")?;
+        let (follower_write, read) = follower.create_or_load::<String, ()>("0");
+        assert_eq!(
+            read.snapshot()?.read_to_end()?,
+            vec![(("a".into(), ()), 1, 1)]
+        );
+        assert_eq!(
+            follower.get_description("0")?.upper(),
+            &Antichain::from_elem(2)
+        );
+
+        // Followers can neither write nor create streams.
+        assert_eq!(
+            follower_write.write(&[(("c".into(), ()), 3, 1)]).recv(),
+            Err("cannot write through a read-only persist runtime".into())
+        );
+        assert_eq!(
+            follower_write.seal(3).recv(),
+            Err("cannot seal through a read-only persist runtime".into())
+        );
+        assert_eq!(
+            follower.create_or_load::<String, ()>("1").0.stream_id(),
+            Err(Error::UnknownRegistration("1".into()))
+        );
+
+        // Listeners receive data as the writer seals it, even if the writer
+        // compacts in the meantime.
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let snap = read.listen(tx)?;
+        assert_eq!(snap.get_seal(), Antichain::from_elem(2));
+        assert_eq!(snap.read_to_end()?, vec![(("a".into(), ()), 1, 1)]);
+        write.write(&[(("c".into(), ()), 4, 1)]).recv()?;
+        write.seal(5).recv()?;
+        write.allow_compaction(Antichain::from_elem(2)).recv()?;
+        let mut records = Vec::new();
+        loop {
+            match rx.recv().map_err(|err| Error::from(err.to_string()))? {
+                ListenEvent::Records(mut r) => records.append(&mut r),
+                ListenEvent::Sealed(ts) if ts >= 5 => break,
+                ListenEvent::Sealed(_) => {}
+            }
+        }
+        records.sort();
+        assert_eq!(
+            records,
+            vec![
+                ((b"b".to_vec(), vec![]), 3, 1),
+                ((b"c".to_vec(), vec![]), 4, 1),
+            ]
+        );
+        assert_eq!(read.snapshot()?.get_seal(), Antichain::from_elem(5));
+
+        follower.stop()?;
+        writer.stop()?;
+        Ok(())
+    }
+
+    #[test]
+    fn blob_consensus() -> Result<(), Error> {
+        let mut registry = MemMultiRegistry::new();
+        let mut old = registry.open_blob_consensus("path", "old")?;
+        let (old_write, _) = old.create_or_load::<String, ()>("0");
+        old_write.write(&[(("a".into(), ()), 1, 1)]).recv()?;
+        old_write.seal(2).recv()?;
+
+        // A new runtime takes over from the old one, which never released its
+        // lock, and sees everything it wrote.
+        let mut new = registry.open_blob_consensus("path", "new")?;
+        let (new_write, new_read) = new.create_or_load::<String, ()>("0");
+        assert_eq!(
+            new_read.snapshot()?.read_to_end()?,
+            vec![(("a".into(), ()), 1, 1)]
+        );
+
+        // From then on, the old runtime's writes fail instead of clobbering
+        // the new one's.
+        assert_eq!(
+            old_write.write(&[(("b".into(), ()), 2, 1)]).recv(),
+            Err(Error::from(
+                "another process took over writing to persist storage after writer epoch 1"
+            ))
+        );
+        new_write.write(&[(("c".into(), ()), 2, 1)]).recv()?;
+        new_write.seal(3).recv()?;
+        assert_eq!(
+            old_write.seal(3).recv(),
+            Err(Error::from(
+                "another process took over writing to persist storage after writer epoch 1"
+            ))
+        );
+        assert_eq!(
+            new_read.snapshot()?.read_to_end()?,
+            vec![(("a".into(), ()), 1, 1), (("c".into(), ()), 2, 1)]
+        );
+
+        // NB: Stop the new runtime first, closing a MemBlob closes it for
+        // every handle to the same path.
+        new.stop()?;
+        old.stop()?;
+        Ok(())
+    }
 }
@@ -371,6 +371,20 @@ impl Blob for S3Blob {
             .map_err(|err| Error::from(err.to_string()))?;
         Ok(())
     }
+
+    async fn compare_and_set(
+        &mut self,
+        _key: &str,
+        _expected: Option<&[u8]>,
+        _value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        let _ = self.core.ensure_open()?;
+        // S3 doesn't offer a conditional put, so there's no way to make this
+        // atomic with respect to other writers.
+        Err(Error::from(
+            "S3 blob storage does not support compare_and_set",
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,254 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Schema evolution for persisted streams.
+//!
+//! Every persisted stream records the version of the schema its data was
+//! written with. Each [Schema] is an ordered list of migrations, and its
+//! version is the number of migrations it contains, so version `n` data is
+//! brought up to date by applying every migration from index `n` onward.
+//!
+//! Data written at an older version is migrated lazily, when it is read in a
+//! snapshot or listen, and eagerly, when trace compaction rewrites it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use ore::cast::CastFrom;
+use persist_types::Codec;
+
+use crate::error::Error;
+
+type MigrationFn = dyn Fn(&[u8], &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> + Send + Sync;
+
+/// An ordered list of migrations describing how the encoded keys and values of
+/// a stream have changed over time.
+///
+/// Migrations may only ever be appended: once data has been written with some
+/// version of a schema, every process reading or writing that stream must use
+/// a schema that contains the same migrations as a prefix.
+#[derive(Clone, Default)]
+pub struct Schema {
+    migrations: Vec<Arc<MigrationFn>>,
+}
+
+impl fmt::Debug for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schema")
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+impl PartialEq for Schema {
+    fn eq(&self, other: &Self) -> bool {
+        self.migrations.len() == other.migrations.len()
+            && self
+                .migrations
+                .iter()
+                .zip(other.migrations.iter())
+                .all(|(x, y)| Arc::ptr_eq(x, y))
+    }
+}
+
+impl Eq for Schema {}
+
+impl Schema {
+    /// Returns a new schema at version 0, with no migrations.
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// The version of data written with this schema.
+    pub fn version(&self) -> u64 {
+        u64::cast_from(self.migrations.len())
+    }
+
+    /// Returns this schema with an additional migration, bumping its version
+    /// by one.
+    ///
+    /// The migration is given a key and value decoded from data written at the
+    /// previous version and returns them as they should be read at the new
+    /// one.
+    pub fn with_migration<K, V, F>(mut self, f: F) -> Self
+    where
+        K: Codec,
+        V: Codec,
+        F: Fn(K, V) -> Result<(K, V), String> + Send + Sync + 'static,
+    {
+        let migration = move |key: &[u8], val: &[u8]| {
+            let (key, val) = f(K::decode(key)?, V::decode(val)?)?;
+            let (mut key_buf, mut val_buf) = (Vec::new(), Vec::new());
+            key.encode(&mut key_buf);
+            val.encode(&mut val_buf);
+            Ok((key_buf, val_buf))
+        };
+        self.migrations.push(Arc::new(migration));
+        self
+    }
+}
+
+/// The schema version of a stream along with, if one has been registered by
+/// this process, the [Schema] needed to read data written at older versions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StreamSchema {
+    version: u64,
+    schema: Option<Schema>,
+}
+
+impl StreamSchema {
+    /// Returns a [StreamSchema] at the given version with no registered
+    /// [Schema].
+    pub fn new(version: u64) -> Self {
+        StreamSchema {
+            version,
+            schema: None,
+        }
+    }
+
+    /// The version of newly written data.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The registered [Schema], if any.
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    /// Registers the [Schema] used to read and write this stream, advancing
+    /// the version to match it.
+    ///
+    /// Returns an error if the schema is older than data already written.
+    pub fn register(&mut self, schema: Schema) -> Result<(), Error> {
+        if schema.version() < self.version {
+            return Err(Error::from(format!(
+                "invalid registration: schema version {} is older than persisted version {}",
+                schema.version(),
+                self.version
+            )));
+        }
+        self.version = schema.version();
+        self.schema = Some(schema);
+        Ok(())
+    }
+
+    /// Whether data written at version `from` can be migrated to the current
+    /// version.
+    pub fn can_read(&self, from: u64) -> bool {
+        from == self.version || (self.schema.is_some() && from <= self.version)
+    }
+
+    /// Migrates a key and value written at version `from` to the current
+    /// version.
+    pub fn migrate(
+        &self,
+        from: u64,
+        key: Vec<u8>,
+        val: Vec<u8>,
+    ) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        if from == self.version {
+            return Ok((key, val));
+        }
+        let schema = match &self.schema {
+            Some(schema) if from < self.version => schema,
+            Some(_) => {
+                return Err(Error::from(format!(
+                    "data written at schema version {} is newer than registered version {}",
+                    from, self.version
+                )))
+            }
+            None => {
+                return Err(Error::from(format!(
+                    "data written at schema version {} requires a registered schema to read at version {}",
+                    from, self.version
+                )))
+            }
+        };
+        let (mut key, mut val) = (key, val);
+        for migration in schema.migrations[usize::cast_from(from)..].iter() {
+            let migrated = migration(&key, &val)?;
+            key = migrated.0;
+            val = migrated.1;
+        }
+        Ok((key, val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(key: &str, val: &str) -> (Vec<u8>, Vec<u8>) {
+        let (mut key_buf, mut val_buf) = (Vec::new(), Vec::new());
+        key.to_owned().encode(&mut key_buf);
+        val.to_owned().encode(&mut val_buf);
+        (key_buf, val_buf)
+    }
+
+    #[test]
+    fn schema_version() {
+        let schema = Schema::new();
+        assert_eq!(schema.version(), 0);
+        let schema = schema
+            .with_migration(|k: String, v: String| Ok((k, format!("{}!", v))))
+            .with_migration(|k: String, v: String| Ok((k.to_uppercase(), v)));
+        assert_eq!(schema.version(), 2);
+        assert_eq!(schema.clone(), schema);
+        assert_ne!(schema, Schema::new());
+    }
+
+    #[test]
+    fn stream_schema_migrate() -> Result<(), Error> {
+        let schema = Schema::new()
+            .with_migration(|k: String, v: String| Ok((k, format!("{}!", v))))
+            .with_migration(|k: String, v: String| Ok((k.to_uppercase(), v)));
+
+        // Without a registered schema, only data at the current version can be
+        // read.
+        let mut s = StreamSchema::new(1);
+        assert!(!s.can_read(0));
+        assert!(s.can_read(1));
+        let (k, v) = encode("a", "x");
+        assert_eq!(s.migrate(1, k.clone(), v.clone())?, (k, v));
+        let (k, v) = encode("a", "x");
+        assert!(s.migrate(0, k, v).is_err());
+
+        // Registering an older schema is an error.
+        assert_eq!(
+            s.clone().register(Schema::new()),
+            Err(Error::from(
+                "invalid registration: schema version 0 is older than persisted version 1"
+            ))
+        );
+
+        // Registering a newer schema advances the version and applies all the
+        // migrations since the data's version.
+        s.register(schema)?;
+        assert_eq!(s.version(), 2);
+        assert!(s.can_read(0) && s.can_read(1) && s.can_read(2));
+        assert!(!s.can_read(3));
+        let (k, v) = encode("a", "x");
+        assert_eq!(s.migrate(0, k, v)?, encode("A", "x!"));
+        let (k, v) = encode("a", "x");
+        assert_eq!(s.migrate(1, k, v)?, encode("A", "x"));
+        let (k, v) = encode("a", "x");
+        assert_eq!(s.migrate(2, k, v)?, encode("a", "x"));
+        let (k, v) = encode("a", "x");
+        assert!(s.migrate(3, k, v).is_err());
+
+        // Errors from the migration itself are surfaced.
+        let mut s = StreamSchema::new(0);
+        s.register(Schema::new().with_migration(|_: String, _: String| Err("boom".to_owned())))?;
+        let (k, v) = encode("a", "x");
+        assert_eq!(s.migrate(0, k, v), Err(Error::from("boom")));
+
+        Ok(())
+    }
+}
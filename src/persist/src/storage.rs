@@ -18,6 +18,7 @@ use async_trait::async_trait;
 use futures_executor::block_on;
 use serde::{Deserialize, Serialize};
 
+use crate::encrypted;
 use crate::error::Error;
 use crate::gen::persist::ProtoMeta;
 
@@ -31,6 +32,7 @@ pub fn check_meta_version_maybe_delete_data<B: Blob>(b: &mut B) -> Result<(), Er
         None => return Ok(()),
         Some(bytes) => bytes,
     };
+    encrypted::check_not_encrypted(&meta)?;
 
     let current_version = ProtoMeta::ENCODING_VERSION;
     let persisted_version = ProtoMeta::encoded_version(&meta)?;
@@ -163,6 +165,14 @@ pub trait BlobRead: Send + 'static {
     /// to be able to close something that we only know is a BlobRead. Possible
     /// there's something better we could be doing here.
     async fn close(&mut self) -> Result<bool, Error>;
+
+    /// The id of the key that values written through this blob are encrypted
+    /// with, if any.
+    ///
+    /// See [crate::encrypted::EncryptedBlob].
+    fn encryption_key_id(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// An abstraction over read-write access to a `bytes key`->`bytes value` store.
@@ -208,6 +218,24 @@ pub trait Blob: BlobRead + Sized {
     ///
     /// Succeeds if the key does not exist.
     async fn delete(&mut self, key: &str) -> Result<(), Error>;
+
+    /// Atomically inserts a key-value pair into the map, but only if the
+    /// current value for the key is `expected` (None meaning that the key
+    /// doesn't exist).
+    ///
+    /// Returns the current value instead if it didn't match. This must be
+    /// atomic with respect to every other write (compare_and_set, set, or
+    /// delete) of the same location, including those issued by other
+    /// processes, so that it can be used to commit META without relying on
+    /// being its only writer. Implementations that can't provide this (e.g.
+    /// because the underlying storage has no conditional writes, as with S3)
+    /// return an error.
+    async fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error>;
 }
 
 /// The partially structured information stored in an exclusive-writer lock.
@@ -241,6 +269,8 @@ pub struct LockInfo {
 }
 
 impl LockInfo {
+    const SHARED_REENTRANCE_ID: &'static str = "shared";
+
     /// Returns a new LockInfo from its component parts.
     ///
     /// Errors if reentrance_id contains a newline.
@@ -271,6 +301,18 @@ impl LockInfo {
         Self::new(reentrance_id, details).expect("reentrance_id was valid")
     }
 
+    /// Constructs a new LockInfo with a reentrance id shared by every process
+    /// that opens it.
+    ///
+    /// This is only safe when something other than the lock keeps those
+    /// processes from clobbering each other's writes, which is the case when
+    /// META is committed with [Blob::compare_and_set]. A process can then take
+    /// over a location from one that crashed without releasing its lock, so no
+    /// local state (like the reentrance id) needs to survive a restart.
+    pub fn new_shared(details: String) -> Self {
+        Self::new(Self::SHARED_REENTRANCE_ID.to_owned(), details).expect("reentrance_id was valid")
+    }
+
     /// Returns Ok if this lock information represents a process that may
     /// proceed in the presence of some existing lock.
     pub fn check_reentrant_for<D: fmt::Debug, R: Read>(
@@ -621,6 +663,58 @@ pub mod tests {
         Ok(())
     }
 
+    pub async fn compare_and_set_impl_test<B: Blob>(blob: &mut B) -> Result<(), Error> {
+        let values = vec!["v0".as_bytes().to_vec(), "v1".as_bytes().to_vec()];
+
+        // The key doesn't exist yet, so a compare_and_set that expects a value
+        // fails and one that expects nothing succeeds.
+        assert_eq!(
+            blob.compare_and_set("k0", Some(&values[0]), values[1].clone())
+                .await?,
+            Err(None)
+        );
+        assert_eq!(blob.get("k0").await?, None);
+        assert_eq!(
+            blob.compare_and_set("k0", None, values[0].clone()).await?,
+            Ok(())
+        );
+        assert_eq!(blob.get("k0").await?, Some(values[0].clone()));
+
+        // Now that it exists, expecting nothing fails and returns the current
+        // value.
+        assert_eq!(
+            blob.compare_and_set("k0", None, values[1].clone()).await?,
+            Err(Some(values[0].clone()))
+        );
+        assert_eq!(
+            blob.compare_and_set("k0", Some(&values[1]), values[1].clone())
+                .await?,
+            Err(Some(values[0].clone()))
+        );
+        assert_eq!(blob.get("k0").await?, Some(values[0].clone()));
+
+        // Expecting the current value succeeds.
+        assert_eq!(
+            blob.compare_and_set("k0", Some(&values[0]), values[1].clone())
+                .await?,
+            Ok(())
+        );
+        assert_eq!(blob.get("k0").await?, Some(values[1].clone()));
+
+        // A set in between is noticed.
+        blob.set("k0", values[0].clone(), RequireAtomic).await?;
+        assert_eq!(
+            blob.compare_and_set("k0", Some(&values[1]), values[1].clone())
+                .await?,
+            Err(Some(values[0].clone()))
+        );
+
+        // Other keys are unaffected.
+        assert_eq!(blob.get("k1").await?, None);
+
+        Ok(())
+    }
+
     #[test]
     pub fn lock_info() -> Result<(), Error> {
         // Invalid reentrance_id.
@@ -9,36 +9,70 @@
 
 //! Test utilities for injecting latency and errors.
 
+use std::collections::HashSet;
 use std::ops::Range;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
 
 use crate::error::Error;
 use crate::storage::{Atomicity, Blob, BlobRead, LockInfo, Log, SeqNo};
 
-#[derive(Debug)]
+/// The number of listings a deleted key keeps showing up in while storage is
+/// slow.
+const STALE_LISTINGS: usize = 2;
+
+#[derive(Debug, Default)]
 struct UnreliableCore {
     unavailable: bool,
-    // TODO: Delays, what else?
+    // While set, blob writes are randomly torn: a non-atomic set leaves a
+    // prefix of the value behind and an atomic one leaves nothing behind, but
+    // either way the write returns an error.
+    partial_writes: Option<SmallRng>,
+    // While set, every operation is delayed by a random amount up to the given
+    // maximum and deleted keys are only eventually removed from listings.
+    slow: Option<(SmallRng, Duration)>,
+    stale_deletes: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Default)]
+struct CrashCore {
+    // The number of writes to let through before crashing, if a crash is
+    // pending.
+    pending: Option<u64>,
+    crashed: bool,
 }
 
 /// A handle for controlling the behavior of an unreliable delegate.
 #[derive(Clone, Debug)]
 pub struct UnreliableHandle {
     core: Arc<Mutex<UnreliableCore>>,
+    crash: Arc<Mutex<CrashCore>>,
 }
 
 impl Default for UnreliableHandle {
     fn default() -> Self {
         UnreliableHandle {
-            core: Arc::new(Mutex::new(UnreliableCore { unavailable: false })),
+            core: Arc::new(Mutex::new(UnreliableCore::default())),
+            crash: Arc::new(Mutex::new(CrashCore::default())),
         }
     }
 }
 
 impl UnreliableHandle {
     fn check_unavailable(&self, details: &str) -> Result<(), Error> {
+        let crashed = self
+            .crash
+            .lock()
+            .expect("never panics while holding lock")
+            .crashed;
+        if crashed {
+            return Err(format!("crashed: {}", details).into());
+        }
         let unavailable = self
             .core
             .lock()
@@ -51,6 +85,64 @@ impl UnreliableHandle {
         }
     }
 
+    /// Counts a successful write towards a pending crash.
+    fn wrote(&self) {
+        let mut crash = self.crash.lock().expect("never panics while holding lock");
+        if let Some(pending) = crash.pending.as_mut() {
+            *pending = pending.saturating_sub(1);
+            if *pending == 0 {
+                crash.pending = None;
+                crash.crashed = true;
+            }
+        }
+    }
+
+    /// Sleeps for a random amount of time if storage is slow.
+    fn delay(&self) {
+        let delay = match self
+            .core
+            .lock()
+            .expect("never panics while holding lock")
+            .slow
+            .as_mut()
+        {
+            Some((rng, max)) => rng.gen_range(Duration::ZERO..=*max),
+            None => return,
+        };
+        thread::sleep(delay);
+    }
+
+    /// Returns the length of the prefix of a write of `len` bytes that should
+    /// be left behind, if the write should be torn.
+    fn torn_write(&self, len: usize) -> Option<usize> {
+        let mut core = self.core.lock().expect("never panics while holding lock");
+        let rng = core.partial_writes.as_mut()?;
+        if rng.gen_bool(0.5) {
+            Some(rng.gen_range(0..=len))
+        } else {
+            None
+        }
+    }
+
+    fn deleted(&self, key: &str) {
+        let mut core = self.core.lock().expect("never panics while holding lock");
+        if core.slow.is_some() {
+            core.stale_deletes.push((key.to_owned(), STALE_LISTINGS));
+        }
+    }
+
+    fn listed(&self, keys: &mut Vec<String>) {
+        let mut core = self.core.lock().expect("never panics while holding lock");
+        let listed: HashSet<_> = keys.iter().cloned().collect();
+        for (key, listings) in core.stale_deletes.iter_mut() {
+            if !listed.contains(key) {
+                keys.push(key.clone());
+            }
+            *listings -= 1;
+        }
+        core.stale_deletes.retain(|(_, listings)| *listings > 0);
+    }
+
     /// Cause all later operators to return an "unavailable" error.
     pub fn make_unavailable(&mut self) -> &mut UnreliableHandle {
         self.core
@@ -60,14 +152,86 @@ impl UnreliableHandle {
         self
     }
 
-    /// Cause all later operators to succeed.
+    /// Cause all later operators to succeed, clearing any partial writes or
+    /// slowness.
     pub fn make_available(&mut self) -> &mut UnreliableHandle {
+        let mut core = self.core.lock().expect("never panics while holding lock");
+        core.unavailable = false;
+        core.partial_writes = None;
+        core.slow = None;
+        core.stale_deletes.clear();
+        drop(core);
+        self
+    }
+
+    /// Cause later blob writes to be randomly torn, as decided by an rng
+    /// seeded with `seed`.
+    pub fn make_partial_writes(&mut self, seed: u64) -> &mut UnreliableHandle {
+        self.core
+            .lock()
+            .expect("never panics while holding lock")
+            .partial_writes = Some(SmallRng::seed_from_u64(seed));
+        self
+    }
+
+    /// Cause later operations to be delayed by up to `max_latency` and deleted
+    /// blob keys to keep showing up in listings for a while, as decided by an
+    /// rng seeded with `seed`.
+    pub fn make_slow(&mut self, seed: u64, max_latency: Duration) -> &mut UnreliableHandle {
         self.core
             .lock()
             .expect("never panics while holding lock")
-            .unavailable = false;
+            .slow = Some((SmallRng::seed_from_u64(seed), max_latency));
+        self
+    }
+
+    /// Crash after `writes` more writes have gone through, after which every
+    /// operation returns a "crashed" error.
+    ///
+    /// Only operations through this handle and its clones crash, see
+    /// [Self::fork]. Closing still releases any locks held by the delegates,
+    /// standing in for whatever would take over the locks of a crashed process.
+    pub fn crash_after(&mut self, writes: u64) -> &mut UnreliableHandle {
+        let mut crash = self.crash.lock().expect("never panics while holding lock");
+        if writes == 0 {
+            crash.pending = None;
+            crash.crashed = true;
+        } else {
+            crash.pending = Some(writes);
+        }
+        drop(crash);
+        self
+    }
+
+    /// Crash immediately if a crash is pending.
+    pub fn crash_if_pending(&mut self) -> &mut UnreliableHandle {
+        let mut crash = self.crash.lock().expect("never panics while holding lock");
+        if crash.pending.take().is_some() {
+            crash.crashed = true;
+        }
+        drop(crash);
         self
     }
+
+    /// Returns whether operations through this handle have crashed.
+    pub fn crashed(&self) -> bool {
+        self.crash
+            .lock()
+            .expect("never panics while holding lock")
+            .crashed
+    }
+
+    /// Returns a new handle sharing the availability, partial writes, and
+    /// slowness of this one, but which is not crashed.
+    ///
+    /// This models restarting a crashed process: the new process sees the same
+    /// storage, but none of the crashed one's state.
+    pub fn fork(&self) -> UnreliableHandle {
+        UnreliableHandle {
+            core: Arc::clone(&self.core),
+            crash: Arc::new(Mutex::new(CrashCore::default())),
+        }
+    }
 }
 
 /// An unreliable delegate to [Log].
@@ -93,21 +257,28 @@ impl<L: Log> UnreliableLog<L> {
 
 impl<L: Log> Log for UnreliableLog<L> {
     fn write_sync(&mut self, buf: Vec<u8>) -> Result<SeqNo, Error> {
+        self.handle.delay();
         self.handle.check_unavailable("log write")?;
-        self.log.write_sync(buf)
+        let seqno = self.log.write_sync(buf)?;
+        self.handle.wrote();
+        Ok(seqno)
     }
 
     fn snapshot<F>(&self, logic: F) -> Result<Range<SeqNo>, Error>
     where
         F: FnMut(SeqNo, &[u8]) -> Result<(), Error>,
     {
+        self.handle.delay();
         self.handle.check_unavailable("log snapshot")?;
         self.log.snapshot(logic)
     }
 
     fn truncate(&mut self, upper: SeqNo) -> Result<(), Error> {
+        self.handle.delay();
         self.handle.check_unavailable("log truncate")?;
-        self.log.truncate(upper)
+        self.log.truncate(upper)?;
+        self.handle.wrote();
+        Ok(())
     }
 
     fn close(&mut self) -> Result<bool, Error> {
@@ -153,13 +324,17 @@ impl<B: BlobRead> UnreliableBlob<B> {
 #[async_trait]
 impl<B: BlobRead + Sync> BlobRead for UnreliableBlob<B> {
     async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        self.handle.delay();
         self.handle.check_unavailable("blob get")?;
         self.blob.get(key).await
     }
 
     async fn list_keys(&self) -> Result<Vec<String>, Error> {
+        self.handle.delay();
         self.handle.check_unavailable("blob list keys")?;
-        self.blob.list_keys().await
+        let mut keys = self.blob.list_keys().await?;
+        self.handle.listed(&mut keys);
+        Ok(keys)
     }
 
     async fn close(&mut self) -> Result<bool, Error> {
@@ -172,6 +347,10 @@ impl<B: BlobRead + Sync> BlobRead for UnreliableBlob<B> {
         self.handle.check_unavailable("blob close")?;
         Ok(did_work)
     }
+
+    fn encryption_key_id(&self) -> Option<&str> {
+        self.blob.encryption_key_id()
+    }
 }
 
 #[async_trait]
@@ -200,20 +379,53 @@ where
     }
 
     async fn set(&mut self, key: &str, value: Vec<u8>, atomic: Atomicity) -> Result<(), Error> {
+        self.handle.delay();
         self.handle.check_unavailable("blob set")?;
-        self.blob.set(key, value, atomic).await
+        if let Some(torn_len) = self.handle.torn_write(value.len()) {
+            // An atomic write is never torn, it just fails.
+            if let Atomicity::AllowNonAtomic = atomic {
+                let mut value = value;
+                value.truncate(torn_len);
+                self.blob.set(key, value, atomic).await?;
+                self.handle.wrote();
+            }
+            return Err(format!("torn write: blob set {}", key).into());
+        }
+        self.blob.set(key, value, atomic).await?;
+        self.handle.wrote();
+        Ok(())
     }
 
     async fn delete(&mut self, key: &str) -> Result<(), Error> {
+        self.handle.delay();
         self.handle.check_unavailable("blob delete")?;
-        self.blob.delete(key).await
+        self.blob.delete(key).await?;
+        self.handle.deleted(key);
+        self.handle.wrote();
+        Ok(())
+    }
+
+    async fn compare_and_set(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: Vec<u8>,
+    ) -> Result<Result<(), Option<Vec<u8>>>, Error> {
+        self.handle.delay();
+        self.handle.check_unavailable("blob compare_and_set")?;
+        if self.handle.torn_write(value.len()).is_some() {
+            return Err(format!("torn write: blob compare_and_set {}", key).into());
+        }
+        let res = self.blob.compare_and_set(key, expected, value).await?;
+        self.handle.wrote();
+        Ok(res)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::mem::{MemBlob, MemLog};
-    use crate::storage::Atomicity::RequireAtomic;
+    use crate::storage::Atomicity::{AllowNonAtomic, RequireAtomic};
 
     use super::*;
 
@@ -257,4 +469,109 @@ mod tests {
         assert!(blob.set("a", b"3".to_vec(), RequireAtomic).await.is_ok());
         assert!(blob.get("a").await.is_ok());
     }
+
+    #[test]
+    fn log_crash() {
+        let (mut log, mut handle) = UnreliableLog::new(MemLog::new_no_reentrance("unreliable"));
+
+        // A crash only happens once the given number of writes have gone
+        // through, after which every operation fails.
+        handle.crash_after(2);
+        assert!(log.snapshot(|_, _| { Ok(()) }).is_ok());
+        assert!(log.write_sync(vec![]).is_ok());
+        assert!(log.write_sync(vec![]).is_ok());
+        assert!(log.snapshot(|_, _| { Ok(()) }).is_err());
+        assert!(log.write_sync(vec![]).is_err());
+
+        // Making storage available doesn't undo a crash.
+        handle.make_available();
+        assert!(log.write_sync(vec![]).is_err());
+
+        // A pending crash can be triggered early.
+        let mut handle = handle.fork();
+        let mut log = UnreliableLog::from_handle(log.log, handle.clone());
+        handle.crash_after(5);
+        assert!(log.write_sync(vec![]).is_ok());
+        handle.crash_if_pending();
+        assert!(log.write_sync(vec![]).is_err());
+    }
+
+    #[tokio::test]
+    async fn blob_crash() {
+        let (mut blob, mut handle) = UnreliableBlob::new(MemBlob::new_no_reentrance("unreliable"));
+
+        handle.crash_after(2);
+        assert!(blob.set("a", b"1".to_vec(), RequireAtomic).await.is_ok());
+        assert!(blob.set("a", b"2".to_vec(), RequireAtomic).await.is_ok());
+        assert!(blob.get("a").await.is_err());
+        assert!(blob.set("a", b"3".to_vec(), RequireAtomic).await.is_err());
+
+        // A fork of a crashed handle sees every write from before the crash.
+        let handle = handle.fork();
+        let blob = UnreliableBlob::from_handle(blob.blob, handle);
+        assert_eq!(blob.get("a").await.unwrap(), Some(b"2".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn blob_partial_writes() {
+        let (mut blob, mut handle) = UnreliableBlob::new(MemBlob::new_no_reentrance("unreliable"));
+        assert!(blob
+            .set("atomic", b"0".to_vec(), RequireAtomic)
+            .await
+            .is_ok());
+
+        // Some writes go through and the rest are torn. A torn non-atomic
+        // write leaves a prefix of its value behind and a torn atomic write
+        // leaves the previous value intact.
+        handle.make_partial_writes(0);
+        let value = b"0123456789".to_vec();
+        let (mut written, mut torn) = (0, 0);
+        let mut atomic = b"0".to_vec();
+        for idx in 0..100 {
+            let key = format!("non-atomic-{}", idx);
+            match blob.set(&key, value.clone(), AllowNonAtomic).await {
+                Ok(()) => {
+                    written += 1;
+                    assert_eq!(blob.get(&key).await.unwrap(), Some(value.clone()));
+                }
+                Err(_) => {
+                    torn += 1;
+                    let prefix = blob.get(&key).await.unwrap().expect("prefix was written");
+                    assert!(value.starts_with(&prefix));
+                }
+            }
+            let next = idx.to_string().into_bytes();
+            if blob
+                .set("atomic", next.clone(), RequireAtomic)
+                .await
+                .is_ok()
+            {
+                atomic = next;
+            }
+            assert_eq!(blob.get("atomic").await.unwrap(), Some(atomic.clone()));
+        }
+        assert!(written > 0 && torn > 0);
+
+        // Making storage available stops tearing writes.
+        handle.make_available();
+        for idx in 0..100 {
+            let key = format!("non-atomic-{}", idx);
+            assert!(blob.set(&key, value.clone(), AllowNonAtomic).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn blob_slow() {
+        let (mut blob, mut handle) = UnreliableBlob::new(MemBlob::new_no_reentrance("unreliable"));
+        handle.make_slow(0, Duration::from_millis(1));
+
+        // A deleted key keeps showing up in listings for a while.
+        assert!(blob.set("a", b"1".to_vec(), RequireAtomic).await.is_ok());
+        assert!(blob.delete("a").await.is_ok());
+        assert_eq!(blob.get("a").await.unwrap(), None);
+        for _ in 0..STALE_LISTINGS {
+            assert_eq!(blob.list_keys().await.unwrap(), vec!["a".to_owned()]);
+        }
+        assert_eq!(blob.list_keys().await.unwrap(), Vec::<String>::new());
+    }
 }
@@ -355,6 +355,9 @@ impl ErrorResponse {
             CoordError::InvalidParameterType(_) => SqlState::INVALID_PARAMETER_VALUE,
             CoordError::InvalidParameterValue { .. } => SqlState::INVALID_PARAMETER_VALUE,
             CoordError::InvalidTableMutationSelection => SqlState::INVALID_TRANSACTION_STATE,
+            CoordError::InvalidWebhookSignature(_) => SqlState::INVALID_AUTHORIZATION_SPECIFICATION,
+            CoordError::UnauthenticatedWebhook(_) => SqlState::INVALID_AUTHORIZATION_SPECIFICATION,
+            CoordError::UnmaterializedWebhook(_) => SqlState::OBJECT_NOT_IN_PREREQUISITE_STATE,
             CoordError::ConstraintViolation(NotNullViolation(_)) => SqlState::NOT_NULL_VIOLATION,
             CoordError::OperationProhibitsTransaction(_) => SqlState::ACTIVE_SQL_TRANSACTION,
             CoordError::OperationRequiresTransaction(_) => SqlState::NO_ACTIVE_SQL_TRANSACTION,
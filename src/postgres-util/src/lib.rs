@@ -207,10 +207,7 @@ pub async fn publication_info(
     conn: &str,
     publication: &str,
 ) -> Result<Vec<TableInfo>, anyhow::Error> {
-    let config = conn.parse()?;
-    let tls = make_tls(&config)?;
-    let (client, connection) = config.connect(tls).await?;
-    tokio::spawn(connection);
+    let client = connect(conn).await?;
 
     client
         .query(
@@ -375,6 +372,16 @@ pub async fn drop_replication_slots(conn: &str, slots: &[String]) -> Result<(),
     Ok(())
 }
 
+/// Starts a regular connection to the database described by the given
+/// connection string.
+pub async fn connect(conn: &str) -> Result<Client, anyhow::Error> {
+    let config: Config = conn.parse()?;
+    let tls = make_tls(&config)?;
+    let (client, connection) = config.connect(tls).await?;
+    tokio::spawn(connection);
+    Ok(client)
+}
+
 /// Starts a replication connection to the upstream database
 pub async fn connect_replication(conn: &str) -> Result<Client, anyhow::Error> {
     let mut config: Config = conn.parse()?;
@@ -36,6 +36,8 @@ use crate::Datum;
 
 mod encoding;
 
+pub use encoding::RowMigration;
+
 /// A packed representation for `Datum`s.
 ///
 /// `Datum` is easy to work with but very space inefficent. A `Datum::Int32(42)`
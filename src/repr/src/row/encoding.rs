@@ -27,7 +27,7 @@ use crate::gen::row::{
     ProtoArray, ProtoArrayDimension, ProtoDate, ProtoDatum, ProtoDatumOther, ProtoDict,
     ProtoDictElement, ProtoInterval, ProtoNumeric, ProtoRow, ProtoTime, ProtoTimestamp,
 };
-use crate::{Datum, Row};
+use crate::{Datum, Row, ScalarType};
 
 impl Codec for Row {
     fn codec_name() -> String {
@@ -296,6 +296,50 @@ impl TryFrom<&ProtoRow> for Row {
     }
 }
 
+/// A change to the columns of persisted [Row]s.
+///
+/// These are applied to rows written before the change when they are read
+/// back, e.g. by wrapping [RowMigration::apply] in a persist schema migration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RowMigration {
+    /// Appends a column that is null in every existing row.
+    AddNullableColumn,
+    /// Widens the numeric column at `index` to the type `to`.
+    WidenColumn {
+        /// The index of the column to widen.
+        index: usize,
+        /// The type to widen the column to.
+        to: ScalarType,
+    },
+}
+
+impl RowMigration {
+    /// Returns a copy of `row` with this migration applied.
+    pub fn apply(&self, row: &Row) -> Result<Row, String> {
+        let mut datums = row.unpack();
+        match self {
+            RowMigration::AddNullableColumn => datums.push(Datum::Null),
+            RowMigration::WidenColumn { index, to } => {
+                let len = datums.len();
+                let datum = datums.get_mut(*index).ok_or_else(|| {
+                    format!("cannot widen column {} of row with {} columns", index, len)
+                })?;
+                *datum = match (*datum, to) {
+                    (Datum::Null, _) => Datum::Null,
+                    (Datum::Int16(x), ScalarType::Int32) => Datum::Int32(i32::from(x)),
+                    (Datum::Int16(x), ScalarType::Int64) => Datum::Int64(i64::from(x)),
+                    (Datum::Int32(x), ScalarType::Int64) => Datum::Int64(i64::from(x)),
+                    (Datum::Float32(x), ScalarType::Float64) => {
+                        Datum::Float64(f64::from(*x).into())
+                    }
+                    (datum, to) => return Err(format!("cannot widen {:?} to {:?}", datum, to)),
+                };
+            }
+        }
+        Ok(Row::pack_slice(&datums))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
@@ -305,7 +349,9 @@ mod tests {
     use crate::adt::array::ArrayDimension;
     use crate::adt::interval::Interval;
     use crate::adt::numeric::Numeric;
-    use crate::{Datum, Row};
+    use crate::{Datum, Row, ScalarType};
+
+    use super::RowMigration;
 
     // TODO: datadriven golden tests for various interesting Datums and Rows to
     // catch any changes in the encoding.
@@ -376,4 +422,59 @@ mod tests {
         row.encode(&mut encoded);
         assert_eq!(Row::decode(&encoded), Ok(row));
     }
+
+    #[test]
+    fn row_migration() {
+        let row = Row::pack_slice(&[
+            Datum::Int16(1),
+            Datum::Int32(2),
+            Datum::Float32(3f32.into()),
+            Datum::Null,
+        ]);
+
+        assert_eq!(
+            RowMigration::AddNullableColumn.apply(&row),
+            Ok(Row::pack_slice(&[
+                Datum::Int16(1),
+                Datum::Int32(2),
+                Datum::Float32(3f32.into()),
+                Datum::Null,
+                Datum::Null,
+            ]))
+        );
+
+        let widen = |index, to| RowMigration::WidenColumn { index, to };
+        let widened = [
+            widen(0, ScalarType::Int64),
+            widen(1, ScalarType::Int64),
+            widen(2, ScalarType::Float64),
+            widen(3, ScalarType::Int64),
+        ]
+        .iter()
+        .try_fold(row.clone(), |row, migration| migration.apply(&row));
+        assert_eq!(
+            widened,
+            Ok(Row::pack_slice(&[
+                Datum::Int64(1),
+                Datum::Int64(2),
+                Datum::Float64(3f64.into()),
+                Datum::Null,
+            ]))
+        );
+        assert_eq!(
+            widen(0, ScalarType::Int32).apply(&row),
+            Ok(Row::pack_slice(&[
+                Datum::Int32(1),
+                Datum::Int32(2),
+                Datum::Float32(3f32.into()),
+                Datum::Null,
+            ]))
+        );
+
+        // Narrowing, changing to a non-numeric type, and out of range columns
+        // are all errors.
+        assert!(widen(1, ScalarType::Int16).apply(&row).is_err());
+        assert!(widen(1, ScalarType::String).apply(&row).is_err());
+        assert!(widen(4, ScalarType::Int64).apply(&row).is_err());
+    }
 }
@@ -148,12 +148,20 @@ impl_display_t!(CsrConnectorAvro);
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CsrConnectorProto<T: AstInfo> {
     pub url: String,
+    /// The fully qualified name of the message to decode, if the schema
+    /// registered for the topic declares more than one message.
+    pub message_name: Option<String>,
     pub seed: Option<CsrSeedCompiledOrLegacy>,
     pub with_options: Vec<SqlOption<T>>,
 }
 
 impl<T: AstInfo> AstDisplay for CsrConnectorProto<T> {
     fn fmt<W: fmt::Write>(&self, f: &mut AstFormatter<W>) {
+        if let Some(message_name) = &self.message_name {
+            f.write_str("MESSAGE '");
+            f.write_node(&display::escape_single_quote_string(message_name));
+            f.write_str("' ");
+        }
         f.write_str("USING CONFLUENT SCHEMA REGISTRY '");
         f.write_node(&display::escape_single_quote_string(&self.url));
         f.write_str("'");
@@ -280,16 +288,54 @@ impl_display_t!(CreateSourceFormat);
 pub enum Format<T: AstInfo> {
     Bytes,
     Avro(AvroSchema<T>),
+    /// `AVRO OCF`: Avro object container files, which embed their own
+    /// schema. Only supported by sinks.
+    AvroOcf,
     Protobuf(ProtobufSchema<T>),
     Regex(String),
     Csv {
         columns: CsvColumns,
         delimiter: char,
     },
-    Json,
+    Json {
+        schema: Option<JsonSchema<T>>,
+    },
+    /// `PARQUET (col type, ...)?`: `columns` is empty if no columns are
+    /// specified, in which case they are discovered during purification.
+    Parquet {
+        columns: Vec<ColumnDef<T>>,
+    },
     Text,
 }
 
+/// The typed columns to project out of each message of a `FORMAT JSON`
+/// source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JsonSchema<T: AstInfo> {
+    /// `SCHEMA '<json schema>'`
+    Inline(String),
+    /// `(col type, ...)`
+    Columns(Vec<ColumnDef<T>>),
+}
+
+impl<T: AstInfo> AstDisplay for JsonSchema<T> {
+    fn fmt<W: fmt::Write>(&self, f: &mut AstFormatter<W>) {
+        match self {
+            Self::Inline(schema) => {
+                f.write_str("SCHEMA '");
+                f.write_node(&display::escape_single_quote_string(schema));
+                f.write_str("'");
+            }
+            Self::Columns(columns) => {
+                f.write_str("(");
+                f.write_node(&display::comma_separated(columns));
+                f.write_str(")");
+            }
+        }
+    }
+}
+impl_display_t!(JsonSchema);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CsvColumns {
     /// `WITH count COLUMNS`
@@ -324,6 +370,8 @@ pub enum SourceIncludeMetadataType {
     Partition,
     Topic,
     Offset,
+    Headers,
+    Filename,
 }
 
 impl AstDisplay for SourceIncludeMetadataType {
@@ -334,6 +382,8 @@ impl AstDisplay for SourceIncludeMetadataType {
             SourceIncludeMetadataType::Partition => f.write_str("PARTITION"),
             SourceIncludeMetadataType::Topic => f.write_str("TOPIC"),
             SourceIncludeMetadataType::Offset => f.write_str("OFFSET"),
+            SourceIncludeMetadataType::Headers => f.write_str("HEADERS"),
+            SourceIncludeMetadataType::Filename => f.write_str("FILENAME"),
         }
     }
 }
@@ -394,6 +444,7 @@ impl<T: AstInfo> AstDisplay for Format<T> {
                 f.write_str("AVRO ");
                 f.write_node(inner);
             }
+            Self::AvroOcf => f.write_str("AVRO OCF"),
             Self::Protobuf(inner) => {
                 f.write_str("PROTOBUF ");
                 f.write_node(inner);
@@ -413,7 +464,21 @@ impl<T: AstInfo> AstDisplay for Format<T> {
                     f.write_str("'");
                 }
             }
-            Self::Json => f.write_str("JSON"),
+            Self::Json { schema } => {
+                f.write_str("JSON");
+                if let Some(schema) = schema {
+                    f.write_str(" ");
+                    f.write_node(schema);
+                }
+            }
+            Self::Parquet { columns } => {
+                f.write_str("PARQUET");
+                if !columns.is_empty() {
+                    f.write_str(" (");
+                    f.write_node(&display::comma_separated(columns));
+                    f.write_str(")");
+                }
+            }
             Self::Text => f.write_str("TEXT"),
         }
     }
@@ -463,11 +528,13 @@ pub enum CreateSourceConnector {
     },
     Kafka {
         broker: String,
-        topic: String,
+        topic: KafkaSourceTopic,
         key: Option<Vec<Ident>>,
     },
     Kinesis {
         arn: String,
+        /// The argument to the `START POSITION` clause, if present.
+        start_position: Option<KinesisStartPosition>,
     },
     /// Avro Object Container File
     AvroOcf {
@@ -494,6 +561,7 @@ pub enum CreateSourceConnector {
         /// The PubNub channel to subscribe to
         channel: String,
     },
+    Webhook,
 }
 
 impl AstDisplay for CreateSourceConnector {
@@ -510,19 +578,25 @@ impl AstDisplay for CreateSourceConnector {
                 f.write_str("KAFKA BROKER '");
                 f.write_node(&display::escape_single_quote_string(broker));
                 f.write_str("'");
-                f.write_str(" TOPIC '");
-                f.write_node(&display::escape_single_quote_string(topic));
-                f.write_str("'");
+                f.write_str(" TOPIC ");
+                f.write_node(topic);
                 if let Some(key) = key.as_ref() {
                     f.write_str(" KEY (");
                     f.write_node(&display::comma_separated(&key));
                     f.write_str(")");
                 }
             }
-            CreateSourceConnector::Kinesis { arn } => {
+            CreateSourceConnector::Kinesis {
+                arn,
+                start_position,
+            } => {
                 f.write_str("KINESIS ARN '");
                 f.write_node(&display::escape_single_quote_string(arn));
                 f.write_str("'");
+                if let Some(start_position) = start_position {
+                    f.write_str(" START POSITION ");
+                    f.write_node(start_position);
+                }
             }
             CreateSourceConnector::AvroOcf { path } => {
                 f.write_str("AVRO OCF '");
@@ -570,6 +644,7 @@ impl AstDisplay for CreateSourceConnector {
                 f.write_str(&display::escape_single_quote_string(channel));
                 f.write_str("'");
             }
+            CreateSourceConnector::Webhook => f.write_str("WEBHOOK"),
         }
     }
 }
@@ -580,6 +655,8 @@ impl<T: AstInfo> From<&CreateSinkConnector<T>> for ConnectorType {
         match connector {
             CreateSinkConnector::Kafka { .. } => ConnectorType::Kafka,
             CreateSinkConnector::AvroOcf { .. } => ConnectorType::AvroOcf,
+            CreateSinkConnector::S3 { .. } => ConnectorType::S3,
+            CreateSinkConnector::Postgres { .. } => ConnectorType::Postgres,
         }
     }
 }
@@ -591,10 +668,22 @@ pub enum CreateSinkConnector<T: AstInfo> {
         broker: String,
         topic: String,
         key: Option<KafkaSinkKey>,
+        /// An expression over the key columns that determines the partition
+        /// of each record.
+        partition_by: Option<Expr<T>>,
         consistency: Option<KafkaConsistency<T>>,
     },
     /// Avro Object Container File
     AvroOcf { path: String },
+    S3 {
+        bucket: String,
+        prefix: Option<String>,
+    },
+    Postgres {
+        conn: String,
+        table: String,
+        key: KafkaSinkKey,
+    },
 }
 
 impl<T: AstInfo> AstDisplay for CreateSinkConnector<T> {
@@ -604,6 +693,7 @@ impl<T: AstInfo> AstDisplay for CreateSinkConnector<T> {
                 broker,
                 topic,
                 key,
+                partition_by,
                 consistency,
             } => {
                 f.write_str("KAFKA BROKER '");
@@ -615,6 +705,10 @@ impl<T: AstInfo> AstDisplay for CreateSinkConnector<T> {
                 if let Some(key) = key.as_ref() {
                     f.write_node(key);
                 }
+                if let Some(partition_by) = partition_by.as_ref() {
+                    f.write_str(" PARTITION BY ");
+                    f.write_node(partition_by);
+                }
                 if let Some(consistency) = consistency.as_ref() {
                     f.write_node(consistency);
                 }
@@ -624,6 +718,24 @@ impl<T: AstInfo> AstDisplay for CreateSinkConnector<T> {
                 f.write_node(&display::escape_single_quote_string(path));
                 f.write_str("'");
             }
+            CreateSinkConnector::S3 { bucket, prefix } => {
+                f.write_str("S3 BUCKET '");
+                f.write_node(&display::escape_single_quote_string(bucket));
+                f.write_str("'");
+                if let Some(prefix) = prefix {
+                    f.write_str(" PREFIX '");
+                    f.write_node(&display::escape_single_quote_string(prefix));
+                    f.write_str("'");
+                }
+            }
+            CreateSinkConnector::Postgres { conn, table, key } => {
+                f.write_str("POSTGRES CONNECTION '");
+                f.write_node(&display::escape_single_quote_string(conn));
+                f.write_str("' TABLE '");
+                f.write_node(&display::escape_single_quote_string(table));
+                f.write_str("'");
+                f.write_node(key);
+            }
         }
     }
 }
@@ -692,6 +804,61 @@ impl<T: AstInfo> AstDisplay for PgTable<T> {
 }
 impl_display_t!(PgTable);
 
+/// The topics specified in a Kafka source's `TOPIC` clause.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KafkaSourceTopic {
+    /// `'<topic>' [, '<topic>' ...]`
+    Names(Vec<String>),
+    /// `PATTERN '<regex>'`
+    Pattern(String),
+}
+
+impl AstDisplay for KafkaSourceTopic {
+    fn fmt<W: fmt::Write>(&self, f: &mut AstFormatter<W>) {
+        match self {
+            KafkaSourceTopic::Names(names) => {
+                for (i, name) in names.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ");
+                    }
+                    f.write_str("'");
+                    f.write_node(&display::escape_single_quote_string(name));
+                    f.write_str("'");
+                }
+            }
+            KafkaSourceTopic::Pattern(pattern) => {
+                f.write_str("PATTERN '");
+                f.write_node(&display::escape_single_quote_string(pattern));
+                f.write_str("'");
+            }
+        }
+    }
+}
+impl_display!(KafkaSourceTopic);
+
+/// The position specified in a Kinesis source's `START POSITION` clause.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KinesisStartPosition {
+    /// `TRIM_HORIZON`
+    TrimHorizon,
+    /// `AT_TIMESTAMP '<timestamp>'`
+    AtTimestamp(String),
+}
+
+impl AstDisplay for KinesisStartPosition {
+    fn fmt<W: fmt::Write>(&self, f: &mut AstFormatter<W>) {
+        match self {
+            KinesisStartPosition::TrimHorizon => f.write_str("TRIM_HORIZON"),
+            KinesisStartPosition::AtTimestamp(timestamp) => {
+                f.write_str("AT_TIMESTAMP '");
+                f.write_node(&display::escape_single_quote_string(timestamp));
+                f.write_str("'");
+            }
+        }
+    }
+}
+impl_display!(KinesisStartPosition);
+
 /// The key sources specified in the S3 source's `DISCOVER OBJECTS` clause.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum S3KeySource {
@@ -1590,8 +1590,12 @@ impl<'a> Parser<'a> {
 
     fn parse_format(&mut self) -> Result<Format<Raw>, ParserError> {
         let format = if self.parse_keyword(AVRO) {
-            self.expect_keyword(USING)?;
-            Format::Avro(self.parse_avro_schema()?)
+            if self.parse_keyword(OCF) {
+                Format::AvroOcf
+            } else {
+                self.expect_keyword(USING)?;
+                Format::Avro(self.parse_avro_schema()?)
+            }
         } else if self.parse_keyword(PROTOBUF) {
             Format::Protobuf(self.parse_protobuf_schema()?)
         } else if self.parse_keyword(REGEX) {
@@ -1619,7 +1623,37 @@ impl<'a> Parser<'a> {
             };
             Format::Csv { columns, delimiter }
         } else if self.parse_keyword(JSON) {
-            Format::Json
+            let schema = if self.parse_keyword(SCHEMA) {
+                Some(JsonSchema::Inline(self.parse_literal_string()?))
+            } else if self.peek_token() == Some(Token::LParen) {
+                let (columns, constraints) = self.parse_columns(Mandatory)?;
+                if !constraints.is_empty() {
+                    return parser_err!(
+                        self,
+                        self.peek_prev_pos(),
+                        "FORMAT JSON column lists cannot contain constraints"
+                    );
+                }
+                Some(JsonSchema::Columns(columns))
+            } else {
+                None
+            };
+            Format::Json { schema }
+        } else if self.parse_keyword(PARQUET) {
+            let columns = if self.peek_token() == Some(Token::LParen) {
+                let (columns, constraints) = self.parse_columns(Mandatory)?;
+                if !constraints.is_empty() {
+                    return parser_err!(
+                        self,
+                        self.peek_prev_pos(),
+                        "FORMAT PARQUET column lists cannot contain constraints"
+                    );
+                }
+                columns
+            } else {
+                vec![]
+            };
+            Format::Parquet { columns }
         } else if self.parse_keyword(TEXT) {
             Format::Text
         } else if self.parse_keyword(BYTES) {
@@ -1627,7 +1661,7 @@ impl<'a> Parser<'a> {
         } else {
             return self.expected(
                 self.peek_pos(),
-                "AVRO, PROTOBUF, REGEX, CSV, JSON, TEXT, or BYTES",
+                "AVRO, PROTOBUF, REGEX, CSV, JSON, PARQUET, TEXT, or BYTES",
                 self.peek_token(),
             );
         };
@@ -1665,10 +1699,14 @@ impl<'a> Parser<'a> {
 
     fn parse_protobuf_schema(&mut self) -> Result<ProtobufSchema<Raw>, ParserError> {
         if self.parse_keywords(&[USING, CONFLUENT, SCHEMA, REGISTRY]) {
-            let csr_connector = self.parse_csr_connector_proto()?;
+            let csr_connector = self.parse_csr_connector_proto(None)?;
             Ok(ProtobufSchema::Csr { csr_connector })
         } else if self.parse_keyword(MESSAGE) {
             let message_name = self.parse_literal_string()?;
+            if self.parse_keywords(&[USING, CONFLUENT, SCHEMA, REGISTRY]) {
+                let csr_connector = self.parse_csr_connector_proto(Some(message_name))?;
+                return Ok(ProtobufSchema::Csr { csr_connector });
+            }
             self.expect_keyword(USING)?;
             let schema = self.parse_schema()?;
             Ok(ProtobufSchema::InlineSchema {
@@ -1719,7 +1757,10 @@ impl<'a> Parser<'a> {
         })
     }
 
-    fn parse_csr_connector_proto(&mut self) -> Result<CsrConnectorProto<Raw>, ParserError> {
+    fn parse_csr_connector_proto(
+        &mut self,
+        message_name: Option<String>,
+    ) -> Result<CsrConnectorProto<Raw>, ParserError> {
         let url = self.parse_literal_string()?;
 
         let seed = if self.parse_keyword(SEED) {
@@ -1775,6 +1816,7 @@ impl<'a> Parser<'a> {
 
         Ok(CsrConnectorProto {
             url,
+            message_name,
             seed,
             with_options,
         })
@@ -1825,6 +1867,29 @@ impl<'a> Parser<'a> {
         Ok(compression)
     }
 
+    fn parse_kinesis_start_position(&mut self) -> Result<KinesisStartPosition, ParserError> {
+        // The positions are spelled like the Kinesis shard iterator types
+        // they correspond to. Keywords cannot contain underscores, so they
+        // are matched as identifiers instead.
+        match self.peek_token() {
+            Some(Token::Ident(id)) if id == "trim_horizon" => {
+                self.next_token();
+                Ok(KinesisStartPosition::TrimHorizon)
+            }
+            Some(Token::Ident(id)) if id == "at_timestamp" => {
+                self.next_token();
+                Ok(KinesisStartPosition::AtTimestamp(
+                    self.parse_literal_string()?,
+                ))
+            }
+            _ => self.expected(
+                self.peek_pos(),
+                "TRIM_HORIZON or AT_TIMESTAMP",
+                self.peek_token(),
+            ),
+        }
+    }
+
     fn parse_create_source(&mut self) -> Result<Statement<Raw>, ParserError> {
         let materialized = self.parse_keyword(MATERIALIZED);
         self.expect_keyword(SOURCE)?;
@@ -1980,7 +2045,9 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_create_source_connector(&mut self) -> Result<CreateSourceConnector, ParserError> {
-        match self.expect_one_of_keywords(&[FILE, KAFKA, KINESIS, AVRO, S3, POSTGRES, PUBNUB])? {
+        match self
+            .expect_one_of_keywords(&[FILE, KAFKA, KINESIS, AVRO, S3, POSTGRES, PUBNUB, WEBHOOK])?
+        {
             PUBNUB => {
                 self.expect_keywords(&[SUBSCRIBE, KEY])?;
                 let subscribe_key = self.parse_literal_string()?;
@@ -1992,6 +2059,7 @@ impl<'a> Parser<'a> {
                     channel,
                 })
             }
+            WEBHOOK => Ok(CreateSourceConnector::Webhook),
             POSTGRES => {
                 self.expect_keyword(CONNECTION)?;
                 let conn = self.parse_literal_string()?;
@@ -2022,7 +2090,13 @@ impl<'a> Parser<'a> {
                 self.expect_keyword(BROKER)?;
                 let broker = self.parse_literal_string()?;
                 self.expect_keyword(TOPIC)?;
-                let topic = self.parse_literal_string()?;
+                let topic = if self.parse_keyword(PATTERN) {
+                    KafkaSourceTopic::Pattern(self.parse_literal_string()?)
+                } else {
+                    KafkaSourceTopic::Names(
+                        self.parse_comma_separated(Parser::parse_literal_string)?,
+                    )
+                };
                 // one token of lookahead:
                 // * `KEY (` means we're parsing a list of columns for the key
                 // * `KEY FORMAT` means there is no key, we'll parse a KeyValueFormat later
@@ -2039,7 +2113,15 @@ impl<'a> Parser<'a> {
             KINESIS => {
                 self.expect_keyword(ARN)?;
                 let arn = self.parse_literal_string()?;
-                Ok(CreateSourceConnector::Kinesis { arn })
+                let start_position = if self.parse_keywords(&[START, POSITION]) {
+                    Some(self.parse_kinesis_start_position()?)
+                } else {
+                    None
+                };
+                Ok(CreateSourceConnector::Kinesis {
+                    arn,
+                    start_position,
+                })
             }
             AVRO => {
                 self.expect_keyword(OCF)?;
@@ -2096,7 +2178,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_create_sink_connector(&mut self) -> Result<CreateSinkConnector<Raw>, ParserError> {
-        match self.expect_one_of_keywords(&[KAFKA, AVRO])? {
+        match self.expect_one_of_keywords(&[KAFKA, AVRO, S3, POSTGRES])? {
             KAFKA => {
                 self.expect_keyword(BROKER)?;
                 let broker = self.parse_literal_string()?;
@@ -2124,11 +2206,17 @@ impl<'a> Parser<'a> {
                 } else {
                     None
                 };
+                let partition_by = if self.parse_keywords(&[PARTITION, BY]) {
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
                 let consistency = self.parse_kafka_consistency()?;
                 Ok(CreateSinkConnector::Kafka {
                     broker,
                     topic,
                     key,
+                    partition_by,
                     consistency,
                 })
             }
@@ -2137,6 +2225,40 @@ impl<'a> Parser<'a> {
                 let path = self.parse_literal_string()?;
                 Ok(CreateSinkConnector::AvroOcf { path })
             }
+            S3 => {
+                // INTO S3 BUCKET '<bucket>' (PREFIX '<prefix>')?
+                self.expect_keyword(BUCKET)?;
+                let bucket = self.parse_literal_string()?;
+                let prefix = if self.parse_keyword(PREFIX) {
+                    Some(self.parse_literal_string()?)
+                } else {
+                    None
+                };
+                Ok(CreateSinkConnector::S3 { bucket, prefix })
+            }
+            POSTGRES => {
+                // INTO POSTGRES CONNECTION '<conn>' TABLE '<table>' KEY (<columns>)
+                self.expect_keyword(CONNECTION)?;
+                let conn = self.parse_literal_string()?;
+                self.expect_keyword(TABLE)?;
+                let table = self.parse_literal_string()?;
+                self.expect_keyword(KEY)?;
+                let key_columns = self.parse_parenthesized_column_list(Mandatory)?;
+                let not_enforced = if self.peek_keywords(&[NOT, ENFORCED]) {
+                    let _ = self.expect_keywords(&[NOT, ENFORCED])?;
+                    true
+                } else {
+                    false
+                };
+                Ok(CreateSinkConnector::Postgres {
+                    conn,
+                    table,
+                    key: KafkaSinkKey {
+                        key_columns,
+                        not_enforced,
+                    },
+                })
+            }
             _ => unreachable!(),
         }
     }
@@ -2388,14 +2510,16 @@ impl<'a> Parser<'a> {
     fn parse_source_include_metadata(&mut self) -> Result<Vec<SourceIncludeMetadata>, ParserError> {
         if self.parse_keyword(INCLUDE) {
             self.parse_comma_separated(|parser| {
-                let ty = match parser
-                    .expect_one_of_keywords(&[KEY, TIMESTAMP, PARTITION, TOPIC, OFFSET])?
-                {
+                let ty = match parser.expect_one_of_keywords(&[
+                    KEY, TIMESTAMP, PARTITION, TOPIC, OFFSET, HEADERS, FILENAME,
+                ])? {
                     KEY => SourceIncludeMetadataType::Key,
                     TIMESTAMP => SourceIncludeMetadataType::Timestamp,
                     PARTITION => SourceIncludeMetadataType::Partition,
                     TOPIC => SourceIncludeMetadataType::Topic,
                     OFFSET => SourceIncludeMetadataType::Offset,
+                    HEADERS => SourceIncludeMetadataType::Headers,
+                    FILENAME => SourceIncludeMetadataType::Filename,
                     _ => unreachable!("only explicitly allowed items can be parsed"),
                 };
                 let alias = parser
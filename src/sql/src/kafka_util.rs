@@ -15,18 +15,19 @@ use std::fs::File;
 use std::io::Read;
 use std::sync::{Arc, Mutex};
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 
 use kafka_util::client::MzClientContext;
 use rdkafka::client::ClientContext;
 use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext};
 use rdkafka::{Offset, TopicPartitionList};
+use regex::Regex;
 use reqwest::Url;
 use tokio::task;
 use tokio::time::Duration;
 
 use ccsr::tls::{Certificate, Identity};
-use sql_parser::ast::Value;
+use sql_parser::ast::{KafkaSourceTopic, Value};
 
 enum ValType {
     Path,
@@ -259,7 +260,7 @@ pub fn extract_config(
 ///   exist.
 pub async fn create_consumer(
     broker: &str,
-    topic: &str,
+    topic: Option<&str>,
     options: &BTreeMap<String, String>,
 ) -> Result<Arc<BaseConsumer<KafkaErrCheckContext>>, anyhow::Error> {
     let mut config = rdkafka::ClientConfig::new();
@@ -272,7 +273,7 @@ pub async fn create_consumer(
         Ok(consumer) => {
             let consumer: Arc<BaseConsumer<KafkaErrCheckContext>> = Arc::new(consumer);
             let context = consumer.context().clone();
-            let topic = String::from(topic);
+            let topic = topic.map(String::from);
             // Wait for a metadata request for up to one second. This greatly
             // increases the probability that we'll see a connection error if
             // e.g. the hostname was mistyped. librdkafka doesn't expose a
@@ -281,7 +282,7 @@ pub async fn create_consumer(
             task::spawn_blocking({
                 let consumer = consumer.clone();
                 move || {
-                    let _ = consumer.fetch_metadata(Some(&topic), Duration::from_secs(1));
+                    let _ = consumer.fetch_metadata(topic.as_deref(), Duration::from_secs(1));
                 }
             })
             .await?;
@@ -315,6 +316,54 @@ pub async fn create_consumer(
     }
 }
 
+/// Compiles a `TOPIC PATTERN` into a regex that must match entire topic names.
+pub fn topic_pattern_regex(pattern: &str) -> Result<Regex, anyhow::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map_err(|e| anyhow!("invalid Kafka topic pattern '{}': {}", pattern, e))
+}
+
+/// Returns the topic whose Confluent Schema Registry subjects describe the
+/// messages of a Kafka source.
+///
+/// All topics of a multi-topic source are expected to share a schema, so the
+/// first listed topic is used. For a topic pattern, the first matching topic
+/// in lexicographic order is used.
+pub async fn schema_registry_topic(
+    broker: &str,
+    topic: &KafkaSourceTopic,
+    options: &BTreeMap<String, String>,
+) -> Result<String, anyhow::Error> {
+    let pattern = match topic {
+        KafkaSourceTopic::Names(names) => match names.first() {
+            Some(name) => return Ok(name.clone()),
+            None => bail!("Kafka sources must specify at least one topic"),
+        },
+        KafkaSourceTopic::Pattern(pattern) => pattern.clone(),
+    };
+    let regex = topic_pattern_regex(&pattern)?;
+    let consumer = create_consumer(broker, None, options).await?;
+    let mut topics = task::spawn_blocking(move || {
+        let metadata = consumer.fetch_metadata(None, Duration::from_secs(10))?;
+        Ok::<_, anyhow::Error>(
+            metadata
+                .topics()
+                .iter()
+                .filter(|t| t.error().is_none() && regex.is_match(t.name()))
+                .map(|t| t.name().to_string())
+                .collect::<Vec<_>>(),
+        )
+    })
+    .await??;
+    topics.sort();
+    match topics.into_iter().next() {
+        Some(topic) => Ok(topic),
+        None => bail!(
+            "no Kafka topics match pattern '{}'; cannot determine schema registry subject",
+            pattern
+        ),
+    }
+}
+
 /// Returns start offsets for the partitions of `topic` and the provided
 /// `kafka_time_offset` option.
 ///
@@ -15,15 +15,17 @@
 //! [`ast`]: crate::ast
 
 use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context};
+use globset::{Glob, GlobBuilder};
 
 use dataflow_types::sources::{AwsConfig, AwsCredentials, SerdeUri};
 use repr::ColumnName;
 use sql_parser::ast::display::AstDisplay;
 use sql_parser::ast::visit_mut::{self, VisitMut};
 use sql_parser::ast::{
-    AstInfo, CreateIndexStatement, CreateSinkStatement, CreateSourceStatement,
+    AstInfo, CreateIndexStatement, CreateSinkConnector, CreateSinkStatement, CreateSourceStatement,
     CreateTableStatement, CreateTypeStatement, CreateViewStatement, Function, FunctionArgs, Ident,
     IfExistsBehavior, Query, Raw, SqlOption, Statement, TableFactor, TableFunction,
     UnresolvedObjectName, Value, ViewDefinition,
@@ -295,7 +297,7 @@ pub fn create_statement(
         Statement::CreateSink(CreateSinkStatement {
             name,
             from,
-            connector: _,
+            connector,
             with_options: _,
             format: _,
             envelope: _,
@@ -305,6 +307,17 @@ pub fn create_statement(
         }) => {
             *name = allocate_name(name)?;
             *from = resolve_item(from)?;
+            if let CreateSinkConnector::Kafka {
+                partition_by: Some(partition_by),
+                ..
+            } = connector
+            {
+                let mut normalizer = QueryNormalizer::new(scx);
+                normalizer.visit_expr_mut(partition_by);
+                if let Some(err) = normalizer.err {
+                    return Err(err.into());
+                }
+            }
             *if_not_exists = false;
         }
 
@@ -514,6 +527,37 @@ pub fn aws_config(
     })
 }
 
+/// Normalizes the path of a file source.
+///
+/// If the last component of `path` contains glob metacharacters, returns the
+/// directory that contains it along with the pattern that the names of the
+/// files to read must match. Otherwise, returns `path` itself.
+pub fn file_source_path(path: &str) -> Result<(PathBuf, Option<Glob>), anyhow::Error> {
+    let is_glob = |s: &str| s.contains(&['*', '?', '[', '{'][..]);
+    let path = Path::new(path);
+    let dir = match path.parent() {
+        Some(dir) if dir.as_os_str().is_empty() => Path::new("."),
+        Some(dir) => dir,
+        None => Path::new("/"),
+    };
+    if is_glob(&dir.to_string_lossy()) {
+        bail!(
+            "file source path {} may only contain a pattern in its last component",
+            path.display()
+        );
+    }
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if is_glob(name) => {
+            let pattern = GlobBuilder::new(name)
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()?;
+            Ok((dir.into(), Some(pattern)))
+        }
+        _ => Ok((path.into(), None)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -523,6 +567,27 @@ mod tests {
     use super::*;
     use crate::catalog::DummyCatalog;
 
+    #[test]
+    fn file_source_paths() -> Result<(), Box<dyn Error>> {
+        let (path, pattern) = file_source_path("/var/log/app.log")?;
+        assert_eq!(path, Path::new("/var/log/app.log"));
+        assert!(pattern.is_none());
+
+        let (path, pattern) = file_source_path("/var/log/app-*.log")?;
+        assert_eq!(path, Path::new("/var/log"));
+        let matcher = pattern.unwrap().compile_matcher();
+        assert!(matcher.is_match("app-2026-10-18.log"));
+        assert!(!matcher.is_match("app.log"));
+
+        let (path, pattern) = file_source_path("app.log.*")?;
+        assert_eq!(path, Path::new("."));
+        assert!(pattern.unwrap().compile_matcher().is_match("app.log.1"));
+
+        assert!(file_source_path("/var/log/*/app.log").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn normalized_create() -> Result<(), Box<dyn Error>> {
         let scx = &StatementContext::new(None, &DummyCatalog);
@@ -359,6 +359,9 @@ pub struct Table {
     pub defaults: Vec<Expr<Raw>>,
     pub temporary: bool,
     pub depends_on: Vec<GlobalId>,
+    /// How far behind the current time to retain the table's history, if at
+    /// all, so that it can be queried `AS OF` earlier times.
+    pub retain_history: Option<Duration>,
 }
 
 #[derive(Clone, Debug)]
@@ -376,7 +379,9 @@ pub struct Sink {
     pub create_sql: String,
     pub from: GlobalId,
     pub connector_builder: SinkConnectorBuilder,
-    pub envelope: SinkEnvelope,
+    /// The envelope of the sink, or `None` if the sink writes out the raw
+    /// updates itself.
+    pub envelope: Option<SinkEnvelope>,
     pub depends_on: Vec<GlobalId>,
 }
 
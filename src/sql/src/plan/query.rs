@@ -1128,6 +1128,40 @@ pub fn plan_index_exprs<'a>(
     Ok((out, qcx.ids.into_iter().collect()))
 }
 
+/// Plans the `PARTITION BY` expression of a Kafka sink, which is evaluated over
+/// the columns of the sink key and must be implicitly castable to `bigint`.
+pub fn plan_partition_by_expr(
+    scx: &StatementContext,
+    key_desc: &RelationDesc,
+    mut expr: Expr<Raw>,
+) -> Result<(::expr::MirScalarExpr, Vec<GlobalId>), PlanError> {
+    let scope = Scope::from_source(None, key_desc.iter_names());
+    let mut qcx = QueryContext::root(scx, QueryLifetime::Static);
+
+    transform_ast::transform_expr(scx, &mut expr)?;
+    let expr = resolve_names_expr(&mut qcx, expr)?;
+
+    let ecx = &ExprContext {
+        qcx: &qcx,
+        name: "PARTITION BY",
+        scope: &scope,
+        relation_type: key_desc.typ(),
+        allow_aggregates: false,
+        allow_subqueries: false,
+        allow_windows: false,
+    };
+    let expr = plan_expr(ecx, &expr)?.type_as_any(ecx)?;
+    let expr = typeconv::plan_cast(ecx, CastContext::Implicit, expr, &ScalarType::Int64)?;
+    let expr = expr.lower_uncorrelated()?;
+
+    // The partition of a record must not depend on when it is written.
+    if expr.contains_temporal() {
+        sql_bail!("PARTITION BY expressions cannot call mz_logical_timestamp()");
+    }
+
+    Ok((expr, qcx.ids.into_iter().collect()))
+}
+
 fn plan_expr_or_col_index(ecx: &ExprContext, e: &Expr<Aug>) -> Result<HirScalarExpr, PlanError> {
     match check_col_index(&ecx.name, e, ecx.relation_type.column_types.len())? {
         Some(column) => Ok(HirScalarExpr::column(column)),
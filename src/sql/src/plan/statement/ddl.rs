@@ -24,22 +24,25 @@ use itertools::Itertools;
 use regex::Regex;
 use reqwest::Url;
 use tracing::{debug, error};
+use uuid::Uuid;
 
 use dataflow_types::{
     sinks::{
         AvroOcfSinkConnectorBuilder, KafkaSinkConnectorBuilder, KafkaSinkConnectorRetention,
-        KafkaSinkFormat, SinkConnectorBuilder, SinkEnvelope,
+        KafkaSinkFormat, S3SinkConnectorBuilder, S3SinkFormat, S3SinkMode, SinkConnectorBuilder,
+        SinkEnvelope,
     },
     sources::{
         encoding::{
             included_column_desc, AvroEncoding, AvroOcfEncoding, ColumnSpec, CsvEncoding,
-            DataEncoding, ProtobufEncoding, RegexEncoding, SourceDataEncoding,
+            DataEncoding, JsonEncoding, ParquetEncoding, ProtobufEncoding, RegexEncoding,
+            SourceDataEncoding,
         },
         persistence::{BringYourOwn, Consistency},
         provide_default_metadata, DebeziumMode, ExternalSourceConnector, FileSourceConnector,
-        IncludedColumnPos, KafkaSourceConnector, KeyEnvelope, KinesisSourceConnector,
-        PostgresSourceConnector, PubNubSourceConnector, S3SourceConnector, SourceConnector,
-        SourceEnvelope, Timeline,
+        IncludedColumnPos, KafkaSourceConnector, KafkaTopics, KeyEnvelope, KinesisSourceConnector,
+        KinesisStartPosition, PostgresSourceConnector, PubNubSourceConnector, S3SourceConnector,
+        SourceConnector, SourceEnvelope, Timeline, WebhookSourceConnector, WebhookValidation,
     },
 };
 use expr::{func, GlobalId, MirRelationExpr, TableFunc, UnaryFunc};
@@ -52,16 +55,16 @@ use sql_parser::ast::{CsrSeedCompiledOrLegacy, SourceIncludeMetadata};
 
 use crate::ast::display::AstDisplay;
 use crate::ast::{
-    AlterIndexAction, AlterIndexStatement, AlterObjectRenameStatement, AvroSchema, ColumnOption,
-    Compression, CreateDatabaseStatement, CreateIndexStatement, CreateRoleOption,
+    AlterIndexAction, AlterIndexStatement, AlterObjectRenameStatement, AvroSchema, ColumnDef,
+    ColumnOption, Compression, CreateDatabaseStatement, CreateIndexStatement, CreateRoleOption,
     CreateRoleStatement, CreateSchemaStatement, CreateSinkConnector, CreateSinkStatement,
     CreateSourceConnector, CreateSourceFormat, CreateSourceStatement, CreateTableStatement,
     CreateTypeAs, CreateTypeStatement, CreateViewStatement, CreateViewsDefinitions,
     CreateViewsStatement, CsrConnectorAvro, CsrConnectorProto, CsrSeedCompiled, CsvColumns,
     DataType, DbzMode, DropDatabaseStatement, DropObjectsStatement, Envelope, Expr, Format, Ident,
-    IfExistsBehavior, KafkaConsistency, KeyConstraint, ObjectType, ProtobufSchema, Raw,
-    SourceIncludeMetadataType, SqlOption, Statement, TableConstraint, UnresolvedObjectName, Value,
-    ViewDefinition, WithOption,
+    IfExistsBehavior, JsonSchema, KafkaConsistency, KafkaSourceTopic, KeyConstraint, ObjectType,
+    ProtobufSchema, Raw, SourceIncludeMetadataType, SqlOption, Statement, TableConstraint,
+    UnresolvedObjectName, Value, ViewDefinition, WithOption,
 };
 use crate::catalog::{CatalogItem, CatalogItemType};
 use crate::kafka_util;
@@ -154,8 +157,17 @@ pub fn plan_create_table(
         temporary,
     } = &stmt;
 
+    let mut with_options = normalize::options(with_options);
+    let retain_history = match with_options.remove("retain_history") {
+        None => None,
+        Some(Value::String(s)) => Some(repr::util::parse_duration(&s)?),
+        Some(_) => bail!("retain_history must be a string"),
+    };
     if !with_options.is_empty() {
-        bail_unsupported!("WITH options");
+        bail!(
+            "unexpected parameters for CREATE TABLE: {}",
+            with_options.keys().join(",")
+        )
     }
 
     let names: Vec<_> = columns
@@ -263,6 +275,7 @@ pub fn plan_create_table(
         defaults,
         temporary,
         depends_on,
+        retain_history,
     };
     Ok(Plan::CreateTable(CreateTablePlan {
         name,
@@ -443,6 +456,16 @@ pub fn plan_create_source(
 
     let mut consistency = Consistency::RealTime;
 
+    // Only persisted tables can replay their history for AS OF queries.
+    // Replaying a source would mean rendering it again, which reads from the
+    // upstream system and writes to the source's persisted streams alongside
+    // the instance that is already running. Until persisted sources can be
+    // replayed read-only, say so rather than lumping this in with the unknown
+    // parameters below.
+    if with_options.contains_key("retain_history") {
+        bail_unsupported!("retain_history for sources");
+    }
+
     let ts_frequency = match with_options.remove("timestamp_frequency_ms") {
         Some(val) => match val {
             Value::Number(n) => match n.parse::<u64>() {
@@ -453,7 +476,11 @@ pub fn plan_create_source(
         },
         None => scx.catalog.config().timestamp_frequency,
     };
-    if !matches!(connector, CreateSourceConnector::Kafka { .. }) && !include_metadata.is_empty() {
+    if !matches!(
+        connector,
+        CreateSourceConnector::Kafka { .. } | CreateSourceConnector::File { .. }
+    ) && !include_metadata.is_empty()
+    {
         bail_unsupported!("INCLUDE metadata with non-Kafka sources");
     }
 
@@ -470,6 +497,29 @@ pub fn plan_create_source(
                 Some(_) => bail!("consistency_topic must be a string"),
             };
 
+            let topics = match topic {
+                KafkaSourceTopic::Names(names) => {
+                    let names: Vec<_> = names.iter().unique().cloned().collect();
+                    match names.len() {
+                        0 => bail!("Kafka sources must specify at least one topic"),
+                        1 => KafkaTopics::Single(names.into_element()),
+                        _ => KafkaTopics::List(names),
+                    }
+                }
+                KafkaSourceTopic::Pattern(pattern) => {
+                    let regex = kafka_util::topic_pattern_regex(pattern)?;
+                    KafkaTopics::Pattern(repr::adt::regex::Regex(regex))
+                }
+            };
+            if !matches!(topics, KafkaTopics::Single(_)) {
+                if with_options.contains_key("start_offset") {
+                    bail_unsupported!("start_offset with multiple Kafka topics");
+                }
+                if consistency != Consistency::RealTime {
+                    bail_unsupported!("consistency_topic with multiple Kafka topics");
+                }
+            }
+
             let group_id_prefix = match with_options.remove("group_id_prefix") {
                 None => None,
                 Some(Value::String(s)) => Some(s),
@@ -505,11 +555,11 @@ pub fn plan_create_source(
                 Some(v) => bail!("invalid start_offset value: {}", v),
             }
 
-            let encoding = get_encoding(format, envelope, with_options_original)?;
+            let encoding = get_encoding(scx, format, envelope, with_options_original)?;
 
             let mut connector = KafkaSourceConnector {
                 addrs: broker.parse()?,
-                topic: topic.clone(),
+                topics,
                 config_options,
                 start_offsets,
                 group_id_prefix,
@@ -518,6 +568,7 @@ pub fn plan_create_source(
                 include_partition: None,
                 include_topic: None,
                 include_offset: None,
+                include_headers: None,
             };
 
             let unwrap_name = |alias: Option<Ident>, default, pos| {
@@ -551,17 +602,18 @@ pub fn plan_create_source(
                         connector.include_partition = unwrap_name(item.alias, "partition", pos);
                     }
                     SourceIncludeMetadataType::Topic => {
-                        // TODO(bwm): This requires deeper thought, the current structure of the
-                        // code requires us to clone the topic name around all over the place
-                        // whether or not anyone ever uses it. Considering we expect the
-                        // overwhelming majority of people will *not* want topics in dataflows that
-                        // is an unnacceptable cost.
-                        bail_unsupported!("INCLUDE TOPIC");
+                        connector.include_topic = unwrap_name(item.alias, "topic", pos);
                     }
                     SourceIncludeMetadataType::Offset => {
                         connector.include_offset = unwrap_name(item.alias, "offset", pos);
                     }
+                    SourceIncludeMetadataType::Headers => {
+                        connector.include_headers = unwrap_name(item.alias, "headers", pos);
+                    }
                     SourceIncludeMetadataType::Key => {} // handled below
+                    SourceIncludeMetadataType::Filename => {
+                        bail_unsupported!("INCLUDE FILENAME with Kafka sources")
+                    }
                 }
             }
 
@@ -577,7 +629,10 @@ pub fn plan_create_source(
 
             (connector, encoding)
         }
-        CreateSourceConnector::Kinesis { arn, .. } => {
+        CreateSourceConnector::Kinesis {
+            arn,
+            start_position,
+        } => {
             let arn: ARN = arn
                 .parse()
                 .map_err(|e| anyhow!("Unable to parse provided ARN: {:#?}", e))?;
@@ -594,9 +649,21 @@ pub fn plan_create_source(
                 .ok_or_else(|| anyhow!("Provided ARN does not include an AWS region"))?;
 
             let aws = normalize::aws_config(&mut with_options, Some(region.into()))?;
-            let connector =
-                ExternalSourceConnector::Kinesis(KinesisSourceConnector { stream_name, aws });
-            let encoding = get_encoding(format, envelope, with_options_original)?;
+            let start_position = match start_position {
+                None | Some(sql_parser::ast::KinesisStartPosition::TrimHorizon) => {
+                    KinesisStartPosition::TrimHorizon
+                }
+                Some(sql_parser::ast::KinesisStartPosition::AtTimestamp(ts)) => {
+                    let ts = strconv::parse_timestamptz(ts)?;
+                    KinesisStartPosition::AtTimestamp(ts.timestamp_millis())
+                }
+            };
+            let connector = ExternalSourceConnector::Kinesis(KinesisSourceConnector {
+                stream_name,
+                aws,
+                start_position,
+            });
+            let encoding = get_encoding(scx, format, envelope, with_options_original)?;
             (connector, encoding)
         }
         CreateSourceConnector::File { path, compression } => {
@@ -610,18 +677,41 @@ pub fn plan_create_source(
                 Some(_) => bail!("BYO consistency not supported for file sources"),
             };
 
+            let mut include_filename = None;
+            for (pos, item) in include_metadata.iter().enumerate() {
+                match item.ty {
+                    SourceIncludeMetadataType::Filename => {
+                        include_filename = Some(IncludedColumnPos {
+                            name: item
+                                .alias
+                                .as_ref()
+                                .map(|a| a.to_string())
+                                .unwrap_or_else(|| String::from("filename")),
+                            pos,
+                        });
+                    }
+                    ty => bail_unsupported!(format!("INCLUDE {} with file sources", ty)),
+                }
+            }
+
+            let (path, pattern) = normalize::file_source_path(path)?;
             let connector = ExternalSourceConnector::File(FileSourceConnector {
-                path: path.clone().into(),
+                path,
+                pattern,
                 compression: match compression {
                     Compression::Gzip => dataflow_types::sources::Compression::Gzip,
                     Compression::None => dataflow_types::sources::Compression::None,
                 },
                 tail,
+                include_filename,
             });
-            let encoding = get_encoding(format, envelope, with_options_original)?;
+            let encoding = get_encoding(scx, format, envelope, with_options_original)?;
             if matches!(encoding, SourceDataEncoding::KeyValue { .. }) {
                 bail!("File sources do not support key decoding");
             }
+            if tail && matches!(encoding.value_ref(), DataEncoding::Parquet(_)) {
+                bail!("tail is not supported for FORMAT PARQUET");
+            }
             (connector, encoding)
         }
         CreateSourceConnector::S3 {
@@ -663,7 +753,7 @@ pub fn plan_create_source(
                     Compression::None => dataflow_types::sources::Compression::None,
                 },
             });
-            let encoding = get_encoding(format, envelope, with_options_original)?;
+            let encoding = get_encoding(scx, format, envelope, with_options_original)?;
             if matches!(encoding, SourceDataEncoding::KeyValue { .. }) {
                 bail!("S3 sources do not support key decoding");
             }
@@ -701,6 +791,38 @@ pub fn plan_create_source(
             });
             (connector, SourceDataEncoding::Single(DataEncoding::Text))
         }
+        CreateSourceConnector::Webhook => {
+            if with_options.contains_key("hmac_secret") {
+                bail!("hmac_secret is not supported, use hmac_secret_file instead");
+            }
+            let validation = match with_options.remove("hmac_secret_file") {
+                None => None,
+                Some(Value::String(secret_file)) => {
+                    let header = match with_options.remove("hmac_header") {
+                        None => "x-signature".into(),
+                        Some(Value::String(header)) => header.to_lowercase(),
+                        Some(_) => bail!("hmac_header must be a string"),
+                    };
+                    Some(WebhookValidation {
+                        secret_file,
+                        header,
+                    })
+                }
+                Some(_) => bail!("hmac_secret_file must be a string"),
+            };
+            let connector = ExternalSourceConnector::Webhook(WebhookSourceConnector { validation });
+            let encoding = get_encoding(scx, format, envelope, with_options_original)?;
+            match encoding {
+                SourceDataEncoding::Single(
+                    DataEncoding::Bytes | DataEncoding::Text | DataEncoding::Json(_),
+                ) => (),
+                _ => bail!("CREATE SOURCE ... WEBHOOK must specify FORMAT BYTES, TEXT, or JSON"),
+            }
+            if !matches!(envelope, Envelope::None) {
+                bail!("CREATE SOURCE ... WEBHOOK only supports ENVELOPE NONE");
+            }
+            (connector, encoding)
+        }
         CreateSourceConnector::AvroOcf { path, .. } => {
             let tail = match with_options.remove("tail") {
                 None => false,
@@ -722,8 +844,10 @@ pub fn plan_create_source(
 
             let connector = ExternalSourceConnector::AvroOcf(FileSourceConnector {
                 path: path.clone().into(),
+                pattern: None,
                 compression: dataflow_types::sources::Compression::None,
                 tail,
+                include_filename: None,
             });
             if !matches!(format, CreateSourceFormat::None) {
                 bail!("avro ocf sources cannot specify a format");
@@ -741,6 +865,17 @@ pub fn plan_create_source(
             (connector, encoding)
         }
     };
+    // Parquet files can only be decoded once they have been read in full,
+    // which only file and S3 sources guarantee.
+    let is_parquet = |e: &DataEncoding| matches!(e, DataEncoding::Parquet(_));
+    if (is_parquet(encoding.value_ref()) || encoding.key_ref().map_or(false, is_parquet))
+        && !matches!(
+            connector,
+            CreateSourceConnector::File { .. } | CreateSourceConnector::S3 { .. }
+        )
+    {
+        bail_unsupported!("FORMAT PARQUET with non-file or S3 sources");
+    }
     let key_envelope = get_key_envelope(&include_metadata, envelope, &encoding)?;
 
     // TODO (materialize#2537): cleanup format validation
@@ -766,13 +901,16 @@ pub fn plan_create_source(
         }
         sql_parser::ast::Envelope::Debezium(mode) => {
             //TODO check that key envelope is not set
-            let is_avro = match encoding.value_ref() {
-                DataEncoding::Avro(_) => true,
-                DataEncoding::AvroOcf(_) => true,
-                _ => false,
-            };
-            if !is_avro {
-                bail!("non-Avro Debezium sources are not supported");
+            match encoding.value_ref() {
+                DataEncoding::Avro(_) | DataEncoding::AvroOcf(_) => (),
+                DataEncoding::Json(JsonEncoding {
+                    columns: Some(_), ..
+                }) => (),
+                DataEncoding::Json(JsonEncoding { columns: None, .. }) => bail!(
+                    "FORMAT JSON ENVELOPE DEBEZIUM requires the columns of the upstream table, \
+                     as in FORMAT JSON (col type, ...) or FORMAT JSON SCHEMA"
+                ),
+                _ => bail!("non-Avro, non-JSON Debezium sources are not supported"),
             }
             let dedup_strat = match with_options.remove("deduplication") {
                 None => match mode {
@@ -1018,19 +1156,20 @@ pub fn plan_create_source(
 }
 
 fn get_encoding<T: sql_parser::ast::AstInfo>(
+    scx: &StatementContext,
     format: &CreateSourceFormat<Raw>,
     envelope: &Envelope,
     with_options: &Vec<SqlOption<T>>,
 ) -> Result<SourceDataEncoding, anyhow::Error> {
-    let encoding = match format {
+    let mut encoding = match format {
         CreateSourceFormat::None => bail!("Source format must be specified"),
-        CreateSourceFormat::Bare(format) => get_encoding_inner(format, with_options)?,
+        CreateSourceFormat::Bare(format) => get_encoding_inner(scx, format, with_options)?,
         CreateSourceFormat::KeyValue { key, value } => {
-            let key = match get_encoding_inner(key, with_options)? {
+            let key = match get_encoding_inner(scx, key, with_options)? {
                 SourceDataEncoding::Single(key) => key,
                 SourceDataEncoding::KeyValue { key, .. } => key,
             };
-            let value = match get_encoding_inner(value, with_options)? {
+            let value = match get_encoding_inner(scx, value, with_options)? {
                 SourceDataEncoding::Single(value) => value,
                 SourceDataEncoding::KeyValue { value, .. } => value,
             };
@@ -1047,16 +1186,30 @@ fn get_encoding<T: sql_parser::ast::AstInfo>(
         bail!("ENVELOPE [DEBEZIUM] UPSERT requires that KEY FORMAT be specified");
     };
 
+    // JSON-encoded Debezium values are change events whose `before` and `after`
+    // fields hold the declared columns, rather than rows of those columns.
+    if let Envelope::Debezium(_) = envelope {
+        let value = match &mut encoding {
+            SourceDataEncoding::Single(value) => value,
+            SourceDataEncoding::KeyValue { value, .. } => value,
+        };
+        if let DataEncoding::Json(json) = value {
+            json.debezium = true;
+        }
+    }
+
     Ok(encoding)
 }
 
 fn get_encoding_inner<T: sql_parser::ast::AstInfo>(
+    scx: &StatementContext,
     format: &Format<Raw>,
     with_options: &Vec<SqlOption<T>>,
 ) -> Result<SourceDataEncoding, anyhow::Error> {
     // Avro/CSR can return a `SourceDataEncoding::KeyValue`
     Ok(SourceDataEncoding::Single(match format {
         Format::Bytes => DataEncoding::Bytes,
+        Format::AvroOcf => bail!("FORMAT AVRO OCF is only supported by sinks"),
         Format::Avro(schema) => {
             let Schema {
                 key_schema,
@@ -1207,11 +1360,103 @@ fn get_encoding_inner<T: sql_parser::ast::AstInfo>(
                 },
             })
         }
-        Format::Json => bail_unsupported!("JSON sources"),
+        Format::Json { schema } => {
+            let columns = match schema {
+                None => None,
+                Some(JsonSchema::Inline(schema)) => {
+                    Some(interchange::json::json_schema_to_columns(schema)?)
+                }
+                Some(JsonSchema::Columns(columns)) => {
+                    Some(plan_format_columns(scx, "JSON", columns, |ty| {
+                        matches!(
+                            ty,
+                            ScalarType::Bool
+                                | ScalarType::Int16
+                                | ScalarType::Int32
+                                | ScalarType::Int64
+                                | ScalarType::Float32
+                                | ScalarType::Float64
+                                | ScalarType::Numeric { .. }
+                                | ScalarType::String
+                                | ScalarType::Date
+                                | ScalarType::Timestamp
+                                | ScalarType::TimestampTz
+                                | ScalarType::Uuid
+                                | ScalarType::Jsonb
+                        )
+                    })?)
+                }
+            };
+            DataEncoding::Json(JsonEncoding {
+                columns,
+                debezium: false,
+            })
+        }
+        Format::Parquet { columns } => {
+            if columns.is_empty() {
+                bail!("[internal error] FORMAT PARQUET columns should be discovered in purify")
+            }
+            let columns = plan_format_columns(scx, "PARQUET", columns, |ty| {
+                matches!(
+                    ty,
+                    ScalarType::Bool
+                        | ScalarType::Int16
+                        | ScalarType::Int32
+                        | ScalarType::Int64
+                        | ScalarType::Float32
+                        | ScalarType::Float64
+                        | ScalarType::Numeric { .. }
+                        | ScalarType::String
+                        | ScalarType::Bytes
+                        | ScalarType::Date
+                        | ScalarType::Timestamp
+                        | ScalarType::TimestampTz
+                )
+            })?;
+            DataEncoding::Parquet(ParquetEncoding { columns })
+        }
         Format::Text => DataEncoding::Text,
     }))
 }
 
+/// Plans the typed columns of a `FORMAT <format> (col type, ...)` source,
+/// admitting only the types for which `supported` returns true.
+fn plan_format_columns(
+    scx: &StatementContext,
+    format: &str,
+    columns: &[ColumnDef<Raw>],
+    supported: impl Fn(&ScalarType) -> bool,
+) -> Result<Vec<(ColumnName, ColumnType)>, anyhow::Error> {
+    let mut planned = Vec::with_capacity(columns.len());
+    for c in columns {
+        let (aug_data_type, _) = resolve_names_data_type(scx, c.data_type.clone())?;
+        let ty = plan::scalar_type_from_sql(scx, &aug_data_type)?;
+        if !supported(&ty) {
+            bail_unsupported!(format!(
+                "FORMAT {} columns of type {}",
+                format,
+                scx.humanize_scalar_type(&ty)
+            ));
+        }
+        let mut nullable = true;
+        for option in &c.options {
+            match &option.option {
+                ColumnOption::NotNull => nullable = false,
+                ColumnOption::Null => nullable = true,
+                other => bail_unsupported!(format!("FORMAT {} column option {}", format, other)),
+            }
+        }
+        planned.push((
+            normalize::column_name(c.name.clone()),
+            ty.nullable(nullable),
+        ));
+    }
+    if let Some(dup) = planned.iter().map(|(name, _)| name).duplicates().next() {
+        bail!("column {} specified more than once", dup.as_str().quoted());
+    }
+    Ok(planned)
+}
+
 /// Extract the key envelope, if it is requested
 fn get_key_envelope(
     included_items: &[SourceIncludeMetadata],
@@ -1234,11 +1479,16 @@ fn get_key_envelope(
                 // Otherwise it gets the names of the columns in the type
                 if let SourceDataEncoding::KeyValue { key, value: _ } = encoding {
                     let is_composite = match key {
-                        DataEncoding::AvroOcf { .. } | DataEncoding::Postgres => {
+                        DataEncoding::AvroOcf { .. }
+                        | DataEncoding::Parquet(_)
+                        | DataEncoding::Postgres => {
                             bail!("{} sources cannot use INCLUDE KEY", key.op_name())
                         }
-                        DataEncoding::Bytes | DataEncoding::Text => false,
+                        DataEncoding::Bytes
+                        | DataEncoding::Text
+                        | DataEncoding::Json(JsonEncoding { columns: None, .. }) => false,
                         DataEncoding::Avro(_)
+                        | DataEncoding::Json(_)
                         | DataEncoding::Csv(_)
                         | DataEncoding::Protobuf(_)
                         | DataEncoding::Regex { .. } => true,
@@ -1407,7 +1657,9 @@ fn kafka_sink_builder(
     topic_prefix: String,
     relation_key_indices: Option<Vec<usize>>,
     key_desc_and_indices: Option<(RelationDesc, Vec<usize>)>,
+    partition_by: Option<::expr::MirScalarExpr>,
     value_desc: RelationDesc,
+    envelope: SinkEnvelope,
     topic_suffix_nonce: String,
     root_dependencies: &[&dyn CatalogItem],
 ) -> Result<SinkConnectorBuilder, anyhow::Error> {
@@ -1426,8 +1678,28 @@ fn kafka_sink_builder(
         None => false,
         Some(_) => bail!("reuse_topic must be a boolean"),
     };
+    // Sinks that reuse their topic are exactly-once unless asked not to be,
+    // which lifts the requirement that their inputs be replayable sources.
+    let exactly_once = match with_options.remove("exactly_once") {
+        Some(Value::Boolean(b)) => b,
+        None => reuse_topic,
+        Some(_) => bail!("exactly_once must be a boolean"),
+    };
+    if exactly_once && !reuse_topic {
+        bail!("exactly_once requires reuse_topic");
+    }
     let config_options = kafka_util::extract_config(with_options)?;
 
+    if envelope == SinkEnvelope::CdcV2 {
+        // CDCv2 sinks write their own progress statements to the data topic.
+        if reuse_topic || consistency_topic.is_some() || consistency.is_some() {
+            bail!("ENVELOPE MATERIALIZE sinks do not support consistency topics");
+        }
+        if key_desc_and_indices.is_some() {
+            bail!("ENVELOPE MATERIALIZE sinks do not support KEY");
+        }
+    }
+
     let avro_key_fullname = match with_options.remove("avro_key_fullname") {
         Some(Value::String(s)) => Some(s),
         None => None,
@@ -1481,7 +1753,15 @@ fn kafka_sink_builder(
                 value_desc.clone(),
                 include_transaction,
             );
-            let value_schema = schema_generator.value_writer_schema().to_string();
+            let value_schema = match envelope {
+                SinkEnvelope::CdcV2 => {
+                    if avro_value_fullname.is_some() {
+                        bail!("ENVELOPE MATERIALIZE sinks do not support avro_value_fullname");
+                    }
+                    avro::cdc_v2::build_writer_schema(value_desc.clone()).to_string()
+                }
+                _ => schema_generator.value_writer_schema().to_string(),
+            };
             let key_schema = schema_generator
                 .key_writer_schema()
                 .map(|key_schema| key_schema.to_string());
@@ -1493,7 +1773,13 @@ fn kafka_sink_builder(
                 ccsr_config,
             }
         }
-        Some(Format::Json) => KafkaSinkFormat::Json,
+        Some(Format::Json { schema: None }) if envelope == SinkEnvelope::CdcV2 => {
+            bail_unsupported!("non-Avro-encoded ENVELOPE MATERIALIZE sinks")
+        }
+        Some(Format::Json { schema: None }) => KafkaSinkFormat::Json,
+        Some(Format::Json { schema: Some(_) }) => {
+            bail!("FORMAT JSON with a schema is only supported for sources")
+        }
         Some(format) => bail_unsupported!(format!("sink format {:?}", format)),
         None => bail_unsupported!("sink without format"),
     };
@@ -1509,7 +1795,7 @@ fn kafka_sink_builder(
 
     let broker_addrs = broker.parse()?;
 
-    let transitive_source_dependencies: Vec<_> = if reuse_topic {
+    let transitive_source_dependencies: Vec<_> = if exactly_once {
         for item in root_dependencies.iter() {
             if item.item_type() == CatalogItemType::Source {
                 if !item.source_connector()?.yields_stable_input() {
@@ -1576,9 +1862,30 @@ fn kafka_sink_builder(
     if retention_bytes.unwrap_or(0) < -1 {
         bail!("retention bytes for sink topics must be greater than or equal to -1");
     }
+
+    let compact = match with_options.remove("cleanup_policy") {
+        None => false,
+        Some(Value::String(policy)) => match policy.as_str() {
+            "delete" => false,
+            "compact" => true,
+            _ => bail!(
+                "cleanup policy for sink topics must be 'delete' or 'compact', not '{}'",
+                policy
+            ),
+        },
+        Some(_) => bail!("cleanup policy for sink topics must be a string"),
+    };
+
+    // Compaction keeps only the latest record for each key, which is only
+    // the full state of the sinked relation if each record is an upsert.
+    if compact && envelope != SinkEnvelope::Upsert {
+        bail!("cleanup policy 'compact' requires ENVELOPE UPSERT");
+    }
+
     let retention = KafkaSinkConnectorRetention {
         retention_ms,
         retention_bytes,
+        compact,
     };
 
     let consistency_topic = consistency_config.clone().map(|config| config.0);
@@ -1590,6 +1897,7 @@ fn kafka_sink_builder(
         topic_prefix,
         consistency_topic_prefix: consistency_topic,
         consistency_format,
+        partition_by,
         topic_suffix_nonce,
         partition_count,
         replication_factor,
@@ -1599,6 +1907,7 @@ fn kafka_sink_builder(
         key_desc_and_indices,
         value_desc,
         reuse_topic,
+        exactly_once,
         transitive_source_dependencies,
         retention,
     }))
@@ -1653,49 +1962,49 @@ fn get_kafka_sink_consistency_config(
                     },
                 ))
             }
+            Some(Format::Json { schema: None }) => Some((topic, KafkaSinkFormat::Json)),
+            Some(Format::Json { schema: Some(_) }) => {
+                bail!("CONSISTENCY FORMAT JSON with a schema is not supported")
+            }
             None => {
                 // If a CONSISTENCY FORMAT is not provided, default to the FORMAT of the sink.
                 match sink_format {
                     format @ KafkaSinkFormat::Avro { .. } => Some((topic, format.clone())),
-                    KafkaSinkFormat::Json => bail_unsupported!("CONSISTENCY FORMAT JSON"),
+                    KafkaSinkFormat::Json => Some((topic, KafkaSinkFormat::Json)),
                 }
             }
             Some(other) => bail_unsupported!(format!("CONSISTENCY FORMAT {}", &other)),
         },
         None => {
-            // Support use of `consistency_topic` with option if the sink is Avro-formatted
-            // for backwards compatibility.
+            // Support use of `consistency_topic` with option for backwards compatibility. The
+            // consistency topic uses the format of the sink.
             if reuse_topic | consistency_topic.is_some() {
+                let consistency_topic = match consistency_topic {
+                    Some(topic) => topic,
+                    None => {
+                        let default_consistency_topic = format!("{}-consistency", topic_prefix);
+                        debug!(
+                            "Using default consistency topic '{}' for topic '{}'",
+                            default_consistency_topic, topic_prefix
+                        );
+                        default_consistency_topic
+                    }
+                };
                 match sink_format {
                     KafkaSinkFormat::Avro {
                         schema_registry_url,
                         ccsr_config,
                         ..
-                    } => {
-                        let consistency_topic = match consistency_topic {
-                            Some(topic) => topic,
-                            None => {
-                                let default_consistency_topic =
-                                    format!("{}-consistency", topic_prefix);
-                                debug!(
-                                    "Using default consistency topic '{}' for topic '{}'",
-                                    default_consistency_topic, topic_prefix
-                                );
-                                default_consistency_topic
-                            }
-                        };
-                        Some((
-                            consistency_topic,
-                            KafkaSinkFormat::Avro {
-                                schema_registry_url: schema_registry_url.clone(),
-                                key_schema: None,
-                                value_schema: avro::get_debezium_transaction_schema()
-                                    .canonical_form(),
-                                ccsr_config: ccsr_config.clone(),
-                            },
-                        ))
-                    }
-                    KafkaSinkFormat::Json => bail!("For FORMAT JSON, you need to manually specify an Avro consistency topic using 'CONSISTENCY TOPIC consistency_topic CONSISTENCY FORMAT AVRO USING CONFLUENT SCHEMA REGISTRY url'. The default of using a JSON consistency topic is not supported."),
+                    } => Some((
+                        consistency_topic,
+                        KafkaSinkFormat::Avro {
+                            schema_registry_url: schema_registry_url.clone(),
+                            key_schema: None,
+                            value_schema: avro::get_debezium_transaction_schema().canonical_form(),
+                            ccsr_config: ccsr_config.clone(),
+                        },
+                    )),
+                    KafkaSinkFormat::Json => Some((consistency_topic, KafkaSinkFormat::Json)),
                 }
             } else {
                 None
@@ -1729,6 +2038,141 @@ fn avro_ocf_sink_builder(
     }))
 }
 
+fn s3_sink_builder(
+    format: Option<Format<Raw>>,
+    with_options: &mut BTreeMap<String, Value>,
+    bucket: String,
+    prefix: Option<String>,
+    prefix_suffix_nonce: String,
+    value_desc: RelationDesc,
+) -> Result<SinkConnectorBuilder, anyhow::Error> {
+    let format = match format {
+        Some(Format::Parquet { columns }) if columns.is_empty() => S3SinkFormat::Parquet,
+        Some(Format::Parquet { .. }) => bail!("S3 sinks cannot specify FORMAT PARQUET columns"),
+        Some(Format::AvroOcf) => S3SinkFormat::AvroOcf,
+        Some(_) => bail_unsupported!("S3 sinks with formats other than PARQUET or AVRO OCF"),
+        None => bail!("S3 sinks must specify FORMAT PARQUET or FORMAT AVRO OCF"),
+    };
+
+    let mode = match with_options.remove("mode") {
+        None => S3SinkMode::Snapshot,
+        Some(Value::String(mode)) => match mode.as_str() {
+            "snapshot" => S3SinkMode::Snapshot,
+            "changes" => S3SinkMode::Changes,
+            _ => bail!(
+                "mode must be 'snapshot' or 'changes', got {}",
+                mode.quoted()
+            ),
+        },
+        Some(_) => bail!("mode must be a string"),
+    };
+
+    let interval = match with_options.remove("interval_ms") {
+        None => 60_000,
+        Some(Value::Number(n)) => match n.parse::<u64>() {
+            Ok(n) if n > 0 => n,
+            _ => bail!("interval_ms must be a positive integer"),
+        },
+        Some(_) => bail!("interval_ms must be a positive integer"),
+    };
+
+    if mode == S3SinkMode::Changes {
+        for name in ["mz_timestamp", "mz_diff"] {
+            if value_desc.get_by_name(&ColumnName::from(name)).is_some() {
+                bail!(
+                    "S3 sinks in changes mode cannot write a column named {}",
+                    name.quoted()
+                );
+            }
+        }
+    }
+
+    if format == S3SinkFormat::Parquet {
+        for (name, typ) in value_desc.iter() {
+            if !matches!(
+                typ.scalar_type,
+                ScalarType::Bool
+                    | ScalarType::Int16
+                    | ScalarType::Int32
+                    | ScalarType::Int64
+                    | ScalarType::Float32
+                    | ScalarType::Float64
+                    | ScalarType::Numeric { .. }
+                    | ScalarType::String
+                    | ScalarType::Bytes
+                    | ScalarType::Date
+                    | ScalarType::Timestamp
+                    | ScalarType::TimestampTz
+            ) {
+                bail_unsupported!(format!(
+                    "column {} of type {:?} in Parquet S3 sinks",
+                    name.as_str().quoted(),
+                    typ.scalar_type
+                ));
+            }
+        }
+    }
+
+    let aws = normalize::aws_config(with_options, None)?;
+
+    Ok(SinkConnectorBuilder::S3(S3SinkConnectorBuilder {
+        bucket,
+        prefix: prefix
+            .map(|p| p.trim_end_matches('/').to_owned())
+            .unwrap_or_default(),
+        prefix_suffix_nonce,
+        aws,
+        format,
+        mode,
+        interval,
+        value_desc,
+    }))
+}
+
+fn postgres_sink_builder(
+    format: Option<Format<Raw>>,
+    conn: String,
+    table: String,
+    key_indices: Vec<usize>,
+    value_desc: RelationDesc,
+    cluster_id: Uuid,
+    root_dependencies: &[&dyn CatalogItem],
+) -> Result<SinkConnectorBuilder, anyhow::Error> {
+    if format.is_some() {
+        bail!("Postgres sinks cannot specify a format");
+    }
+
+    // Postgres sinks pick up where they left off after a restart, which is
+    // only correct if their inputs replay the same updates at the same
+    // timestamps.
+    for item in root_dependencies.iter() {
+        if item.item_type() == CatalogItemType::Source {
+            if !item.source_connector()?.yields_stable_input() {
+                bail!(
+                    "Postgres sinks require that sink input dependencies are replayable, {} is not",
+                    item.name()
+                );
+            }
+        } else {
+            bail!(
+                "Postgres sinks require that sink input dependencies are sources, {} is not",
+                item.name()
+            );
+        }
+    }
+
+    Ok(SinkConnectorBuilder::Postgres(
+        PostgresSinkConnectorBuilder {
+            conn,
+            table,
+            key_indices,
+            value_desc,
+            cluster_id,
+            transitive_source_dependencies: root_dependencies.iter().map(|i| i.id()).collect(),
+        },
+    ))
+}
+
 pub fn describe_create_sink(
     _: &StatementContext,
     _: CreateSinkStatement<Raw>,
@@ -1753,14 +2197,24 @@ pub fn plan_create_sink(
         if_not_exists,
     } = stmt;
 
-    let envelope = match envelope {
-        None | Some(Envelope::Debezium(sql_parser::ast::DbzMode::Plain)) => SinkEnvelope::Debezium,
-        Some(Envelope::Upsert) => SinkEnvelope::Upsert,
-        Some(Envelope::CdcV2) => bail_unsupported!("CDCv2 sinks"),
-        Some(Envelope::Debezium(sql_parser::ast::DbzMode::Upsert)) => {
+    // S3 sinks write out the raw updates themselves, so they take no
+    // envelope. Postgres sinks always apply upserts and deletes by key.
+    let envelope = match (&connector, envelope) {
+        (CreateSinkConnector::S3 { .. }, None) => None,
+        (CreateSinkConnector::S3 { .. }, Some(_)) => bail!("S3 sinks cannot specify an envelope"),
+        (CreateSinkConnector::Postgres { .. }, None) => Some(SinkEnvelope::Upsert),
+        (CreateSinkConnector::Postgres { .. }, Some(_)) => {
+            bail!("Postgres sinks cannot specify an envelope")
+        }
+        (_, None) | (_, Some(Envelope::Debezium(sql_parser::ast::DbzMode::Plain))) => {
+            Some(SinkEnvelope::Debezium)
+        }
+        (_, Some(Envelope::Upsert)) => Some(SinkEnvelope::Upsert),
+        (_, Some(Envelope::CdcV2)) => Some(SinkEnvelope::CdcV2),
+        (_, Some(Envelope::Debezium(sql_parser::ast::DbzMode::Upsert))) => {
             bail_unsupported!("UPSERT doesn't make sense for sinks")
         }
-        Some(Envelope::None) => bail_unsupported!("\"ENVELOPE NONE\" sinks"),
+        (_, Some(Envelope::None)) => Some(SinkEnvelope::AppendOnly),
     };
     let name = scx.allocate_name(normalize::unresolved_object_name(name)?);
     let from = scx.resolve_item(from)?;
@@ -1773,53 +2227,55 @@ pub fn plan_create_sink(
     let mut with_options = normalize::options(&with_options);
 
     let desc = from.desc()?;
-    let key_indices = match &connector {
-        CreateSinkConnector::Kafka { key, .. } => {
-            if let Some(key) = key.clone() {
-                let key_columns = key
-                    .key_columns
-                    .into_iter()
-                    .map(normalize::column_name)
-                    .collect::<Vec<_>>();
-                let mut uniq = HashSet::new();
-                for col in key_columns.iter() {
-                    if !uniq.insert(col) {
-                        bail!("Repeated column name in sink key: {}", col);
-                    }
-                }
-                let indices = key_columns
-                    .iter()
-                    .map(|col| -> anyhow::Result<usize> {
-                        let name_idx = desc
-                            .get_by_name(col)
-                            .map(|(idx, _type)| idx)
-                            .ok_or_else(|| anyhow!("No such column: {}", col))?;
-                        if desc.get_unambiguous_name(name_idx).is_none() {
-                            bail!("Ambiguous column: {}", col);
-                        }
-                        Ok(name_idx)
-                    })
-                    .collect::<Result<Vec<_>, _>>()?;
-                let is_valid_key =
-                    desc.typ().keys.iter().any(|key_columns| {
-                        key_columns.iter().all(|column| indices.contains(column))
-                    });
-                if key.not_enforced && envelope == SinkEnvelope::Upsert {
-                    // TODO: We should report a warning notice back to the user via the pgwire
-                    // protocol. See https://github.com/MaterializeInc/materialize/issues/9333.
-                    tracing::warn!(
-                        "Verification of upsert key disabled for sink '{}' via 'NOT ENFORCED'. This is potentially dangerous and can lead to crashing materialize when the specified key is not in fact a unique key of the sinked view.",
-                        name
-                    );
-                } else if !is_valid_key && envelope == SinkEnvelope::Upsert {
-                    return Err(invalid_upsert_key_err(&desc, &key_columns));
+    let key = match &connector {
+        CreateSinkConnector::Kafka { key, .. } => key.clone(),
+        CreateSinkConnector::Postgres { key, .. } => Some(key.clone()),
+        CreateSinkConnector::AvroOcf { .. } | CreateSinkConnector::S3 { .. } => None,
+    };
+    let key_indices = match key {
+        Some(key) => {
+            let key_columns = key
+                .key_columns
+                .into_iter()
+                .map(normalize::column_name)
+                .collect::<Vec<_>>();
+            let mut uniq = HashSet::new();
+            for col in key_columns.iter() {
+                if !uniq.insert(col) {
+                    bail!("Repeated column name in sink key: {}", col);
                 }
-                Some(indices)
-            } else {
-                None
             }
+            let indices = key_columns
+                .iter()
+                .map(|col| -> anyhow::Result<usize> {
+                    let name_idx = desc
+                        .get_by_name(col)
+                        .map(|(idx, _type)| idx)
+                        .ok_or_else(|| anyhow!("No such column: {}", col))?;
+                    if desc.get_unambiguous_name(name_idx).is_none() {
+                        bail!("Ambiguous column: {}", col);
+                    }
+                    Ok(name_idx)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let is_valid_key = desc
+                .typ()
+                .keys
+                .iter()
+                .any(|key_columns| key_columns.iter().all(|column| indices.contains(column)));
+            if key.not_enforced && envelope == Some(SinkEnvelope::Upsert) {
+                // TODO: We should report a warning notice back to the user via the pgwire
+                // protocol. See https://github.com/MaterializeInc/materialize/issues/9333.
+                tracing::warn!(
+                    "Verification of upsert key disabled for sink '{}' via 'NOT ENFORCED'. This is potentially dangerous and can lead to crashing materialize when the specified key is not in fact a unique key of the sinked view.",
+                    name
+                );
+            } else if !is_valid_key && envelope == Some(SinkEnvelope::Upsert) {
+                return Err(invalid_upsert_key_err(&desc, &key_columns));
+            }
+            Some(indices)
         }
-        CreateSinkConnector::AvroOcf { .. } => None,
+        None => None,
     };
 
     // pick the first valid natural relation key, if any
@@ -1833,23 +2289,43 @@ pub fn plan_create_sink(
         (RelationDesc::new(typ, names), key_indices)
     });
 
-    if key_desc_and_indices.is_none() && envelope == SinkEnvelope::Upsert {
+    if key_desc_and_indices.is_none() && envelope == Some(SinkEnvelope::Upsert) {
         return Err(PlanError::UpsertSinkWithoutKey.into());
     }
 
     let value_desc = match envelope {
-        SinkEnvelope::Debezium => envelopes::dbz_desc(desc.clone()),
-        SinkEnvelope::Upsert => desc.clone(),
+        Some(SinkEnvelope::Debezium) => envelopes::dbz_desc(desc.clone()),
+        Some(SinkEnvelope::Upsert | SinkEnvelope::AppendOnly | SinkEnvelope::CdcV2) | None => {
+            desc.clone()
+        }
     };
 
     if as_of.is_some() {
         bail!("CREATE SINK ... AS OF is no longer supported");
     }
 
+    // Kafka sinks evaluate their PARTITION BY expression over the sink key, so
+    // that all updates to a key are written to the same partition.
+    let (partition_by, partition_by_depends_on) = match &connector {
+        CreateSinkConnector::Kafka {
+            partition_by: Some(expr),
+            ..
+        } => {
+            let key_desc = match &key_desc_and_indices {
+                Some((key_desc, _indices)) => key_desc,
+                None => bail!("PARTITION BY requires a KEY"),
+            };
+            let (expr, depends_on) = query::plan_partition_by_expr(scx, key_desc, expr.clone())?;
+            (Some(expr), depends_on)
+        }
+        _ => (None, vec![]),
+    };
+
     let mut depends_on = vec![from.id()];
     depends_on.extend(from.uses());
 
     let root_user_dependencies = get_root_dependencies(scx, &depends_on);
+    depends_on.extend(partition_by_depends_on);
 
     let connector_builder = match connector {
         CreateSinkConnector::Kafka {
@@ -1865,13 +2341,37 @@ pub fn plan_create_sink(
             topic,
             relation_key_indices,
             key_desc_and_indices,
+            partition_by,
             value_desc,
+            envelope.expect("Kafka sinks always have an envelope"),
             suffix_nonce,
             &root_user_dependencies,
         )?,
+        CreateSinkConnector::AvroOcf { .. } if envelope == Some(SinkEnvelope::CdcV2) => {
+            bail_unsupported!("ENVELOPE MATERIALIZE over OCF (Avro files)")
+        }
         CreateSinkConnector::AvroOcf { path } => {
             avro_ocf_sink_builder(format, path, suffix_nonce, value_desc)?
         }
+        CreateSinkConnector::S3 { bucket, prefix } => s3_sink_builder(
+            format,
+            &mut with_options,
+            bucket,
+            prefix,
+            suffix_nonce,
+            value_desc,
+        )?,
+        CreateSinkConnector::Postgres { conn, table, .. } => postgres_sink_builder(
+            format,
+            conn,
+            table,
+            key_desc_and_indices
+                .map(|(_desc, indices)| indices)
+                .expect("Postgres sinks always have a key"),
+            value_desc,
+            scx.catalog.config().cluster_id,
+            &root_user_dependencies,
+        )?,
     };
 
     if !with_options.is_empty() {
@@ -14,12 +14,13 @@
 use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
 use std::iter;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, ensure, Context};
 use aws_arn::ARN;
 use ccsr::Client;
 use csv::ReaderBuilder;
+use globset::Glob;
 use itertools::Itertools;
 use reqwest::Url;
 use sql_parser::ast::{CsrSeedCompiledOrLegacy, Op};
@@ -30,19 +31,22 @@ use uuid::Uuid;
 
 use dataflow_types::sources::AwsConfig;
 use dataflow_types::sources::{ExternalSourceConnector, PostgresSourceConnector, SourceConnector};
+use interchange::protobuf;
+use ore::str::StrExt;
 use protobuf_native::compiler::{SourceTreeDescriptorDatabase, VirtualSourceTree};
 use protobuf_native::MessageLite;
-use repr::strconv;
+use repr::adt::numeric::NUMERIC_DATUM_MAX_PRECISION;
+use repr::{strconv, ScalarType};
 use sql_parser::parser::parse_columns;
 
 use crate::ast::{
-    display::AstDisplay, AvroSchema, CreateSourceConnector, CreateSourceFormat,
-    CreateSourceStatement, CreateViewsDefinitions, CreateViewsSourceTarget, CreateViewsStatement,
-    CsrConnectorAvro, CsrConnectorProto, CsrSeed, CsrSeedCompiled, CsrSeedCompiledEncoding,
-    CsvColumns, DbzMode, Envelope, Expr, Format, Ident, ProtobufSchema, Query, Raw, RawName,
-    Select, SelectItem, SetExpr, SourceIncludeMetadata, SourceIncludeMetadataType, SqlOption,
-    Statement, TableFactor, TableWithJoins, UnresolvedObjectName, Value, ViewDefinition,
-    WithOption, WithOptionValue,
+    display::AstDisplay, AvroSchema, ColumnDef, ColumnOption, ColumnOptionDef, Compression,
+    CreateSourceConnector, CreateSourceFormat, CreateSourceStatement, CreateViewsDefinitions,
+    CreateViewsSourceTarget, CreateViewsStatement, CsrConnectorAvro, CsrConnectorProto, CsrSeed,
+    CsrSeedCompiled, CsrSeedCompiledEncoding, CsvColumns, DataType, DbzMode, Envelope, Expr,
+    Format, Ident, KafkaSourceTopic, ProtobufSchema, Query, Raw, RawName, Select, SelectItem,
+    SetExpr, SourceIncludeMetadata, SourceIncludeMetadataType, SqlOption, Statement, TableFactor,
+    TableWithJoins, UnresolvedObjectName, Value, ViewDefinition, WithOption, WithOptionValue,
 };
 use crate::catalog::SessionCatalog;
 use crate::kafka_util;
@@ -103,26 +107,46 @@ pub fn purify(
                         *broker += ":9092";
                     }
 
+                    // Sources that read from several topics have no single set
+                    // of partitions to which start offsets could apply.
+                    let single_topic = match topic {
+                        KafkaSourceTopic::Names(names) if names.len() == 1 => Some(&names[0]),
+                        _ => None,
+                    };
+                    if single_topic.is_none() && with_options_map.contains_key("kafka_time_offset")
+                    {
+                        bail!("`kafka_time_offset` is not supported with multiple Kafka topics");
+                    }
+
                     // Verify that the provided security options are valid and then test them.
                     config_options = kafka_util::extract_config(&mut with_options_map)?;
-                    let consumer = kafka_util::create_consumer(&broker, &topic, &config_options)
-                        .await
-                        .map_err(|e| {
-                            anyhow!(
-                                "Cannot create Kafka Consumer for determining start offsets: {}",
-                                e
-                            )
-                        })?;
+                    let consumer = kafka_util::create_consumer(
+                        &broker,
+                        single_topic.map(|t| t.as_str()),
+                        &config_options,
+                    )
+                    .await
+                    .map_err(|e| {
+                        anyhow!(
+                            "Cannot create Kafka Consumer for determining start offsets: {}",
+                            e
+                        )
+                    })?;
 
                     // Translate `kafka_time_offset` to `start_offset`.
-                    match kafka_util::lookup_start_offsets(
-                        consumer.clone(),
-                        &topic,
-                        &with_options_map,
-                        now,
-                    )
-                    .await?
-                    {
+                    let start_offsets = match single_topic {
+                        Some(topic) => {
+                            kafka_util::lookup_start_offsets(
+                                consumer.clone(),
+                                topic,
+                                &with_options_map,
+                                now,
+                            )
+                            .await?
+                        }
+                        None => None,
+                    };
+                    match start_offsets {
                         Some(start_offsets) => {
                             // Drop `kafka_time_offset`
                             with_options.retain(|val| match val {
@@ -165,17 +189,32 @@ pub fn purify(
                 }
                 // Report an error if a file cannot be opened, or if it is a directory.
                 CreateSourceConnector::File { path, .. } => {
-                    let f = File::open(&path).await?;
-                    if f.metadata().await?.is_dir() {
-                        bail!("Expected a regular file, but {} is a directory.", path);
+                    match normalize::file_source_path(path)? {
+                        (path, None) => {
+                            let f = File::open(&path).await?;
+                            if f.metadata().await?.is_dir() {
+                                bail!(
+                                    "Expected a regular file, but {} is a directory.",
+                                    path.display()
+                                );
+                            }
+                            file = Some(f);
+                        }
+                        // Formats whose columns depend on the file contents
+                        // are purified using the first file that matches, if
+                        // any does yet.
+                        (dir, Some(pattern)) => {
+                            if let Some(path) = first_matching_file(&dir, &pattern).await? {
+                                file = Some(File::open(&path).await?);
+                            }
+                        }
                     }
-                    file = Some(f);
                 }
                 CreateSourceConnector::S3 { .. } => {
                     let aws_config = normalize::aws_config(&mut with_options_map, None)?;
                     validate_aws_credentials(&aws_config).await?;
                 }
-                CreateSourceConnector::Kinesis { arn } => {
+                CreateSourceConnector::Kinesis { arn, .. } => {
                     let region = arn
                         .parse::<ARN>()
                         .map_err(|e| anyhow!("Unable to parse provided ARN: {:#?}", e))?
@@ -204,6 +243,15 @@ pub fn purify(
                     let _ = postgres_util::publication_info(&conn, &publication).await?;
                 }
                 CreateSourceConnector::PubNub { .. } => (),
+                // Report an error if the secret cannot be read, rather than
+                // rejecting every request later on.
+                CreateSourceConnector::Webhook => {
+                    if let Some(Value::String(path)) = with_options_map.get("hmac_secret_file") {
+                        tokio::fs::read(path)
+                            .await
+                            .with_context(|| format!("reading hmac_secret_file {}", path))?;
+                    }
+                }
             }
 
             purify_source_format(
@@ -425,6 +473,7 @@ async fn purify_source_format(
                 file,
                 connector_options,
                 with_options,
+                false,
             )
             .await?;
         }
@@ -442,6 +491,7 @@ async fn purify_source_format(
                 None,
                 connector_options,
                 with_options,
+                true,
             )
             .await?;
             purify_source_format_single(
@@ -451,6 +501,7 @@ async fn purify_source_format(
                 None,
                 connector_options,
                 with_options,
+                false,
             )
             .await?;
         }
@@ -465,6 +516,7 @@ async fn purify_source_format_single(
     file: Option<File>,
     connector_options: &BTreeMap<String, String>,
     with_options: &Vec<SqlOption<Raw>>,
+    is_key: bool,
 ) -> Result<(), anyhow::Error> {
     match format {
         Format::Avro(schema) => match schema {
@@ -500,8 +552,14 @@ async fn purify_source_format_single(
         },
         Format::Protobuf(schema) => match schema {
             ProtobufSchema::Csr { csr_connector } => {
-                purify_csr_connector_proto(connector, csr_connector, envelope, with_options)
-                    .await?;
+                purify_csr_connector_proto(
+                    connector,
+                    csr_connector,
+                    envelope,
+                    with_options,
+                    is_key,
+                )
+                .await?;
             }
             ProtobufSchema::InlineSchema {
                 message_name: _,
@@ -521,7 +579,105 @@ async fn purify_source_format_single(
         } => {
             purify_csv(file, connector, *delimiter, columns).await?;
         }
-        Format::Bytes | Format::Regex(_) | Format::Json | Format::Text => (),
+        Format::Parquet { columns } => purify_parquet(connector, file, columns).await?,
+        Format::Bytes | Format::Regex(_) | Format::Json { .. } | Format::Text => (),
+        // Rejected during planning.
+        Format::AvroOcf => (),
+    }
+    Ok(())
+}
+
+/// Returns the first file in `dir`, in the order of their names, whose name
+/// matches `pattern`.
+async fn first_matching_file(dir: &Path, pattern: &Glob) -> Result<Option<PathBuf>, anyhow::Error> {
+    let matcher = pattern.compile_matcher();
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("reading directory {}", dir.display()))?;
+    let mut first: Option<PathBuf> = None;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() && matcher.is_match(entry.file_name()) {
+            let path = entry.path();
+            if first.as_ref().map_or(true, |first| path < *first) {
+                first = Some(path);
+            }
+        }
+    }
+    Ok(first)
+}
+
+/// Fills in the columns of a `FORMAT PARQUET` file source from the schema in
+/// the file's footer, if they were not specified explicitly.
+async fn purify_parquet(
+    connector: &CreateSourceConnector,
+    file: Option<File>,
+    columns: &mut Vec<ColumnDef<Raw>>,
+) -> Result<(), anyhow::Error> {
+    if !columns.is_empty() {
+        return Ok(());
+    }
+    let file = match (connector, file) {
+        (
+            CreateSourceConnector::File {
+                compression: Compression::None,
+                ..
+            },
+            Some(file),
+        ) => file.into_std().await,
+        (
+            CreateSourceConnector::File {
+                compression: Compression::None,
+                ..
+            },
+            None,
+        ) => bail!("FORMAT PARQUET requires a column list if no file matches the source's path"),
+        (CreateSourceConnector::File { .. } | CreateSourceConnector::S3 { .. }, _) => {
+            bail!("FORMAT PARQUET requires a column list for compressed file and S3 sources")
+        }
+        _ => bail_unsupported!("FORMAT PARQUET with non-file or S3 sources"),
+    };
+
+    let file_columns = task::block_in_place(|| {
+        // The Parquet reader has no async equivalent, so we're stuck using
+        // blocking calls here.
+        interchange::parquet::file_columns(file).context("reading Parquet file schema")
+    })?;
+    for (name, typ) in file_columns {
+        let (type_name, typ_mod) = match typ.scalar_type {
+            ScalarType::Bool => ("bool", vec![]),
+            ScalarType::Int16 => ("int2", vec![]),
+            ScalarType::Int32 => ("int4", vec![]),
+            ScalarType::Int64 => ("int8", vec![]),
+            ScalarType::Float32 => ("float4", vec![]),
+            ScalarType::Float64 => ("float8", vec![]),
+            ScalarType::Numeric { scale: None } => ("numeric", vec![]),
+            ScalarType::Numeric { scale: Some(scale) } => (
+                "numeric",
+                vec![NUMERIC_DATUM_MAX_PRECISION as u64, u64::from(scale)],
+            ),
+            ScalarType::String => ("text", vec![]),
+            ScalarType::Bytes => ("bytea", vec![]),
+            ScalarType::Date => ("date", vec![]),
+            ScalarType::Timestamp => ("timestamp", vec![]),
+            ScalarType::TimestampTz => ("timestamptz", vec![]),
+            other => bail!("unsupported Parquet column type {:?}", other),
+        };
+        columns.push(ColumnDef {
+            name: Ident::new(name.as_str()),
+            data_type: DataType::Other {
+                name: RawName::Name(UnresolvedObjectName::unqualified(type_name)),
+                typ_mod,
+            },
+            collation: None,
+            options: if typ.nullable {
+                vec![]
+            } else {
+                vec![ColumnOptionDef {
+                    name: None,
+                    option: ColumnOption::NotNull,
+                }]
+            },
+        });
     }
     Ok(())
 }
@@ -531,15 +687,17 @@ async fn purify_csr_connector_proto(
     csr_connector: &mut CsrConnectorProto<Raw>,
     envelope: &Envelope,
     with_options: &Vec<SqlOption<Raw>>,
+    is_key: bool,
 ) -> Result<(), anyhow::Error> {
-    let topic = if let CreateSourceConnector::Kafka { topic, .. } = connector {
-        topic
+    let (broker, topic) = if let CreateSourceConnector::Kafka { broker, topic, .. } = connector {
+        (broker, topic)
     } else {
         bail!("Confluent Schema Registry is only supported with Kafka sources")
     };
 
     let CsrConnectorProto {
         url,
+        message_name,
         seed,
         with_options: ccsr_options,
     } = csr_connector;
@@ -547,17 +705,34 @@ async fn purify_csr_connector_proto(
         None => {
             let url: Url = url.parse()?;
             let kafka_options = kafka_util::extract_config(&mut normalize::options(with_options))?;
+            let topic = kafka_util::schema_registry_topic(broker, topic, &kafka_options).await?;
             let ccsr_config = kafka_util::generate_ccsr_client_config(
                 url,
                 &kafka_options,
                 normalize::options(&ccsr_options),
             )?;
 
-            let value =
-                compile_proto(&format!("{}-value", topic), ccsr_config.clone().build()?).await?;
-            let key = compile_proto(&format!("{}-key", topic), ccsr_config.build()?)
-                .await
-                .ok();
+            // An explicit message name selects the message of the subject
+            // that this format decodes.
+            let (key_message, value_message) = if is_key {
+                (message_name.as_deref(), None)
+            } else {
+                (None, message_name.as_deref())
+            };
+            let value = compile_proto(
+                &format!("{}-value", topic),
+                value_message,
+                ccsr_config.clone().build()?,
+            )
+            .await?;
+            let key =
+                match compile_proto(&format!("{}-key", topic), key_message, ccsr_config.build()?)
+                    .await
+                {
+                    Ok(key) => Some(key),
+                    Err(e) if key_message.is_some() => return Err(e),
+                    Err(_) => None,
+                };
 
             if matches!(envelope, Envelope::Debezium(DbzMode::Upsert)) && key.is_none() {
                 bail!("Key schema is required for ENVELOPE DEBEZIUM UPSERT");
@@ -583,8 +758,8 @@ async fn purify_csr_connector_avro(
     envelope: &Envelope,
     connector_options: &BTreeMap<String, String>,
 ) -> Result<(), anyhow::Error> {
-    let topic = if let CreateSourceConnector::Kafka { topic, .. } = connector {
-        topic
+    let (broker, topic) = if let CreateSourceConnector::Kafka { broker, topic, .. } = connector {
+        (broker, topic)
     } else {
         bail!("Confluent Schema Registry is only supported with Kafka sources")
     };
@@ -596,6 +771,7 @@ async fn purify_csr_connector_avro(
     } = csr_connector;
     if seed.is_none() {
         let url = url.parse()?;
+        let topic = kafka_util::schema_registry_topic(broker, topic, connector_options).await?;
 
         let ccsr_config = task::block_in_place(|| {
             kafka_util::generate_ccsr_client_config(
@@ -609,7 +785,7 @@ async fn purify_csr_connector_avro(
             key_schema,
             value_schema,
             ..
-        } = get_remote_csr_schema(ccsr_config, topic.clone()).await?;
+        } = get_remote_csr_schema(ccsr_config, topic).await?;
         if matches!(envelope, Envelope::Debezium(DbzMode::Upsert)) && key_schema.is_none() {
             bail!("Key schema is required for ENVELOPE DEBEZIUM UPSERT");
         }
@@ -788,6 +964,7 @@ async fn get_remote_csr_schema(
 /// Collect protobuf message descriptor from CSR and compile the descriptor.
 async fn compile_proto(
     subject_name: &String,
+    message_name: Option<&str>,
     ccsr_client: Client,
 ) -> Result<CsrSeedCompiledEncoding, anyhow::Error> {
     let (primary_subject, dependency_subjects) =
@@ -806,17 +983,45 @@ async fn compile_proto(
         .as_mut()
         .build_file_descriptor_set(&[Path::new(&primary_subject.name)])?;
 
-    // Ensure there is exactly one message in the file.
-    let primary_fd = fds.file(0);
-    let message_name = match primary_fd.message_type_size() {
-        1 => String::from_utf8_lossy(primary_fd.message_type(0).name()).into_owned(),
-        0 => bail_unsupported!(9598, "Protobuf schemas with no messages"),
-        _ => bail_unsupported!(9598, "Protobuf schemas with multiple messages"),
+    let fds = fds.serialize()?;
+
+    // Select the requested message, or the only top-level message if none was
+    // requested.
+    let messages = protobuf::file_messages(&fds, Some(&primary_subject.name))?;
+    let message_name = match message_name {
+        Some(message_name) => {
+            let message_name = message_name.trim_start_matches('.');
+            match messages.into_iter().find(|(name, _)| name == message_name) {
+                Some((name, _)) => name,
+                None => bail!(
+                    "Protobuf schema for subject {} does not contain message {}",
+                    subject_name.quoted(),
+                    message_name.quoted()
+                ),
+            }
+        }
+        None => {
+            let mut top_level = messages
+                .into_iter()
+                .filter(|(_, indexes)| indexes.len() == 1);
+            match (top_level.next(), top_level.next()) {
+                (Some((name, _)), None) => name,
+                (None, _) => bail!(
+                    "Protobuf schema for subject {} contains no messages",
+                    subject_name.quoted()
+                ),
+                (Some(_), Some(_)) => bail!(
+                    "Protobuf schema for subject {} contains multiple messages; \
+                     choose one with FORMAT PROTOBUF MESSAGE '<name>'",
+                    subject_name.quoted()
+                ),
+            }
+        }
     };
 
     // Encode the file descriptor set into a SQL byte string.
     let mut schema = String::new();
-    strconv::format_bytes(&mut schema, &fds.serialize()?);
+    strconv::format_bytes(&mut schema, &fds);
 
     Ok(CsrSeedCompiledEncoding {
         schema,
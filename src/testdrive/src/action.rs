@@ -128,6 +128,7 @@ pub struct State {
     initial_backoff: Duration,
     backoff_factor: f64,
     postgres_clients: HashMap<String, tokio_postgres::Client>,
+    sql_vars: HashMap<String, String>,
     sql_server_clients:
         HashMap<String, tiberius::Client<tokio_util::compat::Compat<tokio::net::TcpStream>>>,
 }
@@ -142,6 +143,15 @@ pub struct Context {
 }
 
 impl State {
+    /// Fills in the variables set by `set-from-sql` in `query`.
+    fn substitute_sql_vars(&self, query: &str) -> String {
+        let mut query = query.to_string();
+        for (name, val) in &self.sql_vars {
+            query = query.replace(&format!("${{{}}}", name), val);
+        }
+        query
+    }
+
     pub fn aws_endpoint(&self) -> String {
         match self.aws_config.endpoint() {
             None => String::new(),
@@ -437,6 +447,7 @@ pub(crate) async fn build(
                     ),
                     "file-append" => Box::new(file::build_append(builtin).map_err(wrap_err)?),
                     "file-delete" => Box::new(file::build_delete(builtin).map_err(wrap_err)?),
+                    "file-rename" => Box::new(file::build_rename(builtin).map_err(wrap_err)?),
                     "http-request" => Box::new(http::build_request(builtin).map_err(wrap_err)?),
                     "kafka-add-partitions" => {
                         Box::new(kafka::build_add_partitions(builtin).map_err(wrap_err)?)
@@ -465,6 +476,9 @@ pub(crate) async fn build(
                     "postgres-execute" => {
                         Box::new(postgres::build_execute(builtin).map_err(wrap_err)?)
                     }
+                    "postgres-verify" => {
+                        Box::new(postgres::build_verify(builtin).map_err(wrap_err)?)
+                    }
                     "postgres-verify-slot" => {
                         Box::new(postgres::build_verify_slot(builtin).map_err(wrap_err)?)
                     }
@@ -497,6 +511,7 @@ pub(crate) async fn build(
                     "s3-add-notifications" => {
                         Box::new(s3::build_add_notifications(builtin).map_err(wrap_err)?)
                     }
+                    "s3-verify-sink" => Box::new(s3::build_verify_sink(builtin).map_err(wrap_err)?),
                     "set-regex" => {
                         context.regex = Some(builtin.args.parse("match").map_err(wrap_err)?);
                         context.regex_replacement = match builtin.args.opt_string("replacement") {
@@ -535,6 +550,13 @@ pub(crate) async fn build(
                         }
                         continue;
                     }
+                    "set-from-sql" => {
+                        let action = sql::build_set_from_sql(builtin).map_err(wrap_err)?;
+                        // The value is only known once the query runs, so leave
+                        // references in place for SQL commands to fill in then.
+                        vars.insert(action.var.clone(), format!("${{{}}}", action.var));
+                        Box::new(action)
+                    }
                     "verify-timestamp-compaction" => Box::new(
                         verify_timestamp_compaction::build_verify_timestamp_compaction_action(
                             builtin,
@@ -772,6 +794,7 @@ pub async fn create_state(
         initial_backoff: config.initial_backoff,
         backoff_factor: config.backoff_factor,
         postgres_clients: HashMap::new(),
+        sql_vars: HashMap::new(),
         sql_server_clients: HashMap::new(),
     };
     Ok((state, pgconn_task))
@@ -51,7 +51,11 @@ pub fn build_compression(cmd: &mut BuiltinCommand) -> Result<Compression, anyhow
 }
 
 fn build_path(cmd: &mut BuiltinCommand) -> Result<String, anyhow::Error> {
-    let path = cmd.args.string("path")?;
+    build_path_arg(cmd, "path")
+}
+
+fn build_path_arg(cmd: &mut BuiltinCommand, name: &str) -> Result<String, anyhow::Error> {
+    let path = cmd.args.string(name)?;
     if path.contains(path::MAIN_SEPARATOR) {
         // The goal isn't security, but preventing mistakes.
         bail!("separators in paths are forbidden")
@@ -132,3 +136,30 @@ impl Action for DeleteAction {
         Ok(())
     }
 }
+
+pub struct RenameAction {
+    path: String,
+    to: String,
+}
+
+pub fn build_rename(mut cmd: BuiltinCommand) -> Result<RenameAction, anyhow::Error> {
+    let path = build_path(&mut cmd)?;
+    let to = build_path_arg(&mut cmd, "to")?;
+    cmd.args.done()?;
+    Ok(RenameAction { path, to })
+}
+
+#[async_trait]
+impl Action for RenameAction {
+    async fn undo(&self, _: &mut State) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
+        let from = state.temp_path.join(&self.path);
+        let to = state.temp_path.join(&self.to);
+        println!("Renaming file {} to {}", from.display(), to.display());
+        tokio::fs::rename(&from, &to).await?;
+        Ok(())
+    }
+}
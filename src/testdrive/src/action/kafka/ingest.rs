@@ -9,6 +9,7 @@
 
 use std::cmp;
 use std::io::{BufRead, Read};
+use std::iter;
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context};
@@ -18,6 +19,7 @@ use futures::stream::{FuturesUnordered, StreamExt};
 use maplit::hashmap;
 use prost::Message;
 use prost_reflect::{DynamicMessage, FileDescriptor, MessageDescriptor};
+use rdkafka::message::OwnedHeaders;
 use rdkafka::producer::FutureRecord;
 use serde::de::DeserializeOwned;
 use tokio::fs;
@@ -35,6 +37,7 @@ pub struct IngestAction {
     format: Format,
     key_format: Option<Format>,
     timestamp: Option<i64>,
+    headers: Vec<(String, String)>,
     publish: bool,
     rows: Vec<String>,
     start_iteration: isize,
@@ -52,7 +55,7 @@ enum Format {
         message: String,
         confluent_wire_format: bool,
         schema_id_subject: Option<String>,
-        schema_message_id: u8,
+        message_indexes: Vec<u8>,
     },
     Bytes {
         terminator: Option<u8>,
@@ -69,7 +72,7 @@ enum Transcoder {
         message: MessageDescriptor,
         confluent_wire_format: bool,
         schema_id: i32,
-        schema_message_id: u8,
+        message_indexes: Vec<u8>,
     },
     Bytes {
         terminator: Option<u8>,
@@ -122,22 +125,20 @@ impl Transcoder {
                 message,
                 confluent_wire_format,
                 schema_id,
-                schema_message_id,
+                message_indexes,
             } => {
                 if let Some(val) = Self::decode_json::<_, serde_json::Value>(row)? {
                     let message = DynamicMessage::deserialize(message.clone(), val)
                         .context("parsing protobuf JSON")?;
                     let mut out = vec![];
                     if *confluent_wire_format {
-                        // See: https://github.com/MaterializeInc/materialize/issues/9250
                         // The first byte is a magic byte (0) that indicates the Confluent
                         // serialization format version, and the next four bytes are a
-                        // 32-bit schema ID, which we default to something fun.
-                        // And, as we only support single-message proto files for now,
-                        // we also set the following message id to 0.
+                        // 32-bit schema ID. The message indexes identify which message
+                        // in the schema was encoded.
                         out.write_u8(0).unwrap();
                         out.write_i32::<NetworkEndian>(*schema_id).unwrap();
-                        out.write_u8(*schema_message_id).unwrap();
+                        out.extend(message_indexes);
                     }
                     message.encode(&mut out)?;
                     Ok(Some(out))
@@ -166,6 +167,43 @@ impl Transcoder {
     }
 }
 
+/// Encodes the Confluent message indexes that follow the schema ID of a
+/// Protobuf message.
+///
+/// `schema-message-indexes` takes a comma-separated path of message indexes,
+/// while `schema-message-id` writes a single raw byte, which is useful for
+/// producing malformed headers.
+fn parse_message_indexes(cmd: &mut BuiltinCommand, prefix: &str) -> Result<Vec<u8>, anyhow::Error> {
+    if let Some(id) = cmd
+        .args
+        .opt_parse::<u8>(&format!("{}schema-message-id", prefix))?
+    {
+        return Ok(vec![id]);
+    }
+    let indexes = match cmd
+        .args
+        .opt_string(&format!("{}schema-message-indexes", prefix))
+    {
+        None => return Ok(vec![0]),
+        Some(indexes) => indexes
+            .split(',')
+            .map(|i| i.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .context("parsing schema-message-indexes")?,
+    };
+    let mut out = vec![];
+    for i in iter::once(i32::try_from(indexes.len())?).chain(indexes) {
+        // Zigzag encode, then write as a varint.
+        let mut n = ((i << 1) ^ (i >> 31)) as u32;
+        while n >= 0x80 {
+            out.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        out.push(n as u8);
+    }
+    Ok(out)
+}
+
 pub fn build_ingest(mut cmd: BuiltinCommand) -> Result<IngestAction, anyhow::Error> {
     let topic_prefix = format!("testdrive-{}", cmd.args.string("topic")?);
     let partition = cmd.args.opt_parse::<i32>("partition")?;
@@ -187,7 +225,7 @@ pub fn build_ingest(mut cmd: BuiltinCommand) -> Result<IngestAction, anyhow::Err
                 // false
                 confluent_wire_format: cmd.args.opt_bool("confluent-wire-format")?.unwrap_or(false),
                 schema_id_subject: cmd.args.opt_string("schema-id-subject"),
-                schema_message_id: cmd.args.opt_parse::<u8>("schema-message-id")?.unwrap_or(0),
+                message_indexes: parse_message_indexes(&mut cmd, "")?,
             }
         }
         "bytes" => Format::Bytes { terminator: None },
@@ -206,10 +244,7 @@ pub fn build_ingest(mut cmd: BuiltinCommand) -> Result<IngestAction, anyhow::Err
                 message,
                 confluent_wire_format: cmd.args.opt_bool("confluent-wire-format")?.unwrap_or(false),
                 schema_id_subject: cmd.args.opt_string("key-schema-id-subject"),
-                schema_message_id: cmd
-                    .args
-                    .opt_parse::<u8>("key-schema-message-id")?
-                    .unwrap_or(0),
+                message_indexes: parse_message_indexes(&mut cmd, "key-")?,
             })
         }
         Some("bytes") => Some(Format::Bytes {
@@ -223,6 +258,16 @@ pub fn build_ingest(mut cmd: BuiltinCommand) -> Result<IngestAction, anyhow::Err
         None => None,
     };
     let timestamp = cmd.args.opt_parse("timestamp")?;
+    let headers = match cmd.args.opt_string("headers") {
+        None => vec![],
+        Some(headers) => headers
+            .split(',')
+            .map(|header| match header.split_once(':') {
+                Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                None => bail!("headers must be of the form key:value, got {}", header),
+            })
+            .collect::<Result<_, _>>()?,
+    };
     cmd.args.done()?;
 
     if publish
@@ -238,6 +283,7 @@ pub fn build_ingest(mut cmd: BuiltinCommand) -> Result<IngestAction, anyhow::Err
         format,
         key_format,
         timestamp,
+        headers,
         publish,
         rows: cmd.input,
         start_iteration,
@@ -307,7 +353,7 @@ impl Action for IngestAction {
                     message,
                     confluent_wire_format,
                     schema_id_subject,
-                    schema_message_id,
+                    message_indexes,
                 } => {
                     let schema_id = if confluent_wire_format {
                         ccsr_client
@@ -333,7 +379,7 @@ impl Action for IngestAction {
                         message,
                         confluent_wire_format,
                         schema_id,
-                        schema_message_id,
+                        message_indexes,
                     })
                 }
                 Format::Bytes { terminator } => Ok(Transcoder::Bytes { terminator }),
@@ -381,6 +427,13 @@ impl Action for IngestAction {
                     if let Some(timestamp) = self.timestamp {
                         record = record.timestamp(timestamp);
                     }
+                    if !self.headers.is_empty() {
+                        let mut headers = OwnedHeaders::new();
+                        for (key, value) in &self.headers {
+                            headers = headers.add(key, value);
+                        }
+                        record = record.headers(headers);
+                    }
                     producer.send(record, timeout).await
                 });
             }
@@ -16,6 +16,7 @@ use async_trait::async_trait;
 use byteorder::{BigEndian, ByteOrder};
 use rdkafka::consumer::{Consumer, StreamConsumer};
 use rdkafka::message::Message;
+use rdkafka::{Offset, TopicPartitionList};
 use tokio::pin;
 use tokio_stream::StreamExt;
 
@@ -36,6 +37,7 @@ pub struct VerifyAction {
     sink: String,
     format: SinkFormat,
     consistency: Option<SinkConsistencyFormat>,
+    partition: Option<i32>,
     sort_messages: bool,
     expected_messages: Vec<String>,
     context: Context,
@@ -60,6 +62,7 @@ pub fn build_verify(
         None => None,
     };
 
+    let partition = cmd.args.opt_parse("partition")?;
     let sort_messages = cmd.args.opt_bool("sort-messages")?.unwrap_or(false);
     let expected_messages = cmd.input;
     if expected_messages.len() == 0 {
@@ -73,6 +76,7 @@ pub fn build_verify(
         sink,
         format,
         consistency,
+        partition,
         sort_messages,
         expected_messages,
         context,
@@ -140,9 +144,20 @@ impl Action for VerifyAction {
         config.set("enable.auto.offset.store", "false");
 
         let consumer: StreamConsumer = config.create().context("creating kafka consumer")?;
-        consumer
-            .subscribe(&[&topic])
-            .context("subscribing to kafka topic")?;
+        match self.partition {
+            // Only read the messages that were written to the given partition.
+            Some(partition) => {
+                let mut tpl = TopicPartitionList::new();
+                tpl.add_partition_offset(&topic, partition, Offset::Beginning)
+                    .context("assigning kafka topic partition")?;
+                consumer
+                    .assign(&tpl)
+                    .context("assigning kafka topic partition")?;
+            }
+            None => consumer
+                .subscribe(&[&topic])
+                .context("subscribing to kafka topic")?,
+        }
 
         let (stream_size, stream_timeout) = match self.partial_search {
             Some(size) => (size, state.default_timeout),
@@ -9,8 +9,10 @@
 
 mod connect;
 mod execute;
+mod verify;
 mod verify_slot;
 
 pub use connect::build_connect;
 pub use execute::build_execute;
+pub use verify::build_verify;
 pub use verify_slot::build_verify_slot;
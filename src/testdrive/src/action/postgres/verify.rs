@@ -0,0 +1,94 @@
+// Copyright Materialize, Inc. and contributors. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+use std::cmp;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use tokio_postgres::{NoTls, SimpleQueryMessage};
+
+use ore::retry::Retry;
+
+use crate::action::{Action, State};
+use crate::parser::BuiltinCommand;
+
+pub struct VerifyAction {
+    connection: String,
+    query: String,
+    expected: Vec<String>,
+}
+
+pub fn build_verify(mut cmd: BuiltinCommand) -> Result<VerifyAction, anyhow::Error> {
+    let connection = cmd.args.string("connection")?;
+    let query = cmd.args.string("query")?;
+    cmd.args.done()?;
+    Ok(VerifyAction {
+        connection,
+        query,
+        expected: cmd.input,
+    })
+}
+
+#[async_trait]
+impl Action for VerifyAction {
+    async fn undo(&self, _: &mut State) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
+        let (client, conn) = tokio_postgres::connect(&self.connection, NoTls)
+            .await
+            .context("connecting to postgres")?;
+        println!(
+            "Executing queries against PostgreSQL server at {}...",
+            self.connection
+        );
+        let conn_handle = tokio::spawn(conn);
+
+        let mut expected = self.expected.clone();
+        expected.sort();
+
+        Retry::default()
+            .initial_backoff(Duration::from_millis(50))
+            .max_duration(cmp::max(state.default_timeout, Duration::from_secs(3)))
+            .retry_async(|_| async {
+                println!(">> {}", self.query);
+                let mut actual = vec![];
+                for message in client
+                    .simple_query(&self.query)
+                    .await
+                    .context("executing postgres query")?
+                {
+                    if let SimpleQueryMessage::Row(row) = message {
+                        let values: Vec<_> = (0..row.len())
+                            .map(|i| row.get(i).unwrap_or("<null>"))
+                            .collect();
+                        actual.push(values.join(" "));
+                    }
+                }
+                actual.sort();
+                if actual != expected {
+                    bail!(
+                        "postgres query returned wrong rows: expected:\n{}\ngot:\n{}",
+                        expected.join("\n"),
+                        actual.join("\n")
+                    );
+                }
+                Ok(())
+            })
+            .await?;
+
+        drop(client);
+        conn_handle
+            .await
+            .unwrap()
+            .context("postgres connection error")
+    }
+}
@@ -8,7 +8,7 @@
 // by the Apache License, Version 2.0.
 
 use std::cmp;
-use std::io::Write;
+use std::io::{Cursor, Write};
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context};
@@ -22,9 +22,11 @@ use aws_sdk_s3::{ByteStream, SdkError};
 use aws_sdk_sqs::model::{DeleteMessageBatchRequestEntry, QueueAttributeName};
 use flate2::write::GzEncoder;
 use flate2::Compression as Flate2Compression;
+use ore::retry::Retry;
 
 use crate::action::file::{build_compression, Compression};
 use crate::action::{Action, State};
+use crate::format::avro::{self, Reader};
 use crate::parser::BuiltinCommand;
 
 pub struct CreateBucketAction {
@@ -407,6 +409,175 @@ impl Action for AddBucketNotifications {
     }
 }
 
+pub struct VerifySinkAction {
+    sink: String,
+    expected: Vec<String>,
+}
+
+pub fn build_verify_sink(mut cmd: BuiltinCommand) -> Result<VerifySinkAction, anyhow::Error> {
+    let sink = cmd.args.string("sink")?;
+    let expected = cmd.input;
+    cmd.args.done()?;
+    Ok(VerifySinkAction { sink, expected })
+}
+
+#[async_trait]
+impl Action for VerifySinkAction {
+    async fn undo(&self, _state: &mut State) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
+        // The sink writes its batches asynchronously, so keep checking the
+        // latest batch until it has the expected contents.
+        let state = &*state;
+        Retry::default()
+            .max_duration(state.default_timeout)
+            .retry_async(|_| self.verify_latest_batch(state))
+            .await
+    }
+}
+
+impl VerifySinkAction {
+    async fn verify_latest_batch(&self, state: &State) -> Result<(), anyhow::Error> {
+        let row = state
+            .pgclient
+            .query_one(
+                "SELECT bucket, prefix FROM mz_catalog_names
+                 JOIN mz_s3_sinks ON global_id = sink_id
+                 WHERE name = $1",
+                &[&self.sink],
+            )
+            .await
+            .context("querying materialize")?;
+        let bucket: String = row.get("bucket");
+        let prefix: String = row.get("prefix");
+
+        // Manifest keys sort in the order of their batches.
+        let mut manifests = vec![];
+        let mut continuation_token = None;
+        loop {
+            let response = state
+                .s3_client
+                .list_objects_v2()
+                .bucket(&bucket)
+                .prefix(format!("{}/manifest/", prefix))
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .context("listing manifests")?;
+            manifests.extend(
+                response
+                    .contents
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|o| o.key),
+            );
+            continuation_token = response.next_continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        let manifest = match manifests.into_iter().max() {
+            None => bail!("sink {} has not written any batches yet", self.sink),
+            Some(manifest) => manifest,
+        };
+        println!("Verifying results of S3 sink batch {}/{}", bucket, manifest);
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&get_object(state, &bucket, &manifest).await?)
+                .context("parsing manifest")?;
+        let format = manifest["format"]
+            .as_str()
+            .ok_or_else(|| anyhow!("manifest is missing its format"))?;
+        let files = manifest["files"]
+            .as_array()
+            .ok_or_else(|| anyhow!("manifest is missing its files"))?;
+
+        // Contents are compared without regard to their order, as the sink
+        // does not promise any.
+        let mut actual = vec![];
+        let mut expected = vec![];
+        for file in files {
+            let file = file
+                .as_str()
+                .ok_or_else(|| anyhow!("invalid file in manifest"))?;
+            let bytes = get_object(state, &bucket, file).await?;
+            match format {
+                "avro" => {
+                    let reader = Reader::new(Cursor::new(bytes)).context("creating avro reader")?;
+                    let schema = reader.writer_schema().clone();
+                    for value in reader {
+                        let value = value.context("reading avro value")?;
+                        actual.push(format!("{:#?}", value));
+                    }
+                    // Expected values are only interpreted once there is a
+                    // schema to interpret them with.
+                    if expected.is_empty() {
+                        for line in &self.expected {
+                            let json = serde_json::from_str(line).context("parsing json")?;
+                            let value = avro::from_json(&json, schema.top_node())?;
+                            expected.push(format!("{:#?}", value));
+                        }
+                    }
+                }
+                "parquet" => {
+                    let columns = interchange::parquet::file_columns(Cursor::new(&bytes))?;
+                    let demanded = vec![true; columns.len()];
+                    let row_groups = interchange::parquet::Decoder::new(columns.clone(), demanded)
+                        .decode_file(bytes)?;
+                    for row in row_groups.collect::<Result<Vec<_>, _>>()?.concat() {
+                        let json =
+                            interchange::json::encode_datums_as_json(row.iter(), &columns, false);
+                        actual.push(json.to_string());
+                    }
+                    if expected.is_empty() {
+                        for line in &self.expected {
+                            let json: serde_json::Value =
+                                serde_json::from_str(line).context("parsing json")?;
+                            expected.push(json.to_string());
+                        }
+                    }
+                }
+                other => bail!("unknown S3 sink format {}", other),
+            }
+        }
+        if files.is_empty() {
+            // An empty batch has no schema to interpret the expected values
+            // with, so they had better be absent.
+            expected.extend(self.expected.iter().cloned());
+        }
+
+        actual.sort();
+        expected.sort();
+        if actual != expected {
+            bail!(
+                "S3 sink batch contents did not match\nexpected:\n{}\nactual:\n{}",
+                expected.join("\n"),
+                actual.join("\n")
+            );
+        }
+        Ok(())
+    }
+}
+
+async fn get_object(state: &State, bucket: &str, key: &str) -> Result<Vec<u8>, anyhow::Error> {
+    let object = state
+        .s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .with_context(|| format!("getting S3 object {}/{}", bucket, key))?;
+    let bytes = object
+        .body
+        .collect()
+        .await
+        .with_context(|| format!("reading S3 object {}/{}", bucket, key))?;
+    Ok(bytes.into_bytes().to_vec())
+}
+
 fn allow_s3_policy(queue_arn: &str, bucket: &str, self_account: &str) -> String {
     format!(
         r#"{{
@@ -33,7 +33,7 @@ use sql_parser::ast::{
 };
 
 use crate::action::{Action, Context, State};
-use crate::parser::{FailSqlCommand, SqlCommand, SqlErrorMatchType, SqlOutput};
+use crate::parser::{BuiltinCommand, FailSqlCommand, SqlCommand, SqlErrorMatchType, SqlOutput};
 
 pub struct SqlAction {
     cmd: SqlCommand,
@@ -107,7 +107,7 @@ impl Action for SqlAction {
     async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
         use Statement::*;
 
-        let query = &self.cmd.query;
+        let query = &state.substitute_sql_vars(&self.cmd.query);
         print_query(&query);
 
         let should_retry = match &self.stmt {
@@ -310,6 +310,44 @@ impl SqlAction {
     }
 }
 
+pub struct SetFromSqlAction {
+    pub(crate) var: String,
+    query: String,
+}
+
+pub fn build_set_from_sql(mut cmd: BuiltinCommand) -> Result<SetFromSqlAction, anyhow::Error> {
+    let var = cmd.args.string("var")?;
+    cmd.args.done()?;
+    Ok(SetFromSqlAction {
+        var,
+        query: cmd.input.join("\n"),
+    })
+}
+
+#[async_trait]
+impl Action for SetFromSqlAction {
+    async fn undo(&self, _: &mut State) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
+        let query = state.substitute_sql_vars(&self.query);
+        print_query(&query);
+        let row = state
+            .pgclient
+            .query_one(query.as_str(), &[])
+            .await
+            .context("executing query")?;
+        if row.len() != 1 {
+            bail!("expected one column, but got {}", row.len());
+        }
+        let val: String = row.try_get(0).context("decoding value as text")?;
+        println!("Setting {} to {}", self.var, val);
+        state.sql_vars.insert(self.var.clone(), val);
+        Ok(())
+    }
+}
+
 pub struct FailSqlAction {
     query: String,
     expected_error: ErrorMatcher,
@@ -374,7 +412,7 @@ impl Action for FailSqlAction {
     async fn redo(&self, state: &mut State) -> Result<(), anyhow::Error> {
         use Statement::{Commit, Rollback};
 
-        let query = &self.query;
+        let query = &state.substitute_sql_vars(&self.query);
         print_query(&query);
 
         let should_retry = match &self.stmt {
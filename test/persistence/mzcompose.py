@@ -13,6 +13,7 @@ import time
 from materialize.mzcompose import Composition
 from materialize.mzcompose.services import (
     Kafka,
+    Localstack,
     Materialized,
     SchemaRegistry,
     Testdrive,
@@ -31,12 +32,25 @@ mz_disable_user_indexes = Materialized(
     options=f"{mz_options} --disable-user-indexes",
 )
 
+mz_file_sources = Materialized(
+    options=f"{mz_options} --experimental --persistent-file-source",
+)
+
+mz_kinesis_sources = Materialized(
+    options=f"{mz_options} --experimental --persistent-kinesis-source",
+)
+
+td_localstack = Testdrive(
+    no_reset=True, entrypoint_extra=["--aws-endpoint=http://localstack:4566"]
+)
+
 prerequisites = ["zookeeper", "kafka", "schema-registry"]
 
 SERVICES = [
     Zookeeper(),
     Kafka(),
     SchemaRegistry(),
+    Localstack(),
     mz_default,
     Testdrive(no_reset=True),
 ]
@@ -52,6 +66,8 @@ def workflow_persistence(c: Composition) -> None:
             workflow_failpoints(c)
 
     workflow_disable_user_indexes(c)
+    workflow_file_sources(c)
+    workflow_kinesis_sources(c)
 
 
 def workflow_kafka_sources(c: Composition) -> None:
@@ -111,6 +127,69 @@ def workflow_user_tables(c: Composition) -> None:
     c.rm_volumes("mzdata")
 
 
+def workflow_file_sources(c: Composition) -> None:
+    seed = round(time.time())
+
+    # The files have to outlive the first testdrive run.
+    temp_dir = f"--temp-dir=/share/tmp/file-sources-{seed}"
+
+    with c.override(mz_file_sources):
+        c.up("materialized")
+        c.wait_for_materialized()
+
+        c.run(
+            "testdrive-svc",
+            f"--seed={seed}",
+            temp_dir,
+            f"file-sources/*{td_test}*-before.td",
+        )
+
+        c.kill("materialized")
+        c.up("materialized")
+        c.wait_for_materialized()
+
+        c.run(
+            "testdrive-svc",
+            f"--seed={seed}",
+            temp_dir,
+            f"file-sources/*{td_test}*-after.td",
+        )
+
+        c.kill("materialized")
+        c.rm("materialized", "testdrive-svc", destroy_volumes=True)
+        c.rm_volumes("mzdata")
+
+
+def workflow_kinesis_sources(c: Composition) -> None:
+    seed = round(time.time())
+
+    c.start_and_wait_for_tcp(services=["localstack"])
+
+    with c.override(mz_kinesis_sources, td_localstack):
+        c.up("materialized")
+        c.wait_for_materialized()
+
+        c.run(
+            "testdrive-svc",
+            f"--seed={seed}",
+            f"kinesis-sources/*{td_test}*-before.td",
+        )
+
+        c.kill("materialized")
+        c.up("materialized")
+        c.wait_for_materialized()
+
+        c.run(
+            "testdrive-svc",
+            f"--seed={seed}",
+            f"kinesis-sources/*{td_test}*-after.td",
+        )
+
+        c.kill("materialized")
+        c.rm("materialized", "testdrive-svc", destroy_volumes=True)
+        c.rm_volumes("mzdata")
+
+
 def workflow_failpoints(c: Composition) -> None:
     seed = round(time.time())
 